@@ -4,7 +4,11 @@
 //! These interfaces are frozen in the protocol and can never change. They are the only interfaces
 //! which the backends need to be aware of in particular.
 
-use crate::protocol::{AllowNull, ArgumentType, Interface, MessageDesc, ANONYMOUS_INTERFACE};
+use std::os::raw::{c_char, c_void};
+
+use crate::protocol::{
+    wl_interface, wl_message, AllowNull, ArgumentType, Interface, MessageDesc, ANONYMOUS_INTERFACE,
+};
 
 /// Interface `wl_display`
 pub static WL_DISPLAY_INTERFACE: Interface = Interface {
@@ -16,16 +20,20 @@ pub static WL_DISPLAY_INTERFACE: Interface = Interface {
             since: 1,
             is_destructor: false,
             signature: &[ArgumentType::NewId(AllowNull::No)],
+            arg_names: &["callback"],
             child_interface: Some(&WL_CALLBACK_INTERFACE),
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         MessageDesc {
             name: "get_registry",
             since: 1,
             is_destructor: false,
             signature: &[ArgumentType::NewId(AllowNull::No)],
+            arg_names: &["registry"],
             child_interface: Some(&WL_REGISTRY_INTERFACE),
             arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
     events: &[
@@ -38,16 +46,20 @@ pub static WL_DISPLAY_INTERFACE: Interface = Interface {
                 ArgumentType::Uint,
                 ArgumentType::Str(AllowNull::No),
             ],
+            arg_names: &["object_id", "code", "message"],
             child_interface: None,
             arg_interfaces: &[&ANONYMOUS_INTERFACE],
+            arg_enums: &[],
         },
         MessageDesc {
             name: "delete_id",
             since: 1,
             is_destructor: false,
             signature: &[ArgumentType::Uint],
+            arg_names: &["id"],
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
     c_ptr: None,
@@ -67,8 +79,10 @@ pub static WL_REGISTRY_INTERFACE: Interface = Interface {
             ArgumentType::Uint,
             ArgumentType::NewId(AllowNull::No),
         ],
+        arg_names: &["name", "interface", "version", "id"],
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     }],
     events: &[
         MessageDesc {
@@ -76,19 +90,63 @@ pub static WL_REGISTRY_INTERFACE: Interface = Interface {
             since: 1,
             is_destructor: false,
             signature: &[ArgumentType::Uint, ArgumentType::Str(AllowNull::No), ArgumentType::Uint],
+            arg_names: &["name", "interface", "version"],
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         MessageDesc {
             name: "global_remove",
             since: 1,
             is_destructor: false,
             signature: &[ArgumentType::Uint],
+            arg_names: &["name"],
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
-    c_ptr: None,
+    c_ptr: Some(unsafe { &*std::ptr::addr_of!(WL_REGISTRY_C_INTERFACE) }),
+};
+
+// C representation of `wl_registry`, allowing the system backend to create registries itself
+//
+// Taking the address of a mutable static only requires `unsafe` with older compilers, hence the
+// `unused_unsafe` allowances.
+
+const NULLPTR: *const c_void = std::ptr::null();
+
+static mut TYPES_NULL: [*const wl_interface; 4] = [NULLPTR as *const wl_interface; 4];
+
+#[allow(unused_unsafe)]
+static mut WL_REGISTRY_C_REQUESTS: [wl_message; 1] = [wl_message {
+    name: b"bind\0" as *const u8 as *const c_char,
+    signature: b"usun\0" as *const u8 as *const c_char,
+    types: unsafe { std::ptr::addr_of!(TYPES_NULL) as *const _ },
+}];
+
+#[allow(unused_unsafe)]
+static mut WL_REGISTRY_C_EVENTS: [wl_message; 2] = [
+    wl_message {
+        name: b"global\0" as *const u8 as *const c_char,
+        signature: b"usu\0" as *const u8 as *const c_char,
+        types: unsafe { std::ptr::addr_of!(TYPES_NULL) as *const _ },
+    },
+    wl_message {
+        name: b"global_remove\0" as *const u8 as *const c_char,
+        signature: b"u\0" as *const u8 as *const c_char,
+        types: unsafe { std::ptr::addr_of!(TYPES_NULL) as *const _ },
+    },
+];
+
+#[allow(unused_unsafe)]
+static mut WL_REGISTRY_C_INTERFACE: wl_interface = wl_interface {
+    name: b"wl_registry\0" as *const u8 as *const c_char,
+    version: 1,
+    request_count: 1,
+    requests: unsafe { std::ptr::addr_of!(WL_REGISTRY_C_REQUESTS) as *const _ },
+    event_count: 2,
+    events: unsafe { std::ptr::addr_of!(WL_REGISTRY_C_EVENTS) as *const _ },
 };
 
 /// Interface `wl_callback`
@@ -101,8 +159,10 @@ pub static WL_CALLBACK_INTERFACE: Interface = Interface {
         since: 1,
         is_destructor: true,
         signature: &[ArgumentType::Uint],
+        arg_names: &["callback_data"],
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     }],
     c_ptr: None,
 };
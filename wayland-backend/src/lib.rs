@@ -37,6 +37,7 @@ mod test;
 
 mod core_interfaces;
 pub mod protocol;
+pub mod region;
 mod types;
 
 /*
@@ -4,6 +4,26 @@ use std::{ffi::CString, os::unix::io::RawFd};
 
 pub use wayland_sys::common::{wl_argument, wl_interface, wl_message};
 
+mod builder;
+mod compat;
+mod conformance;
+mod format;
+mod registry;
+mod stats;
+mod wire_signature;
+
+pub use builder::{MessageBuildError, MessageBuilder};
+pub use compat::{check_interoperable, InterfaceMismatch, MessageMismatch};
+pub use conformance::ConformanceViolation;
+pub(crate) use conformance::{check_arguments, ConformanceHook};
+pub use format::{format_message, CaptureFormatter};
+pub use registry::{lookup_interface, register_interfaces};
+pub(crate) use stats::StatsRecorder;
+pub use stats::{Direction, MessageStats, ProtocolStats};
+pub use wire_signature::{
+    decode_signature, encode_signature, signature_codes, SignatureDecodeError,
+};
+
 /// Describes whether an argument may have a null value.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum AllowNull {
@@ -133,6 +153,11 @@ pub struct MessageDesc {
     pub name: &'static str,
     /// Signature of the message
     pub signature: &'static [ArgumentType],
+    /// Names of the arguments of the message, as written in the protocol
+    ///
+    /// A `new_id` argument without a specified interface is preceded by two arguments, named
+    /// `interface` and `version`, like in its signature.
+    pub arg_names: &'static [&'static str],
     /// Minimum required version of the interface
     pub since: u32,
     /// Whether this message is a destructor
@@ -143,12 +168,50 @@ pub struct MessageDesc {
     pub child_interface: Option<&'static Interface>,
     /// The interfaces passed into this message as arguments.
     pub arg_interfaces: &'static [&'static Interface],
+    /// The enums constraining the values of arguments of this message
+    ///
+    /// Only the enums defined by the protocol file of the message are listed.
+    pub arg_enums: &'static [ArgEnum],
+}
+
+/// The values accepted by an enum argument of a message
+#[derive(Copy, Clone, Debug)]
+pub struct ArgEnum {
+    /// Position of the argument in the signature of the message
+    pub index: usize,
+    /// Name of the enum, as referenced by the argument in the protocol
+    pub name: &'static str,
+    /// Whether the enum is a bitfield, whose values can be combined
+    pub bitfield: bool,
+    /// Values of the entries of the enum
+    pub values: &'static [u32],
+}
+
+impl ArgEnum {
+    /// Whether a value of the argument is valid for this enum
+    pub fn accepts(&self, value: u32) -> bool {
+        if self.bitfield {
+            let all = self.values.iter().fold(0, |acc, v| acc | v);
+            value & !all == 0
+        } else {
+            self.values.contains(&value)
+        }
+    }
 }
 
 /// Special interface representing an anonymous object
 pub static ANONYMOUS_INTERFACE: Interface =
     Interface { name: "<anonymous>", version: 0, requests: &[], events: &[], c_ptr: None };
 
+/// Direction of a message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageDirection {
+    /// A request, sent by the client to the server
+    Request,
+    /// An event, sent by the server to the client
+    Event,
+}
+
 /// Description of the protocol-level information of an object
 #[derive(Copy, Clone, Debug)]
 pub struct ObjectInfo {
@@ -158,6 +221,15 @@ pub struct ObjectInfo {
     pub interface: &'static Interface,
     /// The version
     pub version: u32,
+    /// The serial distinguishing this object from the other objects which had the same protocol ID
+    ///
+    /// It is 0 when it is not tracked, as on the system backends.
+    pub serial: u32,
+    /// Whether this object is still alive
+    ///
+    /// This is only false for objects whose destruction has been sent but not acknowledged yet, as
+    /// reported by the client `Handle::info_many()` of the rust backend.
+    pub alive: bool,
 }
 
 /// A protocol error
@@ -215,16 +287,168 @@ pub fn same_interface(a: &'static Interface, b: &'static Interface) -> bool {
     std::ptr::eq(a, b) || a.name == b.name
 }
 
-pub(crate) fn check_for_signature<Id>(signature: &[ArgumentType], args: &[Argument<Id>]) -> bool {
-    if signature.len() != args.len() {
-        return false;
+/// A mismatch between the arguments of a message and its signature
+///
+/// Its `Display` implementation lists the expected and provided argument kinds side by side,
+/// marking the first mismatch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureMismatch {
+    /// The name of the message
+    pub message: &'static str,
+    /// The position of the first argument which does not match the signature
+    pub position: usize,
+    /// The names of the arguments, from the protocol
+    pub arg_names: &'static [&'static str],
+    /// The argument kinds expected by the signature
+    pub expected: &'static [ArgumentType],
+    /// The argument kinds provided
+    pub provided: Vec<ArgumentType>,
+}
+
+impl std::error::Error for SignatureMismatch {}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for SignatureMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = |i: usize| {
+            self.expected.get(i).map(|&typ| match self.arg_names.get(i) {
+                Some(name) => format!("{}: {}", name, kind_name(typ)),
+                None => kind_name(typ).into(),
+            })
+        };
+        let provided = |i: usize| self.provided.get(i).map(|&typ| kind_name(typ).to_owned());
+        let width =
+            (0..self.expected.len()).filter_map(expected).map(|s| s.len()).max().unwrap_or(0);
+        let width = width.max("<missing>".len());
+
+        write!(f, "arguments of {} differ at position {}", self.message, self.position)?;
+        if let Some(name) = self.arg_names.get(self.position) {
+            write!(f, " ({})", name)?;
+        }
+        write!(f, "\n       {:width$}  provided", "expected", width = width)?;
+        for i in 0..self.expected.len().max(self.provided.len()) {
+            let marker = if i == self.position { '>' } else { ' ' };
+            let expected = expected(i).unwrap_or_else(|| "<missing>".into());
+            let provided = provided(i).unwrap_or_else(|| "<missing>".into());
+            write!(f, "\n{} {:>3}  {:width$}  {}", marker, i, expected, provided, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+// The name of the kind of an argument type, as written in the protocol files
+fn kind_name(typ: ArgumentType) -> &'static str {
+    match typ {
+        ArgumentType::Int => "int",
+        ArgumentType::Uint => "uint",
+        ArgumentType::Fixed => "fixed",
+        ArgumentType::Str(_) => "string",
+        ArgumentType::Object(_) => "object",
+        ArgumentType::NewId(_) => "new_id",
+        ArgumentType::Array(_) => "array",
+        ArgumentType::Fd => "fd",
+    }
+}
+
+/// Compare the arguments of a message with the signature of its description
+///
+/// Only the kinds of the arguments are compared, not their nullability. On mismatch, the returned
+/// [`SignatureMismatch`] describes the difference, and can be displayed in assertions:
+///
+/// ```
+/// # use wayland_backend::protocol::{compare_signature, Argument, MessageDesc};
+/// # fn check(desc: &MessageDesc, args: &[Argument<u32>]) {
+/// if let Err(mismatch) = compare_signature(desc, args) {
+///     panic!("{}", mismatch);
+/// }
+/// # }
+/// ```
+pub fn compare_signature<Id>(
+    desc: &MessageDesc,
+    args: &[Argument<Id>],
+) -> Result<(), SignatureMismatch> {
+    let position = desc
+        .signature
+        .iter()
+        .zip(args.iter())
+        .position(|(&typ, arg)| !arg.get_type().same_type(typ))
+        .or_else(|| {
+            if desc.signature.len() != args.len() {
+                Some(desc.signature.len().min(args.len()))
+            } else {
+                None
+            }
+        });
+    match position {
+        None => Ok(()),
+        Some(position) => Err(SignatureMismatch {
+            message: desc.name,
+            position,
+            arg_names: desc.arg_names,
+            expected: desc.signature,
+            provided: args.iter().map(Argument::get_type).collect(),
+        }),
+    }
+}
+
+/// The interface and version of the object created by a request, if it creates one
+///
+/// The interface of the placeholder, retrieved with `placeholder_interface`, is checked against the
+/// one deduced from the protocol. Objects created by regular constructors have the version of their
+/// parent, while generic constructors like `wl_registry.bind` give the name of the interface and the
+/// version in the message. If the placeholder is anonymous, the interface is then looked up in the
+/// registry of [`register_interfaces()`]. Otherwise the interface of the placeholder is returned,
+/// as it may carry a C pointer where the one of the protocol does not.
+///
+/// The error describes the faulty placeholder.
+pub(crate) fn child_spec<Id>(
+    desc: &MessageDesc,
+    args: &[Argument<Id>],
+    parent_version: u32,
+    placeholder_interface: impl Fn(&Id) -> &'static Interface,
+) -> Result<Option<(&'static Interface, u32)>, String> {
+    let (index, placeholder) = match args.iter().enumerate().find_map(|(i, arg)| match arg {
+        Argument::NewId(id) => Some((i, placeholder_interface(id))),
+        _ => None,
+    }) {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let anonymous = same_interface(placeholder, &ANONYMOUS_INTERFACE);
+
+    if let Some(child_interface) = desc.child_interface {
+        if !anonymous && !same_interface(child_interface, placeholder) {
+            return Err(format!(
+                "expected interface {} but got {}",
+                child_interface.name, placeholder.name
+            ));
+        }
+        let interface = if anonymous { child_interface } else { placeholder };
+        return Ok(Some((interface, parent_version)));
     }
-    for (typ, arg) in signature.iter().copied().zip(args.iter()) {
-        if !arg.get_type().same_type(typ) {
-            return false;
+
+    match args[..index] {
+        [.., Argument::Str(ref name), Argument::Uint(version)] => {
+            let name = name.to_string_lossy();
+            let interface = if anonymous {
+                lookup_interface(&name).ok_or_else(|| {
+                    format!(
+                        "interface {} is unknown, it must be given to the placeholder or registered",
+                        name
+                    )
+                })?
+            } else if placeholder.name != name {
+                return Err(format!(
+                    "the message creates an object of interface {} but got {}",
+                    name, placeholder.name
+                ));
+            } else {
+                placeholder
+            };
+            Ok(Some((interface, version)))
         }
+        _ => Err("target interface must be specified for a generic constructor.".into()),
     }
-    true
 }
 
 #[inline]
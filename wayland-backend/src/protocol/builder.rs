@@ -0,0 +1,235 @@
+//! Construction of messages argument by argument
+
+use std::{ffi::CString, os::unix::io::RawFd};
+
+use smallvec::SmallVec;
+
+use super::{
+    Argument, ArgumentType, Interface, Message, MessageDesc, MessageDirection, INLINE_ARGS,
+};
+
+/// Error generated when building a message that does not match its description
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageBuildError {
+    /// The interface has no message with this opcode in this direction
+    UnknownOpcode {
+        /// The name of the interface
+        interface: &'static str,
+        /// The requested opcode
+        opcode: u16,
+    },
+    /// The interface has no message with this name in this direction
+    UnknownName {
+        /// The name of the interface
+        interface: &'static str,
+        /// The requested name
+        name: String,
+    },
+    /// An argument does not have the type expected by the signature, or is in excess
+    UnexpectedArgument {
+        /// The name of the message
+        message: &'static str,
+        /// The position of the argument
+        index: usize,
+        /// The type expected by the signature, `None` if it has no more arguments
+        expected: Option<ArgumentType>,
+        /// The type of the provided argument
+        found: ArgumentType,
+    },
+    /// A string argument contains an interior nul byte
+    NulInString {
+        /// The name of the message
+        message: &'static str,
+        /// The position of the argument
+        index: usize,
+    },
+    /// The message was built before all the arguments of the signature were provided
+    MissingArguments {
+        /// The name of the message
+        message: &'static str,
+        /// The number of arguments of the signature
+        expected: usize,
+        /// The number of arguments provided
+        found: usize,
+    },
+}
+
+impl std::error::Error for MessageBuildError {}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for MessageBuildError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match self {
+            MessageBuildError::UnknownOpcode { interface, opcode } => {
+                write!(f, "Interface {} has no message with opcode {}", interface, opcode)
+            }
+            MessageBuildError::UnknownName { interface, name } => {
+                write!(f, "Interface {} has no message named {}", interface, name)
+            }
+            MessageBuildError::UnexpectedArgument {
+                message,
+                index,
+                expected: Some(typ),
+                found,
+            } => {
+                write!(
+                    f,
+                    "Argument {} of {} should be of type {:?}, got {:?}",
+                    index, message, typ, found
+                )
+            }
+            MessageBuildError::UnexpectedArgument { message, index, expected: None, .. } => {
+                write!(f, "Message {} has only {} arguments", message, index)
+            }
+            MessageBuildError::NulInString { message, index } => {
+                write!(f, "Argument {} of {} contains a nul byte", index, message)
+            }
+            MessageBuildError::MissingArguments { message, expected, found } => {
+                write!(f, "Message {} expects {} arguments, got {}", message, expected, found)
+            }
+        }
+    }
+}
+
+/// A builder of [`Message`]s, checking the arguments against the signature of the message
+///
+/// Each argument is validated as it is pushed, so that errors point at the faulty argument:
+///
+/// ```
+/// use wayland_backend::protocol::{Interface, MessageBuilder, MessageDirection};
+/// # fn build(wl_surface: &'static Interface) -> Result<(), Box<dyn std::error::Error>> {
+/// // wl_surface@3.attach(wl_buffer@5, 0, 0)
+/// let msg = MessageBuilder::named(wl_surface, MessageDirection::Request, 3u32, "attach")?
+///     .object(5)?
+///     .int(0)?
+///     .int(0)?
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Only the types of the arguments are checked: the builder does not know whether object ids
+/// exist or are null, nor their interfaces.
+#[derive(Debug, Clone)]
+pub struct MessageBuilder<Id> {
+    desc: &'static MessageDesc,
+    sender_id: Id,
+    opcode: u16,
+    args: SmallVec<[Argument<Id>; INLINE_ARGS]>,
+}
+
+impl<Id> MessageBuilder<Id> {
+    /// Start building the message with a given opcode, sent by an object of an interface
+    pub fn new(
+        interface: &'static Interface,
+        direction: MessageDirection,
+        sender_id: Id,
+        opcode: u16,
+    ) -> Result<Self, MessageBuildError> {
+        let desc = messages(interface, direction)
+            .get(opcode as usize)
+            .ok_or(MessageBuildError::UnknownOpcode { interface: interface.name, opcode })?;
+        Ok(MessageBuilder { desc, sender_id, opcode, args: SmallVec::new() })
+    }
+
+    /// Start building the message with a given name, sent by an object of an interface
+    pub fn named(
+        interface: &'static Interface,
+        direction: MessageDirection,
+        sender_id: Id,
+        name: &str,
+    ) -> Result<Self, MessageBuildError> {
+        let opcode =
+            messages(interface, direction).iter().position(|desc| desc.name == name).ok_or_else(
+                || MessageBuildError::UnknownName { interface: interface.name, name: name.into() },
+            )?;
+        Self::new(interface, direction, sender_id, opcode as u16)
+    }
+
+    /// The description of the message being built
+    pub fn desc(&self) -> &'static MessageDesc {
+        self.desc
+    }
+
+    /// Push the next argument
+    pub fn arg(mut self, arg: Argument<Id>) -> Result<Self, MessageBuildError> {
+        let index = self.args.len();
+        let expected = self.desc.signature.get(index).copied();
+        let found = arg.get_type();
+        match expected {
+            Some(typ) if typ.same_type(found) => {
+                self.args.push(arg);
+                Ok(self)
+            }
+            _ => Err(MessageBuildError::UnexpectedArgument {
+                message: self.desc.name,
+                index,
+                expected,
+                found,
+            }),
+        }
+    }
+
+    /// Push an `int` argument
+    pub fn int(self, value: i32) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::Int(value))
+    }
+
+    /// Push an `uint` argument
+    pub fn uint(self, value: u32) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::Uint(value))
+    }
+
+    /// Push a `fixed` argument, rounded to 1/256
+    pub fn fixed(self, value: f64) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::Fixed((value * 256.).round() as i32))
+    }
+
+    /// Push a `string` argument
+    pub fn string(self, value: &str) -> Result<Self, MessageBuildError> {
+        let value = CString::new(value).map_err(|_| MessageBuildError::NulInString {
+            message: self.desc.name,
+            index: self.args.len(),
+        })?;
+        self.arg(Argument::Str(Box::new(value)))
+    }
+
+    /// Push an `object` argument
+    pub fn object(self, id: Id) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::Object(id))
+    }
+
+    /// Push a `new_id` argument
+    pub fn new_id(self, id: Id) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::NewId(id))
+    }
+
+    /// Push an `array` argument
+    pub fn array(self, value: Vec<u8>) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::Array(Box::new(value)))
+    }
+
+    /// Push a `fd` argument
+    pub fn fd(self, fd: RawFd) -> Result<Self, MessageBuildError> {
+        self.arg(Argument::Fd(fd))
+    }
+
+    /// Build the message, checking that all its arguments were provided
+    pub fn build(self) -> Result<Message<Id>, MessageBuildError> {
+        if self.args.len() != self.desc.signature.len() {
+            return Err(MessageBuildError::MissingArguments {
+                message: self.desc.name,
+                expected: self.desc.signature.len(),
+                found: self.args.len(),
+            });
+        }
+        Ok(Message { sender_id: self.sender_id, opcode: self.opcode, args: self.args })
+    }
+}
+
+fn messages(interface: &'static Interface, direction: MessageDirection) -> &'static [MessageDesc] {
+    match direction {
+        MessageDirection::Request => interface.requests,
+        MessageDirection::Event => interface.events,
+    }
+}
@@ -0,0 +1,217 @@
+//! Compatibility checks between interface descriptions
+
+use super::{encode_signature, Interface, MessageDesc, MessageDirection};
+
+/// A difference between two descriptions of an interface making them incompatible
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterfaceMismatch {
+    /// The interfaces have different names
+    Name {
+        /// The name of the first interface
+        expected: &'static str,
+        /// The name of the second interface
+        found: &'static str,
+    },
+    /// A message exists in both interfaces, but with different descriptions
+    Message {
+        /// The name of the interface
+        interface: &'static str,
+        /// Whether the message is a request or an event
+        direction: MessageDirection,
+        /// The opcode of the message
+        opcode: u16,
+        /// The name of the message in the first interface
+        name: &'static str,
+        /// What differs between the two descriptions of the message
+        kind: MessageMismatch,
+    },
+}
+
+/// What differs between two descriptions of a message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageMismatch {
+    /// The messages have different names
+    Name,
+    /// The messages were introduced in different versions of the interface
+    Since,
+    /// The messages have different signatures
+    Signature,
+    /// Only one of the messages is a destructor
+    Destructor,
+    /// The messages create objects of different interfaces
+    ChildInterface,
+    /// The message only exists in a version supported by both interfaces in one of them
+    Missing,
+}
+
+impl std::error::Error for InterfaceMismatch {}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for InterfaceMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            InterfaceMismatch::Name { expected, found } => {
+                write!(f, "Expected interface {}, found {}", expected, found)
+            }
+            InterfaceMismatch::Message { interface, direction, opcode, name, kind } => {
+                let direction = match direction {
+                    MessageDirection::Request => "request",
+                    MessageDirection::Event => "event",
+                };
+                let kind = match kind {
+                    MessageMismatch::Name => "has different names",
+                    MessageMismatch::Since => "was introduced in different versions",
+                    MessageMismatch::Signature => "has different signatures",
+                    MessageMismatch::Destructor => "is not a destructor in both descriptions",
+                    MessageMismatch::ChildInterface => "creates objects of different interfaces",
+                    MessageMismatch::Missing => "is missing from one description",
+                };
+                write!(f, "The {} {}.{} (opcode {}) {}", direction, interface, name, opcode, kind)
+            }
+        }
+    }
+}
+
+impl Interface {
+    /// Check that two descriptions of an interface can be used interchangeably
+    ///
+    /// The interfaces must have the same name, and the messages existing in the versions supported
+    /// by both must be the same on the wire: same name, version, signature, destructor status and
+    /// interface of the created object. A description of a newer version of an interface is thus
+    /// compatible with a description of an older version, up to the older version.
+    ///
+    /// The interfaces referenced by the messages are compared by name only.
+    pub fn check_compatibility(&self, other: &Interface) -> Result<(), InterfaceMismatch> {
+        if self.name != other.name {
+            return Err(InterfaceMismatch::Name { expected: self.name, found: other.name });
+        }
+        let version = self.version.min(other.version);
+        let lists = [
+            (MessageDirection::Request, self.requests, other.requests),
+            (MessageDirection::Event, self.events, other.events),
+        ];
+        for &(direction, ours, theirs) in lists.iter() {
+            for opcode in 0..ours.len().max(theirs.len()) {
+                if let Err(kind) = compare_messages(ours.get(opcode), theirs.get(opcode), version) {
+                    let name = ours.get(opcode).or_else(|| theirs.get(opcode)).unwrap().name;
+                    return Err(InterfaceMismatch::Message {
+                        interface: self.name,
+                        direction,
+                        opcode: opcode as u16,
+                        name,
+                        kind,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether two descriptions of an interface can be used interchangeably
+    ///
+    /// See [`check_compatibility()`](Interface::check_compatibility) for details.
+    pub fn is_compatible_with(&self, other: &Interface) -> bool {
+        self.check_compatibility(other).is_ok()
+    }
+
+    /// A hash of the wire-level description of this interface
+    ///
+    /// The hash covers the name and version of the interface, and the name, version, signature,
+    /// destructor status and child interface name of each of its messages. It does not depend on
+    /// the platform or on the compiler version, so it can be stored or compared across builds: two
+    /// descriptions with the same hash describe the same interface.
+    pub fn signature_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write_str(self.name);
+        hasher.write_u32(self.version);
+        for messages in [self.requests, self.events].iter() {
+            hasher.write_u32(messages.len() as u32);
+            for desc in messages.iter() {
+                hasher.write_str(desc.name);
+                hasher.write_str(&encode_signature(desc.since, desc.signature));
+                hasher.write(&[desc.is_destructor as u8]);
+                hasher.write_str(desc.child_interface.map(|i| i.name).unwrap_or(""));
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// Check that two independently generated descriptions of a protocol are interoperable
+///
+/// Each interface of `a` is checked for compatibility with the interface of the same name in `b`,
+/// if any. This is meant for the `INTERFACES` lists generated by `wayland-scanner`, when several
+/// crates generate code for the same protocol and exchange objects of its interfaces.
+pub fn check_interoperable(
+    a: &[&'static Interface],
+    b: &[&'static Interface],
+) -> Result<(), InterfaceMismatch> {
+    for interface in a {
+        if let Some(other) = b.iter().find(|other| other.name == interface.name) {
+            interface.check_compatibility(other)?;
+        }
+    }
+    Ok(())
+}
+
+fn compare_messages(
+    a: Option<&MessageDesc>,
+    b: Option<&MessageDesc>,
+    version: u32,
+) -> Result<(), MessageMismatch> {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        // a message only existing in one of the descriptions must be newer than the other
+        (Some(desc), None) | (None, Some(desc)) => {
+            return if desc.since > version { Ok(()) } else { Err(MessageMismatch::Missing) };
+        }
+        (None, None) => return Ok(()),
+    };
+    if a.since > version && b.since > version {
+        // the message is not part of the versions supported by both
+        return Ok(());
+    }
+    if a.name != b.name {
+        Err(MessageMismatch::Name)
+    } else if a.since != b.since {
+        Err(MessageMismatch::Since)
+    } else if a.signature != b.signature {
+        Err(MessageMismatch::Signature)
+    } else if a.is_destructor != b.is_destructor {
+        Err(MessageMismatch::Destructor)
+    } else if a.child_interface.map(|i| i.name) != b.child_interface.map(|i| i.name) {
+        Err(MessageMismatch::ChildInterface)
+    } else {
+        Ok(())
+    }
+}
+
+// The 64 bits FNV-1a hash, whose output is fully specified
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_str(&mut self, value: &str) {
+        // length-prefixed to keep the encoding unambiguous
+        self.write_u32(value.len() as u32);
+        self.write(value.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
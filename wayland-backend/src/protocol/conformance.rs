@@ -0,0 +1,160 @@
+//! Checks of the invariants of the protocol which parsing does not enforce
+
+use std::sync::{Arc, Mutex};
+
+use super::{AllowNull, Argument, ArgumentType, Interface, Message, MessageDesc};
+
+/// A violation of the protocol detected by the conformance checks of a backend
+///
+/// These violations do not prevent the messages from being parsed, and are thus not reported as
+/// protocol errors. They are reported to the conformance hook of the backend, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConformanceViolation {
+    /// A message was received for an object after a destructor event destroyed it
+    AfterDestructor {
+        /// The interface of the object
+        interface: &'static str,
+        /// The protocol id of the object
+        id: u32,
+        /// The name of the message
+        message: &'static str,
+    },
+    /// An enum argument has a value which is not defined by the protocol
+    InvalidEnumValue {
+        /// The interface of the object
+        interface: &'static str,
+        /// The protocol id of the object
+        id: u32,
+        /// The name of the message
+        message: &'static str,
+        /// The name of the argument
+        arg: &'static str,
+        /// The name of the enum
+        enum_name: &'static str,
+        /// The value of the argument
+        value: u32,
+    },
+    /// A null object was given for an argument which does not allow it
+    NullObject {
+        /// The interface of the object
+        interface: &'static str,
+        /// The protocol id of the object
+        id: u32,
+        /// The name of the message
+        message: &'static str,
+        /// The name of the argument
+        arg: &'static str,
+    },
+    /// File descriptors were received without any message to consume them
+    UnexpectedFds {
+        /// The number of file descriptors left
+        count: usize,
+    },
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for ConformanceViolation {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match self {
+            ConformanceViolation::AfterDestructor { interface, id, message } => {
+                write!(f, "{}@{}.{} received after a destructor", interface, id, message)
+            }
+            ConformanceViolation::InvalidEnumValue {
+                interface,
+                id,
+                message,
+                arg,
+                enum_name,
+                value,
+            } => write!(
+                f,
+                "{}@{}.{}: value {} of argument {} is not valid for enum {}",
+                interface, id, message, value, arg, enum_name
+            ),
+            ConformanceViolation::NullObject { interface, id, message, arg } => {
+                write!(f, "{}@{}.{}: argument {} must not be null", interface, id, message, arg)
+            }
+            ConformanceViolation::UnexpectedFds { count } => {
+                write!(f, "{} file descriptors received without a message using them", count)
+            }
+        }
+    }
+}
+
+/// Check the arguments of a parsed message against the enums and nullability of its description
+pub(crate) fn check_arguments(
+    interface: &'static Interface,
+    desc: &MessageDesc,
+    msg: &Message<u32>,
+    mut report: impl FnMut(ConformanceViolation),
+) {
+    let arg_name = |index: usize| desc.arg_names.get(index).copied().unwrap_or("<unknown>");
+    for (index, (arg, typ)) in msg.args.iter().zip(desc.signature).enumerate() {
+        if let (Argument::Object(0), ArgumentType::Object(AllowNull::No)) = (arg, typ) {
+            report(ConformanceViolation::NullObject {
+                interface: interface.name,
+                id: msg.sender_id,
+                message: desc.name,
+                arg: arg_name(index),
+            });
+        }
+    }
+    for arg_enum in desc.arg_enums {
+        let value = match msg.args.get(arg_enum.index) {
+            Some(Argument::Uint(value)) => *value,
+            Some(Argument::Int(value)) => *value as u32,
+            _ => continue,
+        };
+        if !arg_enum.accepts(value) {
+            report(ConformanceViolation::InvalidEnumValue {
+                interface: interface.name,
+                id: msg.sender_id,
+                message: desc.name,
+                arg: arg_name(arg_enum.index),
+                enum_name: arg_enum.name,
+                value,
+            });
+        }
+    }
+}
+
+type ConformanceHookFn<C> = dyn FnMut(C, &ConformanceViolation) + Send;
+
+/// The hook reporting conformance violations, enabling the checks when set
+pub(crate) struct ConformanceHook<C> {
+    hook: Arc<Mutex<Option<Box<ConformanceHookFn<C>>>>>,
+}
+
+impl<C> ConformanceHook<C> {
+    pub(crate) fn new() -> ConformanceHook<C> {
+        ConformanceHook { hook: Arc::new(Mutex::new(None)) }
+    }
+
+    pub(crate) fn set(&self, hook: Box<ConformanceHookFn<C>>) {
+        *self.hook.lock().unwrap() = Some(hook);
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.hook.lock().unwrap().is_some()
+    }
+
+    pub(crate) fn invoke(&self, context: C, violation: &ConformanceViolation) {
+        if let Some(hook) = self.hook.lock().unwrap().as_mut() {
+            hook(context, violation);
+        }
+    }
+}
+
+// manual impls to avoid requiring `C: Clone` and `C: Debug`
+impl<C> Clone for ConformanceHook<C> {
+    fn clone(&self) -> ConformanceHook<C> {
+        ConformanceHook { hook: self.hook.clone() }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<C> std::fmt::Debug for ConformanceHook<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConformanceHook").finish_non_exhaustive()
+    }
+}
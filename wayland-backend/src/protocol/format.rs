@@ -0,0 +1,192 @@
+//! Human-readable formatting of messages
+
+use std::{
+    collections::HashMap,
+    fmt::{Result as FmtResult, Write},
+};
+
+use crate::{core_interfaces::WL_DISPLAY_INTERFACE, rs::map::SERVER_ID_LIMIT};
+
+use super::{
+    lookup_interface, same_interface, Argument, Interface, Message, MessageDesc, MessageDirection,
+    ANONYMOUS_INTERFACE,
+};
+
+/// Format a message in the syntax used by `WAYLAND_DEBUG`
+///
+/// The message is formatted as `wl_surface@13.attach(wl_buffer@5, 0, 0)`, given the interface
+/// of the object that sent it and the direction of the message. The interfaces of the object
+/// arguments are taken from the message description, they are thus unknown for arguments that
+/// accept any interface. See [`CaptureFormatter`] to format a whole capture, tracking the
+/// interfaces of all objects.
+pub fn format_message(
+    msg: &Message<u32>,
+    interface: &Interface,
+    direction: MessageDirection,
+) -> String {
+    let mut out = String::new();
+    // writing to a String never fails
+    let _ = write_message(&mut out, msg, interface, direction, |_| None);
+    out
+}
+
+/// A formatter for a whole capture of a connection
+///
+/// This formatter is fed with all the messages exchanged on a connection, in order. It keeps
+/// track of the objects they create and destroy, so that it can format each message as
+/// [`format_message()`] does, without the need to provide the interface of its sender, and
+/// with the interfaces of all object arguments.
+///
+/// It needs to know all interfaces that are bound from the registry, either from the list given
+/// to [`CaptureFormatter::new()`] or from the ones registered with
+/// [`register_interfaces()`](super::register_interfaces).
+#[derive(Debug)]
+pub struct CaptureFormatter {
+    interfaces: Vec<&'static Interface>,
+    objects: HashMap<u32, &'static Interface>,
+}
+
+impl CaptureFormatter {
+    /// Create a new formatter, for a connection only containing the `wl_display`
+    pub fn new(interfaces: &[&'static Interface]) -> CaptureFormatter {
+        let mut objects = HashMap::new();
+        objects.insert(1, &WL_DISPLAY_INTERFACE);
+        CaptureFormatter { interfaces: interfaces.to_vec(), objects }
+    }
+
+    /// The interface of an object of the connection, if it is alive
+    ///
+    /// This can be used to find the signature of the next message of the capture, if it needs
+    /// to be parsed.
+    pub fn interface_of(&self, id: u32) -> Option<&'static Interface> {
+        self.objects.get(&id).copied()
+    }
+
+    /// Format the next message of the capture
+    ///
+    /// The messages sent by unknown objects are formatted as `[unknown]@id.opcode(args)`.
+    pub fn format(&mut self, msg: &Message<u32>, direction: MessageDirection) -> String {
+        let mut out = String::new();
+        match self.objects.get(&msg.sender_id).copied() {
+            Some(interface) => {
+                let objects = &self.objects;
+                let _ = write_message(&mut out, msg, interface, direction, |id| {
+                    objects.get(&id).map(|i| i.name)
+                });
+                if let Some(desc) = messages(interface, direction).get(msg.opcode as usize) {
+                    self.track_objects(msg, desc);
+                }
+            }
+            None => {
+                let _ = write!(out, "[unknown]@{}.{}(", msg.sender_id, msg.opcode);
+                let _ = write_args(&mut out, msg, None, |_| None);
+                out.push(')');
+            }
+        }
+        out
+    }
+
+    fn track_objects(&mut self, msg: &Message<u32>, desc: &MessageDesc) {
+        for (i, arg) in msg.args.iter().enumerate() {
+            if let Argument::NewId(id) = *arg {
+                let interface = desc.child_interface.or_else(|| match msg.args[..i] {
+                    [.., Argument::Str(ref name), Argument::Uint(_)] => self
+                        .interfaces
+                        .iter()
+                        .copied()
+                        .find(|i| i.name.as_bytes() == name.to_bytes())
+                        .or_else(|| lookup_interface(name.to_str().ok()?)),
+                    _ => None,
+                });
+                match interface {
+                    Some(interface) => self.objects.insert(id, interface),
+                    None => self.objects.remove(&id),
+                };
+            }
+        }
+
+        if msg.sender_id == 1 && desc.name == "delete_id" {
+            if let [Argument::Uint(id)] = msg.args[..] {
+                self.objects.remove(&id);
+            }
+        } else if desc.is_destructor && msg.sender_id >= SERVER_ID_LIMIT {
+            // client-created objects are removed by the delete_id event acknowledging their
+            // destruction
+            self.objects.remove(&msg.sender_id);
+        }
+    }
+}
+
+fn messages(interface: &Interface, direction: MessageDirection) -> &'static [MessageDesc] {
+    match direction {
+        MessageDirection::Request => interface.requests,
+        MessageDirection::Event => interface.events,
+    }
+}
+
+fn write_message<'a>(
+    out: &mut String,
+    msg: &Message<u32>,
+    interface: &Interface,
+    direction: MessageDirection,
+    object_interface: impl Fn(u32) -> Option<&'a str>,
+) -> FmtResult {
+    let desc = messages(interface, direction).get(msg.opcode as usize);
+    write!(out, "{}@{}.", interface.name, msg.sender_id)?;
+    match desc {
+        Some(desc) => out.push_str(desc.name),
+        None => write!(out, "{}", msg.opcode)?,
+    }
+    out.push('(');
+    write_args(out, msg, desc, object_interface)?;
+    out.push(')');
+    Ok(())
+}
+
+fn write_args<'a>(
+    out: &mut String,
+    msg: &Message<u32>,
+    desc: Option<&MessageDesc>,
+    object_interface: impl Fn(u32) -> Option<&'a str>,
+) -> FmtResult {
+    let mut arg_interfaces = desc.map(|desc| desc.arg_interfaces).unwrap_or(&[]).iter();
+    for (i, arg) in msg.args.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match *arg {
+            Argument::Int(value) => write!(out, "{}", value)?,
+            Argument::Uint(value) => write!(out, "{}", value)?,
+            Argument::Fixed(value) => write!(out, "{}", value as f64 / 256.)?,
+            Argument::Str(ref value) => write!(out, "{:?}", value.to_string_lossy())?,
+            Argument::Object(id) => {
+                let expected = arg_interfaces.next().copied();
+                if id == 0 {
+                    out.push_str("nil");
+                } else {
+                    let name = match expected {
+                        Some(interface) if !same_interface(interface, &ANONYMOUS_INTERFACE) => {
+                            Some(interface.name)
+                        }
+                        _ => object_interface(id),
+                    };
+                    write!(out, "{}@{}", name.unwrap_or("[unknown]"), id)?;
+                }
+            }
+            Argument::NewId(id) => {
+                let name = match desc.and_then(|desc| desc.child_interface) {
+                    Some(interface) => interface.name.into(),
+                    // a generic new_id is preceded by the interface name and version
+                    None => match msg.args[..i] {
+                        [.., Argument::Str(ref name), Argument::Uint(_)] => name.to_string_lossy(),
+                        _ => "[unknown]".into(),
+                    },
+                };
+                write!(out, "new id {}@{}", name, id)?;
+            }
+            Argument::Array(ref value) => write!(out, "array[{}]", value.len())?,
+            Argument::Fd(fd) => write!(out, "fd {}", fd)?,
+        }
+    }
+    Ok(())
+}
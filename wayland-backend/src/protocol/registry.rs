@@ -0,0 +1,42 @@
+//! Global registry of the known interfaces
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::core_interfaces::{WL_CALLBACK_INTERFACE, WL_DISPLAY_INTERFACE, WL_REGISTRY_INTERFACE};
+
+use super::Interface;
+
+lazy_static::lazy_static! {
+    static ref INTERFACES: RwLock<HashMap<&'static str, &'static Interface>> = {
+        let core: [&'static Interface; 3] =
+            [&WL_DISPLAY_INTERFACE, &WL_REGISTRY_INTERFACE, &WL_CALLBACK_INTERFACE];
+        RwLock::new(core.iter().map(|&interface| (interface.name, interface)).collect())
+    };
+}
+
+/// Register interfaces, making them available from [`lookup_interface()`]
+///
+/// The code generated by `wayland-scanner` provides the list of the interfaces of a protocol as an
+/// `INTERFACES` static, to be registered with this function. `wayland-client` and
+/// `wayland-server` register the interfaces of the core protocol themselves.
+///
+/// If several interfaces have the same name, the one with the highest version is kept, as it
+/// describes all the messages of the others.
+pub fn register_interfaces(interfaces: &[&'static Interface]) {
+    let mut registry = INTERFACES.write().unwrap();
+    for &interface in interfaces {
+        let known = registry.entry(interface.name).or_insert(interface);
+        if known.version < interface.version {
+            *known = interface;
+        }
+    }
+}
+
+/// Find a registered interface from its name
+///
+/// This resolves the interface of objects only known by the name of their interface, like the
+/// objects created by a `wl_registry.bind` request or the anonymous object arguments of a message.
+/// The `wl_display`, `wl_registry` and `wl_callback` interfaces are always known.
+pub fn lookup_interface(name: &str) -> Option<&'static Interface> {
+    INTERFACES.read().unwrap().get(name).copied()
+}
@@ -0,0 +1,218 @@
+//! Statistics of the messages exchanged over a connection
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use super::{Argument, Message};
+
+/// The direction of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// The message was sent by this side of the connection
+    Sent,
+    /// The message was received from the peer
+    Received,
+}
+
+/// The statistics of the messages of a given kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageStats {
+    /// The interface of the objects the messages were sent to
+    pub interface: &'static str,
+    /// The name of the message
+    pub message: &'static str,
+    /// The opcode of the message
+    pub opcode: u16,
+    /// Whether the messages were sent or received
+    pub direction: Direction,
+    /// The number of messages
+    pub count: u64,
+    /// The size of the messages on the wire in bytes, not counting their file descriptors
+    pub bytes: u64,
+}
+
+/// Statistics of the messages exchanged over a connection, per interface and opcode
+///
+/// They are recorded by the rust backends once enabled with `enable_stats()` on their `Handle`.
+/// If the `WAYLAND_STATS` environment variable is set to `1`, or to `client` or `server` like
+/// `WAYLAND_DEBUG`, they are enabled when the backend is created, and printed to stderr when it is
+/// dropped.
+///
+/// The `Display` implementation formats them as a table for each direction, listing the messages
+/// representing the largest share of the traffic first.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolStats {
+    messages: HashMap<(&'static str, u16, Direction), MessageStats>,
+}
+
+impl ProtocolStats {
+    /// The statistics of each kind of message, in no particular order
+    pub fn messages(&self) -> impl Iterator<Item = &MessageStats> {
+        self.messages.values()
+    }
+
+    /// The statistics of the messages of an interface and opcode, if any was recorded
+    pub fn get(&self, interface: &str, opcode: u16, direction: Direction) -> Option<&MessageStats> {
+        self.messages.values().find(|stats| {
+            stats.interface == interface && stats.opcode == opcode && stats.direction == direction
+        })
+    }
+
+    /// The total number of messages, and their size in bytes, in a direction
+    pub fn total(&self, direction: Direction) -> (u64, u64) {
+        self.messages
+            .values()
+            .filter(|stats| stats.direction == direction)
+            .fold((0, 0), |(count, bytes), stats| (count + stats.count, bytes + stats.bytes))
+    }
+
+    pub(crate) fn record<Id>(
+        &mut self,
+        direction: Direction,
+        interface: &'static str,
+        message: &'static str,
+        msg: &Message<Id>,
+    ) {
+        let stats =
+            self.messages.entry((interface, msg.opcode, direction)).or_insert(MessageStats {
+                interface,
+                message,
+                opcode: msg.opcode,
+                direction,
+                count: 0,
+                bytes: 0,
+            });
+        stats.count += 1;
+        stats.bytes += wire_size(msg) as u64;
+    }
+}
+
+// the size of a message on the wire: its header, and its arguments padded to 32 bits
+fn wire_size<Id>(msg: &Message<Id>) -> usize {
+    let padded = |len: usize| (len + 3) & !3;
+    8 + msg
+        .args
+        .iter()
+        .map(|arg| match arg {
+            Argument::Str(s) => 4 + padded(s.as_bytes_with_nul().len()),
+            Argument::Array(a) => 4 + padded(a.len()),
+            Argument::Fd(_) => 0,
+            _ => 4,
+        })
+        .sum::<usize>()
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for ProtocolStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (direction, title) in [(Direction::Sent, "sent"), (Direction::Received, "received")] {
+            let (count, bytes) = self.total(direction);
+            writeln!(f, "{}: {} messages, {} bytes", title, count, bytes)?;
+            let mut messages = self
+                .messages
+                .values()
+                .filter(|stats| stats.direction == direction)
+                .collect::<Vec<_>>();
+            messages.sort_by(|a, b| {
+                b.bytes
+                    .cmp(&a.bytes)
+                    .then(b.count.cmp(&a.count))
+                    .then((a.interface, a.message).cmp(&(b.interface, b.message)))
+            });
+            for stats in messages {
+                writeln!(
+                    f,
+                    "\t{:5.1}% {:10} bytes {:8} messages  {}.{}",
+                    stats.bytes as f64 * 100. / bytes as f64,
+                    stats.bytes,
+                    stats.count,
+                    stats.interface,
+                    stats.message
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The recording of the statistics of a backend, shared by its connections
+#[derive(Clone)]
+pub(crate) struct StatsRecorder {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    // checked before locking, to keep the cost of disabled statistics negligible
+    enabled: AtomicBool,
+    stats: Mutex<ProtocolStats>,
+    // the side of the connection printed with the statistics when dumping them on drop
+    dump_as: Option<&'static str>,
+}
+
+impl StatsRecorder {
+    /// Create a recorder, enabled and dumping on drop if `WAYLAND_STATS` is set for this side
+    pub(crate) fn new(side: &'static str) -> StatsRecorder {
+        let dump =
+            matches!(std::env::var_os("WAYLAND_STATS"), Some(str) if str == "1" || str == side);
+        StatsRecorder {
+            shared: Arc::new(Shared {
+                enabled: AtomicBool::new(dump),
+                stats: Mutex::new(ProtocolStats::default()),
+                dump_as: if dump { Some(side) } else { None },
+            }),
+        }
+    }
+
+    pub(crate) fn enable(&self) {
+        self.shared.enabled.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn stats(&self) -> Option<ProtocolStats> {
+        if self.is_enabled() {
+            Some(self.shared.stats.lock().unwrap().clone())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        *self.shared.stats.lock().unwrap() = ProtocolStats::default();
+    }
+
+    pub(crate) fn record<Id>(
+        &self,
+        direction: Direction,
+        interface: &'static str,
+        message: &'static str,
+        msg: &Message<Id>,
+    ) {
+        if self.is_enabled() {
+            self.shared.stats.lock().unwrap().record(direction, interface, message, msg);
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Debug for StatsRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatsRecorder").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Some(side) = self.dump_as {
+            eprint!("wayland {} protocol statistics:\n{}", side, self.stats.get_mut().unwrap());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Encoding of signatures in the format of libwayland
+
+use super::{AllowNull, ArgumentType};
+
+/// Codes of the argument types in the signature strings of libwayland
+///
+/// These are the characters of the `signature` field of a `wl_message`: each argument is encoded
+/// by its code, preceded by [`NULLABLE`] if it may be null, and the signature is prefixed by the
+/// version introducing the message if it is greater than 1. They are stable, being part of the ABI
+/// of libwayland.
+pub mod signature_codes {
+    /// Code of an [`ArgumentType::Int`](super::ArgumentType::Int)
+    pub const INT: u8 = b'i';
+    /// Code of an [`ArgumentType::Uint`](super::ArgumentType::Uint)
+    pub const UINT: u8 = b'u';
+    /// Code of an [`ArgumentType::Fixed`](super::ArgumentType::Fixed)
+    pub const FIXED: u8 = b'f';
+    /// Code of an [`ArgumentType::Str`](super::ArgumentType::Str)
+    pub const STRING: u8 = b's';
+    /// Code of an [`ArgumentType::Object`](super::ArgumentType::Object)
+    pub const OBJECT: u8 = b'o';
+    /// Code of an [`ArgumentType::NewId`](super::ArgumentType::NewId)
+    pub const NEW_ID: u8 = b'n';
+    /// Code of an [`ArgumentType::Array`](super::ArgumentType::Array)
+    pub const ARRAY: u8 = b'a';
+    /// Code of an [`ArgumentType::Fd`](super::ArgumentType::Fd)
+    pub const FD: u8 = b'h';
+    /// Prefix of the code of an argument which may be null
+    pub const NULLABLE: u8 = b'?';
+}
+
+use signature_codes::*;
+
+impl ArgumentType {
+    /// The code of this argument type in the signature strings of libwayland
+    ///
+    /// See [`signature_codes`] for the list of codes.
+    pub fn code(self) -> u8 {
+        match self {
+            ArgumentType::Int => INT,
+            ArgumentType::Uint => UINT,
+            ArgumentType::Fixed => FIXED,
+            ArgumentType::Str(_) => STRING,
+            ArgumentType::Object(_) => OBJECT,
+            ArgumentType::NewId(_) => NEW_ID,
+            ArgumentType::Array(_) => ARRAY,
+            ArgumentType::Fd => FD,
+        }
+    }
+
+    /// Whether an argument of this type may be null
+    pub fn is_nullable(self) -> bool {
+        matches!(
+            self,
+            ArgumentType::Str(AllowNull::Yes)
+                | ArgumentType::Object(AllowNull::Yes)
+                | ArgumentType::NewId(AllowNull::Yes)
+                | ArgumentType::Array(AllowNull::Yes)
+        )
+    }
+
+    /// The argument type of a code of the signature strings of libwayland
+    ///
+    /// Returns `None` if the code is unknown, or if `nullable` is set for a type which cannot be
+    /// null.
+    pub fn from_code(code: u8, nullable: bool) -> Option<ArgumentType> {
+        let allow_null = if nullable { AllowNull::Yes } else { AllowNull::No };
+        match code {
+            STRING => Some(ArgumentType::Str(allow_null)),
+            OBJECT => Some(ArgumentType::Object(allow_null)),
+            NEW_ID => Some(ArgumentType::NewId(allow_null)),
+            ARRAY => Some(ArgumentType::Array(allow_null)),
+            _ if nullable => None,
+            INT => Some(ArgumentType::Int),
+            UINT => Some(ArgumentType::Uint),
+            FIXED => Some(ArgumentType::Fixed),
+            FD => Some(ArgumentType::Fd),
+            _ => None,
+        }
+    }
+}
+
+/// Error when decoding a signature string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureDecodeError {
+    /// A character is not the code of an argument type
+    UnknownCode {
+        /// The position of the character in the string
+        position: usize,
+        /// The character
+        code: char,
+    },
+    /// An argument type which cannot be null is marked as nullable
+    NotNullable {
+        /// The position of the code of the argument type in the string
+        position: usize,
+    },
+    /// The signature ends with a nullable marker
+    DanglingNullable,
+    /// The version prefix of the signature does not fit a `u32`
+    InvalidVersion,
+}
+
+impl std::error::Error for SignatureDecodeError {}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for SignatureDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            SignatureDecodeError::UnknownCode { position, code } => {
+                write!(f, "Unknown argument type code {:?} at position {}", code, position)
+            }
+            SignatureDecodeError::NotNullable { position } => {
+                write!(f, "The argument type at position {} cannot be null", position)
+            }
+            SignatureDecodeError::DanglingNullable => {
+                f.write_str("The signature ends with a nullable marker")
+            }
+            SignatureDecodeError::InvalidVersion => f.write_str("Invalid version prefix"),
+        }
+    }
+}
+
+/// Encode a signature in the format of libwayland
+///
+/// `since` is the version introducing the message, which prefixes the signature if it is greater
+/// than 1. The result is the `signature` of the `wl_message` of the message, without its trailing
+/// nul byte.
+pub fn encode_signature(since: u32, signature: &[ArgumentType]) -> String {
+    let mut encoded = if since > 1 { since.to_string() } else { String::new() };
+    for &arg in signature {
+        if arg.is_nullable() {
+            encoded.push(NULLABLE as char);
+        }
+        encoded.push(arg.code() as char);
+    }
+    encoded
+}
+
+/// Decode a signature in the format of libwayland
+///
+/// Returns the version introducing the message, which is 1 if the signature has no version
+/// prefix, and the types of its arguments.
+pub fn decode_signature(encoded: &str) -> Result<(u32, Vec<ArgumentType>), SignatureDecodeError> {
+    let digits = encoded.bytes().take_while(u8::is_ascii_digit).count();
+    let since = match digits {
+        0 => 1,
+        _ => encoded[..digits].parse().map_err(|_| SignatureDecodeError::InvalidVersion)?,
+    };
+
+    let mut signature = Vec::new();
+    let mut nullable = false;
+    for (position, code) in encoded.bytes().enumerate().skip(digits) {
+        if code == NULLABLE && !nullable {
+            nullable = true;
+            continue;
+        }
+        match ArgumentType::from_code(code, nullable) {
+            Some(arg) => signature.push(arg),
+            None if nullable && ArgumentType::from_code(code, false).is_some() => {
+                return Err(SignatureDecodeError::NotNullable { position });
+            }
+            None => {
+                // the signature is ASCII up to this byte
+                let code = encoded[position..].chars().next().unwrap();
+                return Err(SignatureDecodeError::UnknownCode { position, code });
+            }
+        }
+        nullable = false;
+    }
+    if nullable {
+        return Err(SignatureDecodeError::DanglingNullable);
+    }
+    Ok((since, signature))
+}
@@ -0,0 +1,326 @@
+//! Region arithmetic for damage tracking
+//!
+//! This module provides a [`Region`] type, representing an area as a list of non-overlapping
+//! rectangles, with the usual set operations. It is meant to be used for damage tracking, both by
+//! clients (to compute the damage to submit with `wl_surface.damage_buffer`) and by compositors
+//! (to minimize the amount of data uploaded to the GPU).
+//!
+//! The [`DamageHistory`] type accumulates the damage of successive frames, to compute the area
+//! that needs to be repainted into a buffer given its age.
+
+use std::collections::VecDeque;
+
+/// A rectangle
+///
+/// A rectangle with a non-positive width or height is empty. Its right and bottom edges are
+/// clamped to `i32::MAX`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// X coordinate of the top-left corner
+    pub x: i32,
+    /// Y coordinate of the top-left corner
+    pub y: i32,
+    /// Width of the rectangle
+    pub width: i32,
+    /// Height of the rectangle
+    pub height: i32,
+}
+
+impl Rect {
+    /// Create a new rectangle
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    /// Check whether this rectangle is empty
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    /// Area of this rectangle
+    pub fn area(&self) -> u64 {
+        if self.is_empty() {
+            0
+        } else {
+            self.width as u64 * self.height as u64
+        }
+    }
+
+    /// Check whether given point is inside this rectangle
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.right() && y < self.bottom()
+    }
+
+    /// Compute the intersection of two rectangles, if not empty
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let rect = Rect {
+            x,
+            y,
+            width: self.right().min(other.right()).saturating_sub(x),
+            height: self.bottom().min(other.bottom()).saturating_sub(y),
+        };
+        if rect.is_empty() {
+            None
+        } else {
+            Some(rect)
+        }
+    }
+
+    fn right(&self) -> i32 {
+        self.x.saturating_add(self.width)
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y.saturating_add(self.height)
+    }
+
+    // Push the parts of self not covered by other into out
+    fn subtract_into(&self, other: &Rect, out: &mut Vec<Rect>) {
+        let inter = match self.intersection(other) {
+            Some(inter) => inter,
+            None => {
+                out.push(*self);
+                return;
+            }
+        };
+        // band above the intersection
+        if inter.y > self.y {
+            out.push(Rect::new(self.x, self.y, self.width, inter.y - self.y));
+        }
+        // band below the intersection
+        if inter.bottom() < self.bottom() {
+            out.push(Rect::new(self.x, inter.bottom(), self.width, self.bottom() - inter.bottom()));
+        }
+        // left and right of the intersection, on its rows
+        if inter.x > self.x {
+            out.push(Rect::new(self.x, inter.y, inter.x - self.x, inter.height));
+        }
+        if inter.right() < self.right() {
+            out.push(Rect::new(inter.right(), inter.y, self.right() - inter.right(), inter.height));
+        }
+    }
+}
+
+/// A region, represented as a list of non-overlapping rectangles
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Region {
+    rects: Vec<Rect>,
+}
+
+impl Region {
+    /// Create a new empty region
+    pub fn new() -> Region {
+        Region { rects: Vec::new() }
+    }
+
+    /// The rectangles making up this region
+    ///
+    /// They do not overlap and are never empty.
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// Check whether this region is empty
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Total area of this region
+    pub fn area(&self) -> u64 {
+        self.rects.iter().map(Rect::area).sum()
+    }
+
+    /// Check whether given point is inside this region
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.rects.iter().any(|r| r.contains(x, y))
+    }
+
+    /// The smallest rectangle containing this whole region, if not empty
+    pub fn bounding_box(&self) -> Option<Rect> {
+        let first = self.rects.first()?;
+        let (mut x1, mut y1, mut x2, mut y2) = (first.x, first.y, first.right(), first.bottom());
+        for r in &self.rects[1..] {
+            x1 = x1.min(r.x);
+            y1 = y1.min(r.y);
+            x2 = x2.max(r.right());
+            y2 = y2.max(r.bottom());
+        }
+        Some(Rect::new(x1, y1, x2.saturating_sub(x1), y2.saturating_sub(y1)))
+    }
+
+    /// Add a rectangle to this region
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        // only add the parts of rect that are not already in the region
+        let mut pieces = vec![rect];
+        let mut remaining = Vec::new();
+        for existing in &self.rects {
+            for piece in pieces.drain(..) {
+                piece.subtract_into(existing, &mut remaining);
+            }
+            std::mem::swap(&mut pieces, &mut remaining);
+            if pieces.is_empty() {
+                return;
+            }
+        }
+        self.rects.extend(pieces);
+    }
+
+    /// Remove a rectangle from this region
+    pub fn subtract(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        let mut rects = Vec::with_capacity(self.rects.len());
+        for existing in &self.rects {
+            existing.subtract_into(&rect, &mut rects);
+        }
+        self.rects = rects;
+    }
+
+    /// Restrict this region to its intersection with a rectangle
+    pub fn intersect(&mut self, rect: Rect) {
+        self.rects = self.rects.iter().filter_map(|r| r.intersection(&rect)).collect();
+    }
+
+    /// Add an other region to this region
+    pub fn union(&mut self, other: &Region) {
+        for &rect in &other.rects {
+            self.add(rect);
+        }
+    }
+
+    /// Remove an other region from this region
+    pub fn subtract_region(&mut self, other: &Region) {
+        for &rect in &other.rects {
+            self.subtract(rect);
+        }
+    }
+
+    /// Restrict this region to its intersection with an other region
+    pub fn intersect_region(&mut self, other: &Region) {
+        // the rects of both regions don't overlap, so neither do their pairwise intersections
+        self.rects = self
+            .rects
+            .iter()
+            .flat_map(|r| other.rects.iter().filter_map(move |o| r.intersection(o)))
+            .collect();
+    }
+
+    /// Merge adjacent rectangles to reduce the number of rectangles of this region
+    ///
+    /// The area covered by the region does not change.
+    pub fn simplify(&mut self) {
+        let mut merged = true;
+        while merged {
+            merged = false;
+            let mut i = 0;
+            while i < self.rects.len() {
+                let mut j = i + 1;
+                while j < self.rects.len() {
+                    if let Some(rect) = try_merge(&self.rects[i], &self.rects[j]) {
+                        self.rects[i] = rect;
+                        self.rects.swap_remove(j);
+                        merged = true;
+                    } else {
+                        j += 1;
+                    }
+                }
+                i += 1;
+            }
+        }
+    }
+}
+
+impl From<Rect> for Region {
+    fn from(rect: Rect) -> Region {
+        let mut region = Region::new();
+        region.add(rect);
+        region
+    }
+}
+
+impl std::iter::FromIterator<Rect> for Region {
+    fn from_iter<T: IntoIterator<Item = Rect>>(iter: T) -> Region {
+        let mut region = Region::new();
+        for rect in iter {
+            region.add(rect);
+        }
+        region
+    }
+}
+
+// Merge two rectangles if their union is a rectangle
+fn try_merge(a: &Rect, b: &Rect) -> Option<Rect> {
+    if a.x == b.x && a.width == b.width {
+        if a.bottom() == b.y {
+            return Some(Rect::new(a.x, a.y, a.width, a.height.saturating_add(b.height)));
+        } else if b.bottom() == a.y {
+            return Some(Rect::new(a.x, b.y, a.width, a.height.saturating_add(b.height)));
+        }
+    }
+    if a.y == b.y && a.height == b.height {
+        if a.right() == b.x {
+            return Some(Rect::new(a.x, a.y, a.width.saturating_add(b.width), a.height));
+        } else if b.right() == a.x {
+            return Some(Rect::new(b.x, a.y, a.width.saturating_add(b.width), a.height));
+        }
+    }
+    None
+}
+
+/// History of the damage of the last frames
+///
+/// This is used to compute the area that needs to be repainted into a buffer depending on its
+/// age: a buffer of age `n` contains the contents of the frame painted `n` frames ago, and needs
+/// to be repainted with the damage of all frames since.
+#[derive(Clone, Debug)]
+pub struct DamageHistory {
+    frames: VecDeque<Region>,
+    max_age: usize,
+}
+
+impl DamageHistory {
+    /// Create a new damage history, remembering the damage of up to `max_age` frames
+    pub fn new(max_age: usize) -> DamageHistory {
+        DamageHistory { frames: VecDeque::with_capacity(max_age), max_age }
+    }
+
+    /// Record the damage of a new frame
+    pub fn push(&mut self, mut damage: Region) {
+        if self.max_age == 0 {
+            return;
+        }
+        if self.frames.len() == self.max_age {
+            self.frames.pop_back();
+        }
+        damage.simplify();
+        self.frames.push_front(damage);
+    }
+
+    /// Compute the damage to repaint into a buffer of given age to bring it up to date
+    ///
+    /// The damage of the current frame must have been pushed beforehand. Returns `None` if the
+    /// age is unknown (`0`) or older than the recorded history, in which case the whole buffer
+    /// needs to be repainted.
+    pub fn damage_for_age(&self, age: usize) -> Option<Region> {
+        if age == 0 || age > self.frames.len() {
+            return None;
+        }
+        let mut damage = Region::new();
+        for frame in self.frames.iter().take(age) {
+            damage.union(frame);
+        }
+        damage.simplify();
+        Some(damage)
+    }
+
+    /// Forget all recorded damage
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
@@ -0,0 +1,764 @@
+//! C API over the rust client backend
+//!
+//! With the `capi` cargo feature, this crate exports a subset of the C API of `libwayland-client`,
+//! implemented over the rust backend, so that components written in other languages can use a
+//! Wayland connection on systems without `libwayland`:
+//!
+//! - `wl_display_connect()`, `wl_display_connect_to_fd()`, `wl_display_disconnect()`,
+//!   `wl_display_get_fd()` and `wl_display_get_error()`;
+//! - `wl_display_dispatch()`, `wl_display_dispatch_pending()`, `wl_display_roundtrip()` and
+//!   `wl_display_flush()`;
+//! - the `wl_proxy_marshal_array*()` family, `wl_proxy_add_dispatcher()`, `wl_proxy_destroy()` and
+//!   the accessors of the proxies.
+//!
+//! The interfaces of the protocols are those given by the C code, as generated by
+//! `wayland-scanner public-code`. Only the functions taking the arguments of the messages as an
+//! array of `wl_argument` are provided: the variadic `wl_proxy_marshal*()` functions and the
+//! listeners of `wl_proxy_add_listener()`, which `libwayland` invokes through `libffi`, need the
+//! calling convention of the platform for each signature, and are left out. The events of a proxy
+//! are delivered to the dispatcher set with `wl_proxy_add_dispatcher()`, as an array as well.
+//!
+//! Event queues are not supported: all the events are dispatched by the dispatching functions of
+//! the display, which, like all the functions of a display and its proxies, must be called from a
+//! single thread.
+//!
+//! The destructor events are not known from the C descriptions of the interfaces, the proxies
+//! receiving them must thus be destroyed by their dispatcher, as is done for `wl_callback`, unless
+//! their interface was registered from rust with
+//! [`register_interfaces()`](crate::protocol::register_interfaces).
+
+#![allow(non_camel_case_types, clippy::missing_safety_doc)]
+
+#[cfg(all(feature = "client_system", not(feature = "dlopen")))]
+compile_error!("The `capi` feature exports the symbols of libwayland-client, and can only be used with the `client_system` feature if `dlopen` is enabled as well.");
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ffi::{CStr, OsStr},
+    os::{
+        raw::{c_char, c_int, c_void},
+        unix::{
+            ffi::OsStrExt,
+            io::{FromRawFd, IntoRawFd, RawFd},
+            net::UnixStream,
+        },
+    },
+    path::PathBuf,
+    ptr,
+    sync::Mutex,
+};
+
+use nix::errno::Errno;
+use smallvec::SmallVec;
+use wayland_sys::common::{wl_argument, wl_array, wl_dispatcher_func_t, wl_interface, wl_message};
+
+use crate::{
+    core_interfaces::WL_CALLBACK_INTERFACE,
+    protocol::{
+        decode_signature, lookup_interface, Argument, ArgumentType, Interface, Message,
+        MessageDesc, ANONYMOUS_INTERFACE, INLINE_ARGS,
+    },
+};
+
+use super::{
+    client::{Backend, BackendConfig, Dispatcher, Handle, ObjectId, Operation, WaylandError},
+    socket::Socket,
+};
+
+/// `wl_proxy_marshal_array_flags()` flag destroying the proxy after sending the request
+pub const WL_MARSHAL_FLAG_DESTROY: u32 = 1 << 0;
+
+/// Opaque type of the proxies of the C API
+#[repr(C)]
+#[derive(Debug)]
+pub struct wl_proxy {
+    _private: [u8; 0],
+}
+
+/// Opaque type of the displays of the C API
+#[repr(C)]
+#[derive(Debug)]
+pub struct wl_display {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct Proxy {
+    display: *mut Display,
+    id: ObjectId,
+    // the C description of the interface, null for the display and internal objects
+    interface: *const wl_interface,
+    version: u32,
+    dispatcher: Option<wl_dispatcher_func_t>,
+    implementation: *const c_void,
+    user_data: *mut c_void,
+}
+
+// the display is a proxy as well, and can be cast to it
+#[repr(C)]
+struct Display {
+    proxy: Proxy,
+    backend: Backend<CDispatch>,
+    fd: RawFd,
+    // the handle of the backend while it is dispatching events
+    dispatching: Cell<*mut Handle<CDispatch>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ProxyPtr(*mut Proxy);
+
+struct CDispatch {
+    display: *mut Display,
+}
+
+impl Dispatcher for CDispatch {
+    type ObjectData = ProxyPtr;
+
+    fn placeholder_data() -> ProxyPtr {
+        ProxyPtr(ptr::null_mut())
+    }
+
+    fn event(
+        &mut self,
+        handle: &mut Handle<Self>,
+        data: &ProxyPtr,
+        msg: Message<ObjectId>,
+    ) -> Option<ProxyPtr> {
+        // the dispatcher of the proxy may send requests or destroy proxies, through this pointer
+        let handle: *mut Handle<Self> = handle;
+        unsafe {
+            let dispatching = &(*self.display).dispatching;
+            let previous = dispatching.replace(handle);
+            let ret = dispatch_event(handle, data.0, msg);
+            dispatching.set(previous);
+            ret
+        }
+    }
+
+    // the proxies are freed by `wl_proxy_destroy()`
+    fn destroyed(_: &ProxyPtr, _: ObjectId) {}
+}
+
+unsafe fn dispatch_event(
+    handle: *mut Handle<CDispatch>,
+    proxy: *mut Proxy,
+    msg: Message<ObjectId>,
+) -> Option<ProxyPtr> {
+    // internal objects and destroyed proxies have no dispatcher
+    let dispatcher = match proxy.is_null() {
+        true => None,
+        false => (*proxy).dispatcher,
+    };
+    let dispatcher = match dispatcher {
+        Some(dispatcher) => dispatcher,
+        None => {
+            // the file descriptors are given to the dispatcher, they need to be closed otherwise
+            close_fds(&msg.args);
+            return None;
+        }
+    };
+    let message = (*(*proxy).interface).events.add(msg.opcode as usize);
+
+    // the arrays are boxed, for their address to be stable until the dispatcher returns
+    let mut arrays = SmallVec::<[Box<wl_array>; 1]>::new();
+    let mut args = SmallVec::<[wl_argument; INLINE_ARGS]>::with_capacity(msg.args.len());
+    let mut child = None;
+    {
+        let handle = &mut *handle;
+        for (i, arg) in msg.args.iter().enumerate() {
+            args.push(match *arg {
+                Argument::Int(i) => wl_argument { i },
+                Argument::Uint(u) => wl_argument { u },
+                Argument::Fixed(f) => wl_argument { f },
+                Argument::Str(ref s) => wl_argument { s: s.as_ptr() },
+                Argument::Object(ref id) if id.is_null() => wl_argument { o: ptr::null() },
+                Argument::Object(ref id) => wl_argument {
+                    o: handle.get_data(id.clone()).map_or(ptr::null_mut(), |data| data.0)
+                        as *const c_void,
+                },
+                Argument::NewId(ref id) => {
+                    let interface = *(*message).types.add(i);
+                    let new_proxy =
+                        Proxy::new((*proxy).display, id.clone(), interface, (*proxy).version);
+                    child = Some(ProxyPtr(new_proxy));
+                    wl_argument { o: new_proxy as *const c_void }
+                }
+                Argument::Array(ref value) => {
+                    let array = Box::new(wl_array {
+                        size: value.len(),
+                        alloc: value.capacity(),
+                        data: value.as_ptr() as *mut c_void,
+                    });
+                    let a: *const wl_array = &*array;
+                    arrays.push(array);
+                    wl_argument { a }
+                }
+                Argument::Fd(h) => wl_argument { h },
+            });
+        }
+    }
+
+    dispatcher(
+        (*proxy).implementation,
+        proxy as *mut c_void,
+        msg.opcode as u32,
+        message,
+        args.as_ptr(),
+    );
+    child
+}
+
+fn close_fds(args: &[Argument<ObjectId>]) {
+    for arg in args {
+        if let Argument::Fd(fd) = *arg {
+            let _ = nix::unistd::close(fd);
+        }
+    }
+}
+
+impl Proxy {
+    fn new(
+        display: *mut Display,
+        id: ObjectId,
+        interface: *const wl_interface,
+        version: u32,
+    ) -> *mut Proxy {
+        Box::into_raw(Box::new(Proxy {
+            display,
+            id,
+            interface,
+            version,
+            dispatcher: None,
+            implementation: ptr::null(),
+            user_data: ptr::null_mut(),
+        }))
+    }
+}
+
+// access the handle of the backend of a display, which may be dispatching events
+unsafe fn with_handle<T>(display: *mut Display, f: impl FnOnce(&mut Handle<CDispatch>) -> T) -> T {
+    let dispatching = (*display).dispatching.get();
+    if dispatching.is_null() {
+        f((*ptr::addr_of_mut!((*display).backend)).handle())
+    } else {
+        f(&mut *dispatching)
+    }
+}
+
+fn set_errno(errno: Errno) {
+    #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+    use nix::libc::__errno as errno_location;
+    #[cfg(any(target_os = "linux", target_os = "dragonfly"))]
+    use nix::libc::__errno_location as errno_location;
+    #[cfg(any(target_os = "freebsd", target_os = "macos", target_os = "ios"))]
+    use nix::libc::__error as errno_location;
+
+    // errno is thread-local
+    unsafe { *errno_location() = errno as c_int };
+}
+
+fn error_errno(error: &WaylandError) -> Errno {
+    match *error {
+        WaylandError::Io { ref error, .. } => {
+            error.raw_os_error().map(Errno::from_i32).unwrap_or(Errno::EIO)
+        }
+        WaylandError::Protocol(_) => Errno::EPROTO,
+        WaylandError::NestedDispatch => Errno::EDEADLK,
+    }
+}
+
+/*
+ * Conversion of the C descriptions of the interfaces
+ */
+
+lazy_static::lazy_static! {
+    // the rust descriptions of the C interfaces, by address
+    static ref INTERFACES: Mutex<HashMap<usize, &'static Interface>> = Mutex::new(HashMap::new());
+}
+
+// the rust description of a C interface, which must live for the whole program
+unsafe fn rust_interface(interface: *const wl_interface) -> &'static Interface {
+    convert_interface(&mut INTERFACES.lock().unwrap(), interface)
+}
+
+unsafe fn convert_interface(
+    known: &mut HashMap<usize, &'static Interface>,
+    c_interface: *const wl_interface,
+) -> &'static Interface {
+    if let Some(interface) = known.get(&(c_interface as usize)) {
+        return interface;
+    }
+    let c_interface = &*c_interface;
+    let name = leak_str(c_interface.name);
+    let version = c_interface.version as u32;
+    // the messages of the interface may refer to it, they are given a description with only its
+    // name while they are converted, which is enough for the creation of objects by requests
+    let stub = Interface { name, version, requests: &[], events: &[], c_ptr: Some(c_interface) };
+    known.insert(c_interface as *const _ as usize, Box::leak(Box::new(stub)));
+    let requests = convert_messages(known, c_interface.requests, c_interface.request_count);
+    let events = convert_messages(known, c_interface.events, c_interface.event_count);
+    let converted = Interface { name, version, requests, events, c_ptr: Some(c_interface) };
+    // an interface registered from rust also knows its destructor events
+    let interface = match lookup_interface(name) {
+        Some(registered) if registered.is_compatible_with(&converted) => registered,
+        _ => Box::leak(Box::new(converted)),
+    };
+    known.insert(c_interface as *const _ as usize, interface);
+    interface
+}
+
+unsafe fn convert_messages(
+    known: &mut HashMap<usize, &'static Interface>,
+    messages: *const wl_message,
+    count: c_int,
+) -> &'static [MessageDesc] {
+    let mut descs = Vec::with_capacity(count as usize);
+    for i in 0..count as usize {
+        let message = &*messages.add(i);
+        let name = leak_str(message.name);
+        let signature = CStr::from_ptr(message.signature).to_string_lossy();
+        let (since, signature) = decode_signature(&signature)
+            .unwrap_or_else(|e| panic!("Invalid signature for message {}: {}", name, e));
+        let mut child_interface = None;
+        let mut arg_interfaces = Vec::new();
+        for (j, arg) in signature.iter().enumerate() {
+            match *arg {
+                ArgumentType::Object(_) => arg_interfaces.push(&ANONYMOUS_INTERFACE),
+                ArgumentType::NewId(_) => {
+                    let interface = *message.types.add(j);
+                    if !interface.is_null() {
+                        child_interface = Some(convert_interface(known, interface));
+                    }
+                }
+                _ => {}
+            }
+        }
+        descs.push(MessageDesc {
+            name,
+            signature: Box::leak(signature.into_boxed_slice()),
+            arg_names: &[],
+            since,
+            is_destructor: false,
+            child_interface,
+            arg_interfaces: Box::leak(arg_interfaces.into_boxed_slice()),
+            arg_enums: &[],
+        });
+    }
+    Box::leak(descs.into_boxed_slice())
+}
+
+unsafe fn leak_str(s: *const c_char) -> &'static str {
+    Box::leak(CStr::from_ptr(s).to_string_lossy().into_owned().into_boxed_str())
+}
+
+/*
+ * Display
+ */
+
+/// Connect to the Wayland display named `name`, or following the environment if it is null
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_connect(name: *const c_char) -> *mut wl_display {
+    if name.is_null() {
+        if let Some(fd) = std::env::var("WAYLAND_SOCKET").ok().and_then(|fd| fd.parse().ok()) {
+            std::env::remove_var("WAYLAND_SOCKET");
+            return wl_display_connect_to_fd(fd);
+        }
+    }
+    let name = match name.is_null() {
+        true => std::env::var_os("WAYLAND_DISPLAY").unwrap_or_else(|| "wayland-0".into()),
+        false => OsStr::from_bytes(CStr::from_ptr(name).to_bytes()).into(),
+    };
+    let mut path = PathBuf::from(name);
+    if path.is_relative() {
+        match std::env::var_os("XDG_RUNTIME_DIR") {
+            Some(runtime_dir) => path = PathBuf::from(runtime_dir).join(path),
+            None => {
+                set_errno(Errno::ENOENT);
+                return ptr::null_mut();
+            }
+        }
+    }
+    match UnixStream::connect(path) {
+        Ok(stream) => wl_display_connect_to_fd(stream.into_raw_fd()),
+        Err(e) => {
+            set_errno(e.raw_os_error().map(Errno::from_i32).unwrap_or(Errno::EIO));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Connect to a Wayland display over an already connected socket
+///
+/// The display takes ownership of the socket.
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_connect_to_fd(fd: c_int) -> *mut wl_display {
+    let socket = Socket::from_raw_fd(fd);
+    let mut backend = match Backend::connect_static(socket, BackendConfig::default()) {
+        Ok(backend) => backend,
+        Err(_) => {
+            set_errno(Errno::EIO);
+            return ptr::null_mut();
+        }
+    };
+    let id = backend.handle().display_id();
+    let fd = backend.connection_fd();
+    let display = Box::into_raw(Box::new(Display {
+        proxy: Proxy {
+            display: ptr::null_mut(),
+            id: id.clone(),
+            interface: ptr::null(),
+            version: 1,
+            dispatcher: None,
+            implementation: ptr::null(),
+            user_data: ptr::null_mut(),
+        },
+        backend,
+        fd,
+        dispatching: Cell::new(ptr::null_mut()),
+    }));
+    (*display).proxy.display = display;
+    let _ = (*display).backend.handle().set_data(id, ProxyPtr(display as *mut Proxy));
+    display as *mut wl_display
+}
+
+/// Close the connection and free the display
+///
+/// This must not be called while the display is dispatching events. The proxies which were not
+/// destroyed are leaked.
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_disconnect(display: *mut wl_display) {
+    drop(Box::from_raw(display as *mut Display));
+}
+
+/// The file descriptor of the connection
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_get_fd(display: *mut wl_display) -> c_int {
+    (*(display as *mut Display)).fd
+}
+
+/// The error which made the connection fail, as an `errno` value, or 0
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_get_error(display: *mut wl_display) -> c_int {
+    with_handle(display as *mut Display, |handle| match handle.last_error() {
+        Some(error) => error_errno(&error) as c_int,
+        None => 0,
+    })
+}
+
+/// Send the pending requests to the server
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_flush(display: *mut wl_display) -> c_int {
+    match with_handle(display as *mut Display, |handle| handle.flush()) {
+        Ok(()) => 0,
+        Err(error) => {
+            set_errno(error_errno(&error));
+            -1
+        }
+    }
+}
+
+unsafe fn dispatch(display: *mut Display, blocking: bool) -> Result<usize, WaylandError> {
+    if !(*display).dispatching.get().is_null() {
+        return Err(WaylandError::NestedDispatch);
+    }
+    let backend = &mut *ptr::addr_of_mut!((*display).backend);
+    match backend.flush() {
+        Err(error) if !error.is_would_block() => return Err(error),
+        _ => {}
+    }
+    loop {
+        match backend.dispatch_events_to(&mut CDispatch { display }) {
+            Ok(0) => {}
+            Ok(dispatched) => return Ok(dispatched),
+            Err(error) if error.is_would_block() => {}
+            Err(error) => return Err(error),
+        }
+        if !blocking {
+            return Ok(0);
+        }
+        let mut fds = [nix::poll::PollFd::new((*display).fd, nix::poll::PollFlags::POLLIN)];
+        match nix::poll::poll(&mut fds, -1) {
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(errno) => {
+                return Err(WaylandError::io(
+                    Operation::Read,
+                    std::io::Error::from_raw_os_error(errno as i32),
+                ))
+            }
+        }
+    }
+}
+
+fn dispatch_result(result: Result<usize, WaylandError>) -> c_int {
+    match result {
+        Ok(dispatched) => dispatched as c_int,
+        Err(error) => {
+            set_errno(error_errno(&error));
+            -1
+        }
+    }
+}
+
+/// Dispatch the events received by the display, reading them from the socket
+///
+/// This blocks until at least one event is dispatched.
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_dispatch(display: *mut wl_display) -> c_int {
+    dispatch_result(dispatch(display as *mut Display, true))
+}
+
+/// Dispatch the events available on the socket, without blocking
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_dispatch_pending(display: *mut wl_display) -> c_int {
+    dispatch_result(dispatch(display as *mut Display, false))
+}
+
+/// Dispatch events until the server has processed all the previous requests
+#[no_mangle]
+pub unsafe extern "C" fn wl_display_roundtrip(display: *mut wl_display) -> c_int {
+    let display = display as *mut Display;
+    let callback = with_handle(display, |handle| {
+        let placeholder = handle.placeholder_id(Some(&WL_CALLBACK_INTERFACE));
+        let msg = crate::message!(handle.display_id(), 0, [Argument::NewId(placeholder)]);
+        // the callback is an internal object, destroyed by its `done` event
+        handle.send_request(msg, Some(ProxyPtr(ptr::null_mut())))
+    });
+    let callback = match callback {
+        Ok(callback) => callback,
+        Err(_) => {
+            set_errno(Errno::EPROTO);
+            return -1;
+        }
+    };
+    let mut dispatched = 0;
+    while with_handle(display, |handle| handle.info(callback.clone()).is_ok()) {
+        match dispatch(display, true) {
+            Ok(count) => dispatched += count,
+            Err(error) => return dispatch_result(Err(error)),
+        }
+    }
+    dispatched as c_int
+}
+
+/*
+ * Proxies
+ */
+
+/// Send a request, creating a proxy of `interface` if it creates an object
+///
+/// The arguments of the request are read from `args`, following its signature. The version of the
+/// created object is the one deduced from the protocol, `version` is ignored. With the
+/// `WL_MARSHAL_FLAG_DESTROY` flag, the proxy is destroyed once the request is sent.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_marshal_array_flags(
+    proxy: *mut wl_proxy,
+    opcode: u32,
+    interface: *const wl_interface,
+    _version: u32,
+    flags: u32,
+    args: *mut wl_argument,
+) -> *mut wl_proxy {
+    let proxy = proxy as *mut Proxy;
+    let display = (*proxy).display;
+    let child = with_handle(display, |handle| marshal(handle, proxy, opcode, interface, args));
+    if flags & WL_MARSHAL_FLAG_DESTROY != 0 {
+        wl_proxy_destroy(proxy as *mut wl_proxy);
+    }
+    child as *mut wl_proxy
+}
+
+unsafe fn marshal(
+    handle: &mut Handle<CDispatch>,
+    proxy: *mut Proxy,
+    opcode: u32,
+    interface: *const wl_interface,
+    args: *mut wl_argument,
+) -> *mut Proxy {
+    let desc = match handle.info((*proxy).id.clone()) {
+        Ok(info) => match info.interface.requests.get(opcode as usize) {
+            Some(desc) => desc,
+            None => panic!("Unknown opcode {} for object {}.", opcode, (*proxy).id),
+        },
+        // requests on destroyed objects are ignored
+        Err(_) => return ptr::null_mut(),
+    };
+    let child_interface = if interface.is_null() { None } else { Some(rust_interface(interface)) };
+
+    let mut msg_args = SmallVec::with_capacity(desc.signature.len());
+    let mut child = ptr::null_mut();
+    for (i, arg_type) in desc.signature.iter().enumerate() {
+        let arg = &*args.add(i);
+        msg_args.push(match *arg_type {
+            ArgumentType::Int => Argument::Int(arg.i),
+            ArgumentType::Uint => Argument::Uint(arg.u),
+            ArgumentType::Fixed => Argument::Fixed(arg.f),
+            ArgumentType::Str(_) if arg.s.is_null() => Argument::Str(Box::default()),
+            ArgumentType::Str(_) => Argument::Str(Box::new(CStr::from_ptr(arg.s).into())),
+            ArgumentType::Object(_) if arg.o.is_null() => Argument::Object(handle.null_id()),
+            ArgumentType::Object(_) => Argument::Object((*(arg.o as *mut Proxy)).id.clone()),
+            ArgumentType::NewId(_) => {
+                child = Proxy::new((*proxy).display, handle.null_id(), interface, 0);
+                Argument::NewId(handle.placeholder_id(child_interface))
+            }
+            ArgumentType::Array(_) if arg.a.is_null() => Argument::Array(Box::default()),
+            ArgumentType::Array(_) => {
+                let array = &*arg.a;
+                let contents = match array.size {
+                    0 => Vec::new(),
+                    size => std::slice::from_raw_parts(array.data as *const u8, size).to_vec(),
+                };
+                Argument::Array(Box::new(contents))
+            }
+            ArgumentType::Fd => Argument::Fd(arg.h),
+        });
+    }
+
+    let msg = Message { sender_id: (*proxy).id.clone(), opcode: opcode as u16, args: msg_args };
+    let data = if child.is_null() { None } else { Some(ProxyPtr(child)) };
+    match handle.send_request(msg, data) {
+        Ok(id) if !child.is_null() => {
+            (*child).version = handle.info(id.clone()).map(|info| info.version).unwrap_or(0);
+            (*child).id = id;
+            child
+        }
+        Ok(_) => ptr::null_mut(),
+        Err(_) => {
+            if !child.is_null() {
+                drop(Box::from_raw(child));
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Send a request which does not create an object
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_marshal_array(
+    proxy: *mut wl_proxy,
+    opcode: u32,
+    args: *mut wl_argument,
+) {
+    wl_proxy_marshal_array_flags(proxy, opcode, ptr::null(), 0, 0, args);
+}
+
+/// Send a request creating a proxy of `interface`
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_marshal_array_constructor(
+    proxy: *mut wl_proxy,
+    opcode: u32,
+    args: *mut wl_argument,
+    interface: *const wl_interface,
+) -> *mut wl_proxy {
+    wl_proxy_marshal_array_flags(proxy, opcode, interface, 0, 0, args)
+}
+
+/// Send a request creating a proxy of `interface`
+///
+/// The version of the created object is the one deduced from the protocol, `version` is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_marshal_array_constructor_versioned(
+    proxy: *mut wl_proxy,
+    opcode: u32,
+    args: *mut wl_argument,
+    interface: *const wl_interface,
+    version: u32,
+) -> *mut wl_proxy {
+    wl_proxy_marshal_array_flags(proxy, opcode, interface, version, 0, args)
+}
+
+/// Set the dispatcher of a proxy, invoked for each of its events
+///
+/// The dispatcher is given `implementation`, the proxy, the opcode and C description of the event,
+/// and its arguments as an array of `wl_argument`. The objects created by the event are given as
+/// new proxies, and the file descriptors are owned by the dispatcher. Fails if the proxy already
+/// has a dispatcher.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_add_dispatcher(
+    proxy: *mut wl_proxy,
+    dispatcher: Option<wl_dispatcher_func_t>,
+    implementation: *const c_void,
+    data: *mut c_void,
+) -> c_int {
+    let proxy = proxy as *mut Proxy;
+    if (*proxy).interface.is_null() {
+        log::error!("The events of {} are handled internally.", (*proxy).id);
+        return -1;
+    }
+    if (*proxy).dispatcher.is_some() {
+        log::error!("Proxy {} already has a dispatcher.", (*proxy).id);
+        return -1;
+    }
+    (*proxy).dispatcher = dispatcher;
+    (*proxy).implementation = implementation;
+    (*proxy).user_data = data;
+    0
+}
+
+/// The implementation given with the dispatcher of a proxy
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_get_listener(proxy: *mut wl_proxy) -> *const c_void {
+    (*(proxy as *mut Proxy)).implementation
+}
+
+/// Destroy a proxy
+///
+/// Its destructor request, if any, must have been sent beforehand. The events received for it
+/// are then ignored.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_destroy(proxy: *mut wl_proxy) {
+    let proxy = proxy as *mut Proxy;
+    let display = (*proxy).display;
+    if proxy == display as *mut Proxy {
+        log::error!("The display cannot be destroyed as a proxy, use wl_display_disconnect().");
+        return;
+    }
+    let id = (*proxy).id.clone();
+    with_handle(display, |handle| {
+        // the events referring to the object now give a null proxy
+        if handle.set_data(id.clone(), ProxyPtr(ptr::null_mut())).is_ok() {
+            let _ = handle.release_object(id);
+        }
+    });
+    drop(Box::from_raw(proxy));
+}
+
+/// Set the user data of a proxy
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_set_user_data(proxy: *mut wl_proxy, user_data: *mut c_void) {
+    (*(proxy as *mut Proxy)).user_data = user_data;
+}
+
+/// The user data of a proxy
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_get_user_data(proxy: *mut wl_proxy) -> *mut c_void {
+    (*(proxy as *mut Proxy)).user_data
+}
+
+/// The protocol id of a proxy
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_get_id(proxy: *mut wl_proxy) -> u32 {
+    (*(proxy as *mut Proxy)).id.protocol_id()
+}
+
+/// The version of a proxy
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_get_version(proxy: *mut wl_proxy) -> u32 {
+    (*(proxy as *mut Proxy)).version
+}
+
+/// The C description of the interface of a proxy, null for the display
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_get_interface(proxy: *mut wl_proxy) -> *const wl_interface {
+    (*(proxy as *mut Proxy)).interface
+}
+
+/// The name of the interface of a proxy
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_get_class(proxy: *mut wl_proxy) -> *const c_char {
+    let proxy = proxy as *mut Proxy;
+    match (*proxy).interface {
+        interface if interface.is_null() => b"wl_display\0".as_ptr() as *const c_char,
+        interface => (*interface).name,
+    }
+}
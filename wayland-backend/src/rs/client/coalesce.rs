@@ -0,0 +1,126 @@
+//! Coalescing of bursts of high-frequency messages
+
+use crate::protocol::{Argument, Interface, Message, MessageDesc};
+
+/// Rules merging bursts of equivalent high-frequency messages
+///
+/// Applications which only care about the final state, like the last position of the pointer,
+/// can avoid handling each of the messages of a burst:
+///
+/// - for the *latest events*, only the last of the events of an object already received when the
+///   first one is dispatched is dispatched. The separating events of the object (like the
+///   `wl_pointer.frame` event following each `wl_pointer.motion` event) are dropped along;
+/// - the *merged rectangles* are requests with `x, y, width, height` arguments (like
+///   `wl_surface.damage`): consecutive requests of an object are merged into their bounding box,
+///   sent when an other request is sent or when the connection is flushed.
+///
+/// Coalescing is disabled by default, and enabled with
+/// [`Handle::set_coalescing()`](super::Handle::set_coalescing).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coalescing {
+    latest_events: Vec<LatestEvent>,
+    merged_rects: Vec<(&'static str, &'static str)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LatestEvent {
+    interface: &'static str,
+    event: &'static str,
+    separator: Option<&'static str>,
+}
+
+impl Coalescing {
+    /// Rules coalescing nothing
+    pub fn new() -> Coalescing {
+        Coalescing::default()
+    }
+
+    /// Rules coalescing the `wl_pointer.motion` events, and the `wl_surface.damage` and
+    /// `wl_surface.damage_buffer` requests
+    pub fn pointer_and_damage() -> Coalescing {
+        Coalescing::new()
+            .latest_event("wl_pointer", "motion", Some("frame"))
+            .merged_rect("wl_surface", "damage")
+            .merged_rect("wl_surface", "damage_buffer")
+    }
+
+    /// Only dispatch the latest of a burst of events
+    ///
+    /// The `separator` event of the object, if any, is dropped when it is between two events of
+    /// the burst.
+    pub fn latest_event(
+        mut self,
+        interface: &'static str,
+        event: &'static str,
+        separator: Option<&'static str>,
+    ) -> Self {
+        self.latest_events.push(LatestEvent { interface, event, separator });
+        self
+    }
+
+    /// Merge a burst of requests with `x, y, width, height` arguments into their bounding box
+    pub fn merged_rect(mut self, interface: &'static str, request: &'static str) -> Self {
+        self.merged_rects.push((interface, request));
+        self
+    }
+
+    /// Whether these rules coalesce nothing
+    pub fn is_empty(&self) -> bool {
+        self.latest_events.is_empty() && self.merged_rects.is_empty()
+    }
+
+    // The opcode of the separator of an event, if it is coalesced
+    pub(super) fn latest_event_separator(
+        &self,
+        interface: &'static Interface,
+        desc: &MessageDesc,
+    ) -> Option<Option<u16>> {
+        // events creating objects cannot be dropped
+        if desc.child_interface.is_some() {
+            return None;
+        }
+        let rule = self
+            .latest_events
+            .iter()
+            .find(|rule| rule.interface == interface.name && rule.event == desc.name)?;
+        Some(rule.separator.and_then(|separator| {
+            interface.events.iter().position(|desc| desc.name == separator).map(|o| o as u16)
+        }))
+    }
+
+    pub(super) fn merges_rect(&self, interface: &'static Interface, desc: &MessageDesc) -> bool {
+        self.merged_rects.iter().any(|&(i, r)| i == interface.name && r == desc.name)
+    }
+}
+
+// Merge a request into a pending one of the same object, if both are rectangles
+pub(super) fn merge_rects(pending: &mut Message<u32>, msg: &Message<u32>) -> bool {
+    if pending.sender_id != msg.sender_id || pending.opcode != msg.opcode {
+        return false;
+    }
+    match (rect(pending), rect(msg)) {
+        (Some(a), Some(b)) => {
+            let x = a.0.min(b.0);
+            let y = a.1.min(b.1);
+            let right = a.2.max(b.2);
+            let bottom = a.3.max(b.3);
+            pending.args[0] = Argument::Int(x as i32);
+            pending.args[1] = Argument::Int(y as i32);
+            pending.args[2] = Argument::Int((right - x).min(i32::MAX as i64) as i32);
+            pending.args[3] = Argument::Int((bottom - y).min(i32::MAX as i64) as i32);
+            true
+        }
+        _ => false,
+    }
+}
+
+// The edges of a rectangle, computed without overflowing for the "infinite" damage
+fn rect(msg: &Message<u32>) -> Option<(i64, i64, i64, i64)> {
+    match msg.args[..] {
+        [Argument::Int(x), Argument::Int(y), Argument::Int(w), Argument::Int(h)] => {
+            let (x, y) = (x as i64, y as i64);
+            Some((x, y, x + w as i64, y + h as i64))
+        }
+        _ => None,
+    }
+}
@@ -2,31 +2,39 @@
 
 use std::{
     fmt,
+    ops::DerefMut,
     os::unix::{
         io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
         net::UnixStream,
     },
     sync::{Arc, Condvar, Mutex},
+    time::Instant,
 };
 
 use crate::{
-    core_interfaces::WL_DISPLAY_INTERFACE,
+    core_interfaces::{WL_DISPLAY_INTERFACE, WL_REGISTRY_INTERFACE},
     protocol::{
-        check_for_signature, same_interface, same_interface_or_anonymous, AllowNull, Argument,
-        ArgumentType, Interface, Message, ObjectInfo, ProtocolError, ANONYMOUS_INTERFACE,
-        INLINE_ARGS,
+        check_arguments, child_spec, compare_signature, format_message, same_interface,
+        same_interface_or_anonymous, AllowNull, Argument, ArgumentType, ConformanceHook,
+        ConformanceViolation, Direction, Interface, Message, MessageDirection, ObjectInfo,
+        ProtocolError, ProtocolStats, StatsRecorder, ANONYMOUS_INTERFACE, INLINE_ARGS,
     },
+    types::client::GlobalCache,
 };
 use smallvec::SmallVec;
 
 use super::{
-    debug::DisplaySlice,
-    map::{Object, ObjectMap, SERVER_ID_LIMIT},
-    socket::{BufferedSocket, Socket},
+    map::{DestructionHooks, Object, ObjectMap, SERVER_ID_LIMIT},
+    socket::{BufferedSocket, Socket, WaylandTransport, MAX_BYTES_OUT, MAX_FDS_OUT},
     wire::MessageParseError,
 };
 
-pub use crate::types::client::{InvalidId, NoWaylandLib, WaylandError};
+pub use super::MemoryUsage;
+pub use crate::types::client::{GlobalInfo, InvalidId, NoWaylandLib, Operation, WaylandError};
+
+mod coalesce;
+
+pub use coalesce::Coalescing;
 
 /// A trait representing your data associated to an object
 ///
@@ -40,6 +48,10 @@ pub trait ObjectData: downcast_rs::DowncastSync {
     ///
     /// If the event has a NewId argument, the callback must return the object data
     /// for the newly created object
+    ///
+    /// The handle can be freely used from this callback, including to send a destructor
+    /// request for the object being dispatched or the newly created one: their object data
+    /// is then notified of their destruction only once.
     fn event(
         self: Arc<Self>,
         handle: &mut Handle,
@@ -47,6 +59,14 @@ pub trait ObjectData: downcast_rs::DowncastSync {
     ) -> Option<Arc<dyn ObjectData>>;
     /// Notification that the object has been destroyed and is no longer active
     fn destroyed(&self, object_id: ObjectId);
+    /// Notification that the object has been destroyed, with access to the connection
+    ///
+    /// This is the notification actually given by the backend, it allows the cleanup of the
+    /// object to send requests, for example to destroy the objects depending on it. The default
+    /// implementation forwards to [`destroyed()`](ObjectData::destroyed).
+    fn destroyed_with_handle(&self, _handle: &mut Handle, object_id: ObjectId) {
+        self.destroyed(object_id)
+    }
     /// Helper for forwarding a Debug implementation of your `ObjectData` type
     ///
     /// By default will just print `ObjectData { ... }`
@@ -64,12 +84,125 @@ impl std::fmt::Debug for dyn ObjectData {
 
 downcast_rs::impl_downcast!(sync ObjectData);
 
+/// The dispatching of the events of a backend to the data of their objects
+///
+/// By default, the backend uses [`DynDispatch`], and each object has its own
+/// `Arc<dyn ObjectData>` handling its events. An application routing all the events through a
+/// single state machine anyway can instead implement this trait for this state machine, and use a
+/// `Backend<D>`: the data of the objects is then stored in the backend as a
+/// [`Dispatcher::ObjectData`], typically an enum of the kinds of objects of the application, and
+/// the events are given to the state machine by [`Backend::dispatch_events_to()`], without any
+/// allocation or dynamic dispatch per object.
+///
+/// This is only available on the rust backend.
+pub trait Dispatcher: Sized {
+    /// The data associated with each object
+    type ObjectData: Clone + fmt::Debug;
+
+    /// The data of the objects created by the server, until the event creating them is dispatched
+    ///
+    /// This is also the data of the `wl_display`.
+    fn placeholder_data() -> Self::ObjectData;
+
+    /// Dispatch an event for an object
+    ///
+    /// If the event has a NewId argument, the callback must return the object data for the newly
+    /// created object.
+    fn event(
+        &mut self,
+        handle: &mut Handle<Self>,
+        data: &Self::ObjectData,
+        msg: Message<ObjectId>,
+    ) -> Option<Self::ObjectData>;
+
+    /// Notification that an object has been destroyed and is no longer active
+    ///
+    /// As objects are also destroyed when sending destructor requests, this notification is not
+    /// given the dispatcher.
+    fn destroyed(data: &Self::ObjectData, object_id: ObjectId);
+
+    /// Notification that an object has been destroyed, with access to the connection
+    ///
+    /// This is the notification actually given by the backend, it allows the cleanup of the
+    /// object to send requests. The default implementation forwards to
+    /// [`destroyed()`](Dispatcher::destroyed).
+    fn destroyed_with_handle(
+        _handle: &mut Handle<Self>,
+        data: &Self::ObjectData,
+        object_id: ObjectId,
+    ) {
+        Self::destroyed(data, object_id)
+    }
+}
+
+/// The default dispatching of events, to the [`ObjectData`] of each object
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DynDispatch;
+
+impl Dispatcher for DynDispatch {
+    type ObjectData = Arc<dyn ObjectData>;
+
+    fn placeholder_data() -> Arc<dyn ObjectData> {
+        Arc::new(UninitObjectData)
+    }
+
+    fn event(
+        &mut self,
+        handle: &mut Handle,
+        data: &Arc<dyn ObjectData>,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        data.clone().event(handle, msg)
+    }
+
+    fn destroyed(data: &Arc<dyn ObjectData>, object_id: ObjectId) {
+        data.destroyed(object_id)
+    }
+
+    fn destroyed_with_handle(handle: &mut Handle, data: &Arc<dyn ObjectData>, object_id: ObjectId) {
+        data.destroyed_with_handle(handle, object_id)
+    }
+}
+
+const CLIENT_DESTROYED: u32 = 1 << 31;
+const SERVER_DESTROYED: u32 = 1 << 30;
+const SERIAL_MASK: u32 = SERVER_DESTROYED - 1;
+
+// The per-object data of the map
+//
+// The destruction flags and the serial are packed in a single word, and the user data is
+// `None` for objects that still have the placeholder data of the dispatcher.
 #[derive(Debug, Clone)]
-struct Data {
-    client_destroyed: bool,
-    server_destroyed: bool,
-    user_data: Arc<dyn ObjectData>,
-    serial: u32,
+struct Data<T> {
+    state: u32,
+    user_data: Option<T>,
+}
+
+impl<T> Data<T> {
+    fn new(serial: u32, client_destroyed: bool, user_data: Option<T>) -> Self {
+        let flags = if client_destroyed { CLIENT_DESTROYED } else { 0 };
+        Data { state: (serial & SERIAL_MASK) | flags, user_data }
+    }
+
+    fn serial(&self) -> u32 {
+        self.state & SERIAL_MASK
+    }
+
+    fn client_destroyed(&self) -> bool {
+        self.state & CLIENT_DESTROYED != 0
+    }
+
+    fn server_destroyed(&self) -> bool {
+        self.state & SERVER_DESTROYED != 0
+    }
+
+    fn set_client_destroyed(&mut self) {
+        self.state |= CLIENT_DESTROYED;
+    }
+
+    fn set_server_destroyed(&mut self) {
+        self.state |= SERVER_DESTROYED;
+    }
 }
 
 /// An ID representing a Wayland object
@@ -124,6 +257,39 @@ impl ObjectId {
     }
 }
 
+/// Limits enforced by the client backend on what the server sends
+///
+/// They protect the client from a malicious or broken server: violating any of them is treated as
+/// a protocol error, which kills the connection, rather than letting the memory used by the backend
+/// grow without bound.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BackendConfig {
+    /// Maximum number of live objects
+    ///
+    /// It is checked when the server creates an object, and counts the objects created by both
+    /// sides. Defaults to 2^20.
+    pub max_objects: usize,
+    /// Maximum size of a single message, in bytes
+    ///
+    /// Defaults to [`MAX_BYTES_OUT`], which is also the limit of `libwayland`. Messages larger than
+    /// twice this value can never be received by the backend.
+    pub max_message_size: usize,
+    /// Maximum number of received file descriptors not yet consumed by a message
+    ///
+    /// Defaults to [`MAX_FDS_OUT`].
+    pub max_queued_fds: usize,
+}
+
+impl Default for BackendConfig {
+    fn default() -> BackendConfig {
+        BackendConfig {
+            max_objects: 1 << 20,
+            max_message_size: MAX_BYTES_OUT,
+            max_queued_fds: MAX_FDS_OUT,
+        }
+    }
+}
+
 /// Main handle of a backend to the Wayland protocol
 ///
 /// This type hosts most of the protocol-related functionality of the backend, and is the
@@ -131,13 +297,25 @@ impl ObjectId {
 /// the backend via [`Backend::handle()`](Backend::handle), and is given to you as argument
 /// in most event callbacks.
 #[derive(Debug)]
-pub struct Handle {
+pub struct Handle<D: Dispatcher = DynDispatch> {
     socket: BufferedSocket,
-    map: ObjectMap<Data>,
+    map: ObjectMap<Data<D::ObjectData>>,
+    destruction_hooks: DestructionHooks<dyn FnOnce(ObjectId) + Send + Sync>,
     last_error: Option<WaylandError>,
     last_serial: u32,
-    pending_placeholder: Option<(&'static Interface, u32)>,
+    registry_cache: Option<(ObjectId, Arc<GlobalCache>)>,
     debug: bool,
+    config: BackendConfig,
+    conformance_hook: ConformanceHook<()>,
+    stats: StatsRecorder,
+    coalescing: Coalescing,
+    // the last request of a burst of merged rectangles, not written yet
+    pending_rect: Option<Message<u32>>,
+    // the separator event following a coalesced event, to drop as well
+    skipped_separator: Option<(u32, u16)>,
+    skip_unknown_opcodes: bool,
+    read_timestamps: bool,
+    last_read: Option<Instant>,
 }
 
 /// A pure rust implementation of a Wayland client backend
@@ -145,9 +323,12 @@ pub struct Handle {
 /// This type hosts the plumbing functionalities for interacting with the wayland protocol,
 /// and most of the protocol-level interactions are made through the [`Handle`] type, accessed
 /// via the [`handle()`](Backend::handle) method.
+///
+/// The events are dispatched to the [`ObjectData`] of each object by default, see [`Dispatcher`]
+/// for dispatching all of them to a single state machine instead.
 #[derive(Debug)]
-pub struct Backend {
-    handle: Handle,
+pub struct Backend<D: Dispatcher = DynDispatch> {
+    handle: Handle<D>,
     prepared_reads: usize,
     read_condvar: Arc<Condvar>,
     read_serial: usize,
@@ -159,19 +340,66 @@ impl Backend {
     /// The provided stream should correspond to an already established unix connection with
     /// the Wayland server. On this rust backend, this method never fails.
     pub fn connect(stream: UnixStream) -> Result<Self, NoWaylandLib> {
-        let socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(stream.into_raw_fd()) });
+        Self::connect_with_transport(unsafe { Socket::from_raw_fd(stream.into_raw_fd()) })
+    }
+
+    /// Initialize a Wayland backend on the provided transport
+    ///
+    /// This allows running the protocol over something else than a plain unix socket, see
+    /// [`WaylandTransport`] for details. This method is only available on the rust backend.
+    pub fn connect_with_transport<T: WaylandTransport + 'static>(
+        transport: T,
+    ) -> Result<Self, NoWaylandLib> {
+        Self::connect_with_config(transport, BackendConfig::default())
+    }
+
+    /// Initialize a Wayland backend on the provided transport, with custom limits
+    ///
+    /// See [`BackendConfig`] for details. This method is only available on the rust backend.
+    pub fn connect_with_config<T: WaylandTransport + 'static>(
+        transport: T,
+        config: BackendConfig,
+    ) -> Result<Self, NoWaylandLib> {
+        Self::connect_static(transport, config)
+    }
+
+    /// Read events from the wayland socket if available, and invoke the associated callbacks
+    ///
+    /// This function will never block, and returns an I/O `WouldBlock` error if no event is available
+    /// to read.
+    ///
+    /// **Note:** this function should only be used if you know that you are the only thread
+    /// reading events from the wayland socket. If this may not be the case, see [`ReadEventsGuard`]
+    pub fn dispatch_events(&mut self) -> Result<usize, WaylandError> {
+        self.dispatch_events_to(&mut DynDispatch)
+    }
+
+    /// Prepare a read of the Wayland socket through an exclusive borrow of this backend
+    ///
+    /// See [`ExclusiveReadEventsGuard`] for details.
+    pub fn prepare_read(&mut self) -> Result<ExclusiveReadEventsGuard<&mut Backend>, WaylandError> {
+        ExclusiveReadEventsGuard::try_new(self)
+    }
+}
+
+impl<D: Dispatcher> Backend<D> {
+    /// Initialize a Wayland backend dispatching its events to a [`Dispatcher`]
+    ///
+    /// The backend is initialized on the provided transport, with custom limits, like with
+    /// [`connect_with_config()`](Backend::connect_with_config). Its events are dispatched by
+    /// [`dispatch_events_to()`](Backend::dispatch_events_to).
+    pub fn connect_static<T: WaylandTransport + 'static>(
+        transport: T,
+        config: BackendConfig,
+    ) -> Result<Self, NoWaylandLib> {
+        let socket = BufferedSocket::new(transport);
         let mut map = ObjectMap::new();
         map.insert_at(
             1,
             Object {
                 interface: &WL_DISPLAY_INTERFACE,
                 version: 1,
-                data: Data {
-                    client_destroyed: false,
-                    server_destroyed: false,
-                    user_data: Arc::new(DumbObjectData),
-                    serial: 0,
-                },
+                data: Data::new(0, false, None),
             },
         )
         .unwrap();
@@ -183,10 +411,20 @@ impl Backend {
             handle: Handle {
                 socket,
                 map,
+                destruction_hooks: DestructionHooks::new(),
+                registry_cache: None,
                 last_error: None,
                 last_serial: 0,
-                pending_placeholder: None,
                 debug,
+                config,
+                conformance_hook: ConformanceHook::new(),
+                stats: StatsRecorder::new("client"),
+                coalescing: Coalescing::new(),
+                pending_rect: None,
+                skipped_separator: None,
+                skip_unknown_opcodes: false,
+                read_timestamps: false,
+                last_read: None,
             },
             prepared_reads: 0,
             read_condvar: Arc::new(Condvar::new()),
@@ -196,52 +434,98 @@ impl Backend {
 
     /// Flush all pending outgoing requests to the server
     pub fn flush(&mut self) -> Result<(), WaylandError> {
-        self.handle.no_last_error()?;
-        if let Err(e) = self.handle.socket.flush() {
-            return Err(self.handle.store_if_not_wouldblock_and_return_error(e));
-        }
-        Ok(())
+        self.handle.flush()
     }
 
-    /// Read events from the wayland socket if available, and invoke the associated callbacks
+    /// Read events from the wayland socket if available, and dispatch them to a [`Dispatcher`]
     ///
     /// This function will never block, and returns an I/O `WouldBlock` error if no event is available
-    /// to read.
-    ///
-    /// **Note:** this function should only be used if you know that you are the only thread
-    /// reading events from the wayland socket. If this may not be the case, see [`ReadEventsGuard`]
-    pub fn dispatch_events(&mut self) -> Result<usize, WaylandError> {
+    /// to read. The backend must be the only one reading events from the wayland socket, the
+    /// socket can be polled for readiness using its [`connection_fd()`](Backend::connection_fd).
+    pub fn dispatch_events_to(&mut self, dispatcher: &mut D) -> Result<usize, WaylandError> {
         self.handle.no_last_error()?;
         let mut dispatched = 0;
         loop {
+            self.handle.check_incoming_limits()?;
+
             // Attempt to read a message
             let map = &self.handle.map;
-            let message = match self.handle.socket.read_one_message(|id, opcode| {
-                map.find(id)
-                    .and_then(|o| o.interface.events.get(opcode as usize))
-                    .map(|desc| desc.signature)
-            }) {
-                Ok(msg) => msg,
+            let mut unknown_target = None;
+            let message = self.handle.socket.read_one_message(|id, opcode| {
+                let object = map.find(id);
+                let desc = object.as_ref().and_then(|o| o.interface.events.get(opcode as usize));
+                if desc.is_none() {
+                    unknown_target = Some((id, opcode, object.map(|o| o.interface.name)));
+                }
+                desc.map(|desc| desc.signature)
+            });
+            let message = match (message, unknown_target) {
+                (Err(MessageParseError::Malformed), Some((id, opcode, Some(interface))))
+                    if self.handle.skip_unknown_opcodes =>
+                {
+                    self.handle.socket.skip_message().map(|()| {
+                        log::warn!(
+                            "Skipping event with unknown opcode {} to {}@{}.",
+                            opcode,
+                            interface,
+                            id
+                        );
+                        None
+                    })
+                }
+                (message, _) => message.map(Some),
+            };
+            let message = match message {
+                Ok(Some(msg)) => msg,
+                // an event with an unknown opcode was skipped
+                Ok(None) => continue,
                 Err(MessageParseError::MissingData) | Err(MessageParseError::MissingFD) => {
                     // need to read more data
                     if let Err(e) = self.handle.socket.fill_incoming_buffers() {
-                        if e.kind() != std::io::ErrorKind::WouldBlock {
-                            return Err(self.handle.store_and_return_error(e));
+                        if e.kind() == std::io::ErrorKind::WouldBlock {
+                            self.handle.check_unexpected_fds();
+                        }
+                        let err = WaylandError::io(Operation::Read, e);
+                        if !err.is_recoverable() {
+                            return Err(self.handle.store_and_return_error(err));
                         } else if dispatched == 0 {
-                            return Err(e.into());
+                            return Err(err);
                         } else {
                             break;
                         }
                     }
+                    if self.handle.read_timestamps {
+                        self.handle.last_read = Some(Instant::now());
+                    }
                     continue;
                 }
                 Err(MessageParseError::Malformed) => {
                     // malformed error, protocol error
-                    let err = WaylandError::Protocol(ProtocolError {
-                        code: 0,
-                        object_id: 0,
-                        object_interface: "".into(),
-                        message: "Malformed Wayland message.".into(),
+                    let err = WaylandError::Protocol(match unknown_target {
+                        Some((id, opcode, Some(interface))) => ProtocolError {
+                            code: 0,
+                            object_id: id,
+                            object_interface: interface.into(),
+                            message: format!(
+                                "Protocol error: server sent an event with unknown opcode {} to {}@{}.",
+                                opcode, interface, id
+                            ),
+                        },
+                        Some((id, _, None)) => ProtocolError {
+                            code: 0,
+                            object_id: id,
+                            object_interface: "".into(),
+                            message: format!(
+                                "Protocol error: server sent an event to unknown object {}.",
+                                id
+                            ),
+                        },
+                        None => ProtocolError {
+                            code: 0,
+                            object_id: 0,
+                            object_interface: "".into(),
+                            message: "Malformed Wayland message.".into(),
+                        },
                     });
                     return Err(self.handle.store_and_return_error(err));
                 }
@@ -251,6 +535,54 @@ impl Backend {
             // These lookups must succeed otherwise we would not have been able to parse this message
             let receiver = self.handle.map.find(message.sender_id).unwrap();
             let message_desc = receiver.interface.events.get(message.opcode as usize).unwrap();
+            self.handle.stats.record(
+                Direction::Received,
+                receiver.interface.name,
+                message_desc.name,
+                &message,
+            );
+
+            if self.handle.conformance_hook.is_enabled() {
+                let hook = &self.handle.conformance_hook;
+                if receiver.data.server_destroyed() {
+                    let violation = ConformanceViolation::AfterDestructor {
+                        interface: receiver.interface.name,
+                        id: message.sender_id,
+                        message: message_desc.name,
+                    };
+                    hook.invoke((), &violation);
+                }
+                check_arguments(receiver.interface, message_desc, &message, |violation| {
+                    hook.invoke((), &violation)
+                });
+            }
+
+            // Drop the events coalesced with the following ones
+            let key = (message.sender_id, message.opcode);
+            let mut coalesced = self.handle.skipped_separator.take() == Some(key);
+            if let Some(separator) =
+                self.handle.coalescing.latest_event_separator(receiver.interface, message_desc)
+            {
+                let separator = separator.map(|opcode| (message.sender_id, opcode));
+                let next =
+                    self.handle.socket.queued_headers().take(2).collect::<SmallVec<[_; 2]>>();
+                coalesced |= match next[..] {
+                    [next, ..] if next == key => true,
+                    [next, after] if Some(next) == separator && after == key => {
+                        self.handle.skipped_separator = separator;
+                        true
+                    }
+                    _ => false,
+                };
+            }
+            if coalesced {
+                for arg in &message.args {
+                    if let Argument::Fd(fd) = *arg {
+                        let _ = ::nix::unistd::close(fd);
+                    }
+                }
+                continue;
+            }
 
             // Short-circuit display-associated events
             if message.sender_id == 1 {
@@ -258,6 +590,18 @@ impl Backend {
                 continue;
             }
 
+            if self.handle.debug {
+                super::debug::print_dispatched_message(
+                    &message,
+                    receiver.interface,
+                    MessageDirection::Event,
+                );
+            }
+            log::debug!(
+                "Dispatching {}",
+                format_message(&message, receiver.interface, MessageDirection::Event)
+            );
+
             let mut created_id = None;
 
             // Convert the arguments and create the new object if applicable
@@ -300,7 +644,7 @@ impl Backend {
                                     return Err(self.handle.store_and_return_error(err));
                                 }
                             }
-                            Argument::Object(ObjectId { id: o, serial: obj.data.serial, interface: obj.interface })
+                            Argument::Object(ObjectId { id: o, serial: obj.data.serial(), interface: obj.interface })
                         } else {
                             Argument::Object(ObjectId { id: 0, serial: 0, interface: &ANONYMOUS_INTERFACE })
                         }
@@ -312,27 +656,61 @@ impl Backend {
                             None => panic!("Received event {}@{}.{} which creates an object without specifying its interface, this is unsupported.", receiver.interface.name, message.sender_id, message_desc.name),
                         };
 
-                        let child_udata = Arc::new(UninitObjectData);
+                        if new_id < SERVER_ID_LIMIT {
+                            let err = WaylandError::Protocol(ProtocolError {
+                                code: 0,
+                                object_id: 0,
+                                object_interface: "".into(),
+                                message: format!(
+                                    "Protocol error: server tried to create an object \"{}\" \
+                                    with id {}, outside of the server id range.",
+                                    child_interface.name, new_id
+                                ),
+                            });
+                            return Err(self.handle.store_and_return_error(err));
+                        }
+
+                        // if this ID belonged to a now destroyed server object, we can replace it,
+                        // but reusing the ID of a live object is an error
+                        match self.handle.map.find(new_id) {
+                            Some(obj) if obj.data.client_destroyed() => self.handle.map.remove(new_id),
+                            Some(obj) => {
+                                let err = WaylandError::Protocol(ProtocolError {
+                                    code: 0,
+                                    object_id: new_id,
+                                    object_interface: obj.interface.name.into(),
+                                    message: format!(
+                                        "Protocol error: server tried to create an object \"{}\" \
+                                        with id {}, which is still used by {}@{}.",
+                                        child_interface.name, new_id, obj.interface.name, new_id
+                                    ),
+                                });
+                                return Err(self.handle.store_and_return_error(err));
+                            }
+                            None => {}
+                        }
 
-                        // if this ID belonged to a now destroyed server object, we can replace it
-                        if new_id >= SERVER_ID_LIMIT
-                            && self.handle.map.with(new_id, |obj| obj.data.client_destroyed).unwrap_or(false)
-                        {
-                            self.handle.map.remove(new_id);
+                        if self.handle.map.len() >= self.handle.config.max_objects {
+                            let err = WaylandError::Protocol(ProtocolError {
+                                code: 0,
+                                object_id: 0,
+                                object_interface: "".into(),
+                                message: format!(
+                                    "Protocol error: server tried to create an object \"{}\" \
+                                    while the limit of {} live objects is reached.",
+                                    child_interface.name, self.handle.config.max_objects
+                                ),
+                            });
+                            return Err(self.handle.store_and_return_error(err));
                         }
 
                         let child_obj = Object {
                             interface: child_interface,
                             version: receiver.version,
-                            data: Data {
-                                client_destroyed: receiver.data.client_destroyed,
-                                server_destroyed: false,
-                                user_data: child_udata,
-                                serial: self.handle.next_serial(),
-                            }
+                            data: Data::new(self.handle.next_serial(), receiver.data.client_destroyed(), None),
                         };
 
-                        let child_id = ObjectId { id: new_id, serial: child_obj.data.serial, interface: child_obj.interface };
+                        let child_id = ObjectId { id: new_id, serial: child_obj.data.serial(), interface: child_obj.interface };
                         created_id = Some(child_id.clone());
 
                         if let Err(()) = self.handle.map.insert_at(new_id, child_obj) {
@@ -355,17 +733,8 @@ impl Backend {
                 });
             }
 
-            if self.handle.debug {
-                super::debug::print_dispatched_message(
-                    receiver.interface.name,
-                    message.sender_id,
-                    message_desc.name,
-                    &args,
-                );
-            }
-
             // If this event is send to an already destroyed object (by the client), swallow it
-            if receiver.data.client_destroyed {
+            if receiver.data.client_destroyed() {
                 // but close any associated FD to avoid leaking them
                 for a in args {
                     if let Argument::Fd(fd) = a {
@@ -378,38 +747,52 @@ impl Backend {
             // Invoke the user callback
             let id = ObjectId {
                 id: message.sender_id,
-                serial: receiver.data.serial,
+                serial: receiver.data.serial(),
                 interface: receiver.interface,
             };
-            log::debug!("Dispatching {}.{} ({})", id, receiver.version, DisplaySlice(&args));
-            let ret = receiver
-                .data
-                .user_data
-                .clone()
-                .event(&mut self.handle, Message { sender_id: id, opcode: message.opcode, args });
+            let ret = dispatcher.event(
+                &mut self.handle,
+                &receiver.data.user_data.clone().unwrap_or_else(D::placeholder_data),
+                Message { sender_id: id, opcode: message.opcode, args },
+            );
 
             // If this event is a destructor, destroy the object
+            //
+            // The callback may have replaced its object data, or already destroyed it by sending
+            // a destructor request, in which case it must not be notified a second time.
             if message_desc.is_destructor {
-                self.handle
-                    .map
-                    .with(message.sender_id, |obj| {
-                        obj.data.server_destroyed = true;
-                        obj.data.client_destroyed = true;
-                    })
-                    .unwrap();
-                receiver.data.user_data.destroyed(ObjectId {
-                    id: message.sender_id,
-                    serial: receiver.data.serial,
-                    interface: receiver.interface,
+                let destroyed = self.handle.map.with(message.sender_id, |obj| {
+                    let already_destroyed = obj.client_destroyed();
+                    obj.set_server_destroyed();
+                    obj.set_client_destroyed();
+                    (already_destroyed, obj.user_data.clone())
                 });
+                if let Ok((false, user_data)) = destroyed {
+                    let id = ObjectId {
+                        id: message.sender_id,
+                        serial: receiver.data.serial(),
+                        interface: receiver.interface,
+                    };
+                    D::destroyed_with_handle(
+                        &mut self.handle,
+                        &user_data.unwrap_or_else(D::placeholder_data),
+                        id.clone(),
+                    );
+                    self.handle.run_destruction_hooks(id);
+                }
             }
 
             match (created_id, ret) {
                 (Some(child_id), Some(child_data)) => {
-                    self.handle
-                        .map
-                        .with(child_id.id, |obj| obj.data.user_data = child_data)
-                        .unwrap();
+                    // the callback may have already destroyed the new object, its data then
+                    // needs to be notified of it
+                    let destroyed = self.handle.map.with(child_id.id, |obj| {
+                        obj.user_data = Some(child_data.clone());
+                        obj.client_destroyed()
+                    });
+                    if destroyed.unwrap_or(true) {
+                        D::destroyed_with_handle(&mut self.handle, &child_data, child_id);
+                    }
                 }
                 (None, None) => {}
                 (Some(child_id), None) => {
@@ -429,9 +812,32 @@ impl Backend {
     }
 
     /// Access the [`Handle`] associated with this backend
-    pub fn handle(&mut self) -> &mut Handle {
+    pub fn handle(&mut self) -> &mut Handle<D> {
         &mut self.handle
     }
+
+    /// The limits enforced by this backend
+    pub fn config(&self) -> &BackendConfig {
+        &self.handle.config
+    }
+
+    /// Access the Wayland socket FD for polling
+    ///
+    /// This method is only available on the rust backend.
+    pub fn connection_fd(&self) -> RawFd {
+        self.handle.socket.as_raw_fd()
+    }
+
+    /// Release the memory kept by the backend after a burst of objects
+    ///
+    /// The object map keeps the size it grew to when many objects were alive at the same time,
+    /// this gives back the memory not needed by the current objects. See
+    /// [`Handle::memory_usage()`] for the memory used by the backend. This method is only available
+    /// on the rust backend.
+    pub fn shrink_to_fit(&mut self) {
+        self.handle.map.shrink_to_fit(true);
+        self.handle.destruction_hooks.shrink_to_fit();
+    }
 }
 
 /// Guard for synchronizing event reading across multiple threads
@@ -514,7 +920,55 @@ impl Drop for ReadEventsGuard {
     }
 }
 
-impl Handle {
+/// Guard for reading events through an exclusive access to the backend
+///
+/// This is an alternative to [`ReadEventsGuard`] for integrations which do their own
+/// synchronization, like a single-threaded event loop: rather than an `Arc<Mutex<Backend>>`, the
+/// guard holds an exclusive access to the backend for its whole lifetime (a `&mut Backend`, or a
+/// `MutexGuard<Backend>`), so that it does not need to lock the backend again when reading.
+///
+/// Reading still synchronizes with the [`ReadEventsGuard`]s of other threads: if any of them is
+/// alive, the reading is left to the last of them.
+#[derive(Debug)]
+pub struct ExclusiveReadEventsGuard<B: DerefMut<Target = Backend>> {
+    backend: B,
+}
+
+impl<B: DerefMut<Target = Backend>> ExclusiveReadEventsGuard<B> {
+    /// Create a new reading guard
+    ///
+    /// This call neither blocks nor invokes any event callback, the events are only read and
+    /// dispatched by [`read()`](ExclusiveReadEventsGuard::read).
+    pub fn try_new(backend: B) -> Result<Self, WaylandError> {
+        Ok(ExclusiveReadEventsGuard { backend })
+    }
+
+    /// Access the Wayland socket FD for polling
+    pub fn connection_fd(&self) -> RawFd {
+        self.backend.handle.socket.as_raw_fd()
+    }
+
+    /// Attempt to read events from the Wayland socket
+    ///
+    /// Unlike [`ReadEventsGuard::read()`], this never blocks waiting for the guards of other
+    /// threads: if any of them is alive, the reading is left to them and this returns `0`.
+    ///
+    /// This returns the number of dispatched events, or `0` if an other thread handles the
+    /// dispatching. If no events are available to read from the socket, this returns a
+    /// `WouldBlock` IO error.
+    pub fn read(mut self) -> Result<usize, WaylandError> {
+        let backend = &mut *self.backend;
+        if backend.prepared_reads == 0 {
+            // no other thread is waiting for a read
+            backend.dispatch_events()
+        } else {
+            backend.handle.no_last_error()?;
+            Ok(0)
+        }
+    }
+}
+
+impl<D: Dispatcher> Handle<D> {
     /// Get the object ID for the `wl_display`
     pub fn display_id(&self) -> ObjectId {
         ObjectId { serial: 0, id: 1, interface: &WL_DISPLAY_INTERFACE }
@@ -531,14 +985,21 @@ impl Handle {
     ///
     /// Returns an error if the provided object ID is no longer valid.
     pub fn info(&self, id: ObjectId) -> Result<ObjectInfo, InvalidId> {
-        let object = self.get_object(id.clone())?;
-        if object.data.client_destroyed {
-            Err(InvalidId)
-        } else {
-            Ok(ObjectInfo { id: id.id, interface: object.interface, version: object.version })
+        match self.object_info(&id) {
+            Ok(info) if info.alive => Ok(info),
+            _ => Err(InvalidId),
         }
     }
 
+    /// Get the detailed information about several wayland objects at once
+    ///
+    /// Unlike [`info()`](Self::info), objects whose destruction has been sent but not yet
+    /// acknowledged by the server are reported, with [`ObjectInfo::alive`] set to false. An error is
+    /// returned for the IDs which are no longer valid.
+    pub fn info_many(&self, ids: &[ObjectId]) -> Vec<Result<ObjectInfo, InvalidId>> {
+        ids.iter().map(|id| self.object_info(id)).collect()
+    }
+
     /// Create a null object ID
     ///
     /// This object ID is always invalid, and can be used as placeholder.
@@ -549,19 +1010,21 @@ impl Handle {
     /// Create a placeholder ID for object creation
     ///
     /// This ID needs to be created beforehand and given as argument to a request creating a
-    /// new object ID. A specification must be specified if the interface and version cannot
-    /// be inferred from the protocol (for example object creation from the `wl_registry`).
-    ///
-    /// If a specification is provided it'll be checked against what can be deduced from the
-    /// protocol specification, and [`send_request`](Handle::send_request) will panic if they
-    /// do not match.
-    pub fn placeholder_id(&mut self, spec: Option<(&'static Interface, u32)>) -> ObjectId {
-        self.pending_placeholder = spec;
-        ObjectId {
-            serial: 0,
-            id: 0,
-            interface: spec.map(|(i, _)| i).unwrap_or(&ANONYMOUS_INTERFACE),
-        }
+    /// new object ID, the actual ID being allocated by [`send_request`](Handle::send_request).
+    /// The interface of the new object and its version are deduced from the protocol: regular
+    /// constructors create objects with the version of their parent, and generic constructors
+    /// (for example object creation from the `wl_registry`) give the name of the interface and
+    /// the version in the message.
+    ///
+    /// The interface of a generic constructor must either be given here, or be registered with
+    /// [`register_interfaces()`](crate::protocol::register_interfaces). If it is given, it'll be
+    /// checked against what can be deduced from the message, and
+    /// [`send_request`](Handle::send_request) will panic if they do not match.
+    ///
+    /// Placeholders leave no state in the `Handle`: they can be created ahead of the requests
+    /// using them, in any order and from any thread.
+    pub fn placeholder_id(&self, interface: Option<&'static Interface>) -> ObjectId {
+        ObjectId { serial: 0, id: 0, interface: interface.unwrap_or(&ANONYMOUS_INTERFACE) }
     }
 
     /// Sends a request to the server
@@ -576,15 +1039,15 @@ impl Handle {
     /// - the message opcode must be valid for the sender interface
     /// - the argument list must match the prototype for the message associated with this opcode
     /// - if the method creates a new object, a [`placeholder_id()`](Handle::placeholder_id) must be given
-    ///   in the argument list, either without a specification, or with a specification that matches the
-    ///   interface and version deduced from the protocol rules
+    ///   in the argument list, either without an interface, or with the interface deduced from the
+    ///   protocol rules
     pub fn send_request(
         &mut self,
         Message { sender_id: id, opcode, args }: Message<ObjectId>,
-        data: Option<Arc<dyn ObjectData>>,
+        data: Option<D::ObjectData>,
     ) -> Result<ObjectId, InvalidId> {
         let object = self.get_object(id.clone())?;
-        if object.data.client_destroyed {
+        if object.data.client_destroyed() {
             return Err(InvalidId);
         }
 
@@ -595,79 +1058,34 @@ impl Handle {
             }
         };
 
-        if !check_for_signature(message_desc.signature, &args) {
+        if let Err(mismatch) = compare_signature(message_desc, &args) {
             panic!(
-                "Unexpected signature for request {}@{}.{}: expected {:?}, got {:?}.",
-                object.interface.name, id.id, message_desc.name, message_desc.signature, args
+                "Unexpected signature for request {}@{}: {}",
+                object.interface.name, id.id, mismatch
             );
         }
 
         // Prepare the child object
-        let child_spec = if message_desc
-            .signature
-            .iter()
-            .any(|arg| matches!(arg, ArgumentType::NewId(_)))
-        {
-            if let Some((iface, version)) = self.pending_placeholder.take() {
-                if let Some(child_interface) = message_desc.child_interface {
-                    if !same_interface(child_interface, iface) {
-                        panic!(
-                            "Wrong placeholder used when sending request {}@{}.{}: expected interface {} but got {}",
-                            object.interface.name,
-                            id.id,
-                            message_desc.name,
-                            child_interface.name,
-                            iface.name
-                        );
-                    }
-                    if version != object.version {
-                        panic!(
-                            "Wrong placeholder used when sending request {}@{}.{}: expected version {} but got {}",
-                            object.interface.name,
-                            id.id, message_desc.name,
-                            object.version,
-                            version
-                        );
-                    }
-                }
-                Some((iface, version))
-            } else if let Some(child_interface) = message_desc.child_interface {
-                Some((child_interface, object.version))
-            } else {
-                panic!(
-                    "Wrong placeholder used when sending request {}@{}.{}: target interface must be specified for a generic constructor.",
-                    object.interface.name,
-                    id.id,
-                    message_desc.name
-                );
-            }
-        } else {
-            None
+        let child_spec = match child_spec(message_desc, &args, object.version, |id| id.interface) {
+            Ok(spec) => spec,
+            Err(e) => panic!(
+                "Wrong placeholder used when sending request {}@{}.{}: {}",
+                object.interface.name, id.id, message_desc.name, e
+            ),
         };
 
         let child = if let Some((child_interface, child_version)) = child_spec {
+            let user_data = data
+                .expect("Sending a request creating an object without providing an object data.");
             let child_serial = self.next_serial();
 
             let child = Object {
                 interface: child_interface,
                 version: child_version,
-                data: Data {
-                    client_destroyed: false,
-                    server_destroyed: false,
-                    user_data: Arc::new(DumbObjectData),
-                    serial: child_serial,
-                },
+                data: Data::new(child_serial, false, Some(user_data)),
             };
 
             let child_id = self.map.client_insert_new(child);
-
-            self.map
-                .with(child_id, |obj| {
-                    obj.data.user_data = data.expect(
-                        "Sending a request creating an object without providing an object data.",
-                    );
-                })
-                .unwrap();
             Some((child_id, child_serial, child_interface))
         } else {
             None
@@ -689,16 +1107,6 @@ impl Handle {
             }
         }).collect::<SmallVec<[_; INLINE_ARGS]>>();
 
-        if self.debug {
-            super::debug::print_send_message(
-                object.interface.name,
-                id.id,
-                message_desc.name,
-                &args,
-            );
-        }
-        log::debug!("Sending {}.{} ({})", id, message_desc.name, DisplaySlice(&args));
-
         // Send the message
 
         let mut msg_args = SmallVec::with_capacity(args.len());
@@ -729,18 +1137,38 @@ impl Handle {
 
         let msg = Message { sender_id: id.id, opcode, args: msg_args };
 
-        if let Err(err) = self.socket.write_message(&msg) {
-            self.last_error = Some(WaylandError::Io(err));
+        if self.debug {
+            super::debug::print_send_message(&msg, object.interface, MessageDirection::Request);
+        }
+        log::debug!(
+            "Sending {}",
+            format_message(&msg, object.interface, MessageDirection::Request)
+        );
+
+        // Merge the bursts of rectangles, the merged request is written once the burst ends
+        let mut merged = false;
+        if let Some(mut pending) = self.pending_rect.take() {
+            merged = coalesce::merge_rects(&mut pending, &msg);
+            if merged {
+                self.pending_rect = Some(pending);
+            } else {
+                self.write_message(&pending);
+            }
+        }
+        if !merged {
+            if self.coalescing.merges_rect(object.interface, message_desc) {
+                self.pending_rect = Some(msg);
+            } else {
+                self.write_message(&msg);
+            }
         }
 
         // Handle destruction if relevant
         if message_desc.is_destructor {
-            self.map
-                .with(id.id, |obj| {
-                    obj.data.client_destroyed = true;
-                })
-                .unwrap();
-            object.data.user_data.destroyed(id);
+            self.map.with(id.id, |obj| obj.set_client_destroyed()).unwrap();
+            let data = object.data.user_data.unwrap_or_else(D::placeholder_data);
+            D::destroyed_with_handle(self, &data, id.clone());
+            self.run_destruction_hooks(id);
         }
         if let Some((child_id, child_serial, child_interface)) = child {
             Ok(ObjectId { id: child_id, serial: child_serial, interface: child_interface })
@@ -749,37 +1177,237 @@ impl Handle {
         }
     }
 
+    /// Destroy an object without sending a destructor request
+    ///
+    /// This is meant for the objects destroyed by a request of another object, like `wl_registry`
+    /// by `wl_fixes.destroy_registry`, and must be invoked right after sending this request. The
+    /// destruction of the object is notified to its object data and destruction hooks, and its ID
+    /// is kept until the server acknowledges it with `wl_display.delete_id`, dropping the events
+    /// the server sent to it in the meantime.
+    ///
+    /// Does nothing if the object is already destroyed.
+    pub fn release_object(&mut self, id: ObjectId) -> Result<(), InvalidId> {
+        let object = self.get_object(id.clone())?;
+        if !object.data.client_destroyed() {
+            self.map.with(id.id, |obj| obj.set_client_destroyed()).unwrap();
+            let data = object.data.user_data.unwrap_or_else(D::placeholder_data);
+            D::destroyed_with_handle(self, &data, id.clone());
+            self.run_destruction_hooks(id);
+        }
+        Ok(())
+    }
+
     /// Access the object data associated with a given object ID
     ///
     /// Returns an error if the object ID is not longer valid
-    pub fn get_data(&self, id: ObjectId) -> Result<Arc<dyn ObjectData>, InvalidId> {
+    pub fn get_data(&self, id: ObjectId) -> Result<D::ObjectData, InvalidId> {
         let object = self.get_object(id)?;
-        Ok(object.data.user_data)
+        Ok(object.data.user_data.unwrap_or_else(D::placeholder_data))
     }
 
     /// Set the object data associated with a given object ID
     ///
     /// Returns an error if the object ID is not longer valid
-    pub fn set_data(&mut self, id: ObjectId, data: Arc<dyn ObjectData>) -> Result<(), InvalidId> {
+    pub fn set_data(&mut self, id: ObjectId, data: D::ObjectData) -> Result<(), InvalidId> {
         self.map
             .with(id.id, move |objdata| {
-                if objdata.data.serial != id.serial {
+                if objdata.serial() != id.serial {
                     Err(InvalidId)
                 } else {
-                    objdata.data.user_data = data;
+                    objdata.user_data = Some(data);
                     Ok(())
                 }
             })
             .unwrap_or(Err(InvalidId))
     }
+
+    /// Enable the conformance checks of the events sent by the server
+    ///
+    /// Beyond the errors preventing the events from being parsed, which are always protocol errors,
+    /// the backend then checks that no event is received for an object after a destructor event,
+    /// that enum arguments have values defined by the protocol, that non-nullable object arguments
+    /// are not null, and that no file descriptor is left once all the received data is parsed.
+    /// The violations are reported to the hook, without interrupting the dispatching. These checks
+    /// are intended for test environments, as they slow down the dispatching of events.
+    ///
+    /// The hook is invoked while the backend is borrowed, and thus must not try to access it. This
+    /// replaces the previously set hook, if any. This method is only available on the rust backend.
+    pub fn set_conformance_hook(&mut self, hook: Box<dyn FnMut(&ConformanceViolation) + Send>) {
+        let mut hook = hook;
+        self.conformance_hook.set(Box::new(move |(), violation| hook(violation)));
+    }
+
+    /// Start recording the statistics of the messages exchanged with the server
+    ///
+    /// The number and size of the messages are then counted for each interface and opcode, in both
+    /// directions. This does nothing if they were already being recorded, for example because the
+    /// `WAYLAND_STATS` environment variable is set, see [`ProtocolStats`] for details. This method
+    /// is only available on the rust backend.
+    pub fn enable_stats(&mut self) {
+        self.stats.enable();
+    }
+
+    /// Get the statistics of the messages exchanged with the server
+    ///
+    /// Returns `None` if they are not recorded, see [`enable_stats()`](Handle::enable_stats).
+    pub fn stats(&self) -> Option<ProtocolStats> {
+        self.stats.stats()
+    }
+
+    /// Clear the statistics of the messages recorded so far
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    /// Get the memory used by the backend
+    ///
+    /// See [`MemoryUsage`] for details. This method is only available on the rust backend.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            socket_buffers: self.socket.buffers_size(),
+            queued_messages: self.socket.queued_bytes(),
+            object_map: self.map.heap_size(),
+            objects: self.map.len(),
+            zombie_objects: self
+                .map
+                .all_objects()
+                .filter(|(_, object)| object.data.client_destroyed())
+                .count(),
+        }
+    }
+
+    /// Set the rules coalescing bursts of high-frequency messages
+    ///
+    /// See [`Coalescing`] for details. This replaces the previously set rules, if any. This method
+    /// is only available on the rust backend.
+    pub fn set_coalescing(&mut self, coalescing: Coalescing) {
+        self.write_pending_rect();
+        self.coalescing = coalescing;
+    }
+
+    /// Skip the events with an opcode unknown to the interface of their object
+    ///
+    /// Such events are sent by a server using a newer version of an interface than the one known
+    /// to the client, and are a protocol error by default. Once enabled, they are discarded using
+    /// the size of the message from its header, logging a warning. The file descriptors they carry
+    /// cannot be known, and are left queued. This method is only available on the rust backend.
+    pub fn set_skip_unknown_opcodes(&mut self, skip: bool) {
+        self.skip_unknown_opcodes = skip;
+    }
+
+    /// Register a hook to be invoked when an object is destroyed
+    ///
+    /// The hooks of an object are invoked in registration order, right after the
+    /// [`Dispatcher::destroyed()`] notification of its object data, like
+    /// [`ObjectData::destroyed()`], and before its ID can be reused.
+    /// They are invoked while the backend is borrowed, and thus must not try to access it.
+    ///
+    /// Returns an error if the object ID is not longer valid
+    pub fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        let object = self.get_object(id.clone())?;
+        if object.data.client_destroyed() {
+            return Err(InvalidId);
+        }
+        self.destruction_hooks.insert(id.id, id.serial, hook);
+        Ok(())
+    }
 }
 
 impl Handle {
+    /// Enable the registry maintained by the backend
+    ///
+    /// The first call creates a `wl_registry` whose events are handled by the backend itself to
+    /// keep track of the globals advertised by the server, subsequent calls return the same
+    /// registry. This allows independent components sharing the connection to see a consistent
+    /// list of globals via [`globals()`](Handle::globals), and to bind them using this registry.
+    ///
+    /// The globals are received on the next dispatch of the events of the connection.
+    pub fn enable_registry_cache(&mut self) -> Result<ObjectId, InvalidId> {
+        if let Some((ref registry, _)) = self.registry_cache {
+            return Ok(registry.clone());
+        }
+        let cache = Arc::new(GlobalCache::default());
+        let placeholder = self.placeholder_id(Some(&WL_REGISTRY_INTERFACE));
+        let registry = self.send_request(
+            message!(self.display_id(), 1, [Argument::NewId(placeholder)]),
+            Some(cache.clone()),
+        )?;
+        self.registry_cache = Some((registry.clone(), cache));
+        Ok(registry)
+    }
+
+    /// Get the globals advertised by the server
+    ///
+    /// Returns `None` if the registry maintained by the backend was not enabled using
+    /// [`enable_registry_cache()`](Handle::enable_registry_cache).
+    pub fn globals(&self) -> Option<Vec<GlobalInfo>> {
+        self.registry_cache.as_ref().map(|(_, cache)| cache.globals())
+    }
+
+    /// Record the time of each read of the Wayland socket
+    ///
+    /// Once enabled, the time at which the events being dispatched were read from the socket is
+    /// available from [`last_read_time()`](Handle::last_read_time). This is meant for profiling:
+    /// it stays enabled for the lifetime of the connection, costing a clock read per read of the
+    /// socket.
+    pub fn enable_read_timestamps(&mut self) {
+        self.read_timestamps = true;
+    }
+
+    /// The time of the last read of the Wayland socket
+    ///
+    /// When invoked from the callbacks of events, this is the time at which they were read.
+    /// Returns `None` until [`enable_read_timestamps()`](Handle::enable_read_timestamps) is
+    /// invoked and the socket is read.
+    pub fn last_read_time(&self) -> Option<Instant> {
+        self.last_read
+    }
+}
+
+impl<D: Dispatcher> Handle<D> {
+    fn write_message(&mut self, msg: &Message<u32>) {
+        if self.stats.is_enabled() {
+            if let Some(object) = self.map.get(msg.sender_id) {
+                let name = object.interface.requests[msg.opcode as usize].name;
+                self.stats.record(Direction::Sent, object.interface.name, name, msg);
+            }
+        }
+        if let Err(err) = self.socket.write_message(msg) {
+            self.last_error = Some(WaylandError::io(Operation::Write, err));
+        }
+    }
+
+    fn write_pending_rect(&mut self) {
+        if let Some(msg) = self.pending_rect.take() {
+            self.write_message(&msg);
+        }
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<(), WaylandError> {
+        self.no_last_error()?;
+        self.write_pending_rect();
+        self.no_last_error()?;
+        if let Err(e) = self.socket.flush() {
+            return Err(self.store_if_not_recoverable_and_return_error(Operation::Flush, e));
+        }
+        Ok(())
+    }
+
     fn next_serial(&mut self) -> u32 {
-        self.last_serial = self.last_serial.wrapping_add(1);
+        self.last_serial = self.last_serial.wrapping_add(1) & SERIAL_MASK;
         self.last_serial
     }
 
+    fn run_destruction_hooks(&mut self, id: ObjectId) {
+        for hook in self.destruction_hooks.take(id.id, id.serial) {
+            hook(id.clone());
+        }
+    }
+
     #[inline]
     fn no_last_error(&self) -> Result<(), WaylandError> {
         if let Some(ref err) = self.last_error {
@@ -798,17 +1426,69 @@ impl Handle {
     }
 
     #[inline]
-    fn store_if_not_wouldblock_and_return_error(&mut self, e: std::io::Error) -> WaylandError {
-        if e.kind() != std::io::ErrorKind::WouldBlock {
-            self.store_and_return_error(e)
+    fn store_if_not_recoverable_and_return_error(
+        &mut self,
+        operation: Operation,
+        e: std::io::Error,
+    ) -> WaylandError {
+        let err = WaylandError::io(operation, e);
+        if !err.is_recoverable() {
+            self.store_and_return_error(err)
         } else {
-            e.into()
+            err
         }
     }
 
-    fn get_object(&self, id: ObjectId) -> Result<Object<Data>, InvalidId> {
+    fn check_incoming_limits(&mut self) -> Result<(), WaylandError> {
+        let message = match self.socket.next_message_size() {
+            Some(size) if size > self.config.max_message_size => format!(
+                "Protocol error: server sent a message of {} bytes, larger than the limit of {}.",
+                size, self.config.max_message_size
+            ),
+            _ if self.socket.queued_fds() > self.config.max_queued_fds => format!(
+                "Protocol error: server sent {} file descriptors, more than the limit of {}.",
+                self.socket.queued_fds(),
+                self.config.max_queued_fds
+            ),
+            _ => return Ok(()),
+        };
+        let err = WaylandError::Protocol(ProtocolError {
+            code: 0,
+            object_id: 0,
+            object_interface: "".into(),
+            message,
+        });
+        Err(self.store_and_return_error(err))
+    }
+
+    // file descriptors left once all the received data is parsed were sent without a message
+    // using them, they are reported and closed
+    fn check_unexpected_fds(&mut self) {
+        if self.socket.queued_bytes() == 0
+            && self.socket.queued_fds() > 0
+            && self.conformance_hook.is_enabled()
+        {
+            let count = self.socket.discard_queued_fds();
+            self.conformance_hook.invoke((), &ConformanceViolation::UnexpectedFds { count });
+        }
+    }
+
+    fn object_info(&self, id: &ObjectId) -> Result<ObjectInfo, InvalidId> {
+        match self.map.get(id.id) {
+            Some(object) if object.data.serial() == id.serial => Ok(ObjectInfo {
+                id: id.id,
+                interface: object.interface,
+                version: object.version,
+                serial: id.serial,
+                alive: !object.data.client_destroyed(),
+            }),
+            _ => Err(InvalidId),
+        }
+    }
+
+    fn get_object(&self, id: ObjectId) -> Result<Object<Data<D::ObjectData>>, InvalidId> {
         let object = self.map.find(id.id).ok_or(InvalidId)?;
-        if object.data.serial != id.serial {
+        if object.data.serial() != id.serial {
             return Err(InvalidId);
         }
         Ok(object)
@@ -831,7 +1511,7 @@ impl Handle {
                             .into(),
                         message: message.to_string_lossy().into(),
                     });
-                    return Err(self.store_and_return_error(err));
+                    Err(self.store_and_return_error(err))
                 } else {
                     unreachable!()
                 }
@@ -839,40 +1519,51 @@ impl Handle {
             1 => {
                 // wl_display.delete_id
                 if let [Argument::Uint(id)] = message.args[..] {
-                    let client_destroyed = self
-                        .map
-                        .with(id, |obj| {
-                            obj.data.server_destroyed = true;
-                            obj.data.client_destroyed
-                        })
-                        .unwrap_or(false);
-                    if client_destroyed {
-                        self.map.remove(id);
-                    }
+                    // the server may only delete the ids of objects the client knows destroyed
+                    let (object_interface, message) = match self.map.find(id) {
+                        Some(obj) if obj.data.client_destroyed() => {
+                            self.map.remove(id);
+                            return Ok(());
+                        }
+                        Some(obj) => (
+                            obj.interface.name,
+                            format!(
+                                "Protocol error: server sent delete_id for {}@{}, which is not destroyed.",
+                                obj.interface.name, id
+                            ),
+                        ),
+                        None => (
+                            "",
+                            format!("Protocol error: server sent delete_id for unknown object {}.", id),
+                        ),
+                    };
+                    let err = WaylandError::Protocol(ProtocolError {
+                        code: 0,
+                        object_id: id,
+                        object_interface: object_interface.into(),
+                        message,
+                    });
+                    Err(self.store_and_return_error(err))
                 } else {
                     unreachable!()
                 }
             }
             _ => unreachable!(),
         }
-        Ok(())
     }
 }
 
-struct DumbObjectData;
-
-impl ObjectData for DumbObjectData {
+impl ObjectData for GlobalCache {
     fn event(
         self: Arc<Self>,
         _handle: &mut Handle,
-        _msg: Message<ObjectId>,
+        msg: Message<ObjectId>,
     ) -> Option<Arc<dyn ObjectData>> {
-        unreachable!()
+        self.handle_event(&msg);
+        None
     }
 
-    fn destroyed(&self, _object_id: ObjectId) {
-        unreachable!()
-    }
+    fn destroyed(&self, _object_id: ObjectId) {}
 }
 
 struct UninitObjectData;
@@ -2,7 +2,7 @@
 
 use crate::protocol::Interface;
 
-use std::cmp::Ordering;
+use std::{collections::HashMap, fmt};
 
 /// Limit separating server-created from client-created objects IDs in the namespace
 pub const SERVER_ID_LIMIT: u32 = 0xFF00_0000;
@@ -22,41 +22,85 @@ pub struct Object<Data> {
 ///
 /// Keeps track of which object id is associated to which
 /// interface object, and which is currently unused.
-#[derive(Debug, Default)]
+///
+/// To keep the slots of the ids compact, the interfaces of the objects are interned, and their
+/// data is stored in a separate slab whose free places are reused.
+#[derive(Debug)]
 pub struct ObjectMap<Data> {
-    client_objects: Vec<Option<Object<Data>>>,
-    server_objects: Vec<Option<Object<Data>>>,
+    client_objects: Vec<Option<Entry>>,
+    server_objects: Vec<Option<Entry>>,
+    interfaces: Vec<&'static Interface>,
+    data: Vec<Option<Data>>,
+    free_data: Vec<u32>,
+    len: usize,
+}
+
+// An object of the map, with the index of its interface and of its data
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    interface: u16,
+    version: u32,
+    data: u32,
+}
+
+impl<Data> Default for ObjectMap<Data> {
+    fn default() -> Self {
+        ObjectMap {
+            client_objects: Vec::new(),
+            server_objects: Vec::new(),
+            interfaces: Vec::new(),
+            data: Vec::new(),
+            free_data: Vec::new(),
+            len: 0,
+        }
+    }
 }
 
 impl<Data: Clone> ObjectMap<Data> {
     /// Create a new empty object map
     pub fn new() -> ObjectMap<Data> {
-        ObjectMap { client_objects: Vec::new(), server_objects: Vec::new() }
+        ObjectMap::default()
+    }
+
+    /// Number of objects in the store
+    pub fn len(&self) -> usize {
+        self.len
     }
 
     /// Find an object in the store
     pub fn find(&self, id: u32) -> Option<Object<Data>> {
-        if id == 0 {
-            None
-        } else if id >= SERVER_ID_LIMIT {
-            self.server_objects.get((id - SERVER_ID_LIMIT) as usize).and_then(Clone::clone)
-        } else {
-            self.client_objects.get((id - 1) as usize).and_then(Clone::clone)
-        }
+        self.entry(id).map(|entry| Object {
+            interface: self.interfaces[entry.interface as usize],
+            version: entry.version,
+            data: self.data[entry.data as usize].clone().unwrap(),
+        })
+    }
+
+    /// Access an object in the store, without cloning its data
+    pub fn get(&self, id: u32) -> Option<Object<&Data>> {
+        self.entry(id).map(|entry| Object {
+            interface: self.interfaces[entry.interface as usize],
+            version: entry.version,
+            data: self.data[entry.data as usize].as_ref().unwrap(),
+        })
     }
 
     /// Remove an object from the store
     ///
     /// Does nothing if the object didn't previously exists
     pub fn remove(&mut self, id: u32) {
-        if id == 0 {
-            // nothing
+        let place = if id == 0 {
+            None
         } else if id >= SERVER_ID_LIMIT {
-            if let Some(place) = self.server_objects.get_mut((id - SERVER_ID_LIMIT) as usize) {
-                *place = None;
-            }
-        } else if let Some(place) = self.client_objects.get_mut((id - 1) as usize) {
-            *place = None;
+            self.server_objects.get_mut((id - SERVER_ID_LIMIT) as usize)
+        } else {
+            self.client_objects.get_mut((id - 1) as usize)
+        };
+        if let Some(place @ Some(_)) = place {
+            let entry = place.take().unwrap();
+            self.data[entry.data as usize] = None;
+            self.free_data.push(entry.data);
+            self.len -= 1;
         }
     }
 
@@ -66,94 +110,194 @@ impl<Data: Clone> ObjectMap<Data> {
     /// (In which case this is a protocol error)
     pub fn insert_at(&mut self, id: u32, object: Object<Data>) -> Result<(), ()> {
         if id == 0 {
-            Err(())
-        } else if id >= SERVER_ID_LIMIT {
-            insert_in_at(&mut self.server_objects, (id - SERVER_ID_LIMIT) as usize, object)
+            return Err(());
+        }
+        let (store, index) = if id >= SERVER_ID_LIMIT {
+            (&self.server_objects, (id - SERVER_ID_LIMIT) as usize)
         } else {
-            insert_in_at(&mut self.client_objects, (id - 1) as usize, object)
+            (&self.client_objects, (id - 1) as usize)
+        };
+        if index > store.len() || matches!(store.get(index), Some(Some(_))) {
+            return Err(());
         }
+        let entry = self.make_entry(object);
+        let store =
+            if id >= SERVER_ID_LIMIT { &mut self.server_objects } else { &mut self.client_objects };
+        insert_in_at(store, index, entry);
+        self.len += 1;
+        Ok(())
     }
 
     /// Allocate a new id for an object in the client namespace
     pub fn client_insert_new(&mut self, object: Object<Data>) -> u32 {
-        insert_in(&mut self.client_objects, object) + 1
+        let entry = self.make_entry(object);
+        self.len += 1;
+        insert_in(&mut self.client_objects, entry) + 1
     }
 
     /// Allocate a new id for an object in the server namespace
     pub fn server_insert_new(&mut self, object: Object<Data>) -> u32 {
-        insert_in(&mut self.server_objects, object) + SERVER_ID_LIMIT
+        let entry = self.make_entry(object);
+        self.len += 1;
+        insert_in(&mut self.server_objects, entry) + SERVER_ID_LIMIT
     }
 
-    /// Mutably access an object of the map
-    pub fn with<T, F: FnOnce(&mut Object<Data>) -> T>(&mut self, id: u32, f: F) -> Result<T, ()> {
-        if id == 0 {
-            Err(())
-        } else if id >= SERVER_ID_LIMIT {
-            if let Some(&mut Some(ref mut obj)) =
-                self.server_objects.get_mut((id - SERVER_ID_LIMIT) as usize)
-            {
-                Ok(f(obj))
-            } else {
-                Err(())
+    /// Mutably access the data of an object of the map
+    pub fn with<T, F: FnOnce(&mut Data) -> T>(&mut self, id: u32, f: F) -> Result<T, ()> {
+        let entry = self.entry(id).ok_or(())?;
+        Ok(f(self.data[entry.data as usize].as_mut().unwrap()))
+    }
+
+    /// Bytes allocated by the store, not counting the heap allocations of the data of the objects
+    pub fn heap_size(&self) -> usize {
+        (self.client_objects.capacity() + self.server_objects.capacity())
+            * std::mem::size_of::<Option<Entry>>()
+            + self.interfaces.capacity() * std::mem::size_of::<&'static Interface>()
+            + self.data.capacity() * std::mem::size_of::<Option<Data>>()
+            + self.free_data.capacity() * std::mem::size_of::<u32>()
+    }
+
+    /// Release the memory of the store which is not used by its current objects
+    ///
+    /// The free ids at the end of the client namespace are only released if `trim_client_ids` is
+    /// set. This must only be done by the client: the server could otherwise refuse a new id
+    /// allocated by the client after an object the server already forgot about.
+    pub fn shrink_to_fit(&mut self, trim_client_ids: bool) {
+        if trim_client_ids {
+            while let Some(None) = self.client_objects.last() {
+                self.client_objects.pop();
             }
-        } else if let Some(&mut Some(ref mut obj)) = self.client_objects.get_mut((id - 1) as usize)
-        {
-            Ok(f(obj))
-        } else {
-            Err(())
         }
+        while let Some(None) = self.data.last() {
+            self.data.pop();
+        }
+        let data_len = self.data.len() as u32;
+        self.free_data.retain(|&index| index < data_len);
+        self.client_objects.shrink_to_fit();
+        self.server_objects.shrink_to_fit();
+        self.data.shrink_to_fit();
+        self.free_data.shrink_to_fit();
     }
 
-    pub fn all_objects(&self) -> impl Iterator<Item = (u32, &Object<Data>)> {
+    pub fn all_objects(&self) -> impl Iterator<Item = (u32, Object<&Data>)> {
         let client_side_iter = self
             .client_objects
             .iter()
             .enumerate()
-            .flat_map(|(idx, obj)| obj.as_ref().map(|obj| (idx as u32 + 1, obj)));
+            .flat_map(|(idx, entry)| entry.map(|entry| (idx as u32 + 1, entry)));
 
         let server_side_iter = self
             .server_objects
             .iter()
             .enumerate()
-            .flat_map(|(idx, obj)| obj.as_ref().map(|obj| (idx as u32 + SERVER_ID_LIMIT, obj)));
+            .flat_map(|(idx, entry)| entry.map(|entry| (idx as u32 + SERVER_ID_LIMIT, entry)));
 
-        client_side_iter.chain(server_side_iter)
+        client_side_iter.chain(server_side_iter).map(move |(id, entry)| {
+            let object = Object {
+                interface: self.interfaces[entry.interface as usize],
+                version: entry.version,
+                data: self.data[entry.data as usize].as_ref().unwrap(),
+            };
+            (id, object)
+        })
+    }
+
+    fn entry(&self, id: u32) -> Option<Entry> {
+        if id == 0 {
+            None
+        } else if id >= SERVER_ID_LIMIT {
+            self.server_objects.get((id - SERVER_ID_LIMIT) as usize).copied().flatten()
+        } else {
+            self.client_objects.get((id - 1) as usize).copied().flatten()
+        }
+    }
+
+    // intern the interface and store the data of an object
+    fn make_entry(&mut self, object: Object<Data>) -> Entry {
+        let interface =
+            match self.interfaces.iter().position(|&i| std::ptr::eq(i, object.interface)) {
+                Some(index) => index,
+                None => {
+                    self.interfaces.push(object.interface);
+                    self.interfaces.len() - 1
+                }
+            };
+        let data = match self.free_data.pop() {
+            Some(index) => {
+                self.data[index as usize] = Some(object.data);
+                index
+            }
+            None => {
+                self.data.push(Some(object.data));
+                (self.data.len() - 1) as u32
+            }
+        };
+        Entry { interface: interface as u16, version: object.version, data }
     }
 }
 
 // insert a new object in a store at the first free place
-fn insert_in<Data>(store: &mut Vec<Option<Object<Data>>>, object: Object<Data>) -> u32 {
+fn insert_in(store: &mut Vec<Option<Entry>>, entry: Entry) -> u32 {
     match store.iter().position(Option::is_none) {
         Some(id) => {
-            store[id] = Some(object);
+            store[id] = Some(entry);
             id as u32
         }
         None => {
-            store.push(Some(object));
+            store.push(Some(entry));
             (store.len() - 1) as u32
         }
     }
 }
 
-// insert an object at a given place in a store
-fn insert_in_at<Data>(
-    store: &mut Vec<Option<Object<Data>>>,
-    id: usize,
-    object: Object<Data>,
-) -> Result<(), ()> {
-    match id.cmp(&store.len()) {
-        Ordering::Greater => Err(()),
-        Ordering::Equal => {
-            store.push(Some(object));
-            Ok(())
-        }
-        Ordering::Less => {
-            let previous = &mut store[id];
-            if !previous.is_none() {
-                return Err(());
-            }
-            *previous = Some(object);
-            Ok(())
-        }
+// insert an object at a given place in a store, which was checked to be free
+fn insert_in_at(store: &mut Vec<Option<Entry>>, id: usize, entry: Entry) {
+    if id == store.len() {
+        store.push(Some(entry));
+    } else {
+        store[id] = Some(entry);
+    }
+}
+
+/// The destruction hooks registered on the objects of a connection
+///
+/// Hooks are stored by protocol id and serial, in the order they were registered, so that the
+/// hooks of an object are never run for another object reusing its id.
+pub struct DestructionHooks<F: ?Sized> {
+    hooks: HashMap<(u32, u32), Vec<Box<F>>>,
+}
+
+impl<F: ?Sized> DestructionHooks<F> {
+    /// Create a new empty hook store
+    pub fn new() -> DestructionHooks<F> {
+        DestructionHooks { hooks: HashMap::new() }
+    }
+
+    /// Register a new hook for given object id and serial
+    pub fn insert(&mut self, id: u32, serial: u32, hook: Box<F>) {
+        self.hooks.entry((id, serial)).or_default().push(hook);
+    }
+
+    /// Remove all the hooks of an object, in registration order
+    pub fn take(&mut self, id: u32, serial: u32) -> Vec<Box<F>> {
+        self.hooks.remove(&(id, serial)).unwrap_or_default()
+    }
+
+    /// Release the memory not used by the current hooks
+    pub fn shrink_to_fit(&mut self) {
+        self.hooks.shrink_to_fit();
+    }
+}
+
+impl<F: ?Sized> Default for DestructionHooks<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<F: ?Sized> fmt::Debug for DestructionHooks<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DestructionHooks").field("objects", &self.hooks.len()).finish()
     }
 }
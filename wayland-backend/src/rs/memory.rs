@@ -0,0 +1,32 @@
+//! Accounting of the memory used by the backends
+
+/// The memory used by a backend to track the state of its connections
+///
+/// It is given by the `memory_usage()` method of the `Handle` of the rust backends. The sizes are
+/// in bytes, and only account for the allocations of the backend itself: the object data set by
+/// the application is not included. They are summed over all the clients for the server backend.
+///
+/// The object maps grow with the number of live objects and keep their size afterwards, this
+/// memory can be released after a burst of objects with `shrink_to_fit()` on the `Backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Bytes allocated for the incoming and outgoing buffers of the sockets
+    pub socket_buffers: usize,
+    /// Bytes of the received messages waiting in the incoming buffers to be dispatched
+    pub queued_messages: usize,
+    /// Bytes allocated for the maps of the objects
+    pub object_map: usize,
+    /// Number of objects in the maps
+    pub objects: usize,
+    /// Number of objects destroyed by the client, and kept until the server acknowledges it
+    ///
+    /// Always `0` for the server backend, which forgets the objects as soon as they are destroyed.
+    pub zombie_objects: usize,
+}
+
+impl MemoryUsage {
+    /// Total number of bytes allocated by the backend
+    pub fn total(&self) -> usize {
+        self.socket_buffers + self.object_map
+    }
+}
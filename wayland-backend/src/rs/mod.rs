@@ -3,7 +3,17 @@
 pub mod client;
 pub mod server;
 
+#[cfg(all(feature = "capi", not(test)))]
+pub mod capi;
+
 mod debug;
-mod map;
+pub(crate) mod map;
+mod memory;
+pub mod proxy;
+pub mod shaping;
+pub mod shim;
 pub(crate) mod socket;
 mod wire;
+
+pub use memory::MemoryUsage;
+pub use socket::{WaylandTransport, MAX_BYTES_OUT, MAX_FDS_OUT};
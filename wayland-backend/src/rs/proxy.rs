@@ -0,0 +1,280 @@
+//! Relaying of a Wayland connection
+//!
+//! A [`ProxyConnection`] sits between a client and an upstream compositor: it relays the requests
+//! of the client to the compositor and the events of the compositor to the client, giving a
+//! [`ProxyFilter`] the opportunity to inspect, rewrite or drop each message on the way. This is
+//! the building block for security sandboxes, protocol downgraders or debugging tools.
+//!
+//! To be able to parse the messages, the proxy tracks the objects of the connection. It thus needs
+//! to know all interfaces the client can bind from the registry: binding an interface that was not
+//! given to [`ProxyConnection::new()`] is an error.
+//!
+//! The proxy is driven by the application, which accepts the client connections (for example on a
+//! [`UnixListener`](std::os::unix::net::UnixListener)) and connects to the upstream compositor
+//! itself, then polls both file descriptors and invokes [`ProxyConnection::relay_requests()`] or
+//! [`ProxyConnection::relay_events()`] accordingly.
+
+use std::{
+    ffi::CStr,
+    io::{Error, ErrorKind, Result as IoResult},
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::UnixStream,
+    },
+};
+
+use smallvec::SmallVec;
+
+use crate::{
+    core_interfaces::WL_DISPLAY_INTERFACE,
+    protocol::{Argument, Interface, Message, MessageDesc, MessageDirection, ObjectInfo},
+};
+
+use super::{
+    map::{Object, ObjectMap, SERVER_ID_LIMIT},
+    socket::BufferedSocket,
+    wire::MessageParseError,
+};
+
+/// Hooks invoked by a [`ProxyConnection`] on each relayed message
+///
+/// Each method receives the message and the object it was sent by, and returns the message to
+/// forward, or `None` to drop it. The default implementations forward all messages unchanged.
+///
+/// The arguments of the message may be modified, but doing so must keep the message valid for
+/// its signature. The objects created by the message are tracked as sent, before filtering, so
+/// the `new_id` arguments and the interfaces they refer to must not be changed. The file
+/// descriptors of dropped messages are closed by the proxy.
+pub trait ProxyFilter {
+    /// Filter a request sent by the client
+    fn request(&mut self, _object: ObjectInfo, msg: Message<u32>) -> Option<Message<u32>> {
+        Some(msg)
+    }
+
+    /// Filter an event sent by the compositor
+    fn event(&mut self, _object: ObjectInfo, msg: Message<u32>) -> Option<Message<u32>> {
+        Some(msg)
+    }
+
+    /// A message to send after the last filtered one
+    ///
+    /// It is invoked after each filtered message until it returns `None`, whether the filtered
+    /// message was dropped or not. The returned messages are sent in the same direction as the
+    /// filtered message, and must not create objects. The default implementation returns `None`.
+    fn synthesized(&mut self) -> Option<Message<u32>> {
+        None
+    }
+}
+
+/// A [`ProxyFilter`] forwarding all messages unchanged
+#[derive(Debug)]
+pub struct ForwardAll;
+
+impl ProxyFilter for ForwardAll {}
+
+/// A connection relayed between a client and an upstream compositor
+#[derive(Debug)]
+pub struct ProxyConnection<F> {
+    client: BufferedSocket,
+    server: BufferedSocket,
+    map: ObjectMap<()>,
+    interfaces: Vec<&'static Interface>,
+    filter: F,
+}
+
+impl<F: ProxyFilter> ProxyConnection<F> {
+    /// Create a new proxy connection
+    ///
+    /// `client` is the connection with the client, and `server` the connection with the upstream
+    /// compositor. `interfaces` lists the interfaces the client may bind from the registry.
+    pub fn new(
+        client: UnixStream,
+        server: UnixStream,
+        interfaces: &[&'static Interface],
+        filter: F,
+    ) -> ProxyConnection<F> {
+        let mut map = ObjectMap::new();
+        map.insert_at(1, Object { interface: &WL_DISPLAY_INTERFACE, version: 1, data: () })
+            .unwrap();
+        ProxyConnection {
+            client: BufferedSocket::new(client),
+            server: BufferedSocket::new(server),
+            map,
+            interfaces: interfaces.to_vec(),
+            filter,
+        }
+    }
+
+    /// Access the filter of this connection
+    pub fn filter(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// The file descriptor of the connection with the client, for polling
+    pub fn client_fd(&self) -> RawFd {
+        self.client.as_raw_fd()
+    }
+
+    /// The file descriptor of the connection with the compositor, for polling
+    pub fn server_fd(&self) -> RawFd {
+        self.server.as_raw_fd()
+    }
+
+    /// Relay the pending requests of the client to the compositor
+    ///
+    /// Returns the number of requests read from the client, including the ones dropped by the
+    /// filter. The relayed requests are buffered, use [`flush()`](ProxyConnection::flush) to
+    /// send them.
+    pub fn relay_requests(&mut self) -> IoResult<usize> {
+        self.relay(MessageDirection::Request)
+    }
+
+    /// Relay the pending events of the compositor to the client
+    ///
+    /// Returns the number of events read from the compositor, including the ones dropped by the
+    /// filter. The relayed events are buffered, use [`flush()`](ProxyConnection::flush) to
+    /// send them.
+    pub fn relay_events(&mut self) -> IoResult<usize> {
+        self.relay(MessageDirection::Event)
+    }
+
+    /// Flush the relayed messages to the client and the compositor
+    pub fn flush(&mut self) -> IoResult<()> {
+        self.server.flush()?;
+        self.client.flush()
+    }
+
+    fn relay(&mut self, direction: MessageDirection) -> IoResult<usize> {
+        let mut relayed = 0;
+        loop {
+            let map = &self.map;
+            let (source, dest) = match direction {
+                MessageDirection::Request => (&mut self.client, &mut self.server),
+                MessageDirection::Event => (&mut self.server, &mut self.client),
+            };
+            let msg = match source.read_one_message(|id, opcode| {
+                map.find(id)
+                    .and_then(|o| messages(o.interface, direction).get(opcode as usize))
+                    .map(|desc| desc.signature)
+            }) {
+                Ok(msg) => msg,
+                Err(MessageParseError::MissingData) | Err(MessageParseError::MissingFD) => {
+                    match source.fill_incoming_buffers() {
+                        Ok(()) => continue,
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(relayed),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Err(MessageParseError::Malformed) => {
+                    return Err(nix::errno::Errno::EPROTO.into());
+                }
+            };
+            relayed += 1;
+
+            let object = map.find(msg.sender_id).unwrap();
+            let desc = &messages(object.interface, direction)[msg.opcode as usize];
+            // the objects are tracked as created by the sending side, whether the message is
+            // forwarded or not, as it may keep using them
+            track_objects(&mut self.map, &self.interfaces, &msg, desc, object.version)?;
+
+            let info = ObjectInfo {
+                id: msg.sender_id,
+                interface: object.interface,
+                version: object.version,
+                serial: 0,
+                alive: true,
+            };
+            let mut fds = message_fds(&msg);
+            let forwarded = match direction {
+                MessageDirection::Request => self.filter.request(info, msg),
+                MessageDirection::Event => self.filter.event(info, msg),
+            };
+            let mut result = match forwarded {
+                Some(msg) => {
+                    fds.extend(message_fds(&msg));
+                    dest.write_message(&msg)
+                }
+                None => Ok(()),
+            };
+            while let Some(msg) = self.filter.synthesized() {
+                fds.extend(message_fds(&msg));
+                if result.is_ok() {
+                    result = dest.write_message(&msg);
+                }
+            }
+            // the fds are dup-ed when written, the received ones need to be closed
+            fds.sort_unstable();
+            fds.dedup();
+            for fd in fds {
+                let _ = nix::unistd::close(fd);
+            }
+            result?;
+        }
+    }
+}
+
+fn track_objects(
+    map: &mut ObjectMap<()>,
+    interfaces: &[&'static Interface],
+    msg: &Message<u32>,
+    desc: &MessageDesc,
+    parent_version: u32,
+) -> IoResult<()> {
+    for (i, arg) in msg.args.iter().enumerate() {
+        if let Argument::NewId(id) = *arg {
+            let (interface, version) = match desc.child_interface {
+                Some(interface) => (interface, parent_version),
+                // a generic new_id is preceded by the interface name and version
+                None => match msg.args[..i] {
+                    [.., Argument::Str(ref name), Argument::Uint(version)] => {
+                        (find_interface(interfaces, name)?, version)
+                    }
+                    _ => return Err(nix::errno::Errno::EPROTO.into()),
+                },
+            };
+            if map.insert_at(id, Object { interface, version, data: () }).is_err() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid new_id {} for interface {}.", id, interface.name),
+                ));
+            }
+        }
+    }
+
+    if msg.sender_id == 1 && desc.name == "delete_id" {
+        if let [Argument::Uint(id)] = msg.args[..] {
+            map.remove(id);
+        }
+    } else if desc.is_destructor && msg.sender_id >= SERVER_ID_LIMIT {
+        // client-created objects are only removed once the compositor acknowledges their
+        // destruction with a delete_id event
+        map.remove(msg.sender_id);
+    }
+    Ok(())
+}
+
+fn find_interface(interfaces: &[&'static Interface], name: &CStr) -> IoResult<&'static Interface> {
+    interfaces.iter().copied().find(|i| i.name.as_bytes() == name.to_bytes()).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Unknown interface {} bound through the proxy.", name.to_string_lossy()),
+        )
+    })
+}
+
+fn messages(interface: &'static Interface, direction: MessageDirection) -> &'static [MessageDesc] {
+    match direction {
+        MessageDirection::Request => interface.requests,
+        MessageDirection::Event => interface.events,
+    }
+}
+
+fn message_fds(msg: &Message<u32>) -> SmallVec<[RawFd; 4]> {
+    msg.args
+        .iter()
+        .filter_map(|arg| match *arg {
+            Argument::Fd(fd) => Some(fd),
+            _ => None,
+        })
+        .collect()
+}
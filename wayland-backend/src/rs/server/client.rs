@@ -10,19 +10,21 @@ use std::{
 use crate::{
     core_interfaces::{WL_CALLBACK_INTERFACE, WL_DISPLAY_INTERFACE, WL_REGISTRY_INTERFACE},
     protocol::{
-        check_for_signature, same_interface, same_interface_or_anonymous, AllowNull, Argument,
-        ArgumentType, Interface, Message, ObjectInfo, ProtocolError, ANONYMOUS_INTERFACE,
+        check_arguments, compare_signature, same_interface, same_interface_or_anonymous, AllowNull,
+        Argument, ArgumentType, ConformanceHook, ConformanceViolation, Direction, Interface,
+        Message, MessageDirection, ObjectInfo, ProtocolError, StatsRecorder, ANONYMOUS_INTERFACE,
         INLINE_ARGS,
     },
-    types::server::{DisconnectReason, InvalidId},
+    types::server::{DisconnectHook, DisconnectReason, InvalidId},
 };
 
 use smallvec::SmallVec;
 
 use crate::rs::{
-    map::{Object, ObjectMap},
+    map::{DestructionHooks, Object, ObjectMap},
     socket::{BufferedSocket, Socket},
     wire::MessageParseError,
+    MemoryUsage,
 };
 
 use super::{
@@ -45,11 +47,15 @@ pub(crate) enum DisplayError {
 pub(crate) struct Client<D> {
     socket: BufferedSocket,
     pub(crate) map: ObjectMap<Data<D>>,
+    destruction_hooks: DestructionHooks<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
     debug: bool,
     last_serial: u32,
     pub(crate) id: ClientId,
     pub(crate) killed: bool,
     pub(crate) data: Arc<dyn ClientData<D>>,
+    disconnect_hook: DisconnectHook<ClientId>,
+    conformance_hook: ConformanceHook<ClientId>,
+    stats: StatsRecorder,
 }
 
 impl<D> Client<D> {
@@ -65,6 +71,9 @@ impl<D> Client<D> {
         id: ClientId,
         debug: bool,
         data: Arc<dyn ClientData<D>>,
+        disconnect_hook: DisconnectHook<ClientId>,
+        conformance_hook: ConformanceHook<ClientId>,
+        stats: StatsRecorder,
     ) -> Self {
         let socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(stream.into_raw_fd()) });
         let mut map = ObjectMap::new();
@@ -80,7 +89,19 @@ impl<D> Client<D> {
 
         data.initialized(id.clone());
 
-        Client { socket, map, debug, id, killed: false, last_serial: 0, data }
+        Client {
+            socket,
+            map,
+            destruction_hooks: DestructionHooks::new(),
+            debug,
+            id,
+            killed: false,
+            last_serial: 0,
+            data,
+            disconnect_hook,
+            conformance_hook,
+            stats,
+        }
     }
 
     pub(crate) fn create_object(
@@ -98,9 +119,33 @@ impl<D> Client<D> {
         ObjectId { id, serial, client_id: self.id.clone(), interface }
     }
 
+    /// Destroy an object, notifying its data and running its destruction hooks
+    ///
+    /// If the client knows about the object, its ID is only freed after sending it
+    /// `wl_display.delete_id`, otherwise it is freed right away.
+    pub(crate) fn destroy_object(&mut self, id: ObjectId, known_by_client: bool) {
+        let object = match self.get_object(id.clone()) {
+            Ok(object) => object,
+            Err(_) => return,
+        };
+        object.data.user_data.destroyed(self.id.clone(), id.clone());
+        self.run_destruction_hooks(id.clone());
+        if known_by_client {
+            self.send_delete_id(id);
+        } else {
+            self.map.remove(id.id);
+        }
+    }
+
     pub(crate) fn object_info(&self, id: ObjectId) -> Result<ObjectInfo, InvalidId> {
         let object = self.get_object(id.clone())?;
-        Ok(ObjectInfo { id: id.id, interface: object.interface, version: object.version })
+        Ok(ObjectInfo {
+            id: id.id,
+            interface: object.interface,
+            version: object.version,
+            serial: id.serial,
+            alive: true,
+        })
     }
 
     pub(crate) fn send_event(
@@ -122,23 +167,10 @@ impl<D> Client<D> {
             }
         };
 
-        if !check_for_signature(message_desc.signature, &args) {
+        if let Err(mismatch) = compare_signature(message_desc, &args) {
             panic!(
-                "Unexpected signature for event {}@{}.{}: expected {:?}, got {:?}.",
-                object.interface.name,
-                object_id.id,
-                message_desc.name,
-                message_desc.signature,
-                args
-            );
-        }
-
-        if self.debug {
-            crate::rs::debug::print_send_message(
-                object.interface.name,
-                object_id.id,
-                message_desc.name,
-                &args,
+                "Unexpected signature for event {}@{}: {}",
+                object.interface.name, object_id.id, mismatch
             );
         }
 
@@ -168,7 +200,7 @@ impl<D> Client<D> {
                     } else if !matches!(message_desc.signature[i], ArgumentType::NewId(AllowNull::Yes)) {
                         panic!("Request {}@{}.{} expects an non-null newid argument.", object.interface.name, object_id.id, message_desc.name);
                     }
-                    Argument::Object(o.id)
+                    Argument::NewId(o.id)
                 },
                 Argument::Object(o) => {
                     if o.id != 0 {
@@ -190,15 +222,18 @@ impl<D> Client<D> {
 
         let msg = Message { sender_id: object_id.id, opcode, args: msg_args };
 
+        if self.debug {
+            crate::rs::debug::print_send_message(&msg, object.interface, MessageDirection::Event);
+        }
+
+        self.stats.record(Direction::Sent, object.interface.name, message_desc.name, &msg);
         if self.socket.write_message(&msg).is_err() {
             self.kill(DisconnectReason::ConnectionClosed);
         }
 
         // Handle destruction if relevant
         if message_desc.is_destructor {
-            self.map.remove(object_id.id);
-            object.data.user_data.destroyed(self.id.clone(), object_id.clone());
-            self.send_delete_id(object_id);
+            self.destroy_object(object_id, true);
         }
 
         Ok(())
@@ -206,6 +241,12 @@ impl<D> Client<D> {
 
     pub(crate) fn send_delete_id(&mut self, object_id: ObjectId) {
         let msg = message!(1, 1, [Argument::Uint(object_id.id)]);
+        self.stats.record(
+            Direction::Sent,
+            WL_DISPLAY_INTERFACE.name,
+            WL_DISPLAY_INTERFACE.events[1].name,
+            &msg,
+        );
         if self.socket.write_message(&msg).is_err() {
             self.kill(DisconnectReason::ConnectionClosed);
         }
@@ -227,16 +268,32 @@ impl<D> Client<D> {
     ) -> Result<(), InvalidId> {
         self.map
             .with(id.id, |objdata| {
-                if objdata.data.serial != id.serial {
+                if objdata.serial != id.serial {
                     Err(InvalidId)
                 } else {
-                    objdata.data.user_data = data;
+                    objdata.user_data = data;
                     Ok(())
                 }
             })
             .unwrap_or(Err(InvalidId))
     }
 
+    pub(crate) fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        self.get_object(id.clone())?;
+        self.destruction_hooks.insert(id.id, id.serial, hook);
+        Ok(())
+    }
+
+    fn run_destruction_hooks(&mut self, id: ObjectId) {
+        for hook in self.destruction_hooks.take(id.id, id.serial) {
+            hook(self.id.clone(), id.clone());
+        }
+    }
+
     pub(crate) fn post_display_error(&mut self, code: DisplayError, message: CString) {
         self.post_error(
             ObjectId {
@@ -294,7 +351,12 @@ impl<D> Client<D> {
     }
 
     pub(crate) fn kill(&mut self, reason: DisconnectReason) {
+        // only notify the first reason of the disconnection
+        if self.killed {
+            return;
+        }
         self.killed = true;
+        self.disconnect_hook.invoke(self.id.clone(), &reason);
         self.data.disconnected(self.id.clone(), reason);
     }
 
@@ -302,6 +364,21 @@ impl<D> Client<D> {
         self.socket.flush()
     }
 
+    pub(crate) fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            socket_buffers: self.socket.buffers_size(),
+            queued_messages: self.socket.queued_bytes(),
+            object_map: self.map.heap_size(),
+            objects: self.map.len(),
+            zombie_objects: 0,
+        }
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit(false);
+        self.destruction_hooks.shrink_to_fit();
+    }
+
     pub(crate) fn all_objects(&self) -> impl Iterator<Item = ObjectId> + '_ {
         let client_id = self.id.clone();
         self.map.all_objects().map(move |(id, obj)| ObjectId {
@@ -329,6 +406,8 @@ impl<D> Client<D> {
                     if let Err(e) = self.socket.fill_incoming_buffers() {
                         if e.kind() != std::io::ErrorKind::WouldBlock {
                             self.kill(DisconnectReason::ConnectionClosed);
+                        } else {
+                            self.check_unexpected_fds();
                         }
                         return Err(e);
                     }
@@ -341,10 +420,30 @@ impl<D> Client<D> {
             };
 
             let obj = self.map.find(msg.sender_id).unwrap();
+            let desc = &obj.interface.requests[msg.opcode as usize];
+            self.stats.record(Direction::Received, obj.interface.name, desc.name, &msg);
+            if self.conformance_hook.is_enabled() {
+                check_arguments(obj.interface, desc, &msg, |violation| {
+                    self.conformance_hook.invoke(self.id.clone(), &violation)
+                });
+            }
             return Ok((msg, obj));
         }
     }
 
+    // file descriptors left once all the received data is parsed were sent without a message
+    // using them, they are reported and closed
+    fn check_unexpected_fds(&mut self) {
+        if self.socket.queued_bytes() == 0
+            && self.socket.queued_fds() > 0
+            && self.conformance_hook.is_enabled()
+        {
+            let count = self.socket.discard_queued_fds();
+            let violation = ConformanceViolation::UnexpectedFds { count };
+            self.conformance_hook.invoke(self.id.clone(), &violation);
+        }
+    }
+
     fn get_object(&self, id: ObjectId) -> Result<Object<Data<D>>, InvalidId> {
         let object = self.map.find(id.id).ok_or(InvalidId)?;
         if object.data.serial != id.serial {
@@ -365,15 +464,16 @@ impl<D> Client<D> {
 
     fn destroy_all_objects(&mut self) {
         for (id, obj) in self.map.all_objects() {
-            obj.data.user_data.destroyed(
-                self.id.clone(),
-                ObjectId {
-                    id,
-                    serial: obj.data.serial,
-                    client_id: self.id.clone(),
-                    interface: obj.interface,
-                },
-            );
+            let object_id = ObjectId {
+                id,
+                serial: obj.data.serial,
+                client_id: self.id.clone(),
+                interface: obj.interface,
+            };
+            obj.data.user_data.destroyed(self.id.clone(), object_id.clone());
+            for hook in self.destruction_hooks.take(id, obj.data.serial) {
+                hook(self.id.clone(), object_id.clone());
+            }
         }
     }
 
@@ -640,11 +740,21 @@ pub(crate) struct ClientStore<D> {
     clients: Vec<Option<Client<D>>>,
     last_serial: u32,
     debug: bool,
+    pub(crate) disconnect_hook: DisconnectHook<ClientId>,
+    pub(crate) conformance_hook: ConformanceHook<ClientId>,
+    pub(crate) stats: StatsRecorder,
 }
 
 impl<D> ClientStore<D> {
     pub(crate) fn new(debug: bool) -> Self {
-        ClientStore { clients: Vec::new(), last_serial: 0, debug }
+        ClientStore {
+            clients: Vec::new(),
+            last_serial: 0,
+            debug,
+            disconnect_hook: DisconnectHook::new(),
+            conformance_hook: ConformanceHook::new(),
+            stats: StatsRecorder::new("server"),
+        }
     }
 
     pub(crate) fn create_client(
@@ -664,7 +774,15 @@ impl<D> ClientStore<D> {
 
         let id = ClientId { id: id as u32, serial };
 
-        *place = Some(Client::new(stream, id.clone(), self.debug, data));
+        *place = Some(Client::new(
+            stream,
+            id.clone(),
+            self.debug,
+            data,
+            self.disconnect_hook.clone(),
+            self.conformance_hook.clone(),
+            self.stats.clone(),
+        ));
 
         id
     }
@@ -702,6 +820,29 @@ impl<D> ClientStore<D> {
         self.last_serial
     }
 
+    pub(crate) fn memory_usage(&self) -> MemoryUsage {
+        self.clients.iter().flatten().map(Client::memory_usage).fold(
+            MemoryUsage::default(),
+            |total, usage| MemoryUsage {
+                socket_buffers: total.socket_buffers + usage.socket_buffers,
+                queued_messages: total.queued_messages + usage.queued_messages,
+                object_map: total.object_map + usage.object_map,
+                objects: total.objects + usage.objects,
+                zombie_objects: total.zombie_objects + usage.zombie_objects,
+            },
+        )
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        while let Some(None) = self.clients.last() {
+            self.clients.pop();
+        }
+        self.clients.shrink_to_fit();
+        for client in self.clients.iter_mut().flatten() {
+            client.shrink_to_fit();
+        }
+    }
+
     pub(crate) fn clients_mut(&mut self) -> impl Iterator<Item = &mut Client<D>> {
         self.clients.iter_mut().flat_map(|o| o.as_mut()).filter(|c| !c.killed)
     }
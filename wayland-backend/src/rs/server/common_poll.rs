@@ -123,6 +123,16 @@ impl<D> Backend<D> {
         self.poll_fd
     }
 
+    /// Release the memory kept by the backend after a burst of objects or clients
+    ///
+    /// The object maps keep the size they grew to when many objects were alive at the same time,
+    /// this gives back the memory not needed by the current objects and clients. See
+    /// [`Handle::memory_usage()`] for the memory used by the backend. This method is only
+    /// available on the rust backend.
+    pub fn shrink_to_fit(&mut self) {
+        self.handle.clients.shrink_to_fit();
+    }
+
     /// Dispatches all pending messages from the specified client.
     ///
     /// This method will not block if there are no pending messages.
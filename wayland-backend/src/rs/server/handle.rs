@@ -2,14 +2,17 @@ use std::{ffi::CString, sync::Arc};
 
 use crate::{
     core_interfaces::{WL_DISPLAY_INTERFACE, WL_REGISTRY_INTERFACE},
-    protocol::{same_interface, Argument, Interface, Message, ObjectInfo, ANONYMOUS_INTERFACE},
+    protocol::{
+        same_interface, Argument, ConformanceViolation, Interface, Message, ObjectInfo,
+        ProtocolStats, ANONYMOUS_INTERFACE,
+    },
     types::server::{DisconnectReason, GlobalInfo, InvalidId},
 };
 use smallvec::SmallVec;
 
 use super::{
     client::ClientStore, registry::Registry, ClientData, ClientId, Credentials, Data,
-    GlobalHandler, GlobalId, ObjectData, ObjectId,
+    GlobalHandler, GlobalId, MemoryUsage, ObjectData, ObjectId,
 };
 use crate::rs::map::Object;
 
@@ -130,9 +133,9 @@ impl<D> Handle<D> {
                         Message { sender_id: object_id.clone(), opcode, args: arguments },
                     );
                     if is_destructor {
-                        object.data.user_data.destroyed(client_id.clone(), object_id.clone());
-                        if let Ok(client) = self.clients.get_client_mut(client_id.clone()) {
-                            client.send_delete_id(object_id);
+                        match self.clients.get_client_mut(client_id.clone()) {
+                            Ok(client) => client.destroy_object(object_id, true),
+                            Err(_) => object.data.user_data.destroyed(client_id.clone(), object_id),
                         }
                     }
                     match (created_id, ret) {
@@ -140,7 +143,7 @@ impl<D> Handle<D> {
                             if let Ok(client) = self.clients.get_client_mut(client_id.clone()) {
                                 client
                                     .map
-                                    .with(child_id.id, |obj| obj.data.user_data = child_data)
+                                    .with(child_id.id, |data| data.user_data = child_data)
                                     .unwrap();
                             }
                         }
@@ -160,7 +163,7 @@ impl<D> Handle<D> {
                     let child_data =
                         handler.bind(self, data, client.clone(), global, object.clone());
                     if let Ok(client) = self.clients.get_client_mut(client.clone()) {
-                        client.map.with(object.id, |obj| obj.data.user_data = child_data).unwrap();
+                        client.map.with(object.id, |data| data.user_data = child_data).unwrap();
                     }
                 }
             }
@@ -224,6 +227,16 @@ impl<D> Handle<D> {
         Ok(Box::new(client.all_objects()))
     }
 
+    /// Returns an iterator over the objects of given interface owned by a client.
+    pub fn objects_of<'a>(
+        &'a self,
+        client_id: ClientId,
+        interface: &'static Interface,
+    ) -> Result<Box<dyn Iterator<Item = ObjectId> + 'a>, InvalidId> {
+        let client = self.clients.get_client(client_id)?;
+        Ok(Box::new(client.all_objects().filter(move |id| same_interface(id.interface, interface))))
+    }
+
     /// Retrieve the `ObjectId` for a wayland object given its protocol numerical ID
     pub fn object_for_protocol_id(
         &self,
@@ -255,6 +268,49 @@ impl<D> Handle<D> {
         Ok(client.create_object(interface, version, data))
     }
 
+    /// Create a new object for given client and send the event introducing it
+    ///
+    /// The `event` closure is given the ID of the created object and builds the event carrying it
+    /// as a "New ID" argument. If it fails, or if the event cannot be sent because its sender is
+    /// not a valid object of the same client, the object is destroyed before returning the error,
+    /// so that it never exists only on the server side.
+    ///
+    /// **Panic:**
+    ///
+    /// This method panics if the event does not have the created object as a "New ID" argument,
+    /// in addition to the checks of [`send_event()`](Handle::send_event).
+    pub fn create_object_with_event<F>(
+        &mut self,
+        client_id: ClientId,
+        interface: &'static Interface,
+        version: u32,
+        data: Arc<dyn ObjectData<D>>,
+        event: F,
+    ) -> Result<ObjectId, InvalidId>
+    where
+        F: FnOnce(&mut Self, ObjectId) -> Result<Message<ObjectId>, InvalidId>,
+    {
+        let id = self.create_object(client_id, interface, version, data)?;
+        let ret = event(self, id.clone()).and_then(|msg| {
+            if !msg.args.iter().any(|arg| matches!(arg, Argument::NewId(o) if *o == id)) {
+                panic!("The event introducing {}@{} does not carry it.", interface.name, id.id);
+            }
+            if msg.sender_id.client_id != id.client_id {
+                return Err(InvalidId);
+            }
+            self.send_event(msg)
+        });
+        match ret {
+            Ok(()) => Ok(id),
+            Err(e) => {
+                if let Ok(client) = self.clients.get_client_mut(id.client_id.clone()) {
+                    client.destroy_object(id, false);
+                }
+                Err(e)
+            }
+        }
+    }
+
     /// Returns an object id that represents a null object.
     pub fn null_id(&mut self) -> ObjectId {
         ObjectId {
@@ -294,6 +350,21 @@ impl<D> Handle<D> {
         self.clients.get_client_mut(id.client_id.clone())?.set_object_data(id, data)
     }
 
+    /// Register a hook to be invoked when an object is destroyed
+    ///
+    /// The hooks of an object are invoked in registration order, right after the
+    /// [`ObjectData::destroyed()`] method of its object data and before its ID can be reused.
+    /// They are invoked while the backend is borrowed, and thus must not try to access it.
+    ///
+    /// Returns an error if the object ID is not longer valid
+    pub fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        self.clients.get_client_mut(id.client_id.clone())?.add_destruction_hook(id, hook)
+    }
+
     /// Posts an error on an object. This will also disconnect the client which created the object.
     pub fn post_error(&mut self, object_id: ObjectId, error_code: u32, message: CString) {
         if let Ok(client) = self.clients.get_client_mut(object_id.client_id.clone()) {
@@ -310,6 +381,70 @@ impl<D> Handle<D> {
         }
     }
 
+    /// Set the hook invoked when a client is disconnected
+    ///
+    /// It is invoked with the reason of the disconnection, be it a protocol error, an error of the
+    /// connection or its closure, before the [`ClientData::disconnected()`] method of the client
+    /// and before any of its objects is destroyed. It is invoked while the backend is borrowed,
+    /// and thus must not try to access it.
+    ///
+    /// This replaces the previously set hook, if any.
+    #[allow(clippy::type_complexity)]
+    pub fn set_disconnect_hook(
+        &mut self,
+        hook: Box<dyn FnMut(ClientId, &DisconnectReason) + Send>,
+    ) {
+        self.clients.disconnect_hook.set(hook);
+    }
+
+    /// Enable the conformance checks of the requests sent by the clients
+    ///
+    /// Beyond the errors preventing the requests from being parsed, which are always protocol
+    /// errors, the backend then checks that enum arguments have values defined by the protocol,
+    /// that non-nullable object arguments are not null, and that no file descriptor is left once
+    /// all the received data is parsed. The violations are reported to the hook with the client
+    /// which sent them, before the request is processed. These checks are intended for test
+    /// environments, like a mock compositor, as they slow down the dispatching of requests.
+    ///
+    /// The hook is invoked while the backend is borrowed, and thus must not try to access it. This
+    /// replaces the previously set hook, if any. This method is only available on the rust backend.
+    #[allow(clippy::type_complexity)]
+    pub fn set_conformance_hook(
+        &mut self,
+        hook: Box<dyn FnMut(ClientId, &ConformanceViolation) + Send>,
+    ) {
+        self.clients.conformance_hook.set(hook);
+    }
+
+    /// Start recording the statistics of the messages exchanged with the clients
+    ///
+    /// The number and size of the messages of all the clients are then counted for each interface
+    /// and opcode, in both directions. This does nothing if they were already being recorded, for
+    /// example because the `WAYLAND_STATS` environment variable is set, see [`ProtocolStats`] for
+    /// details. This method is only available on the rust backend.
+    pub fn enable_stats(&mut self) {
+        self.clients.stats.enable();
+    }
+
+    /// Get the statistics of the messages exchanged with the clients
+    ///
+    /// Returns `None` if they are not recorded, see [`enable_stats()`](Handle::enable_stats).
+    pub fn stats(&self) -> Option<ProtocolStats> {
+        self.clients.stats.stats()
+    }
+
+    /// Clear the statistics of the messages recorded so far
+    pub fn reset_stats(&mut self) {
+        self.clients.stats.reset();
+    }
+
+    /// Get the memory used by the backend for all the clients
+    ///
+    /// See [`MemoryUsage`] for details. This method is only available on the rust backend.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.clients.memory_usage()
+    }
+
     /// Creates a global of the specified interface and version and then advertises it to clients.
     ///
     /// The clients which the global is advertised to is determined by the implementation of the [`GlobalHandler`].
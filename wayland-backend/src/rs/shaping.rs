@@ -0,0 +1,112 @@
+//! Traffic shaping of a transport, for testing
+//!
+//! A [`ShapedTransport`] wraps a [`WaylandTransport`] and degrades it according to a
+//! [`ShapingConfig`]: it can add latency, cap the bandwidth, split the messages in partial reads
+//! and writes, or periodically report the socket as full. It allows exercising the timeout logic,
+//! backpressure handling and buffering of an application as if it ran over a slow link, without
+//! the need for an actual one.
+//!
+//! The latency and bandwidth are simulated by blocking the sending side for the corresponding
+//! duration, so that a test behaves the same from one run to the next.
+
+use std::{
+    io::{ErrorKind, Result as IoResult},
+    os::unix::io::{AsRawFd, RawFd},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use super::WaylandTransport;
+
+/// The degradations applied by a [`ShapedTransport`]
+///
+/// The default configuration does not alter the transport.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShapingConfig {
+    /// Delay added to each sent message
+    pub latency: Duration,
+    /// Maximum bandwidth of the transport, in bytes per second
+    pub bandwidth: Option<usize>,
+    /// Maximum number of bytes sent at once
+    ///
+    /// Larger messages are only partially written. The limit is rounded down to a multiple of 4
+    /// bytes, as the protocol is made of 32-bit words.
+    pub max_write: Option<usize>,
+    /// Maximum number of bytes received at once
+    ///
+    /// The limit is rounded down to a multiple of 4 bytes.
+    pub max_read: Option<usize>,
+    /// Fail every n-th write with a `WouldBlock` error, as if the socket was full
+    pub block_every: Option<usize>,
+}
+
+/// A transport degraded according to a [`ShapingConfig`]
+///
+/// It can be used with [`Backend::connect_with_transport()`](crate::rs::client::Backend::connect_with_transport).
+#[derive(Debug)]
+pub struct ShapedTransport<T> {
+    inner: T,
+    config: ShapingConfig,
+    writes: AtomicUsize,
+}
+
+impl<T: WaylandTransport> ShapedTransport<T> {
+    /// Wrap a transport
+    pub fn new(inner: T, config: ShapingConfig) -> ShapedTransport<T> {
+        ShapedTransport { inner, config, writes: AtomicUsize::new(0) }
+    }
+
+    /// The configuration of this transport
+    pub fn config(&self) -> &ShapingConfig {
+        &self.config
+    }
+
+    /// Access the wrapped transport
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Retrieve the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for ShapedTransport<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl<T: WaylandTransport> WaylandTransport for ShapedTransport<T> {
+    fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> IoResult<usize> {
+        if let Some(period) = self.config.block_every {
+            let writes = self.writes.fetch_add(1, Ordering::Relaxed) + 1;
+            if writes.checked_rem(period) == Some(0) {
+                return Err(ErrorKind::WouldBlock.into());
+            }
+        }
+        let len = limit(bytes.len(), self.config.max_write);
+        let mut delay = self.config.latency;
+        if let Some(bandwidth) = self.config.bandwidth {
+            delay += Duration::from_secs_f64(len as f64 / bandwidth.max(1) as f64);
+        }
+        if delay > Duration::from_secs(0) {
+            std::thread::sleep(delay);
+        }
+        self.inner.send_msg(&bytes[..len], fds)
+    }
+
+    fn rcv_msg(&self, buffer: &mut [u8], fds: &mut [RawFd]) -> IoResult<(usize, usize)> {
+        let len = limit(buffer.len(), self.config.max_read);
+        self.inner.rcv_msg(&mut buffer[..len], fds)
+    }
+}
+
+// the backend handles the protocol by 32-bit words, partial transfers must not split them
+fn limit(len: usize, max: Option<usize>) -> usize {
+    match max {
+        Some(max) => len.min((max & !3).max(4)),
+        None => len,
+    }
+}
@@ -0,0 +1,252 @@
+//! Version shims for proxied connections
+//!
+//! A [`VersionShim`] is a [`ProxyFilter`] presenting to the client other versions of some
+//! interfaces than the ones supported by the upstream compositor. It can hide the newer versions
+//! of a compositor from clients which do not handle them properly, or let clients requiring a newer
+//! version run on an older compositor.
+//!
+//! The shim applies the following rules to the interfaces it is configured for:
+//!
+//! - the `wl_registry.global` events advertise the version presented to the client rather than
+//!   the one of the compositor;
+//! - the version of the `wl_registry.bind` requests is clamped to the version advertised by the
+//!   compositor, which gives the *upstream version* of the created object and of its children,
+//!   while the client keeps using the version it requested;
+//! - the requests which do not exist in the upstream version of their object are dropped. The
+//!   objects they create are *orphaned*: they never exist upstream, and all their requests are
+//!   dropped as well. The objects destroyed by dropped destructors are orphaned too, as they
+//!   keep existing upstream, and their events are dropped;
+//! - the events which do not exist in the version of their object known to the client are
+//!   dropped;
+//! - the events registered with [`VersionShim::synthesize()`] can be followed by events
+//!   synthesized for the client, to emulate the newer versions of a protocol (like the
+//!   `wl_output.done` event marking the end of a batch of `wl_output` properties).
+//!
+//! The ids of orphaned objects created by the client are never reused, as the compositor never
+//! acknowledges their destruction.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::protocol::{Argument, Interface, Message, ObjectInfo};
+
+use super::proxy::{ForwardAll, ProxyFilter};
+
+type SynthesisRule = Box<dyn FnMut(&Message<u32>) -> Option<Message<u32>> + Send>;
+
+struct Synthesis {
+    interface: &'static Interface,
+    since: u32,
+    rule: SynthesisRule,
+}
+
+/// A [`ProxyFilter`] presenting other interface versions to the client than the upstream ones
+///
+/// The messages kept by the shim are then given to an inner filter, which receives the version of
+/// the objects known to the client in their [`ObjectInfo`].
+///
+/// See the [module-level documentation](self) for the rules applied by the shim.
+pub struct VersionShim<F = ForwardAll> {
+    presented: Vec<(&'static Interface, u32)>,
+    syntheses: Vec<Synthesis>,
+    // advertised version of the shimmed globals, by name
+    globals: HashMap<u32, u32>,
+    // upstream version of the objects created from shimmed globals
+    upstream_versions: HashMap<u32, u32>,
+    orphans: HashSet<u32>,
+    pending: VecDeque<Message<u32>>,
+    inner: F,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<F: std::fmt::Debug> std::fmt::Debug for VersionShim<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VersionShim")
+            .field("presented", &self.presented)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VersionShim<ForwardAll> {
+    /// Create a shim forwarding the messages it keeps unchanged
+    pub fn new() -> VersionShim<ForwardAll> {
+        VersionShim::with_filter(ForwardAll)
+    }
+}
+
+impl Default for VersionShim<ForwardAll> {
+    fn default() -> VersionShim<ForwardAll> {
+        VersionShim::new()
+    }
+}
+
+impl<F: ProxyFilter> VersionShim<F> {
+    /// Create a shim giving the messages it keeps to an inner filter
+    pub fn with_filter(inner: F) -> VersionShim<F> {
+        VersionShim {
+            presented: Vec::new(),
+            syntheses: Vec::new(),
+            globals: HashMap::new(),
+            upstream_versions: HashMap::new(),
+            orphans: HashSet::new(),
+            pending: VecDeque::new(),
+            inner,
+        }
+    }
+
+    /// Present the globals of an interface to the client with the given version
+    ///
+    /// The version can be lower or higher than the one advertised by the compositor.
+    pub fn present(mut self, interface: &'static Interface, version: u32) -> Self {
+        self.presented.retain(|&(i, _)| i.name != interface.name);
+        self.presented.push((interface, version));
+        self
+    }
+
+    /// Synthesize events emulating a version of an interface
+    ///
+    /// After each event of an object of this interface whose version is at least `since` for the
+    /// client but lower upstream, `rule` is invoked with the event and may return an event to send
+    /// to the client after it.
+    pub fn synthesize(
+        mut self,
+        interface: &'static Interface,
+        since: u32,
+        rule: impl FnMut(&Message<u32>) -> Option<Message<u32>> + Send + 'static,
+    ) -> Self {
+        self.syntheses.push(Synthesis { interface, since, rule: Box::new(rule) });
+        self
+    }
+
+    /// Access the inner filter
+    pub fn inner(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// The upstream version of an object created from a shimmed global, if any
+    pub fn upstream_version(&self, id: u32) -> Option<u32> {
+        self.upstream_versions.get(&id).copied()
+    }
+
+    /// Whether an object is orphaned, existing only for the client or only upstream
+    pub fn is_orphan(&self, id: u32) -> bool {
+        self.orphans.contains(&id)
+    }
+
+    fn presented_version(&self, name: &[u8]) -> Option<u32> {
+        self.presented.iter().find(|(i, _)| i.name.as_bytes() == name).map(|&(_, v)| v)
+    }
+
+    // record the upstream version of the objects created by a message
+    fn track_children(&mut self, msg: &Message<u32>, upstream_version: Option<u32>) {
+        for arg in &msg.args {
+            if let Argument::NewId(id) = *arg {
+                match upstream_version {
+                    Some(version) => self.upstream_versions.insert(id, version),
+                    None => self.upstream_versions.remove(&id),
+                };
+            }
+        }
+    }
+
+    fn orphan_children(&mut self, msg: &Message<u32>) {
+        for arg in &msg.args {
+            if let Argument::NewId(id) = *arg {
+                self.orphans.insert(id);
+            }
+        }
+    }
+}
+
+impl<F: ProxyFilter> ProxyFilter for VersionShim<F> {
+    fn request(&mut self, object: ObjectInfo, mut msg: Message<u32>) -> Option<Message<u32>> {
+        if self.orphans.contains(&msg.sender_id) {
+            self.orphan_children(&msg);
+            return None;
+        }
+        let desc = &object.interface.requests[msg.opcode as usize];
+        let upstream_version = self.upstream_versions.get(&msg.sender_id).copied();
+
+        if object.interface.name == "wl_registry" && desc.name == "bind" {
+            let advertised = match msg.args[..] {
+                [Argument::Uint(name), ..] => self.globals.get(&name).copied(),
+                _ => None,
+            };
+            match advertised {
+                Some(advertised) => {
+                    if let [_, _, Argument::Uint(version), Argument::NewId(id)] = &mut msg.args[..]
+                    {
+                        *version = (*version).min(advertised);
+                        self.upstream_versions.insert(*id, *version);
+                    }
+                }
+                None => self.track_children(&msg, None),
+            }
+            return self.inner.request(object, msg);
+        }
+
+        if desc.since > upstream_version.unwrap_or(object.version) {
+            self.orphan_children(&msg);
+            if desc.is_destructor {
+                self.orphans.insert(msg.sender_id);
+            }
+            return None;
+        }
+        self.track_children(&msg, upstream_version);
+        self.inner.request(object, msg)
+    }
+
+    fn event(&mut self, object: ObjectInfo, mut msg: Message<u32>) -> Option<Message<u32>> {
+        if self.orphans.contains(&msg.sender_id) {
+            return None;
+        }
+        let desc = &object.interface.events[msg.opcode as usize];
+
+        if object.interface.name == "wl_registry" {
+            match (desc.name, &mut msg.args[..]) {
+                (
+                    "global",
+                    [Argument::Uint(name), Argument::Str(interface), Argument::Uint(version)],
+                ) => {
+                    if let Some(presented) = self.presented_version(interface.to_bytes()) {
+                        self.globals.insert(*name, *version);
+                        *version = presented;
+                    }
+                }
+                ("global_remove", [Argument::Uint(name)]) => {
+                    self.globals.remove(name);
+                }
+                _ => {}
+            }
+        } else if msg.sender_id == 1 && desc.name == "delete_id" {
+            if let [Argument::Uint(id)] = msg.args[..] {
+                self.upstream_versions.remove(&id);
+            }
+        }
+
+        if desc.since > object.version {
+            return None;
+        }
+        let upstream_version = self.upstream_versions.get(&msg.sender_id).copied();
+        self.track_children(&msg, upstream_version);
+
+        if let Some(upstream_version) = upstream_version {
+            for synthesis in &mut self.syntheses {
+                if synthesis.interface.name == object.interface.name
+                    && object.version >= synthesis.since
+                    && upstream_version < synthesis.since
+                {
+                    self.pending.extend((synthesis.rule)(&msg));
+                }
+            }
+        }
+        self.inner.event(object, msg)
+    }
+
+    fn synthesized(&mut self) -> Option<Message<u32>> {
+        if let Some(msg) = self.inner.synthesized() {
+            return Some(msg);
+        }
+        self.pending.pop_front()
+    }
+}
@@ -2,12 +2,15 @@
 
 use std::io::Result as IoResult;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
 
 use nix::sys::{socket, uio};
 
 use crate::protocol::{ArgumentType, Message};
 
-use super::wire::{parse_message, write_to_buffers, MessageParseError, MessageWriteError};
+use super::wire::{
+    div_ceil, parse_message, write_to_buffers, MessageParseError, MessageWriteError,
+};
 
 /// Maximum number of FD that can be sent in a single socket message
 pub const MAX_FDS_OUT: usize = 28;
@@ -24,25 +27,25 @@ pub struct Socket {
     fd: RawFd,
 }
 
-impl Socket {
-    /// Send a single message to the socket
+/// A transport carrying the Wayland protocol
+///
+/// The rust backend exchanges messages over a unix socket by default, but it can be run over any
+/// transport implementing this trait, for example a proxied connection or an instrumented socket
+/// for testing, using [`Backend::connect_with_transport()`](crate::rs::client::Backend::connect_with_transport).
+///
+/// Both methods must be non-blocking. The file descriptor returned by [`AsRawFd`] is the one
+/// polled for readiness when waiting for incoming messages.
+pub trait WaylandTransport: AsRawFd + Send + Sync + std::fmt::Debug {
+    /// Send a single message to the transport
     ///
     /// A single socket message can contain several wayland messages
     ///
-    /// The `fds` slice should not be longer than `MAX_FDS_OUT`, and the `bytes`
-    /// slice should not be longer than `MAX_BYTES_OUT` otherwise the receiving
-    /// end may lose some data.
-    pub fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> IoResult<usize> {
-        let iov = [uio::IoVec::from_slice(bytes)];
-        if !fds.is_empty() {
-            let cmsgs = [socket::ControlMessage::ScmRights(fds)];
-            Ok(socket::sendmsg(self.fd, &iov, &cmsgs, socket::MsgFlags::MSG_DONTWAIT, None)?)
-        } else {
-            Ok(socket::sendmsg(self.fd, &iov, &[], socket::MsgFlags::MSG_DONTWAIT, None)?)
-        }
-    }
+    /// The `fds` slice is never longer than [`MAX_FDS_OUT`], and the `bytes` slice never longer
+    /// than [`MAX_BYTES_OUT`]. Returns the number of bytes sent, the fds are considered sent
+    /// as soon as any byte is.
+    fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> IoResult<usize>;
 
-    /// Receive a single message from the socket
+    /// Receive a single message from the transport
     ///
     /// Return the number of bytes received and the number of Fds received.
     ///
@@ -50,33 +53,64 @@ impl Socket {
     ///
     /// A single socket message can contain several wayland messages.
     ///
-    /// The `buffer` slice should be at least `MAX_BYTES_OUT` long and the `fds`
-    /// slice `MAX_FDS_OUT` long, otherwise some data of the received message may
-    /// be lost.
-    pub fn rcv_msg(&self, buffer: &mut [u8], fds: &mut [RawFd]) -> IoResult<(usize, usize)> {
-        let mut cmsg = nix::cmsg_space!([RawFd; MAX_FDS_OUT]);
-        let iov = [uio::IoVec::from_mut_slice(buffer)];
-
-        let msg = socket::recvmsg(
-            self.fd,
-            &iov[..],
-            Some(&mut cmsg),
-            socket::MsgFlags::MSG_DONTWAIT | socket::MsgFlags::MSG_CMSG_CLOEXEC,
-        )?;
-
-        let mut fd_count = 0;
-        let received_fds = msg.cmsgs().flat_map(|cmsg| match cmsg {
-            socket::ControlMessageOwned::ScmRights(s) => s,
-            _ => Vec::new(),
-        });
-        for (fd, place) in received_fds.zip(fds.iter_mut()) {
-            fd_count += 1;
-            *place = fd;
-        }
-        Ok((msg.bytes, fd_count))
+    /// The `buffer` slice is at least [`MAX_BYTES_OUT`] long and the `fds` slice
+    /// [`MAX_FDS_OUT`] long.
+    fn rcv_msg(&self, buffer: &mut [u8], fds: &mut [RawFd]) -> IoResult<(usize, usize)>;
+}
+
+impl WaylandTransport for Socket {
+    fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> IoResult<usize> {
+        send_msg(self.fd, bytes, fds)
+    }
+
+    fn rcv_msg(&self, buffer: &mut [u8], fds: &mut [RawFd]) -> IoResult<(usize, usize)> {
+        rcv_msg(self.fd, buffer, fds)
+    }
+}
+
+impl WaylandTransport for UnixStream {
+    fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> IoResult<usize> {
+        send_msg(self.as_raw_fd(), bytes, fds)
+    }
+
+    fn rcv_msg(&self, buffer: &mut [u8], fds: &mut [RawFd]) -> IoResult<(usize, usize)> {
+        rcv_msg(self.as_raw_fd(), buffer, fds)
+    }
+}
+
+fn send_msg(fd: RawFd, bytes: &[u8], fds: &[RawFd]) -> IoResult<usize> {
+    let iov = [uio::IoVec::from_slice(bytes)];
+    if !fds.is_empty() {
+        let cmsgs = [socket::ControlMessage::ScmRights(fds)];
+        Ok(socket::sendmsg(fd, &iov, &cmsgs, socket::MsgFlags::MSG_DONTWAIT, None)?)
+    } else {
+        Ok(socket::sendmsg(fd, &iov, &[], socket::MsgFlags::MSG_DONTWAIT, None)?)
     }
 }
 
+fn rcv_msg(fd: RawFd, buffer: &mut [u8], fds: &mut [RawFd]) -> IoResult<(usize, usize)> {
+    let mut cmsg = nix::cmsg_space!([RawFd; MAX_FDS_OUT]);
+    let iov = [uio::IoVec::from_mut_slice(buffer)];
+
+    let msg = socket::recvmsg(
+        fd,
+        &iov[..],
+        Some(&mut cmsg),
+        socket::MsgFlags::MSG_DONTWAIT | socket::MsgFlags::MSG_CMSG_CLOEXEC,
+    )?;
+
+    let mut fd_count = 0;
+    let received_fds = msg.cmsgs().flat_map(|cmsg| match cmsg {
+        socket::ControlMessageOwned::ScmRights(s) => s,
+        _ => Vec::new(),
+    });
+    for (fd, place) in received_fds.zip(fds.iter_mut()) {
+        fd_count += 1;
+        *place = fd;
+    }
+    Ok((msg.bytes, fd_count))
+}
+
 #[cfg(not(tarpaulin_include))]
 impl FromRawFd for Socket {
     unsafe fn from_raw_fd(fd: RawFd) -> Socket {
@@ -112,7 +146,7 @@ impl Drop for Socket {
 /// conversion from/to wayland messages
 #[derive(Debug)]
 pub struct BufferedSocket {
-    socket: Socket,
+    socket: Box<dyn WaylandTransport>,
     in_data: Buffer<u32>,
     in_fds: Buffer<RawFd>,
     out_data: Buffer<u32>,
@@ -120,10 +154,10 @@ pub struct BufferedSocket {
 }
 
 impl BufferedSocket {
-    /// Wrap a Socket into a Buffered Socket
-    pub fn new(socket: Socket) -> BufferedSocket {
+    /// Wrap a transport into a Buffered Socket
+    pub fn new<T: WaylandTransport + 'static>(socket: T) -> BufferedSocket {
         BufferedSocket {
-            socket,
+            socket: Box::new(socket),
             in_data: Buffer::new(2 * MAX_BYTES_OUT / 4), // Incoming buffers are twice as big in order to be
             in_fds: Buffer::new(2 * MAX_FDS_OUT),        // able to store leftover data if needed
             out_data: Buffer::new(MAX_BYTES_OUT / 4),
@@ -233,11 +267,81 @@ impl BufferedSocket {
             return Err(::nix::errno::Errno::EPIPE.into());
         }
         // advance the storage
-        self.in_data.advance(in_bytes / 4 + if in_bytes % 4 > 0 { 1 } else { 0 });
+        self.in_data.advance(div_ceil(in_bytes, 4));
         self.in_fds.advance(in_fds);
         Ok(())
     }
 
+    /// Size in bytes of the next incoming message, if its header was already received
+    pub fn next_message_size(&self) -> Option<usize> {
+        match self.in_data.get_contents() {
+            [_, word_2, ..] => Some((word_2 >> 16) as usize),
+            _ => None,
+        }
+    }
+
+    /// Sender id and opcode of the complete messages received and not yet consumed, in order
+    pub fn queued_headers(&self) -> impl Iterator<Item = (u32, u16)> + '_ {
+        let mut data = self.in_data.get_contents();
+        std::iter::from_fn(move || match *data {
+            [id, word_2, ..] => {
+                let words = div_ceil((word_2 >> 16) as usize, 4);
+                if words < 2 || words > data.len() {
+                    return None;
+                }
+                data = &data[words..];
+                Some((id, (word_2 & 0x0000_FFFF) as u16))
+            }
+            _ => None,
+        })
+    }
+
+    /// Number of received fds not yet consumed by a message
+    pub fn queued_fds(&self) -> usize {
+        self.in_fds.get_contents().len()
+    }
+
+    /// Number of received bytes not yet consumed by a message
+    pub fn queued_bytes(&self) -> usize {
+        self.in_data.get_contents().len() * 4
+    }
+
+    /// Bytes allocated for the incoming and outgoing buffers
+    pub fn buffers_size(&self) -> usize {
+        (self.in_data.storage.len() + self.out_data.storage.len()) * std::mem::size_of::<u32>()
+            + (self.in_fds.storage.len() + self.out_fds.storage.len())
+                * std::mem::size_of::<RawFd>()
+    }
+
+    /// Close the received fds not yet consumed by a message, returning their number
+    pub fn discard_queued_fds(&mut self) -> usize {
+        let fds = self.in_fds.get_contents();
+        let count = fds.len();
+        for &fd in fds {
+            let _ = ::nix::unistd::close(fd);
+        }
+        self.in_fds.clear();
+        count
+    }
+
+    /// Discard the first message of the incoming buffers, using the size from its header
+    ///
+    /// Its file descriptors cannot be known without its signature, and are left in the buffers.
+    pub fn skip_message(&mut self) -> Result<(), MessageParseError> {
+        let data = self.in_data.get_contents();
+        let words = match *data {
+            [_, word_2, ..] => div_ceil((word_2 >> 16) as usize, 4),
+            _ => return Err(MessageParseError::MissingData),
+        };
+        if words < 2 {
+            return Err(MessageParseError::Malformed);
+        } else if words > data.len() {
+            return Err(MessageParseError::MissingData);
+        }
+        self.in_data.offset(words);
+        Ok(())
+    }
+
     /// Read and deserialize a single message from the incoming buffers socket
     ///
     /// This method requires one closure that given an object id and an opcode,
@@ -263,7 +367,6 @@ impl BufferedSocket {
                     Ok((msg, rest_data, rest_fds)) => {
                         (msg, data.len() - rest_data.len(), fds.len() - rest_fds.len())
                     }
-                    // TODO: gracefully handle wayland messages split across unix messages ?
                     Err(e) => return Err(e),
                 }
             } else {
@@ -282,7 +385,7 @@ impl BufferedSocket {
 #[cfg(not(tarpaulin_include))]
 impl AsRawFd for BufferedSocket {
     fn as_raw_fd(&self) -> RawFd {
-        self.socket.fd
+        self.socket.as_raw_fd()
     }
 }
 
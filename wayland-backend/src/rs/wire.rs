@@ -11,6 +11,16 @@ use crate::protocol::{Argument, ArgumentType, Message};
 
 use smallvec::SmallVec;
 
+/// Number of `divisor`-sized chunks needed to hold `value`
+///
+/// `usize::div_ceil()` is only available since rust 1.73.
+pub(crate) fn div_ceil(value: usize, divisor: usize) -> usize {
+    match value % divisor {
+        0 => value / divisor,
+        _ => value / divisor + 1,
+    }
+}
+
 /// Error generated when trying to serialize a message into buffers
 #[derive(Debug)]
 pub enum MessageWriteError {
@@ -98,7 +108,7 @@ pub fn write_to_buffers(
         payload: &'a mut [u32],
     ) -> Result<&'a mut [u32], MessageWriteError> {
         let array_len = array.len();
-        let word_len = array_len / 4 + if array_len % 4 != 0 { 1 } else { 0 };
+        let word_len = div_ceil(array_len, 4);
         // need enough space to store the whole array with padding and a size header
         if payload.len() < 1 + word_len {
             return Err(MessageWriteError::BufferTooSmall);
@@ -177,7 +187,7 @@ pub fn parse_message<'a, 'b>(
         array_len: usize,
         payload: &[u32],
     ) -> Result<(&[u8], &[u32]), MessageParseError> {
-        let word_len = array_len / 4 + if array_len % 4 != 0 { 1 } else { 0 };
+        let word_len = div_ceil(array_len, 4);
         if word_len > payload.len() {
             return Err(MessageParseError::MissingData);
         }
@@ -197,8 +207,11 @@ pub fn parse_message<'a, 'b>(
     let opcode = (word_2 & 0x0000_FFFF) as u16;
     let len = (word_2 >> 16) as usize / 4;
 
-    if len < 2 || len > raw.len() {
+    if len < 2 {
         return Err(MessageParseError::Malformed);
+    } else if len > raw.len() {
+        // the rest of the message may still be in transit
+        return Err(MessageParseError::MissingData);
     }
 
     let (mut payload, rest) = raw.split_at(len);
@@ -3,27 +3,31 @@
 use std::{
     cell::RefCell,
     ffi::CStr,
+    ops::DerefMut,
     os::raw::{c_char, c_int, c_void},
     os::unix::{io::RawFd, net::UnixStream, prelude::IntoRawFd},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex,
     },
+    time::Instant,
 };
 
 use crate::{
-    core_interfaces::WL_DISPLAY_INTERFACE,
+    core_interfaces::{WL_DISPLAY_INTERFACE, WL_REGISTRY_INTERFACE},
     protocol::{
-        check_for_signature, same_interface, AllowNull, Argument, ArgumentType, Interface, Message,
+        child_spec, compare_signature, lookup_interface, same_interface,
+        same_interface_or_anonymous, AllowNull, Argument, ArgumentType, Interface, Message,
         ObjectInfo, ProtocolError, ANONYMOUS_INTERFACE,
     },
+    types::client::GlobalCache,
 };
 use scoped_tls::scoped_thread_local;
 use smallvec::SmallVec;
 
 use wayland_sys::{client::*, common::*, ffi_dispatch};
 
-pub use crate::types::client::{InvalidId, NoWaylandLib, WaylandError};
+pub use crate::types::client::{GlobalInfo, InvalidId, NoWaylandLib, Operation, WaylandError};
 
 use super::{free_arrays, RUST_MANAGED};
 
@@ -41,6 +45,10 @@ pub trait ObjectData: downcast_rs::DowncastSync {
     ///
     /// If the event has a NewId argument, the callback must return the object data
     /// for the newly created object
+    ///
+    /// The handle can be freely used from this callback, including to send a destructor
+    /// request for the object being dispatched or the newly created one: their object data
+    /// is then notified of their destruction only once.
     fn event(
         self: Arc<Self>,
         handle: &mut Handle,
@@ -48,6 +56,14 @@ pub trait ObjectData: downcast_rs::DowncastSync {
     ) -> Option<Arc<dyn ObjectData>>;
     /// Notification that the object has been destroyed and is no longer active
     fn destroyed(&self, object_id: ObjectId);
+    /// Notification that the object has been destroyed, with access to the connection
+    ///
+    /// This is the notification actually given by the backend, it allows the cleanup of the
+    /// object to send requests, for example to destroy the objects depending on it. The default
+    /// implementation forwards to [`destroyed()`](ObjectData::destroyed).
+    fn destroyed_with_handle(&self, _handle: &mut Handle, object_id: ObjectId) {
+        self.destroyed(object_id)
+    }
     /// Helper for forwarding a Debug implementation of your `ObjectData` type
     ///
     /// By default will just print `ObjectData { ... }`
@@ -188,6 +204,7 @@ struct ProxyUserData {
     alive: Arc<AtomicBool>,
     data: Arc<dyn ObjectData>,
     interface: &'static Interface,
+    destruction_hooks: Vec<Box<dyn FnOnce(ObjectId) + Send + Sync>>,
 }
 
 /// Main handle of a backend to the Wayland protocol
@@ -202,7 +219,9 @@ pub struct Handle {
     evq: *mut wl_event_queue,
     display_id: ObjectId,
     last_error: Option<WaylandError>,
-    pending_placeholder: Option<(&'static Interface, u32)>,
+    registry_cache: Option<(ObjectId, Arc<GlobalCache>)>,
+    read_timestamps: bool,
+    last_read: Option<Instant>,
 }
 
 /// A pure rust implementation of a Wayland client backend
@@ -253,7 +272,9 @@ impl Backend {
                     interface: &WL_DISPLAY_INTERFACE,
                 },
                 last_error: None,
-                pending_placeholder: None,
+                registry_cache: None,
+                read_timestamps: false,
+                last_read: None,
             },
         })
     }
@@ -264,9 +285,10 @@ impl Backend {
         let ret =
             unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_flush, self.handle.display) };
         if ret < 0 {
-            Err(self
-                .handle
-                .store_if_not_wouldblock_and_return_error(std::io::Error::last_os_error()))
+            Err(self.handle.store_if_not_recoverable_and_return_error(
+                Operation::Flush,
+                std::io::Error::last_os_error(),
+            ))
         } else {
             Ok(())
         }
@@ -285,6 +307,13 @@ impl Backend {
         self.handle.dispatch_pending()
     }
 
+    /// Prepare a read of the Wayland socket through an exclusive borrow of this backend
+    ///
+    /// See [`ExclusiveReadEventsGuard`] for details.
+    pub fn prepare_read(&mut self) -> Result<ExclusiveReadEventsGuard<&mut Backend>, WaylandError> {
+        ExclusiveReadEventsGuard::try_new(self)
+    }
+
     /// Access the [`Handle`] associated with this backend
     pub fn handle(&mut self) -> &mut Handle {
         &mut self.handle
@@ -302,7 +331,11 @@ impl Handle {
     }
 
     #[inline]
-    fn store_and_return_error(&mut self, err: std::io::Error) -> WaylandError {
+    fn store_and_return_error(
+        &mut self,
+        operation: Operation,
+        err: std::io::Error,
+    ) -> WaylandError {
         // check if it was actually a protocol error
         let err = if err.raw_os_error() == Some(nix::errno::Errno::EPROTO as i32) {
             let mut object_id = 0;
@@ -331,7 +364,7 @@ impl Handle {
                 message: String::new(),
             })
         } else {
-            WaylandError::Io(err)
+            WaylandError::io(operation, err)
         };
         log::error!("{}", err);
         self.last_error = Some(err.clone());
@@ -339,11 +372,15 @@ impl Handle {
     }
 
     #[inline]
-    fn store_if_not_wouldblock_and_return_error(&mut self, e: std::io::Error) -> WaylandError {
-        if e.kind() != std::io::ErrorKind::WouldBlock {
-            self.store_and_return_error(e)
+    fn store_if_not_recoverable_and_return_error(
+        &mut self,
+        operation: Operation,
+        e: std::io::Error,
+    ) -> WaylandError {
+        if !matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted) {
+            self.store_and_return_error(operation, e)
         } else {
-            e.into()
+            WaylandError::io(operation, e)
         }
     }
 
@@ -367,7 +404,10 @@ impl Handle {
                 }
             });
         if ret < 0 {
-            Err(self.store_if_not_wouldblock_and_return_error(std::io::Error::last_os_error()))
+            Err(self.store_if_not_recoverable_and_return_error(
+                Operation::Dispatch,
+                std::io::Error::last_os_error(),
+            ))
         } else {
             Ok(ret as usize)
         }
@@ -392,11 +432,48 @@ impl Handle {
         let ret =
             unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_read_events, self.display) };
         if ret < 0 {
-            Err(self.store_if_not_wouldblock_and_return_error(std::io::Error::last_os_error()))
+            Err(self.store_if_not_recoverable_and_return_error(
+                Operation::Read,
+                std::io::Error::last_os_error(),
+            ))
         } else {
+            self.mark_read();
             Ok(())
         }
     }
+
+    // record the time of a read of the socket, if enabled
+    fn mark_read(&mut self) {
+        if self.read_timestamps {
+            self.last_read = Some(Instant::now());
+        }
+    }
+
+    // notify the destruction of a proxy to its data and hooks, and destroy it
+    fn destroy_proxy(&mut self, id: &ObjectId) {
+        if let Some(ref alive) = id.alive {
+            let udata = unsafe {
+                Box::from_raw(ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_user_data, id.ptr)
+                    as *mut ProxyUserData)
+            };
+            unsafe {
+                ffi_dispatch!(
+                    WAYLAND_CLIENT_HANDLE,
+                    wl_proxy_set_user_data,
+                    id.ptr,
+                    std::ptr::null_mut()
+                );
+            }
+            alive.store(false, Ordering::Release);
+            udata.data.destroyed_with_handle(self, id.clone());
+            for hook in udata.destruction_hooks {
+                hook(id.clone());
+            }
+        }
+        unsafe {
+            ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, id.ptr);
+        }
+    }
 }
 
 /// Guard for synchronizing event reading across multiple threads
@@ -479,15 +556,15 @@ impl ReadEventsGuard {
             unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_read_events, self.display) };
         if ret < 0 {
             // we have done the reading, and there is an error
-            Err(self
-                .backend
-                .lock()
-                .unwrap()
-                .handle
-                .store_if_not_wouldblock_and_return_error(std::io::Error::last_os_error()))
+            Err(self.backend.lock().unwrap().handle.store_if_not_recoverable_and_return_error(
+                Operation::Read,
+                std::io::Error::last_os_error(),
+            ))
         } else {
             // the read occured, dispatch pending events
-            self.backend.lock().unwrap().handle.dispatch_pending()
+            let mut backend = self.backend.lock().unwrap();
+            backend.handle.mark_read();
+            backend.handle.dispatch_pending()
         }
     }
 }
@@ -502,6 +579,102 @@ impl Drop for ReadEventsGuard {
     }
 }
 
+/// Guard for reading events through an exclusive access to the backend
+///
+/// This is an alternative to [`ReadEventsGuard`] for integrations which do their own
+/// synchronization, like a single-threaded event loop: rather than an `Arc<Mutex<Backend>>`, the
+/// guard holds an exclusive access to the backend for its whole lifetime (a `&mut Backend`, or a
+/// `MutexGuard<Backend>`), so that it does not need to lock the backend again when reading.
+///
+/// This synchronization is compatible with the "prepare_read" mechanism of the system libwayland,
+/// and will correctly synchronize with other C libraries using the same Wayland socket.
+#[derive(Debug)]
+pub struct ExclusiveReadEventsGuard<B: DerefMut<Target = Backend>> {
+    backend: B,
+    done: bool,
+}
+
+impl<B: DerefMut<Target = Backend>> ExclusiveReadEventsGuard<B> {
+    /// Create a new reading guard
+    ///
+    /// This call will not block, but event callbacks may be invoked in the process
+    /// of preparing the guard.
+    pub fn try_new(mut backend: B) -> Result<Self, WaylandError> {
+        let display = backend.handle.display;
+        let evq = backend.handle.evq;
+
+        // do the prepare_read() and dispatch as necessary
+        loop {
+            let ret = unsafe {
+                if evq.is_null() {
+                    ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_prepare_read, display)
+                } else {
+                    ffi_dispatch!(
+                        WAYLAND_CLIENT_HANDLE,
+                        wl_display_prepare_read_queue,
+                        display,
+                        evq
+                    )
+                }
+            };
+            if ret < 0 {
+                backend.handle.dispatch_pending()?;
+            } else {
+                break;
+            }
+        }
+
+        // prepare_read is done, we are ready
+        Ok(ExclusiveReadEventsGuard { backend, done: false })
+    }
+
+    /// Access the Wayland socket FD for polling
+    pub fn connection_fd(&self) -> RawFd {
+        unsafe {
+            ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_get_fd, self.backend.handle.display)
+        }
+    }
+
+    /// Attempt to read events from the Wayland socket
+    ///
+    /// If other threads have a live reading guard, this method will block until all of them
+    /// are either dropped or have their `read()` method invoked.
+    ///
+    /// This returns the number of dispatched events, or `0` if an other thread handled the dispatching.
+    /// If no events are available to read from the socket, this also returns `0`: unlike the rust
+    /// backend, libwayland does not report it as an error.
+    pub fn read(mut self) -> Result<usize, WaylandError> {
+        self.done = true;
+        let display = self.backend.handle.display;
+        let ret = unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_read_events, display) };
+        if ret < 0 {
+            // we have done the reading, and there is an error
+            Err(self.backend.handle.store_if_not_recoverable_and_return_error(
+                Operation::Read,
+                std::io::Error::last_os_error(),
+            ))
+        } else {
+            // the read occured, dispatch pending events
+            self.backend.handle.mark_read();
+            self.backend.handle.dispatch_pending()
+        }
+    }
+}
+
+impl<B: DerefMut<Target = Backend>> Drop for ExclusiveReadEventsGuard<B> {
+    fn drop(&mut self) {
+        if !self.done {
+            unsafe {
+                ffi_dispatch!(
+                    WAYLAND_CLIENT_HANDLE,
+                    wl_display_cancel_read,
+                    self.backend.handle.display
+                );
+            }
+        }
+    }
+}
+
 impl Handle {
     /// Get the object ID for the `wl_display`
     pub fn display_id(&self) -> ObjectId {
@@ -531,7 +704,14 @@ impl Handle {
             unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_version, id.ptr) }
         };
 
-        Ok(ObjectInfo { id: id.id, interface: id.interface, version })
+        Ok(ObjectInfo { id: id.id, interface: id.interface, version, serial: 0, alive: true })
+    }
+
+    /// Get the detailed information about several wayland objects at once
+    ///
+    /// An error is returned for the IDs which are no longer valid.
+    pub fn info_many(&self, ids: &[ObjectId]) -> Vec<Result<ObjectInfo, InvalidId>> {
+        ids.iter().map(|id| self.info(id.clone())).collect()
     }
 
     /// Create a null object ID
@@ -544,19 +724,25 @@ impl Handle {
     /// Create a placeholder ID for object creation
     ///
     /// This ID needs to be created beforehand and given as argument to a request creating a
-    /// new object ID. A specification must be specified if the interface and version cannot
-    /// be inferred from the protocol (for example object creation from the `wl_registry`).
+    /// new object ID, the actual ID being allocated by [`send_request`](Handle::send_request).
+    /// The interface of the new object and its version are deduced from the protocol: regular
+    /// constructors create objects with the version of their parent, and generic constructors
+    /// (for example object creation from the `wl_registry`) give the name of the interface and
+    /// the version in the message.
+    ///
+    /// The interface of a generic constructor must either be given here, or be registered with
+    /// [`register_interfaces()`](crate::protocol::register_interfaces). If it is given, it'll be
+    /// checked against what can be deduced from the message, and
+    /// [`send_request`](Handle::send_request) will panic if they do not match.
     ///
-    /// If a specification is provided it'll be checked against what can be deduced from the
-    /// protocol specification, and [`send_request`](Handle::send_request) will panic if they
-    /// do not match.
-    pub fn placeholder_id(&mut self, spec: Option<(&'static Interface, u32)>) -> ObjectId {
-        self.pending_placeholder = spec;
+    /// Placeholders leave no state in the `Handle`: they can be created ahead of the requests
+    /// using them, in any order and from any thread.
+    pub fn placeholder_id(&self, interface: Option<&'static Interface>) -> ObjectId {
         ObjectId {
             ptr: std::ptr::null_mut(),
             alive: None,
             id: 0,
-            interface: spec.map(|(iface, _)| iface).unwrap_or(&ANONYMOUS_INTERFACE),
+            interface: interface.unwrap_or(&ANONYMOUS_INTERFACE),
         }
     }
 
@@ -572,8 +758,8 @@ impl Handle {
     /// - the message opcode must be valid for the sender interface
     /// - the argument list must match the prototype for the message associated with this opcode
     /// - if the method creates a new object, a [`placeholder_id()`](Handle::placeholder_id) must be given
-    ///   in the argument list, either without a specification, or with a specification that matches the
-    ///   interface and version deduced from the protocol rules
+    ///   in the argument list, either without an interface, or with the interface deduced from the
+    ///   protocol rules
     ///
     /// When using the system libwayland backend, the Wayland interfaces must have been generated with the C-ptr
     /// support.
@@ -598,55 +784,20 @@ impl Handle {
                 panic!("Unknown opcode {} for object {}@{}.", opcode, id.interface.name, id.id);
             }
         };
-        if !check_for_signature(message_desc.signature, &args) {
+        if let Err(mismatch) = compare_signature(message_desc, &args) {
             panic!(
-                "Unexpected signature for request {}@{}.{}: expected {:?}, got {:?}.",
-                id.interface.name, id.id, message_desc.name, message_desc.signature, args
+                "Unexpected signature for request {}@{}: {}",
+                id.interface.name, id.id, mismatch
             );
         }
 
         // Prepare the child object data
-        let child_spec = if message_desc
-            .signature
-            .iter()
-            .any(|arg| matches!(arg, ArgumentType::NewId(_)))
-        {
-            if let Some((iface, version)) = self.pending_placeholder.take() {
-                if let Some(child_interface) = message_desc.child_interface {
-                    if !same_interface(child_interface, iface) {
-                        panic!(
-                            "Wrong placeholder used when sending request {}@{}.{}: expected interface {} but got {}",
-                            id.interface.name,
-                            id.id,
-                            message_desc.name,
-                            child_interface.name,
-                            iface.name
-                        );
-                    }
-                    if version != parent_version {
-                        panic!(
-                            "Wrong placeholder used when sending request {}@{}.{}: expected version {} but got {}",
-                            id.interface.name,
-                            id.id,
-                            message_desc.name,
-                            parent_version,
-                            version
-                        );
-                    }
-                }
-                Some((iface, version))
-            } else if let Some(child_interface) = message_desc.child_interface {
-                Some((child_interface, parent_version))
-            } else {
-                panic!(
-                    "Wrong placeholder used when sending request {}@{}.{}: target interface must be specified for a generic constructor.",
-                    id.interface.name,
-                    id.id,
-                    message_desc.name
-                );
-            }
-        } else {
-            None
+        let child_spec = match child_spec(message_desc, &args, parent_version, |id| id.interface) {
+            Ok(spec) => spec,
+            Err(e) => panic!(
+                "Wrong placeholder used when sending request {}@{}.{}: {}",
+                id.interface.name, id.id, message_desc.name, e
+            ),
         };
 
         let child_interface_ptr = child_spec
@@ -736,6 +887,7 @@ impl Handle {
                     "Sending a request creating an object without providing an object data.",
                 ),
                 interface: child_interface,
+                destruction_hooks: Vec::new(),
             });
             unsafe {
                 ffi_dispatch!(
@@ -753,33 +905,31 @@ impl Handle {
         };
 
         if message_desc.is_destructor {
-            if let Some(ref alive) = id.alive {
-                let udata = unsafe {
-                    Box::from_raw(ffi_dispatch!(
-                        WAYLAND_CLIENT_HANDLE,
-                        wl_proxy_get_user_data,
-                        id.ptr
-                    ) as *mut ProxyUserData)
-                };
-                unsafe {
-                    ffi_dispatch!(
-                        WAYLAND_CLIENT_HANDLE,
-                        wl_proxy_set_user_data,
-                        id.ptr,
-                        std::ptr::null_mut()
-                    );
-                }
-                alive.store(false, Ordering::Release);
-                udata.data.destroyed(id.clone());
-            }
-            unsafe {
-                ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, id.ptr);
-            }
+            self.destroy_proxy(&id);
         }
 
         Ok(child_id)
     }
 
+    /// Destroy an object without sending a destructor request
+    ///
+    /// This is meant for the objects destroyed by a request of another object, like `wl_registry`
+    /// by `wl_fixes.destroy_registry`, and must be invoked right after sending this request. The
+    /// destruction of the object is notified to its object data and destruction hooks, and
+    /// `libwayland` keeps its ID until the server acknowledges it with `wl_display.delete_id`.
+    ///
+    /// Does nothing if the object is already destroyed.
+    pub fn release_object(&mut self, id: ObjectId) -> Result<(), InvalidId> {
+        match id.alive {
+            Some(ref alive) if alive.load(Ordering::Acquire) => {
+                self.destroy_proxy(&id);
+                Ok(())
+            }
+            Some(_) => Ok(()),
+            None => Err(InvalidId),
+        }
+    }
+
     /// Access the object data associated with a given object ID
     ///
     /// Returns an error if the object ID is not longer valid or if it corresponds to a Wayland
@@ -826,6 +976,88 @@ impl Handle {
 
         Ok(())
     }
+
+    /// Register a hook to be invoked when an object is destroyed
+    ///
+    /// The hooks of an object are invoked in registration order, right after the
+    /// [`ObjectData::destroyed()`] method of its object data and before its ID can be reused.
+    /// They are invoked while the backend is borrowed, and thus must not try to access it.
+    ///
+    /// Returns an error if the object ID is not longer valid or if it corresponds to a Wayland
+    /// object that is not managed by this backend (when multiple libraries share the same Wayland
+    /// socket via `libwayland`).
+    pub fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        if !id.alive.as_ref().map(|a| a.load(Ordering::Acquire)).unwrap_or(false) {
+            return Err(InvalidId);
+        }
+
+        // The display is never destroyed
+        if id.id == 1 {
+            return Err(InvalidId);
+        }
+
+        let udata = unsafe {
+            &mut *(ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_user_data, id.ptr)
+                as *mut ProxyUserData)
+        };
+
+        udata.destruction_hooks.push(hook);
+
+        Ok(())
+    }
+
+    /// Enable the registry maintained by the backend
+    ///
+    /// The first call creates a `wl_registry` whose events are handled by the backend itself to
+    /// keep track of the globals advertised by the server, subsequent calls return the same
+    /// registry. This allows independent components sharing the connection to see a consistent
+    /// list of globals via [`globals()`](Handle::globals), and to bind them using this registry.
+    ///
+    /// The globals are received on the next dispatch of the events of the connection.
+    pub fn enable_registry_cache(&mut self) -> Result<ObjectId, InvalidId> {
+        if let Some((ref registry, _)) = self.registry_cache {
+            return Ok(registry.clone());
+        }
+        let cache = Arc::new(GlobalCache::default());
+        let placeholder = self.placeholder_id(Some(&WL_REGISTRY_INTERFACE));
+        let registry = self.send_request(
+            message!(self.display_id(), 1, [Argument::NewId(placeholder)]),
+            Some(cache.clone()),
+        )?;
+        self.registry_cache = Some((registry.clone(), cache));
+        Ok(registry)
+    }
+
+    /// Get the globals advertised by the server
+    ///
+    /// Returns `None` if the registry maintained by the backend was not enabled using
+    /// [`enable_registry_cache()`](Handle::enable_registry_cache).
+    pub fn globals(&self) -> Option<Vec<GlobalInfo>> {
+        self.registry_cache.as_ref().map(|(_, cache)| cache.globals())
+    }
+
+    /// Record the time of each read of the Wayland socket
+    ///
+    /// Once enabled, the time at which the events being dispatched were read from the socket is
+    /// available from [`last_read_time()`](Handle::last_read_time). This is meant for profiling:
+    /// it stays enabled for the lifetime of the connection, costing a clock read per read of the
+    /// socket.
+    pub fn enable_read_timestamps(&mut self) {
+        self.read_timestamps = true;
+    }
+
+    /// The time of the last read of the Wayland socket
+    ///
+    /// When invoked from the callbacks of events, this is the time at which they were read.
+    /// Returns `None` until [`enable_read_timestamps()`](Handle::enable_read_timestamps) is
+    /// invoked and the socket is read.
+    pub fn last_read_time(&self) -> Option<Instant> {
+        self.last_read
+    }
 }
 
 unsafe extern "C" fn dispatcher_func(
@@ -880,7 +1112,7 @@ unsafe extern "C" fn dispatcher_func(
                         let obj_udata =
                             &*(ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_user_data, obj)
                                 as *mut ProxyUserData);
-                        if !same_interface(next_interface, obj_udata.interface) {
+                        if !same_interface_or_anonymous(next_interface, obj_udata.interface) {
                             log::error!(
                                 "Received object {}@{} in {}.{} but expected interface {}.",
                                 obj_udata.interface.name,
@@ -898,11 +1130,25 @@ unsafe extern "C" fn dispatcher_func(
                             interface: obj_udata.interface,
                         }));
                     } else {
+                        // the interface of an anonymous foreign object is known from its class
+                        let interface = if same_interface(next_interface, &ANONYMOUS_INTERFACE) {
+                            CStr::from_ptr(ffi_dispatch!(
+                                WAYLAND_CLIENT_HANDLE,
+                                wl_proxy_get_class,
+                                obj
+                            ))
+                            .to_str()
+                            .ok()
+                            .and_then(lookup_interface)
+                            .unwrap_or(next_interface)
+                        } else {
+                            next_interface
+                        };
                         parsed_args.push(Argument::Object(ObjectId {
                             alive: None,
                             id: obj_id,
                             ptr: obj,
-                            interface: next_interface,
+                            interface,
                         }));
                     }
                 } else {
@@ -938,6 +1184,7 @@ unsafe extern "C" fn dispatcher_func(
                         alive: child_alive,
                         data: Arc::new(UninitObjectData),
                         interface: child_interface,
+                        destruction_hooks: Vec::new(),
                     }));
                     created = Some((child_id.clone(), child_udata));
                     ffi_dispatch!(
@@ -976,17 +1223,33 @@ unsafe extern "C" fn dispatcher_func(
         )
     });
 
-    if message_desc.is_destructor {
+    // The callback may have destroyed the object by sending a destructor request, in which case
+    // its user data was already freed and it must not be notified a second time.
+    let alive = id.alive.as_ref().map(|a| a.load(Ordering::Acquire)).unwrap_or(false);
+    if message_desc.is_destructor && alive {
         let udata = Box::from_raw(udata_ptr);
         ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_set_user_data, proxy, std::ptr::null_mut());
         udata.alive.store(false, Ordering::Release);
-        udata.data.destroyed(id);
+        HANDLE.with(|handle| {
+            udata.data.destroyed_with_handle(&mut **handle.borrow_mut(), id.clone())
+        });
+        for hook in udata.destruction_hooks {
+            hook(id.clone());
+        }
         ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, proxy);
     }
 
     match (created, ret) {
-        (Some((_, child_udata_ptr)), Some(child_data)) => {
-            (*child_udata_ptr).data = child_data;
+        (Some((child_id, child_udata_ptr)), Some(child_data)) => {
+            // the callback may have already destroyed the new object and freed its user data,
+            // the returned data then needs to be notified of it
+            if child_id.alive.as_ref().map(|a| a.load(Ordering::Acquire)).unwrap_or(false) {
+                (*child_udata_ptr).data = child_data;
+            } else {
+                HANDLE.with(|handle| {
+                    child_data.destroyed_with_handle(&mut **handle.borrow_mut(), child_id)
+                });
+            }
         }
         (Some((child_id, _)), None) => {
             panic!("Callback creating object {} did not provide any object data.", child_id);
@@ -1015,6 +1278,19 @@ impl Drop for Backend {
     }
 }
 
+impl ObjectData for GlobalCache {
+    fn event(
+        self: Arc<Self>,
+        _handle: &mut Handle,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        self.handle_event(&msg);
+        None
+    }
+
+    fn destroyed(&self, _object_id: ObjectId) {}
+}
+
 struct DumbObjectData;
 
 impl ObjectData for DumbObjectData {
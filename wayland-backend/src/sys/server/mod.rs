@@ -14,7 +14,7 @@ use std::{
 };
 
 use crate::protocol::{
-    check_for_signature, same_interface, AllowNull, Argument, ArgumentType, Interface, Message,
+    compare_signature, same_interface, AllowNull, Argument, ArgumentType, Interface, Message,
     ObjectInfo, ANONYMOUS_INTERFACE,
 };
 use scoped_tls::scoped_thread_local;
@@ -26,6 +26,8 @@ use super::{free_arrays, RUST_MANAGED};
 
 pub use crate::types::server::{Credentials, DisconnectReason, GlobalInfo, InitError, InvalidId};
 
+use crate::types::server::DisconnectHook;
+
 // First pointer is &mut Handle<D>, and second pointer is &mut D
 scoped_thread_local!(static HANDLE: (*mut c_void, *mut c_void));
 
@@ -326,11 +328,13 @@ struct ResourceUserData<D> {
     alive: Arc<AtomicBool>,
     data: Arc<dyn ObjectData<D>>,
     interface: &'static Interface,
+    destruction_hooks: Vec<Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>>,
 }
 
 struct ClientUserData<D> {
     data: Arc<dyn ClientData<D>>,
     alive: Arc<AtomicBool>,
+    disconnect_hook: DisconnectHook<ClientId>,
 }
 
 struct GlobalUserData<D> {
@@ -351,6 +355,7 @@ struct GlobalUserData<D> {
 #[derive(Debug)]
 pub struct Handle<D> {
     display: *mut wl_display,
+    disconnect_hook: DisconnectHook<ClientId>,
     _data: std::marker::PhantomData<fn(&mut D)>,
 }
 
@@ -396,7 +401,13 @@ impl<D> Backend<D> {
             );
         }
 
-        Ok(Backend { handle: Handle { display, _data: std::marker::PhantomData } })
+        Ok(Backend {
+            handle: Handle {
+                display,
+                disconnect_hook: DisconnectHook::new(),
+                _data: std::marker::PhantomData,
+            },
+        })
     }
 
     /// Initializes a connection to a client.
@@ -420,7 +431,7 @@ impl<D> Backend<D> {
             return Err(std::io::Error::last_os_error());
         }
 
-        Ok(unsafe { init_client::<D>(ret, data) })
+        Ok(unsafe { init_client::<D>(ret, data, self.handle.disconnect_hook.clone()) })
     }
 
     /// Flushes pending events destined for a client.
@@ -520,7 +531,7 @@ impl<D> Handle<D> {
         let version =
             unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_version, id.ptr) } as u32;
 
-        Ok(ObjectInfo { id: id.id, version, interface: id.interface })
+        Ok(ObjectInfo { id: id.id, version, interface: id.interface, serial: 0, alive: true })
     }
 
     /// Returns the id of the client which owns the object.
@@ -608,6 +619,42 @@ impl<D> Handle<D> {
         todo!()
     }
 
+    /// Returns an iterator over the objects of given interface owned by a client.
+    pub fn objects_of<'a>(
+        &'a self,
+        client_id: ClientId,
+        interface: &'static Interface,
+    ) -> Result<Box<dyn Iterator<Item = ObjectId> + 'a>, InvalidId> {
+        if !client_id.alive.load(Ordering::Acquire) {
+            return Err(InvalidId);
+        }
+
+        unsafe extern "C" fn collect_resource(
+            resource: *mut wl_resource,
+            data: *mut c_void,
+        ) -> wl_iterator_result {
+            (*(data as *mut Vec<*mut wl_resource>)).push(resource);
+            wl_iterator_result::WL_ITERATOR_CONTINUE
+        }
+
+        let mut resources: Vec<*mut wl_resource> = Vec::new();
+        unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_client_for_each_resource,
+                client_id.ptr,
+                collect_resource,
+                &mut resources as *mut Vec<*mut wl_resource> as *mut c_void
+            );
+        }
+        // the resources of other interfaces are filtered out by their class
+        Ok(Box::new(
+            resources
+                .into_iter()
+                .filter_map(move |ptr| unsafe { ObjectId::from_ptr(interface, ptr) }.ok()),
+        ))
+    }
+
     /// Retrieve the `ObjectId` for a wayland object given its protocol numerical ID
     pub fn object_for_protocol_id(
         &self,
@@ -660,6 +707,52 @@ impl<D> Handle<D> {
         Ok(unsafe { init_resource(resource, interface, Some(data)).0 })
     }
 
+    /// Create a new object for given client and send the event introducing it
+    ///
+    /// The `event` closure is given the ID of the created object and builds the event carrying it
+    /// as a "New ID" argument. If it fails, or if the event cannot be sent because its sender is
+    /// not a valid object of the same client, the object is destroyed before returning the error,
+    /// so that it never exists only on the server side.
+    ///
+    /// **Panic:**
+    ///
+    /// This method panics if the event does not have the created object as a "New ID" argument,
+    /// in addition to the checks of [`send_event()`](Handle::send_event).
+    pub fn create_object_with_event<F>(
+        &mut self,
+        client: ClientId,
+        interface: &'static Interface,
+        version: u32,
+        data: Arc<dyn ObjectData<D>>,
+        event: F,
+    ) -> Result<ObjectId, InvalidId>
+    where
+        F: FnOnce(&mut Self, ObjectId) -> Result<Message<ObjectId>, InvalidId>,
+    {
+        let id = self.create_object(client, interface, version, data)?;
+        let ret = event(self, id.clone()).and_then(|msg| {
+            if !msg.args.iter().any(|arg| matches!(arg, Argument::NewId(o) if *o == id)) {
+                panic!("The event introducing {}@{} does not carry it.", interface.name, id.id);
+            }
+            if msg.sender_id.ptr.is_null() || !msg.sender_id.same_client_as(&id) {
+                return Err(InvalidId);
+            }
+            self.send_event(msg)
+        });
+        match ret {
+            Ok(()) => Ok(id),
+            Err(e) => {
+                // the object was never introduced to the client, no delete_id is sent for it
+                if id.alive.as_ref().map(|alive| alive.load(Ordering::Acquire)).unwrap_or(false) {
+                    unsafe {
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_destroy, id.ptr);
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
     /// Returns an object id that represents a null object.
     pub fn null_id(&mut self) -> ObjectId {
         ObjectId { ptr: std::ptr::null_mut(), id: 0, alive: None, interface: &ANONYMOUS_INTERFACE }
@@ -692,11 +785,8 @@ impl<D> Handle<D> {
                 panic!("Unknown opcode {} for object {}@{}.", opcode, id.interface.name, id.id);
             }
         };
-        if !check_for_signature(message_desc.signature, &args) {
-            panic!(
-                "Unexpected signature for request {}@{}.{}: expected {:?}, got {:?}.",
-                id.interface.name, id.id, message_desc.name, message_desc.signature, args
-            );
+        if let Err(mismatch) = compare_signature(message_desc, &args) {
+            panic!("Unexpected signature for event {}@{}: {}", id.interface.name, id.id, mismatch);
         }
 
         let mut argument_list = SmallVec::<[wl_argument; 4]>::with_capacity(args.len());
@@ -833,6 +923,32 @@ impl<D> Handle<D> {
         Ok(())
     }
 
+    /// Register a hook to be invoked when an object is destroyed
+    ///
+    /// The hooks of an object are invoked in registration order, right after the
+    /// [`ObjectData::destroyed()`] method of its object data and before its ID can be reused.
+    /// They are invoked while the backend is borrowed, and thus must not try to access it.
+    ///
+    /// Returns an error if the object ID is not longer valid
+    pub fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        if !id.alive.as_ref().map(|alive| alive.load(Ordering::Acquire)).unwrap_or(false) {
+            return Err(InvalidId);
+        }
+
+        let udata = unsafe {
+            &mut *(ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_user_data, id.ptr)
+                as *mut ResourceUserData<D>)
+        };
+
+        udata.destruction_hooks.push(hook);
+
+        Ok(())
+    }
+
     /// Posts an error on an object. This will also disconnect the client which created the object.
     pub fn post_error(&mut self, id: ObjectId, error_code: u32, message: CString) {
         if !id.alive.as_ref().map(|alive| alive.load(Ordering::Acquire)).unwrap_or(true) {
@@ -860,6 +976,7 @@ impl<D> Handle<D> {
         if let Some(udata) = unsafe { client_user_data::<D>(client_id.ptr) } {
             let udata = unsafe { &*udata };
             udata.alive.store(false, Ordering::Release);
+            udata.disconnect_hook.invoke(client_id.clone(), &reason);
             udata.data.disconnected(client_id.clone(), reason);
         }
 
@@ -868,6 +985,22 @@ impl<D> Handle<D> {
         }
     }
 
+    /// Set the hook invoked when a client is disconnected
+    ///
+    /// It is invoked with the reason of the disconnection, be it a protocol error, an error of the
+    /// connection or its closure, before the [`ClientData::disconnected()`] method of the client
+    /// and before any of its objects is destroyed. It is invoked while the backend is borrowed,
+    /// and thus must not try to access it.
+    ///
+    /// This replaces the previously set hook, if any.
+    #[allow(clippy::type_complexity)]
+    pub fn set_disconnect_hook(
+        &mut self,
+        hook: Box<dyn FnMut(ClientId, &DisconnectReason) + Send>,
+    ) {
+        self.disconnect_hook.set(hook);
+    }
+
     /// Creates a global of the specified interface and version and then advertises it to clients.
     ///
     /// The clients which the global is advertised to is determined by the implementation of the [`GlobalHandler`].
@@ -992,9 +1125,14 @@ impl<D> Handle<D> {
     }
 }
 
-unsafe fn init_client<D>(client: *mut wl_client, data: Arc<dyn ClientData<D>>) -> ClientId {
+unsafe fn init_client<D>(
+    client: *mut wl_client,
+    data: Arc<dyn ClientData<D>>,
+    disconnect_hook: DisconnectHook<ClientId>,
+) -> ClientId {
     let alive = Arc::new(AtomicBool::new(true));
-    let client_data = Box::into_raw(Box::new(ClientUserData { alive: alive.clone(), data }));
+    let client_data =
+        Box::into_raw(Box::new(ClientUserData { alive: alive.clone(), data, disconnect_hook }));
 
     let listener = signal::rust_listener_create(client_destroy_notify::<D>);
     signal::rust_listener_set_user_data(listener, client_data as *mut c_void);
@@ -1033,10 +1171,10 @@ unsafe extern "C" fn client_destroy_notify<D>(listener: *mut wl_listener, client
     // only notify the killing if it was not already
     if data.alive.load(Ordering::Acquire) {
         data.alive.store(false, Ordering::Release);
-        data.data.disconnected(
-            ClientId { ptr: client_ptr as *mut wl_client, alive: data.alive.clone() },
-            DisconnectReason::ConnectionClosed,
-        );
+        let client_id = ClientId { ptr: client_ptr as *mut wl_client, alive: data.alive.clone() };
+        // libwayland notifies the destruction of the client before destroying its resources
+        data.disconnect_hook.invoke(client_id.clone(), &DisconnectReason::ConnectionClosed);
+        data.data.disconnected(client_id, DisconnectReason::ConnectionClosed);
     }
 }
 
@@ -1107,6 +1245,7 @@ unsafe fn init_resource<D>(
         data: data.unwrap_or_else(|| Arc::new(UninitObjectData)),
         interface,
         alive: alive.clone(),
+        destruction_hooks: Vec::new(),
     }));
     let id = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_id, resource);
 
@@ -1303,7 +1442,10 @@ unsafe extern "C" fn resource_destructor<D>(resource: *mut wl_resource) {
         alive: Some(udata.alive.clone()),
         id,
     };
-    udata.data.destroyed(client_id, object_id);
+    udata.data.destroyed(client_id.clone(), object_id.clone());
+    for hook in udata.destruction_hooks {
+        hook(client_id.clone(), object_id.clone());
+    }
 }
 
 extern "C" {
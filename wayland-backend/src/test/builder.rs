@@ -0,0 +1,122 @@
+use std::ffi::CString;
+
+use crate::protocol::{
+    AllowNull, ArgumentType, MessageBuildError, MessageBuilder, MessageDirection,
+};
+
+use super::*;
+
+#[test]
+fn build_message() {
+    let msg = MessageBuilder::named(
+        &interfaces::TEST_GLOBAL_INTERFACE,
+        MessageDirection::Request,
+        3u32,
+        "many_args",
+    )
+    .and_then(|b| b.uint(42))
+    .and_then(|b| b.int(-13))
+    .and_then(|b| b.fixed(2.5))
+    .and_then(|b| b.array(vec![1, 2, 3]))
+    .and_then(|b| b.string("I like trains"))
+    .and_then(|b| b.fd(4))
+    .and_then(|b| b.build())
+    .unwrap();
+
+    assert_eq!(
+        msg,
+        message!(
+            3,
+            0,
+            [
+                Argument::Uint(42),
+                Argument::Int(-13),
+                Argument::Fixed(640),
+                Argument::Array(Box::new(vec![1, 2, 3])),
+                Argument::Str(Box::new(CString::new("I like trains").unwrap())),
+                Argument::Fd(4),
+            ],
+        )
+    );
+
+    // the untyped new_id of wl_registry.bind expands to the interface and version
+    let msg =
+        MessageBuilder::new(&interfaces::WL_REGISTRY_INTERFACE, MessageDirection::Request, 2u32, 0)
+            .and_then(|b| b.uint(1))
+            .and_then(|b| b.string("test_global"))
+            .and_then(|b| b.uint(3))
+            .and_then(|b| b.new_id(3))
+            .and_then(|b| b.build())
+            .unwrap();
+    assert_eq!(msg.args.len(), 4);
+}
+
+#[test]
+fn build_message_errors() {
+    let builder = || {
+        MessageBuilder::named(
+            &interfaces::TEST_GLOBAL_INTERFACE,
+            MessageDirection::Request,
+            3u32,
+            "link",
+        )
+    };
+
+    assert_eq!(
+        MessageBuilder::new(&interfaces::WL_CALLBACK_INTERFACE, MessageDirection::Request, 1u32, 0)
+            .unwrap_err(),
+        MessageBuildError::UnknownOpcode { interface: "wl_callback", opcode: 0 }
+    );
+    assert_eq!(
+        MessageBuilder::named(
+            &interfaces::WL_CALLBACK_INTERFACE,
+            MessageDirection::Event,
+            1u32,
+            "sync"
+        )
+        .unwrap_err(),
+        MessageBuildError::UnknownName { interface: "wl_callback", name: "sync".into() }
+    );
+
+    // the first argument of link is an object
+    assert_eq!(
+        builder().unwrap().uint(5).unwrap_err(),
+        MessageBuildError::UnexpectedArgument {
+            message: "link",
+            index: 0,
+            expected: Some(ArgumentType::Object(AllowNull::No)),
+            found: ArgumentType::Uint,
+        }
+    );
+    let complete = builder()
+        .and_then(|b| b.object(5))
+        .and_then(|b| b.object(0))
+        .and_then(|b| b.uint(7))
+        .unwrap();
+    assert!(matches!(
+        complete.clone().uint(8),
+        Err(MessageBuildError::UnexpectedArgument { index: 3, expected: None, .. })
+    ));
+    assert!(complete.build().is_ok());
+
+    assert_eq!(
+        builder().and_then(|b| b.object(5)).and_then(|b| b.build()).unwrap_err(),
+        MessageBuildError::MissingArguments { message: "link", expected: 3, found: 1 }
+    );
+
+    let many_args = MessageBuilder::named(
+        &interfaces::TEST_GLOBAL_INTERFACE,
+        MessageDirection::Request,
+        3u32,
+        "many_args",
+    )
+    .and_then(|b| b.uint(1))
+    .and_then(|b| b.int(1))
+    .and_then(|b| b.fixed(1.))
+    .and_then(|b| b.array(Vec::new()))
+    .unwrap();
+    assert_eq!(
+        many_args.string("nul\0byte").unwrap_err(),
+        MessageBuildError::NulInString { message: "many_args", index: 4 }
+    );
+}
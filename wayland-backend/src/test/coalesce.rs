@@ -0,0 +1,209 @@
+use std::{
+    ffi::CString,
+    sync::{Arc, Mutex},
+};
+
+use crate::protocol::{ArgumentType, Interface, MessageDesc};
+use crate::rs::client::Coalescing;
+
+use super::protocol_error::misbehaving_server;
+use super::*;
+
+static POINTER_INTERFACE: Interface = Interface {
+    name: "test_pointer",
+    version: 1,
+    requests: &[
+        MessageDesc {
+            name: "damage",
+            signature: &[
+                ArgumentType::Int,
+                ArgumentType::Int,
+                ArgumentType::Int,
+                ArgumentType::Int,
+            ],
+            arg_names: &["x", "y", "width", "height"],
+            since: 1,
+            is_destructor: false,
+            child_interface: None,
+            arg_interfaces: &[],
+            arg_enums: &[],
+        },
+        MessageDesc {
+            name: "commit",
+            signature: &[],
+            arg_names: &[],
+            since: 1,
+            is_destructor: false,
+            child_interface: None,
+            arg_interfaces: &[],
+            arg_enums: &[],
+        },
+    ],
+    events: &[
+        MessageDesc {
+            name: "motion",
+            signature: &[ArgumentType::Uint],
+            arg_names: &["time"],
+            since: 1,
+            is_destructor: false,
+            child_interface: None,
+            arg_interfaces: &[],
+            arg_enums: &[],
+        },
+        MessageDesc {
+            name: "frame",
+            signature: &[],
+            arg_names: &[],
+            since: 1,
+            is_destructor: false,
+            child_interface: None,
+            arg_interfaces: &[],
+            arg_enums: &[],
+        },
+    ],
+    c_ptr: None,
+};
+
+// records the dispatched events as their opcode and first argument
+struct RecordEvents(Mutex<Vec<(u16, Option<u32>)>>);
+
+impl client_rs::ObjectData for RecordEvents {
+    fn event(
+        self: Arc<Self>,
+        _: &mut client_rs::Handle,
+        msg: Message<client_rs::ObjectId>,
+    ) -> Option<Arc<dyn client_rs::ObjectData>> {
+        let arg = match msg.args[..] {
+            [Argument::Uint(time)] => Some(time),
+            _ => None,
+        };
+        self.0.lock().unwrap().push((msg.opcode, arg));
+        None
+    }
+
+    fn destroyed(&self, _: client_rs::ObjectId) {}
+}
+
+// bind the test pointer as id 3
+fn bind_pointer(client: &mut client_rs::Backend) -> (client_rs::ObjectId, Arc<RecordEvents>) {
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(None);
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    let events = Arc::new(RecordEvents(Mutex::new(Vec::new())));
+    let placeholder = client.handle().placeholder_id(Some(&POINTER_INTERFACE));
+    let pointer = client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(CString::new("test_pointer").unwrap())),
+                    Argument::Uint(1),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(events.clone()),
+        )
+        .unwrap();
+    (pointer, events)
+}
+
+#[test]
+fn coalesce_latest_events() {
+    let (mut server, mut client) = misbehaving_server();
+    client.handle().set_coalescing(Coalescing::new().latest_event(
+        "test_pointer",
+        "motion",
+        Some("frame"),
+    ));
+    let (pointer, events) = bind_pointer(&mut client);
+    assert_eq!(pointer.protocol_id(), 3);
+
+    // a burst of motions, partly separated by frames
+    for time in 0..3 {
+        server.write_message(&message!(3, 0, [Argument::Uint(time)])).unwrap();
+    }
+    server.write_message(&message!(3, 1, [])).unwrap();
+    for time in 3..6 {
+        server.write_message(&message!(3, 0, [Argument::Uint(time)])).unwrap();
+        server.write_message(&message!(3, 1, [])).unwrap();
+    }
+    server.flush().unwrap();
+    client.dispatch_events().unwrap();
+    assert_eq!(*events.0.lock().unwrap(), [(0, Some(5)), (1, None)]);
+
+    // a lone motion is dispatched as is
+    server.write_message(&message!(3, 0, [Argument::Uint(6)])).unwrap();
+    server.write_message(&message!(3, 1, [])).unwrap();
+    server.flush().unwrap();
+    client.dispatch_events().unwrap();
+    assert_eq!(*events.0.lock().unwrap(), [(0, Some(5)), (1, None), (0, Some(6)), (1, None)]);
+}
+
+#[test]
+fn coalesce_merged_rects() {
+    let (mut server, mut client) = misbehaving_server();
+    client.handle().set_coalescing(Coalescing::new().merged_rect("test_pointer", "damage"));
+    let (pointer, _) = bind_pointer(&mut client);
+
+    let damage = |x, y, w, h| {
+        message!(
+            pointer.clone(),
+            0,
+            [Argument::Int(x), Argument::Int(y), Argument::Int(w), Argument::Int(h)]
+        )
+    };
+    client.handle().send_request(damage(0, 0, 10, 10), None).unwrap();
+    client.handle().send_request(damage(20, 5, 10, 10), None).unwrap();
+    client.handle().send_request(message!(pointer.clone(), 1, []), None).unwrap();
+    // the damage of the whole surface does not overflow
+    client.handle().send_request(damage(10, 10, i32::MAX, i32::MAX), None).unwrap();
+    client.handle().send_request(damage(0, 0, 1, 1), None).unwrap();
+    client.flush().unwrap();
+
+    let mut requests = Vec::new();
+    server.fill_incoming_buffers().unwrap();
+    // skip the get_registry and bind requests
+    let signatures = [
+        crate::core_interfaces::WL_DISPLAY_INTERFACE.requests[1].signature,
+        crate::core_interfaces::WL_REGISTRY_INTERFACE.requests[0].signature,
+    ];
+    for signature in signatures.iter() {
+        server.read_one_message(|_, _| Some(*signature)).unwrap();
+    }
+    while let Ok(msg) = server.read_one_message(|_, opcode| {
+        POINTER_INTERFACE.requests.get(opcode as usize).map(|d| d.signature)
+    }) {
+        requests.push(msg);
+    }
+
+    assert_eq!(
+        requests,
+        [
+            message!(
+                3,
+                0,
+                [Argument::Int(0), Argument::Int(0), Argument::Int(30), Argument::Int(15)]
+            ),
+            message!(3, 1, []),
+            message!(
+                3,
+                0,
+                [
+                    Argument::Int(0),
+                    Argument::Int(0),
+                    Argument::Int(i32::MAX),
+                    Argument::Int(i32::MAX)
+                ]
+            ),
+        ]
+    );
+}
@@ -0,0 +1,96 @@
+use crate::protocol::{
+    check_interoperable, Interface, InterfaceMismatch, MessageDesc, MessageDirection,
+    MessageMismatch,
+};
+
+use super::interfaces;
+
+// an independently generated copy of the test protocol
+mod other_interfaces {
+    use crate as wayland_backend;
+    wayland_scanner::generate_interfaces!(
+        "../wayland-scanner/tests/scanner_assets/test-protocol.xml"
+    );
+}
+
+const DESTROY: MessageDesc = MessageDesc {
+    name: "destroy",
+    signature: &[],
+    arg_names: &[],
+    since: 2,
+    is_destructor: true,
+    child_interface: None,
+    arg_interfaces: &[],
+    arg_enums: &[],
+};
+
+// versions of `secondary` before and after the introduction of its destructor
+static SECONDARY_V1: Interface =
+    Interface { name: "secondary", version: 1, requests: &[], events: &[], c_ptr: None };
+static SECONDARY_V2: Interface =
+    Interface { name: "secondary", version: 2, requests: &[], events: &[], c_ptr: None };
+static SECONDARY_V3: Interface =
+    Interface { name: "secondary", version: 3, requests: &[DESTROY], events: &[], c_ptr: None };
+
+// a copy of `secondary` whose destructor was mistakenly declared a regular request
+static SECONDARY_NO_DESTRUCTOR: Interface = Interface {
+    name: "secondary",
+    version: 3,
+    requests: &[MessageDesc { is_destructor: false, ..DESTROY }],
+    events: &[],
+    c_ptr: None,
+};
+
+#[test]
+fn independent_copies_are_interoperable() {
+    assert_eq!(check_interoperable(interfaces::INTERFACES, other_interfaces::INTERFACES), Ok(()));
+    for (a, b) in interfaces::INTERFACES.iter().zip(other_interfaces::INTERFACES) {
+        assert!(a.is_compatible_with(b));
+        assert_eq!(a.signature_hash(), b.signature_hash());
+    }
+}
+
+#[test]
+fn compatibility_across_versions() {
+    // the messages not supported by one of the descriptions are ignored
+    assert!(SECONDARY_V1.is_compatible_with(&interfaces::SECONDARY_INTERFACE));
+    assert!(interfaces::SECONDARY_INTERFACE.is_compatible_with(&SECONDARY_V1));
+    assert!(SECONDARY_V3.is_compatible_with(&interfaces::SECONDARY_INTERFACE));
+
+    // a message supported by both versions must be in both descriptions
+    assert_eq!(
+        SECONDARY_V2.check_compatibility(&interfaces::SECONDARY_INTERFACE),
+        Err(InterfaceMismatch::Message {
+            interface: "secondary",
+            direction: MessageDirection::Request,
+            opcode: 0,
+            name: "destroy",
+            kind: MessageMismatch::Missing,
+        })
+    );
+    assert_eq!(
+        check_interoperable(&[&SECONDARY_NO_DESTRUCTOR], interfaces::INTERFACES),
+        Err(InterfaceMismatch::Message {
+            interface: "secondary",
+            direction: MessageDirection::Request,
+            opcode: 0,
+            name: "destroy",
+            kind: MessageMismatch::Destructor,
+        })
+    );
+    assert_eq!(
+        SECONDARY_V3.check_compatibility(&interfaces::TERTIARY_INTERFACE),
+        Err(InterfaceMismatch::Name { expected: "secondary", found: "tertiary" })
+    );
+}
+
+#[test]
+fn signature_hash_covers_the_messages() {
+    assert_eq!(SECONDARY_V3.signature_hash(), interfaces::SECONDARY_INTERFACE.signature_hash());
+    assert_ne!(SECONDARY_V3.signature_hash(), SECONDARY_NO_DESTRUCTOR.signature_hash());
+    assert_ne!(SECONDARY_V3.signature_hash(), SECONDARY_V2.signature_hash());
+    assert_ne!(
+        interfaces::SECONDARY_INTERFACE.signature_hash(),
+        interfaces::TERTIARY_INTERFACE.signature_hash()
+    );
+}
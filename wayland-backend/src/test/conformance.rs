@@ -0,0 +1,185 @@
+use std::{
+    os::unix::io::IntoRawFd,
+    sync::{Arc, Mutex},
+};
+
+use crate::protocol::{
+    check_arguments, AllowNull, ArgEnum, ArgumentType, ConformanceViolation, Interface, MessageDesc,
+};
+
+use super::protocol_error::{bind_test_global, misbehaving_server, send_sync};
+use super::*;
+
+static ENUM_INTERFACE: Interface = Interface {
+    name: "enum_user",
+    version: 1,
+    requests: &[MessageDesc {
+        name: "configure",
+        signature: &[ArgumentType::Uint, ArgumentType::Int, ArgumentType::Object(AllowNull::No)],
+        arg_names: &["mode", "flags", "target"],
+        since: 1,
+        is_destructor: false,
+        child_interface: None,
+        arg_interfaces: &[&crate::protocol::ANONYMOUS_INTERFACE],
+        arg_enums: &[
+            ArgEnum { index: 0, name: "mode", bitfield: false, values: &[0, 1, 2] },
+            ArgEnum { index: 1, name: "flags", bitfield: true, values: &[1, 2, 8] },
+        ],
+    }],
+    events: &[],
+    c_ptr: None,
+};
+
+fn collect_violations() -> (Arc<Mutex<Vec<ConformanceViolation>>>, impl Fn(&ConformanceViolation)) {
+    let violations = Arc::new(Mutex::new(Vec::new()));
+    let sink = violations.clone();
+    (violations, move |violation: &ConformanceViolation| {
+        sink.lock().unwrap().push(violation.clone())
+    })
+}
+
+#[test]
+fn enum_values() {
+    let desc = &ENUM_INTERFACE.requests[0];
+    assert!(desc.arg_enums[0].accepts(2));
+    assert!(!desc.arg_enums[0].accepts(3));
+    assert!(desc.arg_enums[1].accepts(0));
+    assert!(desc.arg_enums[1].accepts(1 | 8));
+    assert!(!desc.arg_enums[1].accepts(4));
+
+    let mut violations = Vec::new();
+    check_arguments(
+        &ENUM_INTERFACE,
+        desc,
+        &message!(3, 0, [Argument::Uint(1), Argument::Int(3), Argument::Object(4)]),
+        |violation| violations.push(violation),
+    );
+    assert!(violations.is_empty());
+
+    check_arguments(
+        &ENUM_INTERFACE,
+        desc,
+        &message!(3, 0, [Argument::Uint(5), Argument::Int(4), Argument::Object(0)]),
+        |violation| violations.push(violation),
+    );
+    assert_eq!(
+        violations,
+        [
+            ConformanceViolation::NullObject {
+                interface: "enum_user",
+                id: 3,
+                message: "configure",
+                arg: "target"
+            },
+            ConformanceViolation::InvalidEnumValue {
+                interface: "enum_user",
+                id: 3,
+                message: "configure",
+                arg: "mode",
+                enum_name: "mode",
+                value: 5
+            },
+            ConformanceViolation::InvalidEnumValue {
+                interface: "enum_user",
+                id: 3,
+                message: "configure",
+                arg: "flags",
+                enum_name: "flags",
+                value: 4
+            },
+        ]
+    );
+}
+
+#[test]
+fn client_event_after_destructor() {
+    let (mut server, mut client) = misbehaving_server();
+    let (violations, hook) = collect_violations();
+    client.handle().set_conformance_hook(Box::new(hook));
+    let callback = send_sync(&mut client);
+
+    // wl_callback.done is a destructor
+    for _ in 0..2 {
+        server.write_message(&message!(callback.protocol_id(), 0, [Argument::Uint(0)])).unwrap();
+    }
+    server.flush().unwrap();
+    client.dispatch_events().unwrap();
+
+    assert_eq!(
+        *violations.lock().unwrap(),
+        [ConformanceViolation::AfterDestructor {
+            interface: "wl_callback",
+            id: 2,
+            message: "done"
+        }]
+    );
+}
+
+#[test]
+fn client_null_object() {
+    let (mut server, mut client) = misbehaving_server();
+    let (violations, hook) = collect_violations();
+    client.handle().set_conformance_hook(Box::new(hook));
+    bind_test_global(&mut client);
+
+    // test_global.ack_secondary does not allow a null secondary
+    server.write_message(&message!(3, 1, [Argument::Object(0)])).unwrap();
+    server.flush().unwrap();
+    client.dispatch_events().unwrap();
+
+    assert_eq!(
+        *violations.lock().unwrap(),
+        [ConformanceViolation::NullObject {
+            interface: "test_global",
+            id: 3,
+            message: "ack_secondary",
+            arg: "sec"
+        }]
+    );
+}
+
+#[test]
+fn client_unexpected_fds() {
+    let (mut server, mut client) = misbehaving_server();
+    let (violations, hook) = collect_violations();
+    client.handle().set_conformance_hook(Box::new(hook));
+    let callback = send_sync(&mut client);
+
+    // a wl_callback.done event carrying a file descriptor
+    let (fd, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+    server
+        .write_message(&message!(
+            callback.protocol_id(),
+            0,
+            [Argument::Uint(0), Argument::Fd(fd.into_raw_fd())]
+        ))
+        .unwrap();
+    server.flush().unwrap();
+    client.dispatch_events().unwrap();
+
+    assert_eq!(*violations.lock().unwrap(), [ConformanceViolation::UnexpectedFds { count: 1 }]);
+}
+
+#[test]
+fn server_unexpected_fds() {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::<()>::new().unwrap();
+    let violations = Arc::new(Mutex::new(Vec::new()));
+    let sink = violations.clone();
+    server.handle().set_conformance_hook(Box::new(move |_, violation| {
+        sink.lock().unwrap().push(violation.clone())
+    }));
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+
+    // a wl_display.sync request carrying a file descriptor
+    let mut socket = crate::rs::socket::BufferedSocket::new(tx);
+    let (fd, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+    socket
+        .write_message(&message!(1, 0, [Argument::NewId(2), Argument::Fd(fd.into_raw_fd())]))
+        .unwrap();
+    socket.flush().unwrap();
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+
+    assert_eq!(*violations.lock().unwrap(), [ConformanceViolation::UnexpectedFds { count: 1 }]);
+}
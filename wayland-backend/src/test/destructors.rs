@@ -2,7 +2,7 @@ use std::{
     ffi::CString,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
@@ -81,7 +81,7 @@ expand_test!(destructor_request, {
 
     // get the registry client-side
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -90,7 +90,7 @@ expand_test!(destructor_request, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     let test_global_id = client
         .handle()
         .send_request(
@@ -131,6 +131,41 @@ expand_test!(destructor_request, {
     assert!(server_data.0.load(Ordering::Acquire));
 });
 
+expand_test!(release_object, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let client_data = Arc::new(ClientData(AtomicBool::new(false)));
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(DoNothingData));
+
+    // wl_registry has no destructor request, release it client-side
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(client_data.clone()),
+        )
+        .unwrap();
+    client.handle().release_object(registry_id.clone()).unwrap();
+
+    assert!(client_data.0.load(Ordering::Acquire));
+    assert!(!matches!(client.handle().info(registry_id.clone()), Ok(info) if info.alive));
+    // releasing it again does nothing
+    client.handle().release_object(registry_id).unwrap();
+
+    // the events sent by the server to the released object are dropped without error
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+    let _ = client.dispatch_events();
+    assert!(client.handle().last_error().is_none());
+});
+
 expand_test!(destructor_cleanup, {
     let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
     let mut server = server_backend::Backend::new().unwrap();
@@ -144,7 +179,7 @@ expand_test!(destructor_cleanup, {
 
     // get the registry client-side
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -153,7 +188,7 @@ expand_test!(destructor_cleanup, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     client
         .handle()
         .send_request(
@@ -188,6 +223,269 @@ expand_test!(destructor_cleanup, {
     assert!(server_data.0.load(Ordering::Acquire));
 });
 
+type HookLog = Arc<Mutex<Vec<&'static str>>>;
+
+fn log_hook(log: &HookLog, entry: &'static str) {
+    log.lock().unwrap().push(entry);
+}
+
+struct ServerHookData(HookLog);
+
+macro_rules! impl_server_hookdata {
+    ($server_backend:tt) => {
+        impl $server_backend::ObjectData<()> for ServerHookData {
+            fn request(
+                self: Arc<Self>,
+                _: &mut $server_backend::Handle<()>,
+                _: &mut (),
+                _: $server_backend::ClientId,
+                _: Message<$server_backend::ObjectId>,
+            ) -> Option<Arc<dyn $server_backend::ObjectData<()>>> {
+                None
+            }
+
+            fn destroyed(&self, _: $server_backend::ClientId, _: $server_backend::ObjectId) {
+                log_hook(&self.0, "destroyed");
+            }
+        }
+
+        impl $server_backend::GlobalHandler<()> for ServerHookData {
+            fn bind(
+                self: Arc<Self>,
+                handle: &mut $server_backend::Handle<()>,
+                _: &mut (),
+                _: $server_backend::ClientId,
+                _: $server_backend::GlobalId,
+                object_id: $server_backend::ObjectId,
+            ) -> Arc<dyn $server_backend::ObjectData<()>> {
+                for entry in ["first", "second"] {
+                    let log = self.0.clone();
+                    handle
+                        .add_destruction_hook(
+                            object_id.clone(),
+                            Box::new(move |_, _| log_hook(&log, entry)),
+                        )
+                        .unwrap();
+                }
+                self
+            }
+        }
+    };
+}
+
+impl_server_hookdata!(server_rs);
+impl_server_hookdata!(server_sys);
+
+struct ClientHookData(HookLog);
+
+macro_rules! impl_client_hookdata {
+    ($client_backend:tt) => {
+        impl $client_backend::ObjectData for ClientHookData {
+            fn event(
+                self: Arc<Self>,
+                _: &mut $client_backend::Handle,
+                _: Message<$client_backend::ObjectId>,
+            ) -> Option<Arc<dyn $client_backend::ObjectData>> {
+                None
+            }
+            fn destroyed(&self, _object_id: $client_backend::ObjectId) {
+                log_hook(&self.0, "destroyed");
+            }
+        }
+    };
+}
+
+impl_client_hookdata!(client_rs);
+impl_client_hookdata!(client_sys);
+
+expand_test!(destruction_hooks, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let server_log = HookLog::default();
+    let client_log = HookLog::default();
+
+    server.handle().create_global(
+        &interfaces::TEST_GLOBAL_INTERFACE,
+        3,
+        Arc::new(ServerHookData(server_log.clone())),
+    );
+
+    // get the registry client-side
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    // create the test global
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    let test_global_id = client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(
+                        CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes()).unwrap(),
+                    )),
+                    Argument::Uint(3),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(ClientHookData(client_log.clone()))),
+        )
+        .unwrap();
+
+    for entry in ["first", "second"] {
+        let log = client_log.clone();
+        client
+            .handle()
+            .add_destruction_hook(test_global_id.clone(), Box::new(move |_| log_hook(&log, entry)))
+            .unwrap();
+    }
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+
+    client
+        .handle()
+        .send_request(
+            message!(
+                test_global_id.clone(),
+                4, // destroy
+                []
+            ),
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(*client_log.lock().unwrap(), ["destroyed", "first", "second"]);
+    assert!(client.handle().add_destruction_hook(test_global_id, Box::new(|_| {})).is_err());
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+
+    assert_eq!(*server_log.lock().unwrap(), ["destroyed", "first", "second"]);
+});
+
+// destroys another object when it is destroyed
+struct CascadeData<Id>(Mutex<Option<Id>>);
+
+macro_rules! impl_client_cascadedata {
+    ($client_backend:tt) => {
+        impl $client_backend::ObjectData for CascadeData<$client_backend::ObjectId> {
+            fn event(
+                self: Arc<Self>,
+                _: &mut $client_backend::Handle,
+                _: Message<$client_backend::ObjectId>,
+            ) -> Option<Arc<dyn $client_backend::ObjectData>> {
+                None
+            }
+            fn destroyed(&self, _object_id: $client_backend::ObjectId) {
+                unreachable!()
+            }
+            fn destroyed_with_handle(
+                &self,
+                handle: &mut $client_backend::Handle,
+                _object_id: $client_backend::ObjectId,
+            ) {
+                if let Some(dependent) = self.0.lock().unwrap().take() {
+                    handle.send_request(message!(dependent, 4, []), None).unwrap();
+                }
+            }
+        }
+    };
+}
+
+impl_client_cascadedata!(client_rs);
+impl_client_cascadedata!(client_sys);
+
+expand_test!(destructor_cascade, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let server_log = HookLog::default();
+    let client_log = HookLog::default();
+
+    server.handle().create_global(
+        &interfaces::TEST_GLOBAL_INTERFACE,
+        3,
+        Arc::new(ServerHookData(server_log.clone())),
+    );
+
+    // get the registry client-side
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    // bind the test global twice, the first object depending on the second one
+    let cascade_data = Arc::new(CascadeData(Mutex::new(None)));
+    let mut bind = |data: Arc<dyn client_backend::ObjectData>| {
+        let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+        client
+            .handle()
+            .send_request(
+                message!(
+                    registry_id.clone(),
+                    0,
+                    [
+                        Argument::Uint(1),
+                        Argument::Str(Box::new(
+                            CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes())
+                                .unwrap(),
+                        )),
+                        Argument::Uint(3),
+                        Argument::NewId(placeholder),
+                    ],
+                ),
+                Some(data),
+            )
+            .unwrap()
+    };
+    let parent_id = bind(cascade_data.clone());
+    let dependent_id = bind(Arc::new(ClientHookData(client_log.clone())));
+    *cascade_data.0.lock().unwrap() = Some(dependent_id.clone());
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+
+    // destroying the parent destroys the dependent object from its object data
+    client
+        .handle()
+        .send_request(
+            message!(
+                parent_id.clone(),
+                4, // destroy
+                []
+            ),
+            None,
+        )
+        .unwrap();
+    assert_eq!(*client_log.lock().unwrap(), ["destroyed"]);
+    assert!(client.handle().info(dependent_id).is_err());
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+
+    let server_log = server_log.lock().unwrap();
+    assert_eq!(server_log.iter().filter(|&&entry| entry == "destroyed").count(), 2);
+});
+
 struct ServerClientData(AtomicBool);
 
 macro_rules! impl_server_clientdata {
@@ -220,3 +518,63 @@ expand_test!(destructor_client_cleanup, {
 
     assert!(client_data.0.load(Ordering::Acquire));
 });
+
+#[test]
+fn destroyed_object_info() {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_rs::Backend::connect(tx).unwrap();
+
+    server.handle().create_global(
+        &interfaces::TEST_GLOBAL_INTERFACE,
+        3,
+        Arc::new(ServerData(AtomicBool::new(false))),
+    );
+
+    // get the registry client-side
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    // create the test global
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    let test_global_id = client
+        .handle()
+        .send_request(
+            message!(
+                registry_id.clone(),
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(
+                        CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes()).unwrap(),
+                    )),
+                    Argument::Uint(3),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(ClientData(AtomicBool::new(false)))),
+        )
+        .unwrap();
+
+    let infos = client.handle().info_many(&[registry_id.clone(), test_global_id.clone()]);
+    assert!(infos.iter().all(|info| info.as_ref().unwrap().alive));
+    assert_eq!(infos[1].as_ref().unwrap().version, 3);
+
+    // destroy the test global, its id is not released until the server acknowledges it
+    client.handle().send_request(message!(test_global_id.clone(), 4, []), None).unwrap();
+
+    assert!(client.handle().info(test_global_id.clone()).is_err());
+    let infos = client.handle().info_many(&[registry_id, test_global_id.clone()]);
+    assert!(infos[0].as_ref().unwrap().alive);
+    let global_info = *infos[1].as_ref().unwrap();
+    assert!(!global_info.alive);
+    assert_eq!(global_info.id, test_global_id.protocol_id());
+    assert_eq!(global_info.interface.name, interfaces::TEST_GLOBAL_INTERFACE.name);
+}
@@ -0,0 +1,85 @@
+use std::ffi::CString;
+
+use crate::protocol::{format_message, CaptureFormatter, MessageDirection};
+
+use super::*;
+
+fn string(text: &str) -> Argument<u32> {
+    Argument::Str(Box::new(CString::new(text.as_bytes()).unwrap()))
+}
+
+#[test]
+fn format_single_message() {
+    let many_args = message!(
+        3,
+        0,
+        [
+            Argument::Uint(42),
+            Argument::Int(-13),
+            Argument::Fixed(640),
+            Argument::Array(Box::new(vec![1, 2, 3])),
+            string("I like trains"),
+            Argument::Fd(4),
+        ],
+    );
+    assert_eq!(
+        format_message(&many_args, &interfaces::TEST_GLOBAL_INTERFACE, MessageDirection::Request),
+        r#"test_global@3.many_args(42, -13, 2.5, array[3], "I like trains", fd 4)"#
+    );
+
+    let link = message!(3, 3, [Argument::Object(5), Argument::Object(0), Argument::Uint(7)]);
+    assert_eq!(
+        format_message(&link, &interfaces::TEST_GLOBAL_INTERFACE, MessageDirection::Request),
+        "test_global@3.link(secondary@5, nil, 7)"
+    );
+
+    let cycle_quad = message!(3, 2, [Argument::NewId(0xFF00_0000), Argument::Object(0)]);
+    assert_eq!(
+        format_message(&cycle_quad, &interfaces::TEST_GLOBAL_INTERFACE, MessageDirection::Event),
+        "test_global@3.cycle_quad(new id quad@4278190080, nil)"
+    );
+}
+
+#[test]
+fn format_capture() {
+    let mut formatter = CaptureFormatter::new(&[&interfaces::TEST_GLOBAL_INTERFACE]);
+
+    assert_eq!(
+        formatter.format(&message!(1, 1, [Argument::NewId(2)]), MessageDirection::Request),
+        "wl_display@1.get_registry(new id wl_registry@2)"
+    );
+    assert_eq!(
+        formatter.format(
+            &message!(
+                2,
+                0,
+                [Argument::Uint(1), string("test_global"), Argument::Uint(3), Argument::NewId(3)]
+            ),
+            MessageDirection::Request
+        ),
+        r#"wl_registry@2.bind(1, "test_global", 3, new id test_global@3)"#
+    );
+    assert_eq!(formatter.interface_of(3).map(|i| i.name), Some("test_global"));
+
+    // the interface of untyped object arguments is known from the capture
+    assert_eq!(
+        formatter.format(
+            &message!(1, 0, [Argument::Object(3), Argument::Uint(0), string("oops")]),
+            MessageDirection::Event
+        ),
+        r#"wl_display@1.error(test_global@3, 0, "oops")"#
+    );
+
+    // the object is forgotten once its destruction is acknowledged
+    assert_eq!(
+        formatter.format(&message!(3, 4, []), MessageDirection::Request),
+        "test_global@3.destroy()"
+    );
+    assert!(formatter.interface_of(3).is_some());
+    formatter.format(&message!(1, 1, [Argument::Uint(3)]), MessageDirection::Event);
+    assert!(formatter.interface_of(3).is_none());
+    assert_eq!(
+        formatter.format(&message!(3, 0, [Argument::Uint(1)]), MessageDirection::Request),
+        "[unknown]@3.0(1)"
+    );
+}
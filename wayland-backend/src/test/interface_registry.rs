@@ -0,0 +1,127 @@
+use std::ffi::CString;
+
+use crate::protocol::{
+    lookup_interface, register_interfaces, same_interface, CaptureFormatter, Interface,
+    MessageDirection,
+};
+
+use super::*;
+
+// an older version of `test_global`, without any message
+static OLD_TEST_GLOBAL_INTERFACE: Interface =
+    Interface { name: "test_global", version: 1, requests: &[], events: &[], c_ptr: None };
+
+#[test]
+fn lookup_registered_interfaces() {
+    // the core interfaces are always known
+    assert_eq!(lookup_interface("wl_registry").map(|i| i.version), Some(1));
+    assert!(lookup_interface("unknown").is_none());
+
+    register_interfaces(interfaces::INTERFACES);
+    let tertiary = lookup_interface("tertiary").unwrap();
+    assert!(same_interface(tertiary, &interfaces::TERTIARY_INTERFACE));
+
+    // the most recent version of an interface is kept
+    register_interfaces(&[&OLD_TEST_GLOBAL_INTERFACE]);
+    assert_eq!(lookup_interface("test_global").map(|i| i.version), Some(3));
+}
+
+#[test]
+fn format_capture_with_registered_interfaces() {
+    register_interfaces(interfaces::INTERFACES);
+
+    // the formatter is not given the interface of the bound global
+    let mut formatter = CaptureFormatter::new(&[]);
+    formatter.format(&message!(1, 1, [Argument::NewId(2)]), MessageDirection::Request);
+    formatter.format(
+        &message!(
+            2,
+            0,
+            [
+                Argument::Uint(1),
+                Argument::Str(Box::new(CString::new("secondary").unwrap())),
+                Argument::Uint(1),
+                Argument::NewId(3)
+            ]
+        ),
+        MessageDirection::Request,
+    );
+    assert_eq!(formatter.interface_of(3).map(|i| i.name), Some("secondary"));
+}
+
+// bind a global with an anonymous placeholder, its interface being found in the registry
+expand_test!(bind_with_anonymous_placeholder, {
+    register_interfaces(interfaces::INTERFACES);
+
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::<()>::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(DoNothingData));
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(None);
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    let placeholder = client.handle().placeholder_id(None);
+    let test_global_id = client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(CString::new("test_global").unwrap())),
+                    Argument::Uint(2),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+
+    let info = client.handle().info(test_global_id).unwrap();
+    assert!(same_interface(info.interface, &interfaces::TEST_GLOBAL_INTERFACE));
+    assert_eq!(info.version, 2);
+});
+
+expand_test!(panic bind_unknown_interface_with_anonymous_placeholder, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::<()>::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(None);
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    let placeholder = client.handle().placeholder_id(None);
+    client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(CString::new("unknown").unwrap())),
+                    Argument::Uint(1),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+});
@@ -120,7 +120,7 @@ expand_test!(many_args, {
 
     // get the registry client-side
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -129,7 +129,7 @@ expand_test!(many_args, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     let test_global_id = client
         .handle()
         .send_request(
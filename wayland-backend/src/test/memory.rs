@@ -0,0 +1,99 @@
+use std::ffi::CString;
+
+use super::*;
+
+fn sync(client: &mut client_rs::Backend) {
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+    client
+        .handle()
+        .send_request(
+            message!(client_display, 0, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+}
+
+#[test]
+fn memory_usage() {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::<()>::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_rs::Backend::connect(tx).unwrap();
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(DoNothingData));
+
+    let initial = client.handle().memory_usage();
+    assert_eq!(initial.objects, 1);
+    assert_eq!(initial.zombie_objects, 0);
+    assert!(initial.socket_buffers > 0);
+
+    // bind the test global and destroy it right away
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    let test_global_id = client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(
+                        CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes()).unwrap(),
+                    )),
+                    Argument::Uint(3),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    client.handle().send_request(message!(test_global_id, 4, []), None).unwrap();
+    // and a burst of callbacks
+    for _ in 0..100 {
+        sync(&mut client);
+    }
+
+    // the destroyed object is kept until the server acknowledges it
+    let burst = client.handle().memory_usage();
+    assert_eq!(burst.objects, 103);
+    assert_eq!(burst.zombie_objects, 1);
+    assert!(burst.object_map > initial.object_map);
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+    client.dispatch_events().unwrap();
+
+    let usage = client.handle().memory_usage();
+    assert_eq!(usage.objects, 2);
+    assert_eq!(usage.zombie_objects, 0);
+    assert_eq!(usage.queued_messages, 0);
+    assert!(usage.object_map >= burst.object_map);
+    assert_eq!(server.handle().memory_usage().objects, 2);
+
+    // the memory of the burst is released
+    client.shrink_to_fit();
+    server.shrink_to_fit();
+    let shrunk = client.handle().memory_usage();
+    assert_eq!(shrunk.objects, 2);
+    assert!(shrunk.object_map < usage.object_map);
+    assert_eq!(shrunk.total(), shrunk.socket_buffers + shrunk.object_map);
+
+    // and the connection keeps working
+    sync(&mut client);
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+    client.dispatch_events().unwrap();
+    assert_eq!(client.handle().memory_usage().objects, 2);
+    assert!(client.handle().last_error().is_none());
+}
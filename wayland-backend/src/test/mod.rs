@@ -54,11 +54,27 @@ mod interfaces {
     );
 }
 
+mod builder;
+mod coalesce;
+mod compat;
+mod conformance;
 mod destructors;
+mod format;
+mod interface_registry;
 mod many_args;
+mod memory;
 mod object_args;
 mod protocol_error;
+mod proxy;
+mod reentrancy;
+mod region;
+mod registry_cache;
+mod replay;
 mod server_created_objects;
+mod shim;
+mod signature;
+mod static_dispatch;
+mod stats;
 mod sync;
 
 /*
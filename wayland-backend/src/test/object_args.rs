@@ -124,7 +124,7 @@ expand_test!(create_objects, {
 
     // get the registry client-side
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -133,7 +133,7 @@ expand_test!(create_objects, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     let test_global_id = client
         .handle()
         .send_request(
@@ -221,7 +221,7 @@ expand_test!(panic bad_interface, {
     // get the registry client-side
     let client_display = client.handle().display_id();
     let placeholder =
-        client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+        client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -230,7 +230,7 @@ expand_test!(panic bad_interface, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     let test_global_id = client
         .handle()
         .send_request(
@@ -288,7 +288,7 @@ expand_test!(panic double_null, {
     // get the registry client-side
     let client_display = client.handle().display_id();
     let placeholder =
-        client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+        client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -298,7 +298,7 @@ expand_test!(panic double_null, {
         .unwrap();
     // create the test global
     let placeholder =
-        client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+        client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     let test_global_id = client
         .handle()
         .send_request(
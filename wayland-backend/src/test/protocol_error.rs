@@ -55,7 +55,7 @@ expand_test!(protocol_error, {
 
     // get the registry client-side
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -64,7 +64,7 @@ expand_test!(protocol_error, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     client
         .handle()
         .send_request(
@@ -185,3 +185,238 @@ expand_test!(client_wrong_sender, {
     let ret = socket.fill_incoming_buffers().and_then(|_| socket.fill_incoming_buffers());
     assert!(ret.is_err());
 });
+
+struct LogClientData(Arc<Mutex<Vec<&'static str>>>);
+
+impl server_rs::ClientData<()> for LogClientData {
+    fn initialized(&self, _: server_rs::ClientId) {}
+    fn disconnected(&self, _: server_rs::ClientId, _: server_rs::DisconnectReason) {
+        self.0.lock().unwrap().push("disconnected");
+    }
+}
+
+impl server_sys::ClientData<()> for LogClientData {
+    fn initialized(&self, _: server_sys::ClientId) {}
+    fn disconnected(&self, _: server_sys::ClientId, _: server_rs::DisconnectReason) {
+        self.0.lock().unwrap().push("disconnected");
+    }
+}
+
+expand_test!(disconnect_hook, {
+    let mut server = server_backend::Backend::<()>::new().unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let hook_log = log.clone();
+    server.handle().set_disconnect_hook(Box::new(move |_, reason| {
+        hook_log.lock().unwrap().push(match reason {
+            server_rs::DisconnectReason::ConnectionClosed => "hook: closed",
+            server_rs::DisconnectReason::ProtocolError(_) => "hook: protocol error",
+        });
+    }));
+
+    // a client sending an invalid request
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(LogClientData(log.clone()))).unwrap();
+    let mut socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(tx.into_raw_fd()) });
+    socket
+        .write_message(&Message {
+            sender_id: 1, // wl_display
+            opcode: 42,   // inexistant
+            args: smallvec::smallvec![],
+        })
+        .unwrap();
+    socket.flush().unwrap();
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    let entries = std::mem::take(&mut *log.lock().unwrap());
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].starts_with("hook: "));
+    assert_eq!(entries[1], "disconnected");
+
+    // a client closing its connection
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(LogClientData(log.clone()))).unwrap();
+    drop(tx);
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    assert_eq!(*log.lock().unwrap(), ["hook: closed", "disconnected"]);
+});
+
+// A rust client connected to a raw socket, to check its handling of a misbehaving server
+pub(super) fn misbehaving_server() -> (BufferedSocket, client_rs::Backend) {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let server = BufferedSocket::new(unsafe { Socket::from_raw_fd(rx.into_raw_fd()) });
+    let client = client_rs::Backend::connect(tx).unwrap();
+    (server, client)
+}
+
+pub(super) fn send_sync(client: &mut client_rs::Backend) -> client_rs::ObjectId {
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(None);
+    client
+        .handle()
+        .send_request(
+            message!(client_display, 0, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap()
+}
+
+fn expect_protocol_error(client: &mut client_rs::Backend, expected: &str) {
+    match client.dispatch_events() {
+        Err(client_rs::WaylandError::Protocol(err)) => {
+            assert!(err.message.contains(expected), "Unexpected error: {}", err.message)
+        }
+        ret => panic!("Bad ret: {:?}", ret),
+    }
+}
+
+// bind the test global as id 3, with a client data accepting the objects it creates
+pub(super) fn bind_test_global(client: &mut client_rs::Backend) {
+    struct QuadCreator;
+
+    impl client_rs::ObjectData for QuadCreator {
+        fn event(
+            self: Arc<Self>,
+            _: &mut client_rs::Handle,
+            msg: Message<client_rs::ObjectId>,
+        ) -> Option<Arc<dyn client_rs::ObjectData>> {
+            if msg.args.iter().any(|arg| matches!(arg, Argument::NewId(_))) {
+                Some(Arc::new(DoNothingData))
+            } else {
+                None
+            }
+        }
+
+        fn destroyed(&self, _: client_rs::ObjectId) {}
+    }
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(None);
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(CString::new("test_global").unwrap())),
+                    Argument::Uint(3),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(QuadCreator)),
+        )
+        .unwrap();
+}
+
+#[test]
+fn server_double_delete_id() {
+    let (mut server, mut client) = misbehaving_server();
+    let callback = send_sync(&mut client);
+
+    // wl_callback.done, and twice wl_display.delete_id
+    server.write_message(&message!(callback.protocol_id(), 0, [Argument::Uint(0)])).unwrap();
+    server.write_message(&message!(1, 1, [Argument::Uint(callback.protocol_id())])).unwrap();
+    server.write_message(&message!(1, 1, [Argument::Uint(callback.protocol_id())])).unwrap();
+    server.flush().unwrap();
+
+    expect_protocol_error(&mut client, "delete_id for unknown object 2");
+}
+
+#[test]
+fn server_delete_live_id() {
+    let (mut server, mut client) = misbehaving_server();
+    let callback = send_sync(&mut client);
+
+    server.write_message(&message!(1, 1, [Argument::Uint(callback.protocol_id())])).unwrap();
+    server.flush().unwrap();
+
+    expect_protocol_error(&mut client, "delete_id for wl_callback@2, which is not destroyed");
+}
+
+#[test]
+fn server_reuse_live_id() {
+    let (mut server, mut client) = misbehaving_server();
+    bind_test_global(&mut client);
+
+    // test_global.cycle_quad, twice creating the same quad
+    for _ in 0..2 {
+        server
+            .write_message(&message!(3, 2, [Argument::NewId(0xFF00_0000), Argument::Object(0)]))
+            .unwrap();
+    }
+    server.flush().unwrap();
+
+    expect_protocol_error(
+        &mut client,
+        "with id 4278190080, which is still used by quad@4278190080",
+    );
+}
+
+#[test]
+fn server_create_in_client_range() {
+    let (mut server, mut client) = misbehaving_server();
+    bind_test_global(&mut client);
+
+    server.write_message(&message!(3, 2, [Argument::NewId(4), Argument::Object(0)])).unwrap();
+    server.flush().unwrap();
+
+    expect_protocol_error(&mut client, "with id 4, outside of the server id range");
+}
+
+#[test]
+fn server_event_to_unknown_object() {
+    let (mut server, mut client) = misbehaving_server();
+
+    server.write_message(&message!(0xFF00_0005, 0, [])).unwrap();
+    server.flush().unwrap();
+
+    expect_protocol_error(&mut client, "event to unknown object 4278190085");
+}
+
+#[test]
+fn server_unknown_opcode() {
+    let (mut server, mut client) = misbehaving_server();
+
+    server.write_message(&message!(1, 5, [])).unwrap();
+    server.flush().unwrap();
+
+    expect_protocol_error(&mut client, "unknown opcode 5 to wl_display@1");
+}
+
+#[test]
+fn server_unknown_opcode_skipped() {
+    let (mut server, mut client) = misbehaving_server();
+    client.handle().set_skip_unknown_opcodes(true);
+    let callback = send_sync(&mut client);
+
+    // an event from a newer wl_callback, then wl_callback.done and wl_display.delete_id
+    server
+        .write_message(&message!(callback.protocol_id(), 1, [Argument::Uint(1), Argument::Int(-1)]))
+        .unwrap();
+    server.write_message(&message!(callback.protocol_id(), 0, [Argument::Uint(0)])).unwrap();
+    server.write_message(&message!(1, 1, [Argument::Uint(callback.protocol_id())])).unwrap();
+    server.flush().unwrap();
+
+    assert_eq!(client.dispatch_events().unwrap(), 1);
+    assert!(client.handle().info(callback).is_err());
+
+    // events to unknown objects are still protocol errors
+    server.write_message(&message!(0xFF00_0005, 0, [])).unwrap();
+    server.flush().unwrap();
+
+    expect_protocol_error(&mut client, "event to unknown object 4278190085");
+}
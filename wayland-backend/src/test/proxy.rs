@@ -0,0 +1,203 @@
+use std::{
+    ffi::CString,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::protocol::ObjectInfo;
+use crate::rs::proxy::{ProxyConnection, ProxyFilter};
+
+use super::*;
+
+struct ServerData(AtomicBool);
+
+macro_rules! impl_server_objectdata {
+    ($server_backend:tt) => {
+        impl $server_backend::ObjectData<()> for ServerData {
+            fn request(
+                self: Arc<Self>,
+                _: &mut $server_backend::Handle<()>,
+                _: &mut (),
+                _: $server_backend::ClientId,
+                _: Message<$server_backend::ObjectId>,
+            ) -> Option<Arc<dyn $server_backend::ObjectData<()>>> {
+                None
+            }
+
+            fn destroyed(&self, _: $server_backend::ClientId, _: $server_backend::ObjectId) {
+                self.0.store(true, Ordering::Release);
+            }
+        }
+
+        impl $server_backend::GlobalHandler<()> for ServerData {
+            fn bind(
+                self: Arc<Self>,
+                _: &mut $server_backend::Handle<()>,
+                _: &mut (),
+                _: $server_backend::ClientId,
+                _: $server_backend::GlobalId,
+                _: $server_backend::ObjectId,
+            ) -> Arc<dyn $server_backend::ObjectData<()>> {
+                self
+            }
+        }
+    };
+}
+
+impl_server_objectdata!(server_rs);
+impl_server_objectdata!(server_sys);
+
+// records the globals advertized to the client
+struct RegistryData(Mutex<Vec<String>>);
+
+macro_rules! impl_client_objectdata {
+    ($client_backend:tt) => {
+        impl $client_backend::ObjectData for RegistryData {
+            fn event(
+                self: Arc<Self>,
+                _: &mut $client_backend::Handle,
+                msg: Message<$client_backend::ObjectId>,
+            ) -> Option<Arc<dyn $client_backend::ObjectData>> {
+                if let [_, Argument::Str(ref interface), _] = msg.args[..] {
+                    self.0.lock().unwrap().push(interface.to_string_lossy().into_owned());
+                }
+                None
+            }
+
+            fn destroyed(&self, _object_id: $client_backend::ObjectId) {}
+        }
+    };
+}
+
+impl_client_objectdata!(client_rs);
+impl_client_objectdata!(client_sys);
+
+// hides the `secondary` global and counts the relayed requests
+struct HideSecondary {
+    requests: usize,
+}
+
+impl ProxyFilter for HideSecondary {
+    fn request(&mut self, _: ObjectInfo, msg: Message<u32>) -> Option<Message<u32>> {
+        self.requests += 1;
+        Some(msg)
+    }
+
+    fn event(&mut self, object: ObjectInfo, msg: Message<u32>) -> Option<Message<u32>> {
+        if object.interface.name == "wl_registry" {
+            if let [_, Argument::Str(ref interface), _] = msg.args[..] {
+                if interface.to_bytes() == b"secondary" {
+                    return None;
+                }
+            }
+        }
+        Some(msg)
+    }
+}
+
+fn bind_message(registry_id: u32, name: u32, interface: &str, new_id: u32) -> Message<u32> {
+    message!(
+        registry_id,
+        0,
+        [
+            Argument::Uint(name),
+            Argument::Str(Box::new(CString::new(interface.as_bytes()).unwrap())),
+            Argument::Uint(1),
+            Argument::NewId(new_id),
+        ],
+    )
+}
+
+expand_test!(proxy_relay, {
+    let (client_socket, proxy_client) = std::os::unix::net::UnixStream::pair().unwrap();
+    let (proxy_server, server_socket) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(server_socket, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(client_socket).unwrap();
+    let mut proxy = ProxyConnection::new(
+        proxy_client,
+        proxy_server,
+        &[&interfaces::TEST_GLOBAL_INTERFACE],
+        HideSecondary { requests: 0 },
+    );
+
+    let server_data = Arc::new(ServerData(AtomicBool::new(false)));
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 1, server_data.clone());
+    server.handle().create_global(&interfaces::SECONDARY_INTERFACE, 1, server_data.clone());
+
+    // get the registry client-side
+    let registry_data = Arc::new(RegistryData(Mutex::new(Vec::new())));
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(registry_data.clone()),
+        )
+        .unwrap();
+    client.flush().unwrap();
+
+    assert_eq!(proxy.relay_requests().unwrap(), 1);
+    proxy.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    // both globals go through the proxy, but only one reaches the client
+    assert_eq!(proxy.relay_events().unwrap(), 2);
+    proxy.flush().unwrap();
+    client.dispatch_events().unwrap();
+    assert_eq!(*registry_data.0.lock().unwrap(), ["test_global"]);
+
+    // bind and destroy the test global
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    let test_global_id = client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(CString::new("test_global".as_bytes()).unwrap())),
+                    Argument::Uint(1),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    client.handle().send_request(message!(test_global_id, 4, []), None).unwrap();
+    client.flush().unwrap();
+
+    assert_eq!(proxy.relay_requests().unwrap(), 2);
+    assert_eq!(proxy.filter().requests, 3);
+    proxy.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    assert!(server_data.0.load(Ordering::Acquire));
+});
+
+// binding an interface unknown to the proxy is an error
+#[test]
+fn proxy_unknown_interface() {
+    let (client_socket, proxy_client) = std::os::unix::net::UnixStream::pair().unwrap();
+    let (proxy_server, _server_socket) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut proxy = ProxyConnection::new(
+        proxy_client,
+        proxy_server,
+        &[&interfaces::TEST_GLOBAL_INTERFACE],
+        crate::rs::proxy::ForwardAll,
+    );
+    let mut client = crate::rs::socket::BufferedSocket::new(client_socket);
+
+    client.write_message(&message!(1, 1, [Argument::NewId(2)])).unwrap();
+    client.write_message(&bind_message(2, 1, "test_global", 3)).unwrap();
+    client.flush().unwrap();
+    assert_eq!(proxy.relay_requests().unwrap(), 2);
+
+    client.write_message(&bind_message(2, 2, "secondary", 4)).unwrap();
+    client.flush().unwrap();
+    assert!(proxy.relay_requests().is_err());
+}
@@ -0,0 +1,243 @@
+use std::{
+    ffi::CString,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use super::*;
+
+struct ServerData(AtomicBool);
+
+macro_rules! impl_server_objectdata {
+    ($server_backend:tt) => {
+        impl $server_backend::ObjectData<()> for ServerData {
+            fn request(
+                self: Arc<Self>,
+                _: &mut $server_backend::Handle<()>,
+                _: &mut (),
+                _: $server_backend::ClientId,
+                _: Message<$server_backend::ObjectId>,
+            ) -> Option<Arc<dyn $server_backend::ObjectData<()>>> {
+                None
+            }
+
+            fn destroyed(&self, _: $server_backend::ClientId, _: $server_backend::ObjectId) {
+                self.0.store(true, Ordering::Release);
+            }
+        }
+
+        impl $server_backend::GlobalHandler<()> for ServerData {
+            fn bind(
+                self: Arc<Self>,
+                handle: &mut $server_backend::Handle<()>,
+                _: &mut (),
+                client: $server_backend::ClientId,
+                _: $server_backend::GlobalId,
+                object_id: $server_backend::ObjectId,
+            ) -> Arc<dyn $server_backend::ObjectData<()>> {
+                // send a cycle_quad event creating a new object
+                let quad = handle
+                    .create_object(client, &interfaces::QUAD_INTERFACE, 3, Arc::new(DoNothingData))
+                    .unwrap();
+                let null_id = handle.null_id();
+                handle
+                    .send_event(message!(
+                        object_id,
+                        2,
+                        [Argument::NewId(quad), Argument::Object(null_id)],
+                    ))
+                    .unwrap();
+                self
+            }
+        }
+    };
+}
+
+impl_server_objectdata!(server_rs);
+impl_server_objectdata!(server_sys);
+
+// counts the notifications of destruction of its objects
+struct CountingData(AtomicUsize);
+
+// destroys the objects of the events it receives, from within its callback
+struct ReentrantData {
+    destroyed: AtomicUsize,
+    child: Arc<CountingData>,
+}
+
+// replaces its object data from within its callback
+struct ReplacingData {
+    destroyed: AtomicUsize,
+    replacement: Arc<CountingData>,
+}
+
+macro_rules! impl_client_objectdata {
+    ($client_backend:tt) => {
+        impl $client_backend::ObjectData for CountingData {
+            fn event(
+                self: Arc<Self>,
+                _: &mut $client_backend::Handle,
+                _: Message<$client_backend::ObjectId>,
+            ) -> Option<Arc<dyn $client_backend::ObjectData>> {
+                None
+            }
+
+            fn destroyed(&self, _: $client_backend::ObjectId) {
+                self.0.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+
+        impl $client_backend::ObjectData for ReentrantData {
+            fn event(
+                self: Arc<Self>,
+                handle: &mut $client_backend::Handle,
+                msg: Message<$client_backend::ObjectId>,
+            ) -> Option<Arc<dyn $client_backend::ObjectData>> {
+                assert_eq!(msg.opcode, 2);
+                let quad = match &msg.args[..] {
+                    [Argument::NewId(quad), Argument::Object(_)] => quad.clone(),
+                    _ => panic!("Bad argument list!"),
+                };
+                let id = msg.sender_id;
+
+                // the handle can be used on the object being dispatched
+                assert_eq!(handle.info(id.clone()).unwrap().interface.name, "test_global");
+                let data = handle.get_data(id.clone()).unwrap();
+                handle.set_data(id.clone(), data).unwrap();
+                assert_eq!(handle.info(quad.clone()).unwrap().interface.name, "quad");
+
+                // destroy the new object before its data is set
+                handle.send_request(message!(quad.clone(), 0, []), None).unwrap();
+                assert!(handle.info(quad).is_err());
+
+                // destroy the object being dispatched
+                handle.send_request(message!(id.clone(), 4, []), None).unwrap();
+                assert!(handle.info(id.clone()).is_err());
+                assert!(handle.send_request(message!(id, 4, []), None).is_err());
+
+                Some(self.child.clone())
+            }
+
+            fn destroyed(&self, _: $client_backend::ObjectId) {
+                self.destroyed.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+
+        impl $client_backend::ObjectData for ReplacingData {
+            fn event(
+                self: Arc<Self>,
+                handle: &mut $client_backend::Handle,
+                msg: Message<$client_backend::ObjectId>,
+            ) -> Option<Arc<dyn $client_backend::ObjectData>> {
+                assert_eq!(msg.opcode, 0);
+                handle.set_data(msg.sender_id, self.replacement.clone()).unwrap();
+                None
+            }
+
+            fn destroyed(&self, _: $client_backend::ObjectId) {
+                self.destroyed.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+    };
+}
+
+impl_client_objectdata!(client_rs);
+impl_client_objectdata!(client_sys);
+
+// destroy the object being dispatched and the object created by its event from within the callback
+expand_test!(reentrant_destructor_request, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let server_data = Arc::new(ServerData(AtomicBool::new(false)));
+    let child_data = Arc::new(CountingData(AtomicUsize::new(0)));
+    let client_data =
+        Arc::new(ReentrantData { destroyed: AtomicUsize::new(0), child: child_data.clone() });
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, server_data.clone());
+
+    // get the registry client-side
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    // create the test global
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(
+                        CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes()).unwrap(),
+                    )),
+                    Argument::Uint(3),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(client_data.clone()),
+        )
+        .unwrap();
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    client.dispatch_events().unwrap();
+
+    // each object data is notified exactly once
+    assert_eq!(client_data.destroyed.load(Ordering::Acquire), 1);
+    assert_eq!(child_data.0.load(Ordering::Acquire), 1);
+
+    // the destructor requests reached the server
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    assert!(server_data.0.load(Ordering::Acquire));
+});
+
+// replace the data of an object from within the callback of its destructor event
+expand_test!(reentrant_set_data_on_destructor_event, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let replacement = Arc::new(CountingData(AtomicUsize::new(0)));
+    let sync_data = Arc::new(ReplacingData {
+        destroyed: AtomicUsize::new(0),
+        replacement: replacement.clone(),
+    });
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+    client
+        .handle()
+        .send_request(
+            message!(client_display, 0, [Argument::NewId(placeholder)]),
+            Some(sync_data.clone()),
+        )
+        .unwrap();
+    client.flush().unwrap();
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    client.dispatch_events().unwrap();
+
+    // the data in place when the object is destroyed is the one notified
+    assert_eq!(sync_data.destroyed.load(Ordering::Acquire), 0);
+    assert_eq!(replacement.0.load(Ordering::Acquire), 1);
+});
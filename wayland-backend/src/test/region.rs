@@ -0,0 +1,152 @@
+use quickcheck::{Arbitrary, Gen};
+use quickcheck_macros::quickcheck;
+
+use crate::region::{DamageHistory, Rect, Region};
+
+// Use small coordinates, so that the rectangles often overlap and the points are often
+// inside of them
+#[derive(Clone, Debug)]
+struct SmallRect(Rect);
+
+impl Arbitrary for SmallRect {
+    fn arbitrary(g: &mut Gen) -> SmallRect {
+        let coord = |g: &mut Gen| (u8::arbitrary(g) % 32) as i32 - 8;
+        SmallRect(Rect::new(coord(g), coord(g), coord(g), coord(g)))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SmallPoint(i32, i32);
+
+impl Arbitrary for SmallPoint {
+    fn arbitrary(g: &mut Gen) -> SmallPoint {
+        let coord = |g: &mut Gen| (u8::arbitrary(g) % 48) as i32 - 16;
+        SmallPoint(coord(g), coord(g))
+    }
+}
+
+fn region(rects: &[SmallRect]) -> Region {
+    rects.iter().map(|r| r.0).collect()
+}
+
+fn in_rects(rects: &[SmallRect], p: &SmallPoint) -> bool {
+    rects.iter().any(|r| r.0.contains(p.0, p.1))
+}
+
+fn well_formed(region: &Region) -> bool {
+    let rects = region.rects();
+    rects.iter().all(|r| !r.is_empty())
+        && rects
+            .iter()
+            .enumerate()
+            .all(|(i, a)| rects[i + 1..].iter().all(|b| a.intersection(b).is_none()))
+}
+
+#[quickcheck]
+fn region_union(a: Vec<SmallRect>, b: Vec<SmallRect>, points: Vec<SmallPoint>) -> bool {
+    let mut region = region(&a);
+    region.union(&self::region(&b));
+    well_formed(&region)
+        && points.iter().all(|p| region.contains(p.0, p.1) == (in_rects(&a, p) || in_rects(&b, p)))
+}
+
+#[quickcheck]
+fn region_subtract(a: Vec<SmallRect>, b: Vec<SmallRect>, points: Vec<SmallPoint>) -> bool {
+    let mut region = region(&a);
+    region.subtract_region(&self::region(&b));
+    well_formed(&region)
+        && points.iter().all(|p| region.contains(p.0, p.1) == (in_rects(&a, p) && !in_rects(&b, p)))
+}
+
+#[quickcheck]
+fn region_intersect(a: Vec<SmallRect>, b: Vec<SmallRect>, points: Vec<SmallPoint>) -> bool {
+    let mut region = region(&a);
+    region.intersect_region(&self::region(&b));
+    well_formed(&region)
+        && points.iter().all(|p| region.contains(p.0, p.1) == (in_rects(&a, p) && in_rects(&b, p)))
+}
+
+#[quickcheck]
+fn region_area(a: Vec<SmallRect>, b: Vec<SmallRect>) -> bool {
+    let (a, b) = (region(&a), region(&b));
+    let mut union = a.clone();
+    union.union(&b);
+    let mut inter = a.clone();
+    inter.intersect_region(&b);
+    union.area() + inter.area() == a.area() + b.area()
+}
+
+#[quickcheck]
+fn region_simplify(a: Vec<SmallRect>, points: Vec<SmallPoint>) -> bool {
+    let region = region(&a);
+    let mut simplified = region.clone();
+    simplified.simplify();
+    well_formed(&simplified)
+        && simplified.rects().len() <= region.rects().len()
+        && simplified.area() == region.area()
+        && points.iter().all(|p| simplified.contains(p.0, p.1) == region.contains(p.0, p.1))
+}
+
+#[quickcheck]
+fn region_bounding_box(a: Vec<SmallRect>, points: Vec<SmallPoint>) -> bool {
+    let region = region(&a);
+    match region.bounding_box() {
+        None => region.is_empty(),
+        Some(bbox) => points.iter().all(|p| !region.contains(p.0, p.1) || bbox.contains(p.0, p.1)),
+    }
+}
+
+#[test]
+fn region_simplify_merges() {
+    let mut region = Region::new();
+    region.add(Rect::new(0, 0, 10, 10));
+    region.add(Rect::new(0, 10, 10, 10));
+    region.add(Rect::new(10, 0, 10, 20));
+    region.simplify();
+    assert_eq!(region.rects(), &[Rect::new(0, 0, 20, 20)]);
+}
+
+#[test]
+fn region_boundaries() {
+    const MAX: i32 = i32::MAX;
+    const MIN: i32 = i32::MIN;
+
+    // the edges of the rectangles are clamped instead of overflowing
+    let huge = Rect::new(MAX - 10, MAX - 10, 100, 100);
+    assert!(huge.contains(MAX - 1, MAX - 1));
+    assert_eq!(
+        huge.intersection(&Rect::new(MAX - 5, 0, 100, MAX)),
+        Some(Rect::new(MAX - 5, MAX - 10, 5, 10))
+    );
+    assert_eq!(Rect::new(MIN, 0, -1, 10).intersection(&Rect::new(0, 0, 10, 10)), None);
+
+    let mut region = Region::from(huge);
+    region.subtract(Rect::new(MIN, MIN, MAX, MAX));
+    assert_eq!(region.rects(), &[huge]);
+    region.add(Rect::new(MIN, MIN, 10, 10));
+    assert_eq!(region.bounding_box(), Some(Rect::new(MIN, MIN, MAX, MAX)));
+
+    let mut region = Region::from(huge);
+    region.add(Rect::new(MAX - 10, MAX - 20, 100, 10));
+    region.simplify();
+    assert_eq!(region.rects(), &[Rect::new(MAX - 10, MAX - 20, 100, 110)]);
+}
+
+#[test]
+fn damage_history() {
+    let mut history = DamageHistory::new(2);
+    assert_eq!(history.damage_for_age(1), None);
+
+    history.push(Rect::new(0, 0, 10, 10).into());
+    history.push(Rect::new(10, 0, 10, 10).into());
+    history.push(Rect::new(20, 0, 10, 10).into());
+
+    assert_eq!(history.damage_for_age(0), None);
+    assert_eq!(history.damage_for_age(1), Some(Rect::new(20, 0, 10, 10).into()));
+    assert_eq!(history.damage_for_age(2), Some(Rect::new(10, 0, 20, 10).into()));
+    // the first frame was forgotten
+    assert_eq!(history.damage_for_age(3), None);
+
+    history.clear();
+    assert_eq!(history.damage_for_age(1), None);
+}
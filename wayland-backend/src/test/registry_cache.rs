@@ -0,0 +1,40 @@
+use super::*;
+
+expand_test!(registry_cache, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(DoNothingData));
+    let second =
+        server.handle().create_global(&interfaces::SECONDARY_INTERFACE, 2, Arc::new(DoNothingData));
+
+    assert!(client.handle().globals().is_none());
+
+    let registry_id = client.handle().enable_registry_cache().unwrap();
+    // enabling it again reuses the same registry
+    assert_eq!(client.handle().enable_registry_cache().unwrap(), registry_id);
+    assert_eq!(client.handle().globals(), Some(Vec::new()));
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+    client.dispatch_events().unwrap();
+
+    let globals = client.handle().globals().unwrap();
+    assert_eq!(globals.len(), 2);
+    assert_eq!(globals[0].name, 1);
+    assert_eq!(globals[0].interface, "test_global");
+    assert_eq!(globals[0].version, 3);
+    assert_eq!(globals[1].interface, "secondary");
+    assert_eq!(globals[1].version, 2);
+
+    server.handle().remove_global(second);
+    server.flush(None).unwrap();
+    client.dispatch_events().unwrap();
+
+    let globals = client.handle().globals().unwrap();
+    assert_eq!(globals.len(), 1);
+    assert_eq!(globals[0].interface, "test_global");
+});
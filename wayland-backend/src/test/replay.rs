@@ -0,0 +1,445 @@
+//! Replay of recorded sessions through the event dispatching of the rust client backend
+//!
+//! Each file of the `captures` directory is the log of a real session, in the format of
+//! `WAYLAND_DEBUG` and of the [`CaptureFormatter`](crate::protocol::CaptureFormatter): the requests
+//! are prefixed with `->`, and the events are either prefixed with `<-` or unprefixed. Timestamps
+//! in brackets and event queue names in braces are ignored, as are the interface names of the
+//! `object` and `new_id` arguments.
+//!
+//! The requests are sent through `Handle::send_request()`, and the events are written on the
+//! server side of the socket, translating the ids of the objects created by the client as the
+//! backend may allocate them differently than the recorded client. The events are then dispatched
+//! to objects doing nothing with them.
+//!
+//! The comments starting the lines with `#` can hold the expectations of the capture:
+//!
+//! - `# expect: ok` if all events were dispatched successfully, `# expect: malformed` if the
+//!   backend rejected an event it could not parse, or `# expect: protocol-error <interface> <code>`
+//!   if the connection ended with a protocol error;
+//! - `# objects: <n>` for the number of objects alive once the capture was replayed, the display
+//!   included.
+
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    io::{ErrorKind, Read},
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::UnixStream,
+    },
+    path::Path,
+    sync::Mutex,
+};
+
+use nix::sys::{socket, uio};
+use smallvec::SmallVec;
+
+use crate::{
+    client::WaylandError,
+    protocol::{ArgumentType, Interface, MessageDesc},
+};
+
+use super::*;
+
+mod core_interfaces {
+    use crate as wayland_backend;
+    wayland_scanner::generate_interfaces!("../wayland-client/wayland.xml");
+}
+
+#[test]
+fn replay_captures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test/captures");
+    let mut paths = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some("log".as_ref()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let capture = std::fs::read_to_string(&path).unwrap();
+        match std::panic::catch_unwind(|| replay(&capture)) {
+            Ok(Ok(())) => {}
+            Ok(Err(msg)) => panic!("{}: {}", name, msg),
+            Err(_) => panic!("{}: the replay panicked", name),
+        }
+    }
+}
+
+fn replay(capture: &str) -> Result<(), String> {
+    let mut expected_outcome = None;
+    let mut expected_objects = None;
+    let mut lines = Vec::new();
+    for (number, line) in capture.lines().enumerate() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            let comment = comment.trim();
+            if let Some(outcome) = comment.strip_prefix("expect:") {
+                expected_outcome = Some(outcome.trim().to_owned());
+            } else if let Some(objects) = comment.strip_prefix("objects:") {
+                expected_objects = Some(objects.trim().parse::<usize>().unwrap());
+            }
+        } else if !line.is_empty() {
+            let line = parse_line(line).ok_or_else(|| format!("invalid line {}", number + 1))?;
+            lines.push(line);
+        }
+    }
+    let expected_outcome = expected_outcome.ok_or("missing `# expect:` line")?;
+
+    let mut session = Session::new();
+    let mut outcome = "ok".to_owned();
+    for line in &lines {
+        let result = if line.request { session.request(line) } else { session.event(line) };
+        if let Err(err) = result {
+            outcome = classify(&err);
+            break;
+        }
+    }
+
+    if outcome != expected_outcome {
+        return Err(format!("expected outcome `{}`, got `{}`", expected_outcome, outcome));
+    }
+    if let Some(expected) = expected_objects {
+        let objects = session.alive_objects();
+        if objects != expected {
+            return Err(format!("expected {} alive objects, got {}", expected, objects));
+        }
+    }
+    Ok(())
+}
+
+fn classify(err: &WaylandError) -> String {
+    match err {
+        // the errors detected by the backend itself are not about an interface
+        WaylandError::Protocol(err) if err.object_interface.is_empty() => "malformed".into(),
+        WaylandError::Protocol(err) => {
+            format!("protocol-error {} {}", err.object_interface, err.code)
+        }
+        WaylandError::Io { .. } => "io-error".into(),
+        WaylandError::NestedDispatch => "nested-dispatch".into(),
+    }
+}
+
+/*
+ * Parsing of the captures
+ */
+
+struct Line {
+    request: bool,
+    interface: String,
+    id: u32,
+    message: String,
+    args: Vec<String>,
+}
+
+fn parse_line(mut line: &str) -> Option<Line> {
+    // timestamp
+    if let Some(rest) = line.strip_prefix('[') {
+        let end = rest.find(']')?;
+        if rest[..end].chars().all(|c| c.is_ascii_digit() || c == '.' || c == ' ') {
+            line = rest[end + 1..].trim_start();
+        }
+    }
+    // event queue
+    if let Some(rest) = line.strip_prefix('{') {
+        line = rest[rest.find('}')? + 1..].trim_start();
+    }
+    let request = line.starts_with("->");
+    if request || line.starts_with("<-") {
+        line = line[2..].trim_start();
+    }
+
+    let (interface, rest) = line.split_once('@')?;
+    let (id, rest) = rest.split_once('.')?;
+    let (message, args) = rest.split_once('(')?;
+    let args = args.trim_end().strip_suffix(')')?;
+    Some(Line {
+        request,
+        interface: interface.into(),
+        id: id.parse().ok()?,
+        message: message.into(),
+        args: split_args(args)?,
+    })
+}
+
+// split the arguments at the commas outside of the strings
+fn split_args(args: &str) -> Option<Vec<String>> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ',' => split.push(std::mem::take(&mut current)),
+            '"' => {
+                current.push(c);
+                loop {
+                    let c = chars.next()?;
+                    current.push(c);
+                    match c {
+                        '\\' => current.push(chars.next()?),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() || !split.is_empty() {
+        split.push(current);
+    }
+    Some(split.iter().map(|arg| arg.trim().to_owned()).collect())
+}
+
+fn parse_string(arg: &str) -> Option<CString> {
+    let mut string = Vec::new();
+    let mut chars = arg.strip_prefix('"')?.strip_suffix('"')?.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                c => c,
+            },
+            c => c,
+        };
+        string.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    CString::new(string).ok()
+}
+
+// the id of an `object` or `new_id` argument, 0 for a null object
+fn parse_object(arg: &str) -> Option<u32> {
+    if arg == "nil" {
+        return Some(0);
+    }
+    arg.rsplit_once('@')?.1.parse().ok()
+}
+
+fn parse_array(arg: &str) -> Option<usize> {
+    arg.strip_prefix("array[")?.strip_suffix(']')?.parse().ok()
+}
+
+fn parse_fixed(arg: &str) -> Option<i32> {
+    Some((arg.parse::<f64>().ok()? * 256.).round() as i32)
+}
+
+fn lookup(interface: &str) -> Option<&'static Interface> {
+    core_interfaces::INTERFACES.iter().copied().find(|i| i.name == interface)
+}
+
+/*
+ * Replay of the captures
+ */
+
+struct Session {
+    client: client_rs::Backend,
+    server: UnixStream,
+    // the objects of the client, by their id in the capture
+    ids: HashMap<u32, client_rs::ObjectId>,
+    created: Arc<ReplayData>,
+}
+
+impl Session {
+    fn new() -> Session {
+        let (client, server) = UnixStream::pair().unwrap();
+        server.set_nonblocking(true).unwrap();
+        let mut client = client_rs::Backend::connect(client).unwrap();
+        let display = client.handle().display_id();
+        Session {
+            client,
+            server,
+            ids: std::iter::once((1, display)).collect(),
+            created: Arc::new(ReplayData { created: Mutex::new(Vec::new()) }),
+        }
+    }
+
+    fn request(&mut self, line: &Line) -> Result<(), WaylandError> {
+        let sender = self.ids[&line.id].clone();
+        let (opcode, desc) = find_message(sender.interface().requests, &line.message);
+        assert_eq!(desc.signature.len(), line.args.len(), "wrong argument count");
+
+        let mut args = SmallVec::new();
+        let mut new_id = None;
+        for (arg_type, arg) in desc.signature.iter().zip(&line.args) {
+            args.push(match arg_type {
+                ArgumentType::Int => Argument::Int(arg.parse().unwrap()),
+                ArgumentType::Uint => Argument::Uint(arg.parse().unwrap()),
+                ArgumentType::Fixed => Argument::Fixed(parse_fixed(arg).unwrap()),
+                ArgumentType::Str(_) => Argument::Str(Box::new(parse_string(arg).unwrap())),
+                ArgumentType::Object(_) => match parse_object(arg).unwrap() {
+                    0 => Argument::Object(self.client.handle().null_id()),
+                    id => Argument::Object(self.ids[&id].clone()),
+                },
+                ArgumentType::NewId(_) => {
+                    let interface = match desc.child_interface {
+                        Some(interface) => interface,
+                        // a generic constructor, the interface is named by the preceding arguments
+                        None => match &args[args.len() - 2] {
+                            Argument::Str(name) => lookup(name.to_str().unwrap()).unwrap(),
+                            _ => panic!("generic constructor without interface name"),
+                        },
+                    };
+                    new_id = Some(parse_object(arg).unwrap());
+                    Argument::NewId(self.client.handle().placeholder_id(Some(interface)))
+                }
+                ArgumentType::Array(_) => {
+                    Argument::Array(Box::new(vec![0; parse_array(arg).unwrap()]))
+                }
+                ArgumentType::Fd => panic!("the file descriptors of requests are not replayed"),
+            });
+        }
+
+        let data = new_id.map(|_| self.created.clone() as Arc<dyn client_rs::ObjectData>);
+        let id = self
+            .client
+            .handle()
+            .send_request(Message { sender_id: sender, opcode, args }, data)
+            .expect("request to a dead object");
+        if let Some(new_id) = new_id {
+            self.ids.insert(new_id, id);
+        }
+        self.client.flush()?;
+        // the requests are not replayed by the server
+        let mut buffer = [0; 4096];
+        while matches!(self.server.read(&mut buffer), Ok(len) if len > 0) {}
+        Ok(())
+    }
+
+    fn event(&mut self, line: &Line) -> Result<(), WaylandError> {
+        let sender = self.wire_id(line.id);
+        let mut words = vec![sender, 0];
+        let mut fds = Vec::new();
+        let push_bytes = |words: &mut Vec<u32>, bytes: &[u8]| {
+            words.push(bytes.len() as u32);
+            for chunk in bytes.chunks(4) {
+                let mut word = [0; 4];
+                word[..chunk.len()].copy_from_slice(chunk);
+                words.push(u32::from_ne_bytes(word));
+            }
+        };
+
+        let opcode = match lookup(&line.interface) {
+            Some(interface) => {
+                let (opcode, desc) = find_message(interface.events, &line.message);
+                assert_eq!(desc.signature.len(), line.args.len(), "wrong argument count");
+                // the deleted ids are the ones allocated by the client
+                let deleted_id = interface.name == "wl_display" && desc.name == "delete_id";
+                for (arg_type, arg) in desc.signature.iter().zip(&line.args) {
+                    match arg_type {
+                        ArgumentType::Uint if deleted_id => {
+                            words.push(self.wire_id(arg.parse().unwrap()))
+                        }
+                        ArgumentType::Int => words.push(arg.parse::<i32>().unwrap() as u32),
+                        ArgumentType::Uint => words.push(arg.parse().unwrap()),
+                        ArgumentType::Fixed => words.push(parse_fixed(arg).unwrap() as u32),
+                        ArgumentType::Str(_) if arg == "nil" => words.push(0),
+                        ArgumentType::Str(_) => {
+                            push_bytes(&mut words, parse_string(arg).unwrap().as_bytes_with_nul())
+                        }
+                        ArgumentType::Object(_) | ArgumentType::NewId(_) => {
+                            words.push(self.wire_id(parse_object(arg).unwrap()))
+                        }
+                        ArgumentType::Array(_) => {
+                            push_bytes(&mut words, &vec![0; parse_array(arg).unwrap()])
+                        }
+                        ArgumentType::Fd => {
+                            let (read, write) = nix::unistd::pipe().unwrap();
+                            nix::unistd::close(write).unwrap();
+                            fds.push(read);
+                        }
+                    }
+                }
+                opcode
+            }
+            // an object unknown to the recorded client, the opcode and arguments are raw numbers
+            None => {
+                words.extend(line.args.iter().map(|arg| arg.parse::<u32>().unwrap()));
+                line.message.parse().unwrap()
+            }
+        };
+        words[1] = ((words.len() as u32 * 4) << 16) | opcode as u32;
+        self.send(&words, &fds);
+
+        let result = self.client.dispatch_events();
+        for id in self.created.created.lock().unwrap().drain(..) {
+            self.ids.insert(id.protocol_id(), id);
+        }
+        match result {
+            // the events of the display are not counted as dispatched
+            Err(err) if err.io_error().map(std::io::Error::kind) == Some(ErrorKind::WouldBlock) => {
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    fn send(&self, words: &[u32], fds: &[RawFd]) {
+        let bytes = words.iter().flat_map(|word| word.to_ne_bytes()).collect::<Vec<u8>>();
+        let iov = [uio::IoVec::from_slice(&bytes)];
+        let cmsgs = [socket::ControlMessage::ScmRights(fds)];
+        let cmsgs = if fds.is_empty() { &[][..] } else { &cmsgs[..] };
+        let sent =
+            socket::sendmsg(self.server.as_raw_fd(), &iov, cmsgs, socket::MsgFlags::empty(), None)
+                .unwrap();
+        assert_eq!(sent, bytes.len());
+        for &fd in fds {
+            nix::unistd::close(fd).unwrap();
+        }
+    }
+
+    // the id on the wire of an object of the capture
+    fn wire_id(&self, id: u32) -> u32 {
+        self.ids.get(&id).map_or(id, |id| id.protocol_id())
+    }
+
+    fn alive_objects(&mut self) -> usize {
+        let handle = self.client.handle();
+        self.ids.values().filter(|&id| handle.info(id.clone()).is_ok()).count()
+    }
+}
+
+fn find_message(messages: &'static [MessageDesc], name: &str) -> (u16, &'static MessageDesc) {
+    let opcode = match messages.iter().position(|desc| desc.name == name) {
+        Some(opcode) => opcode,
+        None => name.parse().unwrap_or_else(|_| panic!("unknown message {}", name)),
+    };
+    (opcode as u16, &messages[opcode])
+}
+
+// the object data of all objects, collecting the ones created by the server
+struct ReplayData {
+    created: Mutex<Vec<client_rs::ObjectId>>,
+}
+
+impl client_rs::ObjectData for ReplayData {
+    fn event(
+        self: Arc<Self>,
+        _: &mut client_rs::Handle,
+        msg: Message<client_rs::ObjectId>,
+    ) -> Option<Arc<dyn client_rs::ObjectData>> {
+        let mut creates_child = false;
+        for arg in msg.args {
+            match arg {
+                Argument::NewId(id) => {
+                    self.created.lock().unwrap().push(id);
+                    creates_child = true;
+                }
+                Argument::Fd(fd) => {
+                    let _ = nix::unistd::close(fd);
+                }
+                _ => {}
+            }
+        }
+        if creates_child {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn destroyed(&self, _: client_rs::ObjectId) {}
+}
@@ -1,6 +1,6 @@
 use std::{
     ffi::CString,
-    sync::atomic::{AtomicU32, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
 };
 
 use crate::protocol::Message;
@@ -116,7 +116,7 @@ expand_test!(server_created_object, {
 
     // get the registry client-side
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let registry_id = client
         .handle()
         .send_request(
@@ -125,7 +125,7 @@ expand_test!(server_created_object, {
         )
         .unwrap();
     // create the test global
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::TEST_GLOBAL_INTERFACE, 3)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
     let _test_global_id = client
         .handle()
         .send_request(
@@ -152,3 +152,202 @@ expand_test!(server_created_object, {
 
     assert_eq!(client_data.0.load(Ordering::SeqCst), 2);
 });
+
+// the rust client backend refuses server-created objects beyond its limit
+#[test]
+fn server_created_object_limit() {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    // the display, the registry, the test global and the first quad
+    let config = client_rs::BackendConfig { max_objects: 4, ..Default::default() };
+    let mut client = client_rs::Backend::connect_with_config(tx, config).unwrap();
+
+    let client_data = Arc::new(ClientData(AtomicU32::new(0)));
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(ServerData));
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)],),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(
+                        CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes()).unwrap(),
+                    )),
+                    Argument::Uint(1),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(client_data.clone()),
+        )
+        .unwrap();
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    match client.dispatch_events() {
+        Err(client_rs::WaylandError::Protocol(err)) => {
+            assert!(err.message.contains("limit of 4 live objects"))
+        }
+        ret => panic!("Bad ret: {:?}", ret),
+    }
+    // only the first event was dispatched
+    assert_eq!(client_data.0.load(Ordering::SeqCst), 1);
+}
+
+expand_test!(objects_of, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::<()>::new().unwrap();
+    let client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let _client = client_backend::Backend::connect(tx).unwrap();
+
+    let mut quads = Vec::new();
+    for _ in 0..2 {
+        quads.push(
+            server
+                .handle()
+                .create_object(
+                    client_id.clone(),
+                    &interfaces::QUAD_INTERFACE,
+                    3,
+                    Arc::new(DoNothingData),
+                )
+                .unwrap(),
+        );
+    }
+    let global = server
+        .handle()
+        .create_object(
+            client_id.clone(),
+            &interfaces::TEST_GLOBAL_INTERFACE,
+            3,
+            Arc::new(DoNothingData),
+        )
+        .unwrap();
+
+    let found = server
+        .handle()
+        .objects_of(client_id.clone(), &interfaces::QUAD_INTERFACE)
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(found.len(), 2);
+    assert!(quads.iter().all(|id| found.contains(id)));
+
+    let found = server
+        .handle()
+        .objects_of(client_id, &interfaces::TEST_GLOBAL_INTERFACE)
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(found, vec![global]);
+});
+
+expand_test!(create_object_with_event, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::<()>::new().unwrap();
+    let client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let _client = client_backend::Backend::connect(tx).unwrap();
+    let (other_tx, other_rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let other_client_id = server.insert_client(other_rx, Arc::new(DoNothingData)).unwrap();
+    let _other_client = client_backend::Backend::connect(other_tx).unwrap();
+
+    let global = server
+        .handle()
+        .create_object(
+            client_id.clone(),
+            &interfaces::TEST_GLOBAL_INTERFACE,
+            3,
+            Arc::new(DoNothingData),
+        )
+        .unwrap();
+    let other_global = server
+        .handle()
+        .create_object(
+            other_client_id,
+            &interfaces::TEST_GLOBAL_INTERFACE,
+            3,
+            Arc::new(DoNothingData),
+        )
+        .unwrap();
+
+    let count_quads = |server: &mut server_backend::Backend<()>| {
+        server.handle().objects_of(client_id.clone(), &interfaces::QUAD_INTERFACE).unwrap().count()
+    };
+
+    // the quad is created and introduced by cycle_quad
+    let quad = server
+        .handle()
+        .create_object_with_event(
+            client_id.clone(),
+            &interfaces::QUAD_INTERFACE,
+            3,
+            Arc::new(DoNothingData),
+            |handle, id| {
+                Ok(message!(
+                    global.clone(),
+                    2,
+                    [Argument::NewId(id), Argument::Object(handle.null_id())]
+                ))
+            },
+        )
+        .unwrap();
+    assert_eq!(count_quads(&mut server), 1);
+    assert!(server.handle().object_info(quad).is_ok());
+
+    // the quad is destroyed if the event cannot be built, running its destruction hooks
+    let hook_ran = Arc::new(AtomicBool::new(false));
+    let hook_flag = hook_ran.clone();
+    assert!(server
+        .handle()
+        .create_object_with_event(
+            client_id.clone(),
+            &interfaces::QUAD_INTERFACE,
+            3,
+            Arc::new(DoNothingData),
+            |handle, id| {
+                handle
+                    .add_destruction_hook(
+                        id,
+                        Box::new(move |_, _| hook_flag.store(true, Ordering::Release)),
+                    )
+                    .unwrap();
+                Err(server_backend::InvalidId)
+            },
+        )
+        .is_err());
+    assert_eq!(count_quads(&mut server), 1);
+    assert!(hook_ran.load(Ordering::Acquire));
+
+    // or if its sender belongs to another client
+    assert!(server
+        .handle()
+        .create_object_with_event(
+            client_id.clone(),
+            &interfaces::QUAD_INTERFACE,
+            3,
+            Arc::new(DoNothingData),
+            |handle, id| {
+                Ok(message!(
+                    other_global.clone(),
+                    2,
+                    [Argument::NewId(id), Argument::Object(handle.null_id())]
+                ))
+            },
+        )
+        .is_err());
+    assert_eq!(count_quads(&mut server), 1);
+});
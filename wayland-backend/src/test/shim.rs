@@ -0,0 +1,171 @@
+use std::{ffi::CString, os::unix::net::UnixStream};
+
+use crate::protocol::MessageDesc;
+use crate::rs::{proxy::ProxyConnection, shim::VersionShim, socket::BufferedSocket};
+
+use super::*;
+
+fn string(s: &str) -> Argument<u32> {
+    Argument::Str(Box::new(CString::new(s.as_bytes()).unwrap()))
+}
+
+// read the next message sent on a socket by objects with the given messages
+fn read_message(socket: &mut BufferedSocket, messages: &'static [MessageDesc]) -> Message<u32> {
+    loop {
+        match socket
+            .read_one_message(|_, opcode| messages.get(opcode as usize).map(|d| d.signature))
+        {
+            Ok(msg) => return msg,
+            // an error reading the socket means that the message was not sent
+            Err(_) => socket.fill_incoming_buffers().unwrap(),
+        }
+    }
+}
+
+fn assert_no_message(socket: &mut BufferedSocket, messages: &'static [MessageDesc]) {
+    assert!(socket.fill_incoming_buffers().is_err());
+    assert!(socket
+        .read_one_message(|_, opcode| messages.get(opcode as usize).map(|d| d.signature))
+        .is_err());
+}
+
+struct ShimTest {
+    client: BufferedSocket,
+    server: BufferedSocket,
+    proxy: ProxyConnection<VersionShim>,
+}
+
+impl ShimTest {
+    fn new(shim: VersionShim) -> ShimTest {
+        let (client_socket, proxy_client) = UnixStream::pair().unwrap();
+        let (proxy_server, server_socket) = UnixStream::pair().unwrap();
+        let proxy = ProxyConnection::new(
+            proxy_client,
+            proxy_server,
+            &[&interfaces::TEST_GLOBAL_INTERFACE],
+            shim,
+        );
+        ShimTest {
+            client: BufferedSocket::new(client_socket),
+            server: BufferedSocket::new(server_socket),
+            proxy,
+        }
+    }
+
+    // get the registry and advertise the test global with the given version upstream, returning
+    // the version seen by the client
+    fn advertise(&mut self, version: u32) -> u32 {
+        self.client.write_message(&message!(1, 1, [Argument::NewId(2)])).unwrap();
+        self.client.flush().unwrap();
+        assert_eq!(self.proxy.relay_requests().unwrap(), 1);
+        self.proxy.flush().unwrap();
+        read_message(&mut self.server, interfaces::WL_DISPLAY_INTERFACE.requests);
+
+        self.server
+            .write_message(&message!(
+                2,
+                0,
+                [Argument::Uint(1), string("test_global"), Argument::Uint(version)]
+            ))
+            .unwrap();
+        self.server.flush().unwrap();
+        assert_eq!(self.proxy.relay_events().unwrap(), 1);
+        self.proxy.flush().unwrap();
+        match read_message(&mut self.client, interfaces::WL_REGISTRY_INTERFACE.events).args[..] {
+            [Argument::Uint(1), _, Argument::Uint(version)] => version,
+            ref args => panic!("Unexpected global event: {:?}", args),
+        }
+    }
+
+    fn relay_requests(&mut self, requests: &[Message<u32>]) {
+        for msg in requests {
+            self.client.write_message(msg).unwrap();
+        }
+        self.client.flush().unwrap();
+        assert_eq!(self.proxy.relay_requests().unwrap(), requests.len());
+        self.proxy.flush().unwrap();
+    }
+}
+
+// a client requiring version 3 of the test global on a compositor supporting version 1
+#[test]
+fn shim_present_higher_version() {
+    let mut test = ShimTest::new(VersionShim::new().present(&interfaces::TEST_GLOBAL_INTERFACE, 3));
+    assert_eq!(test.advertise(1), 3);
+
+    test.relay_requests(&[
+        // bind the test global with version 3
+        message!(
+            2,
+            0,
+            [Argument::Uint(1), string("test_global"), Argument::Uint(3), Argument::NewId(3)]
+        ),
+        // test_global.get_secondary, since version 2
+        message!(3, 1, [Argument::NewId(4)]),
+        // secondary.destroy
+        message!(4, 0, []),
+        // test_global.destroy, since version 3
+        message!(3, 4, []),
+    ]);
+
+    // only the bind reaches the compositor, with version 1
+    let bind = read_message(&mut test.server, interfaces::WL_REGISTRY_INTERFACE.requests);
+    assert!(matches!(bind.args[..], [Argument::Uint(1), _, Argument::Uint(1), Argument::NewId(3)]));
+    assert_no_message(&mut test.server, interfaces::WL_REGISTRY_INTERFACE.requests);
+
+    let shim = test.proxy.filter();
+    assert_eq!(shim.upstream_version(3), Some(1));
+    assert!(shim.is_orphan(4));
+    // the test global still exists upstream, but not for the client
+    assert!(shim.is_orphan(3));
+}
+
+// a client limited to version 1 of the test global on a compositor supporting version 3
+#[test]
+fn shim_present_lower_version() {
+    let mut test = ShimTest::new(VersionShim::new().present(&interfaces::TEST_GLOBAL_INTERFACE, 1));
+    assert_eq!(test.advertise(3), 1);
+
+    test.relay_requests(&[message!(
+        2,
+        0,
+        [Argument::Uint(1), string("test_global"), Argument::Uint(1), Argument::NewId(3)]
+    )]);
+    let bind = read_message(&mut test.server, interfaces::WL_REGISTRY_INTERFACE.requests);
+    assert!(matches!(bind.args[..], [Argument::Uint(1), _, Argument::Uint(1), Argument::NewId(3)]));
+    assert_eq!(test.proxy.filter().upstream_version(3), Some(1));
+}
+
+// events emulating a newer version are synthesized after the upstream ones
+#[test]
+fn shim_synthesize_events() {
+    let shim = VersionShim::new()
+        .present(&interfaces::TEST_GLOBAL_INTERFACE, 2)
+        // follow each ack_secondary with an other one, from version 2
+        .synthesize(&interfaces::TEST_GLOBAL_INTERFACE, 2, |msg| {
+            if msg.opcode == 1 {
+                Some(message!(msg.sender_id, 1, [Argument::Object(0)]))
+            } else {
+                None
+            }
+        });
+    let mut test = ShimTest::new(shim);
+    assert_eq!(test.advertise(1), 2);
+
+    test.relay_requests(&[message!(
+        2,
+        0,
+        [Argument::Uint(1), string("test_global"), Argument::Uint(2), Argument::NewId(3)]
+    )]);
+    read_message(&mut test.server, interfaces::WL_REGISTRY_INTERFACE.requests);
+
+    test.server.write_message(&message!(3, 1, [Argument::Object(0)])).unwrap();
+    test.server.flush().unwrap();
+    assert_eq!(test.proxy.relay_events().unwrap(), 1);
+    test.proxy.flush().unwrap();
+
+    for _ in 0..2 {
+        let msg = read_message(&mut test.client, interfaces::TEST_GLOBAL_INTERFACE.events);
+        assert_eq!((msg.sender_id, msg.opcode), (3, 1));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::protocol::{
+    compare_signature, decode_signature, encode_signature, signature_codes, AllowNull,
+    ArgumentType, MessageDesc, SignatureDecodeError,
+};
+
+use super::*;
+
+fn link() -> &'static MessageDesc {
+    &interfaces::TEST_GLOBAL_INTERFACE.requests[3]
+}
+
+#[test]
+fn compare_matching_signature() {
+    let args = [Argument::Object(5u32), Argument::Object(0), Argument::Uint(7)];
+    assert_eq!(compare_signature(link(), &args), Ok(()));
+}
+
+#[test]
+fn compare_mismatching_signature() {
+    let args = [Argument::Object(5u32), Argument::Uint(7)];
+    let mismatch = compare_signature(link(), &args).unwrap_err();
+    assert_eq!(mismatch.message, "link");
+    assert_eq!(mismatch.position, 1);
+    assert_eq!(mismatch.arg_names, ["sec", "ter", "time"]);
+    assert_eq!(mismatch.provided, [ArgumentType::Object(AllowNull::Yes), ArgumentType::Uint]);
+    assert_eq!(
+        mismatch.to_string(),
+        "arguments of link differ at position 1 (ter)
+       expected     provided
+    0  sec: object  object
+>   1  ter: object  uint
+    2  time: uint   <missing>"
+    );
+
+    // missing and excess arguments are reported after the last common argument
+    let args = [Argument::Object(5u32), Argument::Object(0)];
+    assert_eq!(compare_signature(link(), &args).unwrap_err().position, 2);
+    let args = [Argument::Object(5u32), Argument::Object(0), Argument::Uint(7), Argument::Uint(8)];
+    let mismatch = compare_signature(link(), &args).unwrap_err();
+    assert_eq!(mismatch.position, 3);
+    assert!(mismatch.to_string().starts_with("arguments of link differ at position 3\n"));
+}
+
+#[test]
+fn encode_libwayland_signatures() {
+    // wl_registry.bind, whose new_id has no interface
+    let bind = &crate::core_interfaces::WL_REGISTRY_INTERFACE.requests[0];
+    assert_eq!(encode_signature(bind.since, bind.signature), "usun");
+    assert_eq!(encode_signature(link().since, link().signature), "3o?ou");
+    assert_eq!(ArgumentType::Array(AllowNull::Yes).code(), signature_codes::ARRAY);
+}
+
+#[test]
+fn decode_libwayland_signatures() {
+    assert_eq!(decode_signature("3o?ou"), Ok((3, link().signature.to_vec())));
+    assert_eq!(decode_signature(""), Ok((1, Vec::new())));
+    assert_eq!(
+        decode_signature("?s?nh"),
+        Ok((
+            1,
+            vec![
+                ArgumentType::Str(AllowNull::Yes),
+                ArgumentType::NewId(AllowNull::Yes),
+                ArgumentType::Fd
+            ]
+        ))
+    );
+    assert_eq!(decode_signature("i?u"), Err(SignatureDecodeError::NotNullable { position: 2 }));
+    assert_eq!(
+        decode_signature("ix"),
+        Err(SignatureDecodeError::UnknownCode { position: 1, code: 'x' })
+    );
+    assert_eq!(decode_signature("o?"), Err(SignatureDecodeError::DanglingNullable));
+    assert_eq!(decode_signature("99999999999i"), Err(SignatureDecodeError::InvalidVersion));
+
+    // every argument type survives the round trip
+    for &allow_null in &[AllowNull::Yes, AllowNull::No] {
+        let signature = [
+            ArgumentType::Int,
+            ArgumentType::Uint,
+            ArgumentType::Fixed,
+            ArgumentType::Str(allow_null),
+            ArgumentType::Object(allow_null),
+            ArgumentType::NewId(allow_null),
+            ArgumentType::Array(allow_null),
+            ArgumentType::Fd,
+        ];
+        assert_eq!(decode_signature(&encode_signature(2, &signature)), Ok((2, signature.to_vec())));
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::rs::client::{BackendConfig, Dispatcher};
+
+use super::*;
+
+struct ServerData;
+
+impl server_rs::GlobalHandler<()> for ServerData {
+    fn bind(
+        self: Arc<Self>,
+        handle: &mut server_rs::Handle<()>,
+        _: &mut (),
+        client: server_rs::ClientId,
+        _: server_rs::GlobalId,
+        object_id: server_rs::ObjectId,
+    ) -> Arc<dyn server_rs::ObjectData<()>> {
+        // create a quad, then replace it by an other one
+        let quad_1 = handle
+            .create_object(client.clone(), &interfaces::QUAD_INTERFACE, 3, Arc::new(DoNothingData))
+            .unwrap();
+        let null_id = handle.null_id();
+        handle
+            .send_event(message!(
+                object_id.clone(),
+                2,
+                [Argument::NewId(quad_1.clone()), Argument::Object(null_id)],
+            ))
+            .unwrap();
+        let quad_2 = handle
+            .create_object(client, &interfaces::QUAD_INTERFACE, 3, Arc::new(DoNothingData))
+            .unwrap();
+        handle
+            .send_event(message!(object_id, 2, [Argument::NewId(quad_2), Argument::Object(quad_1)]))
+            .unwrap();
+        Arc::new(DoNothingData)
+    }
+}
+
+// the kinds of objects of the client
+#[derive(Clone, Debug, PartialEq)]
+enum Object {
+    Uninit,
+    Registry,
+    Global,
+    Quad(usize),
+}
+
+static DESTROYED_QUADS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default)]
+struct State {
+    quads: usize,
+    replaced: Vec<client_rs::ObjectId>,
+}
+
+impl Dispatcher for State {
+    type ObjectData = Object;
+
+    fn placeholder_data() -> Object {
+        Object::Uninit
+    }
+
+    fn event(
+        &mut self,
+        handle: &mut client_rs::Handle<State>,
+        data: &Object,
+        msg: Message<client_rs::ObjectId>,
+    ) -> Option<Object> {
+        if *data == Object::Registry {
+            // the wl_registry.global event of the test global
+            return None;
+        }
+        assert_eq!(*data, Object::Global);
+        assert_eq!(msg.opcode, 2);
+        if let [Argument::NewId(ref new_quad), Argument::Object(ref old_quad)] = msg.args[..] {
+            // the new object has the placeholder data until the event is dispatched
+            assert_eq!(handle.get_data(new_quad.clone()).unwrap(), Object::Uninit);
+            if !old_quad.is_null() {
+                self.replaced.push(old_quad.clone());
+            }
+        } else {
+            panic!("Bad argument list!");
+        }
+        self.quads += 1;
+        Some(Object::Quad(self.quads - 1))
+    }
+
+    fn destroyed(data: &Object, _: client_rs::ObjectId) {
+        if let Object::Quad(_) = *data {
+            DESTROYED_QUADS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn static_dispatch() {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client =
+        client_rs::Backend::<State>::connect_static(tx, BackendConfig::default()).unwrap();
+    let mut state = State::default();
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(ServerData));
+
+    // bind the test global
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(Object::Registry),
+        )
+        .unwrap();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::TEST_GLOBAL_INTERFACE));
+    client
+        .handle()
+        .send_request(
+            message!(
+                registry_id,
+                0,
+                [
+                    Argument::Uint(1),
+                    Argument::Str(Box::new(
+                        CString::new(interfaces::TEST_GLOBAL_INTERFACE.name.as_bytes()).unwrap(),
+                    )),
+                    Argument::Uint(3),
+                    Argument::NewId(placeholder),
+                ],
+            ),
+            Some(Object::Global),
+        )
+        .unwrap();
+
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+    assert_eq!(client.dispatch_events_to(&mut state).unwrap(), 3);
+
+    assert_eq!(state.quads, 2);
+    assert_eq!(state.replaced.len(), 1);
+    let old_quad = state.replaced[0].clone();
+    assert_eq!(client.handle().get_data(old_quad.clone()).unwrap(), Object::Quad(0));
+
+    // the destruction is notified without the dispatcher
+    client.handle().send_request(message!(old_quad.clone(), 0, []), None).unwrap();
+    assert_eq!(DESTROYED_QUADS.load(Ordering::SeqCst), 1);
+    assert!(client.handle().info(old_quad).is_err());
+}
@@ -0,0 +1,86 @@
+use crate::protocol::{Direction, MessageStats};
+
+use super::*;
+
+#[test]
+fn protocol_stats() {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::<()>::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_rs::Backend::connect(tx).unwrap();
+
+    // the statistics are not recorded by default
+    assert!(client.handle().stats().is_none());
+    client.handle().enable_stats();
+    server.handle().enable_stats();
+
+    // send two wl_display.sync requests, answered by wl_callback.done and wl_display.delete_id
+    let client_display = client.handle().display_id();
+    for _ in 0..2 {
+        let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+        client
+            .handle()
+            .send_request(
+                message!(client_display.clone(), 0, [Argument::NewId(placeholder)]),
+                Some(Arc::new(DoNothingData)),
+            )
+            .unwrap();
+    }
+    client.flush().unwrap();
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+    client.dispatch_events().unwrap();
+
+    let sync = |direction| MessageStats {
+        interface: "wl_display",
+        message: "sync",
+        opcode: 0,
+        direction,
+        count: 2,
+        bytes: 24,
+    };
+    let done = |direction| MessageStats {
+        interface: "wl_callback",
+        message: "done",
+        opcode: 0,
+        direction,
+        count: 2,
+        bytes: 24,
+    };
+    let delete_id = |direction| MessageStats {
+        interface: "wl_display",
+        message: "delete_id",
+        opcode: 1,
+        direction,
+        count: 2,
+        bytes: 24,
+    };
+
+    let client_stats = client.handle().stats().unwrap();
+    assert_eq!(client_stats.get("wl_display", 0, Direction::Sent), Some(&sync(Direction::Sent)));
+    assert_eq!(
+        client_stats.get("wl_callback", 0, Direction::Received),
+        Some(&done(Direction::Received))
+    );
+    assert_eq!(
+        client_stats.get("wl_display", 1, Direction::Received),
+        Some(&delete_id(Direction::Received))
+    );
+    assert_eq!(client_stats.total(Direction::Sent), (2, 24));
+    assert_eq!(client_stats.total(Direction::Received), (4, 48));
+
+    let server_stats = server.handle().stats().unwrap();
+    assert_eq!(
+        server_stats.get("wl_display", 0, Direction::Received),
+        Some(&sync(Direction::Received))
+    );
+    assert_eq!(server_stats.get("wl_callback", 0, Direction::Sent), Some(&done(Direction::Sent)));
+    assert_eq!(
+        server_stats.get("wl_display", 1, Direction::Sent),
+        Some(&delete_id(Direction::Sent))
+    );
+    assert_eq!(server_stats.messages().count(), 3);
+
+    client.handle().reset_stats();
+    assert_eq!(client.handle().stats().unwrap().messages().count(), 0);
+}
@@ -1,4 +1,12 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::os::unix::{
+    io::{AsRawFd, RawFd},
+    net::UnixStream,
+};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::rs::shaping::{ShapedTransport, ShapingConfig};
+use crate::rs::WaylandTransport;
 
 use super::*;
 struct SyncData(AtomicBool);
@@ -42,7 +50,7 @@ expand_test!(sync, {
 
     // send the request
     let client_display = client.handle().display_id();
-    let placeholder = client.handle().placeholder_id(Some((&interfaces::WL_CALLBACK_INTERFACE, 1)));
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
     let sync_data = Arc::new(SyncData(AtomicBool::new(false)));
     let sync_id = client
         .handle()
@@ -68,6 +76,154 @@ expand_test!(sync, {
     assert!(client.handle().get_data(sync_id).is_err());
 });
 
+// counts the globals advertised to a registry
+struct GlobalCount(AtomicUsize);
+
+impl client_rs::ObjectData for GlobalCount {
+    fn event(
+        self: Arc<Self>,
+        _: &mut client_rs::Handle,
+        msg: Message<client_rs::ObjectId>,
+    ) -> Option<Arc<dyn client_rs::ObjectData>> {
+        assert_eq!(msg.opcode, 0);
+        self.0.fetch_add(1, Ordering::SeqCst);
+        None
+    }
+
+    fn destroyed(&self, _: client_rs::ObjectId) {}
+}
+
+impl client_sys::ObjectData for GlobalCount {
+    fn event(
+        self: Arc<Self>,
+        _: &mut client_sys::Handle,
+        msg: Message<client_sys::ObjectId>,
+    ) -> Option<Arc<dyn client_sys::ObjectData>> {
+        assert_eq!(msg.opcode, 0);
+        self.0.fetch_add(1, Ordering::SeqCst);
+        None
+    }
+
+    fn destroyed(&self, _: client_sys::ObjectId) {}
+}
+
+// read the events of a registry through an exclusive borrow of the backend
+expand_test!(sync_exclusive_read, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    server.handle().create_global(&interfaces::TEST_GLOBAL_INTERFACE, 3, Arc::new(DoNothingData));
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
+    let globals = Arc::new(GlobalCount(AtomicUsize::new(0)));
+    client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(placeholder)]),
+            Some(globals.clone()),
+        )
+        .unwrap();
+    client.flush().unwrap();
+
+    // the request is already in the socket, and so is the answer once flushed
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    // preparing the guard does not dispatch anything
+    let guard = client.prepare_read().unwrap();
+    assert_eq!(globals.0.load(Ordering::SeqCst), 0);
+    // the single wl_registry.global event
+    assert_eq!(guard.read().unwrap(), 1);
+    assert_eq!(globals.0.load(Ordering::SeqCst), 1);
+});
+
+// reading an empty socket through an exclusive guard
+#[test]
+fn exclusive_read_nothing() {
+    // the rust backend reports it as an error
+    let (tx, _rx) = UnixStream::pair().unwrap();
+    let mut client = client_rs::Backend::connect(tx).unwrap();
+    let err = client.prepare_read().unwrap().read().unwrap_err();
+    assert!(err.is_would_block());
+    assert!(client.handle().last_error().is_none());
+
+    // libwayland dispatches no event
+    let (tx, _rx) = UnixStream::pair().unwrap();
+    let mut client = client_sys::Backend::connect(tx).unwrap();
+    assert_eq!(client.prepare_read().unwrap().read().unwrap(), 0);
+    assert!(client.handle().last_error().is_none());
+}
+
+// the events are dispatched with the time at which they were read
+struct TimedData(std::sync::Mutex<Option<Option<Instant>>>);
+
+impl client_rs::ObjectData for TimedData {
+    fn event(
+        self: Arc<Self>,
+        handle: &mut client_rs::Handle,
+        _: Message<client_rs::ObjectId>,
+    ) -> Option<Arc<dyn client_rs::ObjectData>> {
+        *self.0.lock().unwrap() = Some(handle.last_read_time());
+        None
+    }
+
+    fn destroyed(&self, _: client_rs::ObjectId) {}
+}
+
+impl client_sys::ObjectData for TimedData {
+    fn event(
+        self: Arc<Self>,
+        handle: &mut client_sys::Handle,
+        _: Message<client_sys::ObjectId>,
+    ) -> Option<Arc<dyn client_sys::ObjectData>> {
+        *self.0.lock().unwrap() = Some(handle.last_read_time());
+        None
+    }
+
+    fn destroyed(&self, _: client_sys::ObjectId) {}
+}
+
+expand_test!(read_timestamps, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    for &enabled in &[false, true] {
+        if enabled {
+            client.handle().enable_read_timestamps();
+        }
+        let client_display = client.handle().display_id();
+        let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+        let data = Arc::new(TimedData(std::sync::Mutex::new(None)));
+        client
+            .handle()
+            .send_request(
+                message!(client_display, 0, [Argument::NewId(placeholder)]),
+                Some(data.clone()),
+            )
+            .unwrap();
+        client.flush().unwrap();
+        server.dispatch_all_clients(&mut ()).unwrap();
+        server.flush(None).unwrap();
+
+        let before = Instant::now();
+        client.dispatch_events().unwrap();
+        let after = Instant::now();
+
+        let read_time = data.0.lock().unwrap().take().expect("the event was not dispatched");
+        if enabled {
+            let read_time = read_time.unwrap();
+            assert!(before <= read_time && read_time <= after);
+        } else {
+            assert_eq!(read_time, None);
+        }
+    }
+});
+
 expand_test!(panic test_bad_placeholder, {
     let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
     let mut server = server_backend::Backend::new().unwrap();
@@ -77,7 +233,7 @@ expand_test!(panic test_bad_placeholder, {
     // send the request
     let client_display = client.handle().display_id();
     let placeholder =
-        client.handle().placeholder_id(Some((&interfaces::WL_REGISTRY_INTERFACE, 1)));
+        client.handle().placeholder_id(Some(&interfaces::WL_REGISTRY_INTERFACE));
     let sync_data = Arc::new(SyncData(AtomicBool::new(false)));
     let sync_id = client
         .handle()
@@ -103,6 +259,48 @@ expand_test!(panic test_bad_placeholder, {
     assert!(client.handle().get_data(sync_id).is_err());
 });
 
+// placeholders created ahead of their requests do not interfere with each other
+expand_test!(interleaved_placeholders, {
+    let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut server = server_backend::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let mut client = client_backend::Backend::connect(tx).unwrap();
+
+    let client_display = client.handle().display_id();
+    let registry_placeholder = client.handle().placeholder_id(None);
+    let sync_placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+
+    // send the requests in the reverse order of the creation of their placeholders
+    let sync_data = Arc::new(SyncData(AtomicBool::new(false)));
+    let sync_id = client
+        .handle()
+        .send_request(
+            message!(client_display.clone(), 0, [Argument::NewId(sync_placeholder)]),
+            Some(sync_data.clone()),
+        )
+        .unwrap();
+    let registry_id = client
+        .handle()
+        .send_request(
+            message!(client_display, 1, [Argument::NewId(registry_placeholder)]),
+            Some(Arc::new(DoNothingData)),
+        )
+        .unwrap();
+    assert_eq!(client.handle().info(sync_id).unwrap().interface.name, "wl_callback");
+    assert_eq!(client.handle().info(registry_id).unwrap().interface.name, "wl_registry");
+    client.flush().unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    client.dispatch_events().unwrap();
+    assert!(sync_data.0.load(Ordering::SeqCst));
+});
+
 expand_test!(panic test_bad_signature, {
     let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
     let mut server = server_backend::Backend::new().unwrap();
@@ -132,3 +330,188 @@ expand_test!(panic test_bad_signature, {
     // and the sync object should be dead
     assert!(client.handle().get_data(sync_id).is_err());
 });
+
+// a transport counting the bytes going through it
+#[derive(Debug)]
+struct CountingTransport {
+    stream: UnixStream,
+    sent: Arc<AtomicUsize>,
+    received: Arc<AtomicUsize>,
+}
+
+impl AsRawFd for CountingTransport {
+    fn as_raw_fd(&self) -> RawFd {
+        self.stream.as_raw_fd()
+    }
+}
+
+impl WaylandTransport for CountingTransport {
+    fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> std::io::Result<usize> {
+        let sent = self.stream.send_msg(bytes, fds)?;
+        self.sent.fetch_add(sent, Ordering::SeqCst);
+        Ok(sent)
+    }
+
+    fn rcv_msg(&self, buffer: &mut [u8], fds: &mut [RawFd]) -> std::io::Result<(usize, usize)> {
+        let (received, fd_count) = self.stream.rcv_msg(buffer, fds)?;
+        self.received.fetch_add(received, Ordering::SeqCst);
+        Ok((received, fd_count))
+    }
+}
+
+// the rust client backend can run over a custom transport
+#[test]
+fn sync_custom_transport() {
+    let (tx, rx) = UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let transport =
+        CountingTransport { stream: tx, sent: Arc::default(), received: Arc::default() };
+    let (sent, received) = (transport.sent.clone(), transport.received.clone());
+    let mut client = client_rs::Backend::connect_with_transport(transport).unwrap();
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+    let sync_data = Arc::new(SyncData(AtomicBool::new(false)));
+    client
+        .handle()
+        .send_request(
+            message!(client_display, 0, [Argument::NewId(placeholder)]),
+            Some(sync_data.clone()),
+        )
+        .unwrap();
+    client.flush().unwrap();
+    // header and new_id argument
+    assert_eq!(sent.load(Ordering::SeqCst), 12);
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    client.dispatch_events().unwrap();
+    assert!(sync_data.0.load(Ordering::SeqCst));
+    // wl_callback.done and wl_display.delete_id
+    assert_eq!(received.load(Ordering::SeqCst), 24);
+}
+
+// the client backend copes with a slow link, splitting and delaying its messages
+#[test]
+fn sync_shaped_transport() {
+    let (tx, rx) = UnixStream::pair().unwrap();
+    let mut server = server_rs::Backend::new().unwrap();
+    let _client_id = server.insert_client(rx, Arc::new(DoNothingData)).unwrap();
+    let config = ShapingConfig {
+        latency: Duration::from_millis(5),
+        max_write: Some(4),
+        max_read: Some(4),
+        block_every: Some(2),
+        ..Default::default()
+    };
+    let mut client =
+        client_rs::Backend::connect_with_transport(ShapedTransport::new(tx, config)).unwrap();
+
+    let client_display = client.handle().display_id();
+    let placeholder = client.handle().placeholder_id(Some(&interfaces::WL_CALLBACK_INTERFACE));
+    let sync_data = Arc::new(SyncData(AtomicBool::new(false)));
+    client
+        .handle()
+        .send_request(
+            message!(client_display, 0, [Argument::NewId(placeholder)]),
+            Some(sync_data.clone()),
+        )
+        .unwrap();
+
+    // the 12 bytes of the request are sent 4 by 4, every other write being rejected, and the
+    // last flush has nothing left to write
+    let start = Instant::now();
+    let mut blocked = 0;
+    for _ in 0..6 {
+        match client.flush() {
+            Ok(()) => {}
+            Err(e) if e.is_would_block() => {
+                assert_eq!(e.operation(), client_rs::Operation::Flush);
+                blocked += 1
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+    assert_eq!(blocked, 2);
+    assert!(start.elapsed() >= Duration::from_millis(15));
+
+    server.dispatch_all_clients(&mut ()).unwrap();
+    server.flush(None).unwrap();
+
+    std::thread::sleep(Duration::from_millis(10));
+
+    // the events are received 4 bytes at a time
+    client.dispatch_events().unwrap();
+    assert!(sync_data.0.load(Ordering::SeqCst));
+}
+
+// IO errors report the failing operation, and whether the connection is still usable
+#[test]
+fn error_categories() {
+    let (tx, rx) = UnixStream::pair().unwrap();
+    let mut client = client_rs::Backend::connect(tx).unwrap();
+
+    // nothing to read yet
+    let err = client.dispatch_events().unwrap_err();
+    assert!(err.is_would_block());
+    assert!(err.is_recoverable());
+    assert_eq!(err.operation(), client_rs::Operation::Read);
+    assert!(client.handle().last_error().is_none());
+
+    // the server is gone
+    std::mem::drop(rx);
+    let err = client.dispatch_events().unwrap_err();
+    assert!(!err.is_would_block());
+    assert!(!err.is_recoverable());
+    assert_eq!(err.operation(), client_rs::Operation::Read);
+    let source = std::error::Error::source(&err).unwrap();
+    assert!(source.downcast_ref::<std::io::Error>().is_some());
+    assert!(client.handle().last_error().is_some());
+}
+
+// the rust client backend rejects messages that are larger than its limit
+#[test]
+fn message_size_limit() {
+    let (tx, rx) = UnixStream::pair().unwrap();
+    let config = client_rs::BackendConfig { max_message_size: 64, ..Default::default() };
+    let mut client = client_rs::Backend::connect_with_config(tx, config).unwrap();
+
+    // the header of a 128 bytes long event, the rest of which is never sent
+    let header = [1u32, 128 << 16];
+    let bytes = unsafe { std::slice::from_raw_parts(header.as_ptr() as *const u8, 8) };
+    rx.send_msg(bytes, &[]).unwrap();
+
+    match client.dispatch_events() {
+        Err(client_rs::WaylandError::Protocol(err)) => {
+            assert!(err.message.contains("message of 128 bytes"))
+        }
+        ret => panic!("Bad ret: {:?}", ret),
+    }
+    assert!(client.handle().last_error().is_some());
+}
+
+// the rust client backend rejects file descriptors beyond its limit
+#[test]
+fn queued_fds_limit() {
+    let (tx, rx) = UnixStream::pair().unwrap();
+    let config = client_rs::BackendConfig { max_queued_fds: 1, ..Default::default() };
+    let mut client = client_rs::Backend::connect_with_config(tx, config).unwrap();
+
+    // a wl_display.delete_id event, with two unrelated fds
+    let event = [1u32, (12 << 16) | 1, 3];
+    let bytes = unsafe { std::slice::from_raw_parts(event.as_ptr() as *const u8, 12) };
+    rx.send_msg(bytes, &[rx.as_raw_fd(), rx.as_raw_fd()]).unwrap();
+
+    match client.dispatch_events() {
+        Err(client_rs::WaylandError::Protocol(err)) => {
+            assert!(err.message.contains("2 file descriptors"))
+        }
+        ret => panic!("Bad ret: {:?}", ret),
+    }
+}
@@ -11,21 +11,104 @@ impl std::fmt::Display for NoWaylandLib {
     }
 }
 
+/// The operation of a Wayland connection during which an error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Reading events from the socket
+    Read,
+    /// Writing a request into the outgoing buffers
+    Write,
+    /// Flushing the outgoing buffers to the socket
+    Flush,
+    /// Dispatching the events read from the socket
+    Dispatch,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        f.write_str(match self {
+            Operation::Read => "read",
+            Operation::Write => "write",
+            Operation::Flush => "flush",
+            Operation::Dispatch => "dispatch",
+        })
+    }
+}
+
 /// An error that can occur when using a Wayland connection
 #[derive(Debug)]
 pub enum WaylandError {
     /// The connection encountered an IO error
-    Io(std::io::Error),
+    Io {
+        /// The operation which failed
+        operation: Operation,
+        /// The underlying IO error
+        error: std::io::Error,
+    },
     /// The connection encountered a protocol error
     Protocol(crate::protocol::ProtocolError),
+    /// A blocking operation was invoked on the connection from within the dispatching of its
+    /// events, which would have deadlocked
+    ///
+    /// This error is not produced by the backends themselves, but by the libraries built on top of
+    /// them which are able to detect it. The connection is still usable.
+    NestedDispatch,
+}
+
+impl WaylandError {
+    /// Create an IO error of given operation
+    pub fn io(operation: Operation, error: std::io::Error) -> WaylandError {
+        WaylandError::Io { operation, error }
+    }
+
+    /// The operation during which this error occurred
+    ///
+    /// Protocol errors are always detected while dispatching the events of the server.
+    pub fn operation(&self) -> Operation {
+        match self {
+            WaylandError::Io { operation, .. } => *operation,
+            WaylandError::Protocol(_) | WaylandError::NestedDispatch => Operation::Dispatch,
+        }
+    }
+
+    /// The underlying IO error, if this is an IO error
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self {
+            WaylandError::Io { error, .. } => Some(error),
+            WaylandError::Protocol(_) | WaylandError::NestedDispatch => None,
+        }
+    }
+
+    /// Check whether this error means that the operation would have blocked
+    ///
+    /// This is the case when reading while no events are available, or flushing while the socket
+    /// is full.
+    pub fn is_would_block(&self) -> bool {
+        matches!(self.io_error(), Some(e) if e.kind() == std::io::ErrorKind::WouldBlock)
+    }
+
+    /// Check whether the connection is still usable after this error
+    ///
+    /// This is the case for errors which only mean that the operation needs to be retried later
+    /// (`WouldBlock` and `Interrupted` IO errors, or a nested dispatch). Any other error is fatal:
+    /// it is stored by the backend and returned by all further operations on the connection.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, WaylandError::NestedDispatch)
+            || matches!(
+                self.io_error().map(std::io::Error::kind),
+                Some(std::io::ErrorKind::WouldBlock) | Some(std::io::ErrorKind::Interrupted)
+            )
+    }
 }
 
 #[cfg(not(tarpaulin_include))]
 impl std::error::Error for WaylandError {
-    fn cause(&self) -> Option<&dyn std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            WaylandError::Io(e) => Some(e),
+            WaylandError::Io { error, .. } => Some(error),
             WaylandError::Protocol(e) => Some(e),
+            WaylandError::NestedDispatch => None,
         }
     }
 }
@@ -34,8 +117,13 @@ impl std::error::Error for WaylandError {
 impl std::fmt::Display for WaylandError {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
         match self {
-            WaylandError::Io(e) => write!(f, "Io error: {}", e),
+            WaylandError::Io { operation, error } => {
+                write!(f, "Io error during {}: {}", operation, error)
+            }
             WaylandError::Protocol(e) => std::fmt::Display::fmt(e, f),
+            WaylandError::NestedDispatch => {
+                f.write_str("Blocking operation invoked from within the dispatching of the events")
+            }
         }
     }
 }
@@ -45,12 +133,14 @@ impl Clone for WaylandError {
     fn clone(&self) -> WaylandError {
         match self {
             WaylandError::Protocol(e) => WaylandError::Protocol(e.clone()),
-            WaylandError::Io(e) => {
-                if let Some(code) = e.raw_os_error() {
-                    WaylandError::Io(std::io::Error::from_raw_os_error(code))
+            WaylandError::NestedDispatch => WaylandError::NestedDispatch,
+            WaylandError::Io { operation, error } => {
+                let error = if let Some(code) = error.raw_os_error() {
+                    std::io::Error::from_raw_os_error(code)
                 } else {
-                    WaylandError::Io(std::io::Error::new(e.kind(), ""))
-                }
+                    std::io::Error::new(error.kind(), error.to_string())
+                };
+                WaylandError::Io { operation: *operation, error }
             }
         }
     }
@@ -63,13 +153,6 @@ impl From<crate::protocol::ProtocolError> for WaylandError {
     }
 }
 
-#[cfg(not(tarpaulin_include))]
-impl From<std::io::Error> for WaylandError {
-    fn from(err: std::io::Error) -> WaylandError {
-        WaylandError::Io(err)
-    }
-}
-
 /// An error generated when trying to act on an invalid `ObjectId`.
 #[derive(Clone, Debug)]
 pub struct InvalidId;
@@ -82,3 +165,45 @@ impl std::fmt::Display for InvalidId {
         write!(f, "Invalid ObjectId")
     }
 }
+
+/// Description of a global advertised by the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobalInfo {
+    /// The numeric name of the global
+    pub name: u32,
+    /// The name of the interface of the global
+    pub interface: String,
+    /// The version of the global advertised by the server
+    pub version: u32,
+}
+
+/// The list of globals of a `wl_registry` maintained by the backend
+#[derive(Debug, Default)]
+pub(crate) struct GlobalCache {
+    globals: std::sync::Mutex<Vec<GlobalInfo>>,
+}
+
+impl GlobalCache {
+    /// Update the list with an event of the registry
+    pub(crate) fn handle_event<Id>(&self, msg: &crate::protocol::Message<Id>) {
+        use crate::protocol::Argument;
+        let mut globals = self.globals.lock().unwrap();
+        match (msg.opcode, &msg.args[..]) {
+            // wl_registry.global
+            (0, [Argument::Uint(name), Argument::Str(interface), Argument::Uint(version)]) => {
+                globals.push(GlobalInfo {
+                    name: *name,
+                    interface: interface.to_string_lossy().into_owned(),
+                    version: *version,
+                });
+            }
+            // wl_registry.global_remove
+            (1, [Argument::Uint(name)]) => globals.retain(|global| global.name != *name),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn globals(&self) -> Vec<GlobalInfo> {
+        self.globals.lock().unwrap().clone()
+    }
+}
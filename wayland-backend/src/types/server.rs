@@ -1,3 +1,5 @@
+use std::sync::{Arc, Mutex};
+
 use crate::protocol::Interface;
 
 /// Description of a global advertised to some clients.
@@ -62,6 +64,43 @@ pub enum DisconnectReason {
     ProtocolError(crate::protocol::ProtocolError),
 }
 
+type DisconnectHookFn<C> = dyn FnMut(C, &DisconnectReason) + Send;
+
+/// The hook invoked when a client is disconnected, shared by a backend and all its clients
+pub(crate) struct DisconnectHook<C> {
+    hook: Arc<Mutex<Option<Box<DisconnectHookFn<C>>>>>,
+}
+
+impl<C> DisconnectHook<C> {
+    pub(crate) fn new() -> DisconnectHook<C> {
+        DisconnectHook { hook: Arc::new(Mutex::new(None)) }
+    }
+
+    pub(crate) fn set(&self, hook: Box<DisconnectHookFn<C>>) {
+        *self.hook.lock().unwrap() = Some(hook);
+    }
+
+    pub(crate) fn invoke(&self, client_id: C, reason: &DisconnectReason) {
+        if let Some(hook) = self.hook.lock().unwrap().as_mut() {
+            hook(client_id, reason);
+        }
+    }
+}
+
+// manual impls to avoid requiring `C: Clone` and `C: Debug`
+impl<C> Clone for DisconnectHook<C> {
+    fn clone(&self) -> DisconnectHook<C> {
+        DisconnectHook { hook: self.hook.clone() }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<C> std::fmt::Debug for DisconnectHook<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DisconnectHook").finish_non_exhaustive()
+    }
+}
+
 /// Holds the client credentials
 #[derive(Debug, Clone, Copy)]
 pub struct Credentials {
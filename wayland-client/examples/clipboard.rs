@@ -0,0 +1,235 @@
+//! A command-line clipboard, using the wlr-data-control protocol
+//!
+//! `clipboard paste` prints the text of the current selection, and `clipboard copy <text>` sets
+//! the selection to some text, serving it to the clients pasting it until an other client
+//! replaces it.
+
+use std::{error::Error, io::Write, sync::Arc, sync::Mutex};
+
+use nix::{fcntl::OFlag, unistd};
+
+use wayland_client::{
+    event_created_child,
+    globals::GlobalList,
+    pipe::{read_pipe, write_pipe},
+    protocol::wl_seat,
+    Connection, ConnectionHandle, Dispatch, EventQueue, QueueHandle,
+};
+
+use wayland_protocols::wlr::unstable::data_control::v1::client::{
+    zwlr_data_control_device_v1, zwlr_data_control_manager_v1, zwlr_data_control_offer_v1,
+    zwlr_data_control_source_v1,
+};
+
+/// The text mime types, by order of preference
+const TEXT_MIME_TYPES: &[&str] = &["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let conn = Connection::connect_to_env().unwrap();
+
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["paste"] => paste(&conn).map(|text| {
+            std::io::stdout().write_all(&text).unwrap();
+        }),
+        ["copy", text] => copy(&conn, text),
+        _ => {
+            eprintln!("Usage: clipboard paste | clipboard copy <text>");
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("Clipboard error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Read the text of the current selection of the first seat
+pub fn paste(conn: &Connection) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (mut event_queue, mut state, _) = init(conn, Arc::from(&[][..]))?;
+
+    // the selection is sent when the data device is created
+    while !state.selection_received {
+        event_queue.blocking_dispatch(&mut state)?;
+    }
+    let offer = match state.selection {
+        Some(ref offer) => offer,
+        None => return Ok(Vec::new()),
+    };
+    let offered = offer.data::<OfferMimeTypes>().unwrap().lock().unwrap().clone();
+    let mime_type = TEXT_MIME_TYPES
+        .iter()
+        .find(|mime_type| offered.iter().any(|offered| offered == *mime_type))
+        .ok_or("the selection is not text")?;
+
+    let (read_fd, write_fd) = unistd::pipe2(OFlag::O_CLOEXEC)?;
+    offer.receive(&mut conn.handle(), mime_type.to_string(), write_fd);
+    let _ = unistd::close(write_fd);
+    conn.flush()?;
+    Ok(futures_executor::block_on(read_pipe(read_fd, Some(16 << 20)))?)
+}
+
+/// Set the selection of the first seat to some text, until an other client replaces it
+pub fn copy(conn: &Connection, text: &str) -> Result<(), Box<dyn Error>> {
+    let (mut event_queue, mut state, device) = init(conn, Arc::from(text.as_bytes()))?;
+    let qh = event_queue.handle();
+
+    let source = state.manager.create_data_source(&mut conn.handle(), &qh, ())?;
+    for mime_type in TEXT_MIME_TYPES {
+        source.offer(&mut conn.handle(), mime_type.to_string());
+    }
+    device.set_selection(&mut conn.handle(), Some(&source));
+
+    while !state.cancelled {
+        event_queue.blocking_dispatch(&mut state)?;
+    }
+    source.destroy(&mut conn.handle());
+    conn.flush()?;
+    Ok(())
+}
+
+fn init(
+    conn: &Connection,
+    text: Arc<[u8]>,
+) -> Result<
+    (EventQueue<State>, State, zwlr_data_control_device_v1::ZwlrDataControlDeviceV1),
+    Box<dyn Error>,
+> {
+    // the globals are listed on their own queue, before the state exists
+    let mut globals_queue = conn.new_event_queue();
+    let display = conn.handle().display();
+    let registry = display.get_registry(&mut conn.handle(), &globals_queue.handle(), ())?;
+    let mut globals = GlobalList::new();
+    conn.roundtrip()?;
+    globals_queue.dispatch_pending(&mut globals)?;
+
+    let event_queue = conn.new_event_queue();
+    let qh = event_queue.handle();
+    let manager = globals.bind::<zwlr_data_control_manager_v1::ZwlrDataControlManagerV1, _>(
+        &mut conn.handle(),
+        &qh,
+        &registry,
+        1..3,
+        (),
+    )?;
+    let seat = globals.bind::<wl_seat::WlSeat, _>(&mut conn.handle(), &qh, &registry, 1..2, ())?;
+    let device = manager.get_data_device(&mut conn.handle(), &seat, &qh, ())?;
+
+    let state =
+        State { manager, text, selection: None, selection_received: false, cancelled: false };
+    Ok((event_queue, state, device))
+}
+
+type OfferMimeTypes = Mutex<Vec<String>>;
+
+struct State {
+    manager: zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    text: Arc<[u8]>,
+    selection: Option<zwlr_data_control_offer_v1::ZwlrDataControlOfferV1>,
+    selection_received: bool,
+    cancelled: bool,
+}
+
+impl Dispatch<zwlr_data_control_device_v1::ZwlrDataControlDeviceV1> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                if let Some(previous) = std::mem::replace(&mut self.selection, id) {
+                    previous.destroy(conn);
+                }
+                self.selection_received = true;
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                // only the regular selection is used
+                offer.destroy(conn);
+            }
+            zwlr_data_control_device_v1::Event::Finished => self.cancelled = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, zwlr_data_control_device_v1::ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (
+            zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+            Mutex::new(Vec::new())
+        ),
+    ]);
+}
+
+impl Dispatch<zwlr_data_control_offer_v1::ZwlrDataControlOfferV1> for State {
+    type UserData = OfferMimeTypes;
+
+    fn event(
+        &mut self,
+        _: &zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        mime_types: &OfferMimeTypes,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            mime_types.lock().unwrap().push(mime_type);
+        }
+    }
+}
+
+impl Dispatch<zwlr_data_control_source_v1::ZwlrDataControlSourceV1> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { fd, .. } => {
+                // the transfer goes on in the background, even if the future is dropped
+                let _ = write_pipe(fd, self.text.clone());
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => self.cancelled = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwlr_data_control_manager_v1::ZwlrDataControlManagerV1> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+        _: zwlr_data_control_manager_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        // zwlr_data_control_manager_v1 has no event
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        // the first seat is used, whatever its capabilities
+    }
+}
@@ -0,0 +1,59 @@
+//! Print the state of the outputs of the compositor, like `wayland-info` does
+
+use std::{error::Error, io::Write};
+
+use wayland_client::{globals::OutputInfo, protocol::wl_output, Connection, WEnum};
+
+fn main() {
+    let conn = Connection::connect_to_env().unwrap();
+
+    if let Err(err) = run(&conn, &mut std::io::stdout()) {
+        eprintln!("Failed to list the outputs: {}", err);
+        std::process::exit(1);
+    }
+}
+
+pub fn run(conn: &Connection, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    let outputs = conn.fetch_global_contents::<wl_output::WlOutput>()?;
+    if outputs.is_empty() {
+        writeln!(out, "No output")?;
+    }
+    for (i, output) in outputs.iter().enumerate() {
+        print_output(out, i, output)?;
+    }
+    Ok(())
+}
+
+fn print_output(out: &mut impl Write, i: usize, output: &OutputInfo) -> std::io::Result<()> {
+    match output.name {
+        Some(ref name) => writeln!(out, "Output {}: {}", i, name)?,
+        None => writeln!(out, "Output {}", i)?,
+    }
+    if let Some(ref description) = output.description {
+        writeln!(out, "\tdescription: {}", description)?;
+    }
+    writeln!(out, "\tmake: {}, model: {}", output.make, output.model)?;
+    writeln!(out, "\tlocation: {}, {}", output.location.0, output.location.1)?;
+    writeln!(out, "\tphysical size: {} x {} mm", output.physical_size.0, output.physical_size.1)?;
+    writeln!(out, "\tscale: {}", output.scale)?;
+    if let Some(WEnum::Value(transform)) = output.transform {
+        writeln!(out, "\ttransform: {:?}", transform)?;
+    }
+    for mode in &output.modes {
+        write!(
+            out,
+            "\tmode: {} x {} @ {:.3} Hz",
+            mode.dimensions.0,
+            mode.dimensions.1,
+            mode.refresh_rate as f64 / 1000.
+        )?;
+        if mode.current {
+            write!(out, ", current")?;
+        }
+        if mode.preferred {
+            write!(out, ", preferred")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+//! Dump the contents of each output in a PPM image, using the wlr-screencopy protocol
+//!
+//! The images are written as `output-<n>.ppm` files in the current directory.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use wayland_client::{
+    globals::GlobalList,
+    protocol::{wl_output, wl_registry, wl_shm},
+    shm::ShmPool,
+    Connection, ConnectionHandle, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+};
+
+use wayland_protocols::wlr::unstable::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1, zwlr_screencopy_manager_v1,
+};
+
+fn main() {
+    let conn = Connection::connect_to_env().unwrap();
+
+    match run(&conn, Path::new(".")) {
+        Ok(paths) => {
+            for path in paths {
+                println!("Wrote {}", path.display());
+            }
+        }
+        Err(err) => {
+            eprintln!("Failed to capture the outputs: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn run(conn: &Connection, directory: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut event_queue = conn.new_event_queue();
+    let qh = event_queue.handle();
+
+    let display = conn.handle().display();
+    let registry = display.get_registry(&mut conn.handle(), &qh, ())?;
+
+    let mut state = State { globals: GlobalList::new(), frame: FrameState::default() };
+    conn.roundtrip()?;
+    event_queue.dispatch_pending(&mut state)?;
+
+    let manager = state.globals.bind::<zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1, _>(
+        &mut conn.handle(),
+        &qh,
+        &registry,
+        1..4,
+        (),
+    )?;
+    let shm =
+        state.globals.bind::<wl_shm::WlShm, _>(&mut conn.handle(), &qh, &registry, 1..2, ())?;
+
+    let outputs = state
+        .globals
+        .list()
+        .iter()
+        .filter(|global| global.interface == "wl_output")
+        .map(|global| {
+            registry.bind::<wl_output::WlOutput, _>(&mut conn.handle(), global.name, 1, &qh, ())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut paths = Vec::new();
+    for (i, output) in outputs.iter().enumerate() {
+        let path = directory.join(format!("output-{}.ppm", i));
+        capture(conn, &mut event_queue, &mut state, &manager, &shm, output, &path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn capture(
+    conn: &Connection,
+    event_queue: &mut EventQueue<State>,
+    state: &mut State,
+    manager: &zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+    shm: &wl_shm::WlShm,
+    output: &wl_output::WlOutput,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let qh = event_queue.handle();
+    let frame = manager.capture_output(&mut conn.handle(), 0, output, &qh, ())?;
+
+    // the compositor describes the buffer to copy the frame into
+    state.frame = FrameState::default();
+    while !state.frame.failed && !state.frame.buffer_ready(frame.version()) {
+        event_queue.blocking_dispatch(state)?;
+    }
+    let (format, width, height, stride) = match state.frame.buffer {
+        Some(buffer) if !state.frame.failed => buffer,
+        _ => return Err("the compositor failed to capture the output".into()),
+    };
+    if format != wl_shm::Format::Argb8888 && format != wl_shm::Format::Xrgb8888 {
+        return Err(format!("unsupported buffer format {:?}", format).into());
+    }
+
+    let mut pool = ShmPool::new(&mut conn.handle(), shm, (stride * height) as usize)?;
+    let mut buffer =
+        pool.create_buffer(&mut conn.handle(), width as i32, height as i32, stride as i32, format)?;
+    frame.copy(&mut conn.handle(), buffer.wl_buffer());
+    while !state.frame.failed && !state.frame.ready {
+        event_queue.blocking_dispatch(state)?;
+    }
+    frame.destroy(&mut conn.handle());
+    if state.frame.failed {
+        return Err("the compositor failed to copy the output".into());
+    }
+
+    let canvas = buffer.canvas().expect("the buffer was never attached");
+    write_ppm(path, canvas, width, height, stride, state.frame.y_invert)?;
+
+    drop(buffer);
+    pool.destroy(&mut conn.handle());
+    Ok(())
+}
+
+// Write 32 bits little-endian ARGB or XRGB pixels as a binary PPM image
+fn write_ppm(
+    path: &Path,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    y_invert: bool,
+) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for y in 0..height {
+        let row = if y_invert { height - 1 - y } else { y };
+        let start = (row * stride) as usize;
+        for pixel in pixels[start..start + 4 * width as usize].chunks_exact(4) {
+            out.write_all(&[pixel[2], pixel[1], pixel[0]])?;
+        }
+    }
+    out.flush()
+}
+
+struct State {
+    globals: GlobalList,
+    frame: FrameState,
+}
+
+#[derive(Default)]
+struct FrameState {
+    buffer: Option<(wl_shm::Format, u32, u32, u32)>,
+    buffer_done: bool,
+    y_invert: bool,
+    ready: bool,
+    failed: bool,
+}
+
+impl FrameState {
+    fn buffer_ready(&self, version: u32) -> bool {
+        // before version 3, the shm buffer is the only one described
+        self.buffer.is_some() && (version < 3 || self.buffer_done)
+    }
+}
+
+impl AsMut<GlobalList> for State {
+    fn as_mut(&mut self) -> &mut GlobalList {
+        &mut self.globals
+    }
+}
+
+wayland_client::delegate_dispatch!(State:
+    [wl_registry::WlRegistry] => GlobalList
+);
+
+impl Dispatch<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_screencopy_frame_v1::Event::Buffer {
+                format: WEnum::Value(format),
+                width,
+                height,
+                stride,
+            } => {
+                self.frame.buffer = Some((format, width, height, stride));
+            }
+            zwlr_screencopy_frame_v1::Event::Flags { flags: WEnum::Value(flags) } => {
+                self.frame.y_invert = flags.contains(zwlr_screencopy_frame_v1::Flags::YInvert);
+            }
+            zwlr_screencopy_frame_v1::Event::BufferDone => self.frame.buffer_done = true,
+            zwlr_screencopy_frame_v1::Event::Ready { .. } => self.frame.ready = true,
+            zwlr_screencopy_frame_v1::Event::Failed => self.frame.failed = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+        _: zwlr_screencopy_manager_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        // zwlr_screencopy_manager_v1 has no event
+    }
+}
+
+impl Dispatch<wl_shm::WlShm> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wl_shm::WlShm,
+        _: wl_shm::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        // the supported formats are given by the screencopy frames
+    }
+}
+
+impl Dispatch<wl_output::WlOutput> for State {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wl_output::WlOutput,
+        _: wl_output::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        // see the output_info example for the state of the outputs
+    }
+}
@@ -0,0 +1,211 @@
+//! Tracking of in-flight buffers
+//!
+//! A [`BufferBacklog`] holds the buffers a renderer rotates through (typically two or three) and
+//! tracks which of them the compositor is still reading, by handling their `wl_buffer.release`
+//! events itself. The renderer asks it for a free buffer with [`next_free()`](BufferBacklog::next_free)
+//! before drawing each frame, no [`Dispatch`](crate::Dispatch) implementation is needed for the
+//! buffers.
+//!
+//! ```no_run
+//! # fn draw(conn: &mut wayland_client::ConnectionHandle, pool: &wayland_client::protocol::wl_shm_pool::WlShmPool, surface: &wayland_client::protocol::wl_surface::WlSurface) {
+//! use wayland_client::{backlog::BufferBacklog, protocol::{wl_shm, wl_shm_pool}, WEnum};
+//!
+//! let mut backlog = BufferBacklog::new();
+//! for i in 0..2 {
+//!     let request = wl_shm_pool::Request::CreateBuffer {
+//!         offset: i * 256 * 256 * 4,
+//!         width: 256,
+//!         height: 256,
+//!         stride: 256 * 4,
+//!         format: WEnum::Value(wl_shm::Format::Argb8888),
+//!     };
+//!     backlog.create_buffer(conn, pool, request, i).unwrap();
+//! }
+//!
+//! if let Some(slot) = backlog.next_free() {
+//!     /* draw into the memory at the offset given by slot.data() */
+//!     slot.attach_to(conn, surface).unwrap();
+//! }
+//! # }
+//! ```
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use wayland_backend::{
+    client::{Handle, ObjectData, ObjectId},
+    protocol::Message,
+};
+
+use crate::{
+    protocol::{wl_buffer, wl_surface},
+    ConnectionHandle, DispatchError, Proxy,
+};
+
+type ReleaseCallback = Box<dyn Fn() + Send + Sync>;
+
+/// A set of buffers whose release is tracked
+///
+/// Each buffer is held in a [`BufferSlot`], along with some data of type `T` describing its
+/// contents.
+#[derive(Debug)]
+pub struct BufferBacklog<T> {
+    slots: Vec<BufferSlot<T>>,
+    shared: Arc<BacklogShared>,
+}
+
+impl<T> BufferBacklog<T> {
+    /// Create a new empty backlog
+    pub fn new() -> BufferBacklog<T> {
+        BufferBacklog { slots: Vec::new(), shared: Arc::new(BacklogShared::default()) }
+    }
+
+    /// Create a buffer tracked by this backlog
+    ///
+    /// The request must be a request of `factory` creating a `wl_buffer`, like
+    /// `wl_shm_pool.create_buffer`. The new buffer is initially free.
+    pub fn create_buffer<I: Proxy>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        factory: &I,
+        request: I::Request,
+        data: T,
+    ) -> Result<&mut BufferSlot<T>, DispatchError> {
+        let slot_data =
+            Arc::new(SlotData { busy: AtomicBool::new(false), shared: self.shared.clone() });
+        let id = conn.send_request(factory, request, Some(slot_data.clone()))?;
+        let buffer = wl_buffer::WlBuffer::from_id(conn, id.clone())
+            .map_err(|_| DispatchError::DeadObject { interface: "wl_buffer", id })?;
+        self.slots.push(BufferSlot { buffer, slot_data, data });
+        Ok(self.slots.last_mut().unwrap())
+    }
+
+    /// Get a buffer the compositor is not reading
+    ///
+    /// Returns `None` if all the buffers are busy, in which case the renderer needs to wait for one
+    /// of them to be released, see [`on_release()`](BufferBacklog::on_release).
+    pub fn next_free(&mut self) -> Option<&mut BufferSlot<T>> {
+        self.slots.iter_mut().find(|slot| !slot.is_busy())
+    }
+
+    /// Set a callback invoked each time one of the buffers is released
+    ///
+    /// It is invoked by the thread reading the socket, while the connection is locked: it must not
+    /// use the connection.
+    pub fn on_release<F: Fn() + Send + Sync + 'static>(&self, callback: F) {
+        *self.shared.on_release.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// The slots of this backlog
+    pub fn slots(&self) -> &[BufferSlot<T>] {
+        &self.slots
+    }
+
+    /// Remove all the slots of this backlog, destroying their buffers
+    ///
+    /// The data of the slots is returned. It must not be reused for buffers which were busy, as the
+    /// compositor may still be reading them.
+    pub fn clear(&mut self, conn: &mut ConnectionHandle) -> Vec<T> {
+        self.slots
+            .drain(..)
+            .map(|slot| {
+                slot.buffer.destroy(conn);
+                slot.data
+            })
+            .collect()
+    }
+}
+
+impl<T> Default for BufferBacklog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A buffer of a [`BufferBacklog`]
+#[derive(Debug)]
+pub struct BufferSlot<T> {
+    buffer: wl_buffer::WlBuffer,
+    slot_data: Arc<SlotData>,
+    data: T,
+}
+
+impl<T> BufferSlot<T> {
+    /// The `wl_buffer` of this slot
+    pub fn wl_buffer(&self) -> &wl_buffer::WlBuffer {
+        &self.buffer
+    }
+
+    /// Whether the compositor may currently be reading this buffer
+    pub fn is_busy(&self) -> bool {
+        self.slot_data.busy.load(Ordering::Acquire)
+    }
+
+    /// Mark this buffer as busy, until the compositor releases it
+    ///
+    /// This is done by [`attach_to()`](BufferSlot::attach_to), you need to call it if you attach
+    /// the buffer by other means.
+    pub fn mark_busy(&self) {
+        self.slot_data.busy.store(true, Ordering::Release);
+    }
+
+    /// Attach this buffer to a surface and mark it as busy
+    pub fn attach_to(
+        &self,
+        conn: &mut ConnectionHandle,
+        surface: &wl_surface::WlSurface,
+    ) -> Result<(), DispatchError> {
+        self.mark_busy();
+        conn.send_request(
+            surface,
+            wl_surface::Request::Attach { buffer: Some(self.buffer.clone()), x: 0, y: 0 },
+            None,
+        )
+        .map(|_| ())
+    }
+
+    /// The data associated with this slot
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// Mutable access to the data associated with this slot
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+}
+
+#[derive(Default)]
+struct BacklogShared {
+    on_release: Mutex<Option<ReleaseCallback>>,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Debug for BacklogShared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BacklogShared")
+            .field("on_release", &self.on_release.lock().unwrap().is_some())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct SlotData {
+    busy: AtomicBool,
+    shared: Arc<BacklogShared>,
+}
+
+impl ObjectData for SlotData {
+    fn event(self: Arc<Self>, _: &mut Handle, _: Message<ObjectId>) -> Option<Arc<dyn ObjectData>> {
+        // wl_buffer.release is the only event of wl_buffer
+        self.busy.store(false, Ordering::Release);
+        if let Some(ref callback) = *self.shared.on_release.lock().unwrap() {
+            callback();
+        }
+        None
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+}
@@ -1,23 +1,37 @@
 use std::{
+    cell::RefCell,
     env,
-    io::ErrorKind,
     os::unix::net::UnixStream,
-    os::unix::prelude::FromRawFd,
+    os::unix::prelude::{FromRawFd, RawFd},
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, Mutex, MutexGuard,
+        Arc, Mutex, MutexGuard, TryLockError, Weak,
     },
 };
 
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+
 use wayland_backend::{
-    client::{Backend, Handle, InvalidId, ObjectData, ObjectId, ReadEventsGuard, WaylandError},
-    protocol::{Interface, ObjectInfo, ProtocolError},
+    client::{
+        Backend, ExclusiveReadEventsGuard, Handle, InvalidId, ObjectData, ObjectId, Operation,
+        ReadEventsGuard, WaylandError,
+    },
+    protocol::{Argument, Interface, Message, ObjectInfo, ProtocolError},
+    smallvec::SmallVec,
 };
 
-use nix::{fcntl, Error};
+use nix::{
+    fcntl,
+    sys::socket::{getpeername, getsockname, getsockopt, sockopt, SockAddr, SockType},
+    Error,
+};
 
-use crate::{EventQueue, Proxy};
+use crate::{
+    dynamic::{DynamicData, DynamicError, DynamicEvent, Value},
+    event_queue::BufferedEvents,
+    AnyEventQueue, DispatchError, EventQueue, Proxy,
+};
 
 /// The Wayland connection
 ///
@@ -27,6 +41,9 @@ use crate::{EventQueue, Proxy};
 #[derive(Debug, Clone)]
 pub struct Connection {
     backend: Arc<Mutex<Backend>>,
+    dynamic: Arc<Mutex<Option<Arc<DynamicData>>>>,
+    outgoing: Outgoing,
+    buffered: BufferedEvents,
 }
 
 impl Connection {
@@ -44,44 +61,114 @@ impl Connection {
             let fd = txt.parse::<i32>().map_err(|_| ConnectError::InvalidFd)?;
             // remove the variable so any child processes don't see it
             env::remove_var("WAYLAND_SOCKET");
-            // set the CLOEXEC flag on this FD
-            let flags = fcntl::fcntl(fd, fcntl::FcntlArg::F_GETFD);
-            let result = flags
-                .map(|f| fcntl::FdFlag::from_bits(f).unwrap() | fcntl::FdFlag::FD_CLOEXEC)
-                .and_then(|f| fcntl::fcntl(fd, fcntl::FcntlArg::F_SETFD(f)));
-            match result {
-                Ok(_) => {
-                    // setting the O_CLOEXEC worked
-                    unsafe { FromRawFd::from_raw_fd(fd) }
-                }
-                Err(_) => {
-                    // something went wrong in F_GETFD or F_SETFD
-                    let _ = ::nix::unistd::close(fd);
-                    return Err(ConnectError::InvalidFd);
-                }
-            }
+            // the fd was given to this process, which now owns it
+            unsafe { checked_socket(fd)? }
         } else {
-            let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
-                .map(Into::<PathBuf>::into)
-                .ok_or(ConnectError::NoCompositor)?;
-            socket_path.push(env::var_os("WAYLAND_DISPLAY").ok_or(ConnectError::NoCompositor)?);
-
+            let socket_path = default_socket_path().ok_or(ConnectError::NoCompositor)?;
             UnixStream::connect(socket_path).map_err(|_| ConnectError::NoCompositor)?
         };
 
         let backend = Backend::connect(stream).map_err(|_| ConnectError::NoWaylandLib)?;
-        Ok(Connection { backend: Arc::new(Mutex::new(backend)) })
+        Ok(Connection::from_backend(Arc::new(Mutex::new(backend))))
+    }
+
+    /// Get the connection to the default display shared by the whole process
+    ///
+    /// Independent libraries of a process, like a toolkit and a media library, can use this
+    /// instead of [`connect_to_env()`](Connection::connect_to_env) to use the same connection to
+    /// the compositor rather than each opening their own: the first call connects following the
+    /// environment, and the next ones return this connection as long as it is alive and has not
+    /// failed. Each library should still use its own event queues.
+    ///
+    /// The connection is kept alive by its users only, and a new one is opened once they all
+    /// dropped it. An application can provide its own connection with
+    /// [`share()`](Connection::share), or opt out with
+    /// [`disable_sharing()`](Connection::disable_sharing).
+    pub fn connect_shared() -> Result<Connection, ConnectError> {
+        let mut shared = SHARED.lock().unwrap();
+        if !shared.enabled {
+            return Connection::connect_to_env();
+        }
+        // the socket path must be resolved before connecting, which consumes WAYLAND_SOCKET
+        let socket_path =
+            if env::var_os("WAYLAND_SOCKET").is_some() { None } else { default_socket_path() };
+        if let Some(backend) = shared.backend.upgrade() {
+            let same_display = shared.socket_path.is_none() || shared.socket_path == socket_path;
+            let failed = backend.lock().unwrap().handle().last_error().is_some();
+            if same_display && !failed {
+                let outgoing = shared.outgoing.clone();
+                let buffered = shared.buffered.clone();
+                return Ok(Connection { backend, dynamic: Default::default(), outgoing, buffered });
+            }
+        }
+        let connection = Connection::connect_to_env()?;
+        shared.backend = Arc::downgrade(&connection.backend);
+        shared.outgoing = connection.outgoing.clone();
+        shared.buffered = connection.buffered.clone();
+        shared.socket_path = socket_path;
+        Ok(connection)
+    }
+
+    /// Make this connection the one shared for the default display
+    ///
+    /// The next calls to [`connect_shared()`](Connection::connect_shared) return this connection
+    /// while it is alive, for example if the application connected with
+    /// [`from_socket()`](Connection::from_socket). This does nothing if sharing was disabled.
+    pub fn share(&self) {
+        let mut shared = SHARED.lock().unwrap();
+        if shared.enabled {
+            shared.backend = Arc::downgrade(&self.backend);
+            shared.outgoing = self.outgoing.clone();
+            shared.buffered = self.buffered.clone();
+            shared.socket_path = None;
+        }
+    }
+
+    /// Disable the sharing of connections in this process
+    ///
+    /// The next calls to [`connect_shared()`](Connection::connect_shared) each open a new
+    /// connection, like [`connect_to_env()`](Connection::connect_to_env). The connections already
+    /// shared are not affected.
+    pub fn disable_sharing() {
+        let mut shared = SHARED.lock().unwrap();
+        shared.enabled = false;
+        shared.backend = Weak::new();
+        shared.socket_path = None;
     }
 
     /// Initialize a Wayland connection from an already existing Unix stream
     pub fn from_socket(stream: UnixStream) -> Result<Connection, ConnectError> {
         let backend = Backend::connect(stream).map_err(|_| ConnectError::NoWaylandLib)?;
-        Ok(Connection { backend: Arc::new(Mutex::new(backend)) })
+        Ok(Connection::from_backend(Arc::new(Mutex::new(backend))))
+    }
+
+    /// Initialize a Wayland connection from a socket given as a raw file descriptor
+    ///
+    /// This is meant for the clients handed one end of a socket pair by a launcher or a compositor,
+    /// as [`connect_to_env()`](Connection::connect_to_env) does with the `WAYLAND_SOCKET`
+    /// environment variable. The file descriptor must be a connected unix stream socket, and its
+    /// `CLOEXEC` flag is set so that it does not leak to child processes. It is closed if it is
+    /// not valid.
+    ///
+    /// # Safety
+    ///
+    /// The file descriptor must be owned by the caller, the connection taking ownership of it.
+    pub unsafe fn from_raw_fd_checked(fd: RawFd) -> Result<Connection, ConnectError> {
+        Connection::from_socket(checked_socket(fd)?)
     }
 
     /// Wrap an existing [`Backend`] into a Connection
+    ///
+    /// This registers the interfaces of the core protocol, see
+    /// [`register_interfaces()`](crate::backend::protocol::register_interfaces).
     pub fn from_backend(backend: Arc<Mutex<Backend>>) -> Connection {
-        Connection { backend }
+        wayland_backend::protocol::register_interfaces(crate::protocol::__interfaces::INTERFACES);
+        Connection {
+            backend,
+            dynamic: Default::default(),
+            outgoing: Outgoing::new(),
+            buffered: BufferedEvents::default(),
+        }
     }
 
     /// Get the [`Backend`] underlying this Connection
@@ -91,9 +178,15 @@ impl Connection {
 
     /// Flush pending outgoing events to the server
     ///
-    /// This needs to be done regularly to ensure the server receives all your requests.
+    /// This needs to be done regularly to ensure the server receives all your requests. The
+    /// requests queued by the [`Sender`]s of this connection are sent first.
     pub fn flush(&self) -> Result<(), WaylandError> {
-        self.backend.lock().unwrap().flush()
+        self.outgoing.flush(&self.backend)
+    }
+
+    /// Get a [`Sender`] sending requests on this connection from other threads
+    pub fn sender(&self) -> Sender {
+        Sender { backend: self.backend.clone(), outgoing: self.outgoing.clone() }
     }
 
     /// Start a synchronized read from the socket
@@ -106,9 +199,64 @@ impl Connection {
     /// If you don't need to manage multiple event sources, see
     /// [`blocking_dispatch()`](Connection::blocking_dispatch) for a simpler mechanism.
     pub fn prepare_read(&self) -> Result<ReadEventsGuard, WaylandError> {
+        check_not_dispatching(&self.backend)?;
         ReadEventsGuard::try_new(self.backend.clone())
     }
 
+    /// Start a read from the socket keeping the connection locked
+    ///
+    /// This is an alternative to [`prepare_read()`](Connection::prepare_read) for event loops
+    /// doing their own synchronization, like single-threaded ones: the connection is locked once
+    /// when creating the guard and stays locked until it is read or dropped, rather than being
+    /// locked again for each operation of the guard. See [`ExclusiveReadEventsGuard`] for details.
+    ///
+    /// No other thread can use the connection while the guard is alive, and using the
+    /// connection from the same thread in the meantime deadlocks.
+    pub fn prepare_read_exclusive(
+        &self,
+    ) -> Result<ExclusiveReadEventsGuard<MutexGuard<'_, Backend>>, WaylandError> {
+        ExclusiveReadEventsGuard::try_new(self.backend.lock().unwrap())
+    }
+
+    /// Read events from the socket without dispatching them
+    ///
+    /// This reads all events currently available on the Wayland socket and stores them in the
+    /// internal buffers of their respective event queues, without invoking any of the
+    /// [`Dispatch`](crate::Dispatch) callbacks. If no events are available, this returns `Ok(0)`
+    /// rather than blocking. It still synchronizes with the reading guards of other threads, see
+    /// [`ReadEventsGuard`] for details.
+    ///
+    /// This allows integrations to clearly separate socket reading from callback invocation, for
+    /// example reading from a thread that must never run app callbacks. The buffered events are
+    /// then dispatched from the thread owning each event queue using
+    /// [`dispatch_buffered()`](Connection::dispatch_buffered), which never reads the socket.
+    ///
+    /// Returns the number of events buffered in the event queues of this connection and not
+    /// dispatched yet, including the ones read before this call.
+    pub fn read_without_dispatch(&self) -> Result<usize, WaylandError> {
+        match self.prepare_read()?.read() {
+            Ok(_) => {}
+            Err(e) if e.is_would_block() => {}
+            Err(e) => return Err(e),
+        }
+        Ok(self.buffered.count())
+    }
+
+    /// Dispatch the events buffered in an event queue of this connection
+    ///
+    /// This is the step following [`read_without_dispatch()`](Connection::read_without_dispatch):
+    /// it invokes the [`Dispatch`](crate::Dispatch) callbacks of all the events already read for
+    /// this queue, ignoring its [budget](EventQueue::set_budget), and never reads the socket.
+    ///
+    /// Returns the number of events that were dispatched.
+    pub fn dispatch_buffered<D>(
+        &self,
+        queue: &mut EventQueue<D>,
+        data: &mut D,
+    ) -> Result<usize, DispatchError> {
+        queue.dispatch_budget(data, usize::MAX)
+    }
+
     /// Block until events are received from the server
     ///
     /// This will flush the outgoing socket, and then block until events are received from the
@@ -116,8 +264,11 @@ impl Connection {
     /// [`EventQueue::dispatch_pending()`](EventQueue::dispatch_pending) to dispatch them on
     /// their respective event queues. Alternatively,
     /// [`EventQueue::blocking_dispatch()`](EventQueue::blocking_dispatch) does both.
+    ///
+    /// This cannot be invoked from within the [`Dispatch`](crate::Dispatch) callbacks of this
+    /// connection, and fails with [`WaylandError::NestedDispatch`] in this case.
     pub fn blocking_dispatch(&self) -> Result<usize, WaylandError> {
-        blocking_dispatch_impl(self.backend.clone())
+        blocking_dispatch_impl(self.backend.clone(), &self.outgoing)
     }
 
     /// Do a roundtrip to the server
@@ -125,11 +276,18 @@ impl Connection {
     /// This method will block until the Wayland server has processed and answered all your
     /// preceding requests. This is notably useful during the initial setup of an app, to wait for
     /// the initial state from the server.
+    ///
+    /// This cannot be invoked from within the [`Dispatch`](crate::Dispatch) callbacks of this
+    /// connection, as the events answering the roundtrip could never be dispatched, and fails with
+    /// [`WaylandError::NestedDispatch`] in this case.
     pub fn roundtrip(&self) -> Result<usize, WaylandError> {
+        check_not_dispatching(&self.backend)?;
         let done = Arc::new(AtomicBool::new(false));
         {
             let mut backend = self.backend.lock().unwrap();
             let mut handle = ConnectionHandle::from_handle(backend.handle());
+            // the roundtrip also covers the requests queued by the senders
+            self.outgoing.drain(&mut handle);
             let display = handle.display();
             let cb_done = done.clone();
             let sync_data = Arc::new(SyncData { done: cb_done });
@@ -139,34 +297,125 @@ impl Connection {
                     crate::protocol::wl_display::Request::Sync {},
                     Some(sync_data),
                 )
-                .map_err(|_| WaylandError::Io(Error::EPIPE.into()))?;
+                .map_err(|_| WaylandError::io(Operation::Write, Error::EPIPE.into()))?;
         }
 
         let mut dispatched = 0;
 
         while !done.load(Ordering::Acquire) {
-            dispatched += blocking_dispatch_impl(self.backend.clone())?;
+            dispatched += blocking_dispatch_impl(self.backend.clone(), &self.outgoing)?;
         }
 
         Ok(dispatched)
     }
 
+    /// Collect a snapshot of the state of all the globals of the interface `I`
+    ///
+    /// This binds the globals, collects the events they send in response using roundtrips, and
+    /// releases them if their interface allows it. It is meant for tools that just query the state
+    /// of the compositor, like the outputs or the formats supported for shared memory buffers:
+    ///
+    /// ```no_run
+    /// use wayland_client::{protocol::wl_output::WlOutput, Connection};
+    ///
+    /// let conn = Connection::connect_to_env().unwrap();
+    /// for output in conn.fetch_global_contents::<WlOutput>().unwrap() {
+    ///     println!("{:?}: {} {}", output.name, output.make, output.model);
+    /// }
+    /// ```
+    ///
+    /// The snapshots are returned in the order the globals were advertized. As this dispatches
+    /// events, it cannot be invoked from within the dispatching of the connection.
+    pub fn fetch_global_contents<I: crate::globals::GlobalContents>(
+        &self,
+    ) -> Result<Vec<I::Contents>, DispatchError> {
+        crate::globals::fetch_global_contents::<I>(self).map(|(_, contents)| contents)
+    }
+
+    /// Check which of a set of interfaces the compositor supports
+    ///
+    /// This lists the globals with a single roundtrip, and reports for each requested interface
+    /// name whether it is advertized, and with which version. It is meant for detecting the
+    /// optional features of the compositor at startup:
+    ///
+    /// ```no_run
+    /// use wayland_client::Connection;
+    ///
+    /// let conn = Connection::connect_to_env().unwrap();
+    /// let support = conn
+    ///     .probe_protocols(&["wp_fractional_scale_manager_v1", "wp_tearing_control_manager_v1"])
+    ///     .unwrap();
+    /// if let Some(version) = support.version("wp_fractional_scale_manager_v1") {
+    ///     println!("fractional scaling is supported, version {}", version);
+    /// }
+    /// ```
+    ///
+    /// As this dispatches events, it cannot be invoked from within the dispatching of the
+    /// connection.
+    pub fn probe_protocols(
+        &self,
+        interfaces: &[&str],
+    ) -> Result<crate::globals::ProtocolSupport, DispatchError> {
+        crate::globals::probe_protocols(self, interfaces)
+    }
+
     /// Create a new event queue
     pub fn new_event_queue<D>(&self) -> EventQueue<D> {
-        EventQueue::new(self.backend.clone())
+        EventQueue::new(self.backend.clone(), self.outgoing.clone(), self.buffered.clone())
+    }
+
+    /// Create a new type-erased event queue
+    ///
+    /// See [`AnyEventQueue`] for details.
+    pub fn new_any_event_queue(&self) -> AnyEventQueue {
+        AnyEventQueue::new(self.backend.clone(), self.outgoing.clone(), self.buffered.clone())
     }
 
     /// Retrive the protocol error that occured on the socket (if any)
     pub fn protocol_error(&self) -> Option<ProtocolError> {
         match dbg!(self.backend.lock().unwrap().handle().last_error())? {
             WaylandError::Protocol(err) => Some(err),
-            WaylandError::Io(_) => None,
+            WaylandError::Io { .. } | WaylandError::NestedDispatch => None,
         }
     }
+
+    /// Set the callback receiving the events of the objects created by dynamically typed requests
+    ///
+    /// The callback is captured by the objects when they are created, so replacing it does not
+    /// affect the existing objects. It is shared by the clones of this `Connection`. See the
+    /// [`dynamic`](crate::dynamic) module.
+    pub fn set_dynamic_callback(
+        &self,
+        callback: impl Fn(&mut ConnectionHandle, DynamicEvent) + Send + Sync + 'static,
+    ) {
+        *self.dynamic.lock().unwrap() =
+            Some(Arc::new(DynamicData { callback: Box::new(callback) }));
+    }
+
+    /// Send a request by name, with dynamically typed arguments
+    ///
+    /// `args` lists the arguments of the request in the order of its signature, except the
+    /// `new_id` ones: the object created by the request, if any, is returned. It receives the
+    /// dynamic callback of `object` if it was created by a dynamically typed request, and the one
+    /// set with [`set_dynamic_callback()`](Connection::set_dynamic_callback) otherwise. See the
+    /// [`dynamic`](crate::dynamic) module.
+    pub fn send_dynamic(
+        &self,
+        object: &ObjectId,
+        request: &str,
+        args: &[Value],
+    ) -> Result<Option<ObjectId>, DynamicError> {
+        let fallback = self.dynamic.lock().unwrap().clone();
+        self.handle().send_dynamic_with(object, request, args, fallback)
+    }
 }
 
-pub(crate) fn blocking_dispatch_impl(backend: Arc<Mutex<Backend>>) -> Result<usize, WaylandError> {
-    backend.lock().unwrap().flush()?;
+pub(crate) fn blocking_dispatch_impl(
+    backend: Arc<Mutex<Backend>>,
+    outgoing: &Outgoing,
+) -> Result<usize, WaylandError> {
+    check_not_dispatching(&backend)?;
+    outgoing.flush(&backend)?;
 
     // first, prepare the read
     let guard = ReadEventsGuard::try_new(backend)?;
@@ -180,7 +429,7 @@ pub(crate) fn blocking_dispatch_impl(backend: Arc<Mutex<Backend>>) -> Result<usi
         match nix::poll::poll(&mut fds, -1) {
             Ok(_) => break,
             Err(nix::errno::Errno::EINTR) => continue,
-            Err(e) => return Err(WaylandError::Io(e.into())),
+            Err(e) => return Err(WaylandError::io(Operation::Read, e.into())),
         }
     }
 
@@ -189,11 +438,58 @@ pub(crate) fn blocking_dispatch_impl(backend: Arc<Mutex<Backend>>) -> Result<usi
         Ok(n) => Ok(n),
         // if we are still "wouldblock", that means that there was a dispatch from an other
         // thread with the C-based backend, spuriously return 0.
-        Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => Ok(0),
+        Err(e) if e.is_would_block() => Ok(0),
         Err(e) => Err(e),
     }
 }
 
+thread_local! {
+    // the connections whose events are being dispatched by the current thread, and which are thus
+    // locked until the dispatching is over
+    //
+    // a const initializer is not available with the minimum supported rust version
+    #[allow(clippy::missing_const_for_thread_local)]
+    static DISPATCHING: RefCell<Vec<*const Mutex<Backend>>> = RefCell::new(Vec::new());
+}
+
+/// Marks a connection as being dispatched by the current thread, as long as it is alive
+pub(crate) struct DispatchingGuard {
+    backend: *const Mutex<Backend>,
+}
+
+impl DispatchingGuard {
+    /// Fails with [`WaylandError::NestedDispatch`] if the connection is already being dispatched
+    /// by the current thread
+    pub(crate) fn new(backend: &Arc<Mutex<Backend>>) -> Result<DispatchingGuard, WaylandError> {
+        check_not_dispatching(backend)?;
+        let backend = Arc::as_ptr(backend);
+        DISPATCHING.with(|dispatching| dispatching.borrow_mut().push(backend));
+        Ok(DispatchingGuard { backend })
+    }
+}
+
+impl Drop for DispatchingGuard {
+    fn drop(&mut self) {
+        DISPATCHING.with(|dispatching| {
+            let mut dispatching = dispatching.borrow_mut();
+            if let Some(idx) = dispatching.iter().rposition(|&b| b == self.backend) {
+                dispatching.remove(idx);
+            }
+        });
+    }
+}
+
+/// Check that a blocking operation on a connection is not invoked from within the dispatching of
+/// its events, which would deadlock on its lock
+pub(crate) fn check_not_dispatching(backend: &Arc<Mutex<Backend>>) -> Result<(), WaylandError> {
+    let backend = Arc::as_ptr(backend);
+    if DISPATCHING.with(|dispatching| dispatching.borrow().contains(&backend)) {
+        Err(WaylandError::NestedDispatch)
+    } else {
+        Ok(())
+    }
+}
+
 /// A handle to the Wayland connection
 #[derive(Debug)]
 pub struct ConnectionHandle<'a> {
@@ -231,22 +527,49 @@ impl<'a> ConnectionHandle<'a> {
     ///
     /// This is a low-level interface for sending requests, you will likely instead use
     /// the methods of the types representing each interface.
+    ///
+    /// If the object or one of the objects given as argument is dead, this returns
+    /// [`DispatchError::DeadObject`].
     pub fn send_request<I: Proxy>(
         &mut self,
         proxy: &I,
         request: I::Request,
         data: Option<Arc<dyn ObjectData>>,
-    ) -> Result<ObjectId, InvalidId> {
-        let msg = proxy.write_request(self, request)?;
-        self.inner.handle().send_request(msg, data)
+    ) -> Result<ObjectId, DispatchError> {
+        let msg = proxy.write_request(self, request).map_err(|_| dead_object(proxy.id()))?;
+        self.send_message(msg, data)
+    }
+
+    pub(crate) fn send_message(
+        &mut self,
+        msg: Message<ObjectId>,
+        data: Option<Arc<dyn ObjectData>>,
+    ) -> Result<ObjectId, DispatchError> {
+        let sender_id = msg.sender_id.clone();
+        let object_args = msg
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                Argument::Object(id) if !id.is_null() => Some(id.clone()),
+                _ => None,
+            })
+            .collect::<SmallVec<[_; 4]>>();
+        self.inner.handle().send_request(msg, data).map_err(|_| {
+            // find which object caused the failure
+            let dead_id = std::iter::once(sender_id.clone())
+                .chain(object_args)
+                .find(|id| self.inner.handle().info(id.clone()).is_err())
+                .unwrap_or(sender_id);
+            dead_object(dead_id)
+        })
     }
 
     /// Create a placeholder id for request serialization
     ///
     /// This is a low-level interface for sending requests, you don't need to use it if you
     /// are using the methods of the types representing each interface.
-    pub fn placeholder_id(&mut self, spec: Option<(&'static Interface, u32)>) -> ObjectId {
-        self.inner.handle().placeholder_id(spec)
+    pub fn placeholder_id(&mut self, interface: Option<&'static Interface>) -> ObjectId {
+        self.inner.handle().placeholder_id(interface)
     }
 
     /// Create a null id for request serialization
@@ -269,16 +592,231 @@ impl<'a> ConnectionHandle<'a> {
     pub fn object_info(&mut self, id: ObjectId) -> Result<ObjectInfo, InvalidId> {
         self.inner.handle().info(id)
     }
+
+    /// Get the protocol information related to several object IDs at once
+    ///
+    /// See [`Handle::info_many()`](wayland_backend::client::Handle::info_many) for details.
+    pub fn object_info_many(&mut self, ids: &[ObjectId]) -> Vec<Result<ObjectInfo, InvalidId>> {
+        self.inner.handle().info_many(ids)
+    }
+
+    /// Register a hook to be invoked when this object is destroyed
+    ///
+    /// This allows independent components to each clean up their own state associated with an
+    /// object. The hooks of an object are invoked in registration order, once the object has been
+    /// destroyed and before its protocol id can be reused. They are invoked while the connection
+    /// is borrowed, and thus must not try to access it.
+    pub fn add_destruction_hook<I: Proxy>(
+        &mut self,
+        proxy: &I,
+        hook: impl FnOnce(ObjectId) + Send + Sync + 'static,
+    ) -> Result<(), InvalidId> {
+        self.inner.handle().add_destruction_hook(proxy.id(), Box::new(hook))
+    }
+}
+
+/// A handle sending requests on a [`Connection`] from other threads
+///
+/// Obtained with [`Connection::sender()`], it is cheaply cloneable and can only send requests
+/// and flush them. The requests are not written immediately: they are queued without any lock,
+/// and sent in order on the next flush of the connection, which happens in
+/// [`Connection::flush()`], [`EventQueue::flush()`] and before each blocking dispatch. This way
+/// the threads rendering or doing background work never block on the connection while the
+/// dispatching thread holds it.
+///
+/// The requests queued by a `Sender` are sent after the ones already sent directly on the
+/// connection, and their errors cannot be reported to the `Sender`: requests to dead objects
+/// are logged and dropped. For the same reason, requests creating objects cannot be sent by a
+/// `Sender`, as the new objects would only be created once the request is flushed.
+///
+/// ```no_run
+/// # fn run(conn: wayland_client::Connection, surface: wayland_client::protocol::wl_surface::WlSurface) {
+/// use wayland_client::protocol::wl_surface;
+///
+/// let sender = conn.sender();
+/// std::thread::spawn(move || {
+///     // render to the buffer attached to the surface, then
+///     sender.send_request(&surface, wl_surface::Request::Damage { x: 0, y: 0, width: 64, height: 64 });
+///     sender.send_request(&surface, wl_surface::Request::Commit {});
+///     sender.flush().unwrap();
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Sender {
+    backend: Arc<Mutex<Backend>>,
+    outgoing: Outgoing,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Debug for Sender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl Sender {
+    /// Queue a request associated with the provided object
+    ///
+    /// The request is sent on the next flush of the connection. Requests creating objects are
+    /// dropped with an error logged.
+    pub fn send_request<I>(&self, proxy: &I, request: I::Request)
+    where
+        I: Proxy + 'static,
+        I::Request: Send + 'static,
+    {
+        let id = proxy.id();
+        self.outgoing.push(Box::new(move |conn| {
+            let msg = match I::from_id(conn, id.clone())
+                .and_then(|proxy| proxy.write_request(conn, request))
+            {
+                Ok(msg) => msg,
+                Err(_) => {
+                    log::warn!("Dropping a request queued by a Sender for the dead object {}", id);
+                    return;
+                }
+            };
+            if msg.args.iter().any(|arg| matches!(arg, Argument::NewId(_))) {
+                let name = I::interface().requests.get(msg.opcode as usize).map(|desc| desc.name);
+                log::error!(
+                    "Dropping the request {}.{} queued by a Sender, as it creates an object",
+                    I::interface().name,
+                    name.unwrap_or("unknown")
+                );
+                return;
+            }
+            if let Err(err) = conn.send_message(msg, None) {
+                log::warn!("Dropping a request queued by a Sender: {}", err);
+            }
+        }));
+    }
+
+    /// Flush the queued requests, unless the connection is in use
+    ///
+    /// If another thread holds the connection, the requests are left for its next flush and
+    /// `Ok(false)` is returned without blocking. Otherwise the queued requests are sent and the
+    /// socket is flushed.
+    pub fn flush(&self) -> Result<bool, WaylandError> {
+        let mut backend = match self.backend.try_lock() {
+            Ok(backend) => backend,
+            Err(TryLockError::WouldBlock) => return Ok(false),
+            Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+        };
+        self.outgoing.drain(&mut ConnectionHandle::from_handle(backend.handle()));
+        backend.flush()?;
+        Ok(true)
+    }
+}
+
+type QueuedRequest = Box<dyn FnOnce(&mut ConnectionHandle) + Send>;
+
+// The requests queued by the senders of a connection
+#[derive(Clone)]
+pub(crate) struct Outgoing {
+    tx: UnboundedSender<QueuedRequest>,
+    // only locked with the backend, so that the senders never contend on it
+    rx: Arc<Mutex<UnboundedReceiver<QueuedRequest>>>,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Debug for Outgoing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Outgoing").finish_non_exhaustive()
+    }
+}
+
+impl Outgoing {
+    fn new() -> Outgoing {
+        let (tx, rx) = unbounded();
+        Outgoing { tx, rx: Arc::new(Mutex::new(rx)) }
+    }
+
+    fn push(&self, request: QueuedRequest) {
+        // the receiver lives as long as the senders
+        let _ = self.tx.unbounded_send(request);
+    }
+
+    /// Send the queued requests
+    pub(crate) fn drain(&self, conn: &mut ConnectionHandle) {
+        let mut rx = self.rx.lock().unwrap();
+        while let Ok(request) = rx.try_recv() {
+            request(conn);
+        }
+    }
+
+    /// Send the queued requests and flush the socket
+    pub(crate) fn flush(&self, backend: &Mutex<Backend>) -> Result<(), WaylandError> {
+        let mut backend = backend.lock().unwrap();
+        self.drain(&mut ConnectionHandle::from_handle(backend.handle()));
+        backend.flush()
+    }
+}
+
+fn dead_object(id: ObjectId) -> DispatchError {
+    DispatchError::DeadObject { interface: id.interface().name, id }
+}
+
+// the path of the socket of the default display, following the environment
+fn default_socket_path() -> Option<PathBuf> {
+    let mut socket_path: PathBuf = env::var_os("XDG_RUNTIME_DIR")?.into();
+    socket_path.push(env::var_os("WAYLAND_DISPLAY")?);
+    Some(socket_path)
+}
+
+// The connection shared by the components of the process
+struct SharedConnection {
+    enabled: bool,
+    // the socket path of the display of this connection, `None` if it can't be known
+    socket_path: Option<PathBuf>,
+    backend: Weak<Mutex<Backend>>,
+    outgoing: Outgoing,
+    buffered: BufferedEvents,
+}
+
+lazy_static::lazy_static! {
+    static ref SHARED: Mutex<SharedConnection> = Mutex::new(SharedConnection {
+        enabled: true,
+        socket_path: None,
+        backend: Weak::new(),
+        outgoing: Outgoing::new(),
+        buffered: BufferedEvents::default(),
+    });
+}
+
+// Take ownership of a file descriptor given to connect to a compositor, checking that it is a
+// connected unix stream socket
+unsafe fn checked_socket(fd: RawFd) -> Result<UnixStream, ConnectError> {
+    // set the CLOEXEC flag on this FD
+    let flags = fcntl::fcntl(fd, fcntl::FcntlArg::F_GETFD);
+    let result = flags
+        .map(|f| fcntl::FdFlag::from_bits(f).unwrap() | fcntl::FdFlag::FD_CLOEXEC)
+        .and_then(|f| fcntl::fcntl(fd, fcntl::FcntlArg::F_SETFD(f)));
+    let is_unix_stream = result.is_ok()
+        && matches!(getsockopt(fd, sockopt::SockType), Ok(SockType::Stream))
+        && matches!(getsockname(fd), Ok(SockAddr::Unix(_)))
+        && getpeername(fd).is_ok();
+    if !is_unix_stream {
+        let _ = ::nix::unistd::close(fd);
+        return Err(ConnectError::InvalidSocket);
+    }
+    Ok(FromRawFd::from_raw_fd(fd))
 }
 
+/// An error when connecting to a Wayland server
 #[derive(thiserror::Error, Debug)]
 pub enum ConnectError {
+    /// The wayland library could not be loaded
     #[error("The wayland library could not be loaded")]
     NoWaylandLib,
+    /// No compositor could be found
     #[error("Could not find wayland compositor")]
     NoCompositor,
+    /// `WAYLAND_SOCKET` is not a file descriptor number
     #[error("WAYLAND_SOCKET was set but contained garbage")]
     InvalidFd,
+    /// The given file descriptor is not a connected unix stream socket
+    #[error("The file descriptor is not a connected unix stream socket")]
+    InvalidSocket,
 }
 
 /*
@@ -0,0 +1,256 @@
+//! Managing several connections from a single event loop
+//!
+//! Some tools talk to several compositors at once, like nested sessions or multi-seat setups
+//! running a compositor per seat. A [`ConnectionSet`] holds their [`Connection`]s, each with its
+//! own [`EventQueue`], and polls all their sockets at once.
+//!
+//! All the connections dispatch their events to the same state, which is told the connection the
+//! events come from through the [`CurrentConnection`] trait: the [`Dispatch`](crate::Dispatch)
+//! implementations can then tell apart the objects of the different compositors.
+//!
+//! ```no_run
+//! use wayland_client::{
+//!     connection_set::{ConnectionId, ConnectionSet, ConnectionSetError, CurrentConnection},
+//!     Connection,
+//! };
+//!
+//! struct State {
+//!     current: Option<ConnectionId>,
+//! }
+//!
+//! impl CurrentConnection for State {
+//!     fn set_current_connection(&mut self, connection: Option<ConnectionId>) {
+//!         self.current = connection;
+//!     }
+//! }
+//!
+//! # fn run(first: Connection, second: Connection) {
+//! let mut set = ConnectionSet::<State>::new();
+//! let first = set.insert(first).unwrap();
+//! let second = set.insert(second).unwrap();
+//!
+//! let mut state = State { current: None };
+//! loop {
+//!     match set.blocking_dispatch(&mut state, None) {
+//!         Ok(_) => {}
+//!         Err(ConnectionSetError::Connection { connection, error }) => {
+//!             eprintln!("Connection {:?} failed: {}", connection, error);
+//!             set.remove(connection);
+//!         }
+//!         Err(e) => panic!("{}", e),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::{io, time::Duration};
+
+use wayland_backend::client::WaylandError;
+
+use crate::{
+    poll::{EventSource, Readiness, WaylandSource},
+    Connection, DispatchError, EventQueue, QueueHandle,
+};
+
+/// The identifier of a connection in a [`ConnectionSet`]
+///
+/// Identifiers are never reused by a set, even after their connection is removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(usize);
+
+/// The state dispatched by a [`ConnectionSet`]
+pub trait CurrentConnection {
+    /// Invoked with the connection whose events are about to be dispatched, and with `None` once
+    /// they are
+    fn set_current_connection(&mut self, connection: Option<ConnectionId>);
+}
+
+/// An error of a [`ConnectionSet`]
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectionSetError {
+    /// One of the connections failed
+    ///
+    /// The other connections are unaffected, and the failed one is usually removed from the set.
+    #[error("Connection {connection:?} failed: {error}")]
+    Connection {
+        /// The failed connection
+        connection: ConnectionId,
+        /// Its error
+        #[source]
+        error: DispatchError,
+    },
+    /// The sockets of the connections could not be polled
+    #[error("Failed to poll the connections: {0}")]
+    Poll(#[source] io::Error),
+}
+
+#[derive(Debug)]
+struct Entry<D> {
+    id: ConnectionId,
+    source: WaylandSource,
+    queue: EventQueue<D>,
+}
+
+/// A set of connections polled and dispatched together
+///
+/// See the [module-level documentation](self) for its use.
+#[derive(Debug)]
+pub struct ConnectionSet<D> {
+    entries: Vec<Entry<D>>,
+    next_id: usize,
+}
+
+impl<D: CurrentConnection> ConnectionSet<D> {
+    /// Create an empty set
+    pub fn new() -> ConnectionSet<D> {
+        ConnectionSet { entries: Vec::new(), next_id: 0 }
+    }
+
+    /// Add a connection to the set
+    ///
+    /// An event queue is created for the connection, its objects are to be created with the
+    /// [`queue_handle()`](ConnectionSet::queue_handle) of the connection for their events to be
+    /// dispatched by the set.
+    pub fn insert(&mut self, conn: Connection) -> Result<ConnectionId, WaylandError> {
+        let id = ConnectionId(self.next_id);
+        let queue = conn.new_event_queue();
+        let source = WaylandSource::new(conn)?;
+        self.next_id += 1;
+        self.entries.push(Entry { id, source, queue });
+        Ok(id)
+    }
+
+    /// Remove a connection from the set
+    ///
+    /// Returns the connection and its event queue, `None` if it was not in the set.
+    pub fn remove(&mut self, id: ConnectionId) -> Option<(Connection, EventQueue<D>)> {
+        let index = self.entries.iter().position(|entry| entry.id == id)?;
+        let entry = self.entries.remove(index);
+        Some((entry.source.connection().clone(), entry.queue))
+    }
+
+    /// The identifiers of the connections of the set
+    pub fn ids(&self) -> impl Iterator<Item = ConnectionId> + '_ {
+        self.entries.iter().map(|entry| entry.id)
+    }
+
+    /// The number of connections in the set
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the set has no connection
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// A connection of the set
+    pub fn connection(&self, id: ConnectionId) -> Option<&Connection> {
+        self.entry(id).map(|entry| entry.source.connection())
+    }
+
+    /// The handle of the event queue of a connection of the set
+    pub fn queue_handle(&self, id: ConnectionId) -> Option<QueueHandle<D>> {
+        self.entry(id).map(|entry| entry.queue.handle())
+    }
+
+    /// The event queue of a connection of the set
+    pub fn event_queue(&mut self, id: ConnectionId) -> Option<&mut EventQueue<D>> {
+        self.entries.iter_mut().find(|entry| entry.id == id).map(|entry| &mut entry.queue)
+    }
+
+    /// Dispatch the pending events of all the connections, without reading their sockets
+    ///
+    /// Returns the total number of dispatched events. The dispatching stops at the first
+    /// connection failing, the events of the following ones are dispatched by the next call.
+    pub fn dispatch_pending(&mut self, state: &mut D) -> Result<usize, ConnectionSetError> {
+        let mut dispatched = 0;
+        for entry in &mut self.entries {
+            state.set_current_connection(Some(entry.id));
+            let ret = entry.queue.dispatch_pending(state);
+            state.set_current_connection(None);
+            dispatched += ret.map_err(|error| entry.error(error))?;
+        }
+        Ok(dispatched)
+    }
+
+    /// Block until events are received on any of the connections, and dispatch them
+    ///
+    /// The outgoing requests of all the connections are flushed, and their sockets are polled
+    /// together until one of them is readable or the timeout expires. This returns immediately if
+    /// events are already pending. Returns the total number of dispatched events.
+    pub fn blocking_dispatch(
+        &mut self,
+        state: &mut D,
+        timeout: Option<Duration>,
+    ) -> Result<usize, ConnectionSetError> {
+        let dispatched = self.dispatch_pending(state)?;
+        if dispatched > 0 {
+            return Ok(dispatched);
+        }
+
+        for i in 0..self.entries.len() {
+            if let Err(e) = self.entries[i].source.before_poll() {
+                // cancel the reads already prepared
+                for entry in &mut self.entries[..i] {
+                    let _ = entry.source.after_poll(Readiness::default());
+                }
+                return Err(self.entries[i].error(e.into()));
+            }
+        }
+
+        let mut fds = self
+            .entries
+            .iter()
+            .map(|entry| {
+                nix::poll::PollFd::new(
+                    entry.source.fd(),
+                    nix::poll::PollFlags::POLLIN | nix::poll::PollFlags::POLLERR,
+                )
+            })
+            .collect::<Vec<_>>();
+        let timeout = timeout.map(|t| t.as_millis().min(i32::MAX as u128) as i32).unwrap_or(-1);
+        let poll_error = match nix::poll::poll(&mut fds, timeout) {
+            Ok(_) | Err(nix::errno::Errno::EINTR) => None,
+            Err(e) => Some(io::Error::from(e)),
+        };
+
+        // the prepared reads are all completed or cancelled, even if polling failed
+        let mut ret = match poll_error {
+            Some(e) => Err(ConnectionSetError::Poll(e)),
+            None => Ok(()),
+        };
+        for (entry, fd) in self.entries.iter_mut().zip(&fds) {
+            let revents = fd.revents().unwrap_or_else(nix::poll::PollFlags::empty);
+            let readiness = Readiness {
+                readable: revents.contains(nix::poll::PollFlags::POLLIN),
+                error: revents
+                    .intersects(nix::poll::PollFlags::POLLERR | nix::poll::PollFlags::POLLHUP),
+            };
+            if let Err(e) = entry.source.after_poll(readiness) {
+                if ret.is_ok() {
+                    ret = Err(entry.error(e.into()));
+                }
+            }
+        }
+        ret?;
+
+        self.dispatch_pending(state)
+    }
+
+    fn entry(&self, id: ConnectionId) -> Option<&Entry<D>> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+}
+
+impl<D: CurrentConnection> Default for ConnectionSet<D> {
+    fn default() -> ConnectionSet<D> {
+        ConnectionSet::new()
+    }
+}
+
+impl<D> Entry<D> {
+    fn error(&self, error: DispatchError) -> ConnectionSetError {
+        ConnectionSetError::Connection { connection: self.id, error }
+    }
+}
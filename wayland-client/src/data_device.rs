@@ -0,0 +1,518 @@
+//! Helpers for the clipboard and drag-and-drop
+//!
+//! The [`DataDeviceState`] can be used as a [`DelegateDispatch`](crate::DelegateDispatch) target
+//! for the `wl_data_device_manager`, `wl_data_device`, `wl_data_offer` and `wl_data_source`
+//! interfaces. It keeps track of the offers made to each seat, and handles the transfers of data
+//! through pipes:
+//!
+//! - [`copy()`](DataDeviceState::copy) sets the selection of a seat to some data, which is then
+//!   sent to any client pasting it until the selection changes,
+//! - [`paste()`](DataDeviceState::paste) reads the current selection of a seat, and returns a
+//!   [`PipeTransfer`] future resolving to its contents.
+//!
+//! Drag-and-drop follows the same pattern, with [`start_drag()`](DataDeviceState::start_drag) and
+//! [`take_drop()`](DataDeviceState::take_drop). The transfers happen on the background threads
+//! of the [`pipe`](crate::pipe) module, so that a slow peer can never block the event loop.
+//!
+//! ```no_run
+//! # async fn run(conn: &mut wayland_client::ConnectionHandle<'_>, seat: &wayland_client::protocol::wl_seat::WlSeat, data_devices: &mut wayland_client::data_device::DataDeviceState) {
+//! let text = data_devices.paste(conn, seat, "text/plain;charset=utf-8").unwrap().await.unwrap();
+//! # }
+//! ```
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use nix::{fcntl::OFlag, unistd};
+use wayland_backend::client::ObjectData;
+
+use crate::{
+    pipe::{read_pipe, write_pipe, PipeTransfer},
+    protocol::{
+        wl_data_device, wl_data_device_manager, wl_data_offer, wl_data_source, wl_seat, wl_surface,
+    },
+    ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError, Proxy,
+    QueueHandle,
+};
+
+use wl_data_device_manager::DndAction;
+
+/// An error generated by a [`DataDeviceState`]
+#[derive(Debug, thiserror::Error)]
+pub enum DataDeviceError {
+    /// No data device was created for the seat
+    #[error("No data device for this seat")]
+    NoDevice,
+    /// The seat currently has no selection or drag-and-drop offer
+    #[error("No data is offered")]
+    NoOffer,
+    /// The offer does not provide the requested mime type
+    #[error("Mime type {0} is not offered")]
+    UnsupportedMimeType(String),
+    /// The pipe for the transfer could not be created
+    #[error("Pipe creation failed: {0}")]
+    Io(#[from] io::Error),
+    /// A request could not be sent
+    #[error(transparent)]
+    Dispatch(#[from] DispatchError),
+}
+
+/// An event of the data devices, as reported by [`DataDeviceState::take_events()`]
+#[derive(Debug, Clone)]
+pub enum DataDeviceEvent {
+    /// The selection of a seat changed
+    Selection {
+        /// the seat
+        seat: wl_seat::WlSeat,
+        /// the mime types of the new selection, empty if the selection was cleared
+        mime_types: Vec<String>,
+    },
+    /// A drag-and-drop operation entered a surface
+    DragEnter {
+        /// the seat of the operation
+        seat: wl_seat::WlSeat,
+        /// the surface entered
+        surface: wl_surface::WlSurface,
+        /// position of the drag in surface-local coordinates
+        position: (f64, f64),
+        /// the mime types offered by the operation, empty if it is internal to another client
+        mime_types: Vec<String>,
+    },
+    /// The drag-and-drop operation moved over the surface
+    DragMotion {
+        /// the seat of the operation
+        seat: wl_seat::WlSeat,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// new position of the drag in surface-local coordinates
+        position: (f64, f64),
+    },
+    /// The drag-and-drop operation left the surface without being dropped
+    DragLeave {
+        /// the seat of the operation
+        seat: wl_seat::WlSeat,
+    },
+    /// The drag-and-drop operation was dropped on the surface
+    ///
+    /// The data can be retrieved with [`DataDeviceState::take_drop()`].
+    Drop {
+        /// the seat of the operation
+        seat: wl_seat::WlSeat,
+    },
+    /// A data source created by this client is not used anymore and was destroyed
+    ///
+    /// This happens when another selection replaces it or when its drag-and-drop operation ends.
+    SourceFinished {
+        /// the data source
+        source: wl_data_source::WlDataSource,
+    },
+}
+
+/// User data of the data offers handled by a [`DataDeviceState`]
+#[derive(Debug, Default)]
+pub struct OfferData {
+    mime_types: Mutex<Vec<String>>,
+}
+
+impl OfferData {
+    /// The mime types advertized for this offer
+    pub fn mime_types(&self) -> Vec<String> {
+        self.mime_types.lock().unwrap().clone()
+    }
+
+    fn offers(&self, mime_type: &str) -> bool {
+        self.mime_types.lock().unwrap().iter().any(|m| m == mime_type)
+    }
+}
+
+/// User data of the data sources created by a [`DataDeviceState`]
+#[derive(Debug)]
+pub struct SourceData {
+    mime_type: String,
+    data: Arc<[u8]>,
+}
+
+impl SourceData {
+    /// The mime type of the data of this source
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+}
+
+#[derive(Debug)]
+struct DeviceInfo {
+    seat: wl_seat::WlSeat,
+    device: wl_data_device::WlDataDevice,
+    selection: Option<wl_data_offer::WlDataOffer>,
+    drag: Option<DragInfo>,
+}
+
+#[derive(Debug)]
+struct DragInfo {
+    serial: u32,
+    offer: Option<wl_data_offer::WlDataOffer>,
+}
+
+/// A helper handling the data devices of the seats
+///
+/// See the [module-level documentation](self) for its use.
+#[derive(Debug)]
+pub struct DataDeviceState {
+    manager: wl_data_device_manager::WlDataDeviceManager,
+    devices: Vec<DeviceInfo>,
+    events: Vec<DataDeviceEvent>,
+}
+
+impl DataDeviceState {
+    /// Create a new `DataDeviceState` using given data device manager
+    pub fn new(manager: wl_data_device_manager::WlDataDeviceManager) -> DataDeviceState {
+        DataDeviceState { manager, devices: Vec::new(), events: Vec::new() }
+    }
+
+    /// Create the data device of a seat
+    ///
+    /// This does nothing if the seat already has one.
+    pub fn add_seat<D>(
+        &mut self,
+        conn: &mut ConnectionHandle<'_>,
+        qh: &QueueHandle<D>,
+        seat: &wl_seat::WlSeat,
+    ) -> Result<(), DispatchError>
+    where
+        D: Dispatch<wl_data_device::WlDataDevice, UserData = wl_seat::WlSeat> + 'static,
+    {
+        if self.device(seat).is_ok() {
+            return Ok(());
+        }
+        let device = self.manager.get_data_device(conn, seat, qh, seat.clone())?;
+        self.devices.push(DeviceInfo { seat: seat.clone(), device, selection: None, drag: None });
+        Ok(())
+    }
+
+    /// Take the events received since the last call
+    pub fn take_events(&mut self) -> Vec<DataDeviceEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// The mime types of the current selection of a seat
+    pub fn selection_mime_types(&self, seat: &wl_seat::WlSeat) -> Vec<String> {
+        self.device(seat)
+            .ok()
+            .and_then(|info| info.selection.as_ref())
+            .and_then(|offer| offer.data::<OfferData>())
+            .map(OfferData::mime_types)
+            .unwrap_or_default()
+    }
+
+    /// Set the selection of a seat
+    ///
+    /// The serial must be the one of the input event which triggered this action. The data is sent
+    /// to the clients pasting it until another selection replaces it, at which point a
+    /// [`DataDeviceEvent::SourceFinished`] event is generated for the returned source.
+    pub fn copy<D>(
+        &mut self,
+        conn: &mut ConnectionHandle<'_>,
+        qh: &QueueHandle<D>,
+        seat: &wl_seat::WlSeat,
+        serial: u32,
+        mime_type: &str,
+        data: impl Into<Arc<[u8]>>,
+    ) -> Result<wl_data_source::WlDataSource, DataDeviceError>
+    where
+        D: Dispatch<wl_data_source::WlDataSource, UserData = SourceData> + 'static,
+    {
+        let device = self.device(seat)?.device.clone();
+        let source = self.create_source(conn, qh, mime_type, data.into())?;
+        device.set_selection(conn, Some(&source), serial);
+        Ok(source)
+    }
+
+    /// Read the current selection of a seat
+    pub fn paste(
+        &self,
+        conn: &mut ConnectionHandle<'_>,
+        seat: &wl_seat::WlSeat,
+        mime_type: &str,
+    ) -> Result<PipeTransfer<Vec<u8>>, DataDeviceError> {
+        let offer = self.device(seat)?.selection.as_ref().ok_or(DataDeviceError::NoOffer)?;
+        receive(conn, offer, mime_type)
+    }
+
+    /// Start a drag-and-drop operation
+    ///
+    /// The serial must be the one of the pointer button or touch down event which started the
+    /// drag. A [`DataDeviceEvent::SourceFinished`] event is generated for the returned source once
+    /// the operation is over.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_drag<D>(
+        &mut self,
+        conn: &mut ConnectionHandle<'_>,
+        qh: &QueueHandle<D>,
+        seat: &wl_seat::WlSeat,
+        origin: &wl_surface::WlSurface,
+        serial: u32,
+        mime_type: &str,
+        data: impl Into<Arc<[u8]>>,
+    ) -> Result<wl_data_source::WlDataSource, DataDeviceError>
+    where
+        D: Dispatch<wl_data_source::WlDataSource, UserData = SourceData> + 'static,
+    {
+        let device = self.device(seat)?.device.clone();
+        let source = self.create_source(conn, qh, mime_type, data.into())?;
+        if source.version() >= 3 {
+            source.set_actions(conn, DndAction::Copy);
+        }
+        device.start_drag(conn, Some(&source), origin, None, serial);
+        Ok(source)
+    }
+
+    /// Accept or refuse the drag-and-drop operation currently over a surface of this client
+    ///
+    /// Accepting it with a mime type lets the user drop it on the surface.
+    pub fn accept_drag(
+        &self,
+        conn: &mut ConnectionHandle<'_>,
+        seat: &wl_seat::WlSeat,
+        mime_type: Option<&str>,
+    ) -> Result<(), DataDeviceError> {
+        let drag = self.device(seat)?.drag.as_ref().ok_or(DataDeviceError::NoOffer)?;
+        let offer = drag.offer.as_ref().ok_or(DataDeviceError::NoOffer)?;
+        offer.accept(conn, drag.serial, mime_type.map(String::from));
+        if offer.version() >= 3 {
+            let action = if mime_type.is_some() { DndAction::Copy } else { DndAction::empty() };
+            offer.set_actions(conn, action, action);
+        }
+        Ok(())
+    }
+
+    /// Read the data of a drag-and-drop operation dropped on a surface of this client
+    ///
+    /// This concludes the operation.
+    pub fn take_drop(
+        &mut self,
+        conn: &mut ConnectionHandle<'_>,
+        seat: &wl_seat::WlSeat,
+        mime_type: &str,
+    ) -> Result<PipeTransfer<Vec<u8>>, DataDeviceError> {
+        let info = self.device_mut(seat)?;
+        let offer = info
+            .drag
+            .as_ref()
+            .and_then(|drag| drag.offer.as_ref())
+            .ok_or(DataDeviceError::NoOffer)?;
+        let future = receive(conn, offer, mime_type)?;
+        if let Some(offer) = info.drag.take().and_then(|drag| drag.offer) {
+            if offer.version() >= 3 {
+                offer.finish(conn);
+            }
+            offer.destroy(conn);
+        }
+        Ok(future)
+    }
+
+    fn device(&self, seat: &wl_seat::WlSeat) -> Result<&DeviceInfo, DataDeviceError> {
+        self.devices.iter().find(|info| &info.seat == seat).ok_or(DataDeviceError::NoDevice)
+    }
+
+    fn device_mut(&mut self, seat: &wl_seat::WlSeat) -> Result<&mut DeviceInfo, DataDeviceError> {
+        self.devices.iter_mut().find(|info| &info.seat == seat).ok_or(DataDeviceError::NoDevice)
+    }
+
+    fn create_source<D>(
+        &self,
+        conn: &mut ConnectionHandle<'_>,
+        qh: &QueueHandle<D>,
+        mime_type: &str,
+        data: Arc<[u8]>,
+    ) -> Result<wl_data_source::WlDataSource, DispatchError>
+    where
+        D: Dispatch<wl_data_source::WlDataSource, UserData = SourceData> + 'static,
+    {
+        let source = self.manager.create_data_source(
+            conn,
+            qh,
+            SourceData { mime_type: mime_type.into(), data },
+        )?;
+        source.offer(conn, mime_type.into());
+        Ok(source)
+    }
+}
+
+fn receive(
+    conn: &mut ConnectionHandle<'_>,
+    offer: &wl_data_offer::WlDataOffer,
+    mime_type: &str,
+) -> Result<PipeTransfer<Vec<u8>>, DataDeviceError> {
+    if !offer.data::<OfferData>().map(|data| data.offers(mime_type)).unwrap_or(false) {
+        return Err(DataDeviceError::UnsupportedMimeType(mime_type.into()));
+    }
+    let (read_fd, write_fd) = unistd::pipe2(OFlag::O_CLOEXEC).map_err(io::Error::from)?;
+    // the fd is duplicated when the request is sent
+    offer.receive(conn, mime_type.into(), write_fd);
+    let _ = unistd::close(write_fd);
+    Ok(read_pipe(read_fd, None))
+}
+
+impl DelegateDispatchBase<wl_data_device_manager::WlDataDeviceManager> for DataDeviceState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<wl_data_device_manager::WlDataDeviceManager, D> for DataDeviceState
+where
+    D: Dispatch<wl_data_device_manager::WlDataDeviceManager, UserData = ()>,
+{
+    fn event(
+        _: &mut D,
+        _: &wl_data_device_manager::WlDataDeviceManager,
+        _: wl_data_device_manager::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("wl_data_device_manager has no event")
+    }
+}
+
+impl DelegateDispatchBase<wl_data_device::WlDataDevice> for DataDeviceState {
+    type UserData = wl_seat::WlSeat;
+}
+
+impl<D> DelegateDispatch<wl_data_device::WlDataDevice, D> for DataDeviceState
+where
+    D: Dispatch<wl_data_device::WlDataDevice, UserData = wl_seat::WlSeat>
+        + Dispatch<wl_data_offer::WlDataOffer, UserData = OfferData>
+        + AsMut<DataDeviceState>
+        + 'static,
+{
+    fn event(
+        data: &mut D,
+        _: &wl_data_device::WlDataDevice,
+        event: wl_data_device::Event,
+        seat: &wl_seat::WlSeat,
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let info = match me.device_mut(seat) {
+            Ok(info) => info,
+            Err(_) => return,
+        };
+        let mime_types = |offer: &Option<wl_data_offer::WlDataOffer>| {
+            offer
+                .as_ref()
+                .and_then(|offer| offer.data::<OfferData>())
+                .map(OfferData::mime_types)
+                .unwrap_or_default()
+        };
+        let event = match event {
+            // the offer is tracked once it is used by a selection or enter event
+            wl_data_device::Event::DataOffer { .. } => return,
+            wl_data_device::Event::Selection { id } => {
+                if let Some(old) = std::mem::replace(&mut info.selection, id) {
+                    old.destroy(conn);
+                }
+                DataDeviceEvent::Selection {
+                    seat: seat.clone(),
+                    mime_types: mime_types(&info.selection),
+                }
+            }
+            wl_data_device::Event::Enter { serial, surface, x, y, id } => {
+                let mime_types = mime_types(&id);
+                if let Some(old) = info.drag.replace(DragInfo { serial, offer: id }) {
+                    if let Some(offer) = old.offer {
+                        offer.destroy(conn);
+                    }
+                }
+                DataDeviceEvent::DragEnter {
+                    seat: seat.clone(),
+                    surface,
+                    position: (x, y),
+                    mime_types,
+                }
+            }
+            wl_data_device::Event::Motion { time, x, y } => {
+                DataDeviceEvent::DragMotion { seat: seat.clone(), time, position: (x, y) }
+            }
+            wl_data_device::Event::Leave => {
+                if let Some(offer) = info.drag.take().and_then(|drag| drag.offer) {
+                    offer.destroy(conn);
+                }
+                DataDeviceEvent::DragLeave { seat: seat.clone() }
+            }
+            wl_data_device::Event::Drop => DataDeviceEvent::Drop { seat: seat.clone() },
+        };
+        me.events.push(event);
+    }
+
+    fn event_created_child(opcode: u16, qh: &QueueHandle<D>) -> Arc<dyn ObjectData> {
+        match opcode {
+            wl_data_device::EVT_DATA_OFFER_OPCODE => {
+                qh.make_data::<wl_data_offer::WlDataOffer>(OfferData::default())
+            }
+            _ => panic!(
+                "Missing event_created_child specialization for event opcode {} of wl_data_device",
+                opcode
+            ),
+        }
+    }
+}
+
+impl DelegateDispatchBase<wl_data_offer::WlDataOffer> for DataDeviceState {
+    type UserData = OfferData;
+}
+
+impl<D> DelegateDispatch<wl_data_offer::WlDataOffer, D> for DataDeviceState
+where
+    D: Dispatch<wl_data_offer::WlDataOffer, UserData = OfferData>,
+{
+    fn event(
+        _: &mut D,
+        _: &wl_data_offer::WlDataOffer,
+        event: wl_data_offer::Event,
+        data: &OfferData,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        if let wl_data_offer::Event::Offer { mime_type } = event {
+            data.mime_types.lock().unwrap().push(mime_type);
+        }
+    }
+}
+
+impl DelegateDispatchBase<wl_data_source::WlDataSource> for DataDeviceState {
+    type UserData = SourceData;
+}
+
+impl<D> DelegateDispatch<wl_data_source::WlDataSource, D> for DataDeviceState
+where
+    D: Dispatch<wl_data_source::WlDataSource, UserData = SourceData> + AsMut<DataDeviceState>,
+{
+    fn event(
+        data: &mut D,
+        source: &wl_data_source::WlDataSource,
+        event: wl_data_source::Event,
+        source_data: &SourceData,
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        match event {
+            wl_data_source::Event::Send { mime_type, fd } => {
+                if mime_type == source_data.mime_type {
+                    // a failure only affects the peer, which notices the data is incomplete
+                    drop(write_pipe(fd, source_data.data.clone()));
+                } else {
+                    let _ = unistd::close(fd);
+                }
+            }
+            wl_data_source::Event::Cancelled | wl_data_source::Event::DndFinished => {
+                source.destroy(conn);
+                data.as_mut()
+                    .events
+                    .push(DataDeviceEvent::SourceFinished { source: source.clone() });
+            }
+            _ => {}
+        }
+    }
+}
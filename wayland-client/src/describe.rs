@@ -0,0 +1,120 @@
+//! Structured description of a compositor
+//!
+//! This module is available with the `describe` cargo feature. [`describe_compositor()`] collects
+//! what `wayland-info` would print about the compositor: its globals and their versions, and the
+//! state of its outputs, seats and shared memory formats. The resulting [`CompositorDescription`]
+//! can be inspected, or formatted with its `Display` implementation for diagnostics and bug reports.
+//!
+//! ```no_run
+//! let conn = wayland_client::Connection::connect_to_env().unwrap();
+//! let description = wayland_client::describe_compositor(&conn).unwrap();
+//! println!("{}", description);
+//! ```
+
+use std::fmt;
+
+use crate::{
+    globals::{fetch_global_contents, GlobalDescription, OutputInfo, SeatInfo},
+    protocol::{wl_output, wl_seat, wl_shm},
+    Connection, DispatchError, WEnum,
+};
+
+/// A description of a compositor
+#[derive(Debug, Clone)]
+pub struct CompositorDescription {
+    /// The globals advertized by the compositor, in the order they were advertized
+    pub globals: Vec<GlobalDescription>,
+    /// The state of the outputs
+    pub outputs: Vec<OutputInfo>,
+    /// The state of the seats
+    pub seats: Vec<SeatInfo>,
+    /// The pixel formats supported for shared memory buffers
+    pub shm_formats: Vec<WEnum<wl_shm::Format>>,
+}
+
+/// Describe the compositor of a connection
+///
+/// This binds the outputs, seats and shared memory globals to collect their state, and releases
+/// them afterwards, as [`Connection::fetch_global_contents()`] does. As it dispatches events, it
+/// cannot be invoked from within the dispatching of the connection.
+pub fn describe_compositor(conn: &Connection) -> Result<CompositorDescription, DispatchError> {
+    let (globals, outputs) = fetch_global_contents::<wl_output::WlOutput>(conn)?;
+    let seats = conn.fetch_global_contents::<wl_seat::WlSeat>()?;
+    let shm_formats = conn
+        .fetch_global_contents::<wl_shm::WlShm>()?
+        .into_iter()
+        .flat_map(|shm| shm.formats)
+        .collect();
+    Ok(CompositorDescription { globals: globals.list().to_vec(), outputs, seats, shm_formats })
+}
+
+impl fmt::Display for CompositorDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for global in &self.globals {
+            writeln!(
+                f,
+                "interface: '{}', version: {}, name: {}",
+                global.interface, global.version, global.name
+            )?;
+        }
+
+        for (i, output) in self.outputs.iter().enumerate() {
+            writeln!(f, "output {}:", i)?;
+            if let Some(ref name) = output.name {
+                writeln!(f, "\tname: {}", name)?;
+            }
+            if let Some(ref description) = output.description {
+                writeln!(f, "\tdescription: {}", description)?;
+            }
+            writeln!(f, "\tmake: '{}', model: '{}'", output.make, output.model)?;
+            writeln!(
+                f,
+                "\tx: {}, y: {}, physical_width: {} mm, physical_height: {} mm, scale: {}",
+                output.location.0,
+                output.location.1,
+                output.physical_size.0,
+                output.physical_size.1,
+                output.scale
+            )?;
+            if let Some(subpixel) = output.subpixel {
+                writeln!(f, "\tsubpixel: {:?}", subpixel)?;
+            }
+            if let Some(transform) = output.transform {
+                writeln!(f, "\ttransform: {:?}", transform)?;
+            }
+            for mode in &output.modes {
+                write!(
+                    f,
+                    "\tmode: width: {} px, height: {} px, refresh: {:.3} Hz",
+                    mode.dimensions.0,
+                    mode.dimensions.1,
+                    mode.refresh_rate as f64 / 1000.
+                )?;
+                match (mode.current, mode.preferred) {
+                    (true, true) => writeln!(f, ", current, preferred")?,
+                    (true, false) => writeln!(f, ", current")?,
+                    (false, true) => writeln!(f, ", preferred")?,
+                    (false, false) => writeln!(f)?,
+                }
+            }
+        }
+
+        for seat in &self.seats {
+            writeln!(
+                f,
+                "seat {}: capabilities: {:?}",
+                seat.name.as_deref().unwrap_or("<unnamed>"),
+                seat.capabilities
+            )?;
+        }
+
+        write!(f, "shm formats:")?;
+        for format in &self.shm_formats {
+            match format {
+                WEnum::Value(format) => write!(f, " {:?}", format)?,
+                WEnum::Unknown(raw) => write!(f, " 0x{:08x}", raw)?,
+            }
+        }
+        writeln!(f)
+    }
+}
@@ -0,0 +1,296 @@
+//! Dynamically typed messages, for script bindings and protocol exploration
+//!
+//! Rather than through the types generated for each interface, requests can be sent by name with
+//! [`Connection::send_dynamic()`], their arguments given as a list of [`Value`]s. The events of the
+//! objects created this way are delivered to the callback set with
+//! [`Connection::set_dynamic_callback()`], as [`DynamicEvent`]s carrying the interface, the name
+//! and the arguments of the event.
+//!
+//! The messages are described by the [`Interface`]s of the objects, and the interface of the
+//! object created by a generic constructor like `wl_registry.bind` is found from its name in the
+//! interface registry of `wayland-backend`, see
+//! [`register_interfaces()`](crate::backend::protocol::register_interfaces).
+//!
+//! ```no_run
+//! let conn = wayland_client::Connection::connect_to_env().unwrap();
+//! conn.set_dynamic_callback(|_, event| {
+//!     println!("{}.{} {:?}", event.interface.name, event.name, event.args);
+//! });
+//! let display = conn.handle().display();
+//! let display_id = wayland_client::Proxy::id(&display);
+//! conn.send_dynamic(&display_id, "get_registry", &[]).unwrap();
+//! conn.roundtrip().unwrap();
+//! ```
+
+use std::{ffi::CString, os::unix::io::RawFd, sync::Arc};
+
+use wayland_backend::{
+    client::{Handle, ObjectData, ObjectId},
+    protocol::{
+        lookup_interface, AllowNull, Argument, ArgumentType, Interface, Message, MessageDesc,
+    },
+    smallvec::SmallVec,
+};
+
+use crate::{ConnectionHandle, DispatchError};
+
+/// The value of an argument of a dynamically typed message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// An `int` argument
+    Int(i32),
+    /// An `uint` argument
+    Uint(u32),
+    /// A `fixed` argument
+    Fixed(f64),
+    /// A `string` argument
+    Str(String),
+    /// An `object` argument, `None` for a null object
+    Object(Option<ObjectId>),
+    /// A `new_id` argument of an event
+    ///
+    /// The `new_id` arguments of requests are not given, the object created by a request is
+    /// returned by [`Connection::send_dynamic()`](crate::Connection::send_dynamic).
+    NewId(ObjectId),
+    /// An `array` argument
+    Array(Vec<u8>),
+    /// A `fd` argument
+    ///
+    /// The file descriptors received in events are owned by the callback, which is responsible for
+    /// closing them.
+    Fd(RawFd),
+}
+
+/// An event delivered to the dynamic callback
+#[derive(Debug, Clone)]
+pub struct DynamicEvent {
+    /// The object which received this event
+    pub sender: ObjectId,
+    /// The interface of the object
+    pub interface: &'static Interface,
+    /// The name of the event
+    pub name: &'static str,
+    /// The names of the arguments of the event, as written in the protocol
+    pub arg_names: &'static [&'static str],
+    /// The arguments of the event
+    pub args: Vec<Value>,
+}
+
+/// An error sending a dynamically typed request
+#[derive(thiserror::Error, Debug)]
+pub enum DynamicError {
+    /// The interface of the object has no request with this name
+    #[error("Interface {interface} has no request named {request}")]
+    UnknownRequest {
+        /// The interface of the object
+        interface: &'static str,
+        /// The name of the request
+        request: String,
+    },
+    /// The number of arguments does not match the signature of the request
+    #[error("Request {request} takes {expected} arguments, {found} were given")]
+    ArgumentCount {
+        /// The name of the request
+        request: &'static str,
+        /// The expected number of arguments, without the `new_id` ones
+        expected: usize,
+        /// The number of given arguments
+        found: usize,
+    },
+    /// An argument does not match the signature of the request
+    #[error("Argument {index} of request {request} should be of type {expected:?}")]
+    BadArgument {
+        /// The name of the request
+        request: &'static str,
+        /// The position of the argument in the given list
+        index: usize,
+        /// The type of argument expected by the signature
+        expected: ArgumentType,
+    },
+    /// The object created by a generic constructor has an interface absent from the registry
+    #[error("Interface {0} is not registered")]
+    UnknownInterface(String),
+    /// The request creates an object, but no dynamic callback was set to receive its events
+    #[error("No dynamic callback to receive the events of the objects created by {0}")]
+    NoCallback(&'static str),
+    /// The request could not be sent
+    #[error(transparent)]
+    Dispatch(#[from] DispatchError),
+}
+
+pub(crate) type DynamicCallback = dyn Fn(&mut ConnectionHandle, DynamicEvent) + Send + Sync;
+
+/// The object data of the objects created by dynamically typed requests
+pub(crate) struct DynamicData {
+    pub(crate) callback: Box<DynamicCallback>,
+}
+
+impl std::fmt::Debug for DynamicData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicData").finish_non_exhaustive()
+    }
+}
+
+impl ObjectData for DynamicData {
+    fn event(
+        self: Arc<Self>,
+        handle: &mut Handle,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        let interface = msg.sender_id.interface();
+        let desc = interface.events.get(msg.opcode as usize)?;
+        let mut creates_child = false;
+        let args = msg
+            .args
+            .into_iter()
+            .map(|arg| match arg {
+                Argument::Int(i) => Value::Int(i),
+                Argument::Uint(u) => Value::Uint(u),
+                Argument::Fixed(f) => Value::Fixed(f as f64 / 256.),
+                Argument::Str(s) => Value::Str(s.to_string_lossy().into_owned()),
+                Argument::Object(id) => Value::Object(if id.is_null() { None } else { Some(id) }),
+                Argument::NewId(id) => {
+                    creates_child = true;
+                    Value::NewId(id)
+                }
+                Argument::Array(a) => Value::Array(*a),
+                Argument::Fd(fd) => Value::Fd(fd),
+            })
+            .collect();
+        let event = DynamicEvent {
+            sender: msg.sender_id,
+            interface,
+            name: desc.name,
+            arg_names: desc.arg_names,
+            args,
+        };
+        (self.callback)(&mut ConnectionHandle::from_handle(handle), event);
+        // the objects created by events are dynamic too
+        if creates_child {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl<'a> ConnectionHandle<'a> {
+    /// Send a request by name, with dynamically typed arguments
+    ///
+    /// The objects created by this request receive the dynamic callback of `object`, and this fails
+    /// with [`DynamicError::NoCallback`] if it has none. See
+    /// [`Connection::send_dynamic()`](crate::Connection::send_dynamic) for details.
+    pub fn send_dynamic(
+        &mut self,
+        object: &ObjectId,
+        request: &str,
+        args: &[Value],
+    ) -> Result<Option<ObjectId>, DynamicError> {
+        self.send_dynamic_with(object, request, args, None)
+    }
+
+    pub(crate) fn send_dynamic_with(
+        &mut self,
+        object: &ObjectId,
+        request: &str,
+        args: &[Value],
+        fallback: Option<Arc<DynamicData>>,
+    ) -> Result<Option<ObjectId>, DynamicError> {
+        let interface = object.interface();
+        let (opcode, desc) = interface
+            .requests
+            .iter()
+            .enumerate()
+            .find(|(_, desc)| desc.name == request)
+            .ok_or_else(|| DynamicError::UnknownRequest {
+                interface: interface.name,
+                request: request.into(),
+            })?;
+        let expected =
+            desc.signature.iter().filter(|arg| !matches!(arg, ArgumentType::NewId(_))).count();
+        if args.len() != expected {
+            return Err(DynamicError::ArgumentCount {
+                request: desc.name,
+                expected,
+                found: args.len(),
+            });
+        }
+
+        let mut msg_args = SmallVec::new();
+        let mut values = args.iter().enumerate();
+        let mut creates_child = false;
+        for arg_type in desc.signature {
+            if let ArgumentType::NewId(_) = arg_type {
+                let child_interface = match desc.child_interface {
+                    Some(child_interface) => child_interface,
+                    None => generic_child_interface(&msg_args)?,
+                };
+                msg_args.push(Argument::NewId(self.placeholder_id(Some(child_interface))));
+                creates_child = true;
+                continue;
+            }
+            let (index, value) = values.next().unwrap();
+            msg_args.push(convert_argument(self, desc, index, *arg_type, value)?);
+        }
+
+        let data = if creates_child {
+            let data = self
+                .get_object_data(object.clone())
+                .ok()
+                .and_then(|data| data.downcast_arc::<DynamicData>().ok())
+                .or(fallback)
+                .ok_or(DynamicError::NoCallback(desc.name))?;
+            Some(data as Arc<dyn ObjectData>)
+        } else {
+            None
+        };
+        let msg = Message { sender_id: object.clone(), opcode: opcode as u16, args: msg_args };
+        let id = self.send_message(msg, data)?;
+        Ok(if creates_child { Some(id) } else { None })
+    }
+}
+
+// the interface of the object created by a generic constructor, named by its `interface` argument
+fn generic_child_interface(
+    args: &[Argument<ObjectId>],
+) -> Result<&'static Interface, DynamicError> {
+    // the `new_id` is preceded by the interface name and the version
+    let name = match args.len().checked_sub(2).and_then(|i| args.get(i)) {
+        Some(Argument::Str(name)) => name.to_string_lossy(),
+        _ => return Err(DynamicError::UnknownInterface(String::new())),
+    };
+    lookup_interface(&name).ok_or_else(|| DynamicError::UnknownInterface(name.into_owned()))
+}
+
+fn convert_argument(
+    conn: &mut ConnectionHandle,
+    desc: &'static MessageDesc,
+    index: usize,
+    arg_type: ArgumentType,
+    value: &Value,
+) -> Result<Argument<ObjectId>, DynamicError> {
+    let bad_argument =
+        || DynamicError::BadArgument { request: desc.name, index, expected: arg_type };
+    let arg = match (arg_type, value) {
+        (ArgumentType::Int, Value::Int(i)) => Argument::Int(*i),
+        (ArgumentType::Uint, Value::Uint(u)) => Argument::Uint(*u),
+        (ArgumentType::Fixed, Value::Fixed(f)) => Argument::Fixed((f * 256.).round() as i32),
+        (ArgumentType::Str(_), Value::Str(s)) => {
+            Argument::Str(Box::new(CString::new(s.as_bytes()).map_err(|_| bad_argument())?))
+        }
+        (ArgumentType::Object(_), Value::Object(Some(id))) => Argument::Object(id.clone()),
+        (ArgumentType::Object(AllowNull::Yes), Value::Object(None)) => {
+            Argument::Object(conn.null_id())
+        }
+        (ArgumentType::Array(_), Value::Array(a)) => Argument::Array(Box::new(a.clone())),
+        (ArgumentType::Fd, Value::Fd(fd)) => Argument::Fd(*fd),
+        _ => return Err(bad_argument()),
+    };
+    Ok(arg)
+}
@@ -1,11 +1,17 @@
-use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::Location;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 
 use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use wayland_backend::{
     client::{Backend, Handle, ObjectData, ObjectId, ReadEventsGuard, WaylandError},
-    protocol::Message,
+    protocol::{Argument, Interface, Message},
 };
 
+use crate::conn::{check_not_dispatching, DispatchingGuard, Outgoing};
 use crate::{ConnectionHandle, DispatchError, Proxy};
 
 /// A trait which provides an implementation for handling events from the server on a proxy with some type of
@@ -31,12 +37,11 @@ pub trait Dispatch<I: Proxy>: Sized {
     ///
     /// If the interface does not have any such event, you can ignore it. If not, the
     /// [`event_created_child!`](event_created_child!) macro is provided for overriding it.
-    fn event_created_child(opcode: u16, _qhandle: &QueueHandle<Self>) -> Arc<dyn ObjectData> {
-        panic!(
-            "Missing event_created_child specialization for event opcode {} of {}",
-            opcode,
-            I::interface().name
-        );
+    ///
+    /// If it is not overridden, dispatching an event creating an object fails with
+    /// [`DispatchError::MissingChildData`].
+    fn event_created_child(_opcode: u16, _qhandle: &QueueHandle<Self>) -> Arc<dyn ObjectData> {
+        Arc::new(MissingChildData)
     }
 }
 
@@ -62,18 +67,21 @@ pub trait Dispatch<I: Proxy>: Sized {
 ///
 ///     event_created_child!(MyState, WlFoo, [
 ///     // there can be multiple lines if this interface has multiple object-creating event
-///         2 => (WlBar, BarUserData::new()),
-///     //  ~     ~~~~~  ~~~~~~~~~~~~~~~~~~
-///     //  |       |       |
-///     //  |       |       +-- an expression whose evaluation produces the user data value
-///     //  |       +-- the type of the newly created objecy
+///         wl_foo::EVT_CREATE_BAR_OPCODE => (WlBar, BarUserData::new()),
+///     //  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~     ~~~~~  ~~~~~~~~~~~~~~~~~~
+///     //  |                                   |       |
+///     //  |                                   |       +-- an expression whose evaluation produces the user data value
+///     //  |                                   +-- the type of the newly created objecy
 ///     //  +-- the opcode of the event that creates a new object
 ///     ]);
 /// }
 /// ```
+///
+/// The opcode can be given as any pattern matching an `u16`, the `EVT_*_OPCODE` constants generated
+/// in the module of each interface are the most convenient way to designate the events.
 #[macro_export]
 macro_rules! event_created_child {
-    ($selftype:ty, $iface:ty, [$($opcode:expr => ($child_iface:ty, $child_udata:expr)),* $(,)?]) => {
+    ($selftype:ty, $iface:ty, [$($opcode:pat => ($child_iface:ty, $child_udata:expr)),* $(,)?]) => {
         fn event_created_child(
             opcode: u16,
             qhandle: &$crate::QueueHandle<Self>
@@ -92,20 +100,183 @@ macro_rules! event_created_child {
     }
 }
 
-type QueueCallback<D> = fn(
+// The callbacks are monomorphized for the state type of their event queue, but their signature does
+// not mention it: this way they can be stored in the object data (which must be 'static) even if the
+// state type borrows from the stack frame driving the event loop.
+//
+// They must only ever be invoked with a pointer to the state of the queue they were created for.
+type QueueCallback = unsafe fn(
     &mut ConnectionHandle<'_>,
     Message<ObjectId>,
-    &mut D,
+    *mut (),
     Arc<dyn ObjectData>,
-    &QueueHandle<D>,
+    &QueueSender,
 ) -> Result<(), DispatchError>;
 
-struct QueueEvent<D>(QueueCallback<D>, Message<ObjectId>, Arc<dyn ObjectData>);
+type ChildDataCallback = fn(u16, &QueueSender) -> Arc<dyn ObjectData>;
+
+// The fourth field is the id of the view of an `AnyEventQueue` the event is destined to, and
+// determines the state type its callback expects. It is always 0 for a plain `EventQueue`. The
+// fifth field is the time at which the event was read from the socket, if the backend records it.
+// The last one counts the event as buffered until it is dispatched or dropped.
+struct QueueEvent(
+    QueueCallback,
+    Message<ObjectId>,
+    Arc<dyn ObjectData>,
+    usize,
+    Option<Instant>,
+    BufferedEvent,
+);
 
 #[cfg(not(tarpaulin_include))]
-impl<D> std::fmt::Debug for QueueEvent<D> {
+impl std::fmt::Debug for QueueEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("QueueEvent").field("msg", &self.1).finish_non_exhaustive()
+        f.debug_struct("QueueEvent")
+            .field("msg", &self.1)
+            .field("view", &self.3)
+            .finish_non_exhaustive()
+    }
+}
+
+// The number of events read from the socket and not dispatched yet, for all the event queues of a
+// connection
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BufferedEvents(Arc<AtomicUsize>);
+
+impl BufferedEvents {
+    pub(crate) fn count(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn track(&self) -> BufferedEvent {
+        self.0.fetch_add(1, Ordering::AcqRel);
+        BufferedEvent(self.0.clone())
+    }
+}
+
+// Counts an event in its `BufferedEvents` until it is dropped
+struct BufferedEvent(Arc<AtomicUsize>);
+
+impl Drop for BufferedEvent {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct QueueSender {
+    tx: UnboundedSender<QueueEvent>,
+    view: usize,
+    buffered: BufferedEvents,
+}
+
+impl QueueSender {
+    fn send(
+        &self,
+        func: QueueCallback,
+        msg: Message<ObjectId>,
+        odata: Arc<dyn ObjectData>,
+        received: Option<Instant>,
+    ) {
+        let evt = QueueEvent(func, msg, odata, self.view, received, self.buffered.track());
+        if self.tx.unbounded_send(evt).is_err() {
+            log::error!("Event received for EventQueue after it was dropped.");
+        }
+    }
+}
+
+/// Timing of the dispatching of an event
+///
+/// It is reported to the profiler of an event queue, see [`EventQueue::set_profiler()`], once
+/// the handler of the event returns.
+#[derive(Debug, Clone, Copy)]
+pub struct EventTiming {
+    /// The interface of the object that received the event
+    pub interface: &'static Interface,
+    /// The opcode of the event
+    pub opcode: u16,
+    /// The time at which the event was read from the socket by the backend
+    pub received: Instant,
+    /// The time spent by the event in the queue, until its handler was invoked
+    pub queued: Duration,
+    /// The time spent in the handler of the event
+    pub handler: Duration,
+}
+
+impl EventTiming {
+    /// The name of the event
+    pub fn event_name(&self) -> &'static str {
+        self.interface.events.get(self.opcode as usize).map(|desc| desc.name).unwrap_or("unknown")
+    }
+
+    /// The time from the reception of the event to the completion of its handler
+    pub fn total(&self) -> Duration {
+        self.queued + self.handler
+    }
+}
+
+type Profiler = Box<dyn FnMut(&EventTiming) + Send>;
+
+// The profiler is only ever accessed through `&mut`, so sharing references to it between threads
+// is harmless: this keeps the event queue `Sync` without locking it
+struct SyncProfiler(Profiler);
+
+unsafe impl Sync for SyncProfiler {}
+
+// The timing measurements of an event queue
+//
+// The events are only timed once the backend records the time of the socket reads, which is
+// enabled when a profiler is set or the latency watermark is enabled.
+#[derive(Default)]
+struct Instrumentation {
+    profiler: Option<SyncProfiler>,
+    // the longest time an event waited in the queue
+    max_queued: Duration,
+}
+
+impl Instrumentation {
+    fn set_profiler(&mut self, backend: &Mutex<Backend>, profiler: Profiler) {
+        backend.lock().unwrap().handle().enable_read_timestamps();
+        self.profiler = Some(SyncProfiler(profiler));
+    }
+}
+
+// SAFETY: `data` must point to the state type expected by the callback of the event
+unsafe fn dispatch_event(
+    handle: &mut ConnectionHandle<'_>,
+    evt: QueueEvent,
+    data: *mut (),
+    tx: &QueueSender,
+    instrumentation: &mut Instrumentation,
+) -> Result<(), DispatchError> {
+    // the event stays counted as buffered until its callback returns
+    let QueueEvent(cb, msg, odata, _, received, _buffered) = evt;
+    if is_ignored(&odata) {
+        discard_event(handle, msg);
+        return Ok(());
+    }
+    let received = match received {
+        Some(received) => received,
+        // the event was read before the timing of the events was enabled
+        None => return cb(handle, msg, data, odata, tx),
+    };
+    let start = Instant::now();
+    let queued = start.saturating_duration_since(received);
+    instrumentation.max_queued = instrumentation.max_queued.max(queued);
+    match instrumentation.profiler {
+        Some(SyncProfiler(ref mut profiler)) => {
+            let (interface, opcode) = (msg.sender_id.interface(), msg.opcode);
+            cb(handle, msg, data, odata, tx)?;
+            profiler(&EventTiming {
+                interface,
+                opcode,
+                received,
+                queued,
+                handler: start.elapsed(),
+            });
+            Ok(())
+        }
+        None => cb(handle, msg, data, odata, tx),
     }
 }
 
@@ -119,10 +290,54 @@ impl<D> std::fmt::Debug for QueueEvent<D> {
 /// as argument to the method creating it. All event received by that object will be processed by that event
 /// queue, when [`dispatch_pending()`](EventQueue::dispatch_pending) or
 /// [`blocking_dispatch()`](EventQueue::blocking_dispatch) is invoked.
+///
+/// The state type `D` does not need to be `'static`: it can borrow from the stack frame driving the
+/// event loop, avoiding the need to wrap shared parts of your state in `Rc<RefCell<_>>`.
+///
+/// ```no_run
+/// use wayland_client::{protocol::wl_registry, Connection, ConnectionHandle, Dispatch, QueueHandle};
+///
+/// struct State<'a> {
+///     globals: &'a mut Vec<String>,
+/// }
+///
+/// impl Dispatch<wl_registry::WlRegistry> for State<'_> {
+///     type UserData = ();
+///
+///     fn event(
+///         &mut self,
+///         _: &wl_registry::WlRegistry,
+///         event: wl_registry::Event,
+///         _: &(),
+///         _: &mut ConnectionHandle,
+///         _: &QueueHandle<Self>,
+///     ) {
+///         if let wl_registry::Event::Global { interface, .. } = event {
+///             self.globals.push(interface);
+///         }
+///     }
+/// }
+///
+/// let conn = Connection::connect_to_env().unwrap();
+/// let mut globals = Vec::new();
+/// {
+///     let mut event_queue = conn.new_event_queue::<State>();
+///     let display = conn.handle().display();
+///     display.get_registry(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+///     event_queue.blocking_dispatch(&mut State { globals: &mut globals }).unwrap();
+/// }
+/// println!("Advertized globals: {:?}", globals);
+/// ```
 pub struct EventQueue<D> {
-    rx: UnboundedReceiver<QueueEvent<D>>,
+    rx: UnboundedReceiver<QueueEvent>,
+    // the events taken from `rx` to inspect the pending events, and not dispatched yet
+    peeked: VecDeque<QueueEvent>,
+    // the maximum number of events dispatched by each call to `dispatch_pending()`
+    budget: Option<usize>,
     handle: QueueHandle<D>,
     backend: Arc<Mutex<Backend>>,
+    outgoing: Outgoing,
+    instrumentation: Instrumentation,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -136,9 +351,41 @@ impl<D> std::fmt::Debug for EventQueue<D> {
 }
 
 impl<D> EventQueue<D> {
-    pub(crate) fn new(backend: Arc<Mutex<Backend>>) -> Self {
+    pub(crate) fn new(
+        backend: Arc<Mutex<Backend>>,
+        outgoing: Outgoing,
+        buffered: BufferedEvents,
+    ) -> Self {
         let (tx, rx) = unbounded();
-        EventQueue { rx, handle: QueueHandle { tx }, backend }
+        EventQueue {
+            rx,
+            peeked: VecDeque::new(),
+            budget: None,
+            handle: QueueHandle { tx: QueueSender { tx, view: 0, buffered }, _state: PhantomData },
+            backend,
+            outgoing,
+            instrumentation: Instrumentation::default(),
+        }
+    }
+
+    /// Convert this event queue into a type-erased [`AnyEventQueue`]
+    ///
+    /// The objects already assigned to this queue keep delivering their events to it. They can
+    /// be dispatched from the view returned by [`AnyEventQueue::view_from_handle()`] given a
+    /// [`QueueHandle`] of this queue, while new views can be created for other state types.
+    pub fn into_any(self) -> AnyEventQueue {
+        AnyEventQueue {
+            inner: Arc::new(Mutex::new(AnyQueueInner {
+                rx: self.rx,
+                pending: self.peeked,
+                next_view: 1,
+                instrumentation: self.instrumentation,
+            })),
+            tx: self.handle.tx.tx,
+            buffered: self.handle.tx.buffered,
+            backend: self.backend,
+            outgoing: self.outgoing,
+        }
     }
 
     /// Get a [`QueueHandle`] for this event queue
@@ -146,14 +393,142 @@ impl<D> EventQueue<D> {
         self.handle.clone()
     }
 
+    /// Set the profiler of this event queue
+    ///
+    /// The profiler is invoked with the [`EventTiming`] of each event dispatched by this queue,
+    /// once its handler returns. This makes it possible to find the handlers that are slow to
+    /// run, or the events that wait too long in the queue before being dispatched.
+    ///
+    /// This enables the recording of the time of the reads of the socket by the backend, for the
+    /// whole connection: only the events read from then on are reported.
+    pub fn set_profiler<F: FnMut(&EventTiming) + Send + 'static>(&mut self, profiler: F) {
+        self.instrumentation.set_profiler(&self.backend, Box::new(profiler));
+    }
+
+    /// Remove the profiler of this event queue
+    pub fn remove_profiler(&mut self) {
+        self.instrumentation.profiler = None;
+    }
+
+    /// The longest time an event waited in this queue before being dispatched
+    ///
+    /// This is measured from the moment the event was read from the socket to the invocation of
+    /// its handler, for all events dispatched since the creation of the queue or the last call to
+    /// [`reset_latency_watermark()`](EventQueue::reset_latency_watermark). A high value means that
+    /// the event loop does not dispatch the queue promptly after reading the socket.
+    ///
+    /// Only the events read after
+    /// [`enable_latency_watermark()`](EventQueue::enable_latency_watermark) or
+    /// [`set_profiler()`](EventQueue::set_profiler) was invoked on a queue of the connection are
+    /// measured.
+    pub fn latency_watermark(&self) -> Duration {
+        self.instrumentation.max_queued
+    }
+
+    /// Start measuring the latency watermark of this event queue
+    ///
+    /// Like [`set_profiler()`](EventQueue::set_profiler), this enables the recording of the time
+    /// of the reads of the socket by the backend, for the whole connection.
+    pub fn enable_latency_watermark(&mut self) {
+        self.backend.lock().unwrap().handle().enable_read_timestamps();
+    }
+
+    /// Reset the latency watermark of this event queue
+    pub fn reset_latency_watermark(&mut self) {
+        self.instrumentation.max_queued = Duration::ZERO;
+    }
+
     /// Dispatch pending events
     ///
     /// Events are accumulated in the event queue internal buffer when the Wayland socket is read using
     /// the read APIs on [`Connection`](crate::Connection), or when reading is done from an other thread.
     /// This method will dispatch all such pending events by sequentially invoking their associated handlers:
     /// the [`Dispatch`](crate::Dispatch) implementations on the provided `&mut D`.
+    ///
+    /// The event queues of a connection cannot be dispatched from within the callbacks of one of
+    /// them, this fails with [`WaylandError::NestedDispatch`] rather than deadlocking.
+    ///
+    /// If a budget is set with [`set_budget()`](EventQueue::set_budget), at most this number of
+    /// events are dispatched.
     pub fn dispatch_pending(&mut self, data: &mut D) -> Result<usize, DispatchError> {
-        Self::dispatching_impl(&mut self.backend.lock().unwrap(), &mut self.rx, &self.handle, data)
+        let budget = self.budget.unwrap_or(usize::MAX);
+        self.dispatch_budget(data, budget)
+    }
+
+    /// Dispatch at most `budget` pending events
+    ///
+    /// The remaining events stay in the queue until its next dispatch, which makes it possible to
+    /// bound the time spent dispatching a queue receiving a lot of events. When several queues
+    /// share a connection, the latency-sensitive ones (like the input events) can then be
+    /// dispatched first on each iteration of the event loop, and the bulk ones (like the frame
+    /// callbacks or the output events) with a budget.
+    pub fn dispatch_budget(&mut self, data: &mut D, budget: usize) -> Result<usize, DispatchError> {
+        let _guard = DispatchingGuard::new(&self.backend)?;
+        Self::dispatching_impl(
+            &mut self.backend.lock().unwrap(),
+            &mut self.rx,
+            &mut self.peeked,
+            &self.handle,
+            &mut self.instrumentation,
+            data,
+            budget,
+        )
+    }
+
+    /// Set the maximum number of events dispatched by each call to
+    /// [`dispatch_pending()`](EventQueue::dispatch_pending) and
+    /// [`blocking_dispatch()`](EventQueue::blocking_dispatch)
+    ///
+    /// `None`, the default, dispatches all pending events. A budget of `0` is treated as `1`, so
+    /// that the queue keeps progressing.
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget.map(|budget| budget.max(1));
+    }
+
+    /// The budget of this event queue, see [`set_budget()`](EventQueue::set_budget)
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Check whether this queue has events waiting to be dispatched
+    ///
+    /// This does not read the socket, only the events already read for this queue are considered.
+    pub fn has_pending(&mut self) -> bool {
+        if self.peeked.is_empty() {
+            self.peeked.extend(self.rx.try_recv().ok());
+        }
+        !self.peeked.is_empty()
+    }
+
+    /// The number of events waiting to be dispatched
+    ///
+    /// Like [`has_pending()`](EventQueue::has_pending), this does not read the socket. An
+    /// application can use it to detect that it falls behind the server, and shed some of its
+    /// load.
+    pub fn pending_len(&mut self) -> usize {
+        self.fetch_pending();
+        self.peeked.len()
+    }
+
+    /// Inspect the events waiting to be dispatched, in the order they will be dispatched
+    ///
+    /// This does not read the socket, and the events stay in the queue.
+    pub fn peek(&mut self) -> impl Iterator<Item = &Message<ObjectId>> {
+        self.fetch_pending();
+        self.peeked.iter().map(|evt| &evt.1)
+    }
+
+    /// Remove the events waiting to be dispatched from the queue, without dispatching them
+    ///
+    /// The events are appended to `events`, and their number is returned. The file descriptors
+    /// they carry are then owned by the caller, and the objects they create are never handed
+    /// to the [`Dispatch`](crate::Dispatch) implementations. This is mostly useful in tests, to
+    /// assert on the exact events sent by the server.
+    pub fn drain_into(&mut self, events: &mut Vec<Message<ObjectId>>) -> usize {
+        self.fetch_pending();
+        let drained = self.peeked.len();
+        events.extend(self.peeked.drain(..).map(|evt| evt.1));
+        drained
     }
 
     /// Block waiting for events and dispatch them
@@ -163,22 +538,12 @@ impl<D> EventQueue<D> {
     ///
     /// A simple app event loop can consist in invoking this method in a loop.
     pub fn blocking_dispatch(&mut self, data: &mut D) -> Result<usize, DispatchError> {
-        let dispatched = Self::dispatching_impl(
-            &mut self.backend.lock().unwrap(),
-            &mut self.rx,
-            &self.handle,
-            data,
-        )?;
+        let dispatched = self.dispatch_pending(data)?;
         if dispatched > 0 {
             Ok(dispatched)
         } else {
-            crate::conn::blocking_dispatch_impl(self.backend.clone())?;
-            Self::dispatching_impl(
-                &mut self.backend.lock().unwrap(),
-                &mut self.rx,
-                &self.handle,
-                data,
-            )
+            crate::conn::blocking_dispatch_impl(self.backend.clone(), &self.outgoing)?;
+            self.dispatch_pending(data)
         }
     }
 
@@ -192,6 +557,7 @@ impl<D> EventQueue<D> {
     /// If you don't need to manage multiple event sources, see
     /// [`blocking_dispatch()`](EventQueue::blocking_dispatch) for a simpler mechanism.
     pub fn prepare_read(&self) -> Result<ReadEventsGuard, WaylandError> {
+        check_not_dispatching(&self.backend)?;
         ReadEventsGuard::try_new(self.backend.clone())
     }
 
@@ -199,29 +565,328 @@ impl<D> EventQueue<D> {
     ///
     /// This needs to be done regularly to ensure the server receives all your requests.
     pub fn flush(&self) -> Result<(), WaylandError> {
-        self.backend.lock().unwrap().flush()
+        self.outgoing.flush(&self.backend)
+    }
+
+    fn fetch_pending(&mut self) {
+        while let Ok(evt) = self.rx.try_recv() {
+            self.peeked.push_back(evt);
+        }
     }
 
     fn dispatching_impl(
         backend: &mut Backend,
-        rx: &mut UnboundedReceiver<QueueEvent<D>>,
+        rx: &mut UnboundedReceiver<QueueEvent>,
+        peeked: &mut VecDeque<QueueEvent>,
         qhandle: &QueueHandle<D>,
+        instrumentation: &mut Instrumentation,
         data: &mut D,
+        budget: usize,
     ) -> Result<usize, DispatchError> {
         let mut handle = ConnectionHandle::from_handle(backend.handle());
         let mut dispatched = 0;
 
-        while let Ok(Some(QueueEvent(cb, msg, odata))) = rx.try_next() {
-            cb(&mut handle, msg, data, odata, qhandle)?;
+        while dispatched < budget {
+            let evt = match peeked.pop_front() {
+                Some(evt) => evt,
+                None => match rx.try_recv() {
+                    Ok(evt) => evt,
+                    Err(_) => break,
+                },
+            };
+            // SAFETY: all the events of this queue were sent by objects created through a
+            // `QueueHandle<D>` of this queue, so their callback expects a `D`
+            unsafe {
+                dispatch_event(
+                    &mut handle,
+                    evt,
+                    data as *mut D as *mut (),
+                    &qhandle.tx,
+                    instrumentation,
+                )?
+            };
             dispatched += 1;
         }
         Ok(dispatched)
     }
 }
 
-/// A handle representing an [`EventQueue`], used to assign objects upon creation.
+/// A type-erased event queue
+///
+/// Unlike [`EventQueue`], this event queue is not tied to a single state type. Instead, typed
+/// [`QueueView`]s are created from it, each with its own state type. Objects created through the
+/// [`QueueHandle`] of a view have their events delivered to this queue, and dispatched by that
+/// view.
+///
+/// This makes it possible to split the state of an application into several types without
+/// recreating the objects already assigned to a queue: converting the original queue with
+/// [`EventQueue::into_any()`], its existing objects can still be dispatched with the original
+/// state type, while new objects are created through views for the new state types.
+///
+/// All views share the same buffer of pending events: the events of an object are dispatched in
+/// order, but the events destined to different views are only ordered relative to each other if
+/// these views are dispatched in that order.
+pub struct AnyEventQueue {
+    inner: Arc<Mutex<AnyQueueInner>>,
+    tx: UnboundedSender<QueueEvent>,
+    buffered: BufferedEvents,
+    backend: Arc<Mutex<Backend>>,
+    outgoing: Outgoing,
+}
+
+struct AnyQueueInner {
+    rx: UnboundedReceiver<QueueEvent>,
+    // events received for views other than the one being dispatched
+    pending: VecDeque<QueueEvent>,
+    next_view: usize,
+    instrumentation: Instrumentation,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Debug for AnyEventQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnyEventQueue").field("tx", &self.tx).finish_non_exhaustive()
+    }
+}
+
+impl AnyEventQueue {
+    pub(crate) fn new(
+        backend: Arc<Mutex<Backend>>,
+        outgoing: Outgoing,
+        buffered: BufferedEvents,
+    ) -> Self {
+        let (tx, rx) = unbounded();
+        AnyEventQueue {
+            inner: Arc::new(Mutex::new(AnyQueueInner {
+                rx,
+                pending: VecDeque::new(),
+                next_view: 1,
+                instrumentation: Instrumentation::default(),
+            })),
+            tx,
+            buffered,
+            backend,
+            outgoing,
+        }
+    }
+
+    /// Create a new view of this event queue for the state type `D`
+    ///
+    /// Each call creates a distinct view: the objects created through the handle of a view are
+    /// only dispatched by this view, even if an other view has the same state type.
+    pub fn view<D>(&self) -> QueueView<D> {
+        let view = {
+            let mut inner = self.inner.lock().unwrap();
+            let view = inner.next_view;
+            inner.next_view += 1;
+            view
+        };
+        QueueView {
+            inner: self.inner.clone(),
+            handle: QueueHandle {
+                tx: QueueSender { tx: self.tx.clone(), view, buffered: self.buffered.clone() },
+                _state: PhantomData,
+            },
+            backend: self.backend.clone(),
+            outgoing: self.outgoing.clone(),
+        }
+    }
+
+    /// Get the view of this event queue associated with a queue handle
+    ///
+    /// This can be a handle of the [`EventQueue`] this queue was converted from, or of an other
+    /// view of this queue. Returns `None` if the handle belongs to an other queue.
+    pub fn view_from_handle<D>(&self, handle: &QueueHandle<D>) -> Option<QueueView<D>> {
+        if handle.tx.tx.same_receiver(&self.tx) {
+            Some(QueueView {
+                inner: self.inner.clone(),
+                handle: handle.clone(),
+                backend: self.backend.clone(),
+                outgoing: self.outgoing.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Set the profiler of this event queue
+    ///
+    /// The profiler is shared by all views of the queue. See [`EventQueue::set_profiler()`] for
+    /// details.
+    pub fn set_profiler<F: FnMut(&EventTiming) + Send + 'static>(&self, profiler: F) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.instrumentation.set_profiler(&self.backend, Box::new(profiler));
+    }
+
+    /// Remove the profiler of this event queue
+    pub fn remove_profiler(&self) {
+        self.inner.lock().unwrap().instrumentation.profiler = None;
+    }
+
+    /// The longest time an event waited in this queue before being dispatched
+    ///
+    /// It covers the events dispatched by all views of the queue. See
+    /// [`EventQueue::latency_watermark()`] for details.
+    pub fn latency_watermark(&self) -> Duration {
+        self.inner.lock().unwrap().instrumentation.max_queued
+    }
+
+    /// Start measuring the latency watermark of this event queue
+    ///
+    /// See [`EventQueue::enable_latency_watermark()`] for details.
+    pub fn enable_latency_watermark(&self) {
+        self.backend.lock().unwrap().handle().enable_read_timestamps();
+    }
+
+    /// Reset the latency watermark of this event queue
+    pub fn reset_latency_watermark(&self) {
+        self.inner.lock().unwrap().instrumentation.max_queued = Duration::ZERO;
+    }
+
+    /// Start a synchronized read from the socket
+    ///
+    /// See [`EventQueue::prepare_read()`] for details.
+    pub fn prepare_read(&self) -> Result<ReadEventsGuard, WaylandError> {
+        check_not_dispatching(&self.backend)?;
+        ReadEventsGuard::try_new(self.backend.clone())
+    }
+
+    /// Flush pending outgoing events to the server
+    ///
+    /// This needs to be done regularly to ensure the server receives all your requests.
+    pub fn flush(&self) -> Result<(), WaylandError> {
+        self.outgoing.flush(&self.backend)
+    }
+}
+
+/// A typed view of an [`AnyEventQueue`]
+///
+/// It provides the [`QueueHandle`] used to assign objects to the queue, and dispatches the events
+/// of these objects using its state type `D`.
+pub struct QueueView<D> {
+    inner: Arc<Mutex<AnyQueueInner>>,
+    handle: QueueHandle<D>,
+    backend: Arc<Mutex<Backend>>,
+    outgoing: Outgoing,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<D> std::fmt::Debug for QueueView<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueueView").field("handle", &self.handle).finish_non_exhaustive()
+    }
+}
+
+impl<D> QueueView<D> {
+    /// Get a [`QueueHandle`] for this view
+    pub fn handle(&self) -> QueueHandle<D> {
+        self.handle.clone()
+    }
+
+    /// Dispatch the pending events of this view
+    ///
+    /// Only the events of the objects assigned to this view are dispatched, the events of the
+    /// other views stay in the queue until their view is dispatched. Like for
+    /// [`EventQueue::dispatch_pending()`], this cannot be invoked from within the callbacks of the
+    /// connection.
+    pub fn dispatch_pending(&mut self, data: &mut D) -> Result<usize, DispatchError> {
+        self.dispatch_budget(data, usize::MAX)
+    }
+
+    /// Dispatch at most `budget` pending events of this view
+    ///
+    /// See [`EventQueue::dispatch_budget()`] for details.
+    pub fn dispatch_budget(&mut self, data: &mut D, budget: usize) -> Result<usize, DispatchError> {
+        let _guard = DispatchingGuard::new(&self.backend)?;
+        Self::dispatching_impl(
+            &mut self.backend.lock().unwrap(),
+            &mut self.inner.lock().unwrap(),
+            &self.handle,
+            data,
+            budget,
+        )
+    }
+
+    /// Check whether this view has events waiting to be dispatched
+    ///
+    /// This does not read the socket, only the events already read for this queue are considered.
+    pub fn has_pending(&self) -> bool {
+        let view = self.handle.tx.view;
+        let mut inner = self.inner.lock().unwrap();
+        if inner.pending.iter().any(|evt| evt.3 == view) {
+            return true;
+        }
+        while let Ok(evt) = inner.rx.try_recv() {
+            let found = evt.3 == view;
+            inner.pending.push_back(evt);
+            if found {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Block waiting for events and dispatch the events of this view
+    ///
+    /// This method is similar to [`dispatch_pending`](QueueView::dispatch_pending), but if there
+    /// are no pending events for this view it will also block waiting for the Wayland server to
+    /// send an event. This can return `Ok(0)` if the received events were all destined to other
+    /// views.
+    pub fn blocking_dispatch(&mut self, data: &mut D) -> Result<usize, DispatchError> {
+        let dispatched = self.dispatch_pending(data)?;
+        if dispatched > 0 {
+            Ok(dispatched)
+        } else {
+            crate::conn::blocking_dispatch_impl(self.backend.clone(), &self.outgoing)?;
+            self.dispatch_pending(data)
+        }
+    }
+
+    fn dispatching_impl(
+        backend: &mut Backend,
+        inner: &mut AnyQueueInner,
+        qhandle: &QueueHandle<D>,
+        data: &mut D,
+        budget: usize,
+    ) -> Result<usize, DispatchError> {
+        let mut handle = ConnectionHandle::from_handle(backend.handle());
+        let mut dispatched = 0;
+        let view = qhandle.tx.view;
+
+        while dispatched < budget {
+            let evt = match inner.pending.iter().position(|evt| evt.3 == view) {
+                Some(idx) => inner.pending.remove(idx).unwrap(),
+                None => match inner.rx.try_recv() {
+                    Ok(evt) if evt.3 == view => evt,
+                    Ok(evt) => {
+                        inner.pending.push_back(evt);
+                        continue;
+                    }
+                    Err(_) => break,
+                },
+            };
+            // SAFETY: the events of this view were sent by objects created through its
+            // `QueueHandle<D>`, so their callback expects a `D`
+            unsafe {
+                dispatch_event(
+                    &mut handle,
+                    evt,
+                    data as *mut D as *mut (),
+                    &qhandle.tx,
+                    &mut inner.instrumentation,
+                )?
+            };
+            dispatched += 1;
+        }
+        Ok(dispatched)
+    }
+}
+
+/// A handle representing an [`EventQueue`] (or a [`QueueView`]), used to assign objects upon creation.
 pub struct QueueHandle<D> {
-    tx: UnboundedSender<QueueEvent<D>>,
+    tx: QueueSender,
+    // invariant over D, while staying Send + Sync
+    _state: PhantomData<fn(&mut D)>,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -233,36 +898,52 @@ impl<Data> std::fmt::Debug for QueueHandle<Data> {
 
 impl<Data> Clone for QueueHandle<Data> {
     fn clone(&self) -> Self {
-        QueueHandle { tx: self.tx.clone() }
+        QueueHandle { tx: self.tx.clone(), _state: PhantomData }
     }
 }
 
-pub(crate) struct QueueSender<D> {
-    func: QueueCallback<D>,
-    pub(crate) handle: QueueHandle<D>,
+pub(crate) struct ProxySender {
+    func: QueueCallback,
+    // invoked instead of `func` for the events whose child object could not be initialized
+    missing_child: QueueCallback,
+    tx: QueueSender,
 }
 
 pub(crate) trait ErasedQueueSender<I> {
-    fn send(&self, msg: Message<ObjectId>, odata: Arc<dyn ObjectData>);
+    fn send(&self, msg: Message<ObjectId>, odata: Arc<dyn ObjectData>, received: Option<Instant>);
+    fn send_missing_child(
+        &self,
+        msg: Message<ObjectId>,
+        odata: Arc<dyn ObjectData>,
+        received: Option<Instant>,
+    );
 }
 
-impl<I: Proxy, D> ErasedQueueSender<I> for QueueSender<D>
-where
-    D: Dispatch<I>,
-{
-    fn send(&self, msg: Message<ObjectId>, odata: Arc<dyn ObjectData>) {
-        if self.handle.tx.unbounded_send(QueueEvent(self.func, msg, odata)).is_err() {
-            log::error!("Event received for EventQueue after it was dropped.");
-        }
+impl<I: Proxy> ErasedQueueSender<I> for ProxySender {
+    fn send(&self, msg: Message<ObjectId>, odata: Arc<dyn ObjectData>, received: Option<Instant>) {
+        self.tx.send(self.func, msg, odata, received);
+    }
+
+    fn send_missing_child(
+        &self,
+        msg: Message<ObjectId>,
+        odata: Arc<dyn ObjectData>,
+        received: Option<Instant>,
+    ) {
+        self.tx.send(self.missing_child, msg, odata, received);
     }
 }
 
-impl<D: 'static> QueueHandle<D> {
+impl<D> QueueHandle<D> {
     /// Create an object data associated with this event queue
     ///
     /// This creates an implementation of [`ObjectData`] fitting for direct use with `wayland-backend` APIs
     /// that forwards all events to the event queue associated with this token, integrating the object into
     /// the [`Dispatch`]-based logic of `wayland-client`.
+    ///
+    /// The location of the caller is recorded, and reported by the errors concerning this object, like
+    /// [`DispatchError::MissingChildData`].
+    #[track_caller]
     pub fn make_data<I: Proxy + 'static>(
         &self,
         user_data: <D as Dispatch<I>>::UserData,
@@ -270,56 +951,113 @@ impl<D: 'static> QueueHandle<D> {
     where
         D: Dispatch<I>,
     {
-        let sender = Box::new(QueueSender { func: queue_callback::<I, D>, handle: self.clone() });
+        let sender = Box::new(ProxySender {
+            func: queue_callback::<I, D>,
+            missing_child: missing_child_callback::<I, D>,
+            tx: self.tx.clone(),
+        });
 
         let has_creating_event =
             I::interface().events.iter().any(|desc| desc.child_interface.is_some());
 
         let odata_maker = if has_creating_event {
-            let qhandle = self.clone();
+            let tx = self.tx.clone();
+            let child_data: ChildDataCallback = child_data_callback::<I, D>;
             Box::new(move |msg: &Message<ObjectId>| {
-                Some(<D as Dispatch<I>>::event_created_child(msg.opcode, &qhandle))
+                // only the events creating an object need child data
+                I::interface().events[msg.opcode as usize]
+                    .child_interface
+                    .map(|_| child_data(msg.opcode, &tx))
             }) as Box<_>
         } else {
             Box::new(|_: &Message<ObjectId>| None) as Box<_>
         };
-        Arc::new(QueueProxyData { sender, odata_maker, udata: user_data })
+        Arc::new(QueueProxyData::<I, _> {
+            sender,
+            odata_maker,
+            created_at: Location::caller(),
+            udata: user_data,
+        })
     }
 }
 
-fn queue_callback<I: Proxy + 'static, D: Dispatch<I> + 'static>(
+/// # Safety
+///
+/// `data` must be a valid pointer to the `D` of the event queue `tx` belongs to.
+unsafe fn queue_callback<I: Proxy + 'static, D: Dispatch<I>>(
     handle: &mut ConnectionHandle<'_>,
     msg: Message<ObjectId>,
-    data: &mut D,
+    data: *mut (),
     odata: Arc<dyn ObjectData>,
-    qhandle: &QueueHandle<D>,
+    tx: &QueueSender,
 ) -> Result<(), DispatchError> {
+    let data = &mut *(data as *mut D);
+    let qhandle = QueueHandle::<D> { tx: tx.clone(), _state: PhantomData };
     let (proxy, event) = I::parse_event(handle, msg)?;
     let proxy_data = (&*odata)
         .downcast_ref::<QueueProxyData<I, <D as Dispatch<I>>::UserData>>()
         .expect("Wrong user_data value for object");
-    data.event(&proxy, event, &proxy_data.udata, handle, qhandle);
+    data.event(&proxy, event, &proxy_data.udata, handle, &qhandle);
     Ok(())
 }
 
+fn missing_child_callback<I: Proxy + 'static, D: Dispatch<I>>(
+    _: &mut ConnectionHandle<'_>,
+    msg: Message<ObjectId>,
+    _: *mut (),
+    odata: Arc<dyn ObjectData>,
+    _: &QueueSender,
+) -> Result<(), DispatchError> {
+    let proxy_data = (&*odata)
+        .downcast_ref::<QueueProxyData<I, <D as Dispatch<I>>::UserData>>()
+        .expect("Wrong user_data value for object");
+    Err(DispatchError::MissingChildData {
+        interface: I::interface().name,
+        event: I::interface().events[msg.opcode as usize].name,
+        state: std::any::type_name::<D>(),
+        created_at: proxy_data.created_at,
+    })
+}
+
+fn child_data_callback<I: Proxy, D: Dispatch<I>>(
+    opcode: u16,
+    tx: &QueueSender,
+) -> Arc<dyn ObjectData> {
+    let qhandle = QueueHandle::<D> { tx: tx.clone(), _state: PhantomData };
+    <D as Dispatch<I>>::event_created_child(opcode, &qhandle)
+}
+
 type ObjectDataFactory = dyn Fn(&Message<ObjectId>) -> Option<Arc<dyn ObjectData>> + Send + Sync;
 
 /// The [`ObjectData`] implementation used by Wayland proxies, integrating with [`Dispatch`]
 pub struct QueueProxyData<I: Proxy, U> {
     pub(crate) sender: Box<dyn ErasedQueueSender<I> + Send + Sync>,
     odata_maker: Box<ObjectDataFactory>,
+    created_at: &'static Location<'static>,
     /// The user data associated with this object
     pub udata: U,
 }
 
+impl<I: Proxy, U> QueueProxyData<I, U> {
+    /// The location of the code that created this object
+    pub fn created_at(&self) -> &'static Location<'static> {
+        self.created_at
+    }
+}
+
 impl<I: Proxy + 'static, U: Send + Sync + 'static> ObjectData for QueueProxyData<I, U> {
     fn event(
         self: Arc<Self>,
-        _: &mut Handle,
+        handle: &mut Handle,
         msg: Message<ObjectId>,
     ) -> Option<Arc<dyn ObjectData>> {
         let ret = (self.odata_maker)(&msg);
-        self.sender.send(msg, self.clone());
+        let received = handle.last_read_time();
+        if ret.as_ref().map(|child| child.is::<MissingChildData>()).unwrap_or(false) {
+            self.sender.send_missing_child(msg, self.clone(), received);
+        } else {
+            self.sender.send(msg, self.clone(), received);
+        }
         ret
     }
 
@@ -329,7 +1067,10 @@ impl<I: Proxy + 'static, U: Send + Sync + 'static> ObjectData for QueueProxyData
 #[cfg(not(tarpaulin_include))]
 impl<I: Proxy, U: std::fmt::Debug> std::fmt::Debug for QueueProxyData<I, U> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("QueueProxyData").field("udata", &self.udata).finish()
+        f.debug_struct("QueueProxyData")
+            .field("created_at", &self.created_at)
+            .field("udata", &self.udata)
+            .finish()
     }
 }
 
@@ -343,6 +1084,114 @@ impl ObjectData for TemporaryData {
     fn destroyed(&self, _: ObjectId) {}
 }
 
+// The data of the objects created by an event for which the `Dispatch` implementation did not
+// override `event_created_child()`. The event itself is reported as an error by the queue.
+struct MissingChildData;
+
+impl ObjectData for MissingChildData {
+    fn event(
+        self: Arc<Self>,
+        _: &mut Handle,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        log::warn!("Ignoring event {} for uninitialized object {}", msg.opcode, msg.sender_id);
+        // objects created by this event are left uninitialized as well
+        let creates_child = msg
+            .sender_id
+            .interface()
+            .events
+            .get(msg.opcode as usize)
+            .map(|desc| desc.child_interface.is_some())
+            .unwrap_or(false);
+        if creates_child {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+}
+
+// The data of the objects whose remaining events are discarded, see
+// `Proxy::ignore_remaining_events()`. Their events still queued were received with the data they had
+// before being ignored, which is recorded here to recognize them.
+lazy_static::lazy_static! {
+    static ref IGNORED: Mutex<Vec<Weak<dyn ObjectData>>> = Mutex::new(Vec::new());
+}
+
+// The length of `IGNORED`, to skip locking it while no object is ignored
+static IGNORED_LEN: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn ignore_remaining_events(conn: &mut ConnectionHandle, id: ObjectId) {
+    let handle = conn.inner.handle();
+    if let Ok(data) = handle.get_data(id.clone()) {
+        if !data.is::<IgnoredData>() {
+            let mut ignored = IGNORED.lock().unwrap();
+            // forget the objects whose events were all dispatched or dropped
+            ignored.retain(|data| data.strong_count() > 0);
+            ignored.push(Arc::downgrade(&data));
+            IGNORED_LEN.store(ignored.len(), Ordering::Release);
+        }
+    }
+    // the events received from now on are discarded by the backend
+    let _ = handle.set_data(id, Arc::new(IgnoredData));
+}
+
+fn is_ignored(odata: &Arc<dyn ObjectData>) -> bool {
+    if IGNORED_LEN.load(Ordering::Acquire) == 0 {
+        return false;
+    }
+    let ptr = Arc::as_ptr(odata) as *const u8;
+    IGNORED.lock().unwrap().iter().any(|data| std::ptr::eq(data.as_ptr() as *const u8, ptr))
+}
+
+// Drop a queued event of an ignored object, closing its file descriptors and ignoring the objects it
+// creates
+fn discard_event(handle: &mut ConnectionHandle, msg: Message<ObjectId>) {
+    log::debug!("Discarding event {} of ignored object {}", msg.opcode, msg.sender_id);
+    for arg in msg.args {
+        match arg {
+            Argument::Fd(fd) => {
+                let _ = nix::unistd::close(fd);
+            }
+            Argument::NewId(child) => ignore_remaining_events(handle, child),
+            _ => {}
+        }
+    }
+}
+
+// The data of the objects whose remaining events are discarded, and of the objects these events
+// create.
+struct IgnoredData;
+
+impl ObjectData for IgnoredData {
+    fn event(
+        self: Arc<Self>,
+        _: &mut Handle,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        log::debug!("Discarding event {} of ignored object {}", msg.opcode, msg.sender_id);
+        let mut creates_child = false;
+        for arg in msg.args {
+            match arg {
+                Argument::Fd(fd) => {
+                    let _ = nix::unistd::close(fd);
+                }
+                Argument::NewId(_) => creates_child = true,
+                _ => {}
+            }
+        }
+        if creates_child {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+}
+
 /*
  * Dispatch delegation helpers
  */
@@ -424,12 +1273,11 @@ pub trait DelegateDispatch<
     ///
     /// If the interface does not have any such event, you can ignore it. If not, the
     /// [`event_created_child!`](event_created_child!) macro is provided for overriding it.
-    fn event_created_child(opcode: u16, _qhandle: &QueueHandle<D>) -> Arc<dyn ObjectData> {
-        panic!(
-            "Missing event_created_child specialization for event opcode {} of {}",
-            opcode,
-            I::interface().name
-        );
+    ///
+    /// If it is not overridden, dispatching an event creating an object fails with
+    /// [`DispatchError::MissingChildData`].
+    fn event_created_child(_opcode: u16, _qhandle: &QueueHandle<D>) -> Arc<dyn ObjectData> {
+        Arc::new(MissingChildData)
     }
 }
 
@@ -0,0 +1,276 @@
+//! Fences for pacing a render loop on the server
+//!
+//! A [`Fence`] wraps a `wl_callback` object, created either by a `wl_display.sync` request,
+//! which is signaled once the server has processed all previous requests, or by a
+//! `wl_surface.frame` request, which is signaled when it is a good time to draw a new frame of
+//! the surface.
+//!
+//! The fence is signaled as soon as its event is read from the socket, without going through an
+//! event queue, so that no [`Dispatch`](crate::Dispatch) implementation is needed for it.
+//!
+//! ```no_run
+//! # fn frame_loop(conn: &wayland_client::Connection, surface: &wayland_client::protocol::wl_surface::WlSurface) {
+//! use wayland_client::fence::Fence;
+//!
+//! loop {
+//!     let fence = Fence::frame(&mut conn.handle(), surface).unwrap();
+//!     /* draw and commit the surface */
+//!     let timestamp = fence.wait(conn).unwrap();
+//! #   let _ = timestamp;
+//! }
+//! # }
+//! ```
+//!
+//! For applications redrawing on demand, a [`FrameThrottle`] manages the frame fences of a surface
+//! and only invokes the drawing closure once the compositor is ready for a new frame.
+
+use std::sync::{Arc, Mutex};
+
+use wayland_backend::{
+    client::{Handle, ObjectData, ObjectId, WaylandError},
+    protocol::{Argument, Message},
+};
+
+use crate::{
+    protocol::{wl_display, wl_surface},
+    Connection, ConnectionHandle, DispatchError, Proxy,
+};
+
+type FenceCallback = Box<dyn FnOnce(u32) + Send>;
+
+/// A fence signaled by the server
+///
+/// The fence carries the `callback_data` of the `wl_callback.done` event, which is the serial of
+/// the event for a sync fence, and a timestamp in milliseconds for a frame fence.
+///
+/// Cloning a fence gives an other handle to the same fence.
+#[derive(Clone)]
+pub struct Fence {
+    data: Arc<FenceData>,
+}
+
+#[derive(Default)]
+struct FenceData {
+    inner: Mutex<FenceInner>,
+}
+
+#[derive(Default)]
+struct FenceInner {
+    callback_data: Option<u32>,
+    callbacks: Vec<FenceCallback>,
+}
+
+impl Fence {
+    /// Create a fence signaled once the server has processed all previous requests
+    pub fn sync(conn: &mut ConnectionHandle) -> Result<Fence, DispatchError> {
+        let display = conn.display();
+        Fence::from_request(conn, &display, wl_display::Request::Sync {})
+    }
+
+    /// Create a fence signaled when it is a good time to draw a new frame of a surface
+    ///
+    /// The frame callback is only requested for the next commit of the surface.
+    pub fn frame(
+        conn: &mut ConnectionHandle,
+        surface: &wl_surface::WlSurface,
+    ) -> Result<Fence, DispatchError> {
+        Fence::from_request(conn, surface, wl_surface::Request::Frame {})
+    }
+
+    fn from_request<I: Proxy>(
+        conn: &mut ConnectionHandle,
+        proxy: &I,
+        request: I::Request,
+    ) -> Result<Fence, DispatchError> {
+        let data = Arc::new(FenceData::default());
+        conn.send_request(proxy, request, Some(data.clone()))?;
+        Ok(Fence { data })
+    }
+
+    /// Check whether this fence was signaled
+    pub fn is_signaled(&self) -> bool {
+        self.callback_data().is_some()
+    }
+
+    /// The callback data of this fence, if it was signaled
+    pub fn callback_data(&self) -> Option<u32> {
+        self.data.inner.lock().unwrap().callback_data
+    }
+
+    /// Block until this fence is signaled
+    ///
+    /// This flushes the connection and reads the socket until the fence is signaled, storing the
+    /// other events in their event queues. Returns the callback data of the fence.
+    pub fn wait(&self, conn: &Connection) -> Result<u32, WaylandError> {
+        loop {
+            if let Some(callback_data) = self.callback_data() {
+                return Ok(callback_data);
+            }
+            conn.blocking_dispatch()?;
+        }
+    }
+
+    /// Register a callback invoked with the callback data once this fence is signaled
+    ///
+    /// If the fence is already signaled, the callback is invoked immediately. Otherwise it is
+    /// invoked by the thread reading the socket, while the connection is locked: it must not use
+    /// the connection.
+    pub fn on_signal<F: FnOnce(u32) + Send + 'static>(&self, callback: F) {
+        let mut inner = self.data.inner.lock().unwrap();
+        match inner.callback_data {
+            Some(callback_data) => {
+                std::mem::drop(inner);
+                callback(callback_data);
+            }
+            None => inner.callbacks.push(Box::new(callback)),
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Debug for Fence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fence").field("callback_data", &self.callback_data()).finish()
+    }
+}
+
+impl ObjectData for FenceData {
+    fn event(
+        self: Arc<Self>,
+        _: &mut Handle,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        // wl_callback.done is the only event of wl_callback
+        let callback_data = match msg.args[..] {
+            [Argument::Uint(callback_data)] => callback_data,
+            _ => return None,
+        };
+        let callbacks = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.callback_data = Some(callback_data);
+            std::mem::take(&mut inner.callbacks)
+        };
+        for callback in callbacks {
+            callback(callback_data);
+        }
+        None
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+}
+
+/// A helper pacing the redraws of a surface with its frame callbacks
+///
+/// Redraws are requested with [`request_redraw()`](FrameThrottle::request_redraw), and the draw
+/// closure is invoked when the previous frame callback of the surface was signaled, or right away
+/// for the first frame, as the compositor never signals frame callbacks for a surface which was
+/// never committed. Before each invocation of the closure, a frame callback is requested for the
+/// surface: the closure must commit the surface for it to be signaled.
+///
+/// The closure is given the callback data of the last signaled frame callback, which is `None`
+/// for the first frame.
+///
+/// ```no_run
+/// # fn redraw_loop(conn: &wayland_client::Connection, surface: wayland_client::protocol::wl_surface::WlSurface) {
+/// use wayland_client::fence::FrameThrottle;
+///
+/// let mut throttle = FrameThrottle::new(surface, |conn, surface, _timestamp| {
+///     /* draw */
+///     surface.commit(conn);
+/// });
+/// loop {
+///     /* redraw on some event */
+///     throttle.request_redraw(&mut conn.handle()).unwrap();
+///     conn.blocking_dispatch().unwrap();
+///     throttle.dispatch(&mut conn.handle()).unwrap();
+/// }
+/// # }
+/// ```
+pub struct FrameThrottle<F> {
+    surface: wl_surface::WlSurface,
+    draw: F,
+    pending: Option<Fence>,
+    redraw_requested: bool,
+    last_callback_data: Option<u32>,
+}
+
+impl<F> FrameThrottle<F>
+where
+    F: FnMut(&mut ConnectionHandle, &wl_surface::WlSurface, Option<u32>),
+{
+    /// Create a throttle drawing a surface with the given closure
+    pub fn new(surface: wl_surface::WlSurface, draw: F) -> FrameThrottle<F> {
+        FrameThrottle {
+            surface,
+            draw,
+            pending: None,
+            redraw_requested: false,
+            last_callback_data: None,
+        }
+    }
+
+    /// The surface drawn by this throttle
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.surface
+    }
+
+    /// Request a redraw of the surface
+    ///
+    /// The surface is drawn immediately if the compositor is ready for a new frame, otherwise it
+    /// will be drawn by [`dispatch()`](FrameThrottle::dispatch) once the pending frame callback is
+    /// signaled. Several requests before a redraw only give a single redraw.
+    ///
+    /// Returns whether the surface was drawn.
+    pub fn request_redraw(&mut self, conn: &mut ConnectionHandle) -> Result<bool, DispatchError> {
+        self.redraw_requested = true;
+        self.dispatch(conn)
+    }
+
+    /// Draw the surface if a redraw was requested and the compositor is ready for a new frame
+    ///
+    /// This is meant to be invoked after the events of the connection were read. Returns whether
+    /// the surface was drawn.
+    pub fn dispatch(&mut self, conn: &mut ConnectionHandle) -> Result<bool, DispatchError> {
+        if !self.redraw_requested {
+            return Ok(false);
+        }
+        if let Some(ref fence) = self.pending {
+            match fence.callback_data() {
+                Some(callback_data) => self.last_callback_data = Some(callback_data),
+                None => return Ok(false),
+            }
+        }
+        // The frame callback must be requested before the commit of the closure to apply to it.
+        // The previous one was destroyed by the server when it was signaled.
+        self.pending = Some(Fence::frame(conn, &self.surface)?);
+        self.redraw_requested = false;
+        (self.draw)(conn, &self.surface, self.last_callback_data);
+        Ok(true)
+    }
+
+    /// Whether this throttle waits for a frame callback before the next redraw
+    pub fn is_waiting(&self) -> bool {
+        self.pending.as_ref().map(|fence| !fence.is_signaled()).unwrap_or(false)
+    }
+
+    /// Stop waiting for the pending frame callback
+    ///
+    /// The compositor may never signal the frame callbacks of a surface which is not visible, or
+    /// which was not committed after its last redraw. This makes the next redraw happen
+    /// immediately, like the first one.
+    pub fn reset(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<F> std::fmt::Debug for FrameThrottle<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameThrottle")
+            .field("surface", &self.surface)
+            .field("pending", &self.pending)
+            .field("redraw_requested", &self.redraw_requested)
+            .field("last_callback_data", &self.last_callback_data)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,183 @@
+//! Integration of the connection into a GLib `MainContext`
+//!
+//! This module is available with the `glib` cargo feature. A [`GlibSource`] attaches a connection
+//! to a `MainContext` as a `GSource`, following the contract of [`EventSource`] in its phases:
+//! the read is prepared and the requests flushed when the context prepares the source, the socket
+//! is read when the context checks it, and the provided callback is invoked to dispatch the event
+//! queues when the context dispatches it.
+//!
+//! ```no_run
+//! # fn run<D: 'static>(conn: wayland_client::Connection, mut queue: wayland_client::EventQueue<D>, mut state: D) {
+//! use wayland_client::glib_source::GlibSource;
+//!
+//! // the source is removed from the context when dropped
+//! let _source = GlibSource::attach(conn, None, move |read| {
+//!     read.unwrap();
+//!     queue.dispatch_pending(&mut state).unwrap();
+//!     glib::Continue(true)
+//! })
+//! .unwrap();
+//! glib::MainLoop::new(None, false).run();
+//! # }
+//! ```
+
+use std::os::raw::c_int;
+
+use glib::{
+    ffi,
+    translate::{from_glib_full, ToGlibPtr},
+};
+use wayland_backend::client::WaylandError;
+
+use crate::{
+    poll::{EventSource, Readiness, WaylandSource},
+    Connection,
+};
+
+type Callback = Box<dyn FnMut(Result<usize, WaylandError>) -> glib::Continue>;
+
+struct Inner {
+    wayland: WaylandSource,
+    callback: Callback,
+    // the tag of the fd of the connection in the source
+    tag: ffi::gpointer,
+    // the result of the last read, or of a failure to prepare it, waiting to be dispatched
+    pending: Option<Result<usize, WaylandError>>,
+}
+
+// the layout of the source, whose beginning is the `GSource` as expected by GLib
+#[repr(C)]
+struct RawSource {
+    source: ffi::GSource,
+    inner: *mut Inner,
+}
+
+static SOURCE_FUNCS: ffi::GSourceFuncs = ffi::GSourceFuncs {
+    prepare: Some(prepare),
+    check: Some(check),
+    dispatch: Some(dispatch),
+    finalize: Some(finalize),
+    closure_callback: None,
+    closure_marshal: None,
+};
+
+/// A Wayland connection attached to a GLib `MainContext`
+///
+/// The source is removed from its context when this is dropped.
+#[derive(Debug)]
+pub struct GlibSource {
+    source: glib::Source,
+}
+
+impl GlibSource {
+    /// Attach a connection to a `MainContext`, or to the default one if `None`
+    ///
+    /// The callback is invoked from the context with the number of events read from the socket,
+    /// or the error that occured, and is expected to dispatch the event queues of the connection.
+    /// It is also invoked once right after the source is attached, to dispatch the events which
+    /// were already read. The source is removed if it returns `Continue(false)`.
+    ///
+    /// **Panic:** the context must not be owned by an other thread, as the callback is invoked
+    /// from the thread iterating it.
+    pub fn attach<F>(
+        conn: Connection,
+        context: Option<&glib::MainContext>,
+        callback: F,
+    ) -> Result<GlibSource, WaylandError>
+    where
+        F: FnMut(Result<usize, WaylandError>) -> glib::Continue + 'static,
+    {
+        let context = context.cloned().unwrap_or_else(glib::MainContext::default);
+        let _acquire =
+            context.acquire().expect("The main context is already owned by an other thread");
+        let wayland = WaylandSource::new(conn)?;
+        let fd = wayland.fd();
+        let inner = Box::into_raw(Box::new(Inner {
+            wayland,
+            callback: Box::new(callback),
+            tag: std::ptr::null_mut(),
+            pending: Some(Ok(0)),
+        }));
+        unsafe {
+            let raw = ffi::g_source_new(
+                &SOURCE_FUNCS as *const ffi::GSourceFuncs as *mut ffi::GSourceFuncs,
+                std::mem::size_of::<RawSource>() as u32,
+            );
+            (*(raw as *mut RawSource)).inner = inner;
+            (*inner).tag =
+                ffi::g_source_add_unix_fd(raw, fd, ffi::G_IO_IN | ffi::G_IO_ERR | ffi::G_IO_HUP);
+            ffi::g_source_attach(raw, context.to_glib_none().0);
+            Ok(GlibSource { source: from_glib_full(raw) })
+        }
+    }
+
+    /// The underlying GLib source
+    pub fn source(&self) -> &glib::Source {
+        &self.source
+    }
+}
+
+impl Drop for GlibSource {
+    fn drop(&mut self) {
+        self.source.destroy();
+    }
+}
+
+unsafe fn inner<'a>(source: *mut ffi::GSource) -> &'a mut Inner {
+    &mut *(*(source as *mut RawSource)).inner
+}
+
+unsafe extern "C" fn prepare(source: *mut ffi::GSource, timeout: *mut c_int) -> ffi::gboolean {
+    let inner = inner(source);
+    *timeout = -1;
+    if inner.pending.is_some() {
+        // skip the poll to dispatch right away
+        return ffi::GTRUE;
+    }
+    if let Err(e) = inner.wayland.before_poll() {
+        inner.pending = Some(Err(e));
+        return ffi::GTRUE;
+    }
+    ffi::GFALSE
+}
+
+unsafe extern "C" fn check(source: *mut ffi::GSource) -> ffi::gboolean {
+    let inner = inner(source);
+    if inner.pending.is_some() {
+        return ffi::GTRUE;
+    }
+    let condition = ffi::g_source_query_unix_fd(source, inner.tag);
+    let readiness = Readiness {
+        readable: condition & ffi::G_IO_IN != 0,
+        error: condition & (ffi::G_IO_ERR | ffi::G_IO_HUP) != 0,
+    };
+    // the prepared read is always completed, reading or cancelling it
+    match inner.wayland.after_poll(readiness) {
+        Ok(0) => ffi::GFALSE,
+        ret => {
+            inner.pending = Some(ret);
+            ffi::GTRUE
+        }
+    }
+}
+
+unsafe extern "C" fn dispatch(
+    source: *mut ffi::GSource,
+    _: ffi::GSourceFunc,
+    _: ffi::gpointer,
+) -> ffi::gboolean {
+    let inner = inner(source);
+    let ret = inner.pending.take().unwrap_or(Ok(0));
+    if (inner.callback)(ret).0 {
+        ffi::G_SOURCE_CONTINUE
+    } else {
+        ffi::G_SOURCE_REMOVE
+    }
+}
+
+unsafe extern "C" fn finalize(source: *mut ffi::GSource) {
+    let raw = source as *mut RawSource;
+    // dropping the inner state cancels any prepared read
+    drop(Box::from_raw((*raw).inner));
+    (*raw).inner = std::ptr::null_mut();
+}
@@ -1,14 +1,22 @@
 //! Helpers for listing and bindings globals
 
+use std::collections::BTreeMap;
 use std::ops::Range;
+use std::sync::Arc;
+
+use wayland_backend::{
+    client::{Handle, InvalidId, ObjectData, ObjectId},
+    protocol::Message,
+};
 
 use crate::{
-    protocol::wl_registry, ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch,
-    Proxy, QueueHandle,
+    protocol::{wl_fixes, wl_output, wl_registry, wl_seat, wl_shm},
+    Connection, ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError,
+    Proxy, QueueHandle, WEnum,
 };
 
 /// Description of an advertized global
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GlobalDescription {
     /// identifier of this global
     pub name: u32,
@@ -93,6 +101,40 @@ impl GlobalList {
         GlobalList { globals: Vec::new() }
     }
 
+    /// Create a list from the registry maintained by the backend
+    ///
+    /// This enables the registry of the backend (see
+    /// [`Handle::enable_registry_cache()`](wayland_backend::client::Handle::enable_registry_cache)),
+    /// and returns it with a list of the globals it received so far. This registry is shared by
+    /// all the components using the connection, and is not dispatched to a `GlobalList`: the list
+    /// is updated with [`refresh_from_cache()`](GlobalList::refresh_from_cache), once the events of
+    /// the connection have been dispatched.
+    pub fn from_registry_cache(
+        conn: &mut ConnectionHandle<'_>,
+    ) -> Result<(GlobalList, wl_registry::WlRegistry), InvalidId> {
+        let registry_id = conn.inner.handle().enable_registry_cache()?;
+        let registry = wl_registry::WlRegistry::from_id(conn, registry_id)?;
+        let mut list = GlobalList::new();
+        list.refresh_from_cache(conn);
+        Ok((list, registry))
+    }
+
+    /// Replace the contents of the list with the globals of the registry maintained by the backend
+    ///
+    /// The list is left untouched if that registry was not enabled.
+    pub fn refresh_from_cache(&mut self, conn: &mut ConnectionHandle<'_>) {
+        if let Some(globals) = conn.inner.handle().globals() {
+            self.globals = globals
+                .into_iter()
+                .map(|global| GlobalDescription {
+                    name: global.name,
+                    interface: global.interface,
+                    version: global.version,
+                })
+                .collect();
+        }
+    }
+
     /// Access the list of currently advertized globals
     pub fn list(&self) -> &[GlobalDescription] {
         &self.globals
@@ -102,7 +144,11 @@ impl GlobalList {
     ///
     /// You can specify the requested interface as type parameter, and the version range. You
     /// also need to provide the user data value that will be set for the newly created object.
-    pub fn bind<I: Proxy + 'static, D: Dispatch<I> + 'static>(
+    ///
+    /// The global is bound with the version given by [`negotiate_version()`]: the highest version
+    /// supported both by the server and by the requested range.
+    #[track_caller]
+    pub fn bind<I: Proxy + 'static, D: Dispatch<I>>(
         &self,
         conn: &mut ConnectionHandle<'_>,
         qh: &QueueHandle<D>,
@@ -110,25 +156,167 @@ impl GlobalList {
         version: Range<u32>,
         user_data: <D as Dispatch<I>>::UserData,
     ) -> Result<I, BindError> {
-        for desc in &self.globals {
-            if desc.interface != I::interface().name {
-                continue;
-            }
+        let desc = self
+            .globals
+            .iter()
+            .find(|desc| desc.interface == I::interface().name)
+            .ok_or(BindError::MissingGlobal { interface: I::interface().name })?;
+        let version = negotiate_version::<I>(desc.version, version)?;
+        Ok(registry
+            .bind::<I, D>(conn, desc.name, version, qh, user_data)
+            .expect("invalid wl_registry"))
+    }
 
-            if version.contains(&desc.version) {
-                return Ok(registry
-                    .bind::<I, D>(conn, desc.name, desc.version, qh, user_data)
-                    .expect("invalid wl_registry"));
-            } else {
-                return Err(BindError::WrongVersion {
-                    interface: I::interface().name,
-                    requested: version,
-                    got: desc.version,
-                });
+    /// Destroy a registry, if the compositor supports it
+    ///
+    /// With the core protocol, a `wl_registry` cannot be destroyed and keeps receiving the globals
+    /// advertized by the compositor for the whole lifetime of the connection. If the `wl_fixes`
+    /// global is in this list, it is bound to destroy the registry, and released right away. The
+    /// registry is then destroyed client-side, and its id is reused once the server acknowledges
+    /// its destruction.
+    ///
+    /// Returns whether the registry was destroyed, it is left untouched otherwise.
+    pub fn destroy_registry(
+        &self,
+        conn: &mut ConnectionHandle<'_>,
+        registry: &wl_registry::WlRegistry,
+    ) -> bool {
+        let desc = match self
+            .globals
+            .iter()
+            .find(|desc| desc.interface == wl_fixes::WlFixes::interface().name)
+        {
+            Some(desc) => desc,
+            None => return false,
+        };
+        let request =
+            wl_registry::Request::Bind { name: desc.name, id: (wl_fixes::WlFixes::interface(), 1) };
+        let fixes = match conn.send_request(registry, request, Some(Arc::new(FixesData))) {
+            Ok(id) => {
+                wl_fixes::WlFixes::from_id(conn, id).expect("Newly created object is invalid")
             }
+            Err(_) => return false,
+        };
+        fixes.destroy_registry(conn, registry);
+        let _ = conn.inner.handle().release_object(registry.id());
+        fixes.destroy(conn);
+        true
+    }
+
+    /// Compare the globals of this list with the ones of a previous list
+    ///
+    /// This is meant to be used when re-binding the globals after reconnecting to a compositor:
+    /// given the list of globals of the previous connection, it reports all interfaces whose
+    /// version changed, so that the app can disable (or enable) the features depending on them
+    /// instead of sending requests the compositor does not support anymore.
+    ///
+    /// If several globals have the same interface, their highest version is considered. The
+    /// changes are sorted by interface name.
+    pub fn capability_changes(&self, previous: &GlobalList) -> Vec<CapabilityChange> {
+        let old_versions = previous.max_versions();
+        let new_versions = self.max_versions();
+
+        let mut interfaces = old_versions.keys().chain(new_versions.keys()).collect::<Vec<_>>();
+        interfaces.sort_unstable();
+        interfaces.dedup();
+
+        interfaces
+            .into_iter()
+            .filter_map(|&interface| {
+                let old_version = old_versions.get(interface).copied();
+                let new_version = new_versions.get(interface).copied();
+                if old_version == new_version {
+                    None
+                } else {
+                    Some(CapabilityChange { interface: interface.into(), old_version, new_version })
+                }
+            })
+            .collect()
+    }
+
+    fn max_versions(&self) -> BTreeMap<&str, u32> {
+        let mut versions = BTreeMap::new();
+        for desc in &self.globals {
+            let version = versions.entry(desc.interface.as_str()).or_insert(desc.version);
+            *version = (*version).max(desc.version);
         }
+        versions
+    }
+}
 
-        Err(BindError::MissingGlobal { interface: I::interface().name })
+/// Negotiate the version with which to bind a global
+///
+/// Given the version advertized by the server for the interface `I` and the range of versions
+/// supported by the client, this returns the highest version supported by both, also capped to the
+/// version of `I` known by these bindings. An error is returned if the advertized version is
+/// lower than the supported range.
+pub fn negotiate_version<I: Proxy>(
+    advertized: u32,
+    supported: Range<u32>,
+) -> Result<u32, BindError> {
+    let interface = I::interface();
+    let max = (supported.end.saturating_sub(1)).min(interface.version);
+    if advertized < supported.start || supported.start > max {
+        return Err(BindError::WrongVersion {
+            interface: interface.name,
+            requested: supported,
+            got: advertized,
+        });
+    }
+    Ok(advertized.min(max))
+}
+
+/// A change of the version of an interface advertized by the server
+///
+/// See [`GlobalList::capability_changes()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityChange {
+    /// interface name
+    pub interface: String,
+    /// previously advertized version, `None` if the interface was not advertized
+    pub old_version: Option<u32>,
+    /// newly advertized version, `None` if the interface is no longer advertized
+    pub new_version: Option<u32>,
+}
+
+impl CapabilityChange {
+    /// Check whether this change removes capabilities
+    ///
+    /// This is the case if the interface is no longer advertized or with a lower version.
+    pub fn is_downgrade(&self) -> bool {
+        match (self.old_version, self.new_version) {
+            (Some(old), Some(new)) => new < old,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// The support of a set of interfaces by the compositor
+///
+/// See [`Connection::probe_protocols()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolSupport {
+    versions: Vec<(String, Option<u32>)>,
+}
+
+impl ProtocolSupport {
+    /// The version with which an interface is advertized
+    ///
+    /// If several globals have this interface, their highest version is given. `None` is returned
+    /// if the interface is not advertized, or was not probed.
+    pub fn version(&self, interface: &str) -> Option<u32> {
+        self.versions.iter().find(|(name, _)| name == interface).and_then(|&(_, version)| version)
+    }
+
+    /// Check whether an interface is advertized
+    pub fn is_supported(&self, interface: &str) -> bool {
+        self.version(interface).is_some()
+    }
+
+    /// Iterate over the probed interfaces and their versions, in the order they were requested
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<u32>)> {
+        self.versions.iter().map(|(name, version)| (name.as_str(), *version))
     }
 }
 
@@ -141,7 +329,8 @@ pub enum BindError {
         /// The requested interface
         interface: &'static str,
     },
-    /// The version advertized by the server did not fit in the requested range
+    /// The version advertized by the server is lower than the requested range, or the requested
+    /// range contains no version known for this interface
     #[error("Global {interface} has version {got}, which is outside of the requested range ({requested:?})")]
     WrongVersion {
         /// The requested interface
@@ -152,3 +341,270 @@ pub enum BindError {
         got: u32,
     },
 }
+
+/// An interface whose globals describe their state by sending events once bound
+///
+/// This is used by [`Connection::fetch_global_contents()`] to collect a snapshot of the state of
+/// these globals. It is implemented for `wl_output`, `wl_seat` and `wl_shm`.
+pub trait GlobalContents: Proxy + 'static {
+    /// The snapshot of the state of a global
+    type Contents: Default + Send + Sync + 'static;
+
+    /// Update the snapshot with an event of the global
+    fn update(contents: &mut Self::Contents, event: Self::Event);
+
+    /// Release the object once its contents are collected, if the interface allows it
+    fn release(&self, _conn: &mut ConnectionHandle) {}
+}
+
+/// The state of a `wl_output`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputInfo {
+    /// The name of the output, since version 4
+    pub name: Option<String>,
+    /// The description of the output, since version 4
+    pub description: Option<String>,
+    /// The manufacturer of the output
+    pub make: String,
+    /// The model of the output
+    pub model: String,
+    /// The position of the output in the global compositor space
+    pub location: (i32, i32),
+    /// The physical size of the output in millimeters
+    pub physical_size: (i32, i32),
+    /// The subpixel orientation of the output
+    pub subpixel: Option<WEnum<wl_output::Subpixel>>,
+    /// The transform applied to the buffers displayed on the output
+    pub transform: Option<WEnum<wl_output::Transform>>,
+    /// The scale factor of the output
+    pub scale: i32,
+    /// The modes of the output
+    pub modes: Vec<OutputMode>,
+}
+
+/// A mode of a `wl_output`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputMode {
+    /// The size of the mode in hardware units
+    pub dimensions: (i32, i32),
+    /// The vertical refresh rate in mHz
+    pub refresh_rate: i32,
+    /// Whether this is the current mode
+    pub current: bool,
+    /// Whether this is the preferred mode
+    pub preferred: bool,
+}
+
+impl GlobalContents for wl_output::WlOutput {
+    type Contents = OutputInfo;
+
+    fn update(info: &mut OutputInfo, event: wl_output::Event) {
+        match event {
+            wl_output::Event::Geometry {
+                x,
+                y,
+                physical_width,
+                physical_height,
+                subpixel,
+                make,
+                model,
+                transform,
+            } => {
+                info.location = (x, y);
+                info.physical_size = (physical_width, physical_height);
+                info.subpixel = Some(subpixel);
+                info.make = make;
+                info.model = model;
+                info.transform = Some(transform);
+            }
+            wl_output::Event::Mode { flags, width, height, refresh } => {
+                let flags = match flags {
+                    WEnum::Value(flags) => flags,
+                    WEnum::Unknown(raw) => wl_output::Mode::from_bits_truncate(raw),
+                };
+                let current = flags.contains(wl_output::Mode::Current);
+                if current {
+                    for mode in &mut info.modes {
+                        mode.current = false;
+                    }
+                }
+                info.modes.push(OutputMode {
+                    dimensions: (width, height),
+                    refresh_rate: refresh,
+                    current,
+                    preferred: flags.contains(wl_output::Mode::Preferred),
+                });
+            }
+            wl_output::Event::Scale { factor } => info.scale = factor,
+            wl_output::Event::Name { name } => info.name = Some(name),
+            wl_output::Event::Description { description } => info.description = Some(description),
+            wl_output::Event::Done => {}
+        }
+    }
+
+    fn release(&self, conn: &mut ConnectionHandle) {
+        if self.version() >= 3 {
+            wl_output::WlOutput::release(self, conn);
+        }
+    }
+}
+
+/// The state of a `wl_seat`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeatInfo {
+    /// The name of the seat, since version 2
+    pub name: Option<String>,
+    /// The capabilities of the seat
+    pub capabilities: wl_seat::Capability,
+}
+
+impl Default for SeatInfo {
+    fn default() -> SeatInfo {
+        SeatInfo { name: None, capabilities: wl_seat::Capability::empty() }
+    }
+}
+
+impl GlobalContents for wl_seat::WlSeat {
+    type Contents = SeatInfo;
+
+    fn update(info: &mut SeatInfo, event: wl_seat::Event) {
+        match event {
+            wl_seat::Event::Capabilities { capabilities } => {
+                info.capabilities = match capabilities {
+                    WEnum::Value(caps) => caps,
+                    WEnum::Unknown(raw) => wl_seat::Capability::from_bits_truncate(raw),
+                };
+            }
+            wl_seat::Event::Name { name } => info.name = Some(name),
+        }
+    }
+
+    fn release(&self, conn: &mut ConnectionHandle) {
+        if self.version() >= 5 {
+            wl_seat::WlSeat::release(self, conn);
+        }
+    }
+}
+
+/// The state of a `wl_shm`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShmInfo {
+    /// The pixel formats supported by the compositor, in addition to `argb8888` and `xrgb8888`
+    pub formats: Vec<WEnum<wl_shm::Format>>,
+}
+
+impl GlobalContents for wl_shm::WlShm {
+    type Contents = ShmInfo;
+
+    fn update(info: &mut ShmInfo, event: wl_shm::Event) {
+        match event {
+            wl_shm::Event::Format { format } => info.formats.push(format),
+        }
+    }
+}
+
+// The data of the `wl_fixes` objects bound to destroy a registry, this interface has no event
+struct FixesData;
+
+impl ObjectData for FixesData {
+    fn event(self: Arc<Self>, _: &mut Handle, _: Message<ObjectId>) -> Option<Arc<dyn ObjectData>> {
+        None
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+}
+
+// The state of the event queue used by `Connection::fetch_global_contents()`
+struct FetchState<I: GlobalContents> {
+    globals: GlobalList,
+    contents: Vec<I::Contents>,
+}
+
+impl<I: GlobalContents> AsMut<GlobalList> for FetchState<I> {
+    fn as_mut(&mut self) -> &mut GlobalList {
+        &mut self.globals
+    }
+}
+
+impl<I: GlobalContents> Dispatch<wl_registry::WlRegistry> for FetchState<I> {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        proxy: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        data: &(),
+        connhandle: &mut ConnectionHandle,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        <GlobalList as DelegateDispatch<wl_registry::WlRegistry, Self>>::event(
+            self, proxy, event, data, connhandle, qhandle,
+        )
+    }
+}
+
+impl<I: GlobalContents> Dispatch<I> for FetchState<I> {
+    type UserData = usize;
+
+    fn event(
+        &mut self,
+        _: &I,
+        event: I::Event,
+        index: &usize,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<Self>,
+    ) {
+        I::update(&mut self.contents[*index], event);
+    }
+}
+
+// Returns the list of globals along with the snapshots
+pub(crate) fn fetch_global_contents<I: GlobalContents>(
+    conn: &Connection,
+) -> Result<(GlobalList, Vec<I::Contents>), DispatchError> {
+    let mut queue = conn.new_event_queue::<FetchState<I>>();
+    let qh = queue.handle();
+    let mut state = FetchState::<I> { globals: GlobalList::new(), contents: Vec::new() };
+
+    let display = conn.handle().display();
+    let registry = display.get_registry(&mut conn.handle(), &qh, ())?;
+    conn.roundtrip()?;
+    queue.dispatch_pending(&mut state)?;
+
+    // bind all the globals of the interface, and collect the events they send in response
+    let mut objects = Vec::new();
+    for desc in state.globals.list().iter().filter(|desc| desc.interface == I::interface().name) {
+        let version = negotiate_version::<I>(desc.version, 1..u32::MAX)
+            .expect("the version range covers all versions");
+        let index = objects.len();
+        objects.push(registry.bind::<I, _>(&mut conn.handle(), desc.name, version, &qh, index)?);
+    }
+    state.contents = objects.iter().map(|_| I::Contents::default()).collect();
+    conn.roundtrip()?;
+    queue.dispatch_pending(&mut state)?;
+
+    for object in &objects {
+        object.release(&mut conn.handle());
+    }
+    state.globals.destroy_registry(&mut conn.handle(), &registry);
+    Ok((state.globals, state.contents))
+}
+
+pub(crate) fn probe_protocols(
+    conn: &Connection,
+    interfaces: &[&str],
+) -> Result<ProtocolSupport, DispatchError> {
+    let mut queue = conn.new_event_queue::<GlobalList>();
+    let mut globals = GlobalList::new();
+
+    let display = conn.handle().display();
+    let registry = display.get_registry(&mut conn.handle(), &queue.handle(), ())?;
+    conn.roundtrip()?;
+    queue.dispatch_pending(&mut globals)?;
+    globals.destroy_registry(&mut conn.handle(), &registry);
+
+    let advertized = globals.max_versions();
+    let versions =
+        interfaces.iter().map(|&name| (name.to_owned(), advertized.get(name).copied())).collect();
+    Ok(ProtocolSupport { versions })
+}
@@ -7,25 +7,44 @@ use wayland_backend::{
     protocol::{Interface, Message},
 };
 
+pub mod backlog;
 mod conn;
+pub mod connection_set;
+pub mod data_device;
+#[cfg(feature = "describe")]
+pub mod describe;
+pub mod dynamic;
 mod event_queue;
+pub mod fence;
+#[cfg(feature = "glib")]
+pub mod glib_source;
 pub mod globals;
+pub mod pipe;
+pub mod poll;
+pub mod seat;
+pub mod serial;
+pub mod shm;
+pub mod timecode;
 
 /// Backend reexports
 pub mod backend {
     pub use wayland_backend::client::{
-        Backend, Handle, InvalidId, NoWaylandLib, ObjectData, ObjectId, ReadEventsGuard,
-        WaylandError,
+        Backend, ExclusiveReadEventsGuard, Handle, InvalidId, NoWaylandLib, ObjectData, ObjectId,
+        Operation, ReadEventsGuard, WaylandError,
     };
     pub use wayland_backend::protocol;
     pub use wayland_backend::smallvec;
 }
 
 pub use wayland_backend::protocol::WEnum;
+pub use wayland_backend::region;
 
-pub use conn::{Connection, ConnectionHandle};
+pub use conn::{ConnectError, Connection, ConnectionHandle, Sender};
+#[cfg(feature = "describe")]
+pub use describe::describe_compositor;
 pub use event_queue::{
-    DelegateDispatch, DelegateDispatchBase, Dispatch, EventQueue, QueueHandle, QueueProxyData,
+    AnyEventQueue, DelegateDispatch, DelegateDispatchBase, Dispatch, EventQueue, EventTiming,
+    QueueHandle, QueueProxyData, QueueView,
 };
 
 /// Generated protocol definitions
@@ -39,7 +58,7 @@ pub mod protocol {
     pub mod __interfaces {
         wayland_scanner::generate_interfaces!("wayland.xml");
     }
-    wayland_scanner::generate_client_code!("wayland.xml");
+    wayland_scanner::generate_client_code!("wayland.xml", borrowed_events);
 }
 
 /// Trait representing a Wayland interface
@@ -61,6 +80,27 @@ pub trait Proxy: Sized {
     /// Access the user-data associated with this object
     fn data<U: Send + Sync + 'static>(&self) -> Option<&U>;
 
+    /// Check if this object is still alive
+    ///
+    /// Requests sent to a dead object fail with [`DispatchError::DeadObject`].
+    fn is_alive(&self, conn: &mut ConnectionHandle) -> bool {
+        conn.object_info(self.id()).is_ok()
+    }
+
+    /// Discard the remaining events of this object
+    ///
+    /// The events of this object are from now on dropped without invoking its [`Dispatch`]
+    /// implementation, including the ones already waiting in its event queue, and the file
+    /// descriptors they carry are closed. The objects they create are ignored the same way.
+    ///
+    /// This is meant for tearing down objects quickly, typically right before sending their
+    /// destructor request, without handling the events the server sends until it acknowledges
+    /// their destruction. The proxies of this object created afterwards with
+    /// [`from_id()`](Proxy::from_id) don't have any user data.
+    fn ignore_remaining_events(&self, conn: &mut ConnectionHandle) {
+        crate::event_queue::ignore_remaining_events(conn, self.id())
+    }
+
     /// Create an object proxy from its ID
     ///
     /// Returns an error this the provided object ID does not correspond to
@@ -104,4 +144,28 @@ pub enum DispatchError {
     /// The backend generated an error
     #[error("Backend error: {0}")]
     Backend(#[from] WaylandError),
+    /// An object involved in a request was already destroyed
+    #[error("Object {id} of interface {interface} is dead")]
+    DeadObject {
+        /// The interface of the dead object
+        interface: &'static str,
+        /// The ID of the dead object
+        id: ObjectId,
+    },
+    /// An event created an object, but the `Dispatch` implementation handling its parent did not
+    /// provide the data of the new object
+    ///
+    /// The [`Dispatch::event_created_child()`] method of this implementation needs to be overridden,
+    /// see the [`event_created_child!`] macro.
+    #[error("Missing event_created_child() in Dispatch<{interface}> for {state}, required by event {interface}.{event} of an object created at {created_at}")]
+    MissingChildData {
+        /// The interface of the parent object
+        interface: &'static str,
+        /// The name of the object-creating event
+        event: &'static str,
+        /// The type name of the state implementing `Dispatch`
+        state: &'static str,
+        /// The location of the code that created the parent object
+        created_at: &'static std::panic::Location<'static>,
+    },
 }
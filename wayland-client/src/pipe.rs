@@ -0,0 +1,171 @@
+//! Transfers of data through pipes, off the dispatching thread
+//!
+//! Several protocols transfer data between clients through a pipe whose fd is sent along with a
+//! request or an event: the selection and drag-and-drop of `wl_data_device`, or the primary
+//! selection protocol. The peer at the other end may be slow or even never close the pipe, so these
+//! transfers must not happen on the thread dispatching the events.
+//!
+//! [`read_pipe()`] and [`write_pipe()`] run a transfer on a pool of background threads, and return
+//! a [`Future`] resolving once it is complete. Threads are spawned as needed, and exit after some
+//! time without a transfer to run.
+//!
+//! ```no_run
+//! # async fn run(fd: std::os::unix::io::RawFd) {
+//! // a text selection is not expected to exceed a few megabytes
+//! let text = wayland_client::pipe::read_pipe(fd, Some(16 << 20)).await.unwrap();
+//! # }
+//! ```
+
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, Read, Write},
+    os::unix::io::{FromRawFd, RawFd},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_channel::oneshot;
+
+/// How long a thread of the pool waits for a new transfer before exiting
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Pool {
+    sender: Mutex<mpsc::Sender<Job>>,
+    receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+    idle: Arc<AtomicUsize>,
+}
+
+lazy_static::lazy_static! {
+    static ref POOL: Pool = {
+        let (sender, receiver) = mpsc::channel();
+        Pool {
+            sender: Mutex::new(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+            idle: Arc::new(AtomicUsize::new(0)),
+        }
+    };
+}
+
+impl Pool {
+    fn run(&self, job: Job) {
+        // the number of idle threads is decremented for each job sent, so that it never exceeds
+        // the number of threads actually waiting for one
+        let claimed = self
+            .idle
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |idle| idle.checked_sub(1))
+            .is_ok();
+        if claimed {
+            let _ = self.sender.lock().unwrap().send(job);
+        } else {
+            let receiver = self.receiver.clone();
+            let idle = self.idle.clone();
+            std::thread::Builder::new()
+                .name("wayland-pipe".into())
+                .spawn(move || worker(job, &receiver, &idle))
+                .expect("Failed to spawn a pipe transfer thread");
+        }
+    }
+}
+
+fn worker(first_job: Job, receiver: &Mutex<mpsc::Receiver<Job>>, idle: &AtomicUsize) {
+    first_job();
+    loop {
+        idle.fetch_add(1, Ordering::AcqRel);
+        let job = receiver.lock().unwrap().recv_timeout(IDLE_TIMEOUT);
+        let job = match job {
+            Ok(job) => job,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if idle
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |idle| idle.checked_sub(1))
+                    .is_ok()
+                {
+                    return;
+                }
+                // a job was sent in the meantime, expecting this thread to be waiting for it
+                match receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        };
+        job();
+    }
+}
+
+/// A transfer through a pipe, running on a background thread
+///
+/// It resolves once the transfer is complete, to the data read for [`read_pipe()`]. Dropping it
+/// does not cancel the transfer.
+#[derive(Debug)]
+pub struct PipeTransfer<T> {
+    rx: oneshot::Receiver<io::Result<T>>,
+}
+
+impl<T> Future for PipeTransfer<T> {
+    type Output = io::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx).map(|ret| {
+            ret.unwrap_or_else(|_| {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "transfer aborted"))
+            })
+        })
+    }
+}
+
+/// Read a pipe to completion on a background thread
+///
+/// This takes ownership of the fd, which is closed once the transfer is over. The transfer fails
+/// with an [`InvalidData`](io::ErrorKind::InvalidData) error if the peer writes more than
+/// `max_size` bytes.
+pub fn read_pipe(fd: RawFd, max_size: Option<usize>) -> PipeTransfer<Vec<u8>> {
+    let file = unsafe { File::from_raw_fd(fd) };
+    let (tx, rx) = oneshot::channel();
+    POOL.run(Box::new(move || {
+        let _ = tx.send(read_to_end(file, max_size));
+    }));
+    PipeTransfer { rx }
+}
+
+fn read_to_end(file: File, max_size: Option<usize>) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    match max_size {
+        Some(max_size) => {
+            // read one byte past the limit to detect the data exceeding it
+            file.take(max_size as u64 + 1).read_to_end(&mut contents)?;
+            if contents.len() > max_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("the data exceeds {} bytes", max_size),
+                ));
+            }
+        }
+        None => {
+            let mut file = file;
+            file.read_to_end(&mut contents)?;
+        }
+    }
+    Ok(contents)
+}
+
+/// Write some data to a pipe on a background thread
+///
+/// This takes ownership of the fd, which is closed once all the data is written.
+pub fn write_pipe(fd: RawFd, data: impl Into<Arc<[u8]>>) -> PipeTransfer<()> {
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    let data = data.into();
+    let (tx, rx) = oneshot::channel();
+    POOL.run(Box::new(move || {
+        let _ = tx.send(file.write_all(&data));
+    }));
+    PipeTransfer { rx }
+}
@@ -0,0 +1,125 @@
+//! Integration of the connection into event loops
+//!
+//! Reading the Wayland socket from an event loop requires a precise ordering of operations, to
+//! synchronize with the other threads and libraries reading the same socket: a read must be
+//! prepared and the outgoing requests flushed before polling the socket, and the prepared read must
+//! then be either performed or cancelled.
+//!
+//! The [`EventSource`] trait describes this contract, so that integrations with `mio`, `polling`,
+//! the glib `MainContext` or a custom `epoll` loop all follow it the same way. [`WaylandSource`]
+//! implements it for a [`Connection`]. An iteration of the event loop goes as follows:
+//!
+//! 1. dispatch the pending events of the event queues, using
+//!    [`EventQueue::dispatch_pending()`](crate::EventQueue::dispatch_pending)
+//! 2. invoke [`before_poll()`](EventSource::before_poll)
+//! 3. poll the fd given by [`fd()`](EventSource::fd) for readability, along with the other
+//!    sources of the loop
+//! 4. invoke [`after_poll()`](EventSource::after_poll) with the readiness of the fd, even if it is
+//!    not ready
+//!
+//! ```no_run
+//! use wayland_client::poll::{EventSource, Readiness, WaylandSource};
+//! # fn run<D>(conn: wayland_client::Connection, mut queue: wayland_client::EventQueue<D>, state: &mut D) {
+//! let mut source = WaylandSource::new(conn).unwrap();
+//! loop {
+//!     queue.dispatch_pending(state).unwrap();
+//!     source.before_poll().unwrap();
+//!     let mut fds = [nix::poll::PollFd::new(source.fd(), nix::poll::PollFlags::POLLIN)];
+//!     let readable = nix::poll::poll(&mut fds, 1000).unwrap() > 0;
+//!     source.after_poll(Readiness { readable, error: false }).unwrap();
+//! }
+//! # }
+//! ```
+
+use std::os::unix::io::RawFd;
+
+use wayland_backend::client::{ReadEventsGuard, WaylandError};
+
+use crate::Connection;
+
+/// The readiness of the fd of an [`EventSource`], as reported by the event loop
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Readiness {
+    /// The fd is ready for reading
+    pub readable: bool,
+    /// The fd is in an error or hangup state
+    pub error: bool,
+}
+
+/// The contract for polling a Wayland connection from an event loop
+///
+/// See the [module documentation](self) for the expected order of the calls.
+pub trait EventSource {
+    /// The fd to poll for readability
+    fn fd(&self) -> RawFd;
+
+    /// Prepare for polling the fd
+    ///
+    /// This flushes the outgoing requests and prepares a read of the socket, which must be
+    /// completed by [`after_poll()`](EventSource::after_poll). Requests which could not be
+    /// flushed because the socket is full are sent on the next call.
+    fn before_poll(&mut self) -> Result<(), WaylandError>;
+
+    /// Complete the read prepared by [`before_poll()`](EventSource::before_poll)
+    ///
+    /// The socket is read if the fd is readable or in an error state, and the read is cancelled
+    /// otherwise. Returns the number of events read, which then need to be dispatched from their
+    /// event queues.
+    fn after_poll(&mut self, readiness: Readiness) -> Result<usize, WaylandError>;
+}
+
+/// An [`EventSource`] reading a [`Connection`]
+#[derive(Debug)]
+pub struct WaylandSource {
+    conn: Connection,
+    fd: RawFd,
+    guard: Option<ReadEventsGuard>,
+}
+
+impl WaylandSource {
+    /// Create a source for a connection
+    pub fn new(conn: Connection) -> Result<WaylandSource, WaylandError> {
+        let fd = conn.prepare_read()?.connection_fd();
+        Ok(WaylandSource { conn, fd, guard: None })
+    }
+
+    /// The connection of this source
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl EventSource for WaylandSource {
+    fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    fn before_poll(&mut self) -> Result<(), WaylandError> {
+        // a read prepared by a previous call without a matching `after_poll()` is cancelled
+        self.guard = None;
+        match self.conn.flush() {
+            Ok(()) => {}
+            Err(e) if e.is_would_block() => {}
+            Err(e) => return Err(e),
+        }
+        self.guard = Some(self.conn.prepare_read()?);
+        Ok(())
+    }
+
+    fn after_poll(&mut self, readiness: Readiness) -> Result<usize, WaylandError> {
+        let guard = match self.guard.take() {
+            Some(guard) => guard,
+            None => return Ok(0),
+        };
+        if !(readiness.readable || readiness.error) {
+            // dropping the guard cancels the read
+            return Ok(0);
+        }
+        match guard.read() {
+            Ok(n) => Ok(n),
+            // the events were read by an other thread in the meantime
+            Err(e) if e.is_would_block() => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
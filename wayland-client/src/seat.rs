@@ -0,0 +1,970 @@
+//! Helpers for handling the input devices of seats
+//!
+//! The [`SeatState`] can be used as a [`DelegateDispatch`](crate::DelegateDispatch) target for the
+//! `wl_seat`, `wl_pointer`, `wl_keyboard` and `wl_touch` interfaces. It creates and releases the
+//! input devices of each seat according to its capabilities, tracks which surface has the focus of
+//! each device, and translates the events of all these devices into a single stream of
+//! [`InputEvent`]s.
+//!
+//! With the `xkbcommon` cargo feature, the keymaps sent by the compositor are loaded with
+//! `libxkbcommon`, and key events additionally carry the keysym and text they produce.
+//!
+//! ```no_run
+//! use wayland_client::{delegate_dispatch, globals::GlobalList, protocol::{wl_registry, wl_seat, wl_pointer, wl_keyboard, wl_touch}, seat::{InputEvent, SeatState}};
+//!
+//! struct App {
+//!     globals: GlobalList,
+//!     seats: SeatState,
+//! }
+//!
+//! impl AsMut<GlobalList> for App {
+//!     fn as_mut(&mut self) -> &mut GlobalList {
+//!         &mut self.globals
+//!     }
+//! }
+//!
+//! impl AsMut<SeatState> for App {
+//!     fn as_mut(&mut self) -> &mut SeatState {
+//!         &mut self.seats
+//!     }
+//! }
+//!
+//! delegate_dispatch!(App: [wl_registry::WlRegistry] => GlobalList);
+//! delegate_dispatch!(App: [wl_seat::WlSeat, wl_pointer::WlPointer, wl_keyboard::WlKeyboard, wl_touch::WlTouch] => SeatState);
+//!
+//! # fn run(conn: &mut wayland_client::ConnectionHandle, qh: &wayland_client::QueueHandle<App>, registry: &wl_registry::WlRegistry, app: &mut App) {
+//! // once the globals are known
+//! app.seats.bind_seats(conn, qh, registry, &app.globals).unwrap();
+//!
+//! // after each dispatch of the event queue
+//! for event in app.seats.take_events() {
+//!     if let InputEvent::PointerButton { surface, button, .. } = event {
+//!         /* ... */
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::os::unix::io::RawFd;
+
+#[cfg(feature = "xkbcommon")]
+use std::os::unix::io::{FromRawFd, OwnedFd};
+
+#[cfg(feature = "xkbcommon")]
+use xkbcommon::xkb;
+
+use crate::{
+    globals::GlobalList,
+    protocol::{wl_keyboard, wl_pointer, wl_registry, wl_seat, wl_surface, wl_touch},
+    serial::SerialTracker,
+    ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError, Proxy,
+    QueueHandle, WEnum,
+};
+
+/// An input event, as reported by [`SeatState::take_events()`]
+///
+/// Positions are in surface-local coordinates.
+#[derive(Debug, Clone)]
+pub enum InputEvent {
+    /// The capabilities of a seat changed
+    ///
+    /// The input devices of the seat have already been created or released accordingly.
+    Capabilities {
+        /// the seat
+        seat: wl_seat::WlSeat,
+        /// its new capabilities
+        capabilities: wl_seat::Capability,
+    },
+    /// The pointer entered a surface
+    PointerEnter {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface now focused by the pointer
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// position of the pointer
+        position: (f64, f64),
+    },
+    /// The pointer left a surface
+    PointerLeave {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface previously focused by the pointer
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+    },
+    /// The pointer moved
+    PointerMotion {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface focused by the pointer
+        surface: wl_surface::WlSurface,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// new position of the pointer
+        position: (f64, f64),
+    },
+    /// A button of the pointer was pressed or released
+    PointerButton {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface focused by the pointer
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// the button, as a linux evdev code
+        button: u32,
+        /// whether the button was pressed or released
+        state: WEnum<wl_pointer::ButtonState>,
+    },
+    /// The pointer scrolled
+    ///
+    /// Since version 5 of `wl_seat`, the scrolling of both axes during a frame of the pointer is
+    /// reported as a single event. With older versions, each axis is reported separately.
+    PointerAxis {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface focused by the pointer
+        surface: wl_surface::WlSurface,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// the horizontal scrolling
+        horizontal: AxisScroll,
+        /// the vertical scrolling
+        vertical: AxisScroll,
+        /// the device that scrolled, if the compositor advertized it
+        source: Option<WEnum<wl_pointer::AxisSource>>,
+    },
+    /// A hold gesture began on the pointer
+    ///
+    /// Hold gestures are reported by the `zwp_pointer_gestures_v1` protocol extension, see
+    /// [`SeatState::push_event()`].
+    PointerHoldBegin {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface focused by the pointer
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// number of fingers holding the touchpad
+        fingers: u32,
+    },
+    /// A hold gesture ended
+    PointerHoldEnd {
+        /// the seat of the pointer
+        seat: wl_seat::WlSeat,
+        /// the surface of the gesture
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// whether the gesture was cancelled rather than ended by lifting the fingers
+        cancelled: bool,
+    },
+    /// The keyboard focus entered a surface
+    KeyboardEnter {
+        /// the seat of the keyboard
+        seat: wl_seat::WlSeat,
+        /// the surface now focused by the keyboard
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// the keys currently pressed, as linux evdev codes
+        keys: Vec<u32>,
+    },
+    /// The keyboard focus left a surface
+    KeyboardLeave {
+        /// the seat of the keyboard
+        seat: wl_seat::WlSeat,
+        /// the surface previously focused by the keyboard
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+    },
+    /// A key was pressed or released
+    Key {
+        /// the seat of the keyboard
+        seat: wl_seat::WlSeat,
+        /// the surface focused by the keyboard
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// the key event
+        event: KeyEvent,
+    },
+    /// The state of the modifiers changed
+    Modifiers {
+        /// the seat of the keyboard
+        seat: wl_seat::WlSeat,
+        /// the new state of the modifiers
+        modifiers: Modifiers,
+    },
+    /// The compositor advertized its key repetition settings
+    RepeatInfo {
+        /// the seat of the keyboard
+        seat: wl_seat::WlSeat,
+        /// repetitions per second, 0 disables key repetition
+        rate: i32,
+        /// delay before repetition starts, in milliseconds
+        delay: i32,
+    },
+    /// A new touch point appeared
+    TouchDown {
+        /// the seat of the touch device
+        seat: wl_seat::WlSeat,
+        /// the surface touched
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// identifier of the touch point
+        id: i32,
+        /// position of the touch point
+        position: (f64, f64),
+    },
+    /// A touch point disappeared
+    TouchUp {
+        /// the seat of the touch device
+        seat: wl_seat::WlSeat,
+        /// the surface that was touched
+        surface: wl_surface::WlSurface,
+        /// serial of the event
+        serial: u32,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// identifier of the touch point
+        id: i32,
+    },
+    /// A touch point moved
+    TouchMotion {
+        /// the seat of the touch device
+        seat: wl_seat::WlSeat,
+        /// the surface touched
+        surface: wl_surface::WlSurface,
+        /// timestamp of the event, in milliseconds
+        time: u32,
+        /// identifier of the touch point
+        id: i32,
+        /// new position of the touch point
+        position: (f64, f64),
+    },
+    /// The compositor took over all the touch points of a seat
+    TouchCancel {
+        /// the seat of the touch device
+        seat: wl_seat::WlSeat,
+    },
+}
+
+/// The scrolling of a pointer along an axis
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AxisScroll {
+    /// the scrolled distance, in surface-local coordinates
+    pub absolute: f64,
+    /// the scrolled distance in steps of a wheel, where 120 is a full step
+    ///
+    /// Before version 8 of `wl_seat`, the compositor only reports full steps. It is 0 if the
+    /// scrolling was not done with a wheel.
+    pub value120: i32,
+    /// the scrolling stopped, for example when the fingers were lifted from a touchpad
+    pub stop: bool,
+    /// the scrolling is inverted relative to the physical motion, like with "natural scrolling"
+    ///
+    /// This is only reported since version 9 of `wl_seat`.
+    pub inverted: bool,
+}
+
+impl AxisScroll {
+    /// Whether this axis did not scroll
+    pub fn is_none(&self) -> bool {
+        *self == AxisScroll::default()
+    }
+}
+
+// The scrolling reported during the current frame of a pointer
+#[derive(Debug, Default)]
+struct PendingAxis {
+    time: u32,
+    horizontal: AxisScroll,
+    vertical: AxisScroll,
+    source: Option<WEnum<wl_pointer::AxisSource>>,
+}
+
+impl PendingAxis {
+    fn axis_mut(&mut self, axis: WEnum<wl_pointer::Axis>) -> Option<&mut AxisScroll> {
+        match axis {
+            WEnum::Value(wl_pointer::Axis::HorizontalScroll) => Some(&mut self.horizontal),
+            WEnum::Value(wl_pointer::Axis::VerticalScroll) => Some(&mut self.vertical),
+            _ => None,
+        }
+    }
+}
+
+/// A key press or release
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyEvent {
+    /// timestamp of the event, in milliseconds
+    pub time: u32,
+    /// the key, as a linux evdev code
+    pub raw_code: u32,
+    /// whether the key was pressed or released
+    pub state: WEnum<wl_keyboard::KeyState>,
+    /// the keysym of the key, if the keymap of the keyboard could be loaded
+    #[cfg(feature = "xkbcommon")]
+    pub keysym: Option<u32>,
+    /// the text produced by the key, if any
+    #[cfg(feature = "xkbcommon")]
+    pub utf8: Option<String>,
+}
+
+/// The state of the keyboard modifiers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    /// modifiers physically held down, as a mask of the keymap modifiers
+    pub depressed: u32,
+    /// modifiers latched until the next key press
+    pub latched: u32,
+    /// modifiers locked until pressed again
+    pub locked: u32,
+    /// active layout
+    pub group: u32,
+    /// the control key is active
+    #[cfg(feature = "xkbcommon")]
+    pub ctrl: bool,
+    /// the alt key is active
+    #[cfg(feature = "xkbcommon")]
+    pub alt: bool,
+    /// the shift key is active
+    #[cfg(feature = "xkbcommon")]
+    pub shift: bool,
+    /// caps lock is active
+    #[cfg(feature = "xkbcommon")]
+    pub caps_lock: bool,
+    /// the logo key is active
+    #[cfg(feature = "xkbcommon")]
+    pub logo: bool,
+    /// num lock is active
+    #[cfg(feature = "xkbcommon")]
+    pub num_lock: bool,
+}
+
+/// The state of a seat and of its input devices
+#[derive(Debug)]
+pub struct SeatInfo {
+    seat: wl_seat::WlSeat,
+    name: Option<String>,
+    capabilities: wl_seat::Capability,
+    pointer: Option<wl_pointer::WlPointer>,
+    keyboard: Option<wl_keyboard::WlKeyboard>,
+    touch: Option<wl_touch::WlTouch>,
+    pointer_focus: Option<wl_surface::WlSurface>,
+    pending_axis: Option<PendingAxis>,
+    keyboard_focus: Option<wl_surface::WlSurface>,
+    touch_points: Vec<(i32, wl_surface::WlSurface)>,
+    modifiers: Modifiers,
+    #[cfg(feature = "xkbcommon")]
+    xkb_state: Option<XkbState>,
+}
+
+impl SeatInfo {
+    fn new(seat: wl_seat::WlSeat) -> SeatInfo {
+        SeatInfo {
+            seat,
+            name: None,
+            capabilities: wl_seat::Capability::empty(),
+            pointer: None,
+            keyboard: None,
+            touch: None,
+            pointer_focus: None,
+            pending_axis: None,
+            keyboard_focus: None,
+            touch_points: Vec::new(),
+            modifiers: Modifiers::default(),
+            #[cfg(feature = "xkbcommon")]
+            xkb_state: None,
+        }
+    }
+
+    /// The seat
+    pub fn seat(&self) -> &wl_seat::WlSeat {
+        &self.seat
+    }
+
+    /// The name of the seat, if the compositor advertized it
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The current capabilities of the seat
+    pub fn capabilities(&self) -> wl_seat::Capability {
+        self.capabilities
+    }
+
+    /// The pointer of the seat, if it has one
+    pub fn pointer(&self) -> Option<&wl_pointer::WlPointer> {
+        self.pointer.as_ref()
+    }
+
+    /// The keyboard of the seat, if it has one
+    pub fn keyboard(&self) -> Option<&wl_keyboard::WlKeyboard> {
+        self.keyboard.as_ref()
+    }
+
+    /// The touch device of the seat, if it has one
+    pub fn touch(&self) -> Option<&wl_touch::WlTouch> {
+        self.touch.as_ref()
+    }
+
+    /// The surface currently focused by the pointer
+    pub fn pointer_focus(&self) -> Option<&wl_surface::WlSurface> {
+        self.pointer_focus.as_ref()
+    }
+
+    /// The surface currently focused by the keyboard
+    pub fn keyboard_focus(&self) -> Option<&wl_surface::WlSurface> {
+        self.keyboard_focus.as_ref()
+    }
+
+    /// The current state of the keyboard modifiers
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+}
+
+/// A helper handling the input devices of all seats
+///
+/// See the [module-level documentation](self) for its use. The `wl_seat`s must be created with `()`
+/// as user data, [`bind_seats()`](SeatState::bind_seats) does it for all the seats advertized by the
+/// compositor.
+#[derive(Debug)]
+pub struct SeatState {
+    seats: Vec<SeatInfo>,
+    events: Vec<InputEvent>,
+    serials: SerialTracker,
+    #[cfg(feature = "xkbcommon")]
+    xkb_context: XkbContext,
+}
+
+impl Default for SeatState {
+    fn default() -> Self {
+        SeatState::new()
+    }
+}
+
+impl SeatState {
+    /// Create a new `SeatState`
+    pub fn new() -> SeatState {
+        SeatState {
+            seats: Vec::new(),
+            events: Vec::new(),
+            serials: SerialTracker::new(),
+            #[cfg(feature = "xkbcommon")]
+            xkb_context: XkbContext(xkb::Context::new(xkb::CONTEXT_NO_FLAGS)),
+        }
+    }
+
+    /// Bind all the seats of a global list
+    ///
+    /// They are bound with the highest version supported by both the compositor and these
+    /// bindings, the events of the newer versions being reported when they are available.
+    pub fn bind_seats<D>(
+        &mut self,
+        conn: &mut ConnectionHandle<'_>,
+        qh: &QueueHandle<D>,
+        registry: &wl_registry::WlRegistry,
+        globals: &GlobalList,
+    ) -> Result<(), DispatchError>
+    where
+        D: Dispatch<wl_seat::WlSeat, UserData = ()> + 'static,
+    {
+        for desc in
+            globals.list().iter().filter(|desc| desc.interface == wl_seat::WlSeat::interface().name)
+        {
+            let version = desc.version.min(wl_seat::WlSeat::interface().version);
+            let seat = registry.bind::<wl_seat::WlSeat, D>(conn, desc.name, version, qh, ())?;
+            self.seat_mut(&seat);
+        }
+        Ok(())
+    }
+
+    /// The seats known to this helper
+    pub fn seats(&self) -> &[SeatInfo] {
+        &self.seats
+    }
+
+    /// The state of a given seat
+    pub fn info(&self, seat: &wl_seat::WlSeat) -> Option<&SeatInfo> {
+        self.seats.iter().find(|info| &info.seat == seat)
+    }
+
+    /// Take the input events received since the last call
+    pub fn take_events(&mut self) -> Vec<InputEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// The serials of the latest input events of all seats
+    pub fn serials(&self) -> &SerialTracker {
+        &self.serials
+    }
+
+    /// Add an event to the input events of the seats
+    ///
+    /// This is meant for the helpers of protocol extensions reporting input events, like the hold
+    /// gestures of `zwp_pointer_gestures_v1`, so that applications receive them along with the
+    /// events of the core protocol.
+    pub fn push_event(&mut self, event: InputEvent) {
+        self.serials.record(&event);
+        self.events.push(event);
+    }
+
+    fn seat_mut(&mut self, seat: &wl_seat::WlSeat) -> &mut SeatInfo {
+        match self.seats.iter().position(|info| &info.seat == seat) {
+            Some(i) => &mut self.seats[i],
+            None => {
+                self.seats.push(SeatInfo::new(seat.clone()));
+                self.seats.last_mut().unwrap()
+            }
+        }
+    }
+
+    #[cfg(feature = "xkbcommon")]
+    fn load_keymap(&self, fd: RawFd, size: u32) -> Option<XkbState> {
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let keymap = unsafe {
+            xkb::Keymap::new_from_fd(
+                &self.xkb_context.0,
+                fd,
+                size as usize,
+                xkb::KEYMAP_FORMAT_TEXT_V1,
+                xkb::KEYMAP_COMPILE_NO_FLAGS,
+            )
+        };
+        match keymap {
+            Ok(Some(keymap)) => Some(XkbState(xkb::State::new(&keymap))),
+            Ok(None) => {
+                log::warn!("The compositor sent an invalid keymap.");
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to map the keymap: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl DelegateDispatchBase<wl_seat::WlSeat> for SeatState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<wl_seat::WlSeat, D> for SeatState
+where
+    D: Dispatch<wl_seat::WlSeat, UserData = ()>
+        + Dispatch<wl_pointer::WlPointer, UserData = wl_seat::WlSeat>
+        + Dispatch<wl_keyboard::WlKeyboard, UserData = wl_seat::WlSeat>
+        + Dispatch<wl_touch::WlTouch, UserData = wl_seat::WlSeat>
+        + AsMut<SeatState>
+        + 'static,
+{
+    fn event(
+        data: &mut D,
+        seat: &wl_seat::WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        match event {
+            wl_seat::Event::Capabilities { capabilities } => {
+                let capabilities = match capabilities {
+                    WEnum::Value(caps) => caps,
+                    WEnum::Unknown(raw) => wl_seat::Capability::from_bits_truncate(raw),
+                };
+                let info = me.seat_mut(seat);
+                info.capabilities = capabilities;
+                if capabilities.contains(wl_seat::Capability::Pointer) {
+                    if info.pointer.is_none() {
+                        info.pointer = seat.get_pointer(conn, qh, seat.clone()).ok();
+                    }
+                } else if let Some(pointer) = info.pointer.take() {
+                    if pointer.version() >= 3 {
+                        pointer.release(conn);
+                    }
+                    info.pointer_focus = None;
+                    info.pending_axis = None;
+                }
+                if capabilities.contains(wl_seat::Capability::Keyboard) {
+                    if info.keyboard.is_none() {
+                        info.keyboard = seat.get_keyboard(conn, qh, seat.clone()).ok();
+                    }
+                } else if let Some(keyboard) = info.keyboard.take() {
+                    if keyboard.version() >= 3 {
+                        keyboard.release(conn);
+                    }
+                    info.keyboard_focus = None;
+                    info.modifiers = Modifiers::default();
+                    #[cfg(feature = "xkbcommon")]
+                    {
+                        info.xkb_state = None;
+                    }
+                }
+                if capabilities.contains(wl_seat::Capability::Touch) {
+                    if info.touch.is_none() {
+                        info.touch = seat.get_touch(conn, qh, seat.clone()).ok();
+                    }
+                } else if let Some(touch) = info.touch.take() {
+                    if touch.version() >= 3 {
+                        touch.release(conn);
+                    }
+                    info.touch_points.clear();
+                }
+                me.events.push(InputEvent::Capabilities { seat: seat.clone(), capabilities });
+            }
+            wl_seat::Event::Name { name } => {
+                me.seat_mut(seat).name = Some(name);
+            }
+        }
+    }
+}
+
+impl DelegateDispatchBase<wl_pointer::WlPointer> for SeatState {
+    type UserData = wl_seat::WlSeat;
+}
+
+impl<D> DelegateDispatch<wl_pointer::WlPointer, D> for SeatState
+where
+    D: Dispatch<wl_pointer::WlPointer, UserData = wl_seat::WlSeat> + AsMut<SeatState>,
+{
+    fn event(
+        data: &mut D,
+        pointer: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+        seat: &wl_seat::WlSeat,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let info = me.seat_mut(seat);
+        let event = match event {
+            wl_pointer::Event::Enter { serial, surface, surface_x, surface_y } => {
+                info.pointer_focus = Some(surface.clone());
+                InputEvent::PointerEnter {
+                    seat: seat.clone(),
+                    surface,
+                    serial,
+                    position: (surface_x, surface_y),
+                }
+            }
+            wl_pointer::Event::Leave { serial, surface } => {
+                info.pointer_focus = None;
+                InputEvent::PointerLeave { seat: seat.clone(), surface, serial }
+            }
+            wl_pointer::Event::Motion { time, surface_x, surface_y } => {
+                let surface = match info.pointer_focus {
+                    Some(ref surface) => surface.clone(),
+                    None => return,
+                };
+                InputEvent::PointerMotion {
+                    seat: seat.clone(),
+                    surface,
+                    time,
+                    position: (surface_x, surface_y),
+                }
+            }
+            wl_pointer::Event::Button { serial, time, button, state } => {
+                let surface = match info.pointer_focus {
+                    Some(ref surface) => surface.clone(),
+                    None => return,
+                };
+                InputEvent::PointerButton {
+                    seat: seat.clone(),
+                    surface,
+                    serial,
+                    time,
+                    button,
+                    state,
+                }
+            }
+            wl_pointer::Event::Axis { time, axis, value } => {
+                let pending = info.pending_axis.get_or_insert_with(PendingAxis::default);
+                pending.time = time;
+                if let Some(scroll) = pending.axis_mut(axis) {
+                    scroll.absolute += value;
+                }
+                // the pointers without frames report each axis separately
+                if pointer.version() >= 5 {
+                    return;
+                }
+                match info.take_axis_event() {
+                    Some(event) => event,
+                    None => return,
+                }
+            }
+            wl_pointer::Event::AxisSource { axis_source } => {
+                info.pending_axis.get_or_insert_with(PendingAxis::default).source =
+                    Some(axis_source);
+                return;
+            }
+            wl_pointer::Event::AxisStop { time, axis } => {
+                let pending = info.pending_axis.get_or_insert_with(PendingAxis::default);
+                pending.time = time;
+                if let Some(scroll) = pending.axis_mut(axis) {
+                    scroll.stop = true;
+                }
+                return;
+            }
+            wl_pointer::Event::AxisDiscrete { axis, discrete } => {
+                let pending = info.pending_axis.get_or_insert_with(PendingAxis::default);
+                if let Some(scroll) = pending.axis_mut(axis) {
+                    scroll.value120 = scroll.value120.saturating_add(discrete.saturating_mul(120));
+                }
+                return;
+            }
+            wl_pointer::Event::AxisValue120 { axis, value120 } => {
+                let pending = info.pending_axis.get_or_insert_with(PendingAxis::default);
+                if let Some(scroll) = pending.axis_mut(axis) {
+                    scroll.value120 = scroll.value120.saturating_add(value120);
+                }
+                return;
+            }
+            wl_pointer::Event::AxisRelativeDirection { axis, direction } => {
+                let pending = info.pending_axis.get_or_insert_with(PendingAxis::default);
+                if let Some(scroll) = pending.axis_mut(axis) {
+                    scroll.inverted =
+                        direction == WEnum::Value(wl_pointer::AxisRelativeDirection::Inverted);
+                }
+                return;
+            }
+            wl_pointer::Event::Frame => match info.take_axis_event() {
+                Some(event) => event,
+                None => return,
+            },
+        };
+        me.push_event(event);
+    }
+}
+
+impl SeatInfo {
+    // The scrolling of the current frame of the pointer, dropped if it has no focus
+    fn take_axis_event(&mut self) -> Option<InputEvent> {
+        let pending = self.pending_axis.take()?;
+        let surface = self.pointer_focus.clone()?;
+        Some(InputEvent::PointerAxis {
+            seat: self.seat.clone(),
+            surface,
+            time: pending.time,
+            horizontal: pending.horizontal,
+            vertical: pending.vertical,
+            source: pending.source,
+        })
+    }
+}
+
+impl DelegateDispatchBase<wl_keyboard::WlKeyboard> for SeatState {
+    type UserData = wl_seat::WlSeat;
+}
+
+impl<D> DelegateDispatch<wl_keyboard::WlKeyboard, D> for SeatState
+where
+    D: Dispatch<wl_keyboard::WlKeyboard, UserData = wl_seat::WlSeat> + AsMut<SeatState>,
+{
+    fn event(
+        data: &mut D,
+        _: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        seat: &wl_seat::WlSeat,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let event = match event {
+            wl_keyboard::Event::Keymap { format, fd, size } => {
+                if format != WEnum::Value(wl_keyboard::KeymapFormat::XkbV1) {
+                    let _ = nix::unistd::close(fd);
+                    return;
+                }
+                handle_keymap(me, seat, fd, size);
+                return;
+            }
+            wl_keyboard::Event::Enter { serial, surface, keys } => {
+                me.seat_mut(seat).keyboard_focus = Some(surface.clone());
+                let keys = keys
+                    .chunks_exact(4)
+                    .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect();
+                InputEvent::KeyboardEnter { seat: seat.clone(), surface, serial, keys }
+            }
+            wl_keyboard::Event::Leave { serial, surface } => {
+                me.seat_mut(seat).keyboard_focus = None;
+                InputEvent::KeyboardLeave { seat: seat.clone(), surface, serial }
+            }
+            wl_keyboard::Event::Key { serial, time, key, state } => {
+                let info = me.seat_mut(seat);
+                let surface = match info.keyboard_focus {
+                    Some(ref surface) => surface.clone(),
+                    None => return,
+                };
+                InputEvent::Key {
+                    seat: seat.clone(),
+                    surface,
+                    serial,
+                    event: info.key_event(time, key, state),
+                }
+            }
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                let info = me.seat_mut(seat);
+                info.update_modifiers(mods_depressed, mods_latched, mods_locked, group);
+                InputEvent::Modifiers { seat: seat.clone(), modifiers: info.modifiers }
+            }
+            wl_keyboard::Event::RepeatInfo { rate, delay } => {
+                InputEvent::RepeatInfo { seat: seat.clone(), rate, delay }
+            }
+        };
+        me.push_event(event);
+    }
+}
+
+#[cfg(feature = "xkbcommon")]
+fn handle_keymap(me: &mut SeatState, seat: &wl_seat::WlSeat, fd: RawFd, size: u32) {
+    let state = me.load_keymap(fd, size);
+    me.seat_mut(seat).xkb_state = state;
+}
+
+#[cfg(not(feature = "xkbcommon"))]
+fn handle_keymap(_: &mut SeatState, _: &wl_seat::WlSeat, fd: RawFd, _: u32) {
+    let _ = nix::unistd::close(fd);
+}
+
+impl SeatInfo {
+    fn key_event(&self, time: u32, key: u32, state: WEnum<wl_keyboard::KeyState>) -> KeyEvent {
+        #[cfg(feature = "xkbcommon")]
+        {
+            // xkb keycodes are offset by 8 from the evdev ones
+            let keycode = xkb::Keycode::from(key + 8);
+            let (keysym, utf8) = match self.xkb_state {
+                Some(ref state) => {
+                    let utf8 = state.0.key_get_utf8(keycode);
+                    (
+                        Some(state.0.key_get_one_sym(keycode).raw()),
+                        if utf8.is_empty() { None } else { Some(utf8) },
+                    )
+                }
+                None => (None, None),
+            };
+            KeyEvent { time, raw_code: key, state, keysym, utf8 }
+        }
+        #[cfg(not(feature = "xkbcommon"))]
+        {
+            KeyEvent { time, raw_code: key, state }
+        }
+    }
+
+    fn update_modifiers(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        self.modifiers.depressed = depressed;
+        self.modifiers.latched = latched;
+        self.modifiers.locked = locked;
+        self.modifiers.group = group;
+        #[cfg(feature = "xkbcommon")]
+        if let Some(ref mut state) = self.xkb_state {
+            let state = &mut state.0;
+            state.update_mask(depressed, latched, locked, 0, 0, group);
+            let active = |name: &str| state.mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE);
+            self.modifiers.ctrl = active(xkb::MOD_NAME_CTRL);
+            self.modifiers.alt = active(xkb::MOD_NAME_ALT);
+            self.modifiers.shift = active(xkb::MOD_NAME_SHIFT);
+            self.modifiers.caps_lock = active(xkb::MOD_NAME_CAPS);
+            self.modifiers.logo = active(xkb::MOD_NAME_LOGO);
+            self.modifiers.num_lock = active(xkb::MOD_NAME_NUM);
+        }
+    }
+}
+
+impl DelegateDispatchBase<wl_touch::WlTouch> for SeatState {
+    type UserData = wl_seat::WlSeat;
+}
+
+impl<D> DelegateDispatch<wl_touch::WlTouch, D> for SeatState
+where
+    D: Dispatch<wl_touch::WlTouch, UserData = wl_seat::WlSeat> + AsMut<SeatState>,
+{
+    fn event(
+        data: &mut D,
+        _: &wl_touch::WlTouch,
+        event: wl_touch::Event,
+        seat: &wl_seat::WlSeat,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let info = me.seat_mut(seat);
+        let event = match event {
+            wl_touch::Event::Down { serial, time, surface, id, x, y } => {
+                info.touch_points.retain(|&(point, _)| point != id);
+                info.touch_points.push((id, surface.clone()));
+                InputEvent::TouchDown {
+                    seat: seat.clone(),
+                    surface,
+                    serial,
+                    time,
+                    id,
+                    position: (x, y),
+                }
+            }
+            wl_touch::Event::Up { serial, time, id } => {
+                let i = match info.touch_points.iter().position(|&(point, _)| point == id) {
+                    Some(i) => i,
+                    None => return,
+                };
+                let (_, surface) = info.touch_points.remove(i);
+                InputEvent::TouchUp { seat: seat.clone(), surface, serial, time, id }
+            }
+            wl_touch::Event::Motion { time, id, x, y } => {
+                let surface = match info.touch_points.iter().find(|&&(point, _)| point == id) {
+                    Some((_, surface)) => surface.clone(),
+                    None => return,
+                };
+                InputEvent::TouchMotion { seat: seat.clone(), surface, time, id, position: (x, y) }
+            }
+            wl_touch::Event::Cancel => {
+                info.touch_points.clear();
+                InputEvent::TouchCancel { seat: seat.clone() }
+            }
+            _ => return,
+        };
+        me.push_event(event);
+    }
+}
+
+#[cfg(feature = "xkbcommon")]
+struct XkbContext(xkb::Context);
+
+#[cfg(all(feature = "xkbcommon", not(tarpaulin_include)))]
+impl std::fmt::Debug for XkbContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XkbContext").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "xkbcommon")]
+struct XkbState(xkb::State);
+
+#[cfg(all(feature = "xkbcommon", not(tarpaulin_include)))]
+impl std::fmt::Debug for XkbState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XkbState").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,249 @@
+//! Tracking of the serials of the input events
+//!
+//! Several requests must be given the serial of the input event which triggered them: the
+//! compositor checks it and silently ignores the request if it is not the serial of a suitable
+//! recent event, leaving the client guessing why its cursor was not set or its window did not
+//! move. A [`SerialTracker`] records the latest serials of each seat with the event they come from,
+//! and checks a serial against the events allowing a given [`SerialUsage`].
+//!
+//! The [`SeatState`](crate::seat::SeatState) maintains one, available from
+//! [`SeatState::serials()`](crate::seat::SeatState::serials).
+//!
+//! ```no_run
+//! # fn run(seats: &wayland_client::seat::SeatState, seat: &wayland_client::protocol::wl_seat::WlSeat) {
+//! use wayland_client::serial::SerialUsage;
+//!
+//! // the serial of the button press or touch down to start an interactive move with
+//! let serial = seats.serials().latest(seat, SerialUsage::Move).unwrap().serial;
+//! # }
+//! ```
+
+use std::fmt;
+
+use crate::{
+    protocol::{wl_keyboard, wl_pointer, wl_seat, wl_surface},
+    seat::InputEvent,
+    WEnum,
+};
+
+/// The kind of input event a serial comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialKind {
+    /// `wl_pointer.enter`
+    PointerEnter,
+    /// `wl_pointer.button`, for a button press
+    PointerButton,
+    /// `wl_keyboard.enter`
+    KeyboardEnter,
+    /// `wl_keyboard.key`, for a key press
+    Key,
+    /// `wl_touch.down`
+    TouchDown,
+}
+
+impl fmt::Display for SerialKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SerialKind::PointerEnter => "wl_pointer.enter",
+            SerialKind::PointerButton => "wl_pointer.button",
+            SerialKind::KeyboardEnter => "wl_keyboard.enter",
+            SerialKind::Key => "wl_keyboard.key",
+            SerialKind::TouchDown => "wl_touch.down",
+        })
+    }
+}
+
+/// A request requiring the serial of an input event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialUsage {
+    /// `wl_pointer.set_cursor`, requiring the serial of the latest pointer enter
+    SetCursor,
+    /// An interactive move, like `xdg_toplevel.move`, requiring a button press or a touch down
+    Move,
+    /// An interactive resize, like `xdg_toplevel.resize`, requiring a button press or a touch down
+    Resize,
+    /// `wl_data_device.start_drag`, requiring a button press or a touch down
+    StartDrag,
+    /// A popup grab, like `xdg_popup.grab`, requiring a button press, a touch down or a key press
+    PopupGrab,
+    /// `wl_data_device.set_selection`, requiring a button press, a touch down, a key press or a
+    /// keyboard enter
+    SetSelection,
+}
+
+impl SerialUsage {
+    /// The kinds of input events whose serial can be used for this request
+    pub fn allowed_kinds(self) -> &'static [SerialKind] {
+        use SerialKind::*;
+        match self {
+            SerialUsage::SetCursor => &[PointerEnter],
+            SerialUsage::Move | SerialUsage::Resize | SerialUsage::StartDrag => {
+                &[PointerButton, TouchDown]
+            }
+            SerialUsage::PopupGrab => &[PointerButton, TouchDown, Key],
+            SerialUsage::SetSelection => &[PointerButton, TouchDown, Key, KeyboardEnter],
+        }
+    }
+}
+
+impl fmt::Display for SerialUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SerialUsage::SetCursor => "set_cursor",
+            SerialUsage::Move => "move",
+            SerialUsage::Resize => "resize",
+            SerialUsage::StartDrag => "start_drag",
+            SerialUsage::PopupGrab => "popup grab",
+            SerialUsage::SetSelection => "set_selection",
+        })
+    }
+}
+
+/// A serial recorded by a [`SerialTracker`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Serial {
+    /// the serial
+    pub serial: u32,
+    /// the kind of event it comes from
+    pub kind: SerialKind,
+    /// the seat of the event
+    pub seat: wl_seat::WlSeat,
+    /// the surface focused by the device of the event
+    pub surface: wl_surface::WlSurface,
+}
+
+/// A serial which cannot be used for a request
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SerialError {
+    /// No event allowing this request was received on the seat
+    #[error("No input event allowing {usage} was received on this seat")]
+    NoSerial {
+        /// the request
+        usage: SerialUsage,
+    },
+    /// The serial is not the one of the latest events of the seat
+    #[error(
+        "Serial {serial} used for {usage} is not the one of a recent input event of this seat"
+    )]
+    UnknownSerial {
+        /// the request
+        usage: SerialUsage,
+        /// the serial
+        serial: u32,
+    },
+    /// The serial comes from an event which does not allow this request
+    #[error("Serial {serial} used for {usage} comes from a {kind} event, which does not allow it")]
+    WrongEvent {
+        /// the request
+        usage: SerialUsage,
+        /// the serial
+        serial: u32,
+        /// the kind of event it comes from
+        kind: SerialKind,
+    },
+}
+
+/// The latest serials of the input events of each seat
+///
+/// The latest serial of each kind of event is kept for each seat. The serials of button and key
+/// releases are not recorded, as they do not allow any request, and leaving the focus of a surface
+/// forgets the serial of the corresponding enter event.
+#[derive(Debug, Default)]
+pub struct SerialTracker {
+    // the recorded serials, in the order they were received
+    serials: Vec<Serial>,
+}
+
+impl SerialTracker {
+    /// Create a new empty `SerialTracker`
+    pub fn new() -> SerialTracker {
+        SerialTracker::default()
+    }
+
+    /// Record the serial of an input event, if it has one
+    pub fn record(&mut self, event: &InputEvent) {
+        let (seat, surface, serial, kind) = match event {
+            InputEvent::PointerEnter { seat, surface, serial, .. } => {
+                (seat, surface, *serial, SerialKind::PointerEnter)
+            }
+            InputEvent::PointerButton { seat, surface, serial, state, .. }
+                if *state == WEnum::Value(wl_pointer::ButtonState::Pressed) =>
+            {
+                (seat, surface, *serial, SerialKind::PointerButton)
+            }
+            InputEvent::KeyboardEnter { seat, surface, serial, .. } => {
+                (seat, surface, *serial, SerialKind::KeyboardEnter)
+            }
+            InputEvent::Key { seat, surface, serial, event }
+                if event.state == WEnum::Value(wl_keyboard::KeyState::Pressed) =>
+            {
+                (seat, surface, *serial, SerialKind::Key)
+            }
+            InputEvent::TouchDown { seat, surface, serial, .. } => {
+                (seat, surface, *serial, SerialKind::TouchDown)
+            }
+            InputEvent::PointerLeave { seat, .. } => {
+                self.forget(seat, SerialKind::PointerEnter);
+                return;
+            }
+            InputEvent::KeyboardLeave { seat, .. } => {
+                self.forget(seat, SerialKind::KeyboardEnter);
+                return;
+            }
+            _ => return,
+        };
+        self.record_serial(Serial { serial, kind, seat: seat.clone(), surface: surface.clone() });
+    }
+
+    /// Record a serial, replacing the previous serial of the same kind on its seat
+    ///
+    /// This can be used for the events not handled by the [`SeatState`](crate::seat::SeatState).
+    pub fn record_serial(&mut self, serial: Serial) {
+        self.forget(&serial.seat, serial.kind);
+        self.serials.push(serial);
+    }
+
+    /// The latest serial of a seat allowing a request
+    pub fn latest(
+        &self,
+        seat: &wl_seat::WlSeat,
+        usage: SerialUsage,
+    ) -> Result<&Serial, SerialError> {
+        self.serials
+            .iter()
+            .rev()
+            .find(|serial| &serial.seat == seat && usage.allowed_kinds().contains(&serial.kind))
+            .ok_or(SerialError::NoSerial { usage })
+    }
+
+    /// Check that a serial can be used for a request
+    ///
+    /// The serial must be the latest one of its kind on the seat, and come from an event allowing
+    /// this request.
+    pub fn check(
+        &self,
+        seat: &wl_seat::WlSeat,
+        usage: SerialUsage,
+        serial: u32,
+    ) -> Result<&Serial, SerialError> {
+        let recorded = self
+            .serials
+            .iter()
+            .rev()
+            .find(|recorded| &recorded.seat == seat && recorded.serial == serial)
+            .ok_or(SerialError::UnknownSerial { usage, serial })?;
+        if !usage.allowed_kinds().contains(&recorded.kind) {
+            return Err(SerialError::WrongEvent { usage, serial, kind: recorded.kind });
+        }
+        Ok(recorded)
+    }
+
+    /// Forget the serials of a seat, for example once it was removed
+    pub fn forget_seat(&mut self, seat: &wl_seat::WlSeat) {
+        self.serials.retain(|serial| &serial.seat != seat);
+    }
+
+    fn forget(&mut self, seat: &wl_seat::WlSeat, kind: SerialKind) {
+        self.serials.retain(|serial| &serial.seat != seat || serial.kind != kind);
+    }
+}
@@ -0,0 +1,460 @@
+//! Shared memory buffers
+//!
+//! A [`ShmPool`] manages the shared memory backing a `wl_shm_pool`: it creates a sealed memfd (or
+//! an unlinked POSIX shared memory object where memfd is not available), maps it, and grows it as
+//! needed when [`Buffer`]s are allocated from it.
+//!
+//! The compositor may read the contents of a buffer from the moment it is attached to a surface
+//! until it sends the `wl_buffer.release` event. The memory of a [`Buffer`] is only reused, and
+//! its mapping is only unmapped, once the compositor has released it, even if the [`Buffer`] was
+//! dropped in the meantime or the pool was resized.
+//!
+//! ```no_run
+//! # fn draw(conn: &mut wayland_client::ConnectionHandle, shm: &wayland_client::protocol::wl_shm::WlShm, surface: &wayland_client::protocol::wl_surface::WlSurface) {
+//! use wayland_client::{protocol::wl_shm::Format, shm::ShmPool};
+//!
+//! let mut pool = ShmPool::new(conn, shm, 4096).unwrap();
+//! let mut buffer = pool.create_buffer(conn, 256, 256, 256 * 4, Format::Argb8888).unwrap();
+//! if let Some(canvas) = buffer.canvas() {
+//!     canvas.fill(0xFF);
+//! }
+//! buffer.attach_to(conn, surface).unwrap();
+//! # }
+//! ```
+
+use std::{
+    fs::File,
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    ptr::NonNull,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use nix::{
+    errno::Errno,
+    fcntl,
+    sys::{mman, stat},
+    unistd,
+};
+use wayland_backend::{
+    client::{Handle, ObjectData, ObjectId},
+    protocol::Message,
+};
+
+use crate::{
+    protocol::{wl_buffer, wl_shm, wl_shm_pool, wl_surface},
+    ConnectionHandle, DispatchError, Proxy, WEnum,
+};
+
+/// An error generated by a [`ShmPool`]
+#[derive(Debug, thiserror::Error)]
+pub enum ShmError {
+    /// The shared memory could not be created, resized or mapped
+    #[error("Shared memory error: {0}")]
+    Io(#[from] io::Error),
+    /// The requested buffer does not fit in a pool
+    #[error("Invalid buffer dimensions")]
+    InvalidDimensions,
+    /// A request could not be sent
+    #[error(transparent)]
+    Dispatch(#[from] DispatchError),
+}
+
+/// A pool of shared memory from which buffers are allocated
+///
+/// The pool grows as needed to fit the buffers allocated from it. It is not destroyed when dropped,
+/// use [`destroy()`](ShmPool::destroy) for that.
+#[derive(Debug)]
+pub struct ShmPool {
+    pool: wl_shm_pool::WlShmPool,
+    file: File,
+    mapping: Arc<Mapping>,
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl ShmPool {
+    /// Create a new pool of given initial size, in bytes
+    pub fn new(
+        conn: &mut ConnectionHandle,
+        shm: &wl_shm::WlShm,
+        size: usize,
+    ) -> Result<ShmPool, ShmError> {
+        let size = size.max(1);
+        if size > i32::MAX as usize {
+            return Err(ShmError::InvalidDimensions);
+        }
+        let file = unsafe { File::from_raw_fd(create_shm_fd()?) };
+        file.set_len(size as u64)?;
+        seal_shrink(&file);
+        let mapping = Arc::new(Mapping::new(file.as_raw_fd(), size)?);
+
+        let pool_id = conn.send_request(
+            shm,
+            wl_shm::Request::CreatePool { fd: file.as_raw_fd(), size: size as i32 },
+            Some(Arc::new(PoolData)),
+        )?;
+        let pool = wl_shm_pool::WlShmPool::from_id(conn, pool_id.clone())
+            .map_err(|_| DispatchError::DeadObject { interface: "wl_shm_pool", id: pool_id })?;
+
+        Ok(ShmPool {
+            pool,
+            file,
+            mapping,
+            state: Arc::new(Mutex::new(PoolState { free: vec![(0, size)], dropped: Vec::new() })),
+        })
+    }
+
+    /// The `wl_shm_pool` of this pool
+    pub fn wl_shm_pool(&self) -> &wl_shm_pool::WlShmPool {
+        &self.pool
+    }
+
+    /// The current size of this pool, in bytes
+    pub fn size(&self) -> usize {
+        self.mapping.len
+    }
+
+    /// Allocate a new buffer from this pool
+    ///
+    /// The memory of the buffer is `stride * height` bytes long, the pool is grown if it does not
+    /// fit in the currently free space.
+    pub fn create_buffer(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
+    ) -> Result<Buffer, ShmError> {
+        if width <= 0 || height <= 0 || stride <= 0 {
+            return Err(ShmError::InvalidDimensions);
+        }
+        let len =
+            (stride as usize).checked_mul(height as usize).ok_or(ShmError::InvalidDimensions)?;
+
+        self.destroy_dropped_buffers(conn);
+
+        let allocated = self.state.lock().unwrap().allocate(len);
+        let offset = match allocated {
+            Some(offset) => offset,
+            None => {
+                self.grow(conn, len)?;
+                self.state.lock().unwrap().allocate(len).ok_or(ShmError::InvalidDimensions)?
+            }
+        };
+
+        let data = Arc::new(BufferData {
+            mapping: self.mapping.clone(),
+            offset,
+            len,
+            pool_state: self.state.clone(),
+            state: Mutex::new(BufferState::default()),
+        });
+        let buffer = conn
+            .send_request(
+                &self.pool,
+                wl_shm_pool::Request::CreateBuffer {
+                    offset: offset as i32,
+                    width,
+                    height,
+                    stride,
+                    format: WEnum::Value(format),
+                },
+                Some(data.clone()),
+            )
+            .and_then(|id| {
+                wl_buffer::WlBuffer::from_id(conn, id.clone())
+                    .map_err(|_| DispatchError::DeadObject { interface: "wl_buffer", id })
+            });
+        match buffer {
+            Ok(buffer) => Ok(Buffer { buffer, data }),
+            Err(err) => {
+                self.state.lock().unwrap().free(offset, len);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Destroy this pool
+    ///
+    /// The buffers allocated from it remain valid.
+    pub fn destroy(mut self, conn: &mut ConnectionHandle) {
+        self.destroy_dropped_buffers(conn);
+        self.pool.destroy(conn);
+    }
+
+    fn grow(&mut self, conn: &mut ConnectionHandle, needed: usize) -> Result<(), ShmError> {
+        let old_size = self.mapping.len;
+        let new_size = old_size
+            .checked_add(needed)
+            .map(|size| size.max(old_size * 2))
+            .filter(|&size| size <= i32::MAX as usize)
+            .ok_or(ShmError::InvalidDimensions)?;
+        self.file.set_len(new_size as u64)?;
+        // Buffers allocated before keep the old mapping alive, covering their memory
+        self.mapping = Arc::new(Mapping::new(self.file.as_raw_fd(), new_size)?);
+        conn.send_request(
+            &self.pool,
+            wl_shm_pool::Request::Resize { size: new_size as i32 },
+            None,
+        )?;
+        self.state.lock().unwrap().free(old_size, new_size - old_size);
+        Ok(())
+    }
+
+    fn destroy_dropped_buffers(&mut self, conn: &mut ConnectionHandle) {
+        let dropped = std::mem::take(&mut self.state.lock().unwrap().dropped);
+        for buffer in dropped {
+            buffer.destroy(conn);
+        }
+    }
+}
+
+/// A buffer allocated from a [`ShmPool`]
+///
+/// Dropping the buffer destroys it once the compositor has released it, its memory is then
+/// returned to the pool.
+#[derive(Debug)]
+pub struct Buffer {
+    buffer: wl_buffer::WlBuffer,
+    data: Arc<BufferData>,
+}
+
+impl Buffer {
+    /// The `wl_buffer` of this buffer
+    pub fn wl_buffer(&self) -> &wl_buffer::WlBuffer {
+        &self.buffer
+    }
+
+    /// Whether the compositor may currently be reading this buffer
+    ///
+    /// A buffer is busy from the moment it is attached using [`attach_to()`](Buffer::attach_to)
+    /// until the compositor releases it.
+    pub fn is_busy(&self) -> bool {
+        self.data.state.lock().unwrap().busy
+    }
+
+    /// Access the memory of this buffer
+    ///
+    /// Returns `None` if the buffer is busy.
+    pub fn canvas(&mut self) -> Option<&mut [u8]> {
+        if self.is_busy() {
+            return None;
+        }
+        // The range of the buffer is only accessed through this handle, and is kept mapped by
+        // its data
+        Some(unsafe {
+            std::slice::from_raw_parts_mut(
+                self.data.mapping.ptr.as_ptr().add(self.data.offset),
+                self.data.len,
+            )
+        })
+    }
+
+    /// Attach this buffer to a surface, marking it busy until the compositor releases it
+    ///
+    /// The buffer must be attached using this method rather than `wl_surface.attach` for its memory
+    /// to be protected until it is released.
+    pub fn attach_to(
+        &self,
+        conn: &mut ConnectionHandle,
+        surface: &wl_surface::WlSurface,
+    ) -> Result<(), DispatchError> {
+        self.data.state.lock().unwrap().busy = true;
+        conn.send_request(
+            surface,
+            wl_surface::Request::Attach { buffer: Some(self.buffer.clone()), x: 0, y: 0 },
+            None,
+        )
+        .map(|_| ())
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let mut state = self.data.state.lock().unwrap();
+        if state.busy {
+            // destroyed when the release event is received
+            state.dropped = true;
+        } else {
+            self.data.pool_state.lock().unwrap().dropped.push(self.buffer.clone());
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    // free ranges of the pool, as sorted and non-contiguous (offset, len) pairs
+    free: Vec<(usize, usize)>,
+    // dropped buffers which are not busy, waiting to be destroyed
+    dropped: Vec<wl_buffer::WlBuffer>,
+}
+
+impl PoolState {
+    fn allocate(&mut self, len: usize) -> Option<usize> {
+        let index = self.free.iter().position(|&(_, free_len)| free_len >= len)?;
+        let (offset, free_len) = self.free[index];
+        if free_len == len {
+            self.free.remove(index);
+        } else {
+            self.free[index] = (offset + len, free_len - len);
+        }
+        Some(offset)
+    }
+
+    fn free(&mut self, offset: usize, len: usize) {
+        let index = self.free.partition_point(|&(free_offset, _)| free_offset < offset);
+        self.free.insert(index, (offset, len));
+        // merge with the next range, then with the previous one
+        if index + 1 < self.free.len() && offset + len == self.free[index + 1].0 {
+            self.free[index].1 += self.free.remove(index + 1).1;
+        }
+        if index > 0 && self.free[index - 1].0 + self.free[index - 1].1 == offset {
+            self.free[index - 1].1 += self.free.remove(index).1;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+    busy: bool,
+    dropped: bool,
+}
+
+#[derive(Debug)]
+struct BufferData {
+    mapping: Arc<Mapping>,
+    offset: usize,
+    len: usize,
+    pool_state: Arc<Mutex<PoolState>>,
+    state: Mutex<BufferState>,
+}
+
+impl ObjectData for BufferData {
+    fn event(
+        self: Arc<Self>,
+        handle: &mut Handle,
+        msg: Message<ObjectId>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        // wl_buffer.release is the only event of wl_buffer
+        let mut state = self.state.lock().unwrap();
+        state.busy = false;
+        if state.dropped {
+            // wl_buffer.destroy
+            let _ = handle.send_request(
+                Message { sender_id: msg.sender_id, opcode: 0, args: Default::default() },
+                None,
+            );
+        }
+        None
+    }
+
+    fn destroyed(&self, _: ObjectId) {
+        self.pool_state.lock().unwrap().free(self.offset, self.len);
+    }
+}
+
+struct PoolData;
+
+impl ObjectData for PoolData {
+    fn event(self: Arc<Self>, _: &mut Handle, _: Message<ObjectId>) -> Option<Arc<dyn ObjectData>> {
+        None
+    }
+
+    fn destroyed(&self, _: ObjectId) {}
+}
+
+// A shared memory mapping of a whole pool, unmapped when dropped
+#[derive(Debug)]
+struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// The mapping is plain memory, its accesses are synchronized by the buffers using it
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(fd: RawFd, len: usize) -> io::Result<Mapping> {
+        let ptr = unsafe {
+            mman::mmap(
+                std::ptr::null_mut(),
+                len,
+                mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE,
+                mman::MapFlags::MAP_SHARED,
+                fd,
+                0,
+            )?
+        };
+        Ok(Mapping { ptr: NonNull::new(ptr as *mut u8).unwrap(), len })
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        let _ = unsafe { mman::munmap(self.ptr.as_ptr() as *mut _, self.len) };
+    }
+}
+
+/// Create a shared file descriptor in memory
+fn create_shm_fd() -> io::Result<RawFd> {
+    // Only try memfd on linux
+    #[cfg(target_os = "linux")]
+    loop {
+        match nix::sys::memfd::memfd_create(
+            std::ffi::CStr::from_bytes_with_nul(b"wayland-client-shm\0").unwrap(),
+            nix::sys::memfd::MemFdCreateFlag::MFD_CLOEXEC
+                | nix::sys::memfd::MemFdCreateFlag::MFD_ALLOW_SEALING,
+        ) {
+            Ok(fd) => return Ok(fd),
+            Err(Errno::EINTR) => continue,
+            Err(Errno::ENOSYS) => break,
+            Err(errno) => return Err(errno.into()),
+        }
+    }
+
+    // Fallback to using shm_open
+    loop {
+        let name = format!(
+            "/wayland-client-shm-{}",
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos()
+        );
+        match mman::shm_open(
+            name.as_str(),
+            fcntl::OFlag::O_CREAT
+                | fcntl::OFlag::O_EXCL
+                | fcntl::OFlag::O_RDWR
+                | fcntl::OFlag::O_CLOEXEC,
+            stat::Mode::S_IRUSR | stat::Mode::S_IWUSR,
+        ) {
+            Ok(fd) => {
+                return match mman::shm_unlink(name.as_str()) {
+                    Ok(()) => Ok(fd),
+                    Err(errno) => {
+                        let _ = unistd::close(fd);
+                        Err(errno.into())
+                    }
+                }
+            }
+            Err(Errno::EEXIST) | Err(Errno::EINTR) => continue,
+            Err(errno) => return Err(errno.into()),
+        }
+    }
+}
+
+/// Prevent the memory from shrinking, so that the compositor can safely map it
+fn seal_shrink(file: &File) {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = fcntl::fcntl(
+            file.as_raw_fd(),
+            fcntl::FcntlArg::F_ADD_SEALS(
+                fcntl::SealFlag::F_SEAL_SHRINK | fcntl::SealFlag::F_SEAL_SEAL,
+            ),
+        );
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = file;
+}
@@ -0,0 +1,122 @@
+//! Timestamps of the input events
+//!
+//! The input events of the core protocol, like `wl_pointer.motion` or `wl_keyboard.key`, carry their
+//! time as a 32-bit number of milliseconds, which wraps around every 49.7 days. A [`Timecode`]
+//! follows the timestamps of a source of events to unwrap them into 64-bit times, given as
+//! [`Duration`]s from the unspecified origin of the clock of the compositor, and converts them into
+//! [`Instant`]s.
+//!
+//! If the `zwp_input_timestamps_v1` protocol is bound, its `timestamp` event gives the time of the
+//! following input event with a nanosecond precision. It can be passed to
+//! [`Timecode::set_precise()`], the precise time being then returned for this input event.
+//!
+//! ```
+//! use std::time::Duration;
+//! use wayland_client::timecode::Timecode;
+//!
+//! let mut timecode = Timecode::new();
+//! assert_eq!(timecode.extend(u32::MAX - 10), Duration::from_millis(u32::MAX as u64 - 10));
+//! // the timestamp wrapped around
+//! assert_eq!(timecode.extend(5), Duration::from_millis(u32::MAX as u64 + 6));
+//!
+//! // a zwp_input_timestamps_v1.timestamp event, for an event 5s after the previous one
+//! timecode.set_precise(0, 4_294_972, 301_250_000);
+//! assert_eq!(timecode.extend(5005), Duration::new(4_294_972, 301_250_000));
+//! ```
+
+use std::time::{Duration, Instant};
+
+/// The timestamps of a source of input events
+///
+/// Each source of events, like a `wl_pointer` or a `wl_keyboard`, should have its own `Timecode`.
+/// The timestamps of a source are expected to be mostly increasing: a timestamp is read as
+/// following the previous one unless it is less than 2^31 milliseconds (24.8 days) older. Such a
+/// timestamp, as can be seen when the events of several devices are merged, is unwrapped to an
+/// earlier time, without changing the times of the next events.
+#[derive(Debug, Clone, Default)]
+pub struct Timecode {
+    // the latest timestamp and its unwrapped value, in milliseconds
+    latest: Option<(u32, u64)>,
+    // the precise time of the next event
+    precise: Option<Duration>,
+    // an instant and the time of the compositor it corresponds to
+    anchor: Option<(Instant, Duration)>,
+}
+
+impl Timecode {
+    /// Create a `Timecode` for a new source of events
+    pub fn new() -> Timecode {
+        Timecode::default()
+    }
+
+    /// Unwrap the 32-bit millisecond timestamp of an event into its time
+    ///
+    /// If a precise time was set with [`set_precise()`](Timecode::set_precise) and it matches the
+    /// timestamp, it is returned instead.
+    pub fn extend(&mut self, time: u32) -> Duration {
+        if let Some(precise) = self.precise.take() {
+            let millis = precise.as_millis() as u64;
+            if millis as u32 == time {
+                self.advance(time, millis);
+                return precise;
+            }
+        }
+
+        let millis = match self.latest {
+            Some((latest_time, latest)) => {
+                let delta = time.wrapping_sub(latest_time) as i32;
+                (latest as i64 + delta as i64).max(0) as u64
+            }
+            None => time as u64,
+        };
+        self.advance(time, millis);
+        Duration::from_millis(millis)
+    }
+
+    /// Set the precise time of the next event
+    ///
+    /// The arguments are the ones of the `zwp_input_timestamps_v1.timestamp` event, which is sent
+    /// right before the event whose time it gives. The precise time is only used by the next call
+    /// to [`extend()`](Timecode::extend), if its timestamp agrees with it.
+    pub fn set_precise(&mut self, tv_sec_hi: u32, tv_sec_lo: u32, tv_nsec: u32) {
+        self.precise = Some(timespec_to_duration(tv_sec_hi, tv_sec_lo, tv_nsec));
+    }
+
+    /// Convert the time of an event into an [`Instant`]
+    ///
+    /// The origin of the clock of the compositor is not known to the client, so the first time
+    /// converted is considered to be the current instant, and the next ones are placed relatively
+    /// to it. The times converted to instants after the current one, because the first event was
+    /// received late, move this reference back, so that the conversion converges to the smallest
+    /// delay observed between the compositor and the client.
+    pub fn instant(&mut self, time: Duration) -> Instant {
+        let now = Instant::now();
+        if let Some((instant, origin)) = self.anchor {
+            let converted = if time >= origin {
+                instant.checked_add(time - origin)
+            } else {
+                instant.checked_sub(origin - time)
+            };
+            match converted {
+                Some(converted) if converted <= now => return converted,
+                _ => {}
+            }
+        }
+        self.anchor = Some((now, time));
+        now
+    }
+
+    fn advance(&mut self, time: u32, millis: u64) {
+        if !matches!(self.latest, Some((_, latest)) if millis < latest) {
+            self.latest = Some((time, millis));
+        }
+    }
+}
+
+/// Convert the arguments of a `zwp_input_timestamps_v1.timestamp` event into a [`Duration`]
+///
+/// The seconds are split into their 32 most and least significant bits.
+pub fn timespec_to_duration(tv_sec_hi: u32, tv_sec_lo: u32, tv_nsec: u32) -> Duration {
+    let secs = ((tv_sec_hi as u64) << 32) | tv_sec_lo as u64;
+    Duration::from_secs(secs).saturating_add(Duration::from_nanos(tv_nsec as u64))
+}
@@ -14,6 +14,10 @@
 //! what time, as well as handles to the buffers containing these frames, to
 //! attach them to a wayland surface.
 //!
+//! Alternatively, the [`shape`] module sets the cursor of pointers from a [`shape::CursorShape`],
+//! using the `wp_cursor_shape_v1` protocol when the compositor supports it and falling back to
+//! the cursor theme otherwise.
+//!
 //! # Example
 //!
 //! Several functions of this crate send wayland requests under the hood, requiring you to provide a
@@ -71,6 +75,8 @@ use xcursor::parser as xparser;
 use xcursor::CursorTheme as XCursorTheme;
 use xparser::Image as XCursorImage;
 
+pub mod shape;
+
 /// Represents a cursor theme loaded from the system.
 #[derive(Debug)]
 pub struct CursorTheme {
@@ -142,11 +148,13 @@ impl CursorTheme {
         // Flush to ensure the compositor has access to the buffer when it tries to map it.
         file.flush().expect("Flush on shm fd failed");
 
-        let pool_id = conn.send_request(
-            &shm,
-            wl_shm::Request::CreatePool { fd: file.as_raw_fd(), size: INITIAL_POOL_SIZE },
-            Some(Arc::new(IgnoreObjectData)),
-        )?;
+        let pool_id = conn
+            .send_request(
+                &shm,
+                wl_shm::Request::CreatePool { fd: file.as_raw_fd(), size: INITIAL_POOL_SIZE },
+                Some(Arc::new(IgnoreObjectData)),
+            )
+            .map_err(|_| InvalidId)?;
         let pool = WlShmPool::from_id(conn, pool_id)?;
 
         let name = String::from(name);
@@ -430,7 +438,7 @@ fn create_shm_fd() -> IoResult<RawFd> {
     }
 }
 
-struct IgnoreObjectData;
+pub(crate) struct IgnoreObjectData;
 
 impl ObjectData for IgnoreObjectData {
     fn event(
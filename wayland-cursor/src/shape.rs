@@ -0,0 +1,421 @@
+//! Setting the cursor of pointers by shape
+//!
+//! A [`CursorManager`] sets the cursor of pointers from a [`CursorShape`]. It uses the
+//! `wp_cursor_shape_v1` protocol when the compositor supports it (this requires the `cursor_shape`
+//! cargo feature), letting the compositor draw the cursor matching its theme, and falls back to
+//! loading the cursor images from a [`CursorTheme`] otherwise.
+//!
+//! The compositor only accepts a cursor set with the serial of the latest `wl_pointer.enter` event,
+//! and may reset the cursor each time the pointer enters a surface. The manager thus needs to be
+//! notified of these events with [`pointer_enter()`](CursorManager::pointer_enter), it then sets
+//! the current shape of the pointer again with the right serial.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use wayland_client::backend::InvalidId;
+use wayland_client::protocol::wl_compositor::{self, WlCompositor};
+use wayland_client::protocol::wl_pointer::WlPointer;
+use wayland_client::protocol::wl_shm::WlShm;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::{ConnectionHandle, Proxy};
+
+#[cfg(feature = "cursor_shape")]
+use wayland_protocols::staging::cursor_shape::v1::client::{
+    wp_cursor_shape_device_v1::{self, WpCursorShapeDeviceV1},
+    wp_cursor_shape_manager_v1::{self, WpCursorShapeManagerV1},
+};
+
+use crate::{CursorTheme, IgnoreObjectData};
+
+macro_rules! cursor_shapes {
+    ($($(#[$doc:meta])* $variant:ident => [$($name:expr),+]),* $(,)?) => {
+        /// A cursor shape
+        ///
+        /// The shapes are the ones of the CSS specification.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub enum CursorShape {
+            $(
+                $(#[$doc])*
+                $variant,
+            )*
+        }
+
+        impl CursorShape {
+            const ALL: &'static [CursorShape] = &[$(CursorShape::$variant),*];
+
+            /// The names of the cursor images of this shape in cursor themes, by order of preference
+            ///
+            /// The first one is the CSS name of the shape, the others are legacy X11 names.
+            pub fn names(self) -> &'static [&'static str] {
+                match self {
+                    $(CursorShape::$variant => &[$($name),+],)*
+                }
+            }
+
+            /// The shape of a cursor image of a cursor theme, from its name
+            ///
+            /// CSS names are matched first. Some legacy X11 names are shared by several shapes,
+            /// they map to the first of these shapes in declaration order.
+            pub fn from_name(name: &str) -> Option<CursorShape> {
+                let css = CursorShape::ALL.iter().find(|shape| shape.names()[0] == name);
+                css.or_else(|| CursorShape::ALL.iter().find(|shape| shape.names()[1..].contains(&name)))
+                    .copied()
+            }
+
+            #[cfg(feature = "cursor_shape")]
+            fn wp_shape(self) -> wp_cursor_shape_device_v1::Shape {
+                match self {
+                    $(CursorShape::$variant => wp_cursor_shape_device_v1::Shape::$variant,)*
+                }
+            }
+        }
+    };
+}
+
+cursor_shapes! {
+    /// The platform-dependent default cursor, typically an arrow
+    Default => ["default", "left_ptr"],
+    /// A context menu is available
+    ContextMenu => ["context-menu"],
+    /// Help is available
+    Help => ["help", "question_arrow"],
+    /// Pointer that indicates a link or another interactive element
+    Pointer => ["pointer", "hand2", "hand1"],
+    /// Progress indicator, the user can still interact
+    Progress => ["progress", "left_ptr_watch"],
+    /// Program is busy, the user should wait
+    Wait => ["wait", "watch"],
+    /// A cell or set of cells may be selected
+    Cell => ["cell", "plus"],
+    /// Simple crosshair
+    Crosshair => ["crosshair", "cross"],
+    /// Selectable text
+    Text => ["text", "xterm"],
+    /// Selectable vertical text
+    VerticalText => ["vertical-text"],
+    /// A drag-and-drop will create an alias or shortcut
+    Alias => ["alias", "link"],
+    /// A drag-and-drop will copy
+    Copy => ["copy"],
+    /// A drag-and-drop will move
+    Move => ["move"],
+    /// The dragged item cannot be dropped here
+    NoDrop => ["no-drop", "circle"],
+    /// The requested action will not be carried out
+    NotAllowed => ["not-allowed", "crossed_circle"],
+    /// Something can be grabbed
+    Grab => ["grab", "openhand", "hand1"],
+    /// Something is being grabbed
+    Grabbing => ["grabbing", "closedhand"],
+    /// Resizing towards the east
+    EResize => ["e-resize", "right_side"],
+    /// Resizing towards the north
+    NResize => ["n-resize", "top_side"],
+    /// Resizing towards the north-east
+    NeResize => ["ne-resize", "top_right_corner"],
+    /// Resizing towards the north-west
+    NwResize => ["nw-resize", "top_left_corner"],
+    /// Resizing towards the south
+    SResize => ["s-resize", "bottom_side"],
+    /// Resizing towards the south-east
+    SeResize => ["se-resize", "bottom_right_corner"],
+    /// Resizing towards the south-west
+    SwResize => ["sw-resize", "bottom_left_corner"],
+    /// Resizing towards the west
+    WResize => ["w-resize", "left_side"],
+    /// Resizing horizontally
+    EwResize => ["ew-resize", "sb_h_double_arrow"],
+    /// Resizing vertically
+    NsResize => ["ns-resize", "sb_v_double_arrow"],
+    /// Resizing along the north-east / south-west diagonal
+    NeswResize => ["nesw-resize", "fd_double_arrow"],
+    /// Resizing along the north-west / south-east diagonal
+    NwseResize => ["nwse-resize", "bd_double_arrow"],
+    /// Resizing a column
+    ColResize => ["col-resize", "sb_h_double_arrow"],
+    /// Resizing a row
+    RowResize => ["row-resize", "sb_v_double_arrow"],
+    /// Scrolling in any direction
+    AllScroll => ["all-scroll", "fleur"],
+    /// Zooming in
+    ZoomIn => ["zoom-in"],
+    /// Zooming out
+    ZoomOut => ["zoom-out"],
+}
+
+/// An error generated by a [`CursorManager`]
+#[derive(Debug)]
+pub enum CursorError {
+    /// The cursor theme provides no image for this shape
+    MissingShape(CursorShape),
+    /// An object involved in the request is dead
+    InvalidId(InvalidId),
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CursorError::MissingShape(shape) => {
+                write!(f, "The cursor theme has no cursor for shape {:?}", shape)
+            }
+            CursorError::InvalidId(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for CursorError {}
+
+impl From<InvalidId> for CursorError {
+    fn from(e: InvalidId) -> CursorError {
+        CursorError::InvalidId(e)
+    }
+}
+
+#[derive(Debug)]
+enum Backend {
+    #[cfg(feature = "cursor_shape")]
+    Shape(WpCursorShapeManagerV1),
+    Theme {
+        theme: CursorTheme,
+        surface: WlSurface,
+    },
+}
+
+#[derive(Debug)]
+struct PointerState {
+    pointer: WlPointer,
+    shape: CursorShape,
+    enter_serial: Option<u32>,
+    #[cfg(feature = "cursor_shape")]
+    device: Option<WpCursorShapeDeviceV1>,
+}
+
+/// Sets the cursor of pointers by shape
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct CursorManager {
+    backend: Backend,
+    pointers: Vec<PointerState>,
+}
+
+impl CursorManager {
+    /// Create a manager drawing the cursors from the default cursor theme
+    ///
+    /// The theme is loaded with [`CursorTheme::load()`].
+    pub fn new(
+        conn: &mut ConnectionHandle,
+        compositor: &WlCompositor,
+        shm: WlShm,
+        size: u32,
+    ) -> Result<CursorManager, InvalidId> {
+        let theme = CursorTheme::load(conn, shm, size)?;
+        CursorManager::with_theme(conn, compositor, theme)
+    }
+
+    /// Create a manager drawing the cursors from given cursor theme
+    ///
+    /// A surface is created to display the cursor images.
+    pub fn with_theme(
+        conn: &mut ConnectionHandle,
+        compositor: &WlCompositor,
+        theme: CursorTheme,
+    ) -> Result<CursorManager, InvalidId> {
+        let surface_id = conn
+            .send_request(
+                compositor,
+                wl_compositor::Request::CreateSurface {},
+                Some(Arc::new(IgnoreObjectData)),
+            )
+            .map_err(|_| InvalidId)?;
+        let surface = WlSurface::from_id(conn, surface_id)?;
+        Ok(CursorManager { backend: Backend::Theme { theme, surface }, pointers: Vec::new() })
+    }
+
+    /// Create a manager letting the compositor draw the cursors, using `wp_cursor_shape_v1`
+    #[cfg(feature = "cursor_shape")]
+    pub fn with_shape_manager(manager: WpCursorShapeManagerV1) -> CursorManager {
+        CursorManager { backend: Backend::Shape(manager), pointers: Vec::new() }
+    }
+
+    /// Create a manager using `wp_cursor_shape_v1` if available, and the default cursor theme
+    /// otherwise
+    #[cfg(feature = "cursor_shape")]
+    pub fn with_fallback(
+        conn: &mut ConnectionHandle,
+        manager: Option<WpCursorShapeManagerV1>,
+        compositor: &WlCompositor,
+        shm: WlShm,
+        size: u32,
+    ) -> Result<CursorManager, InvalidId> {
+        match manager {
+            Some(manager) => Ok(CursorManager::with_shape_manager(manager)),
+            None => CursorManager::new(conn, compositor, shm, size),
+        }
+    }
+
+    /// Notify the manager that a pointer entered a surface of the client
+    ///
+    /// The current shape of the pointer, [`CursorShape::Default`] if none was set, is applied
+    /// again.
+    pub fn pointer_enter(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        pointer: &WlPointer,
+        serial: u32,
+    ) -> Result<(), CursorError> {
+        let index = self.pointer_index(pointer);
+        self.pointers[index].enter_serial = Some(serial);
+        self.apply(conn, index)
+    }
+
+    /// Notify the manager that a pointer left the surfaces of the client
+    pub fn pointer_leave(&mut self, pointer: &WlPointer) {
+        if let Some(state) = self.pointers.iter_mut().find(|state| &state.pointer == pointer) {
+            state.enter_serial = None;
+        }
+    }
+
+    /// Set the shape of the cursor of a pointer
+    ///
+    /// The shape is applied right away if the pointer is over a surface of the client, and
+    /// otherwise the next time it enters one.
+    pub fn set_shape(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        pointer: &WlPointer,
+        shape: CursorShape,
+    ) -> Result<(), CursorError> {
+        let index = self.pointer_index(pointer);
+        self.pointers[index].shape = shape;
+        self.apply(conn, index)
+    }
+
+    /// The current shape of the cursor of a pointer
+    pub fn shape(&self, pointer: &WlPointer) -> CursorShape {
+        self.pointers
+            .iter()
+            .find(|state| &state.pointer == pointer)
+            .map(|state| state.shape)
+            .unwrap_or(CursorShape::Default)
+    }
+
+    /// Forget about a pointer, before releasing it
+    pub fn remove_pointer(&mut self, conn: &mut ConnectionHandle, pointer: &WlPointer) {
+        if let Some(index) = self.pointers.iter().position(|state| &state.pointer == pointer) {
+            let _state = self.pointers.remove(index);
+            #[cfg(feature = "cursor_shape")]
+            if let Some(device) = _state.device {
+                device.destroy(conn);
+            }
+        }
+        let _ = conn;
+    }
+
+    fn pointer_index(&mut self, pointer: &WlPointer) -> usize {
+        match self.pointers.iter().position(|state| &state.pointer == pointer) {
+            Some(index) => index,
+            None => {
+                self.pointers.push(PointerState {
+                    pointer: pointer.clone(),
+                    shape: CursorShape::Default,
+                    enter_serial: None,
+                    #[cfg(feature = "cursor_shape")]
+                    device: None,
+                });
+                self.pointers.len() - 1
+            }
+        }
+    }
+
+    fn apply(&mut self, conn: &mut ConnectionHandle, index: usize) -> Result<(), CursorError> {
+        let state = &mut self.pointers[index];
+        let serial = match state.enter_serial {
+            Some(serial) => serial,
+            None => return Ok(()),
+        };
+        match self.backend {
+            #[cfg(feature = "cursor_shape")]
+            Backend::Shape(ref manager) => {
+                if state.device.is_none() {
+                    let id = conn
+                        .send_request(
+                            manager,
+                            wp_cursor_shape_manager_v1::Request::GetPointer {
+                                pointer: state.pointer.clone(),
+                            },
+                            Some(Arc::new(IgnoreObjectData)),
+                        )
+                        .map_err(|_| InvalidId)?;
+                    state.device = Some(WpCursorShapeDeviceV1::from_id(conn, id)?);
+                }
+                if let Some(ref device) = state.device {
+                    device.set_shape(conn, serial, state.shape.wp_shape());
+                }
+                Ok(())
+            }
+            Backend::Theme { ref mut theme, ref surface } => {
+                let shape = state.shape;
+                // the loaded cursors are cached by the theme, looking them up again is cheap
+                let name = theme_name(shape, |name| theme.get_cursor(conn, name).is_some())
+                    .ok_or(CursorError::MissingShape(shape))?;
+                let cursor = theme.get_cursor(conn, name).unwrap();
+                let image = &cursor[0];
+                let (width, height) = image.dimensions();
+                let (hotspot_x, hotspot_y) = image.hotspot();
+                surface.attach(conn, Some(image), 0, 0);
+                if surface.version() >= 4 {
+                    surface.damage_buffer(conn, 0, 0, width as i32, height as i32);
+                } else {
+                    surface.damage(conn, 0, 0, width as i32, height as i32);
+                }
+                surface.commit(conn);
+                state.pointer.set_cursor(
+                    conn,
+                    serial,
+                    Some(surface),
+                    hotspot_x as i32,
+                    hotspot_y as i32,
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The name of the first cursor image of `shape` provided by the theme
+fn theme_name(shape: CursorShape, mut provided: impl FnMut(&str) -> bool) -> Option<&'static str> {
+    shape.names().iter().copied().find(|name| provided(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{theme_name, CursorShape};
+
+    #[test]
+    fn name_round_trip() {
+        for &shape in CursorShape::ALL {
+            assert_eq!(CursorShape::from_name(shape.names()[0]), Some(shape));
+        }
+        assert_eq!(CursorShape::from_name("left_ptr"), Some(CursorShape::Default));
+        assert_eq!(CursorShape::from_name("xterm"), Some(CursorShape::Text));
+        // shared legacy name, the first shape declared wins
+        assert_eq!(CursorShape::from_name("sb_h_double_arrow"), Some(CursorShape::EwResize));
+        assert_eq!(CursorShape::from_name("no-such-cursor"), None);
+    }
+
+    #[test]
+    fn theme_lookup_fallback() {
+        // the CSS name is preferred when the theme has it
+        assert_eq!(theme_name(CursorShape::Pointer, |_| true), Some("pointer"));
+        // otherwise the legacy names are tried in order
+        assert_eq!(theme_name(CursorShape::Pointer, |name| name == "hand1"), Some("hand1"));
+        assert_eq!(
+            theme_name(CursorShape::Pointer, |name| name == "hand1" || name == "hand2"),
+            Some("hand2")
+        );
+        assert_eq!(theme_name(CursorShape::ZoomIn, |name| name != "zoom-in"), None);
+    }
+}
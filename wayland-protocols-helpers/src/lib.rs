@@ -0,0 +1,91 @@
+//! Client-side helpers for the protocol extensions of `wayland-protocols`
+//!
+//! The bindings of `wayland-protocols` only define the objects of the protocols. The helpers of
+//! this crate build on them and on `wayland-client` to drive these objects for the common use
+//! cases, keeping track of their state.
+//!
+//! The modules follow the layout of `wayland-protocols`, and are enabled by the same cargo
+//! features:
+//!
+//! - `staging_protocols` adds a `staging` module, with the helpers of the protocols in the staging
+//!   process
+//! - `unstable_protocols` adds an `unstable` module, with the helpers of the protocols that are not
+//!   yet considered stable
+//! - `wlr_protocols` adds a `wlr` module, with the helpers of the protocols of the wlroots family
+
+#![warn(missing_docs)]
+
+pub mod xdg_shell {
+    //! Helpers for the XDG Shell protocol
+
+    pub mod window;
+}
+
+#[cfg(feature = "staging_protocols")]
+pub mod staging {
+    //! Helpers for the staging protocols
+
+    pub mod session_lock {
+        //! Helpers for the session lock protocol
+
+        pub mod v1 {
+            //! Version 1
+
+            pub mod lock;
+        }
+    }
+}
+
+#[cfg(feature = "unstable_protocols")]
+pub mod unstable {
+    //! Helpers for the unstable protocols
+
+    pub mod idle_inhibit {
+        //! Helpers for the screensaver inhibition protocol
+
+        pub mod v1 {
+            //! Unstable version 1
+
+            pub mod inhibitor;
+        }
+    }
+
+    pub mod linux_dmabuf {
+        //! Helpers for the Linux DMA-BUF protocol
+
+        pub mod v1 {
+            //! Unstable version 1
+
+            pub mod feedback;
+        }
+    }
+
+    pub mod pointer_gestures {
+        //! Helpers for the pointer gestures protocol
+
+        pub mod v1 {
+            //! Unstable version 1
+
+            pub mod hold;
+        }
+    }
+}
+
+#[cfg(feature = "wlr_protocols")]
+pub mod wlr {
+    //! Helpers for the protocols of the wlroots family
+
+    pub mod unstable {
+        //! Helpers for the unstable protocols of the wlroots family
+
+        pub mod screencopy {
+            //! Helpers for the screencopy protocol
+
+            pub mod v1 {
+                //! Unstable version 1
+
+                pub mod capture;
+            }
+        }
+    }
+}
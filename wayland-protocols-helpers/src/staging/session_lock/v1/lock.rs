@@ -0,0 +1,420 @@
+//! The lifecycle of a session lock and the configuration of its surfaces
+//!
+//! The [`SessionLockState`] can be used as a [`DelegateDispatch`] target for the
+//! `ext_session_lock_manager_v1`, `ext_session_lock_v1` and `ext_session_lock_surface_v1`
+//! interfaces. It creates [`SessionLock`]s and their [`LockSurface`]s, and takes care of the
+//! requests the protocol expects in response to the events of the compositor:
+//!
+//! - the configurations of the lock surfaces are acknowledged before a
+//!   [`SessionLockEvent::Configure`] is reported, so the next commit of the surface is expected to
+//!   attach a buffer of the configured size;
+//! - a lock is released with `unlock_and_destroy` once the session was locked, and with `destroy`
+//!   otherwise, as using the other request is a protocol error;
+//! - the lock surfaces of a lock are destroyed right before it is released;
+//! - when the compositor ends a lock, it is released along with its lock surfaces before a
+//!   [`SessionLockEvent::Finished`] is reported.
+//!
+//! The session is only locked once [`SessionLockEvent::Locked`] is reported, and a lock surface
+//! should be created for each output, including the outputs appearing while the session is locked.
+//!
+//! ```no_run
+//! use wayland_client::{delegate_dispatch, protocol::{wl_output, wl_surface}};
+//! use wayland_protocols::staging::session_lock::v1::client::{
+//!     ext_session_lock_manager_v1, ext_session_lock_surface_v1, ext_session_lock_v1,
+//! };
+//! use wayland_protocols_helpers::staging::session_lock::v1::lock::{
+//!     SessionLockEvent, SessionLockState,
+//! };
+//!
+//! struct App {
+//!     session_lock: SessionLockState,
+//! }
+//!
+//! impl AsMut<SessionLockState> for App {
+//!     fn as_mut(&mut self) -> &mut SessionLockState {
+//!         &mut self.session_lock
+//!     }
+//! }
+//!
+//! delegate_dispatch!(App: [
+//!     ext_session_lock_manager_v1::ExtSessionLockManagerV1,
+//!     ext_session_lock_v1::ExtSessionLockV1,
+//!     ext_session_lock_surface_v1::ExtSessionLockSurfaceV1
+//! ] => SessionLockState);
+//!
+//! # fn run(conn: &mut wayland_client::ConnectionHandle, qh: &wayland_client::QueueHandle<App>, outputs: Vec<(wl_surface::WlSurface, wl_output::WlOutput)>, app: &mut App) {
+//! let lock = app.session_lock.lock(conn, qh).unwrap();
+//! for (surface, output) in outputs {
+//!     app.session_lock.create_lock_surface(conn, qh, &lock, surface, &output).unwrap();
+//! }
+//!
+//! // after each dispatch of the event queue
+//! for event in app.session_lock.take_events() {
+//!     match event {
+//!         SessionLockEvent::Locked { lock } => { /* ask for the password */ }
+//!         SessionLockEvent::Finished { lock } => { /* the compositor ended the lock */ }
+//!         SessionLockEvent::Configure { surface, configure } => {
+//!             let (width, height) = configure.size;
+//!             /* draw the lock screen with this size, attach the buffer and commit */
+//!         }
+//!     }
+//! }
+//!
+//! // once the user is authenticated
+//! app.session_lock.unlock(conn, &lock);
+//! # }
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use wayland_client::{
+    protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError, QueueHandle,
+};
+
+use wayland_protocols::staging::session_lock::v1::client::{
+    ext_session_lock_manager_v1::{self, ExtSessionLockManagerV1},
+    ext_session_lock_surface_v1::{self, ExtSessionLockSurfaceV1},
+    ext_session_lock_v1::{self, ExtSessionLockV1},
+};
+
+/// An event of a session lock, as reported by [`SessionLockState::take_events()`]
+#[derive(Debug, Clone)]
+pub enum SessionLockEvent {
+    /// The session is locked
+    ///
+    /// From now on, the lock can only be released with [`SessionLockState::unlock()`], which
+    /// unlocks the session.
+    Locked {
+        /// the lock
+        lock: SessionLock,
+    },
+    /// The compositor ended the lock
+    ///
+    /// This happens right after its creation if the compositor denied it, for example because the
+    /// session was already locked by another client. The lock and its lock surfaces are already
+    /// destroyed, the `wl_surface`s are left for the application to destroy or reuse.
+    Finished {
+        /// the lock
+        lock: SessionLock,
+    },
+    /// A lock surface was configured
+    ///
+    /// The configuration is already acknowledged, the next commit of the surface must attach a
+    /// buffer of the configured size.
+    Configure {
+        /// the lock surface
+        surface: LockSurface,
+        /// its new configuration
+        configure: LockSurfaceConfigure,
+    },
+}
+
+/// The configuration of a lock surface by the compositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockSurfaceConfigure {
+    /// The size the buffers of the surface must have, in surface-local coordinates
+    pub size: (u32, u32),
+    /// The serial of this configuration
+    pub serial: u32,
+}
+
+#[derive(Debug, Default)]
+struct LockInner {
+    locked: bool,
+    finished: bool,
+}
+
+/// A session lock, an `ext_session_lock_v1`
+///
+/// Locks are created by [`SessionLockState::lock()`]. This is a handle, its clones refer to the
+/// same lock.
+#[derive(Debug, Clone)]
+pub struct SessionLock {
+    lock: ExtSessionLockV1,
+    inner: Arc<Mutex<LockInner>>,
+}
+
+impl PartialEq for SessionLock {
+    fn eq(&self, other: &SessionLock) -> bool {
+        self.lock == other.lock
+    }
+}
+
+impl Eq for SessionLock {}
+
+impl SessionLock {
+    /// The `ext_session_lock_v1` of this lock
+    pub fn ext_session_lock(&self) -> &ExtSessionLockV1 {
+        &self.lock
+    }
+
+    /// Whether the session is locked by this lock
+    pub fn is_locked(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.locked && !inner.finished
+    }
+
+    /// Whether the compositor ended this lock
+    pub fn is_finished(&self) -> bool {
+        self.inner.lock().unwrap().finished
+    }
+}
+
+/// A lock surface, an `ext_session_lock_surface_v1` and its surface, displayed on an output
+///
+/// Lock surfaces are created by [`SessionLockState::create_lock_surface()`]. This is a handle, its
+/// clones refer to the same lock surface.
+#[derive(Debug, Clone)]
+pub struct LockSurface {
+    surface: WlSurface,
+    lock_surface: ExtSessionLockSurfaceV1,
+    output: WlOutput,
+    lock: SessionLock,
+    configure: Arc<Mutex<Option<LockSurfaceConfigure>>>,
+}
+
+impl PartialEq for LockSurface {
+    fn eq(&self, other: &LockSurface) -> bool {
+        self.lock_surface == other.lock_surface
+    }
+}
+
+impl Eq for LockSurface {}
+
+impl LockSurface {
+    /// The `wl_surface` of this lock surface
+    pub fn wl_surface(&self) -> &WlSurface {
+        &self.surface
+    }
+
+    /// The `ext_session_lock_surface_v1` of this lock surface
+    pub fn ext_session_lock_surface(&self) -> &ExtSessionLockSurfaceV1 {
+        &self.lock_surface
+    }
+
+    /// The output this lock surface is displayed on
+    pub fn output(&self) -> &WlOutput {
+        &self.output
+    }
+
+    /// The lock this lock surface belongs to
+    pub fn lock(&self) -> &SessionLock {
+        &self.lock
+    }
+
+    /// The last configuration of this lock surface, `None` if it was not configured yet
+    ///
+    /// No buffer may be attached to the surface before it was configured.
+    pub fn configure(&self) -> Option<LockSurfaceConfigure> {
+        *self.configure.lock().unwrap()
+    }
+}
+
+/// A helper handling the `ext_session_lock_manager_v1` global and the locks created from it
+///
+/// A lock and its lock surfaces are tracked until the lock is released with
+/// [`unlock()`](SessionLockState::unlock) or ended by the compositor, their events being reported
+/// by [`take_events()`](SessionLockState::take_events) in the order they were received.
+#[derive(Debug)]
+pub struct SessionLockState {
+    manager: ExtSessionLockManagerV1,
+    locks: Vec<SessionLock>,
+    surfaces: Vec<LockSurface>,
+    events: Vec<SessionLockEvent>,
+}
+
+impl SessionLockState {
+    /// Create a new `SessionLockState` from the `ext_session_lock_manager_v1` global
+    ///
+    /// Only one client can lock the session at a time, the compositor ends the locks requested
+    /// while the session is already locked.
+    pub fn new(manager: ExtSessionLockManagerV1) -> SessionLockState {
+        SessionLockState { manager, locks: Vec::new(), surfaces: Vec::new(), events: Vec::new() }
+    }
+
+    /// The `ext_session_lock_manager_v1` global
+    pub fn manager(&self) -> &ExtSessionLockManagerV1 {
+        &self.manager
+    }
+
+    /// Request the compositor to lock the session
+    ///
+    /// The session is locked once [`SessionLockEvent::Locked`] is reported for the returned lock,
+    /// or the request was denied if [`SessionLockEvent::Finished`] is reported instead.
+    pub fn lock<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+    ) -> Result<SessionLock, DispatchError>
+    where
+        D: Dispatch<ExtSessionLockV1, UserData = ()> + 'static,
+    {
+        let lock = self.manager.lock(conn, qh, ())?;
+        let lock = SessionLock { lock, inner: Arc::new(Mutex::new(Default::default())) };
+        self.locks.push(lock.clone());
+        Ok(lock)
+    }
+
+    /// Create a lock surface displayed on an output while the session is locked
+    ///
+    /// The surface must not have a role yet, nor a buffer attached. There can only be one lock
+    /// surface per output for a given lock.
+    pub fn create_lock_surface<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        lock: &SessionLock,
+        surface: WlSurface,
+        output: &WlOutput,
+    ) -> Result<LockSurface, DispatchError>
+    where
+        D: Dispatch<ExtSessionLockSurfaceV1, UserData = ()> + 'static,
+    {
+        let lock_surface = lock.lock.get_lock_surface(conn, &surface, output, qh, ())?;
+        let lock_surface = LockSurface {
+            surface,
+            lock_surface,
+            output: output.clone(),
+            lock: lock.clone(),
+            configure: Arc::new(Mutex::new(None)),
+        };
+        self.surfaces.push(lock_surface.clone());
+        Ok(lock_surface)
+    }
+
+    /// Destroy a lock surface, for example when its output is removed
+    ///
+    /// Its `wl_surface` is left for the application to destroy or reuse.
+    pub fn destroy_lock_surface(&mut self, conn: &mut ConnectionHandle, surface: &LockSurface) {
+        surface.lock_surface.destroy(conn);
+        self.surfaces.retain(|s| s != surface);
+    }
+
+    /// Release a lock, unlocking the session if it was locked by it
+    ///
+    /// Its lock surfaces are destroyed, their `wl_surface`s are left for the application to
+    /// destroy or reuse. Does nothing if the compositor already ended the lock.
+    pub fn unlock(&mut self, conn: &mut ConnectionHandle, lock: &SessionLock) {
+        if self.locks.contains(lock) {
+            self.release(conn, lock);
+        }
+    }
+
+    /// The locks created by this helper and not released yet
+    pub fn locks(&self) -> &[SessionLock] {
+        &self.locks
+    }
+
+    /// The lock surfaces created by this helper and not destroyed yet
+    pub fn lock_surfaces(&self) -> &[LockSurface] {
+        &self.surfaces
+    }
+
+    /// Take the session lock events received since the last call
+    pub fn take_events(&mut self) -> Vec<SessionLockEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn release(&mut self, conn: &mut ConnectionHandle, lock: &SessionLock) {
+        // the lock surfaces go first, so that none outlives its lock
+        for surface in self.surfaces.iter().filter(|s| &s.lock == lock) {
+            surface.lock_surface.destroy(conn);
+        }
+        self.surfaces.retain(|s| &s.lock != lock);
+        // once the locked event was received, destroy is a protocol error
+        if lock.inner.lock().unwrap().locked {
+            lock.lock.unlock_and_destroy(conn);
+        } else {
+            lock.lock.destroy(conn);
+        }
+        self.locks.retain(|l| l != lock);
+    }
+}
+
+impl DelegateDispatchBase<ExtSessionLockManagerV1> for SessionLockState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ExtSessionLockManagerV1, D> for SessionLockState
+where
+    D: Dispatch<ExtSessionLockManagerV1, UserData = ()> + AsMut<SessionLockState>,
+{
+    fn event(
+        _: &mut D,
+        _: &ExtSessionLockManagerV1,
+        _: ext_session_lock_manager_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
+
+impl DelegateDispatchBase<ExtSessionLockV1> for SessionLockState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ExtSessionLockV1, D> for SessionLockState
+where
+    D: Dispatch<ExtSessionLockV1, UserData = ()> + AsMut<SessionLockState>,
+{
+    fn event(
+        data: &mut D,
+        ext_lock: &ExtSessionLockV1,
+        event: ext_session_lock_v1::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let lock = match me.locks.iter().find(|lock| &lock.lock == ext_lock) {
+            Some(lock) => lock.clone(),
+            None => return,
+        };
+        match event {
+            ext_session_lock_v1::Event::Locked => {
+                lock.inner.lock().unwrap().locked = true;
+                me.events.push(SessionLockEvent::Locked { lock });
+            }
+            ext_session_lock_v1::Event::Finished => {
+                lock.inner.lock().unwrap().finished = true;
+                // the lock is of no use anymore, the compositor expects it to be destroyed
+                me.release(conn, &lock);
+                me.events.push(SessionLockEvent::Finished { lock });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl DelegateDispatchBase<ExtSessionLockSurfaceV1> for SessionLockState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ExtSessionLockSurfaceV1, D> for SessionLockState
+where
+    D: Dispatch<ExtSessionLockSurfaceV1, UserData = ()> + AsMut<SessionLockState>,
+{
+    fn event(
+        data: &mut D,
+        lock_surface: &ExtSessionLockSurfaceV1,
+        event: ext_session_lock_surface_v1::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let surface = match me.surfaces.iter().find(|s| &s.lock_surface == lock_surface) {
+            Some(surface) => surface.clone(),
+            None => return,
+        };
+        if let ext_session_lock_surface_v1::Event::Configure { serial, width, height } = event {
+            let configure = LockSurfaceConfigure { size: (width, height), serial };
+            *surface.configure.lock().unwrap() = Some(configure);
+            // acknowledge before the application gets a chance to commit the surface
+            lock_surface.ack_configure(conn, serial);
+            me.events.push(SessionLockEvent::Configure { surface, configure });
+        }
+    }
+}
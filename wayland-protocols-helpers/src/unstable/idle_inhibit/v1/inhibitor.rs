@@ -0,0 +1,204 @@
+//! Idle inhibitors tied to the visibility of their surfaces
+//!
+//! An inhibitor only prevents the system from going idle while its surface is visible, and the
+//! compositor does not tell the client when it stops being effective. The [`IdleInhibitState`]
+//! tracks which surfaces want to inhibit idleness, and only keeps an inhibitor alive for those
+//! which the application reports as visible, for example from the `enter` and `leave` events of
+//! the `wl_surface`, or when its window is minimized.
+//!
+//! It can be used as a [`DelegateDispatch`] target for the `zwp_idle_inhibit_manager_v1` and
+//! `zwp_idle_inhibitor_v1` interfaces, which have no events:
+//!
+//! ```no_run
+//! use wayland_client::{delegate_dispatch, protocol::wl_surface};
+//! use wayland_protocols::unstable::idle_inhibit::v1::client::{
+//!     zwp_idle_inhibit_manager_v1, zwp_idle_inhibitor_v1,
+//! };
+//! use wayland_protocols_helpers::unstable::idle_inhibit::v1::inhibitor::IdleInhibitState;
+//!
+//! struct App {
+//!     idle_inhibit: IdleInhibitState,
+//! }
+//!
+//! impl AsMut<IdleInhibitState> for App {
+//!     fn as_mut(&mut self) -> &mut IdleInhibitState {
+//!         &mut self.idle_inhibit
+//!     }
+//! }
+//!
+//! delegate_dispatch!(App: [
+//!     zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1,
+//!     zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1
+//! ] => IdleInhibitState);
+//!
+//! # fn run(conn: &mut wayland_client::ConnectionHandle, qh: &wayland_client::QueueHandle<App>, surface: wl_surface::WlSurface, app: &mut App) {
+//! // while a video is playing
+//! app.idle_inhibit.inhibit(conn, qh, &surface).unwrap();
+//!
+//! // when the surface leaves its last output, or enters one again
+//! app.idle_inhibit.set_visible(conn, qh, &surface, false).unwrap();
+//!
+//! // once the video is stopped, or before destroying the surface
+//! app.idle_inhibit.uninhibit(conn, &surface);
+//! # }
+//! ```
+
+use wayland_client::{
+    protocol::wl_surface::WlSurface, ConnectionHandle, DelegateDispatch, DelegateDispatchBase,
+    Dispatch, DispatchError, QueueHandle,
+};
+
+use wayland_protocols::unstable::idle_inhibit::v1::client::{
+    zwp_idle_inhibit_manager_v1::{self, ZwpIdleInhibitManagerV1},
+    zwp_idle_inhibitor_v1::{self, ZwpIdleInhibitorV1},
+};
+
+#[derive(Debug)]
+struct InhibitedSurface {
+    surface: WlSurface,
+    visible: bool,
+    inhibitor: Option<ZwpIdleInhibitorV1>,
+}
+
+/// A helper handling the `zwp_idle_inhibit_manager_v1` global and the inhibitors created from it
+///
+/// It keeps track of the surfaces inhibiting idleness, of whether they are visible, and of the
+/// inhibitor of each visible one.
+#[derive(Debug)]
+pub struct IdleInhibitState {
+    manager: ZwpIdleInhibitManagerV1,
+    surfaces: Vec<InhibitedSurface>,
+}
+
+impl IdleInhibitState {
+    /// Create a new `IdleInhibitState` from the `zwp_idle_inhibit_manager_v1` global
+    ///
+    /// The manager creates a new inhibitor each time a surface becomes visible again, it must not be
+    /// destroyed while the helper is in use.
+    pub fn new(manager: ZwpIdleInhibitManagerV1) -> IdleInhibitState {
+        IdleInhibitState { manager, surfaces: Vec::new() }
+    }
+
+    /// The `zwp_idle_inhibit_manager_v1` global
+    pub fn manager(&self) -> &ZwpIdleInhibitManagerV1 {
+        &self.manager
+    }
+
+    /// Inhibit idleness while the surface is visible
+    ///
+    /// The surface is considered visible until the application reports otherwise with
+    /// [`set_visible()`](IdleInhibitState::set_visible), so an inhibitor is created right away if
+    /// the surface was not tracked yet. Does nothing if it already was.
+    pub fn inhibit<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        surface: &WlSurface,
+    ) -> Result<(), DispatchError>
+    where
+        D: Dispatch<ZwpIdleInhibitorV1, UserData = ()> + 'static,
+    {
+        if self.surfaces.iter().any(|s| &s.surface == surface) {
+            return Ok(());
+        }
+        let inhibitor = self.manager.create_inhibitor(conn, surface, qh, ())?;
+        self.surfaces.push(InhibitedSurface {
+            surface: surface.clone(),
+            visible: true,
+            inhibitor: Some(inhibitor),
+        });
+        Ok(())
+    }
+
+    /// Stop inhibiting idleness for this surface
+    ///
+    /// Its inhibitor is destroyed if it had one. This should be done before destroying the surface,
+    /// the inhibitor of a destroyed surface is inert but stays alive otherwise.
+    pub fn uninhibit(&mut self, conn: &mut ConnectionHandle, surface: &WlSurface) {
+        if let Some(pos) = self.surfaces.iter().position(|s| &s.surface == surface) {
+            if let Some(inhibitor) = self.surfaces.remove(pos).inhibitor {
+                inhibitor.destroy(conn);
+            }
+        }
+    }
+
+    /// Report whether the surface is visible
+    ///
+    /// If the surface inhibits idleness, its inhibitor is destroyed when it becomes hidden, and
+    /// created again once it is visible. Does nothing for the other surfaces.
+    pub fn set_visible<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        surface: &WlSurface,
+        visible: bool,
+    ) -> Result<(), DispatchError>
+    where
+        D: Dispatch<ZwpIdleInhibitorV1, UserData = ()> + 'static,
+    {
+        let inhibited = match self.surfaces.iter_mut().find(|s| &s.surface == surface) {
+            Some(inhibited) => inhibited,
+            None => return Ok(()),
+        };
+        inhibited.visible = visible;
+        match (visible, inhibited.inhibitor.take()) {
+            (true, None) => {
+                inhibited.inhibitor = Some(self.manager.create_inhibitor(conn, surface, qh, ())?);
+            }
+            (false, Some(inhibitor)) => inhibitor.destroy(conn),
+            (_, inhibitor) => inhibited.inhibitor = inhibitor,
+        }
+        Ok(())
+    }
+
+    /// Whether idleness is currently inhibited by this surface
+    ///
+    /// This is the case if it was given to [`inhibit()`](IdleInhibitState::inhibit) and is
+    /// visible.
+    pub fn is_inhibiting(&self, surface: &WlSurface) -> bool {
+        self.surfaces.iter().any(|s| &s.surface == surface && s.inhibitor.is_some())
+    }
+
+    /// The surfaces given to [`inhibit()`](IdleInhibitState::inhibit), visible or not
+    pub fn surfaces(&self) -> impl Iterator<Item = &WlSurface> {
+        self.surfaces.iter().map(|s| &s.surface)
+    }
+}
+
+impl DelegateDispatchBase<ZwpIdleInhibitManagerV1> for IdleInhibitState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ZwpIdleInhibitManagerV1, D> for IdleInhibitState
+where
+    D: Dispatch<ZwpIdleInhibitManagerV1, UserData = ()> + AsMut<IdleInhibitState>,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpIdleInhibitManagerV1,
+        _: zwp_idle_inhibit_manager_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
+
+impl DelegateDispatchBase<ZwpIdleInhibitorV1> for IdleInhibitState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ZwpIdleInhibitorV1, D> for IdleInhibitState
+where
+    D: Dispatch<ZwpIdleInhibitorV1, UserData = ()> + AsMut<IdleInhibitState>,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpIdleInhibitorV1,
+        _: zwp_idle_inhibitor_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
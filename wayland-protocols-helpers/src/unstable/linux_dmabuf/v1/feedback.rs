@@ -0,0 +1,251 @@
+//! Parsing of the dmabuf feedback
+//!
+//! Since version 4 of `zwp_linux_dmabuf_v1`, the compositor describes the devices and the
+//! format/modifier pairs it supports through the events of a `zwp_linux_dmabuf_feedback_v1`: the
+//! pairs are stored in a table shared through a file descriptor, and grouped by preference into
+//! tranches referring to the table by index.
+//!
+//! A [`FeedbackParser`] is fed the events of a feedback object, and assembles them into a
+//! [`DmabufFeedback`] each time the compositor is done sending a new feedback:
+//!
+//! ```no_run
+//! use wayland_protocols::unstable::linux_dmabuf::v1::client::zwp_linux_dmabuf_feedback_v1;
+//! use wayland_protocols_helpers::unstable::linux_dmabuf::v1::feedback::FeedbackParser;
+//!
+//! # fn handle(parser: &mut FeedbackParser, event: zwp_linux_dmabuf_feedback_v1::Event) {
+//! // in the `Dispatch` implementation for `ZwpLinuxDmabufFeedbackV1`
+//! match parser.handle_event(event) {
+//!     Ok(Some(feedback)) => {
+//!         for tranche in &feedback.tranches {
+//!             /* pick a format from tranche.formats, by order of preference of the tranches */
+//!         }
+//!     }
+//!     Ok(None) => { /* the feedback is not complete yet */ }
+//!     Err(e) => eprintln!("Invalid dmabuf feedback: {}", e),
+//! }
+//! # }
+//! ```
+
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::os::unix::io::RawFd;
+
+use nix::libc::dev_t;
+use nix::sys::{mman, stat};
+use nix::unistd;
+use wayland_client::WEnum;
+
+use wayland_protocols::unstable::linux_dmabuf::v1::client::zwp_linux_dmabuf_feedback_v1::{
+    Event, TrancheFlags,
+};
+
+/// A format and modifier pair supported by the compositor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DmabufFormat {
+    /// The `fourcc` code of the format, as defined in `drm_fourcc.h`
+    pub format: u32,
+    /// The modifier of the format
+    pub modifier: u64,
+}
+
+/// A set of formats with the same preference, for a given device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmabufTranche {
+    /// The device the buffers using these formats must be allocated on
+    pub target_device: dev_t,
+    /// The formats of the tranche
+    pub formats: Vec<DmabufFormat>,
+    /// The flags of the tranche
+    pub flags: TrancheFlags,
+}
+
+/// A complete dmabuf feedback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmabufFeedback {
+    /// The main device of the compositor, with which it imports the buffers
+    pub main_device: dev_t,
+    /// The tranches of the feedback, by decreasing order of preference
+    pub tranches: Vec<DmabufTranche>,
+}
+
+/// An error of a dmabuf feedback
+#[derive(Debug)]
+pub enum FeedbackError {
+    /// The format table could not be mapped, or is malformed
+    FormatTable(io::Error),
+    /// A tranche refers to an index outside of the format table
+    InvalidFormatIndex(u16),
+    /// A device was sent with a size different from the one of `dev_t`
+    InvalidDevice,
+    /// The feedback was completed without its main device
+    MissingMainDevice,
+}
+
+impl fmt::Display for FeedbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedbackError::FormatTable(e) => write!(f, "Invalid format table: {}", e),
+            FeedbackError::InvalidFormatIndex(i) => {
+                write!(f, "Format index {} is outside of the format table", i)
+            }
+            FeedbackError::InvalidDevice => write!(f, "Invalid device number"),
+            FeedbackError::MissingMainDevice => write!(f, "The main device was not sent"),
+        }
+    }
+}
+
+impl Error for FeedbackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FeedbackError::FormatTable(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingTranche {
+    target_device: Option<dev_t>,
+    formats: Vec<DmabufFormat>,
+    flags: TrancheFlags,
+}
+
+impl Default for PendingTranche {
+    fn default() -> PendingTranche {
+        PendingTranche { target_device: None, formats: Vec::new(), flags: TrancheFlags::empty() }
+    }
+}
+
+/// Assembles the events of a `zwp_linux_dmabuf_feedback_v1` into [`DmabufFeedback`]s
+///
+/// The format table is kept from one feedback to the next, as the compositor only sends it again
+/// when it changes.
+#[derive(Debug, Default)]
+pub struct FeedbackParser {
+    format_table: Vec<DmabufFormat>,
+    main_device: Option<dev_t>,
+    tranches: Vec<DmabufTranche>,
+    pending_tranche: PendingTranche,
+}
+
+impl FeedbackParser {
+    /// Create a new parser, for a newly created feedback object
+    pub fn new() -> FeedbackParser {
+        FeedbackParser::default()
+    }
+
+    /// The current format table
+    pub fn format_table(&self) -> &[DmabufFormat] {
+        &self.format_table
+    }
+
+    /// Process an event of the feedback object
+    ///
+    /// Returns the new feedback on the `done` event. The file descriptor of the `format_table`
+    /// event is closed once the table is read, and a malformed table leaves the previous one in
+    /// place.
+    pub fn handle_event(&mut self, event: Event) -> Result<Option<DmabufFeedback>, FeedbackError> {
+        match event {
+            Event::FormatTable { fd, size } => {
+                let table = read_format_table(fd, size as usize);
+                let _ = unistd::close(fd);
+                self.format_table = table.map_err(FeedbackError::FormatTable)?;
+            }
+            Event::MainDevice { device } => {
+                self.main_device = Some(parse_device(&device)?);
+            }
+            Event::TrancheTargetDevice { device } => {
+                self.pending_tranche.target_device = Some(parse_device(&device)?);
+            }
+            Event::TrancheFormats { indices } => {
+                for index in indices.chunks_exact(2) {
+                    let index = u16::from_ne_bytes([index[0], index[1]]);
+                    let format = self
+                        .format_table
+                        .get(index as usize)
+                        .ok_or(FeedbackError::InvalidFormatIndex(index))?;
+                    self.pending_tranche.formats.push(*format);
+                }
+            }
+            Event::TrancheFlags { flags } => {
+                self.pending_tranche.flags = match flags {
+                    WEnum::Value(flags) => flags,
+                    WEnum::Unknown(raw) => TrancheFlags::from_bits_truncate(raw),
+                };
+            }
+            Event::TrancheDone => {
+                let tranche = std::mem::take(&mut self.pending_tranche);
+                let main_device = self.main_device.ok_or(FeedbackError::MissingMainDevice)?;
+                self.tranches.push(DmabufTranche {
+                    // the target device of a tranche defaults to the main device
+                    target_device: tranche.target_device.unwrap_or(main_device),
+                    formats: tranche.formats,
+                    flags: tranche.flags,
+                });
+            }
+            Event::Done => {
+                // the next feedback starts from scratch, except for the format table
+                self.pending_tranche = PendingTranche::default();
+                let tranches = std::mem::take(&mut self.tranches);
+                let main_device =
+                    self.main_device.take().ok_or(FeedbackError::MissingMainDevice)?;
+                return Ok(Some(DmabufFeedback { main_device, tranches }));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+}
+
+/// Parse a `dev_t` from the array of an event
+fn parse_device(array: &[u8]) -> Result<dev_t, FeedbackError> {
+    Ok(dev_t::from_ne_bytes(array.try_into().map_err(|_| FeedbackError::InvalidDevice)?))
+}
+
+/// Read the format table, an array of 16 bytes entries: the format as an `u32`, 4 bytes of
+/// padding, and the modifier as an `u64`
+///
+/// The table is rejected if its size is not a whole number of entries, or if the file is shorter
+/// than the advertised size.
+fn read_format_table(fd: RawFd, size: usize) -> io::Result<Vec<DmabufFormat>> {
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+    // reading a mapping beyond the end of its file raises SIGBUS
+    if (stat::fstat(fd)?.st_size as u64) < size as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "The format table is larger than its file",
+        ));
+    }
+    // the table must be mapped privately, the compositor may share the same file with all clients
+    let ptr = unsafe {
+        mman::mmap(
+            std::ptr::null_mut(),
+            size,
+            mman::ProtFlags::PROT_READ,
+            mman::MapFlags::MAP_PRIVATE,
+            fd,
+            0,
+        )?
+    };
+    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, size) };
+    let entries = bytes.chunks_exact(16);
+    let table = if entries.remainder().is_empty() {
+        Ok(entries
+            .map(|entry| DmabufFormat {
+                format: u32::from_ne_bytes(entry[0..4].try_into().unwrap()),
+                modifier: u64::from_ne_bytes(entry[8..16].try_into().unwrap()),
+            })
+            .collect())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The size of the format table is not a multiple of 16 bytes",
+        ))
+    };
+    let _ = unsafe { mman::munmap(ptr, size) };
+    table
+}
@@ -0,0 +1,212 @@
+//! Hold gestures reported along with the input events of the seats
+//!
+//! A hold gesture is performed by resting fingers on a touchpad without moving them, which is
+//! typically used to stop kinetic scrolling. The [`HoldGestureState`] creates a
+//! `zwp_pointer_gesture_hold_v1` for each pointer of the [`SeatState`] of `wayland-client`, and
+//! adds the events of these gestures to its input events, as [`InputEvent::PointerHoldBegin`] and
+//! [`InputEvent::PointerHoldEnd`].
+//!
+//! Hold gestures were added in version 3 of `zwp_pointer_gestures_v1`, nothing is reported if the
+//! compositor only supports an older version.
+//!
+//! ```no_run
+//! use wayland_client::{delegate_dispatch, protocol::{wl_seat, wl_pointer, wl_keyboard, wl_touch}, seat::{InputEvent, SeatState}};
+//! use wayland_protocols::unstable::pointer_gestures::v1::client::{
+//!     zwp_pointer_gesture_hold_v1, zwp_pointer_gestures_v1,
+//! };
+//! use wayland_protocols_helpers::unstable::pointer_gestures::v1::hold::HoldGestureState;
+//!
+//! struct App {
+//!     seats: SeatState,
+//!     gestures: HoldGestureState,
+//! }
+//!
+//! impl AsMut<SeatState> for App {
+//!     fn as_mut(&mut self) -> &mut SeatState {
+//!         &mut self.seats
+//!     }
+//! }
+//!
+//! impl AsMut<HoldGestureState> for App {
+//!     fn as_mut(&mut self) -> &mut HoldGestureState {
+//!         &mut self.gestures
+//!     }
+//! }
+//!
+//! delegate_dispatch!(App: [wl_seat::WlSeat, wl_pointer::WlPointer, wl_keyboard::WlKeyboard, wl_touch::WlTouch] => SeatState);
+//! delegate_dispatch!(App: [
+//!     zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+//!     zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1
+//! ] => HoldGestureState);
+//!
+//! # fn run(conn: &mut wayland_client::ConnectionHandle, qh: &wayland_client::QueueHandle<App>, app: &mut App) {
+//! // after each dispatch of the event queue
+//! let events = app.seats.take_events();
+//! if events.iter().any(|event| matches!(event, InputEvent::Capabilities { .. })) {
+//!     app.gestures.update(conn, qh, &app.seats).unwrap();
+//! }
+//! # }
+//! ```
+
+use wayland_client::{
+    protocol::{wl_pointer::WlPointer, wl_seat::WlSeat, wl_surface::WlSurface},
+    seat::{InputEvent, SeatState},
+    ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError, Proxy,
+    QueueHandle,
+};
+
+use wayland_protocols::unstable::pointer_gestures::v1::client::{
+    zwp_pointer_gesture_hold_v1::{self, ZwpPointerGestureHoldV1},
+    zwp_pointer_gestures_v1::{self, ZwpPointerGesturesV1},
+};
+
+#[derive(Debug)]
+struct HoldGesture {
+    pointer: WlPointer,
+    gesture: ZwpPointerGestureHoldV1,
+    // the surface of the ongoing gesture
+    surface: Option<WlSurface>,
+}
+
+/// A helper creating the hold gestures of the pointers of the seats
+///
+/// The hold gestures are created with the seat of their pointer as user data. The
+/// [`SeatState`] receiving their events must be reachable with `AsMut` from the state of the
+/// event queue, like the `HoldGestureState` itself.
+#[derive(Debug)]
+pub struct HoldGestureState {
+    manager: ZwpPointerGesturesV1,
+    gestures: Vec<HoldGesture>,
+}
+
+impl HoldGestureState {
+    /// Create a new `HoldGestureState` from the `zwp_pointer_gestures_v1` global
+    ///
+    /// The global should be bound with version 3 or higher, and `()` as user data.
+    pub fn new(manager: ZwpPointerGesturesV1) -> HoldGestureState {
+        HoldGestureState { manager, gestures: Vec::new() }
+    }
+
+    /// The `zwp_pointer_gestures_v1` global
+    pub fn manager(&self) -> &ZwpPointerGesturesV1 {
+        &self.manager
+    }
+
+    /// Whether the compositor supports hold gestures
+    pub fn is_supported(&self) -> bool {
+        self.manager.version() >= zwp_pointer_gestures_v1::REQ_GET_HOLD_GESTURE_SINCE
+    }
+
+    /// Follow the pointers of the seats
+    ///
+    /// A hold gesture is created for each new pointer, and the gestures of the pointers released
+    /// since the last call are destroyed. This should be invoked whenever the seat helper reports
+    /// an [`InputEvent::Capabilities`]. Does nothing if hold gestures are not supported.
+    pub fn update<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        seats: &SeatState,
+    ) -> Result<(), DispatchError>
+    where
+        D: Dispatch<ZwpPointerGestureHoldV1, UserData = WlSeat> + 'static,
+    {
+        if !self.is_supported() {
+            return Ok(());
+        }
+        let pointers = seats
+            .seats()
+            .iter()
+            .filter_map(|info| info.pointer().map(|pointer| (info.seat(), pointer)))
+            .collect::<Vec<_>>();
+        let mut i = 0;
+        while i < self.gestures.len() {
+            if pointers.iter().any(|&(_, pointer)| pointer == &self.gestures[i].pointer) {
+                i += 1;
+            } else {
+                self.gestures.remove(i).gesture.destroy(conn);
+            }
+        }
+        for (seat, pointer) in pointers {
+            if self.gestures.iter().all(|g| &g.pointer != pointer) {
+                let gesture = self.manager.get_hold_gesture(conn, pointer, qh, seat.clone())?;
+                self.gestures.push(HoldGesture {
+                    pointer: pointer.clone(),
+                    gesture,
+                    surface: None,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The pointers which have a hold gesture
+    pub fn pointers(&self) -> impl Iterator<Item = &WlPointer> {
+        self.gestures.iter().map(|g| &g.pointer)
+    }
+}
+
+impl DelegateDispatchBase<ZwpPointerGesturesV1> for HoldGestureState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ZwpPointerGesturesV1, D> for HoldGestureState
+where
+    D: Dispatch<ZwpPointerGesturesV1, UserData = ()> + AsMut<HoldGestureState>,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwpPointerGesturesV1,
+        _: zwp_pointer_gestures_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
+
+impl DelegateDispatchBase<ZwpPointerGestureHoldV1> for HoldGestureState {
+    type UserData = WlSeat;
+}
+
+impl<D> DelegateDispatch<ZwpPointerGestureHoldV1, D> for HoldGestureState
+where
+    D: Dispatch<ZwpPointerGestureHoldV1, UserData = WlSeat>
+        + AsMut<HoldGestureState>
+        + AsMut<SeatState>,
+{
+    fn event(
+        data: &mut D,
+        gesture: &ZwpPointerGestureHoldV1,
+        event: zwp_pointer_gesture_hold_v1::Event,
+        seat: &WlSeat,
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me: &mut HoldGestureState = data.as_mut();
+        let hold = me.gestures.iter_mut().find(|g| &g.gesture == gesture);
+        let event = match event {
+            zwp_pointer_gesture_hold_v1::Event::Begin { serial, time, surface, fingers } => {
+                if let Some(hold) = hold {
+                    hold.surface = Some(surface.clone());
+                }
+                InputEvent::PointerHoldBegin { seat: seat.clone(), surface, serial, time, fingers }
+            }
+            zwp_pointer_gesture_hold_v1::Event::End { serial, time, cancelled } => {
+                let surface = match hold.and_then(|hold| hold.surface.take()) {
+                    Some(surface) => surface,
+                    None => return,
+                };
+                InputEvent::PointerHoldEnd {
+                    seat: seat.clone(),
+                    surface,
+                    serial,
+                    time,
+                    cancelled: cancelled != 0,
+                }
+            }
+            _ => return,
+        };
+        AsMut::<SeatState>::as_mut(data).push_event(event);
+    }
+}
@@ -0,0 +1,567 @@
+//! Capture of the outputs into client buffers
+//!
+//! Capturing an output with `zwlr_screencopy_manager_v1` is a handshake: the compositor first
+//! describes the buffers it can copy the frame into, the client allocates one and requests the
+//! copy, and the compositor reports whether it succeeded.
+//!
+//! The [`ScreencopyState`] can be used as a [`DelegateDispatch`] target for the
+//! `zwlr_screencopy_manager_v1` and `zwlr_screencopy_frame_v1` interfaces, and drives this
+//! handshake for each [`Frame`] it creates. The shared memory buffers are allocated from a
+//! [`ShmPool`] of the helper, and the captured images are handed back as
+//! [`ScreencopyEvent::Ready`] events, along with the metadata of the frame. The frames are
+//! destroyed once they are ready or failed.
+//!
+//! Dmabufs cannot be allocated by the helper. If it was asked to
+//! [prefer them](ScreencopyState::set_prefer_dmabuf) and the compositor supports them, a
+//! [`ScreencopyEvent::DmabufRequested`] is reported instead, and the application provides a
+//! matching buffer with [`ScreencopyState::copy_to_dmabuf()`].
+//!
+//! This helper only covers `wlr-screencopy`. Its successor in the `ext` namespace,
+//! `ext-image-copy-capture-v1`, describes its buffer constraints and capture sessions differently
+//! and is not part of this version of `wayland-protocols`, it would need a helper of its own.
+//!
+//! ```no_run
+//! use wayland_client::{delegate_dispatch, protocol::wl_output};
+//! use wayland_protocols::wlr::unstable::screencopy::v1::client::{
+//!     zwlr_screencopy_frame_v1, zwlr_screencopy_manager_v1,
+//! };
+//! use wayland_protocols_helpers::wlr::unstable::screencopy::v1::capture::{
+//!     FrameBuffer, ScreencopyEvent, ScreencopyState,
+//! };
+//!
+//! struct App {
+//!     screencopy: ScreencopyState,
+//! }
+//!
+//! impl AsMut<ScreencopyState> for App {
+//!     fn as_mut(&mut self) -> &mut ScreencopyState {
+//!         &mut self.screencopy
+//!     }
+//! }
+//!
+//! delegate_dispatch!(App: [
+//!     zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+//!     zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1
+//! ] => ScreencopyState);
+//!
+//! # fn run(conn: &mut wayland_client::ConnectionHandle, qh: &wayland_client::QueueHandle<App>, output: wl_output::WlOutput, app: &mut App) {
+//! app.screencopy.capture_output(conn, qh, &output, false).unwrap();
+//!
+//! // after each dispatch of the event queue
+//! for event in app.screencopy.take_events() {
+//!     match event {
+//!         ScreencopyEvent::Ready { frame, captured } => {
+//!             if let FrameBuffer::Shm { params, data } = captured.buffer {
+//!                 /* save the image, of params.width x params.height pixels */
+//!             }
+//!         }
+//!         ScreencopyEvent::Failed { frame, error } => eprintln!("Capture failed: {}", error),
+//!         ScreencopyEvent::DmabufRequested { .. } => unreachable!(),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use wayland_client::{
+    protocol::{wl_buffer::WlBuffer, wl_output::WlOutput, wl_shm},
+    shm::{Buffer, ShmError, ShmPool},
+    ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError, Proxy,
+    QueueHandle, WEnum,
+};
+
+use wayland_protocols::wlr::unstable::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+};
+
+/// The parameters of the shared memory buffers a frame can be copied into
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShmParams {
+    /// The format of the buffer
+    pub format: wl_shm::Format,
+    /// The width of the buffer, in pixels
+    pub width: u32,
+    /// The height of the buffer, in pixels
+    pub height: u32,
+    /// The length of a row of the buffer, in bytes
+    pub stride: u32,
+}
+
+/// The parameters of the dmabufs a frame can be copied into
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DmabufParams {
+    /// The `fourcc` code of the format, as defined in `drm_fourcc.h`
+    pub format: u32,
+    /// The width of the buffer, in pixels
+    pub width: u32,
+    /// The height of the buffer, in pixels
+    pub height: u32,
+}
+
+/// The buffer holding a captured frame
+#[derive(Debug, Clone)]
+pub enum FrameBuffer {
+    /// A shared memory buffer, whose contents were copied out of the pool
+    Shm {
+        /// the parameters of the buffer
+        params: ShmParams,
+        /// the `stride * height` bytes of the image
+        data: Vec<u8>,
+    },
+    /// A dmabuf, as given to [`ScreencopyState::copy_to_dmabuf()`]
+    ///
+    /// It is left for the application to destroy or reuse.
+    Dmabuf {
+        /// the parameters of the buffer
+        params: DmabufParams,
+        /// the buffer
+        buffer: WlBuffer,
+    },
+}
+
+/// A successfully captured frame
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    /// The buffer holding the image
+    pub buffer: FrameBuffer,
+    /// Whether the image is upside down, its first row being the bottom of the output
+    pub y_invert: bool,
+    /// The time the frame was presented at, in the `CLOCK_MONOTONIC` domain
+    pub presented_at: Duration,
+}
+
+/// The reason why a frame could not be captured
+#[derive(Debug)]
+pub enum CaptureError {
+    /// The compositor failed to copy the frame
+    ///
+    /// This happens for example if the output was removed, or if the buffer did not match the
+    /// parameters of the frame.
+    Failed,
+    /// The compositor offered no shared memory buffer, and dmabufs were not requested
+    UnsupportedBuffer,
+    /// The shared memory buffer could not be allocated
+    Shm(ShmError),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Failed => write!(f, "The compositor failed to copy the frame"),
+            CaptureError::UnsupportedBuffer => {
+                write!(f, "The compositor offered no supported buffer type")
+            }
+            CaptureError::Shm(e) => write!(f, "Failed to allocate the buffer: {}", e),
+        }
+    }
+}
+
+impl Error for CaptureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CaptureError::Shm(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An event of a frame, as reported by [`ScreencopyState::take_events()`]
+#[derive(Debug)]
+pub enum ScreencopyEvent {
+    /// The frame is waiting for a dmabuf matching the parameters
+    ///
+    /// The buffer must be given to [`ScreencopyState::copy_to_dmabuf()`].
+    DmabufRequested {
+        /// the frame
+        frame: Frame,
+        /// the parameters of the buffer to allocate
+        params: DmabufParams,
+    },
+    /// The frame was captured
+    Ready {
+        /// the frame
+        frame: Frame,
+        /// its image
+        captured: CapturedFrame,
+    },
+    /// The frame could not be captured
+    Failed {
+        /// the frame
+        frame: Frame,
+        /// the reason of the failure
+        error: CaptureError,
+    },
+}
+
+/// The progress of the capture of a frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// The compositor is describing the buffers the frame can be copied into
+    Negotiating,
+    /// The frame is waiting for the application to provide a dmabuf
+    DmabufRequested,
+    /// The compositor is copying the frame into the buffer
+    Copying,
+    /// The frame was captured
+    Ready,
+    /// The frame could not be captured
+    Failed,
+}
+
+#[derive(Debug)]
+struct FrameInner {
+    status: FrameStatus,
+    shm: Option<ShmParams>,
+    dmabuf: Option<DmabufParams>,
+    y_invert: bool,
+    shm_buffer: Option<(ShmParams, Buffer)>,
+    dmabuf_buffer: Option<(DmabufParams, WlBuffer)>,
+}
+
+/// A frame being captured, a `zwlr_screencopy_frame_v1`
+///
+/// Frames are created by [`ScreencopyState::capture_output()`]. This is a handle, its clones refer
+/// to the same frame.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    frame: ZwlrScreencopyFrameV1,
+    output: WlOutput,
+    prefer_dmabuf: bool,
+    inner: Arc<Mutex<FrameInner>>,
+}
+
+impl PartialEq for Frame {
+    fn eq(&self, other: &Frame) -> bool {
+        self.frame == other.frame
+    }
+}
+
+impl Eq for Frame {}
+
+impl Frame {
+    /// The `zwlr_screencopy_frame_v1` of this frame
+    pub fn zwlr_screencopy_frame(&self) -> &ZwlrScreencopyFrameV1 {
+        &self.frame
+    }
+
+    /// The output captured by this frame
+    pub fn output(&self) -> &WlOutput {
+        &self.output
+    }
+
+    /// The progress of the capture of this frame
+    pub fn status(&self) -> FrameStatus {
+        self.inner.lock().unwrap().status
+    }
+}
+
+/// A helper handling the `zwlr_screencopy_manager_v1` global and the frames created from it
+///
+/// It keeps track of the frames being captured until they are ready or failed, and of the shared
+/// memory pool their buffers are allocated from, which is reused by the following captures.
+#[derive(Debug)]
+pub struct ScreencopyState {
+    manager: ZwlrScreencopyManagerV1,
+    shm: wl_shm::WlShm,
+    pool: Option<ShmPool>,
+    prefer_dmabuf: bool,
+    frames: Vec<Frame>,
+    events: Vec<ScreencopyEvent>,
+}
+
+impl ScreencopyState {
+    /// Create a new `ScreencopyState` from the `zwlr_screencopy_manager_v1` and `wl_shm` globals
+    ///
+    /// The dmabuf parameters of the frames are only described by version 3 of the screencopy
+    /// global, older versions only let the frames be copied into shared memory buffers.
+    pub fn new(manager: ZwlrScreencopyManagerV1, shm: wl_shm::WlShm) -> ScreencopyState {
+        ScreencopyState {
+            manager,
+            shm,
+            pool: None,
+            prefer_dmabuf: false,
+            frames: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// The `zwlr_screencopy_manager_v1` global
+    pub fn manager(&self) -> &ZwlrScreencopyManagerV1 {
+        &self.manager
+    }
+
+    /// Set whether the frames should be copied into dmabufs when the compositor supports them
+    ///
+    /// The compositor describes the dmabufs since version 3 of `zwlr_screencopy_manager_v1`. This
+    /// only applies to the frames created afterwards, and is disabled by default.
+    pub fn set_prefer_dmabuf(&mut self, prefer_dmabuf: bool) {
+        self.prefer_dmabuf = prefer_dmabuf;
+    }
+
+    /// Capture the next frame of an output
+    ///
+    /// If `overlay_cursor` is `true`, the cursor is drawn on the captured image.
+    pub fn capture_output<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        output: &WlOutput,
+        overlay_cursor: bool,
+    ) -> Result<Frame, DispatchError>
+    where
+        D: Dispatch<ZwlrScreencopyFrameV1, UserData = ()> + 'static,
+    {
+        let frame = self.manager.capture_output(conn, overlay_cursor as i32, output, qh, ())?;
+        Ok(self.track_frame(frame, output))
+    }
+
+    /// Capture the next frame of a region of an output
+    ///
+    /// The region is given in the logical coordinates of the output, it is clipped to the output if
+    /// it extends beyond it. If `overlay_cursor` is `true`, the cursor is drawn on the captured
+    /// image.
+    #[allow(clippy::too_many_arguments)]
+    pub fn capture_output_region<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        output: &WlOutput,
+        overlay_cursor: bool,
+        (x, y): (i32, i32),
+        (width, height): (i32, i32),
+    ) -> Result<Frame, DispatchError>
+    where
+        D: Dispatch<ZwlrScreencopyFrameV1, UserData = ()> + 'static,
+    {
+        let frame = self.manager.capture_output_region(
+            conn,
+            overlay_cursor as i32,
+            output,
+            x,
+            y,
+            width,
+            height,
+            qh,
+            (),
+        )?;
+        Ok(self.track_frame(frame, output))
+    }
+
+    /// Copy a frame into a dmabuf, after a [`ScreencopyEvent::DmabufRequested`]
+    ///
+    /// The buffer must match the requested parameters. Does nothing if the frame is not waiting for
+    /// a dmabuf.
+    pub fn copy_to_dmabuf(&mut self, conn: &mut ConnectionHandle, frame: &Frame, buffer: WlBuffer) {
+        let mut inner = frame.inner.lock().unwrap();
+        if inner.status != FrameStatus::DmabufRequested {
+            return;
+        }
+        if let Some(params) = inner.dmabuf {
+            frame.frame.copy(conn, &buffer);
+            inner.dmabuf_buffer = Some((params, buffer));
+            inner.status = FrameStatus::Copying;
+        }
+    }
+
+    /// Stop capturing a frame and destroy it
+    ///
+    /// No event is reported for the frame afterwards.
+    pub fn cancel(&mut self, conn: &mut ConnectionHandle, frame: &Frame) {
+        if self.frames.contains(frame) {
+            frame.frame.destroy(conn);
+            self.frames.retain(|f| f != frame);
+        }
+    }
+
+    /// The frames being captured
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Take the frame events received since the last call
+    pub fn take_events(&mut self) -> Vec<ScreencopyEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn track_frame(&mut self, frame: ZwlrScreencopyFrameV1, output: &WlOutput) -> Frame {
+        let frame = Frame {
+            frame,
+            output: output.clone(),
+            prefer_dmabuf: self.prefer_dmabuf,
+            inner: Arc::new(Mutex::new(FrameInner {
+                status: FrameStatus::Negotiating,
+                shm: None,
+                dmabuf: None,
+                y_invert: false,
+                shm_buffer: None,
+                dmabuf_buffer: None,
+            })),
+        };
+        self.frames.push(frame.clone());
+        frame
+    }
+
+    // All the buffer parameters were received, allocate a buffer and request the copy
+    fn start_copy(&mut self, conn: &mut ConnectionHandle, frame: Frame) {
+        let (shm, dmabuf) = {
+            let inner = frame.inner.lock().unwrap();
+            (inner.shm, inner.dmabuf)
+        };
+        match (shm, dmabuf) {
+            (_, Some(params)) if frame.prefer_dmabuf => {
+                frame.inner.lock().unwrap().status = FrameStatus::DmabufRequested;
+                self.events.push(ScreencopyEvent::DmabufRequested { frame, params });
+            }
+            (Some(params), _) => match self.allocate(conn, params) {
+                Ok(buffer) => {
+                    frame.frame.copy(conn, buffer.wl_buffer());
+                    let mut inner = frame.inner.lock().unwrap();
+                    inner.shm_buffer = Some((params, buffer));
+                    inner.status = FrameStatus::Copying;
+                }
+                Err(e) => self.finish(conn, frame, Err(CaptureError::Shm(e))),
+            },
+            (None, _) => self.finish(conn, frame, Err(CaptureError::UnsupportedBuffer)),
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        params: ShmParams,
+    ) -> Result<Buffer, ShmError> {
+        let len = params.stride as usize * params.height as usize;
+        if self.pool.is_none() {
+            self.pool = Some(ShmPool::new(conn, &self.shm, len)?);
+        }
+        self.pool.as_mut().unwrap().create_buffer(
+            conn,
+            params.width as i32,
+            params.height as i32,
+            params.stride as i32,
+            params.format,
+        )
+    }
+
+    // The frame is done, report it and destroy it
+    fn finish(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        frame: Frame,
+        result: Result<Duration, CaptureError>,
+    ) {
+        let event = {
+            let mut inner = frame.inner.lock().unwrap();
+            let shm_buffer = inner.shm_buffer.take();
+            let dmabuf_buffer = inner.dmabuf_buffer.take();
+            let buffer = match (shm_buffer, dmabuf_buffer) {
+                // the buffer was never attached to a surface, it is not busy
+                (Some((params, mut buffer)), _) => Some(FrameBuffer::Shm {
+                    params,
+                    data: buffer.canvas().map(|data| data.to_vec()).unwrap_or_default(),
+                }),
+                (None, Some((params, buffer))) => Some(FrameBuffer::Dmabuf { params, buffer }),
+                (None, None) => None,
+            };
+            match (result, buffer) {
+                (Ok(presented_at), Some(buffer)) => {
+                    inner.status = FrameStatus::Ready;
+                    let captured = CapturedFrame { buffer, y_invert: inner.y_invert, presented_at };
+                    ScreencopyEvent::Ready { frame: frame.clone(), captured }
+                }
+                (Err(error), _) => {
+                    inner.status = FrameStatus::Failed;
+                    ScreencopyEvent::Failed { frame: frame.clone(), error }
+                }
+                // the compositor reported a frame without copying it
+                (Ok(_), None) => {
+                    inner.status = FrameStatus::Failed;
+                    ScreencopyEvent::Failed { frame: frame.clone(), error: CaptureError::Failed }
+                }
+            }
+        };
+        frame.frame.destroy(conn);
+        self.frames.retain(|f| f != &frame);
+        self.events.push(event);
+    }
+}
+
+impl DelegateDispatchBase<ZwlrScreencopyManagerV1> for ScreencopyState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ZwlrScreencopyManagerV1, D> for ScreencopyState
+where
+    D: Dispatch<ZwlrScreencopyManagerV1, UserData = ()> + AsMut<ScreencopyState>,
+{
+    fn event(
+        _: &mut D,
+        _: &ZwlrScreencopyManagerV1,
+        _: zwlr_screencopy_manager_v1::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+    }
+}
+
+impl DelegateDispatchBase<ZwlrScreencopyFrameV1> for ScreencopyState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<ZwlrScreencopyFrameV1, D> for ScreencopyState
+where
+    D: Dispatch<ZwlrScreencopyFrameV1, UserData = ()> + AsMut<ScreencopyState>,
+{
+    fn event(
+        data: &mut D,
+        zwlr_frame: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let frame = match me.frames.iter().find(|frame| &frame.frame == zwlr_frame) {
+            Some(frame) => frame.clone(),
+            None => return,
+        };
+        match event {
+            zwlr_screencopy_frame_v1::Event::Buffer { format, width, height, stride } => {
+                // the shm formats unknown to this version of the protocol cannot be allocated
+                if let WEnum::Value(format) = format {
+                    frame.inner.lock().unwrap().shm =
+                        Some(ShmParams { format, width, height, stride });
+                }
+                // before version 3, the shm buffer is the only one described
+                if zwlr_frame.version() < 3 {
+                    me.start_copy(conn, frame);
+                }
+            }
+            zwlr_screencopy_frame_v1::Event::LinuxDmabuf { format, width, height } => {
+                frame.inner.lock().unwrap().dmabuf = Some(DmabufParams { format, width, height });
+            }
+            zwlr_screencopy_frame_v1::Event::BufferDone => me.start_copy(conn, frame),
+            zwlr_screencopy_frame_v1::Event::Flags { flags } => {
+                if let WEnum::Value(flags) = flags {
+                    frame.inner.lock().unwrap().y_invert =
+                        flags.contains(zwlr_screencopy_frame_v1::Flags::YInvert);
+                }
+            }
+            zwlr_screencopy_frame_v1::Event::Ready { tv_sec_hi, tv_sec_lo, tv_nsec } => {
+                let secs = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+                me.finish(conn, frame, Ok(Duration::new(secs, tv_nsec)));
+            }
+            zwlr_screencopy_frame_v1::Event::Failed => {
+                me.finish(conn, frame, Err(CaptureError::Failed))
+            }
+            // the frames are copied with `copy`, which does not wait for damage
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! A minimal window abstraction over `xdg_toplevel`
+//!
+//! The [`XdgShellState`] can be used as a [`DelegateDispatch`] target for the `xdg_wm_base`,
+//! `xdg_surface` and `xdg_toplevel` interfaces. It answers the pings of the compositor and creates
+//! [`Window`]s, whose configure sequences it tracks and acknowledges.
+//!
+//! Each time the compositor configures a window, the configuration is acknowledged before a
+//! [`WindowEvent::Configure`] is reported, so the next commit of the surface is expected to match
+//! it. The surface is committed once when the window is created, and no buffer must be attached to
+//! it before it was configured for the first time.
+//!
+//! ```no_run
+//! use wayland_client::{delegate_dispatch, protocol::wl_surface};
+//! use wayland_protocols::xdg_shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+//! use wayland_protocols_helpers::xdg_shell::window::{WindowEvent, XdgShellState};
+//!
+//! struct App {
+//!     xdg_shell: XdgShellState,
+//! }
+//!
+//! impl AsMut<XdgShellState> for App {
+//!     fn as_mut(&mut self) -> &mut XdgShellState {
+//!         &mut self.xdg_shell
+//!     }
+//! }
+//!
+//! delegate_dispatch!(App: [xdg_wm_base::XdgWmBase, xdg_surface::XdgSurface, xdg_toplevel::XdgToplevel] => XdgShellState);
+//!
+//! # fn run(conn: &mut wayland_client::ConnectionHandle, qh: &wayland_client::QueueHandle<App>, surface: wl_surface::WlSurface, app: &mut App) {
+//! let window = app.xdg_shell.create_window(conn, qh, surface).unwrap();
+//! window.set_title(conn, "A fantastic window!".into());
+//!
+//! // after each dispatch of the event queue
+//! for event in app.xdg_shell.take_events() {
+//!     match event {
+//!         WindowEvent::Configure { window, configure } => {
+//!             let (width, height) = configure.new_size.unwrap_or((256, 256));
+//!             /* draw the window with this size, attach the buffer and commit */
+//!         }
+//!         WindowEvent::Close { window } => { /* ... */ }
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+
+use wayland_client::{
+    protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    ConnectionHandle, DelegateDispatch, DelegateDispatchBase, Dispatch, DispatchError, QueueHandle,
+};
+
+use wayland_protocols::xdg_shell::client::{
+    xdg_surface::{self, XdgSurface},
+    xdg_toplevel::{self, XdgToplevel},
+    xdg_wm_base::{self, XdgWmBase},
+};
+
+/// An event of a window, as reported by [`XdgShellState::take_events()`]
+#[derive(Debug, Clone)]
+pub enum WindowEvent {
+    /// The window was configured
+    ///
+    /// The configuration is already acknowledged, the next commit of the surface must apply it.
+    Configure {
+        /// the window
+        window: Window,
+        /// its new configuration
+        configure: WindowConfigure,
+    },
+    /// The user requested to close the window
+    ///
+    /// The compositor does not close it by itself, the application destroys it with
+    /// [`XdgShellState::destroy_window()`] if it agrees.
+    Close {
+        /// the window
+        window: Window,
+    },
+}
+
+/// The configuration of a window by the compositor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowConfigure {
+    /// The size the window should have, if the compositor has a preference
+    ///
+    /// If it is `None`, the window chooses its own size.
+    pub new_size: Option<(u32, u32)>,
+    /// The size the window should not exceed, if known
+    ///
+    /// This is the `configure_bounds` event of `xdg_toplevel` version 4, typically the size of the
+    /// output the window is on, minus the panels.
+    pub bounds: Option<(u32, u32)>,
+    /// The states of the window
+    pub states: Vec<xdg_toplevel::State>,
+    /// The serial of this configuration
+    pub serial: u32,
+}
+
+impl WindowConfigure {
+    /// Whether the window is maximized
+    pub fn is_maximized(&self) -> bool {
+        self.states.contains(&xdg_toplevel::State::Maximized)
+    }
+
+    /// Whether the window is fullscreen
+    pub fn is_fullscreen(&self) -> bool {
+        self.states.contains(&xdg_toplevel::State::Fullscreen)
+    }
+
+    /// Whether the window is being resized
+    pub fn is_resizing(&self) -> bool {
+        self.states.contains(&xdg_toplevel::State::Resizing)
+    }
+
+    /// Whether the window is activated, typically drawn with focused decorations
+    pub fn is_activated(&self) -> bool {
+        self.states.contains(&xdg_toplevel::State::Activated)
+    }
+}
+
+#[derive(Debug, Default)]
+struct WindowInner {
+    pending: WindowConfigure,
+    current: Option<WindowConfigure>,
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+}
+
+/// A window, an `xdg_toplevel` and its surfaces
+///
+/// Windows are created by [`XdgShellState::create_window()`]. This is a handle, its clones refer to
+/// the same window.
+#[derive(Debug, Clone)]
+pub struct Window {
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    toplevel: XdgToplevel,
+    inner: Arc<Mutex<WindowInner>>,
+}
+
+impl PartialEq for Window {
+    fn eq(&self, other: &Window) -> bool {
+        self.xdg_surface == other.xdg_surface
+    }
+}
+
+impl Eq for Window {}
+
+impl Window {
+    /// The `wl_surface` of this window
+    pub fn wl_surface(&self) -> &WlSurface {
+        &self.surface
+    }
+
+    /// The `xdg_surface` of this window
+    pub fn xdg_surface(&self) -> &XdgSurface {
+        &self.xdg_surface
+    }
+
+    /// The `xdg_toplevel` of this window
+    pub fn xdg_toplevel(&self) -> &XdgToplevel {
+        &self.toplevel
+    }
+
+    /// The last configuration of this window, `None` if it was not configured yet
+    ///
+    /// No buffer may be attached to the surface before the window was configured.
+    pub fn configure(&self) -> Option<WindowConfigure> {
+        self.inner.lock().unwrap().current.clone()
+    }
+
+    /// Set the title of this window
+    pub fn set_title(&self, conn: &mut ConnectionHandle, title: String) {
+        self.toplevel.set_title(conn, title);
+    }
+
+    /// Set the application id of this window
+    pub fn set_app_id(&self, conn: &mut ConnectionHandle, app_id: String) {
+        self.toplevel.set_app_id(conn, app_id);
+    }
+
+    /// The minimum size of this window, `None` if unlimited
+    pub fn min_size(&self) -> Option<(u32, u32)> {
+        self.inner.lock().unwrap().min_size
+    }
+
+    /// Set the minimum size of this window, `None` to remove the limit
+    ///
+    /// As the other double-buffered states of the surface, it is applied on the next commit.
+    pub fn set_min_size(&self, conn: &mut ConnectionHandle, size: Option<(u32, u32)>) {
+        let (width, height) = size.unwrap_or((0, 0));
+        self.toplevel.set_min_size(conn, width as i32, height as i32);
+        self.inner.lock().unwrap().min_size = size;
+    }
+
+    /// The maximum size of this window, `None` if unlimited
+    pub fn max_size(&self) -> Option<(u32, u32)> {
+        self.inner.lock().unwrap().max_size
+    }
+
+    /// Set the maximum size of this window, `None` to remove the limit
+    ///
+    /// As the other double-buffered states of the surface, it is applied on the next commit.
+    pub fn set_max_size(&self, conn: &mut ConnectionHandle, size: Option<(u32, u32)>) {
+        let (width, height) = size.unwrap_or((0, 0));
+        self.toplevel.set_max_size(conn, width as i32, height as i32);
+        self.inner.lock().unwrap().max_size = size;
+    }
+
+    /// Request the window to be maximized
+    ///
+    /// The window is maximized once a configuration with the maximized state is received.
+    pub fn set_maximized(&self, conn: &mut ConnectionHandle) {
+        self.toplevel.set_maximized(conn);
+    }
+
+    /// Request the window to be unmaximized
+    pub fn unset_maximized(&self, conn: &mut ConnectionHandle) {
+        self.toplevel.unset_maximized(conn);
+    }
+
+    /// Request the window to be fullscreen, on given output or on the one chosen by the compositor
+    ///
+    /// The window is fullscreen once a configuration with the fullscreen state is received.
+    pub fn set_fullscreen(&self, conn: &mut ConnectionHandle, output: Option<&WlOutput>) {
+        self.toplevel.set_fullscreen(conn, output);
+    }
+
+    /// Request the window to leave fullscreen
+    pub fn unset_fullscreen(&self, conn: &mut ConnectionHandle) {
+        self.toplevel.unset_fullscreen(conn);
+    }
+
+    /// Request the window to be minimized
+    ///
+    /// The compositor gives no feedback about whether the window is minimized.
+    pub fn set_minimized(&self, conn: &mut ConnectionHandle) {
+        self.toplevel.set_minimized(conn);
+    }
+}
+
+/// A helper handling the `xdg_wm_base` global and the windows created from it
+///
+/// It answers the pings of the compositor on behalf of the application, which is considered
+/// unresponsive otherwise, and keeps track of the windows until they are destroyed with
+/// [`destroy_window()`](XdgShellState::destroy_window).
+#[derive(Debug)]
+pub struct XdgShellState {
+    wm_base: XdgWmBase,
+    windows: Vec<Window>,
+    events: Vec<WindowEvent>,
+}
+
+impl XdgShellState {
+    /// Create a new `XdgShellState` from the `xdg_wm_base` global
+    ///
+    /// The windows only receive their [bounds](WindowConfigure::bounds) if the global was bound
+    /// with version 4 or higher.
+    pub fn new(wm_base: XdgWmBase) -> XdgShellState {
+        XdgShellState { wm_base, windows: Vec::new(), events: Vec::new() }
+    }
+
+    /// The `xdg_wm_base` global
+    pub fn wm_base(&self) -> &XdgWmBase {
+        &self.wm_base
+    }
+
+    /// Create a window from a surface
+    ///
+    /// The surface must not have a role yet, nor a buffer attached. It is committed to request the
+    /// initial configuration of the window.
+    pub fn create_window<D>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        qh: &QueueHandle<D>,
+        surface: WlSurface,
+    ) -> Result<Window, DispatchError>
+    where
+        D: Dispatch<XdgSurface, UserData = ()> + Dispatch<XdgToplevel, UserData = ()> + 'static,
+    {
+        let xdg_surface = self.wm_base.get_xdg_surface(conn, &surface, qh, ())?;
+        let toplevel = xdg_surface.get_toplevel(conn, qh, ())?;
+        surface.commit(conn);
+        let window = Window {
+            surface,
+            xdg_surface,
+            toplevel,
+            inner: Arc::new(Mutex::new(Default::default())),
+        };
+        self.windows.push(window.clone());
+        Ok(window)
+    }
+
+    /// Destroy a window
+    ///
+    /// Its `xdg_toplevel` and `xdg_surface` are destroyed, its `wl_surface` is left for the
+    /// application to destroy or reuse.
+    pub fn destroy_window(&mut self, conn: &mut ConnectionHandle, window: &Window) {
+        window.toplevel.destroy(conn);
+        window.xdg_surface.destroy(conn);
+        self.windows.retain(|w| w != window);
+    }
+
+    /// The windows created by this helper and not destroyed yet
+    pub fn windows(&self) -> &[Window] {
+        &self.windows
+    }
+
+    /// Take the window events received since the last call
+    pub fn take_events(&mut self) -> Vec<WindowEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl DelegateDispatchBase<XdgWmBase> for XdgShellState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<XdgWmBase, D> for XdgShellState
+where
+    D: Dispatch<XdgWmBase, UserData = ()> + AsMut<XdgShellState>,
+{
+    fn event(
+        _: &mut D,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(conn, serial);
+        }
+    }
+}
+
+impl DelegateDispatchBase<XdgSurface> for XdgShellState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<XdgSurface, D> for XdgShellState
+where
+    D: Dispatch<XdgSurface, UserData = ()> + AsMut<XdgShellState>,
+{
+    fn event(
+        data: &mut D,
+        xdg_surface: &XdgSurface,
+        event: xdg_surface::Event,
+        _: &(),
+        conn: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let window = match me.windows.iter().find(|window| &window.xdg_surface == xdg_surface) {
+            Some(window) => window.clone(),
+            None => return,
+        };
+        if let xdg_surface::Event::Configure { serial } = event {
+            // the configuration is complete: the events of the role object were all received
+            let configure = {
+                let mut inner = window.inner.lock().unwrap();
+                inner.pending.serial = serial;
+                let configure = inner.pending.clone();
+                inner.current = Some(configure.clone());
+                configure
+            };
+            // acknowledge before the application gets a chance to commit the surface
+            xdg_surface.ack_configure(conn, serial);
+            me.events.push(WindowEvent::Configure { window, configure });
+        }
+    }
+}
+
+impl DelegateDispatchBase<XdgToplevel> for XdgShellState {
+    type UserData = ();
+}
+
+impl<D> DelegateDispatch<XdgToplevel, D> for XdgShellState
+where
+    D: Dispatch<XdgToplevel, UserData = ()> + AsMut<XdgShellState>,
+{
+    fn event(
+        data: &mut D,
+        toplevel: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        _: &(),
+        _: &mut ConnectionHandle,
+        _: &QueueHandle<D>,
+    ) {
+        let me = data.as_mut();
+        let window = match me.windows.iter().find(|window| &window.toplevel == toplevel) {
+            Some(window) => window.clone(),
+            None => return,
+        };
+        match event {
+            xdg_toplevel::Event::Configure { width, height, states } => {
+                let mut inner = window.inner.lock().unwrap();
+                inner.pending.new_size = if width > 0 && height > 0 {
+                    Some((width as u32, height as u32))
+                } else {
+                    None
+                };
+                // the array holds the raw values of the states, unknown values are skipped
+                inner.pending.states = states
+                    .chunks_exact(4)
+                    .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .flat_map(xdg_toplevel::State::try_from)
+                    .collect();
+            }
+            xdg_toplevel::Event::ConfigureBounds { width, height } => {
+                window.inner.lock().unwrap().pending.bounds = if width > 0 && height > 0 {
+                    Some((width as u32, height as u32))
+                } else {
+                    None
+                };
+            }
+            xdg_toplevel::Event::Close => {
+                me.events.push(WindowEvent::Close { window });
+            }
+            _ => {}
+        }
+    }
+}
@@ -7,10 +7,17 @@
 //! protocol. The creation of these modules (and the dependency on the associated crate) is
 //! controlled by the two cargo features `client` and `server`.
 //!
+//! The cargo feature `staging_protocols` adds a `staging` module, containing bindings to the
+//! protocols in the staging process, which are guaranteed to not receive backward incompatible
+//! changes anymore.
+//!
 //! The cargo feature `unstable_protocols` adds an `unstable` module, containing bindings
 //! to protocols that are not yet considered stable. As such, no stability guarantee is
 //! given for these protocols.
 //!
+//! The cargo feature `wlr_protocols` adds a `wlr` module, containing bindings to the protocols
+//! of the wlroots family.
+//!
 //! Some protocols require unstable rust features, the inclusion of them is controlled
 //! by the cargo feature `nightly`.
 
@@ -19,13 +26,15 @@
 #[macro_use]
 mod protocol_macro;
 
-#[cfg(feature = "unstable_protocols")]
+#[cfg(feature = "staging_protocols")]
 pub mod staging;
 
 #[cfg(feature = "unstable_protocols")]
 pub mod unstable;
 
 pub mod misc;
+
+#[cfg(feature = "wlr_protocols")]
 pub mod wlr;
 
 mod stable;
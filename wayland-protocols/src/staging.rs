@@ -11,6 +11,97 @@
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 
+#[cfg(feature = "unstable_protocols")]
+pub mod cursor_shape {
+    //! This protocol extension offers a simpler way for clients to set a cursor.
+    //!
+    //! Rather than attaching a buffer to a surface, clients pick the cursor image among a set of
+    //! shapes predefined by the compositor, for both the pointer and tablet tools. As it refers
+    //! to the unstable tablet protocol, it also requires the `unstable_protocols` feature.
+
+    #[allow(missing_docs)]
+    pub mod v1 {
+        wayland_protocol!(
+            "./protocols/staging/cursor-shape/cursor-shape-v1.xml",
+            [crate::unstable::tablet::v2]
+        );
+    }
+}
+
+pub mod fractional_scale {
+    //! This protocol allows a compositor to suggest for surfaces to render at
+    //! fractional scales.
+    //!
+    //! A client can submit scaled content by utilizing wp_viewport. This is done by
+    //! creating a wp_viewport object for the surface and setting the destination
+    //! rectangle to the surface size before the scale factor is applied.
+
+    #[allow(missing_docs)]
+    pub mod v1 {
+        wayland_protocol!(
+            "./protocols/staging/fractional-scale/fractional-scale-v1.xml",
+            []
+        );
+    }
+}
+
+pub mod security_context {
+    //! This interface allows a client to register a new Wayland connection to
+    //! the compositor and attach a security context to it.
+    //!
+    //! This is intended to be used by sandboxes. Sandbox engines attach a
+    //! security context to all connections coming from inside the sandbox. The
+    //! compositor can then restrict the features that the sandboxed connections
+    //! can use.
+
+    #[allow(missing_docs)]
+    pub mod v1 {
+        wayland_protocol!(
+            "./protocols/staging/security-context/security-context-v1.xml",
+            []
+        );
+    }
+}
+
+pub mod session_lock {
+    //! This protocol allows for a privileged Wayland client to lock the session
+    //! and display arbitrary graphics while the session is locked.
+    //!
+    //! The compositor may choose to restrict this protocol to a special client
+    //! launched by the compositor itself or expose it to all privileged clients,
+    //! this is compositor policy.
+    //!
+    //! The client is responsible for performing authentication and informing the
+    //! compositor when the session should be unlocked. If the client dies while
+    //! the session is locked the session remains locked, possibly permanently
+    //! depending on compositor policy.
+
+    #[allow(missing_docs)]
+    pub mod v1 {
+        wayland_protocol!(
+            "./protocols/staging/ext-session-lock/ext-session-lock-v1.xml",
+            []
+        );
+    }
+}
+
+pub mod tearing_control {
+    //! For some use cases like games or drawing tablets it can make sense to
+    //! reduce latency by accepting tearing with the use of asynchronous page
+    //! flips.
+    //!
+    //! This global is a factory interface, allowing clients to inform which type
+    //! of presentation the content of their surfaces is suitable for.
+
+    #[allow(missing_docs)]
+    pub mod v1 {
+        wayland_protocol!(
+            "./protocols/staging/tearing-control/tearing-control-v1.xml",
+            []
+        );
+    }
+}
+
 pub mod xdg_activation {
     //! The way for a client to pass focus to another toplevel is as follows.
     //!
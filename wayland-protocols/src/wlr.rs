@@ -2,10 +2,11 @@
 //!
 //! This module regroup bindings to the protocol extensions from
 //! [wlr-protocols](https://github.com/swaywm/wlr-protocols).
+//!
+//! It is only available with the `wlr_protocols` cargo feature.
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 
-#[cfg(feature = "unstable_protocols")]
 pub mod unstable {
     //! Unstable protocols from wlr-protocols
     //!
@@ -3,43 +3,91 @@ use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 
 use crate::{
+    common::Side,
     protocol::{Interface, Protocol, Type},
     util::{dotted_to_relname, is_keyword, snake_to_camel},
-    Side,
 };
 
-pub fn generate_client_objects(protocol: &Protocol) -> TokenStream {
-    protocol.interfaces.iter().map(generate_objects_for).collect()
+/// Generate the client-side objects of a protocol
+///
+/// With `borrowed_events`, the interfaces having events with string or array arguments also get
+/// an `EventRef<'a>` enum borrowing these arguments from the parsed message, and a
+/// `parse_event_ref()` method producing it.
+pub fn generate_client_objects(
+    protocol: &Protocol,
+    borrowed_events: bool,
+    type_attributes: &crate::input::TypeAttributes,
+    feature_gates: &crate::input::FeatureGates,
+) -> TokenStream {
+    protocol
+        .interfaces
+        .iter()
+        .map(|interface| {
+            generate_objects_for(interface, borrowed_events, type_attributes, feature_gates)
+        })
+        .collect()
 }
 
-fn generate_objects_for(interface: &Interface) -> TokenStream {
+fn generate_objects_for(
+    interface: &Interface,
+    borrowed_events: bool,
+    type_attributes: &crate::input::TypeAttributes,
+    feature_gates: &crate::input::FeatureGates,
+) -> TokenStream {
     let mod_name = Ident::new(&interface.name, Span::call_site());
+    let mod_cfg = feature_gates.interface_cfg(&interface.name);
     let mod_doc = interface.description.as_ref().map(crate::util::description_to_doc_attr);
+    let iface_doc = crate::util::to_doc_attr(&format!(
+        "{}\n\nSee also the [Event] enum for this interface.",
+        interface
+            .description
+            .as_ref()
+            .map(|(short, long)| format!("{}\n\n{}", short, long))
+            .unwrap_or_default(),
+    ));
     let iface_name = Ident::new(&snake_to_camel(&interface.name), Span::call_site());
     let iface_const_name = format_ident!("{}_INTERFACE", interface.name.to_ascii_uppercase());
 
-    let enums = crate::common::generate_enums_for(interface);
+    let enums = crate::common::generate_enums_for(interface, type_attributes);
     let sinces = crate::common::gen_since_constants(&interface.requests, &interface.events);
 
     let requests = crate::common::gen_message_enum(
         &format_ident!("Request"),
         Side::Client,
         false,
+        false,
         &interface.requests,
+        type_attributes,
     );
     let events = crate::common::gen_message_enum(
         &format_ident!("Event"),
         Side::Client,
         true,
+        false,
         &interface.events,
+        type_attributes,
     );
 
-    let parse_body = crate::common::gen_parse_body(interface, Side::Client);
+    let parse_body = crate::common::gen_parse_body(interface, Side::Client, false);
     let write_body = crate::common::gen_write_body(interface, Side::Client);
     let methods = gen_methods(interface);
+    // the comparisons of the objects are implemented by hand below
+    let iface_attrs = type_attributes.to_attrs(&["Debug", "Clone", "PartialEq", "Eq"]);
+
+    // the events without strings or arrays are parsed without allocating anyway
+    let has_borrowed_args = interface
+        .events
+        .iter()
+        .any(|evt| evt.args.iter().any(|arg| matches!(arg.typ, Type::String | Type::Array)));
+    let (events_ref, parse_ref_method) = if borrowed_events && has_borrowed_args {
+        (gen_event_ref(interface, type_attributes), gen_parse_ref_method(interface))
+    } else {
+        (TokenStream::new(), TokenStream::new())
+    };
 
     quote! {
         #mod_doc
+        #mod_cfg
         pub mod #mod_name {
             use std::sync::Arc;
 
@@ -52,8 +100,11 @@ fn generate_objects_for(interface: &Interface) -> TokenStream {
             #sinces
             #requests
             #events
+            #events_ref
 
+            #iface_doc
             #[derive(Debug, Clone)]
+            #iface_attrs
             pub struct #iface_name {
                 id: ObjectId,
                 version: u32,
@@ -68,6 +119,7 @@ fn generate_objects_for(interface: &Interface) -> TokenStream {
 
             impl std::cmp::Eq for #iface_name {}
 
+            #[allow(deprecated)]
             impl super::wayland_client::Proxy for #iface_name {
                 type Request = Request;
                 type Event = Event;
@@ -111,18 +163,91 @@ fn generate_objects_for(interface: &Interface) -> TokenStream {
                 }
             }
 
+            #[allow(deprecated)]
             impl #iface_name {
+                #parse_ref_method
                 #methods
             }
         }
     }
 }
 
+fn gen_event_ref(
+    interface: &Interface,
+    type_attributes: &crate::input::TypeAttributes,
+) -> TokenStream {
+    let events_ref = crate::common::gen_message_enum(
+        &format_ident!("EventRef"),
+        Side::Client,
+        true,
+        true,
+        &interface.events,
+        type_attributes,
+    );
+
+    let into_owned_arms = interface.events.iter().map(|evt| {
+        let evt_name = Ident::new(&snake_to_camel(&evt.name), Span::call_site());
+        let arg_names = evt
+            .args
+            .iter()
+            .map(|arg| {
+                format_ident!("{}{}", if is_keyword(&arg.name) { "_" } else { "" }, arg.name)
+            })
+            .collect::<Vec<_>>();
+        let conversions = evt.args.iter().zip(&arg_names).map(|(arg, arg_name)| {
+            match (arg.typ, arg.allow_null) {
+                _ if arg.enum_.is_some() => quote! { #arg_name },
+                (Type::String, false) => quote! { #arg_name: #arg_name.into_owned() },
+                (Type::String, true) => {
+                    quote! { #arg_name: #arg_name.map(::std::borrow::Cow::into_owned) }
+                }
+                (Type::Array, false) => quote! { #arg_name: #arg_name.to_vec() },
+                (Type::Array, true) => quote! { #arg_name: #arg_name.map(<[u8]>::to_vec) },
+                _ => quote! { #arg_name },
+            }
+        });
+        quote! {
+            EventRef::#evt_name { #(#arg_names),* } => Event::#evt_name { #(#conversions),* }
+        }
+    });
+
+    quote! {
+        #[doc = "Borrowed version of the [Event] enum, produced by `parse_event_ref()`\n\nIts string and array arguments borrow from the parsed message."]
+        #events_ref
+
+        #[allow(deprecated)]
+        impl<'a> EventRef<'a> {
+            /// Copy the borrowed arguments of this event, to keep it after its message is dropped
+            pub fn into_owned(self) -> Event {
+                match self {
+                    #(#into_owned_arms,)*
+                }
+            }
+        }
+    }
+}
+
+fn gen_parse_ref_method(interface: &Interface) -> TokenStream {
+    let parse_body = crate::common::gen_parse_body(interface, Side::Client, true);
+    quote! {
+        /// Parse an event of this interface without copying its string and array arguments
+        ///
+        /// This is the allocation-free counterpart of [`Proxy::parse_event()`], for the event
+        /// handling hot paths: the returned [`EventRef`] borrows from `msg`, and
+        /// [`EventRef::into_owned()`] gives the regular [`Event`] when it needs to be kept.
+        pub fn parse_event_ref<'a>(conn: &mut ConnectionHandle, msg: &'a Message<ObjectId>) -> Result<(Self, EventRef<'a>), DispatchError> {
+            #parse_body
+        }
+    }
+}
+
 fn gen_methods(interface: &Interface) -> TokenStream {
     interface.requests.iter().map(|request| {
         let created_interface = request.args.iter().find(|arg| arg.typ == Type::NewId).map(|arg| &arg.interface);
 
         let method_name = format_ident!("{}{}", if is_keyword(&request.name) { "_" } else { "" }, request.name);
+        let doc_attr = crate::common::gen_method_doc_attr(request, |arg| arg.typ == Type::NewId);
+        let deprecated_attr = crate::common::deprecated_attr(request);
         let enum_variant = Ident::new(&snake_to_camel(&request.name), Span::call_site());
 
         let fn_args = request.args.iter().flat_map(|arg| {
@@ -195,8 +320,11 @@ fn gen_methods(interface: &Interface) -> TokenStream {
                 let created_iface_mod = Ident::new(created_interface, Span::call_site());
                 let created_iface_type = Ident::new(&snake_to_camel(created_interface), Span::call_site());
                 quote! {
+                    #doc_attr
+                    #deprecated_attr
                     #[allow(clippy::too_many_arguments)]
-                    pub fn #method_name<D: Dispatch<super::#created_iface_mod::#created_iface_type> + 'static>(&self, conn: &mut ConnectionHandle, #(#fn_args,)* qh: &QueueHandle<D>, udata: <D as Dispatch<super::#created_iface_mod::#created_iface_type>>::UserData) -> Result<super::#created_iface_mod::#created_iface_type, InvalidId> {
+                    #[track_caller]
+                    pub fn #method_name<D: Dispatch<super::#created_iface_mod::#created_iface_type>>(&self, conn: &mut ConnectionHandle, #(#fn_args,)* qh: &QueueHandle<D>, udata: <D as Dispatch<super::#created_iface_mod::#created_iface_type>>::UserData) -> Result<super::#created_iface_mod::#created_iface_type, DispatchError> {
                         let ret = conn.send_request(
                             self,
                             Request::#enum_variant {
@@ -204,16 +332,20 @@ fn gen_methods(interface: &Interface) -> TokenStream {
                             },
                             Some(qh.make_data::<super::#created_iface_mod::#created_iface_type>(udata))
                         )?;
-                        Proxy::from_id(conn, ret)
+                        Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
                     }
                 }
             },
             Some(None) => {
                 // a bind-like request
                 quote! {
+                    #doc_attr
+                    #[doc = "\n\nThe interface of the created object is given by the type parameter `I`, and the version is capped to the highest version of `I` known by these bindings."]
+                    #deprecated_attr
                     #[allow(clippy::too_many_arguments)]
-                    pub fn #method_name<I: Proxy + 'static, D: Dispatch<I> + 'static>(&self, conn: &mut ConnectionHandle, #(#fn_args,)* qh: &QueueHandle<D>, udata: <D as Dispatch<I>>::UserData) -> Result<I, InvalidId> {
-                        let placeholder = conn.placeholder_id(Some((I::interface(), version)));
+                    #[track_caller]
+                    pub fn #method_name<I: Proxy + 'static, D: Dispatch<I>>(&self, conn: &mut ConnectionHandle, #(#fn_args,)* qh: &QueueHandle<D>, udata: <D as Dispatch<I>>::UserData) -> Result<I, DispatchError> {
+                        let version = version.min(I::interface().version);
                         let ret = conn.send_request(
                             self,
                             Request::#enum_variant {
@@ -221,13 +353,15 @@ fn gen_methods(interface: &Interface) -> TokenStream {
                             },
                             Some(qh.make_data::<I>(udata))
                         )?;
-                        Proxy::from_id(conn, ret)
+                        Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
                     }
                 }
             },
             None => {
                 // a non-creating request
                 quote! {
+                    #doc_attr
+                    #deprecated_attr
                     #[allow(clippy::too_many_arguments)]
                     pub fn #method_name(&self, conn: &mut ConnectionHandle, #(#fn_args),*) {
                         let _ = conn.send_request(
@@ -250,18 +384,127 @@ mod tests {
     fn client_gen() {
         let protocol_file =
             std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
-        let protocol_parsed = crate::parse::parse(protocol_file);
-        let generated: String = super::generate_client_objects(&protocol_parsed).to_string();
-        let generated = crate::format_rust_code(&generated);
-
-        let reference =
-            std::fs::read_to_string("./tests/scanner_assets/test-client-code.rs").unwrap();
-        let reference = crate::format_rust_code(&reference);
-
-        if reference != generated {
-            let diff = similar::TextDiff::from_lines(&reference, &generated);
-            print!("{}", diff.unified_diff().context_radius(10).header("reference", "generated"));
-            panic!("Generated does not match reference!")
-        }
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let generated = super::generate_client_objects(
+            &protocol_parsed,
+            false,
+            &Default::default(),
+            &Default::default(),
+        );
+        crate::golden::assert_golden(&generated, "./tests/scanner_assets/test-client-code.rs");
+    }
+
+    #[test]
+    fn client_gen_borrowed_events() {
+        let protocol_file =
+            std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let generated = super::generate_client_objects(
+            &protocol_parsed,
+            true,
+            &Default::default(),
+            &Default::default(),
+        );
+        crate::golden::assert_golden(
+            &generated,
+            "./tests/scanner_assets/test-client-code-borrowed.rs",
+        );
+    }
+
+    #[test]
+    fn client_gen_type_attributes() {
+        let protocol_file =
+            std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let type_attributes = crate::input::TypeAttributes {
+            derives: vec![
+                syn::parse_quote!(PartialEq),
+                syn::parse_quote!(Eq),
+                syn::parse_quote!(Hash),
+                syn::parse_quote!(serde::Serialize),
+            ],
+            attributes: vec![syn::parse_quote!(serde(rename_all = "snake_case"))],
+        };
+        let generated: String = super::generate_client_objects(
+            &protocol_parsed,
+            false,
+            &type_attributes,
+            &Default::default(),
+        )
+        .to_string();
+        let generated = generated.replace(char::is_whitespace, "");
+
+        // the traits already derived are skipped
+        assert!(generated.contains(
+            "#[derive(Copy,Clone,Debug,PartialEq)]\
+             #[derive(Eq,Hash,serde::Serialize)]\
+             #[serde(rename_all=\"snake_case\")]\
+             #[non_exhaustive]\
+             pubenumError{"
+        ));
+        // as well as those implemented by hand for the proxies
+        assert!(generated.contains(
+            "#[derive(Debug,Clone)]\
+             #[derive(Hash,serde::Serialize)]\
+             #[serde(rename_all=\"snake_case\")]\
+             pubstructWlDisplay{"
+        ));
+        assert!(generated.contains(
+            "#[derive(Debug)]\
+             #[derive(PartialEq,Eq,Hash,serde::Serialize)]\
+             #[serde(rename_all=\"snake_case\")]\
+             #[non_exhaustive]\
+             pubenumEvent{"
+        ));
+    }
+
+    #[test]
+    fn client_gen_type_attributes_golden() {
+        let protocol_file =
+            std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let type_attributes = crate::input::TypeAttributes {
+            derives: vec![syn::parse_quote!(Hash)],
+            attributes: vec![syn::parse_quote!(allow(missing_docs))],
+        };
+        let generated = super::generate_client_objects(
+            &protocol_parsed,
+            false,
+            &type_attributes,
+            &Default::default(),
+        );
+        crate::golden::assert_golden(
+            &generated,
+            "./tests/scanner_assets/test-client-code-attributes.rs",
+        );
+    }
+
+    #[test]
+    fn client_gen_feature_gates() {
+        let protocol_file =
+            std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let feature_gates = crate::input::FeatureGates {
+            protocol: Some(syn::parse_quote!("test")),
+            interfaces: vec![(syn::parse_quote!(quad), syn::parse_quote!("quad"))],
+        };
+        assert!(feature_gates.check(&protocol_parsed).is_ok());
+        let generated: String = super::generate_client_objects(
+            &protocol_parsed,
+            false,
+            &Default::default(),
+            &feature_gates,
+        )
+        .to_string();
+        let generated = generated.replace(char::is_whitespace, "");
+
+        assert!(generated.contains("#[cfg(feature=\"test\")]pubmodtertiary{"));
+        assert!(generated.contains("#[cfg(feature=\"test\")]#[cfg(feature=\"quad\")]pubmodquad{"));
+
+        let unknown = crate::input::FeatureGates {
+            protocol: None,
+            interfaces: vec![(syn::parse_quote!(quintuple), syn::parse_quote!("quintuple"))],
+        };
+        assert!(unknown.check(&protocol_parsed).is_err());
     }
 }
@@ -2,138 +2,186 @@ use proc_macro2::{Ident, Literal, Span, TokenStream};
 
 use quote::{format_ident, quote, ToTokens};
 
-use crate::{protocol::*, util::*, Side};
+use crate::{protocol::*, util::*};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Side {
+    /// wayland client applications
+    Client,
+    /// wayland compositors
+    Server,
+}
 
-pub(crate) fn generate_enums_for(interface: &Interface) -> TokenStream {
-    interface.enums.iter().map(ToTokens::into_token_stream).collect()
+pub(crate) fn generate_enums_for(
+    interface: &Interface,
+    type_attributes: &crate::input::TypeAttributes,
+) -> TokenStream {
+    interface.enums.iter().map(|enu| gen_enum(enu, type_attributes)).collect()
 }
 
-impl ToTokens for Enum {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let enum_decl;
-        let enum_impl;
+fn gen_enum(enu: &Enum, type_attributes: &crate::input::TypeAttributes) -> TokenStream {
+    let enum_decl;
+    let enum_impl;
 
-        let doc_attr = self.description.as_ref().map(description_to_doc_attr);
-        let ident = Ident::new(&snake_to_camel(&self.name), Span::call_site());
+    let doc_attr = versioned_doc_attr(enu.description.as_ref(), None, enu.since);
+    let ident = Ident::new(&snake_to_camel(&enu.name), Span::call_site());
 
-        if self.bitfield {
-            let entries = self.entries.iter().map(|entry| {
-                let doc_attr = entry
-                    .description
-                    .as_ref()
-                    .map(description_to_doc_attr)
-                    .or_else(|| entry.summary.as_ref().map(|s| to_doc_attr(s)));
+    if enu.bitfield {
+        let entries = enu.entries.iter().map(|entry| {
+            let doc_attr =
+                versioned_doc_attr(entry.description.as_ref(), entry.summary.as_ref(), entry.since);
 
-                let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
-                let ident = format_ident!("{}{}", prefix, snake_to_camel(&entry.name));
+            let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
+            let ident = format_ident!("{}{}", prefix, snake_to_camel(&entry.name));
 
-                let value = Literal::u32_unsuffixed(entry.value);
+            let value = Literal::u32_unsuffixed(entry.value);
 
-                quote! {
-                    #doc_attr
-                    const #ident = #value;
-                }
-            });
+            quote! {
+                #doc_attr
+                const #ident = #value;
+            }
+        });
 
-            enum_decl = quote! {
-                bitflags::bitflags! {
-                    #doc_attr
-                    pub struct #ident: u32 {
-                        #(#entries)*
-                    }
+        // bitflags derives these traits itself
+        let extra_attrs = type_attributes.to_attrs(&[
+            "Copy",
+            "Clone",
+            "Debug",
+            "PartialEq",
+            "Eq",
+            "PartialOrd",
+            "Ord",
+            "Hash",
+        ]);
+        enum_decl = quote! {
+            bitflags::bitflags! {
+                #doc_attr
+                #extra_attrs
+                pub struct #ident: u32 {
+                    #(#entries)*
                 }
-            };
-            enum_impl = quote! {
-                impl std::convert::TryFrom<u32> for #ident {
-                    type Error = ();
-                    fn try_from(val: u32) -> Result<#ident, ()> {
-                        #ident::from_bits(val).ok_or(())
-                    }
+            }
+        };
+        enum_impl = quote! {
+            impl std::convert::TryFrom<u32> for #ident {
+                type Error = ();
+                fn try_from(val: u32) -> Result<#ident, ()> {
+                    #ident::from_bits(val).ok_or(())
                 }
-                impl std::convert::From<#ident> for u32 {
-                    fn from(val: #ident) -> u32 {
-                        val.bits()
-                    }
+            }
+            impl std::convert::From<#ident> for u32 {
+                fn from(val: #ident) -> u32 {
+                    val.bits()
                 }
-            };
-        } else {
-            let variants = self.entries.iter().map(|entry| {
-                let doc_attr = entry
-                    .description
-                    .as_ref()
-                    .map(description_to_doc_attr)
-                    .or_else(|| entry.summary.as_ref().map(|s| to_doc_attr(s)));
-
-                let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
-                let variant = format_ident!("{}{}", prefix, snake_to_camel(&entry.name));
+            }
+        };
+    } else {
+        let variants = enu.entries.iter().map(|entry| {
+            let doc_attr =
+                versioned_doc_attr(entry.description.as_ref(), entry.summary.as_ref(), entry.since);
 
-                let value = Literal::u32_unsuffixed(entry.value);
+            let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
+            let variant = format_ident!("{}{}", prefix, snake_to_camel(&entry.name));
 
-                quote! {
-                    #doc_attr
-                    #variant = #value
-                }
-            });
+            let value = Literal::u32_unsuffixed(entry.value);
 
-            enum_decl = quote! {
+            quote! {
                 #doc_attr
-                #[repr(u32)]
-                #[derive(Copy, Clone, Debug, PartialEq)]
-                #[non_exhaustive]
-                pub enum #ident {
-                    #(#variants,)*
-                }
-            };
+                #variant = #value
+            }
+        });
 
-            let match_arms = self.entries.iter().map(|entry| {
-                let value = Literal::u32_unsuffixed(entry.value);
+        let extra_attrs = type_attributes.to_attrs(&["Copy", "Clone", "Debug", "PartialEq"]);
+        enum_decl = quote! {
+            #doc_attr
+            #[repr(u32)]
+            #[derive(Copy, Clone, Debug, PartialEq)]
+            #extra_attrs
+            #[non_exhaustive]
+            pub enum #ident {
+                #(#variants,)*
+            }
+        };
 
-                let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
-                let variant = format_ident!("{}{}", prefix, snake_to_camel(&entry.name));
+        let match_arms = enu.entries.iter().map(|entry| {
+            let value = Literal::u32_unsuffixed(entry.value);
 
-                quote! {
-                    #value => Ok(#ident::#variant)
-                }
-            });
+            let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
+            let variant = format_ident!("{}{}", prefix, snake_to_camel(&entry.name));
 
-            enum_impl = quote! {
-                impl std::convert::TryFrom<u32> for #ident {
-                    type Error = ();
-                    fn try_from(val: u32) -> Result<#ident, ()> {
-                        match val {
-                            #(#match_arms,)*
-                            _ => Err(())
-                        }
+            quote! {
+                #value => Ok(#ident::#variant)
+            }
+        });
+
+        enum_impl = quote! {
+            impl std::convert::TryFrom<u32> for #ident {
+                type Error = ();
+                fn try_from(val: u32) -> Result<#ident, ()> {
+                    match val {
+                        #(#match_arms,)*
+                        _ => Err(())
                     }
                 }
-                impl std::convert::From<#ident> for u32 {
-                    fn from(val: #ident) -> u32 {
-                        val as u32
-                    }
+            }
+            impl std::convert::From<#ident> for u32 {
+                fn from(val: #ident) -> u32 {
+                    val as u32
                 }
-            };
-        }
+            }
+        };
+    }
+
+    quote! {
+        #enum_decl
+        #enum_impl
+    }
+}
 
-        enum_decl.to_tokens(tokens);
-        enum_impl.to_tokens(tokens);
+/// Documentation of an enum or enum entry, from its description or summary and `since` attribute
+fn versioned_doc_attr(
+    description: Option<&(String, String)>,
+    summary: Option<&String>,
+    since: u16,
+) -> Option<TokenStream> {
+    let mut docs = match (description, summary) {
+        (Some((short, long)), _) => format!("{}\n\n{}\n", short, long.trim()),
+        (None, Some(summary)) => format!("{}\n", summary),
+        (None, None) => String::new(),
+    };
+    if since > 1 {
+        docs += &format!("\nOnly available since version {} of the interface", since);
+    }
+    if docs.is_empty() {
+        None
+    } else {
+        Some(to_doc_attr(&docs))
     }
 }
 
 pub(crate) fn gen_since_constants(requests: &[Message], events: &[Message]) -> TokenStream {
-    let req_constants = requests.iter().map(|msg| {
+    let req_constants = requests.iter().enumerate().map(|(opcode, msg)| {
         let cstname = format_ident!("REQ_{}_SINCE", msg.name.to_ascii_uppercase());
+        let opname = format_ident!("REQ_{}_OPCODE", msg.name.to_ascii_uppercase());
         let since = msg.since;
+        let opcode = opcode as u16;
         quote! {
             /// The minimal object version supporting this request
             pub const #cstname: u32 = #since;
+            /// The wire opcode for this request
+            pub const #opname: u16 = #opcode;
         }
     });
-    let evt_constants = events.iter().map(|msg| {
+    let evt_constants = events.iter().enumerate().map(|(opcode, msg)| {
         let cstname = format_ident!("EVT_{}_SINCE", msg.name.to_ascii_uppercase());
+        let opname = format_ident!("EVT_{}_OPCODE", msg.name.to_ascii_uppercase());
         let since = msg.since;
+        let opcode = opcode as u16;
         quote! {
             /// The minimal object version supporting this event
             pub const #cstname: u32 = #since;
+            /// The wire opcode for this event
+            pub const #opname: u16 = #opcode;
         }
     });
 
@@ -143,28 +191,61 @@ pub(crate) fn gen_since_constants(requests: &[Message], events: &[Message]) -> T
     }
 }
 
+/// Documentation of a message, from its description and its destructor and `since` attributes
+pub(crate) fn message_docs(msg: &Message, receiver: bool) -> String {
+    let mut docs = String::new();
+    if let Some((ref short, ref long)) = msg.description {
+        docs += &format!("{}\n\n{}\n", short, long.trim());
+    }
+    if let Some(Type::Destructor) = msg.typ {
+        docs += &format!(
+            "\nThis is a destructor, once {} this object cannot be used any longer.",
+            if receiver { "received" } else { "sent" },
+        );
+    }
+    if msg.since > 1 {
+        docs += &format!("\nOnly available since version {} of the interface", msg.since);
+    }
+    docs
+}
+
+/// The `#[deprecated]` attribute of a message marked with `deprecated-since`
+pub(crate) fn deprecated_attr(msg: &Message) -> Option<TokenStream> {
+    msg.deprecated_since.map(|version| {
+        let note = format!("Deprecated since version {} of the interface", version);
+        quote!(#[deprecated(note = #note)])
+    })
+}
+
+/// Documentation of the method sending a message, listing the arguments it takes
+///
+/// `skip` filters out the arguments that are not parameters of the method.
+pub(crate) fn gen_method_doc_attr(msg: &Message, skip: impl Fn(&Arg) -> bool) -> TokenStream {
+    let mut docs = message_docs(msg, false);
+    let args = msg
+        .args
+        .iter()
+        .filter(|arg| !skip(arg))
+        .filter_map(|arg| arg.summary.as_ref().map(|summary| (&arg.name, summary)))
+        .map(|(name, summary)| format!("- `{}`: {}", name, summary))
+        .collect::<Vec<_>>();
+    if !args.is_empty() {
+        docs = format!("{}\n\n## Arguments\n\n{}", docs.trim_end(), args.join("\n"));
+    }
+    to_doc_attr(&docs)
+}
+
 pub(crate) fn gen_message_enum(
     name: &Ident,
     side: Side,
     receiver: bool,
+    borrowed: bool,
     messages: &[Message],
+    type_attributes: &crate::input::TypeAttributes,
 ) -> TokenStream {
     let variants = messages.iter().map(|msg| {
-        let mut docs = String::new();
-        if let Some((ref short, ref long)) = msg.description {
-            docs += &format!("{}\n\n{}\n", short, long.trim());
-        }
-        if let Some(Type::Destructor) = msg.typ {
-            docs += &format!(
-                "\nThis is a destructor, once {} this object cannot be used any longer.",
-                if receiver { "received" } else { "sent" },
-            );
-        }
-        if msg.since > 1 {
-            docs += &format!("\nOnly available since version {} of the interface", msg.since);
-        }
-
-        let doc_attr = to_doc_attr(&docs);
+        let doc_attr = to_doc_attr(&message_docs(msg, receiver));
+        let deprecated_attr = deprecated_attr(msg);
         let msg_name = Ident::new(&snake_to_camel(&msg.name), Span::call_site());
         let msg_variant_decl = if msg.args.is_empty() {
             msg_name.into_token_stream()
@@ -180,7 +261,9 @@ pub(crate) fn gen_message_enum(
                         Type::Uint => quote! { u32 },
                         Type::Int => quote! { i32 },
                         Type::Fixed => quote! { f64 },
+                        Type::String if borrowed => quote! { ::std::borrow::Cow<'a, str> },
                         Type::String => quote! { String },
+                        Type::Array if borrowed => quote! { &'a [u8] },
                         Type::Array => quote! { Vec<u8> },
                         Type::Fd => quote! { ::std::os::unix::io::RawFd },
                         Type::Object => {
@@ -254,20 +337,51 @@ pub(crate) fn gen_message_enum(
 
         quote! {
             #doc_attr
+            #deprecated_attr
             #msg_variant_decl
         }
     });
 
+    let since_arms = messages.iter().map(|msg| {
+        let msg_name = Ident::new(&snake_to_camel(&msg.name), Span::call_site());
+        let since = msg.since;
+        quote! { #name::#msg_name { .. } => #since }
+    });
+
+    // the borrowed variant of the enum borrows its strings and arrays from the parsed message
+    let generics = if borrowed {
+        quote! { <'a> }
+    } else {
+        quote! {}
+    };
+
+    let extra_attrs = type_attributes.to_attrs(&["Debug"]);
+
     quote! {
         #[derive(Debug)]
+        #extra_attrs
         #[non_exhaustive]
-        pub enum #name {
+        pub enum #name #generics {
             #(#variants,)*
         }
+
+        #[allow(deprecated)]
+        impl #generics #name #generics {
+            /// The minimal object version supporting this message
+            pub const fn since(&self) -> u32 {
+                match *self {
+                    #(#since_arms,)*
+                }
+            }
+        }
     }
 }
 
-pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
+/// Generate the body of the function parsing a message into a `Request` or `Event`
+///
+/// With `borrowed`, the message is parsed from a `msg: &'a Message<ObjectId>` into the borrowed
+/// `EventRef<'a>` instead, whose strings and arrays are not copied.
+pub(crate) fn gen_parse_body(interface: &Interface, side: Side, borrowed: bool) -> TokenStream {
     let msgs = match side {
         Side::Client => &interface.events,
         Side::Server => &interface.requests,
@@ -280,12 +394,18 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
         Span::call_site(),
     );
     let msg_type = Ident::new(
-        match side {
-            Side::Client => "Event",
-            Side::Server => "Request",
+        match (side, borrowed) {
+            (Side::Client, true) => "EventRef",
+            (Side::Client, false) => "Event",
+            (Side::Server, _) => "Request",
         },
         Span::call_site(),
     );
+    let bad_message = if borrowed {
+        quote! { DispatchError::BadMessage { msg: msg.clone(), interface: Self::interface().name } }
+    } else {
+        quote! { DispatchError::BadMessage { msg, interface: Self::interface().name } }
+    };
 
     let match_arms = msgs.iter().enumerate().map(|(opcode, msg)| {
         let opcode = opcode as u16;
@@ -317,8 +437,10 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
                     Type::Uint | Type::Int | Type::Fd => quote!{ #arg_name: *#arg_name },
                     Type::Fixed => quote!{ #arg_name: (*#arg_name as f64) / 256.},
                     Type::String => {
-                        let string_conversion = quote! {
-                            String::from_utf8_lossy(#arg_name.as_bytes()).into_owned()
+                        let string_conversion = if borrowed {
+                            quote! { String::from_utf8_lossy(#arg_name.as_bytes()) }
+                        } else {
+                            quote! { String::from_utf8_lossy(#arg_name.as_bytes()).into_owned() }
                         };
 
                         if arg.allow_null {
@@ -341,7 +463,7 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
                             quote! {
                                 match <super::#created_iface_mod::#created_iface_type as #object_type>::from_id(conn, #arg_name.clone()) {
                                     Ok(p) => p,
-                                    Err(_) => return Err(DispatchError::BadMessage { msg, interface: Self::interface().name }),
+                                    Err(_) => return Err(#bad_message),
                                 }
                             }
                         } else {
@@ -364,7 +486,7 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
                             quote! {
                                 match <super::#created_iface_mod::#created_iface_type as #object_type>::from_id(conn, #arg_name.clone()) {
                                     Ok(p) => p,
-                                    Err(_) => return Err(DispatchError::BadMessage { msg, interface: Self::interface().name }),
+                                    Err(_) => return Err(#bad_message),
                                 }
                             }
                         } else if side == Side::Server {
@@ -392,6 +514,13 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
                             }
                         }
                     },
+                    Type::Array if borrowed => {
+                        if arg.allow_null {
+                            quote! { #arg_name: if #arg_name.is_empty() { None } else { Some(#arg_name.as_slice()) } }
+                        } else {
+                            quote! { #arg_name: #arg_name.as_slice() }
+                        }
+                    },
                     Type::Array => {
                         if arg.allow_null {
                             quote! { if #arg_name.len() == 0 { None } else { Some(*#arg_name.clone()) } }
@@ -409,7 +538,7 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
                 if let [#(#args_pat),*] = &msg.args[..] {
                     Ok((me, #msg_type::#msg_name { #(#arg_names),* }))
                 } else {
-                    Err(DispatchError::BadMessage { msg, interface: Self::interface().name })
+                    Err(#bad_message)
                 }
             }
         }
@@ -419,7 +548,7 @@ pub(crate) fn gen_parse_body(interface: &Interface, side: Side) -> TokenStream {
         let me = Self::from_id(conn, msg.sender_id.clone()).unwrap();
         match msg.opcode {
             #(#match_arms),*
-            _ => Err(DispatchError::BadMessage { msg, interface: Self::interface().name }),
+            _ => Err(#bad_message),
         }
     }
 }
@@ -479,16 +608,14 @@ pub(crate) fn gen_write_body(interface: &Interface, side: Side) -> TokenStream {
                     if let Some(ref created_interface) = arg.interface {
                         let created_iface_mod = Ident::new(created_interface, Span::call_site());
                         let created_iface_type = Ident::new(&snake_to_camel(created_interface), Span::call_site());
-                        quote! { {
-                            let my_info = conn.object_info(self.id())?;
-                            let placeholder = conn.placeholder_id(Some((super::#created_iface_mod::#created_iface_type::interface(), my_info.version)));
-                            Argument::NewId(placeholder)
-                        } }
+                        quote! {
+                            Argument::NewId(conn.placeholder_id(Some(super::#created_iface_mod::#created_iface_type::interface())))
+                        }
                     } else {
                         quote! {
                             Argument::Str(Box::new(std::ffi::CString::new(#arg_name.0.name).unwrap())),
                             Argument::Uint(#arg_name.1),
-                            Argument::NewId(conn.placeholder_id(Some((#arg_name.0, #arg_name.1))))
+                            Argument::NewId(conn.placeholder_id(Some(#arg_name.0)))
                         }
                     }
                 } else {
@@ -0,0 +1,63 @@
+//! Comparison of the generated code with golden files
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use proc_macro2::TokenStream;
+
+/// Format some code with rustfmt
+pub(crate) fn format_rust_code(code: &str) -> io::Result<String> {
+    let mut proc = Command::new("rustfmt")
+        .arg("--emit=stdout")
+        .arg("--edition=2018")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    proc.stdin.take().unwrap().write_all(code.as_bytes())?;
+    let output = proc.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "rustfmt failed to format the code",
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Compare the generated code with the golden file at the given path
+///
+/// Both are formatted with rustfmt, so that the golden file does not need to be formatted the same
+/// way. Returns `None` if they match, and a unified diff from the golden file to the generated code
+/// otherwise.
+pub(crate) fn diff_golden(generated: &TokenStream, golden: &Path) -> io::Result<Option<String>> {
+    let generated = format_rust_code(&generated.to_string())?;
+    let reference = format_rust_code(&std::fs::read_to_string(golden)?)?;
+    if reference == generated {
+        return Ok(None);
+    }
+    let golden_name = golden.display().to_string();
+    let diff = similar::TextDiff::from_lines(&reference, &generated);
+    Ok(Some(diff.unified_diff().context_radius(10).header(&golden_name, "generated").to_string()))
+}
+
+/// Write the generated code to the golden file at the given path, formatted with rustfmt
+pub(crate) fn bless(generated: &TokenStream, golden: &Path) -> io::Result<()> {
+    std::fs::write(golden, format_rust_code(&generated.to_string())?)
+}
+
+/// Check that the generated code matches the golden file at the given path
+///
+/// If the `WAYLAND_SCANNER_BLESS` environment variable is set, the golden file is instead
+/// overwritten with the generated code.
+#[cfg(test)]
+pub(crate) fn assert_golden(generated: &TokenStream, golden: &str) {
+    if std::env::var_os("WAYLAND_SCANNER_BLESS").is_some() {
+        bless(generated, golden.as_ref()).unwrap();
+    } else if let Some(diff) = diff_golden(generated, golden.as_ref()).unwrap() {
+        print!("{}", diff);
+        panic!("Generated does not match reference!")
+    }
+}
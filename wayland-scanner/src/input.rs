@@ -0,0 +1,315 @@
+//! Parsing of the inputs of the macros, shared with the command-line interface
+
+use std::{ffi::OsString, path::PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    LitStr, Token,
+};
+
+use crate::protocol::Protocol;
+
+/// Input of the `generate_protocol!` macro: `"path/to/protocol.xml" [, [import, ...]] [, option...]`
+pub(crate) struct ProtocolInput {
+    path: LitStr,
+    imports: Vec<syn::Path>,
+    type_attributes: TypeAttributes,
+    feature_gates: FeatureGates,
+}
+
+impl Parse for ProtocolInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut imports = Vec::new();
+        let mut type_attributes = TypeAttributes::default();
+        let mut feature_gates = FeatureGates::default();
+        if input.parse::<Option<Token![,]>>()?.is_some() && input.peek(syn::token::Bracket) {
+            let content;
+            syn::bracketed!(content in input);
+            imports = Punctuated::<syn::Path, Token![,]>::parse_terminated(&content)?
+                .into_iter()
+                .collect();
+            input.parse::<Option<Token![,]>>()?;
+        }
+        while !input.is_empty() {
+            let option = input.parse::<syn::Ident>()?;
+            if !type_attributes.parse_option(&option, input)?
+                && !feature_gates.parse_option(&option, input)?
+            {
+                return Err(unknown_option(&option, &[]));
+            }
+            if input.parse::<Option<Token![,]>>()?.is_none() {
+                break;
+            }
+        }
+        Ok(ProtocolInput { path, imports, type_attributes, feature_gates })
+    }
+}
+
+/// Input of the `generate_client_code!` macro: `"path/to/protocol.xml" [, option...]`
+pub(crate) struct ClientInput {
+    path: LitStr,
+    borrowed_events: bool,
+    type_attributes: TypeAttributes,
+    feature_gates: FeatureGates,
+}
+
+impl Parse for ClientInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut borrowed_events = false;
+        let mut type_attributes = TypeAttributes::default();
+        let mut feature_gates = FeatureGates::default();
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let option = input.parse::<syn::Ident>()?;
+            if option == "borrowed_events" {
+                borrowed_events = true;
+            } else if !type_attributes.parse_option(&option, input)?
+                && !feature_gates.parse_option(&option, input)?
+            {
+                return Err(unknown_option(&option, &["borrowed_events"]));
+            }
+        }
+        Ok(ClientInput { path, borrowed_events, type_attributes, feature_gates })
+    }
+}
+
+/// Input of the `generate_server_code!` macro: `"path/to/protocol.xml" [, option...]`
+pub(crate) struct ServerInput {
+    path: LitStr,
+    type_attributes: TypeAttributes,
+    feature_gates: FeatureGates,
+}
+
+impl Parse for ServerInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut type_attributes = TypeAttributes::default();
+        let mut feature_gates = FeatureGates::default();
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let option = input.parse::<syn::Ident>()?;
+            if !type_attributes.parse_option(&option, input)?
+                && !feature_gates.parse_option(&option, input)?
+            {
+                return Err(unknown_option(&option, &[]));
+            }
+        }
+        Ok(ServerInput { path, type_attributes, feature_gates })
+    }
+}
+
+impl ProtocolInput {
+    /// Generate the code of the `generate_protocol!` macro
+    pub(crate) fn generate(&self) -> Result<TokenStream, syn::Error> {
+        let protocol = load_protocol(&self.path)?;
+        self.feature_gates.check(&protocol)?;
+        Ok(crate::protocol_gen::generate_protocol(
+            &protocol,
+            &self.imports,
+            &self.type_attributes,
+            &self.feature_gates,
+        ))
+    }
+}
+
+impl ClientInput {
+    /// Generate the code of the `generate_client_code!` macro
+    pub(crate) fn generate(&self) -> Result<TokenStream, syn::Error> {
+        let protocol = load_protocol(&self.path)?;
+        self.feature_gates.check(&protocol)?;
+        Ok(crate::client_gen::generate_client_objects(
+            &protocol,
+            self.borrowed_events,
+            &self.type_attributes,
+            &self.feature_gates,
+        ))
+    }
+}
+
+impl ServerInput {
+    /// Generate the code of the `generate_server_code!` macro
+    pub(crate) fn generate(&self) -> Result<TokenStream, syn::Error> {
+        let protocol = load_protocol(&self.path)?;
+        self.feature_gates.check(&protocol)?;
+        Ok(crate::server_gen::generate_server_objects(
+            &protocol,
+            &self.type_attributes,
+            &self.feature_gates,
+        ))
+    }
+}
+
+/// Generate the code of the `generate_interfaces!` macro
+pub(crate) fn generate_interfaces(path: &LitStr) -> Result<TokenStream, syn::Error> {
+    let protocol = load_protocol(path)?;
+    Ok(crate::interfaces::generate(&protocol, true))
+}
+
+/// The error for an unknown option of a macro, accepting the given options besides the common ones
+fn unknown_option(option: &syn::Ident, accepted: &[&str]) -> syn::Error {
+    let expected = accepted
+        .iter()
+        .chain(&["derives", "attributes", "feature", "interface_features"])
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    syn::Error::new(
+        option.span(),
+        format!("Unknown option `{}`, expected one of {}", option, expected),
+    )
+}
+
+/// The additional derives and attributes of the types generated for a protocol
+#[derive(Default)]
+pub(crate) struct TypeAttributes {
+    pub(crate) derives: Vec<syn::Path>,
+    pub(crate) attributes: Vec<syn::Meta>,
+}
+
+impl TypeAttributes {
+    /// Parse the value of the `derives = [...]` or `attributes = [...]` options
+    ///
+    /// Returns `false` if the option is neither of them.
+    fn parse_option(&mut self, option: &syn::Ident, input: ParseStream) -> syn::Result<bool> {
+        if option != "derives" && option != "attributes" {
+            return Ok(false);
+        }
+        input.parse::<Token![=]>()?;
+        let content;
+        syn::bracketed!(content in input);
+        if option == "derives" {
+            self.derives.extend(Punctuated::<syn::Path, Token![,]>::parse_terminated(&content)?);
+        } else {
+            self.attributes.extend(Punctuated::<syn::Meta, Token![,]>::parse_terminated(&content)?);
+        }
+        Ok(true)
+    }
+
+    /// The attributes to add to a generated type, skipping the traits it already derives
+    pub(crate) fn to_attrs(&self, derived: &[&str]) -> TokenStream {
+        let derives = self
+            .derives
+            .iter()
+            .filter(|path| {
+                !path
+                    .segments
+                    .last()
+                    .map(|seg| derived.iter().any(|&d| seg.ident == d))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        let attributes = &self.attributes;
+        let derive_attr =
+            if derives.is_empty() { None } else { Some(quote!(#[derive(#(#derives),*)])) };
+        quote! {
+            #derive_attr
+            #(#[#attributes])*
+        }
+    }
+}
+
+/// The cargo features gating the generated code of a protocol, or of some of its interfaces
+#[derive(Default, Clone)]
+pub(crate) struct FeatureGates {
+    pub(crate) protocol: Option<LitStr>,
+    pub(crate) interfaces: Vec<(syn::Ident, LitStr)>,
+}
+
+impl FeatureGates {
+    /// Parse the value of the `feature = "..."` or `interface_features = [...]` options
+    ///
+    /// Returns `false` if the option is neither of them.
+    fn parse_option(&mut self, option: &syn::Ident, input: ParseStream) -> syn::Result<bool> {
+        if option == "feature" {
+            input.parse::<Token![=]>()?;
+            self.protocol = Some(input.parse()?);
+        } else if option == "interface_features" {
+            input.parse::<Token![=]>()?;
+            let content;
+            syn::bracketed!(content in input);
+            let gates = Punctuated::<InterfaceFeature, Token![,]>::parse_terminated(&content)?;
+            self.interfaces.extend(gates.into_iter().map(|gate| (gate.interface, gate.feature)));
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Check that the gated interfaces are defined by the protocol
+    pub(crate) fn check(&self, protocol: &Protocol) -> Result<(), syn::Error> {
+        for (interface, _) in &self.interfaces {
+            if !protocol.interfaces.iter().any(|iface| interface == &iface.name) {
+                return Err(syn::Error::new(
+                    interface.span(),
+                    format!("Unknown interface `{}` in protocol {}", interface, protocol.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The same gates, without the one of the whole protocol
+    pub(crate) fn interfaces_only(&self) -> FeatureGates {
+        FeatureGates { protocol: None, interfaces: self.interfaces.clone() }
+    }
+
+    /// The `cfg` attribute gating the whole protocol, if any
+    pub(crate) fn protocol_cfg(&self) -> TokenStream {
+        match self.protocol {
+            Some(ref feature) => quote!(#[cfg(feature = #feature)]),
+            None => TokenStream::new(),
+        }
+    }
+
+    /// The `cfg` attributes gating the code of an interface
+    pub(crate) fn interface_cfg(&self, interface: &str) -> TokenStream {
+        let features = self
+            .interfaces
+            .iter()
+            .filter(|(iface, _)| iface == interface)
+            .map(|(_, feature)| feature);
+        let protocol_cfg = self.protocol_cfg();
+        quote! {
+            #protocol_cfg
+            #(#[cfg(feature = #features)])*
+        }
+    }
+}
+
+/// An `interface = "feature"` entry of the `interface_features` option
+struct InterfaceFeature {
+    interface: syn::Ident,
+    feature: LitStr,
+}
+
+impl Parse for InterfaceFeature {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let interface = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let feature = input.parse()?;
+        Ok(InterfaceFeature { interface, feature })
+    }
+}
+
+/// Load and parse the protocol file at the given path, relative to the crate being compiled
+///
+/// Failures are reported as errors spanning the path literal given to the macro.
+fn load_protocol(path: &LitStr) -> Result<Protocol, syn::Error> {
+    let mut buf = PathBuf::new();
+    if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
+        buf.push(manifest_dir);
+    }
+    buf.push(OsString::from(path.value()));
+    let file = std::fs::File::open(&buf).map_err(|e| {
+        syn::Error::new(
+            path.span(),
+            format!("Failed to open protocol file {}: {}", buf.display(), e),
+        )
+    })?;
+    crate::parse::parse(file).map_err(|e| {
+        syn::Error::new(path.span(), format!("Invalid protocol file {}:{}", buf.display(), e))
+    })
+}
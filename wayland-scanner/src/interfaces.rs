@@ -1,29 +1,47 @@
 use proc_macro2::TokenStream;
 
-use crate::protocol::{Interface, Message, Protocol, Type};
+use crate::protocol::{Enum, Interface, Message, Protocol, Type};
 
 use quote::{format_ident, quote};
 
 pub fn generate(protocol: &Protocol, with_c_interfaces: bool) -> TokenStream {
-    let interfaces =
-        protocol.interfaces.iter().map(|iface| generate_interface(iface, with_c_interfaces));
+    let interfaces = protocol
+        .interfaces
+        .iter()
+        .map(|iface| generate_interface(iface, protocol, with_c_interfaces));
+    // the list of the interfaces, to be registered with `wayland_backend::protocol::register_interfaces()`
+    let const_names = protocol
+        .interfaces
+        .iter()
+        .map(|iface| format_ident!("{}_INTERFACE", iface.name.to_ascii_uppercase()));
+    let list = quote! {
+        pub static INTERFACES: &[&wayland_backend::protocol::Interface] = &[ #(&#const_names),* ];
+    };
     if with_c_interfaces {
         let prefix = super::c_interfaces::generate_interfaces_prefix(protocol);
         quote! {
             #prefix
             #(#interfaces)*
+            #list
         }
     } else {
-        interfaces.collect()
+        quote! {
+            #(#interfaces)*
+            #list
+        }
     }
 }
 
-pub(crate) fn generate_interface(interface: &Interface, with_c: bool) -> TokenStream {
+pub(crate) fn generate_interface(
+    interface: &Interface,
+    protocol: &Protocol,
+    with_c: bool,
+) -> TokenStream {
     let const_name = format_ident!("{}_INTERFACE", interface.name.to_ascii_uppercase());
     let iface_name = &interface.name;
     let iface_version = interface.version;
-    let requests = build_messagedesc_list(&interface.requests);
-    let events = build_messagedesc_list(&interface.events);
+    let requests = build_messagedesc_list(interface, protocol, &interface.requests);
+    let events = build_messagedesc_list(interface, protocol, &interface.events);
 
     let c_name = format_ident!("{}_interface", interface.name);
 
@@ -53,7 +71,11 @@ pub(crate) fn generate_interface(interface: &Interface, with_c: bool) -> TokenSt
     }
 }
 
-fn build_messagedesc_list(list: &[Message]) -> TokenStream {
+fn build_messagedesc_list(
+    interface: &Interface,
+    protocol: &Protocol,
+    list: &[Message],
+) -> TokenStream {
     let desc_list = list.iter().map(|message| {
         let name = &message.name;
         let since = message.since;
@@ -79,6 +101,14 @@ fn build_messagedesc_list(list: &[Message]) -> TokenStream {
                 }
             }
         });
+        let arg_names = message.args.iter().flat_map(|arg| {
+            if arg.typ == Type::NewId && arg.interface.is_none() {
+                // the generic new_id is preceded by the interface name and version
+                vec!["interface", "version", arg.name.as_str()]
+            } else {
+                vec![arg.name.as_str()]
+            }
+        });
         let child_interface = match message
             .args
             .iter()
@@ -102,14 +132,39 @@ fn build_messagedesc_list(list: &[Message]) -> TokenStream {
                 }
             }
         });
+        let arg_enums = message
+            .args
+            .iter()
+            .scan(0usize, |index, arg| {
+                let arg_index = *index;
+                // the generic new_id expands to multiple arguments
+                *index += if arg.typ == Type::NewId && arg.interface.is_none() { 3 } else { 1 };
+                Some((arg_index, arg))
+            })
+            .filter_map(|(index, arg)| {
+                let enum_name = arg.enum_.as_ref()?;
+                let enu = find_enum(interface, protocol, enum_name)?;
+                let bitfield = enu.bitfield;
+                let values = enu.entries.iter().map(|entry| entry.value);
+                Some(quote! {
+                    wayland_backend::protocol::ArgEnum {
+                        index: #index,
+                        name: #enum_name,
+                        bitfield: #bitfield,
+                        values: &[ #(#values),* ],
+                    }
+                })
+            });
         quote! {
             wayland_backend::protocol::MessageDesc {
                 name: #name,
                 signature: &[ #(#signature),* ],
+                arg_names: &[ #(#arg_names),* ],
                 since: #since,
                 is_destructor: #is_destructor,
                 child_interface: #child_interface,
                 arg_interfaces: &[ #(#arg_interfaces),* ],
+                arg_enums: &[ #(#arg_enums),* ],
             }
         }
     });
@@ -119,24 +174,25 @@ fn build_messagedesc_list(list: &[Message]) -> TokenStream {
     )
 }
 
+// Resolve an enum referenced by an argument, if it is defined by the same protocol
+fn find_enum<'a>(interface: &'a Interface, protocol: &'a Protocol, name: &str) -> Option<&'a Enum> {
+    let (interface, name) = match name.split_once('.') {
+        Some((iface_name, name)) => {
+            (protocol.interfaces.iter().find(|iface| iface.name == iface_name)?, name)
+        }
+        None => (interface, name),
+    };
+    interface.enums.iter().find(|enu| enu.name == name)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
     fn interface_gen() {
         let protocol_file =
             std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
-        let protocol_parsed = crate::parse::parse(protocol_file);
-        let generated: String = super::generate(&protocol_parsed, true).to_string();
-        let generated = crate::format_rust_code(&generated);
-
-        let reference =
-            std::fs::read_to_string("./tests/scanner_assets/test-interfaces.rs").unwrap();
-        let reference = crate::format_rust_code(&reference);
-
-        if reference != generated {
-            let diff = similar::TextDiff::from_lines(&reference, &generated);
-            print!("{}", diff.unified_diff().context_radius(10).header("reference", "generated"));
-            panic!("Generated does not match reference!")
-        }
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let generated = super::generate(&protocol_parsed, true);
+        crate::golden::assert_golden(&generated, "./tests/scanner_assets/test-interfaces.rs");
     }
 }
@@ -1,101 +1,105 @@
-use std::{ffi::OsString, path::PathBuf};
-
 use syn::{parse_macro_input, LitStr};
 
 mod c_interfaces;
 mod client_gen;
 mod common;
+#[cfg(test)]
+mod golden;
+mod input;
 mod interfaces;
 mod parse;
 mod protocol;
+mod protocol_gen;
 mod server_gen;
 mod util;
 
+use input::{ClientInput, ProtocolInput, ServerInput};
+
 #[proc_macro]
 pub fn generate_interfaces(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let path: OsString = parse_macro_input!(stream as LitStr).value().into();
-    let path = if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
-        let mut buf = PathBuf::from(manifest_dir);
-        buf.push(path);
-        buf
-    } else {
-        path.into()
-    };
-    let file = match std::fs::File::open(&path) {
-        Ok(file) => file,
-        Err(e) => panic!("Failed to open protocol file {}: {}", path.display(), e),
-    };
-    let protocol = parse::parse(file);
-    interfaces::generate(&protocol, true).into()
+    let path = parse_macro_input!(stream as LitStr);
+    input::generate_interfaces(&path).unwrap_or_else(|e| e.to_compile_error()).into()
 }
 
+/// Generate the client-side code of a protocol
+///
+/// The path to the protocol file can be followed by the `borrowed_events` option. The interfaces
+/// having events with string or array arguments then also get an `EventRef<'a>` enum borrowing
+/// these arguments from the message being dispatched, and a `parse_event_ref()` method producing
+/// it, to parse their events without allocating:
+///
+/// ```ignore
+/// wayland_scanner::generate_client_code!("./protocols/wayland.xml", borrowed_events);
+/// ```
+///
+/// The `derives`, `attributes`, `feature` and `interface_features` options are also accepted, see
+/// [`generate_protocol!`](generate_protocol!).
 #[proc_macro]
 pub fn generate_client_code(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let path: OsString = parse_macro_input!(stream as LitStr).value().into();
-    let path = if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
-        let mut buf = PathBuf::from(manifest_dir);
-        buf.push(path);
-        buf
-    } else {
-        path.into()
-    };
-    let file = match std::fs::File::open(&path) {
-        Ok(file) => file,
-        Err(e) => panic!("Failed to open protocol file {}: {}", path.display(), e),
-    };
-    let protocol = parse::parse(file);
-    client_gen::generate_client_objects(&protocol).into()
+    let input = parse_macro_input!(stream as ClientInput);
+    input.generate().unwrap_or_else(|e| e.to_compile_error()).into()
 }
 
+/// Generate the server-side code of a protocol
+///
+/// The path to the protocol file can be followed by the `derives`, `attributes`, `feature` and
+/// `interface_features` options, see [`generate_protocol!`](generate_protocol!).
 #[proc_macro]
 pub fn generate_server_code(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let path: OsString = parse_macro_input!(stream as LitStr).value().into();
-    let path = if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
-        let mut buf = PathBuf::from(manifest_dir);
-        buf.push(path);
-        buf
-    } else {
-        path.into()
-    };
-    let file = match std::fs::File::open(&path) {
-        Ok(file) => file,
-        Err(e) => panic!("Failed to open protocol file {}: {}", path.display(), e),
-    };
-    let protocol = parse::parse(file);
-    server_gen::generate_server_objects(&protocol).into()
+    let input = parse_macro_input!(stream as ServerInput);
+    input.generate().unwrap_or_else(|e| e.to_compile_error()).into()
 }
 
-#[cfg(test)]
-fn format_rust_code(code: &str) -> String {
-    use std::{
-        io::Write,
-        process::{Command, Stdio},
-    };
-    if let Ok(mut proc) = Command::new("rustfmt")
-        .arg("--emit=stdout")
-        .arg("--edition=2018")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        //.stderr(Stdio::null())
-        .spawn()
-    {
-        {
-            let stdin = proc.stdin.as_mut().unwrap();
-            stdin.write_all(code.as_bytes()).unwrap();
-        }
-        if let Ok(output) = proc.wait_with_output() {
-            if output.status.success() {
-                return std::str::from_utf8(&output.stdout).unwrap().to_owned();
-            }
-        }
-    }
-    panic!("Rustfmt failed!");
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum Side {
-    /// wayland client applications
-    Client,
-    /// wayland compositors
-    Server,
+/// Generate the interfaces, client-side and server-side code of a protocol at once
+///
+/// This expands to a `__interfaces` module, shared by a `client` module gated behind the
+/// `client` cargo feature of the invoking crate and a `server` module gated behind its `server`
+/// feature. The crate must depend on `wayland-backend` and `bitflags`, and on `wayland-client`
+/// and `wayland-server` for the respective features.
+///
+/// The path to the protocol file can be followed by a list of the modules generated by this
+/// macro for the protocols this one depends on:
+///
+/// ```ignore
+/// pub mod xdg_shell {
+///     wayland_scanner::generate_protocol!("./protocols/xdg-shell.xml");
+/// }
+///
+/// pub mod xdg_decoration {
+///     wayland_scanner::generate_protocol!("./protocols/xdg-decoration.xml", [crate::xdg_shell]);
+/// }
+/// ```
+///
+/// The types generated for the protocol can be adapted to the needs of the invoking crate with the
+/// `derives` option, listing additional traits to derive, and the `attributes` option, listing
+/// additional attributes. They apply to the proxy or resource structs, the `Request` and `Event`
+/// enums and the enums of the protocol alike, so the derived traits must be implementable for all
+/// of them. The traits these types already implement are skipped:
+///
+/// ```ignore
+/// wayland_scanner::generate_protocol!(
+///     "./protocols/xdg-shell.xml",
+///     derives = [my_crate::Inspect],
+///     attributes = [inspect(skip_private)],
+/// );
+/// ```
+///
+/// The generated code can also be gated behind cargo features of the invoking crate, to compile
+/// only the parts of the protocols it needs: the `feature` option gates the whole protocol, and the
+/// `interface_features` option gates the client-side and server-side code of some interfaces. The
+/// interface definitions in `__interfaces` are not gated by the latter. As the generated code of an
+/// interface refers to the interfaces created by its requests and events, the feature of an
+/// interface must enable the features of these interfaces as well.
+///
+/// ```ignore
+/// wayland_scanner::generate_protocol!(
+///     "./protocols/xdg-shell.xml",
+///     feature = "xdg_shell",
+///     interface_features = [xdg_positioner = "xdg_popup", xdg_popup = "xdg_popup"],
+/// );
+/// ```
+#[proc_macro]
+pub fn generate_protocol(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(stream as ProtocolInput);
+    input.generate().unwrap_or_else(|e| e.to_compile_error()).into()
 }
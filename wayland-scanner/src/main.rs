@@ -0,0 +1,130 @@
+//! Command-line interface of the scanner
+//!
+//! This generates the same code as the macros of the crate, but writes it to a file instead of
+//! expanding it at every compilation of the crate invoking them. It is only built with the `cli`
+//! cargo feature, for example with `cargo install wayland-scanner --features cli`, and is named
+//! `wayland-rs-scanner` to not shadow the `wayland-scanner` of libwayland generating C code:
+//!
+//! ```text
+//! wayland-rs-scanner <macro> '<arguments>' [output file]
+//! ```
+//!
+//! `<macro>` is the name of one of the macros (`generate_interfaces`, `generate_client_code`,
+//! `generate_server_code` or `generate_protocol`) and `<arguments>` are the arguments it would be
+//! given, options included. The path to the protocol file is relative to the `CARGO_MANIFEST_DIR`
+//! environment variable if it is set, like for the macros, and to the current directory otherwise.
+//! The code is written to stdout if no output file is given.
+//!
+//! The output file can also be a golden file to check the generated code against, to catch the
+//! unintended changes of the generated code of a protocol crate, for example in its CI:
+//!
+//! ```text
+//! wayland-rs-scanner <macro> '<arguments>' --check <golden file>
+//! wayland-rs-scanner <macro> '<arguments>' --bless <golden file>
+//! ```
+//!
+//! `--check` compares the generated code with the golden file, both formatted with rustfmt, and
+//! fails with a diff of the changes if they differ. `--bless` writes the formatted generated code to
+//! the golden file, to create it or accept the changes.
+//!
+//! The generated file can then be committed to the source tree, or written to `OUT_DIR` by a build
+//! step, and included where the macro would have been invoked:
+//!
+//! ```ignore
+//! pub mod xdg_shell {
+//!     include!(concat!(env!("OUT_DIR"), "/xdg_shell.rs"));
+//! }
+//! ```
+
+use std::{io::Write, path::Path};
+
+use proc_macro2::TokenStream;
+use syn::LitStr;
+
+// A proc-macro crate can only export its macros, so the binary cannot call the code generation of
+// the library and compiles it on its own. It is only built with the `cli` feature.
+mod c_interfaces;
+mod client_gen;
+mod common;
+mod golden;
+mod input;
+mod interfaces;
+mod parse;
+mod protocol;
+mod protocol_gen;
+mod server_gen;
+mod util;
+
+use input::{ClientInput, ProtocolInput, ServerInput};
+
+const USAGE: &str = "Usage: wayland-rs-scanner <generate_interfaces|generate_client_code|\
+generate_server_code|generate_protocol> '<macro arguments>' [[--check|--bless] output file]";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (mode, output) = match args.get(2).map(String::as_str) {
+        None if args.len() == 2 => (None, None),
+        Some("--check") | Some("--bless") if args.len() == 4 => {
+            (Some(args[2].as_str()), Some(Path::new(&args[3])))
+        }
+        Some(_) if args.len() == 3 => (None, Some(Path::new(&args[2]))),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let code = match generate(&args[0], &args[1]) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("wayland-rs-scanner: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let ret = match (mode, output) {
+        (Some("--check"), Some(golden)) => match golden::diff_golden(&code, golden) {
+            Ok(None) => Ok(()),
+            Ok(Some(diff)) => {
+                print!("{}", diff);
+                eprintln!(
+                    "wayland-rs-scanner: the generated code does not match {}",
+                    golden.display()
+                );
+                std::process::exit(1);
+            }
+            Err(e) => Err(e),
+        },
+        (Some(_), Some(golden)) => golden::bless(&code, golden),
+        (_, Some(output)) => std::fs::write(output, code.to_string()),
+        (_, None) => std::io::stdout().write_all(code.to_string().as_bytes()),
+    };
+    if let Err(e) = ret {
+        match output {
+            Some(output) => eprintln!("wayland-rs-scanner: {}: {}", output.display(), e),
+            None => eprintln!("wayland-rs-scanner: {}", e),
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Generate the code of the given macro for the given arguments
+fn generate(command: &str, args: &str) -> Result<TokenStream, String> {
+    let ret = match command {
+        "generate_interfaces" => {
+            let path = syn::parse_str::<LitStr>(args).map_err(|e| e.to_string())?;
+            input::generate_interfaces(&path)
+        }
+        "generate_client_code" => {
+            syn::parse_str::<ClientInput>(args).map_err(|e| e.to_string())?.generate()
+        }
+        "generate_server_code" => {
+            syn::parse_str::<ServerInput>(args).map_err(|e| e.to_string())?.generate()
+        }
+        "generate_protocol" => {
+            syn::parse_str::<ProtocolInput>(args).map_err(|e| e.to_string())?.generate()
+        }
+        _ => return Err(format!("unknown macro `{}`\n{}", command, USAGE)),
+    };
+    ret.map_err(|e| e.to_string())
+}
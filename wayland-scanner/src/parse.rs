@@ -1,255 +1,538 @@
 use super::protocol::*;
+use std::collections::HashSet;
+use std::fmt;
 use std::io::Read;
 use xml::attribute::OwnedAttribute;
+use xml::common::{Position, TextPosition};
 use xml::reader::ParserConfig;
 use xml::reader::XmlEvent;
 use xml::EventReader;
 
-macro_rules! extract_from(
-    ($it: expr => $pattern: pat => $result: expr) => (
-        match $it.next() {
-            Ok($pattern) => { $result },
-            e => panic!("Ill-formed protocol file: {:?}", e)
-        }
-    )
-);
-
-macro_rules! extract_end_tag(
-    ($it: expr => $tag: expr) => (
-        extract_from!($it => XmlEvent::EndElement { name } => {
-            assert!(name.local_name == $tag, "Ill-formed protocol file");
-        });
-    )
-);
-
-pub fn parse<S: Read>(stream: S) -> Protocol {
-    let mut reader =
-        EventReader::new_with_config(stream, ParserConfig::new().trim_whitespace(true));
-    reader.next().expect("Could not read from event reader");
-    parse_protocol(reader)
+/// An error encountered while parsing or validating a protocol file
+///
+/// The position points to the XML element responsible for the error.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub position: TextPosition,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+fn error<T>(position: TextPosition, message: impl Into<String>) -> Result<T> {
+    Err(ParseError { position, message: message.into() })
+}
+
+/// An `enum="..."` attribute, checked once the whole protocol is known
+struct EnumRef {
+    interface: String,
+    enum_: String,
+    position: TextPosition,
 }
 
-fn parse_protocol<R: Read>(mut reader: EventReader<R>) -> Protocol {
-    let mut protocol = extract_from!(
-        reader => XmlEvent::StartElement { name, attributes, .. } => {
-            assert!(name.local_name == "protocol", "Missing protocol toplevel tag");
-            assert!(attributes[0].name.local_name == "name", "Protocol must have a name");
-            Protocol::new(attributes[0].value.clone())
+struct Parser<R: Read> {
+    reader: EventReader<R>,
+    enum_refs: Vec<EnumRef>,
+}
+
+pub fn parse<S: Read>(stream: S) -> Result<Protocol> {
+    let mut parser = Parser {
+        reader: EventReader::new_with_config(stream, ParserConfig::new().trim_whitespace(true)),
+        enum_refs: Vec::new(),
+    };
+    // StartDocument
+    parser.next()?;
+    let protocol = parser.parse_protocol()?;
+    check_enum_refs(&protocol, &parser.enum_refs)?;
+    Ok(protocol)
+}
+
+impl<R: Read> Parser<R> {
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+
+    fn next(&mut self) -> Result<XmlEvent> {
+        self.reader.next().map_err(|e| ParseError {
+            position: e.position(),
+            message: format!("Ill-formed protocol file: {}", e.msg()),
+        })
+    }
+
+    fn unexpected<T>(&self, event: XmlEvent, context: &str) -> Result<T> {
+        let message = match event {
+            XmlEvent::StartElement { name, .. } => {
+                format!("Unexpected element `<{}>` in {}", name.local_name, context)
+            }
+            XmlEvent::EndElement { name } => {
+                format!("Unexpected closing tag `</{}>` in {}", name.local_name, context)
+            }
+            XmlEvent::EndDocument => format!("Unexpected end of file in {}", context),
+            e => format!("Unexpected content in {}: {:?}", context, e),
+        };
+        error(self.position(), message)
+    }
+
+    fn expect_end_tag(&mut self, tag: &str) -> Result<()> {
+        match self.next()? {
+            XmlEvent::EndElement { ref name } if name.local_name == tag => Ok(()),
+            e => self.unexpected(e, &format!("`<{}>`", tag)),
         }
-    );
+    }
+
+    fn parse_protocol(&mut self) -> Result<Protocol> {
+        let mut protocol = match self.next()? {
+            XmlEvent::StartElement { name, attributes, .. } if name.local_name == "protocol" => {
+                match attributes.into_iter().find(|a| a.name.local_name == "name") {
+                    Some(attr) => Protocol::new(attr.value),
+                    None => return error(self.position(), "Protocol must have a name"),
+                }
+            }
+            _ => return error(self.position(), "Missing protocol toplevel tag"),
+        };
+        let mut interface_names = HashSet::new();
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
-                match &name.local_name[..] {
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
                     "copyright" => {
                         // parse the copyright
-                        let copyright = match reader.next() {
-                            Ok(XmlEvent::Characters(copyright))
-                            | Ok(XmlEvent::CData(copyright)) => copyright,
-                            e => panic!("Ill-formed protocol file: {:?}", e),
+                        let copyright = match self.next()? {
+                            XmlEvent::Characters(copyright) | XmlEvent::CData(copyright) => {
+                                copyright
+                            }
+                            e => return self.unexpected(e, "`<copyright>`"),
                         };
 
-                        extract_end_tag!(reader => "copyright");
+                        self.expect_end_tag("copyright")?;
                         protocol.copyright = Some(copyright);
                     }
                     "interface" => {
-                        protocol.interfaces.push(parse_interface(&mut reader, attributes));
+                        let position = self.position();
+                        let interface = self.parse_interface(attributes)?;
+                        if !interface_names.insert(interface.name.clone()) {
+                            return error(
+                                position,
+                                format!("Duplicate interface `{}`", interface.name),
+                            );
+                        }
+                        protocol.interfaces.push(interface);
                     }
                     "description" => {
-                        protocol.description = Some(parse_description(&mut reader, attributes));
+                        protocol.description = Some(self.parse_description(attributes)?);
                     }
-                    _ => panic!(
-                        "Ill-formed protocol file: unexpected token `{}` in protocol {}",
-                        name.local_name, protocol.name
-                    ),
-                }
-            }
-            Ok(XmlEvent::EndElement { name }) => {
-                assert!(
-                    name.local_name == "protocol",
-                    "Unexpected closing token `{}`",
-                    name.local_name
-                );
-                break;
+                    _ => {
+                        return error(
+                            self.position(),
+                            format!(
+                                "Unexpected element `<{}>` in protocol `{}`",
+                                name.local_name, protocol.name
+                            ),
+                        )
+                    }
+                },
+                XmlEvent::EndElement { ref name } if name.local_name == "protocol" => break,
+                e => return self.unexpected(e, &format!("protocol `{}`", protocol.name)),
             }
-            e => panic!("Ill-formed protocol file: {:?}", e),
         }
-    }
-
-    protocol
-}
 
-fn parse_interface<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Interface {
-    let mut interface = Interface::new();
-    for attr in attrs {
-        match &attr.name.local_name[..] {
-            "name" => interface.name = attr.value,
-            "version" => interface.version = attr.value.parse().unwrap(),
-            _ => {}
-        }
+        Ok(protocol)
     }
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => {
-                    interface.description = Some(parse_description(reader, attributes))
-                }
-                "request" => interface.requests.push(parse_request(reader, attributes)),
-                "event" => interface.events.push(parse_event(reader, attributes)),
-                "enum" => interface.enums.push(parse_enum(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
-            },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "interface" => break,
-            _ => {}
+    fn parse_interface(&mut self, attrs: Vec<OwnedAttribute>) -> Result<Interface> {
+        let position = self.position();
+        let mut interface = Interface::new();
+        for attr in attrs {
+            match &attr.name.local_name[..] {
+                "name" => interface.name = attr.value,
+                "version" => interface.version = parse_version(position, "version", &attr.value)?,
+                _ => {}
+            }
         }
-    }
+        if interface.name.is_empty() {
+            return error(position, "Interface must have a name");
+        }
+        let context = format!("interface `{}`", interface.name);
 
-    interface
-}
+        let mut request_names = HashSet::new();
+        let mut event_names = HashSet::new();
+        let mut enum_names = HashSet::new();
 
-fn parse_description<R: Read>(
-    reader: &mut EventReader<R>,
-    attrs: Vec<OwnedAttribute>,
-) -> (String, String) {
-    let mut summary = String::new();
-    for attr in attrs {
-        if &attr.name.local_name[..] == "summary" {
-            summary = attr.value.split_whitespace().collect::<Vec<_>>().join(" ");
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let position = self.position();
+                    match &name.local_name[..] {
+                        "description" => {
+                            interface.description = Some(self.parse_description(attributes)?)
+                        }
+                        "request" => {
+                            let request = self.parse_message(&interface, "request", attributes)?;
+                            check_message(
+                                position,
+                                &request,
+                                &interface,
+                                &interface.requests,
+                                &mut request_names,
+                                "request",
+                            )?;
+                            interface.requests.push(request);
+                        }
+                        "event" => {
+                            let event = self.parse_message(&interface, "event", attributes)?;
+                            check_message(
+                                position,
+                                &event,
+                                &interface,
+                                &interface.events,
+                                &mut event_names,
+                                "event",
+                            )?;
+                            interface.events.push(event);
+                        }
+                        "enum" => {
+                            let enu = self.parse_enum(&interface, attributes)?;
+                            if !enum_names.insert(enu.name.clone()) {
+                                return error(
+                                    position,
+                                    format!("Duplicate enum `{}` in {}", enu.name, context),
+                                );
+                            }
+                            interface.enums.push(enu);
+                        }
+                        _ => {
+                            return error(
+                                position,
+                                format!(
+                                    "Unexpected element `<{}>` in {}",
+                                    name.local_name, context
+                                ),
+                            )
+                        }
+                    }
+                }
+                XmlEvent::EndElement { ref name } if name.local_name == "interface" => break,
+                e @ XmlEvent::EndElement { .. } | e @ XmlEvent::EndDocument => {
+                    return self.unexpected(e, &context)
+                }
+                _ => {}
+            }
         }
+
+        Ok(interface)
     }
 
-    let description = match reader.next() {
-        Ok(XmlEvent::Characters(txt)) => {
-            extract_end_tag!(reader => "description");
-            txt
+    fn parse_description(&mut self, attrs: Vec<OwnedAttribute>) -> Result<(String, String)> {
+        let mut summary = String::new();
+        for attr in attrs {
+            if &attr.name.local_name[..] == "summary" {
+                summary = attr.value.split_whitespace().collect::<Vec<_>>().join(" ");
+            }
         }
-        Ok(XmlEvent::EndElement { ref name }) if name.local_name == "description" => String::new(),
-        e => panic!("Ill-formed protocol file: {:?}", e),
-    };
 
-    (summary, description)
-}
+        let description = match self.next()? {
+            XmlEvent::Characters(txt) => {
+                self.expect_end_tag("description")?;
+                txt
+            }
+            XmlEvent::EndElement { ref name } if name.local_name == "description" => String::new(),
+            e => return self.unexpected(e, "`<description>`"),
+        };
 
-fn parse_request<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Message {
-    let mut request = Message::new();
-    for attr in attrs {
-        match &attr.name.local_name[..] {
-            "name" => request.name = attr.value,
-            "type" => request.typ = Some(parse_type(&attr.value)),
-            "since" => request.since = attr.value.parse().unwrap(),
-            _ => {}
-        }
+        Ok((summary, description))
     }
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => request.description = Some(parse_description(reader, attributes)),
-                "arg" => request.args.push(parse_arg(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
-            },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "request" => break,
-            _ => {}
+    fn parse_message(
+        &mut self,
+        interface: &Interface,
+        kind: &str,
+        attrs: Vec<OwnedAttribute>,
+    ) -> Result<Message> {
+        let position = self.position();
+        let mut message = Message::new();
+        for attr in attrs {
+            match &attr.name.local_name[..] {
+                "name" => message.name = attr.value,
+                "type" => message.typ = Some(parse_type(position, &attr.value)?),
+                "since" => message.since = parse_version(position, "since", &attr.value)?,
+                "deprecated-since" => {
+                    message.deprecated_since =
+                        Some(parse_version(position, "deprecated-since", &attr.value)?)
+                }
+                _ => {}
+            }
         }
-    }
+        if message.name.is_empty() {
+            return error(position, format!("Missing `name` attribute on `<{}>`", kind));
+        }
+        if let Some(typ) = message.typ {
+            if typ != Type::Destructor {
+                return error(
+                    position,
+                    format!("Invalid {} type `{:?}`, only `destructor` is allowed", kind, typ),
+                );
+            }
+        }
+        let context = format!("{} `{}.{}`", kind, interface.name, message.name);
 
-    request
-}
+        let mut arg_names = HashSet::new();
 
-fn parse_enum<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Enum {
-    let mut enu = Enum::new();
-    for attr in attrs {
-        match &attr.name.local_name[..] {
-            "name" => enu.name = attr.value,
-            "since" => enu.since = attr.value.parse().unwrap(),
-            "bitfield" => {
-                if &attr.value[..] == "true" {
-                    enu.bitfield = true
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let position = self.position();
+                    match &name.local_name[..] {
+                        "description" => {
+                            message.description = Some(self.parse_description(attributes)?)
+                        }
+                        "arg" => {
+                            let arg = self.parse_arg(interface, attributes)?;
+                            if !arg_names.insert(arg.name.clone()) {
+                                return error(
+                                    position,
+                                    format!("Duplicate argument `{}` in {}", arg.name, context),
+                                );
+                            }
+                            message.args.push(arg);
+                        }
+                        _ => {
+                            return error(
+                                position,
+                                format!(
+                                    "Unexpected element `<{}>` in {}",
+                                    name.local_name, context
+                                ),
+                            )
+                        }
+                    }
+                }
+                XmlEvent::EndElement { ref name } if name.local_name == kind => break,
+                e @ XmlEvent::EndElement { .. } | e @ XmlEvent::EndDocument => {
+                    return self.unexpected(e, &context)
                 }
+                _ => {}
             }
-            _ => {}
         }
+
+        Ok(message)
     }
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => enu.description = Some(parse_description(reader, attributes)),
-                "entry" => enu.entries.push(parse_entry(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
-            },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "enum" => break,
-            _ => {}
+    fn parse_enum(&mut self, interface: &Interface, attrs: Vec<OwnedAttribute>) -> Result<Enum> {
+        let position = self.position();
+        let mut enu = Enum::new();
+        for attr in attrs {
+            match &attr.name.local_name[..] {
+                "name" => enu.name = attr.value,
+                "since" => enu.since = parse_since_u16(position, interface, &attr.value)?,
+                "bitfield" => {
+                    if &attr.value[..] == "true" {
+                        enu.bitfield = true
+                    }
+                }
+                _ => {}
+            }
         }
-    }
+        if enu.name.is_empty() {
+            return error(position, "Missing `name` attribute on `<enum>`");
+        }
+        let context = format!("enum `{}.{}`", interface.name, enu.name);
 
-    enu
-}
+        let mut entry_names = HashSet::new();
 
-fn parse_event<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Message {
-    let mut event = Message::new();
-    for attr in attrs {
-        match &attr.name.local_name[..] {
-            "name" => event.name = attr.value,
-            "type" => event.typ = Some(parse_type(&attr.value)),
-            "since" => event.since = attr.value.parse().unwrap(),
-            _ => {}
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let position = self.position();
+                    match &name.local_name[..] {
+                        "description" => {
+                            enu.description = Some(self.parse_description(attributes)?)
+                        }
+                        "entry" => {
+                            let entry = self.parse_entry(interface, attributes)?;
+                            if !entry_names.insert(entry.name.clone()) {
+                                return error(
+                                    position,
+                                    format!("Duplicate entry `{}` in {}", entry.name, context),
+                                );
+                            }
+                            enu.entries.push(entry);
+                        }
+                        _ => {
+                            return error(
+                                position,
+                                format!(
+                                    "Unexpected element `<{}>` in {}",
+                                    name.local_name, context
+                                ),
+                            )
+                        }
+                    }
+                }
+                XmlEvent::EndElement { ref name } if name.local_name == "enum" => break,
+                e @ XmlEvent::EndElement { .. } | e @ XmlEvent::EndDocument => {
+                    return self.unexpected(e, &context)
+                }
+                _ => {}
+            }
         }
-    }
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => event.description = Some(parse_description(reader, attributes)),
-                "arg" => event.args.push(parse_arg(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
-            },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "event" => break,
-            _ => {}
-        }
+        Ok(enu)
     }
 
-    event
-}
-
-fn parse_arg<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Arg {
-    let mut arg = Arg::new();
-    for attr in attrs {
-        match &attr.name.local_name[..] {
-            "name" => arg.name = attr.value,
-            "type" => arg.typ = parse_type(&attr.value),
-            "summary" => {
-                arg.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
+    fn parse_arg(&mut self, interface: &Interface, attrs: Vec<OwnedAttribute>) -> Result<Arg> {
+        let position = self.position();
+        let mut arg = Arg::new();
+        let mut typ = None;
+        for attr in attrs {
+            match &attr.name.local_name[..] {
+                "name" => arg.name = attr.value,
+                "type" => typ = Some(parse_type(position, &attr.value)?),
+                "summary" => {
+                    arg.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
+                }
+                "interface" => arg.interface = Some(attr.value),
+                "allow-null" => {
+                    if attr.value == "true" {
+                        arg.allow_null = true
+                    }
+                }
+                "enum" => arg.enum_ = Some(attr.value),
+                _ => {}
+            }
+        }
+        if arg.name.is_empty() {
+            return error(position, "Missing `name` attribute on `<arg>`");
+        }
+        arg.typ = match typ {
+            Some(Type::Destructor) => {
+                return error(position, format!("Argument `{}` cannot be a destructor", arg.name))
+            }
+            Some(typ) => typ,
+            None => {
+                return error(
+                    position,
+                    format!("Missing `type` attribute on argument `{}`", arg.name),
+                )
+            }
+        };
+        if arg.interface.is_some() && !matches!(arg.typ, Type::Object | Type::NewId) {
+            return error(
+                position,
+                format!(
+                    "Argument `{}` of type `{:?}` cannot have an `interface` attribute",
+                    arg.name, arg.typ
+                ),
+            );
+        }
+        if let Some(ref enu) = arg.enum_ {
+            if !matches!(arg.typ, Type::Int | Type::Uint) {
+                return error(
+                    position,
+                    format!("Enum argument `{}` must be of type `int` or `uint`", arg.name),
+                );
             }
-            "interface" => arg.interface = Some(attr.value),
-            "allow-null" => {
-                if attr.value == "true" {
-                    arg.allow_null = true
+            self.enum_refs.push(EnumRef {
+                interface: interface.name.clone(),
+                enum_: enu.clone(),
+                position,
+            });
+        }
+
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                    "description" => arg.description = Some(self.parse_description(attributes)?),
+                    _ => {
+                        return error(
+                            self.position(),
+                            format!(
+                                "Unexpected element `<{}>` in argument `{}`",
+                                name.local_name, arg.name
+                            ),
+                        )
+                    }
+                },
+                XmlEvent::EndElement { ref name } if name.local_name == "arg" => break,
+                e @ XmlEvent::EndElement { .. } | e @ XmlEvent::EndDocument => {
+                    return self.unexpected(e, &format!("argument `{}`", arg.name))
                 }
+                _ => {}
             }
-            "enum" => arg.enum_ = Some(attr.value),
-            _ => {}
         }
+
+        Ok(arg)
     }
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => arg.description = Some(parse_description(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
-            },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "arg" => break,
-            _ => {}
+    fn parse_entry(&mut self, interface: &Interface, attrs: Vec<OwnedAttribute>) -> Result<Entry> {
+        let position = self.position();
+        let mut entry = Entry::new();
+        let mut has_value = false;
+        for attr in attrs {
+            match &attr.name.local_name[..] {
+                "name" => entry.name = attr.value,
+                "value" => {
+                    let value = if let Some(hex) = attr.value.strip_prefix("0x") {
+                        u32::from_str_radix(hex, 16)
+                    } else {
+                        attr.value.parse()
+                    };
+                    entry.value = match value {
+                        Ok(value) => value,
+                        Err(_) => {
+                            return error(position, format!("Invalid entry value `{}`", attr.value))
+                        }
+                    };
+                    has_value = true;
+                }
+                "since" => entry.since = parse_since_u16(position, interface, &attr.value)?,
+                "summary" => {
+                    entry.summary =
+                        Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
+                }
+                _ => {}
+            }
+        }
+        if entry.name.is_empty() {
+            return error(position, "Missing `name` attribute on `<entry>`");
+        }
+        if !has_value {
+            return error(position, format!("Missing `value` attribute on entry `{}`", entry.name));
+        }
+
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                    "description" => entry.description = Some(self.parse_description(attributes)?),
+                    _ => {
+                        return error(
+                            self.position(),
+                            format!(
+                                "Unexpected element `<{}>` in entry `{}`",
+                                name.local_name, entry.name
+                            ),
+                        )
+                    }
+                },
+                XmlEvent::EndElement { ref name } if name.local_name == "entry" => break,
+                e @ XmlEvent::EndElement { .. } | e @ XmlEvent::EndDocument => {
+                    return self.unexpected(e, &format!("entry `{}`", entry.name))
+                }
+                _ => {}
+            }
         }
-    }
 
-    arg
+        Ok(entry)
+    }
 }
 
-fn parse_type(txt: &str) -> Type {
-    match txt {
+fn parse_type(position: TextPosition, txt: &str) -> Result<Type> {
+    Ok(match txt {
         "int" => Type::Int,
         "uint" => Type::Uint,
         "fixed" => Type::Fixed,
@@ -259,40 +542,236 @@ fn parse_type(txt: &str) -> Type {
         "array" => Type::Array,
         "fd" => Type::Fd,
         "destructor" => Type::Destructor,
-        e => panic!("Unexpected type: {}", e),
+        e => return error(position, format!("Unknown type `{}`", e)),
+    })
+}
+
+/// Parse a `version` or `since` attribute, which must be a non-zero integer
+fn parse_version(position: TextPosition, attr: &str, txt: &str) -> Result<u32> {
+    match txt.parse() {
+        Ok(0) | Err(_) => error(
+            position,
+            format!("Invalid `{}` attribute `{}`, expected a positive integer", attr, txt),
+        ),
+        Ok(v) => Ok(v),
     }
 }
 
-fn parse_entry<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Entry {
-    let mut entry = Entry::new();
-    for attr in attrs {
-        match &attr.name.local_name[..] {
-            "name" => entry.name = attr.value,
-            "value" => {
-                entry.value = if attr.value.starts_with("0x") {
-                    u32::from_str_radix(&attr.value[2..], 16).unwrap()
-                } else {
-                    attr.value.parse().unwrap()
-                };
-            }
-            "since" => entry.since = attr.value.parse().unwrap(),
-            "summary" => {
-                entry.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
-            }
-            _ => {}
+fn parse_since_u16(position: TextPosition, interface: &Interface, txt: &str) -> Result<u16> {
+    let since = parse_version(position, "since", txt)?;
+    if since > interface.version {
+        return error(
+            position,
+            format!(
+                "`since` attribute {} is greater than the version {} of interface `{}`",
+                since, interface.version, interface.name
+            ),
+        );
+    }
+    // the interface version is itself a u32, but no protocol ever gets close to u16::MAX
+    Ok(since as u16)
+}
+
+fn check_message(
+    position: TextPosition,
+    message: &Message,
+    interface: &Interface,
+    previous: &[Message],
+    names: &mut HashSet<String>,
+    kind: &str,
+) -> Result<()> {
+    if !names.insert(message.name.clone()) {
+        return error(
+            position,
+            format!("Duplicate {} `{}` in interface `{}`", kind, message.name, interface.name),
+        );
+    }
+    if message.since > interface.version {
+        return error(
+            position,
+            format!(
+                "{} `{}` has `since` {}, greater than the version {} of interface `{}`",
+                kind, message.name, message.since, interface.version, interface.name
+            ),
+        );
+    }
+    if let Some(deprecated_since) = message.deprecated_since {
+        if deprecated_since < message.since || deprecated_since > interface.version {
+            return error(
+                position,
+                format!(
+                    "{} `{}` has `deprecated-since` {}, outside of the versions {} to {} of interface `{}`",
+                    kind,
+                    message.name,
+                    deprecated_since,
+                    message.since,
+                    interface.version,
+                    interface.name
+                ),
+            );
         }
     }
+    // opcodes are assigned in order of appearance, so new messages must be appended
+    if let Some(last) = previous.last() {
+        if message.since < last.since {
+            return error(
+                position,
+                format!(
+                    "{} `{}` has `since` {}, lower than the `since` {} of the preceding {} `{}`",
+                    kind, message.name, message.since, last.since, kind, last.name
+                ),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check that every `enum="..."` attribute refers to an existing enum
+///
+/// References of the form `interface.enum` to an interface defined in another protocol
+/// cannot be resolved here and are left to the compiler.
+fn check_enum_refs(protocol: &Protocol, refs: &[EnumRef]) -> Result<()> {
+    for enum_ref in refs {
+        let (iface_name, enum_name) = match enum_ref.enum_.split_once('.') {
+            Some((iface, name)) => (iface, name),
+            None => (&enum_ref.interface[..], &enum_ref.enum_[..]),
+        };
+        let iface = match protocol.interfaces.iter().find(|i| i.name == iface_name) {
+            Some(iface) => iface,
+            None => continue,
+        };
+        if !iface.enums.iter().any(|e| e.name == enum_name) {
+            return error(
+                enum_ref.position,
+                format!("Unknown enum `{}` in interface `{}`", enum_name, iface_name),
+            );
+        }
+    }
+    Ok(())
+}
 
-    loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => entry.description = Some(parse_description(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
-            },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "entry" => break,
-            _ => {}
+#[cfg(test)]
+mod tests {
+    fn parse_err(body: &str) -> super::ParseError {
+        let xml =
+            format!("<?xml version=\"1.0\"?>\n<protocol name=\"test\">\n{}\n</protocol>", body);
+        match super::parse(xml.as_bytes()) {
+            Ok(_) => panic!("Protocol unexpectedly parsed successfully"),
+            Err(e) => e,
         }
     }
 
-    entry
+    #[test]
+    fn valid_protocol() {
+        let protocol_file =
+            std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
+        super::parse(protocol_file).unwrap();
+    }
+
+    #[test]
+    fn unknown_arg_type() {
+        let err = parse_err(
+            r#"<interface name="foo" version="1">
+  <request name="bar">
+    <arg name="baz" type="float"/>
+  </request>
+</interface>"#,
+        );
+        assert_eq!(err.to_string(), "5:5: Unknown type `float`");
+    }
+
+    #[test]
+    fn bad_since() {
+        let err = parse_err(
+            r#"<interface name="foo" version="2">
+  <request name="bar" since="two"/>
+</interface>"#,
+        );
+        assert_eq!(err.position.row, 3);
+        assert!(err.message.contains("Invalid `since` attribute `two`"));
+
+        let err = parse_err(
+            r#"<interface name="foo" version="2">
+  <event name="bar" since="3"/>
+</interface>"#,
+        );
+        assert_eq!(err.position.row, 3);
+        assert!(err.message.contains("greater than the version 2"));
+
+        let err = parse_err(
+            r#"<interface name="foo" version="2">
+  <event name="bar" since="2"/>
+  <event name="baz"/>
+</interface>"#,
+        );
+        assert_eq!(err.position.row, 4);
+        assert!(err.message.contains("lower than the `since` 2"));
+    }
+
+    #[test]
+    fn bad_deprecated_since() {
+        let err = parse_err(
+            r#"<interface name="foo" version="3">
+  <request name="bar" since="2" deprecated-since="1"/>
+</interface>"#,
+        );
+        assert_eq!(err.position.row, 3);
+        assert!(err.message.contains("outside of the versions 2 to 3"));
+    }
+
+    #[test]
+    fn duplicate_messages() {
+        let err = parse_err(
+            r#"<interface name="foo" version="1">
+  <request name="bar"/>
+  <event name="bar"/>
+  <request name="bar"/>
+</interface>"#,
+        );
+        assert_eq!(err.to_string(), "6:3: Duplicate request `bar` in interface `foo`");
+
+        let err = parse_err(
+            r#"<interface name="foo" version="1"/>
+<interface name="foo" version="1"/>"#,
+        );
+        assert_eq!(err.to_string(), "4:1: Duplicate interface `foo`");
+    }
+
+    #[test]
+    fn missing_enum() {
+        let err = parse_err(
+            r#"<interface name="foo" version="1">
+  <request name="bar">
+    <arg name="baz" type="uint" enum="qux"/>
+  </request>
+</interface>"#,
+        );
+        assert_eq!(err.to_string(), "5:5: Unknown enum `qux` in interface `foo`");
+
+        let err = parse_err(
+            r#"<interface name="foo" version="1">
+  <request name="bar">
+    <arg name="baz" type="uint" enum="foo2.qux"/>
+  </request>
+</interface>
+<interface name="foo2" version="1"/>"#,
+        );
+        assert_eq!(err.to_string(), "5:5: Unknown enum `qux` in interface `foo2`");
+
+        // enums of interfaces from other protocols cannot be checked
+        let xml = r#"<protocol name="test">
+<interface name="foo" version="1">
+  <request name="bar">
+    <arg name="baz" type="uint" enum="wl_output.transform"/>
+  </request>
+</interface>
+</protocol>"#;
+        super::parse(xml.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn ill_formed_xml() {
+        let err = parse_err(r#"<interface name="foo" version="1">"#);
+        assert_eq!(err.position.row, 3);
+    }
 }
@@ -0,0 +1,98 @@
+use proc_macro2::TokenStream;
+
+use quote::quote;
+
+use crate::protocol::Protocol;
+
+/// Generate a shared `__interfaces` module and the `client` and `server` modules of a protocol
+///
+/// The two sides are gated behind the `client` and `server` cargo features of the invoking crate,
+/// and both use the same interface definitions. The `imports` are the modules generated the same
+/// way for the protocols this one depends on, the `type_attributes` are added to the types
+/// generated for the protocol, and the modules are gated by the `feature_gates`.
+pub fn generate_protocol(
+    protocol: &Protocol,
+    imports: &[syn::Path],
+    type_attributes: &crate::input::TypeAttributes,
+    feature_gates: &crate::input::FeatureGates,
+) -> TokenStream {
+    let interfaces = crate::interfaces::generate(protocol, true);
+    // the modules of the interfaces are inside the ones gated by the protocol feature
+    let interface_gates = feature_gates.interfaces_only();
+    let client_code = crate::client_gen::generate_client_objects(
+        protocol,
+        false,
+        type_attributes,
+        &interface_gates,
+    );
+    let server_code =
+        crate::server_gen::generate_server_objects(protocol, type_attributes, &interface_gates);
+    let protocol_cfg = feature_gates.protocol_cfg();
+
+    // The core interfaces are defined by both wayland-client and wayland-server, pick only one
+    // of them to avoid ambiguous glob imports when both features are enabled.
+    let interface_imports = imports.iter().map(|import| {
+        quote! {
+            #[cfg(feature = "client")]
+            use #import::client::__interfaces::*;
+            #[cfg(not(feature = "client"))]
+            use #import::server::__interfaces::*;
+        }
+    });
+
+    quote! {
+        #[cfg(any(feature = "client", feature = "server"))]
+        #protocol_cfg
+        pub mod __interfaces {
+            #[cfg(feature = "client")]
+            use wayland_client::protocol::__interfaces::*;
+            #[cfg(not(feature = "client"))]
+            use wayland_server::protocol::__interfaces::*;
+            #(#interface_imports)*
+
+            #interfaces
+        }
+
+        #[cfg(feature = "client")]
+        #protocol_cfg
+        pub mod client {
+            //! Client-side API of this protocol
+            use wayland_client;
+            use wayland_client::protocol::*;
+            #(use #imports::client::*;)*
+
+            pub use super::__interfaces;
+            use self::__interfaces::*;
+
+            #client_code
+        }
+
+        #[cfg(feature = "server")]
+        #protocol_cfg
+        pub mod server {
+            //! Server-side API of this protocol
+            use wayland_server;
+            use wayland_server::protocol::*;
+            #(use #imports::server::*;)*
+
+            pub use super::__interfaces;
+            use self::__interfaces::*;
+
+            #server_code
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn protocol_gen() {
+        let input = syn::parse_str::<crate::input::ProtocolInput>(
+            r#""./tests/scanner_assets/test-protocol.xml", [crate::other_protocol],
+            feature = "test_protocol", interface_features = [quad = "quad"]"#,
+        )
+        .unwrap();
+        let generated = input.generate().unwrap();
+        crate::golden::assert_golden(&generated, "./tests/scanner_assets/test-protocol-code.rs");
+    }
+}
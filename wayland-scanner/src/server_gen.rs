@@ -3,48 +3,93 @@ use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 
 use crate::{
+    common::Side,
     protocol::{Interface, Protocol, Type},
     util::{dotted_to_relname, is_keyword, snake_to_camel},
-    Side,
 };
 
-pub fn generate_server_objects(protocol: &Protocol) -> TokenStream {
+pub fn generate_server_objects(
+    protocol: &Protocol,
+    type_attributes: &crate::input::TypeAttributes,
+    feature_gates: &crate::input::FeatureGates,
+) -> TokenStream {
     protocol
         .interfaces
         .iter()
-        .filter(|iface| iface.name != "wl_display" && iface.name != "wl_registry")
-        .map(generate_objects_for)
+        .filter(|iface| !BACKEND_INTERFACES.contains(&&iface.name[..]))
+        .map(|interface| {
+            generate_objects_for(&untype_backend_objects(interface), type_attributes, feature_gates)
+        })
         .collect()
 }
 
-fn generate_objects_for(interface: &Interface) -> TokenStream {
+// The interfaces handled by the backend, which have no server-side objects
+const BACKEND_INTERFACES: &[&str] = &["wl_display", "wl_registry"];
+
+// The object arguments of these interfaces, like the registry of `wl_fixes.destroy_registry`,
+// are given as plain object ids
+fn untype_backend_objects(interface: &Interface) -> Interface {
+    let mut interface = interface.clone();
+    for msg in interface.requests.iter_mut().chain(interface.events.iter_mut()) {
+        for arg in &mut msg.args {
+            if arg.typ == Type::Object
+                && matches!(arg.interface.as_deref(), Some(name) if BACKEND_INTERFACES.contains(&name))
+            {
+                arg.interface = None;
+            }
+        }
+    }
+    interface
+}
+
+fn generate_objects_for(
+    interface: &Interface,
+    type_attributes: &crate::input::TypeAttributes,
+    feature_gates: &crate::input::FeatureGates,
+) -> TokenStream {
     let mod_name = Ident::new(&interface.name, Span::call_site());
+    let mod_cfg = feature_gates.interface_cfg(&interface.name);
     let mod_doc = interface.description.as_ref().map(crate::util::description_to_doc_attr);
+    let iface_doc = crate::util::to_doc_attr(&format!(
+        "{}\n\nSee also the [Request] enum for this interface.",
+        interface
+            .description
+            .as_ref()
+            .map(|(short, long)| format!("{}\n\n{}", short, long))
+            .unwrap_or_default(),
+    ));
     let iface_name = Ident::new(&snake_to_camel(&interface.name), Span::call_site());
     let iface_const_name = format_ident!("{}_INTERFACE", interface.name.to_ascii_uppercase());
 
-    let enums = crate::common::generate_enums_for(interface);
+    let enums = crate::common::generate_enums_for(interface, type_attributes);
     let sinces = crate::common::gen_since_constants(&interface.requests, &interface.events);
 
     let requests = crate::common::gen_message_enum(
         &format_ident!("Request"),
         Side::Server,
         true,
+        false,
         &interface.requests,
+        type_attributes,
     );
     let events = crate::common::gen_message_enum(
         &format_ident!("Event"),
         Side::Server,
         false,
+        false,
         &interface.events,
+        type_attributes,
     );
 
-    let parse_body = crate::common::gen_parse_body(interface, Side::Server);
+    let parse_body = crate::common::gen_parse_body(interface, Side::Server, false);
     let write_body = crate::common::gen_write_body(interface, Side::Server);
     let methods = gen_methods(interface);
+    // the comparisons of the objects are implemented by hand below
+    let iface_attrs = type_attributes.to_attrs(&["Debug", "Clone", "PartialEq", "Eq"]);
 
     quote! {
         #mod_doc
+        #mod_cfg
         pub mod #mod_name {
             use std::sync::Arc;
 
@@ -58,7 +103,9 @@ fn generate_objects_for(interface: &Interface) -> TokenStream {
             #requests
             #events
 
+            #iface_doc
             #[derive(Debug, Clone)]
+            #iface_attrs
             pub struct #iface_name {
                 id: ObjectId,
                 version: u32,
@@ -73,6 +120,7 @@ fn generate_objects_for(interface: &Interface) -> TokenStream {
 
             impl std::cmp::Eq for #iface_name {}
 
+            #[allow(deprecated)]
             impl super::wayland_server::Resource for #iface_name {
                 type Request = Request;
                 type Event = Event;
@@ -120,6 +168,7 @@ fn generate_objects_for(interface: &Interface) -> TokenStream {
                 }
             }
 
+            #[allow(deprecated)]
             impl #iface_name {
                 #methods
             }
@@ -138,6 +187,8 @@ fn gen_methods(interface: &Interface) -> TokenStream {
                 request.name
             );
             let enum_variant = Ident::new(&snake_to_camel(&request.name), Span::call_site());
+            let doc_attr = crate::common::gen_method_doc_attr(request, |_| false);
+            let deprecated_attr = crate::common::deprecated_attr(request);
 
             let fn_args = request.args.iter().flat_map(|arg| {
                 let arg_name =
@@ -202,6 +253,8 @@ fn gen_methods(interface: &Interface) -> TokenStream {
             });
 
             quote! {
+                #doc_attr
+                #deprecated_attr
                 #[allow(clippy::too_many_arguments)]
                 pub fn #method_name(&self, conn: &mut DisplayHandle, #(#fn_args),*) {
                     let _ = conn.send_event(
@@ -222,18 +275,35 @@ mod tests {
     fn server_gen() {
         let protocol_file =
             std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
-        let protocol_parsed = crate::parse::parse(protocol_file);
-        let generated: String = super::generate_server_objects(&protocol_parsed).to_string();
-        let generated = crate::format_rust_code(&generated);
-
-        let reference =
-            std::fs::read_to_string("./tests/scanner_assets/test-server-code.rs").unwrap();
-        let reference = crate::format_rust_code(&reference);
-
-        if reference != generated {
-            let diff = similar::TextDiff::from_lines(&reference, &generated);
-            print!("{}", diff.unified_diff().context_radius(10).header("reference", "generated"));
-            panic!("Generated does not match reference!")
-        }
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let generated = super::generate_server_objects(
+            &protocol_parsed,
+            &Default::default(),
+            &Default::default(),
+        );
+        crate::golden::assert_golden(&generated, "./tests/scanner_assets/test-server-code.rs");
+    }
+
+    #[test]
+    fn server_gen_type_attributes() {
+        let protocol_file =
+            std::fs::File::open("./tests/scanner_assets/test-protocol.xml").unwrap();
+        let protocol_parsed = crate::parse::parse(protocol_file).unwrap();
+        let type_attributes = crate::input::TypeAttributes {
+            derives: vec![syn::parse_quote!(Eq), syn::parse_quote!(Hash)],
+            attributes: vec![syn::parse_quote!(allow(missing_docs))],
+        };
+        let generated: String =
+            super::generate_server_objects(&protocol_parsed, &type_attributes, &Default::default())
+                .to_string();
+        let generated = generated.replace(char::is_whitespace, "");
+
+        // the resources already implement Eq
+        assert!(generated.contains(
+            "#[derive(Debug,Clone)]#[derive(Hash)]#[allow(missing_docs)]pubstructTestGlobal{"
+        ));
+        assert!(generated.contains(
+            "#[derive(Debug)]#[derive(Eq,Hash)]#[allow(missing_docs)]#[non_exhaustive]pubenumRequest{"
+        ));
     }
 }
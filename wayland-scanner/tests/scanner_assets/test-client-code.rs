@@ -41,12 +41,20 @@ pub mod wl_display {
     }
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_SYNC_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_SYNC_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_GET_REGISTRY_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_GET_REGISTRY_OPCODE: u16 = 1u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_ERROR_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_ERROR_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_DELETE_ID_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_DELETE_ID_OPCODE: u16 = 1u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
@@ -55,6 +63,16 @@ pub mod wl_display {
         #[doc = "get global registry object\n\nThis request creates a registry object that allows the client\nto list and bind the global objects available from the\ncompositor.\n\nIt should be noted that the server side resources consumed in\nresponse to a get_registry request can only be released when the\nclient disconnects, not when the client side proxy is destroyed.\nTherefore, clients should invoke get_registry as infrequently as\npossible to avoid wasting memory."]
         GetRegistry {},
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Sync { .. } => 1u32,
+                Request::GetRegistry { .. } => 1u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {
@@ -73,6 +91,17 @@ pub mod wl_display {
             id: u32,
         },
     }
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Event::Error { .. } => 1u32,
+                Event::DeleteId { .. } => 1u32,
+            }
+        }
+    }
+    #[doc = "core global object\n\nThe core global object.  This is a special singleton object.  It\nis used for internal Wayland protocol features.\n\nSee also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct WlDisplay {
         id: ObjectId,
@@ -85,6 +114,7 @@ pub mod wl_display {
         }
     }
     impl std::cmp::Eq for WlDisplay {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for WlDisplay {
         type Request = Request;
         type Event = Event;
@@ -157,58 +187,49 @@ pub mod wl_display {
                 Request::Sync {} => Ok(Message {
                     sender_id: self.id.clone(),
                     opcode: 0u16,
-                    args: smallvec::smallvec![{
-                        let my_info = conn.object_info(self.id())?;
-                        let placeholder = conn.placeholder_id(Some((
-                            super::wl_callback::WlCallback::interface(),
-                            my_info.version,
-                        )));
-                        Argument::NewId(placeholder)
-                    }],
+                    args: smallvec::smallvec![Argument::NewId(conn.placeholder_id(Some(super::wl_callback::WlCallback::interface())))],
                 }),
                 Request::GetRegistry {} => Ok(Message {
                     sender_id: self.id.clone(),
                     opcode: 1u16,
-                    args: smallvec::smallvec![{
-                        let my_info = conn.object_info(self.id())?;
-                        let placeholder = conn.placeholder_id(Some((
-                            super::wl_registry::WlRegistry::interface(),
-                            my_info.version,
-                        )));
-                        Argument::NewId(placeholder)
-                    }],
+                    args: smallvec::smallvec![Argument::NewId(conn.placeholder_id(Some(super::wl_registry::WlRegistry::interface())))],
                 }),
             }
         }
     }
+    #[allow(deprecated)]
     impl WlDisplay {
+        #[doc = "asynchronous roundtrip\n\nThe sync request asks the server to emit the 'done' event\non the returned wl_callback object.  Since requests are\nhandled in-order and events are delivered in-order, this can\nbe used as a barrier to ensure all previous requests and the\nresulting events have been handled.\n\nThe object returned by this request will be destroyed by the\ncompositor after the callback is fired and as such the client must not\nattempt to use it after that point.\n\nThe callback_data passed in the callback is the event serial."]
         #[allow(clippy::too_many_arguments)]
-        pub fn sync<D: Dispatch<super::wl_callback::WlCallback> + 'static>(
+        #[track_caller]
+        pub fn sync<D: Dispatch<super::wl_callback::WlCallback>>(
             &self,
             conn: &mut ConnectionHandle,
             qh: &QueueHandle<D>,
             udata: <D as Dispatch<super::wl_callback::WlCallback>>::UserData,
-        ) -> Result<super::wl_callback::WlCallback, InvalidId> {
+        ) -> Result<super::wl_callback::WlCallback, DispatchError> {
             let ret = conn.send_request(
                 self,
                 Request::Sync {},
                 Some(qh.make_data::<super::wl_callback::WlCallback>(udata)),
             )?;
-            Proxy::from_id(conn, ret)
+            Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
         }
+        #[doc = "get global registry object\n\nThis request creates a registry object that allows the client\nto list and bind the global objects available from the\ncompositor.\n\nIt should be noted that the server side resources consumed in\nresponse to a get_registry request can only be released when the\nclient disconnects, not when the client side proxy is destroyed.\nTherefore, clients should invoke get_registry as infrequently as\npossible to avoid wasting memory."]
         #[allow(clippy::too_many_arguments)]
-        pub fn get_registry<D: Dispatch<super::wl_registry::WlRegistry> + 'static>(
+        #[track_caller]
+        pub fn get_registry<D: Dispatch<super::wl_registry::WlRegistry>>(
             &self,
             conn: &mut ConnectionHandle,
             qh: &QueueHandle<D>,
             udata: <D as Dispatch<super::wl_registry::WlRegistry>>::UserData,
-        ) -> Result<super::wl_registry::WlRegistry, InvalidId> {
+        ) -> Result<super::wl_registry::WlRegistry, DispatchError> {
             let ret = conn.send_request(
                 self,
                 Request::GetRegistry {},
                 Some(qh.make_data::<super::wl_registry::WlRegistry>(udata)),
             )?;
-            Proxy::from_id(conn, ret)
+            Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
         }
     }
 }
@@ -224,10 +245,16 @@ pub mod wl_registry {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_BIND_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_BIND_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_GLOBAL_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_GLOBAL_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_GLOBAL_REMOVE_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_GLOBAL_REMOVE_OPCODE: u16 = 1u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
@@ -239,6 +266,15 @@ pub mod wl_registry {
             id: (&'static Interface, u32)
         },
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Bind { .. } => 1u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {
@@ -257,6 +293,17 @@ pub mod wl_registry {
             name: u32,
         },
     }
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Event::Global { .. } => 1u32,
+                Event::GlobalRemove { .. } => 1u32,
+            }
+        }
+    }
+    #[doc = "global registry object\n\nThe singleton global registry object.  The server has a number of\nglobal objects that are available to all clients.  These objects\ntypically represent an actual object in the server (for example,\nan input device) or they are singleton objects that provide\nextension functionality.\n\nWhen a client creates a registry object, the registry object\nwill emit a global event for each global currently in the\nregistry.  Globals come and go as a result of device or\nmonitor hotplugs, reconfiguration or other events, and the\nregistry will send out global and global_remove events to\nkeep the client up to date with the changes.  To mark the end\nof the initial burst of events, the client can use the\nwl_display.sync request immediately after calling\nwl_display.get_registry.\n\nA client can bind to a global object by using the bind\nrequest.  This creates a client-side handle that lets the object\nemit events to the client and lets the client invoke requests on\nthe object.\n\nSee also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct WlRegistry {
         id: ObjectId,
@@ -269,6 +316,7 @@ pub mod wl_registry {
         }
     }
     impl std::cmp::Eq for WlRegistry {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for WlRegistry {
         type Request = Request;
         type Event = Event;
@@ -346,29 +394,33 @@ pub mod wl_registry {
                         Argument::Uint(name),
                         Argument::Str(Box::new(std::ffi::CString::new(id.0.name).unwrap())),
                         Argument::Uint(id.1),
-                        Argument::NewId(conn.placeholder_id(Some((id.0, id.1))))
+                        Argument::NewId(conn.placeholder_id(Some(id.0)))
                     ],
                 }),
             }
         }
     }
+    #[allow(deprecated)]
     impl WlRegistry {
+        #[doc = "bind an object to the display\n\nBinds a new, client-created object to the server using the\nspecified name as the identifier.\n\n## Arguments\n\n- `name`: unique numeric name of the object"]
+        #[doc = "\n\nThe interface of the created object is given by the type parameter `I`, and the version is capped to the highest version of `I` known by these bindings."]
         #[allow(clippy::too_many_arguments)]
-        pub fn bind<I: Proxy + 'static, D: Dispatch<I> + 'static>(
+        #[track_caller]
+        pub fn bind<I: Proxy + 'static, D: Dispatch<I>>(
             &self,
             conn: &mut ConnectionHandle,
             name: u32,
             version: u32,
             qh: &QueueHandle<D>,
             udata: <D as Dispatch<I>>::UserData,
-        ) -> Result<I, InvalidId> {
-            let placeholder = conn.placeholder_id(Some((I::interface(), version)));
+        ) -> Result<I, DispatchError> {
+            let version = version.min(I::interface().version);
             let ret = conn.send_request(
                 self,
                 Request::Bind { name, id: (I::interface(), version) },
                 Some(qh.make_data::<I>(udata)),
             )?;
-            Proxy::from_id(conn, ret)
+            Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
         }
     }
 }
@@ -384,9 +436,18 @@ pub mod wl_callback {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_DONE_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_DONE_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {}
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {
@@ -396,6 +457,16 @@ pub mod wl_callback {
             callback_data: u32,
         },
     }
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Event::Done { .. } => 1u32,
+            }
+        }
+    }
+    #[doc = "callback object\n\nClients can handle the 'done' event to get notified when\nthe related request is done.\n\nSee also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct WlCallback {
         id: ObjectId,
@@ -408,6 +479,7 @@ pub mod wl_callback {
         }
     }
     impl std::cmp::Eq for WlCallback {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for WlCallback {
         type Request = Request;
         type Event = Event;
@@ -463,6 +535,7 @@ pub mod wl_callback {
             match msg {}
         }
     }
+    #[allow(deprecated)]
     impl WlCallback {}
 }
 pub mod test_global {
@@ -476,24 +549,41 @@ pub mod test_global {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_MANY_ARGS_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_MANY_ARGS_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_GET_SECONDARY_SINCE: u32 = 2u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_GET_SECONDARY_OPCODE: u16 = 1u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_GET_TERTIARY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_GET_TERTIARY_OPCODE: u16 = 2u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_LINK_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_LINK_OPCODE: u16 = 3u16;
     #[doc = r" The minimal object version supporting this request"]
-    pub const REQ_DESTROY_SINCE: u32 = 4u32;
+    pub const REQ_DESTROY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 4u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_MANY_ARGS_EVT_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_MANY_ARGS_EVT_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_ACK_SECONDARY_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_ACK_SECONDARY_OPCODE: u16 = 1u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_CYCLE_QUAD_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_CYCLE_QUAD_OPCODE: u16 = 2u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "a request with every possible non-object arg"]
+        #[deprecated(note = "Deprecated since version 2 of the interface")]
         ManyArgs {
             #[doc = "an unsigned int"]
             unsigned_int: u32,
@@ -514,9 +604,22 @@ pub mod test_global {
         GetTertiary {},
         #[doc = "link a secondary and a tertiary\n\n\n\nOnly available since version 3 of the interface"]
         Link { sec: super::secondary::Secondary, ter: Option<super::tertiary::Tertiary>, time: u32 },
-        #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 4 of the interface"]
+        #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::ManyArgs { .. } => 1u32,
+                Request::GetSecondary { .. } => 2u32,
+                Request::GetTertiary { .. } => 3u32,
+                Request::Link { .. } => 3u32,
+                Request::Destroy { .. } => 3u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {
@@ -540,6 +643,18 @@ pub mod test_global {
         #[doc = "create a new quad optionally replacing a previous one"]
         CycleQuad { new_quad: super::quad::Quad, old_quad: Option<super::quad::Quad> },
     }
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Event::ManyArgsEvt { .. } => 1u32,
+                Event::AckSecondary { .. } => 1u32,
+                Event::CycleQuad { .. } => 1u32,
+            }
+        }
+    }
+    #[doc = "See also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct TestGlobal {
         id: ObjectId,
@@ -552,6 +667,7 @@ pub mod test_global {
         }
     }
     impl std::cmp::Eq for TestGlobal {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for TestGlobal {
         type Request = Request;
         type Event = Event;
@@ -704,26 +820,12 @@ pub mod test_global {
                 Request::GetSecondary {} => Ok(Message {
                     sender_id: self.id.clone(),
                     opcode: 1u16,
-                    args: smallvec::smallvec![{
-                        let my_info = conn.object_info(self.id())?;
-                        let placeholder = conn.placeholder_id(Some((
-                            super::secondary::Secondary::interface(),
-                            my_info.version,
-                        )));
-                        Argument::NewId(placeholder)
-                    }],
+                    args: smallvec::smallvec![Argument::NewId(conn.placeholder_id(Some(super::secondary::Secondary::interface())))],
                 }),
                 Request::GetTertiary {} => Ok(Message {
                     sender_id: self.id.clone(),
                     opcode: 2u16,
-                    args: smallvec::smallvec![{
-                        let my_info = conn.object_info(self.id())?;
-                        let placeholder = conn.placeholder_id(Some((
-                            super::tertiary::Tertiary::interface(),
-                            my_info.version,
-                        )));
-                        Argument::NewId(placeholder)
-                    }],
+                    args: smallvec::smallvec![Argument::NewId(conn.placeholder_id(Some(super::tertiary::Tertiary::interface())))],
                 }),
                 Request::Link { sec, ter, time } => Ok(Message {
                     sender_id: self.id.clone(),
@@ -746,7 +848,10 @@ pub mod test_global {
             }
         }
     }
+    #[allow(deprecated)]
     impl TestGlobal {
+        #[doc = "a request with every possible non-object arg\n\n## Arguments\n\n- `unsigned_int`: an unsigned int\n- `signed_int`: a singed int\n- `fixed_point`: a fixed point number\n- `number_array`: an array\n- `some_text`: some text\n- `file_descriptor`: a file descriptor"]
+        #[deprecated(note = "Deprecated since version 2 of the interface")]
         #[allow(clippy::too_many_arguments)]
         pub fn many_args(
             &self,
@@ -771,34 +876,39 @@ pub mod test_global {
                 None,
             );
         }
+        #[doc = "Only available since version 2 of the interface"]
         #[allow(clippy::too_many_arguments)]
-        pub fn get_secondary<D: Dispatch<super::secondary::Secondary> + 'static>(
+        #[track_caller]
+        pub fn get_secondary<D: Dispatch<super::secondary::Secondary>>(
             &self,
             conn: &mut ConnectionHandle,
             qh: &QueueHandle<D>,
             udata: <D as Dispatch<super::secondary::Secondary>>::UserData,
-        ) -> Result<super::secondary::Secondary, InvalidId> {
+        ) -> Result<super::secondary::Secondary, DispatchError> {
             let ret = conn.send_request(
                 self,
                 Request::GetSecondary {},
                 Some(qh.make_data::<super::secondary::Secondary>(udata)),
             )?;
-            Proxy::from_id(conn, ret)
+            Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
         }
+        #[doc = "Only available since version 3 of the interface"]
         #[allow(clippy::too_many_arguments)]
-        pub fn get_tertiary<D: Dispatch<super::tertiary::Tertiary> + 'static>(
+        #[track_caller]
+        pub fn get_tertiary<D: Dispatch<super::tertiary::Tertiary>>(
             &self,
             conn: &mut ConnectionHandle,
             qh: &QueueHandle<D>,
             udata: <D as Dispatch<super::tertiary::Tertiary>>::UserData,
-        ) -> Result<super::tertiary::Tertiary, InvalidId> {
+        ) -> Result<super::tertiary::Tertiary, DispatchError> {
             let ret = conn.send_request(
                 self,
                 Request::GetTertiary {},
                 Some(qh.make_data::<super::tertiary::Tertiary>(udata)),
             )?;
-            Proxy::from_id(conn, ret)
+            Ok(Proxy::from_id(conn, ret).expect("Newly created object is invalid"))
         }
+        #[doc = "link a secondary and a tertiary\n\n\n\nOnly available since version 3 of the interface"]
         #[allow(clippy::too_many_arguments)]
         pub fn link(
             &self,
@@ -809,6 +919,7 @@ pub mod test_global {
         ) {
             let _ = conn.send_request(self, Request::Link { sec: sec.clone(), ter: ter.cloned(), time }, None);
         }
+        #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         #[allow(clippy::too_many_arguments)]
         pub fn destroy(&self, conn: &mut ConnectionHandle) {
             let _ = conn.send_request(self, Request::Destroy {}, None);
@@ -826,15 +937,34 @@ pub mod secondary {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_DESTROY_SINCE: u32 = 2u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 2 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Destroy { .. } => 2u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {}
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
+    #[doc = "See also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct Secondary {
         id: ObjectId,
@@ -847,6 +977,7 @@ pub mod secondary {
         }
     }
     impl std::cmp::Eq for Secondary {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for Secondary {
         type Request = Request;
         type Event = Event;
@@ -901,7 +1032,9 @@ pub mod secondary {
             }
         }
     }
+    #[allow(deprecated)]
     impl Secondary {
+        #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 2 of the interface"]
         #[allow(clippy::too_many_arguments)]
         pub fn destroy(&self, conn: &mut ConnectionHandle) {
             let _ = conn.send_request(self, Request::Destroy {}, None);
@@ -919,15 +1052,34 @@ pub mod tertiary {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_DESTROY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Destroy { .. } => 3u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {}
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
+    #[doc = "See also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct Tertiary {
         id: ObjectId,
@@ -940,6 +1092,7 @@ pub mod tertiary {
         }
     }
     impl std::cmp::Eq for Tertiary {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for Tertiary {
         type Request = Request;
         type Event = Event;
@@ -994,7 +1147,9 @@ pub mod tertiary {
             }
         }
     }
+    #[allow(deprecated)]
     impl Tertiary {
+        #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         #[allow(clippy::too_many_arguments)]
         pub fn destroy(&self, conn: &mut ConnectionHandle) {
             let _ = conn.send_request(self, Request::Destroy {}, None);
@@ -1012,15 +1167,34 @@ pub mod quad {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_DESTROY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Destroy { .. } => 3u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {}
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
+    #[doc = "See also the [Event] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct Quad {
         id: ObjectId,
@@ -1033,6 +1207,7 @@ pub mod quad {
         }
     }
     impl std::cmp::Eq for Quad {}
+    #[allow(deprecated)]
     impl super::wayland_client::Proxy for Quad {
         type Request = Request;
         type Event = Event;
@@ -1087,7 +1262,9 @@ pub mod quad {
             }
         }
     }
+    #[allow(deprecated)]
     impl Quad {
+        #[doc = "This is a destructor, once sent this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         #[allow(clippy::too_many_arguments)]
         pub fn destroy(&self, conn: &mut ConnectionHandle) {
             let _ = conn.send_request(self, Request::Destroy {}, None);
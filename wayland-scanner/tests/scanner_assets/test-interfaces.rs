@@ -14,18 +14,22 @@ pub static WL_DISPLAY_INTERFACE: wayland_backend::protocol::Interface = wayland_
         wayland_backend::protocol::MessageDesc {
             name: "sync",
             signature: &[wayland_backend::protocol::ArgumentType::NewId(wayland_backend::protocol::AllowNull::No)],
+            arg_names: &["callback"],
             since: 1u32,
             is_destructor: false,
             child_interface: Some(&WL_CALLBACK_INTERFACE),
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "get_registry",
             signature: &[wayland_backend::protocol::ArgumentType::NewId(wayland_backend::protocol::AllowNull::No)],
+            arg_names: &["registry"],
             since: 1u32,
             is_destructor: false,
             child_interface: Some(&WL_REGISTRY_INTERFACE),
             arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
     events: &[
@@ -36,18 +40,22 @@ pub static WL_DISPLAY_INTERFACE: wayland_backend::protocol::Interface = wayland_
                 wayland_backend::protocol::ArgumentType::Uint,
                 wayland_backend::protocol::ArgumentType::Str(wayland_backend::protocol::AllowNull::No),
             ],
+            arg_names: &["object_id", "code", "message"],
             since: 1u32,
             is_destructor: false,
             child_interface: None,
             arg_interfaces: &[&wayland_backend::protocol::ANONYMOUS_INTERFACE],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "delete_id",
             signature: &[wayland_backend::protocol::ArgumentType::Uint],
+            arg_names: &["id"],
             since: 1u32,
             is_destructor: false,
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
     c_ptr: Some(unsafe { &wl_display_interface }),
@@ -101,10 +109,12 @@ pub static WL_REGISTRY_INTERFACE: wayland_backend::protocol::Interface = wayland
             wayland_backend::protocol::ArgumentType::Uint,
             wayland_backend::protocol::ArgumentType::NewId(wayland_backend::protocol::AllowNull::No),
         ],
+        arg_names: &["name", "interface", "version", "id"],
         since: 1u32,
         is_destructor: false,
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     }],
     events: &[
         wayland_backend::protocol::MessageDesc {
@@ -114,18 +124,22 @@ pub static WL_REGISTRY_INTERFACE: wayland_backend::protocol::Interface = wayland
                 wayland_backend::protocol::ArgumentType::Str(wayland_backend::protocol::AllowNull::No),
                 wayland_backend::protocol::ArgumentType::Uint,
             ],
+            arg_names: &["name", "interface", "version"],
             since: 1u32,
             is_destructor: false,
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "global_remove",
             signature: &[wayland_backend::protocol::ArgumentType::Uint],
+            arg_names: &["name"],
             since: 1u32,
             is_destructor: false,
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
     c_ptr: Some(unsafe { &wl_registry_interface }),
@@ -164,10 +178,12 @@ pub static WL_CALLBACK_INTERFACE: wayland_backend::protocol::Interface = wayland
     events: &[wayland_backend::protocol::MessageDesc {
         name: "done",
         signature: &[wayland_backend::protocol::ArgumentType::Uint],
+        arg_names: &["callback_data"],
         since: 1u32,
         is_destructor: true,
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     }],
     c_ptr: Some(unsafe { &wl_callback_interface }),
 };
@@ -200,26 +216,32 @@ pub static TEST_GLOBAL_INTERFACE: wayland_backend::protocol::Interface = wayland
                 wayland_backend::protocol::ArgumentType::Str(wayland_backend::protocol::AllowNull::No),
                 wayland_backend::protocol::ArgumentType::Fd,
             ],
+            arg_names: &["unsigned_int", "signed_int", "fixed_point", "number_array", "some_text", "file_descriptor"],
             since: 1u32,
             is_destructor: false,
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "get_secondary",
             signature: &[wayland_backend::protocol::ArgumentType::NewId(wayland_backend::protocol::AllowNull::No)],
+            arg_names: &["sec"],
             since: 2u32,
             is_destructor: false,
             child_interface: Some(&SECONDARY_INTERFACE),
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "get_tertiary",
             signature: &[wayland_backend::protocol::ArgumentType::NewId(wayland_backend::protocol::AllowNull::No)],
+            arg_names: &["ter"],
             since: 3u32,
             is_destructor: false,
             child_interface: Some(&TERTIARY_INTERFACE),
             arg_interfaces: &[],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "link",
@@ -228,18 +250,22 @@ pub static TEST_GLOBAL_INTERFACE: wayland_backend::protocol::Interface = wayland
                 wayland_backend::protocol::ArgumentType::Object(wayland_backend::protocol::AllowNull::Yes),
                 wayland_backend::protocol::ArgumentType::Uint,
             ],
+            arg_names: &["sec", "ter", "time"],
             since: 3u32,
             is_destructor: false,
             child_interface: None,
             arg_interfaces: &[&SECONDARY_INTERFACE, &TERTIARY_INTERFACE],
+            arg_enums: &[],
         },
         wayland_backend::protocol::MessageDesc {
             name: "destroy",
             signature: &[],
-            since: 4u32,
+            arg_names: &[],
+            since: 3u32,
             is_destructor: true,
             child_interface: None,
-            arg_interfaces: &[]
+            arg_interfaces: &[],
+            arg_enums: &[],
         },
     ],
     events: &[
@@ -253,18 +279,22 @@ pub static TEST_GLOBAL_INTERFACE: wayland_backend::protocol::Interface = wayland
             wayland_backend::protocol::ArgumentType::Str(wayland_backend::protocol::AllowNull::No),
             wayland_backend::protocol::ArgumentType::Fd,
         ],
+        arg_names: &["unsigned_int", "signed_int", "fixed_point", "number_array", "some_text", "file_descriptor"],
         since: 1u32,
         is_destructor: false,
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     },
     wayland_backend::protocol::MessageDesc {
         name: "ack_secondary",
         signature: &[wayland_backend::protocol::ArgumentType::Object(wayland_backend::protocol::AllowNull::No)],
+        arg_names: &["sec"],
         since: 1u32,
         is_destructor: false,
         child_interface: None,
         arg_interfaces: &[&SECONDARY_INTERFACE],
+        arg_enums: &[],
     },
     wayland_backend::protocol::MessageDesc {
         name: "cycle_quad",
@@ -272,10 +302,12 @@ pub static TEST_GLOBAL_INTERFACE: wayland_backend::protocol::Interface = wayland
             wayland_backend::protocol::ArgumentType::NewId(wayland_backend::protocol::AllowNull::No),
             wayland_backend::protocol::ArgumentType::Object(wayland_backend::protocol::AllowNull::Yes),
         ],
+        arg_names: &["new_quad", "old_quad"],
         since: 1u32,
         is_destructor: false,
         child_interface: Some(&QUAD_INTERFACE),
         arg_interfaces: &[&QUAD_INTERFACE],
+        arg_enums: &[],
     },
 ],
     c_ptr: Some(unsafe { &test_global_interface }),
@@ -314,7 +346,7 @@ pub static mut test_global_requests: [wayland_backend::protocol::wl_message; 5]
     },
     wayland_backend::protocol::wl_message {
         name: b"destroy\0" as *const u8 as *const std::os::raw::c_char,
-        signature: b"4\0" as *const u8 as *const std::os::raw::c_char,
+        signature: b"3\0" as *const u8 as *const std::os::raw::c_char,
         types: unsafe { &types_null as *const _ },
     },
 ];
@@ -358,10 +390,12 @@ pub static SECONDARY_INTERFACE: wayland_backend::protocol::Interface = wayland_b
     requests: &[wayland_backend::protocol::MessageDesc {
         name: "destroy",
         signature: &[],
+        arg_names: &[],
         since: 2u32,
         is_destructor: true,
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     }],
     events: &[],
     c_ptr: Some(unsafe { &secondary_interface }),
@@ -387,10 +421,12 @@ pub static TERTIARY_INTERFACE: wayland_backend::protocol::Interface = wayland_ba
     requests: &[wayland_backend::protocol::MessageDesc {
         name: "destroy",
         signature: &[],
+        arg_names: &[],
         since: 3u32,
         is_destructor: true,
         child_interface: None,
         arg_interfaces: &[],
+        arg_enums: &[],
     }],
     events: &[],
     c_ptr: Some(unsafe { &tertiary_interface }),
@@ -416,10 +452,12 @@ pub static mut tertiary_interface: wayland_backend::protocol::wl_interface =
         requests: &[wayland_backend::protocol::MessageDesc {
             name: "destroy",
             signature: &[],
+            arg_names: &[],
             since: 3u32,
             is_destructor: true,
             child_interface: None,
             arg_interfaces: &[],
+            arg_enums: &[],
         }],
         events: &[],
         c_ptr: Some(unsafe { &quad_interface }),
@@ -438,4 +476,13 @@ pub static mut tertiary_interface: wayland_backend::protocol::wl_interface =
             requests: unsafe { &quad_requests as *const _ },
             event_count: 0,
             events: NULLPTR as *const wayland_backend::protocol::wl_message,
-        };
\ No newline at end of file
+        };
+pub static INTERFACES: &[&wayland_backend::protocol::Interface] = &[
+    &WL_DISPLAY_INTERFACE,
+    &WL_REGISTRY_INTERFACE,
+    &WL_CALLBACK_INTERFACE,
+    &TEST_GLOBAL_INTERFACE,
+    &SECONDARY_INTERFACE,
+    &TERTIARY_INTERFACE,
+    &QUAD_INTERFACE,
+];
\ No newline at end of file
@@ -10,9 +10,18 @@ pub mod wl_callback {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_DONE_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_DONE_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {}
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {
@@ -22,6 +31,16 @@ pub mod wl_callback {
             callback_data: u32
         },
     }
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Event::Done { .. } => 1u32,
+            }
+        }
+    }
+    #[doc = "callback object\n\nClients can handle the 'done' event to get notified when\nthe related request is done.\n\nSee also the [Request] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct WlCallback {
         id: ObjectId,
@@ -34,6 +53,7 @@ pub mod wl_callback {
         }
     }
     impl std::cmp::Eq for WlCallback {}
+    #[allow(deprecated)]
     impl super::wayland_server::Resource for WlCallback {
         type Request = Request;
         type Event = Event;
@@ -94,7 +114,9 @@ pub mod wl_callback {
             self.data = Some(odata);
         }
     }
+    #[allow(deprecated)]
     impl WlCallback {
+        #[doc = "done event\n\nNotify the client when the related request is done.\n\nThis is a destructor, once sent this object cannot be used any longer.\n\n## Arguments\n\n- `callback_data`: request-specific data for the callback"]
         #[allow(clippy::too_many_arguments)]
         pub fn done(&self, conn: &mut DisplayHandle, callback_data: u32) {
             let _ = conn.send_event(self, Event::Done { callback_data });
@@ -112,24 +134,41 @@ pub mod test_global {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_MANY_ARGS_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_MANY_ARGS_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_GET_SECONDARY_SINCE: u32 = 2u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_GET_SECONDARY_OPCODE: u16 = 1u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_GET_TERTIARY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_GET_TERTIARY_OPCODE: u16 = 2u16;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_LINK_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_LINK_OPCODE: u16 = 3u16;
     #[doc = r" The minimal object version supporting this request"]
-    pub const REQ_DESTROY_SINCE: u32 = 4u32;
+    pub const REQ_DESTROY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 4u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_MANY_ARGS_EVT_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_MANY_ARGS_EVT_OPCODE: u16 = 0u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_ACK_SECONDARY_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_ACK_SECONDARY_OPCODE: u16 = 1u16;
     #[doc = r" The minimal object version supporting this event"]
     pub const EVT_CYCLE_QUAD_SINCE: u32 = 1u32;
+    #[doc = r" The wire opcode for this event"]
+    pub const EVT_CYCLE_QUAD_OPCODE: u16 = 2u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "a request with every possible non-object arg"]
+        #[deprecated(note = "Deprecated since version 2 of the interface")]
         ManyArgs {
             #[doc = "an unsigned int"]
             unsigned_int: u32,
@@ -156,9 +195,22 @@ pub mod test_global {
         },
         #[doc = "link a secondary and a tertiary\n\n\n\nOnly available since version 3 of the interface"]
         Link { sec: super::secondary::Secondary, ter: Option<super::tertiary::Tertiary>, time: u32 },
-        #[doc = "This is a destructor, once received this object cannot be used any longer.\nOnly available since version 4 of the interface"]
+        #[doc = "This is a destructor, once received this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::ManyArgs { .. } => 1u32,
+                Request::GetSecondary { .. } => 2u32,
+                Request::GetTertiary { .. } => 3u32,
+                Request::Link { .. } => 3u32,
+                Request::Destroy { .. } => 3u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {
@@ -182,6 +234,18 @@ pub mod test_global {
         #[doc = "create a new quad optionally replacing a previous one"]
         CycleQuad { new_quad: super::quad::Quad, old_quad: Option<super::quad::Quad> },
     }
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Event::ManyArgsEvt { .. } => 1u32,
+                Event::AckSecondary { .. } => 1u32,
+                Event::CycleQuad { .. } => 1u32,
+            }
+        }
+    }
+    #[doc = "See also the [Request] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct TestGlobal {
         id: ObjectId,
@@ -194,6 +258,7 @@ pub mod test_global {
         }
     }
     impl std::cmp::Eq for TestGlobal {}
+    #[allow(deprecated)]
     impl super::wayland_server::Resource for TestGlobal {
         type Request = Request;
         type Event = Event;
@@ -406,7 +471,9 @@ pub mod test_global {
             self.data = Some(odata);
         }
     }
+    #[allow(deprecated)]
     impl TestGlobal {
+        #[doc = "an event with every possible non-object arg\n\n## Arguments\n\n- `unsigned_int`: an unsigned int\n- `signed_int`: a singed int\n- `fixed_point`: a fixed point number\n- `number_array`: an array\n- `some_text`: some text\n- `file_descriptor`: a file descriptor"]
         #[allow(clippy::too_many_arguments)]
         pub fn many_args_evt(
             &self,
@@ -430,6 +497,7 @@ pub mod test_global {
                 },
             );
         }
+        #[doc = "acking the creation of a secondary"]
         #[allow(clippy::too_many_arguments)]
         pub fn ack_secondary(
             &self,
@@ -438,6 +506,7 @@ pub mod test_global {
         ) {
             let _ = conn.send_event(self, Event::AckSecondary { sec: sec.clone() });
         }
+        #[doc = "create a new quad optionally replacing a previous one"]
         #[allow(clippy::too_many_arguments)]
         pub fn cycle_quad(
             &self,
@@ -460,15 +529,34 @@ pub mod secondary {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_DESTROY_SINCE: u32 = 2u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "This is a destructor, once received this object cannot be used any longer.\nOnly available since version 2 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Destroy { .. } => 2u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {}
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
+    #[doc = "See also the [Request] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct Secondary {
         id: ObjectId,
@@ -481,6 +569,7 @@ pub mod secondary {
         }
     }
     impl std::cmp::Eq for Secondary {}
+    #[allow(deprecated)]
     impl super::wayland_server::Resource for Secondary {
         type Request = Request;
         type Event = Event;
@@ -542,6 +631,7 @@ pub mod secondary {
             self.data = Some(odata);
         }
     }
+    #[allow(deprecated)]
     impl Secondary {}
 }
 pub mod tertiary {
@@ -555,15 +645,34 @@ pub mod tertiary {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_DESTROY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "This is a destructor, once received this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Destroy { .. } => 3u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {}
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
+    #[doc = "See also the [Request] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct Tertiary {
         id: ObjectId,
@@ -576,6 +685,7 @@ pub mod tertiary {
         }
     }
     impl std::cmp::Eq for Tertiary {}
+    #[allow(deprecated)]
     impl super::wayland_server::Resource for Tertiary {
         type Request = Request;
         type Event = Event;
@@ -637,6 +747,7 @@ pub mod tertiary {
             self.data = Some(odata);
         }
     }
+    #[allow(deprecated)]
     impl Tertiary {}
 }
 pub mod quad {
@@ -650,15 +761,34 @@ pub mod quad {
     use std::sync::Arc;
     #[doc = r" The minimal object version supporting this request"]
     pub const REQ_DESTROY_SINCE: u32 = 3u32;
+    #[doc = r" The wire opcode for this request"]
+    pub const REQ_DESTROY_OPCODE: u16 = 0u16;
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Request {
         #[doc = "This is a destructor, once received this object cannot be used any longer.\nOnly available since version 3 of the interface"]
         Destroy,
     }
+    #[allow(deprecated)]
+    impl Request {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {
+                Request::Destroy { .. } => 3u32,
+            }
+        }
+    }
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum Event {}
+    #[allow(deprecated)]
+    impl Event {
+        #[doc = r" The minimal object version supporting this message"]
+        pub const fn since(&self) -> u32 {
+            match *self {}
+        }
+    }
+    #[doc = "See also the [Request] enum for this interface."]
     #[derive(Debug, Clone)]
     pub struct Quad {
         id: ObjectId,
@@ -671,6 +801,7 @@ pub mod quad {
         }
     }
     impl std::cmp::Eq for Quad {}
+    #[allow(deprecated)]
     impl super::wayland_server::Resource for Quad {
         type Request = Request;
         type Event = Event;
@@ -732,5 +863,6 @@ pub mod quad {
             self.data = Some(odata);
         }
     }
+    #[allow(deprecated)]
     impl Quad {}
-}
\ No newline at end of file
+}
@@ -1,16 +1,17 @@
 use std::sync::Arc;
 
 use wayland_backend::{
-    protocol::ProtocolError,
-    server::{ClientId, DisconnectReason, InvalidId},
+    protocol::{Interface, ProtocolError},
+    server::{ClientId, DisconnectReason, InvalidId, ObjectId},
 };
 
-use crate::{dispatch::ResourceData, Dispatch, DisplayHandle, Resource};
+use crate::{dispatch::ResourceData, socket::SecurityContext, Dispatch, DisplayHandle, Resource};
 
 #[derive(Debug)]
 pub struct Client {
     pub(crate) id: ClientId,
     pub(crate) data: Arc<dyn std::any::Any + Send + Sync>,
+    pub(crate) security_context: Option<Arc<SecurityContext>>,
 }
 
 impl Client {
@@ -18,8 +19,8 @@ impl Client {
         handle: &mut DisplayHandle<'_>,
         id: ClientId,
     ) -> Result<Client, InvalidId> {
-        let data = handle.inner.handle().get_client_data(id.clone())?;
-        Ok(Client { id, data })
+        let (data, security_context) = handle.inner.handle().get_client_data(id.clone())?;
+        Ok(Client { id, data, security_context })
     }
 
     pub fn id(&self) -> ClientId {
@@ -30,6 +31,15 @@ impl Client {
         (&*self.data).downcast_ref()
     }
 
+    /// The security context of this client
+    ///
+    /// This is `None` unless the client connected through a
+    /// [`SecurityContextListener`](crate::socket::SecurityContextListener), in which case it
+    /// identifies the sandbox it runs in.
+    pub fn security_context(&self) -> Option<&SecurityContext> {
+        self.security_context.as_deref()
+    }
+
     pub fn get_credentials(
         &self,
         handle: &mut DisplayHandle<'_>,
@@ -56,6 +66,48 @@ impl Client {
         I::from_id(handle, id)
     }
 
+    /// Create a resource for this client and send the event of `parent` introducing it
+    ///
+    /// This is for the objects created by the server, like the `wl_data_offer`s introduced by the
+    /// `wl_data_device.data_offer` event: the `event` closure builds this event from the new
+    /// resource. If the event cannot be sent, for example because `parent` was destroyed, the
+    /// resource is destroyed as well and the client never learns about it.
+    ///
+    /// **Panic:**
+    ///
+    /// This method panics if the event does not carry the new resource as its "New ID" argument.
+    pub fn create_resource_with_event<I, P, D, F>(
+        &self,
+        handle: &mut DisplayHandle<'_>,
+        version: u32,
+        user_data: <D as Dispatch<I>>::UserData,
+        parent: &P,
+        event: F,
+    ) -> Result<I, InvalidId>
+    where
+        I: Resource + 'static,
+        P: Resource,
+        D: Dispatch<I> + 'static,
+        F: FnOnce(I) -> P::Event,
+    {
+        let id = handle
+            .inner
+            .typed_handle::<D>()
+            .expect("Wrong D type passed to Client::create_resource_with_event")
+            .create_object_with_event(
+                self.id.clone(),
+                I::interface(),
+                version,
+                Arc::new(ResourceData::<I, _>::new(user_data)),
+                |backend_handle, id| {
+                    let mut handle = DisplayHandle::from_handle(backend_handle);
+                    let resource = I::from_id(&mut handle, id)?;
+                    parent.write_event(&mut handle, event(resource))
+                },
+            )?;
+        I::from_id(handle, id)
+    }
+
     pub fn object_from_protocol_id<I: Resource + 'static>(
         &self,
         handle: &mut DisplayHandle<'_>,
@@ -69,6 +121,32 @@ impl Client {
         I::from_id(handle, object_id)
     }
 
+    /// The live resources of this client with the interface `I`
+    ///
+    /// This is useful to send events to every resource a client has bound from a global, for
+    /// example to resend the `wl_output` events after a change of mode.
+    pub fn objects_of<I: Resource + 'static>(
+        &self,
+        handle: &mut DisplayHandle<'_>,
+    ) -> Result<Vec<I>, InvalidId> {
+        let ids = handle.inner.handle().objects_of(self.id.clone(), I::interface())?;
+        Ok(ids.into_iter().filter_map(|id| I::from_id(handle, id).ok()).collect())
+    }
+
+    /// The IDs of the live resources of this client with the given interface
+    ///
+    /// Untyped variant of [`objects_of()`](Client::objects_of). The interface is given by its
+    /// description rather than its name, as some backends need it to create the IDs of the
+    /// resources; resources of an interface with the same name but a different description are
+    /// also returned.
+    pub fn objects_of_interface(
+        &self,
+        handle: &mut DisplayHandle<'_>,
+        interface: &'static Interface,
+    ) -> Result<Vec<ObjectId>, InvalidId> {
+        handle.inner.handle().objects_of(self.id.clone(), interface)
+    }
+
     pub fn kill(&self, handle: &mut DisplayHandle<'_>, error: ProtocolError) {
         handle.inner.handle().kill_client(self.id.clone(), DisconnectReason::ProtocolError(error))
     }
@@ -8,7 +8,11 @@ use crate::{Client, DisplayHandle, Resource};
 /// of associated user data.
 pub trait Dispatch<I: Resource>: Sized {
     /// The user data associated with the type of resource.
-    type UserData: DestructionNotify + Send + Sync + 'static;
+    ///
+    /// It is attached to each resource when it is created, can be retrieved with
+    /// [`Resource::data()`] and is given to [`request()`](Dispatch::request) when processing the
+    /// requests of the resource.
+    type UserData: Send + Sync + 'static;
 
     /// Called when a request from a client is processed.
     ///
@@ -24,23 +28,36 @@ pub trait Dispatch<I: Resource>: Sized {
         dhandle: &mut DisplayHandle<'_>,
         data_init: &mut DataInit<'_, Self>,
     );
-}
 
-/// A trait to be implemented on user data, which indicates when an object has been destroyed by a client.
-pub trait DestructionNotify {
-    /// Called when the object this user data is associated with has been destroyed.
+    /// Called when the resource has been destroyed.
     ///
-    /// Note this type only provides an immutable reference, you will need to use interior mutability to change
-    /// the inside of the object.
+    /// Note this only provides an immutable reference to the user data, you will need to use interior
+    /// mutability to change the inside of it.
     ///
     /// Typically a [`Mutex`](std::sync::Mutex) would be used to have interior mutability.
     ///
-    /// You are given the [`ObjectId`] and [`ClientId`] associated with the destroyed object for cleanup
-    /// convenience.
+    /// You are given the [`ObjectId`] and [`ClientId`] associated with the destroyed resource for
+    /// cleanup convenience.
     #[cfg(not(tarpaulin_include))]
+    fn destroyed(_client_id: ClientId, _resource: ObjectId, _data: &Self::UserData) {}
+}
+
+/// A trait to be implemented on user data, which indicates when an object has been destroyed by a client.
+///
+/// The user data of resources no longer needs to implement this trait, and its
+/// [`object_destroyed()`](DestructionNotify::object_destroyed) method is no longer invoked. The
+/// destruction of a resource is instead notified to [`Dispatch::destroyed()`], which receives the
+/// user data and can forward the notification to it. This trait will be removed in the next
+/// release.
+#[deprecated(note = "Implement `Dispatch::destroyed()` instead, this trait is no longer used")]
+pub trait DestructionNotify {
+    /// Called when the object this user data is associated with has been destroyed.
+    ///
+    /// Not invoked anymore, see [`Dispatch::destroyed()`].
     fn object_destroyed(&self, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
+#[allow(deprecated)]
 impl DestructionNotify for () {}
 
 #[derive(Debug)]
@@ -91,7 +108,7 @@ impl<'a, D> DataInit<'a, D> {
 /// The base trait used to define a delegate type to hand some type of resource.
 pub trait DelegateDispatchBase<I: Resource> {
     /// The type of user data the delegate holds.
-    type UserData: DestructionNotify + Send + Sync + 'static;
+    type UserData: Send + Sync + 'static;
 }
 
 /// A trait which defines a delegate to handle some type of resource.
@@ -116,6 +133,11 @@ pub trait DelegateDispatch<
         dhandle: &mut DisplayHandle<'_>,
         data_init: &mut DataInit<'_, D>,
     );
+
+    /// Called when the resource has been destroyed.
+    ///
+    /// See [`Dispatch::destroyed()`].
+    fn destroyed(_client_id: ClientId, _resource: ObjectId, _data: &Self::UserData) {}
 }
 
 impl<I, U> ResourceData<I, U> {
@@ -124,11 +146,8 @@ impl<I, U> ResourceData<I, U> {
     }
 }
 
-impl<
-        I: Resource + 'static,
-        U: DestructionNotify + Send + Sync + 'static,
-        D: Dispatch<I, UserData = U> + 'static,
-    > ObjectData<D> for ResourceData<I, U>
+impl<I: Resource + 'static, U: Send + Sync + 'static, D: Dispatch<I, UserData = U> + 'static>
+    ObjectData<D> for ResourceData<I, U>
 {
     fn request(
         self: Arc<Self>,
@@ -175,7 +194,7 @@ impl<
         cid: wayland_backend::server::ClientId,
         oid: wayland_backend::server::ObjectId,
     ) {
-        self.udata.object_destroyed(cid, oid)
+        <D as Dispatch<I>>::destroyed(cid, oid, &self.udata)
     }
 }
 
@@ -266,6 +285,10 @@ macro_rules! delegate_dispatch {
                 ) {
                     <$dispatch_to as $crate::DelegateDispatch<$interface, Self>>::request(self, client, resource, request, data, dhandle, data_init)
                 }
+
+                fn destroyed(client_id: $crate::backend::ClientId, resource: $crate::backend::ObjectId, data: &Self::UserData) {
+                    <$dispatch_to as $crate::DelegateDispatch<$interface, Self>>::destroyed(client_id, resource, data)
+                }
             }
         )*
     };
@@ -1,5 +1,5 @@
 use std::{
-    os::unix::net::UnixStream,
+    os::unix::{io::RawFd, net::UnixStream},
     sync::{Arc, Mutex, MutexGuard},
 };
 
@@ -13,17 +13,31 @@ use wayland_backend::{
 
 use crate::{
     global::{GlobalData, GlobalDispatch},
+    serial::SerialCounter,
+    socket::{split_client_data, SecurityContext, SecurityContextClientData},
     Client, Resource,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Display<D> {
     backend: Arc<Mutex<Backend<D>>>,
+    serials: SerialCounter,
+}
+
+// manual impl to avoid requiring `D: Clone`
+impl<D> Clone for Display<D> {
+    fn clone(&self) -> Display<D> {
+        Display { backend: self.backend.clone(), serials: self.serials.clone() }
+    }
 }
 
 impl<D: 'static> Display<D> {
     pub fn new() -> Result<Display<D>, InitError> {
-        Ok(Display { backend: Arc::new(Mutex::new(Backend::new()?)) })
+        wayland_backend::protocol::register_interfaces(crate::protocol::__interfaces::INTERFACES);
+        Ok(Display {
+            backend: Arc::new(Mutex::new(Backend::new()?)),
+            serials: SerialCounter::new(),
+        })
     }
 
     pub fn handle(&self) -> DisplayHandle<'_> {
@@ -40,7 +54,32 @@ impl<D: 'static> Display<D> {
         data: Arc<dyn ClientData<D>>,
     ) -> std::io::Result<Client> {
         let id = self.backend.lock().unwrap().insert_client(stream, data.clone())?;
-        Ok(Client { id, data: data.into_any_arc() })
+        Ok(Client { id, data: data.into_any_arc(), security_context: None })
+    }
+
+    /// Insert a client tagged with a security context
+    ///
+    /// This is meant for the clients accepted by a
+    /// [`SecurityContextListener`](crate::socket::SecurityContextListener), with the context of
+    /// the listener. The context is available from
+    /// [`Client::security_context()`], while [`Client::get_data()`] still gives access to `data`.
+    pub fn insert_client_with_security_context(
+        &self,
+        stream: UnixStream,
+        context: Arc<SecurityContext>,
+        data: Arc<dyn ClientData<D>>,
+    ) -> std::io::Result<Client> {
+        let tagged =
+            Arc::new(SecurityContextClientData { context: context.clone(), data: data.clone() });
+        let id = self.backend.lock().unwrap().insert_client(stream, tagged)?;
+        Ok(Client { id, data: data.into_any_arc(), security_context: Some(context) })
+    }
+
+    /// The allocator of the serials of this display
+    ///
+    /// All the clones of the display share the same [`SerialCounter`].
+    pub fn serial_counter(&self) -> SerialCounter {
+        self.serials.clone()
     }
 
     pub fn dispatch_clients(&self, data: &mut D) -> std::io::Result<usize> {
@@ -51,6 +90,25 @@ impl<D: 'static> Display<D> {
         self.backend.lock().unwrap().flush(None)
     }
 
+    /// The file descriptor to monitor for the requests of the clients
+    ///
+    /// When it becomes readable, [`dispatch_clients()`](Display::dispatch_clients) has requests
+    /// to dispatch.
+    pub fn poll_fd(&self) -> RawFd {
+        self.backend.lock().unwrap().poll_fd()
+    }
+
+    /// Serve the clients with a minimal event loop, until it is stopped
+    ///
+    /// See [`EventLoop`](crate::event_loop::EventLoop) for details.
+    pub fn run(
+        &self,
+        event_loop: &mut crate::event_loop::EventLoop<D>,
+        data: &mut D,
+    ) -> std::io::Result<()> {
+        event_loop.run(self, data)
+    }
+
     pub fn create_global<I: Resource + 'static>(
         &self,
         version: u32,
@@ -73,6 +131,20 @@ impl<D: 'static> Display<D> {
     pub fn remove_global(&self, id: GlobalId) {
         self.backend.lock().unwrap().handle().remove_global(id)
     }
+
+    /// Set the hook invoked when a client is disconnected
+    ///
+    /// It receives the reason of the disconnection before the
+    /// [`ClientData::disconnected()`](wayland_backend::server::ClientData::disconnected) method of
+    /// the client is invoked and before its resources are destroyed, allowing to clean up the
+    /// state associated with the client in a deterministic order. It must not try to access the
+    /// display.
+    pub fn set_disconnect_hook(
+        &self,
+        hook: impl FnMut(ClientId, &DisconnectReason) + Send + 'static,
+    ) {
+        self.backend.lock().unwrap().handle().set_disconnect_hook(Box::new(hook))
+    }
 }
 
 pub struct DisplayHandle<'a> {
@@ -145,9 +217,27 @@ impl<'a> DisplayHandle<'a> {
     pub fn post_error<I: Resource>(&mut self, resource: &I, code: u32, error: String) {
         self.inner.handle().post_error(resource.id(), code, std::ffi::CString::new(error).unwrap())
     }
+
+    /// Register a hook to be invoked when this resource is destroyed
+    ///
+    /// The hooks of a resource are invoked in registration order, after the
+    /// [`Dispatch::destroyed()`](crate::Dispatch::destroyed) method of its implementation and
+    /// before its protocol id can be reused. They must not try to access the display.
+    pub fn add_destruction_hook<I: Resource>(
+        &mut self,
+        resource: &I,
+        hook: impl FnOnce(ClientId, ObjectId) + Send + Sync + 'static,
+    ) -> Result<(), InvalidId> {
+        self.inner.handle().add_destruction_hook(resource.id(), Box::new(hook))
+    }
 }
 
 /* Dynamic dispatch plumbing for erasing type parameter on DisplayHandle */
+
+// the client data provided by the compositor, and the security context of the client
+pub(crate) type ErasedClientData =
+    (Arc<dyn std::any::Any + Send + Sync>, Option<Arc<SecurityContext>>);
+
 pub(crate) trait ErasedDisplayHandle: downcast_rs::Downcast {
     fn get_object_data(
         &mut self,
@@ -163,13 +253,20 @@ pub(crate) trait ErasedDisplayHandle: downcast_rs::Downcast {
         interface: &'static Interface,
         pid: u32,
     ) -> Result<ObjectId, InvalidId>;
+    fn objects_of(
+        &mut self,
+        cid: ClientId,
+        interface: &'static Interface,
+    ) -> Result<Vec<ObjectId>, InvalidId>;
     fn post_error(&mut self, id: ObjectId, code: u32, msg: std::ffi::CString);
     fn get_client_credentials(&mut self, id: ClientId) -> Result<Credentials, InvalidId>;
-    fn get_client_data(
-        &mut self,
-        id: ClientId,
-    ) -> Result<Arc<dyn std::any::Any + Send + Sync>, InvalidId>;
+    fn get_client_data(&mut self, id: ClientId) -> Result<ErasedClientData, InvalidId>;
     fn kill_client(&mut self, id: ClientId, reason: DisconnectReason);
+    fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId>;
 }
 
 downcast_rs::impl_downcast!(ErasedDisplayHandle);
@@ -208,6 +305,14 @@ impl<D: 'static> ErasedDisplayHandle for Handle<D> {
         Handle::<D>::object_for_protocol_id(self, cid, interface, pid)
     }
 
+    fn objects_of(
+        &mut self,
+        cid: ClientId,
+        interface: &'static Interface,
+    ) -> Result<Vec<ObjectId>, InvalidId> {
+        Handle::<D>::objects_of(self, cid, interface).map(|ids| ids.collect())
+    }
+
     fn post_error(&mut self, id: ObjectId, code: u32, msg: std::ffi::CString) {
         Handle::<D>::post_error(self, id, code, msg)
     }
@@ -216,16 +321,21 @@ impl<D: 'static> ErasedDisplayHandle for Handle<D> {
         Handle::<D>::get_client_credentials(self, id)
     }
 
-    fn get_client_data(
-        &mut self,
-        id: ClientId,
-    ) -> Result<Arc<dyn std::any::Any + Send + Sync>, InvalidId> {
-        Handle::<D>::get_client_data(self, id).map(|udata| udata.into_any_arc())
+    fn get_client_data(&mut self, id: ClientId) -> Result<ErasedClientData, InvalidId> {
+        Handle::<D>::get_client_data(self, id).map(split_client_data)
     }
 
     fn kill_client(&mut self, id: ClientId, reason: DisconnectReason) {
         Handle::<D>::kill_client(self, id, reason)
     }
+
+    fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        Handle::<D>::add_destruction_hook(self, id, hook)
+    }
 }
 
 impl<D: 'static> ErasedDisplayHandle for Backend<D> {
@@ -262,6 +372,14 @@ impl<D: 'static> ErasedDisplayHandle for Backend<D> {
         Handle::<D>::object_for_protocol_id(self.handle(), cid, interface, pid)
     }
 
+    fn objects_of(
+        &mut self,
+        cid: ClientId,
+        interface: &'static Interface,
+    ) -> Result<Vec<ObjectId>, InvalidId> {
+        Handle::<D>::objects_of(self.handle(), cid, interface).map(|ids| ids.collect())
+    }
+
     fn post_error(&mut self, id: ObjectId, code: u32, msg: std::ffi::CString) {
         Handle::<D>::post_error(self.handle(), id, code, msg)
     }
@@ -270,14 +388,19 @@ impl<D: 'static> ErasedDisplayHandle for Backend<D> {
         Handle::<D>::get_client_credentials(self.handle(), id)
     }
 
-    fn get_client_data(
-        &mut self,
-        id: ClientId,
-    ) -> Result<Arc<dyn std::any::Any + Send + Sync>, InvalidId> {
-        Handle::<D>::get_client_data(self.handle(), id).map(|udata| udata.into_any_arc())
+    fn get_client_data(&mut self, id: ClientId) -> Result<ErasedClientData, InvalidId> {
+        Handle::<D>::get_client_data(self.handle(), id).map(split_client_data)
     }
 
     fn kill_client(&mut self, id: ClientId, reason: DisconnectReason) {
         Handle::<D>::kill_client(self.handle(), id, reason)
     }
+
+    fn add_destruction_hook(
+        &mut self,
+        id: ObjectId,
+        hook: Box<dyn FnOnce(ClientId, ObjectId) + Send + Sync>,
+    ) -> Result<(), InvalidId> {
+        Handle::<D>::add_destruction_hook(self.handle(), id, hook)
+    }
 }
@@ -0,0 +1,490 @@
+//! A minimal event loop for servers
+//!
+//! An [`EventLoop`] drives a [`Display`]: it accepts the clients connecting to its listening
+//! sockets, dispatches their requests and flushes the events sent to them, and runs timers and
+//! idle callbacks in between. This is enough for small compositors and test servers, larger ones
+//! will rather integrate the display in the event loop of their choice, monitoring
+//! [`Display::poll_fd()`].
+//!
+//! The timers and idle callbacks are registered with a [`LoopHandle`], which can be stored in the
+//! state of the compositor to schedule them from request handlers, for example to repeat keys or
+//! to schedule the next repaint. They receive the state and a [`DisplayHandle`] to send events.
+//!
+//! ```no_run
+//! # use std::{sync::Arc, time::Duration};
+//! # use wayland_server::backend::{ClientData, ClientId, DisconnectReason};
+//! # struct State;
+//! # struct ClientState;
+//! # impl ClientData<State> for ClientState {
+//! #     fn initialized(&self, _: ClientId) {}
+//! #     fn disconnected(&self, _: ClientId, _: DisconnectReason) {}
+//! # }
+//! use wayland_server::{
+//!     event_loop::{EventLoop, TimeoutAction},
+//!     socket::ListeningSocket,
+//!     Display,
+//! };
+//!
+//! let display = Display::<State>::new().unwrap();
+//! let mut event_loop = EventLoop::new().unwrap();
+//! let socket = ListeningSocket::bind_auto("wayland", 1..33).unwrap();
+//! event_loop.add_listening_socket(socket, |_| Arc::new(ClientState));
+//!
+//! // stop serving after ten seconds
+//! let signal = event_loop.signal();
+//! event_loop.handle().add_timer(Duration::from_secs(10), move |_, _| {
+//!     signal.stop();
+//!     TimeoutAction::Drop
+//! });
+//!
+//! display.run(&mut event_loop, &mut State).unwrap();
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt, io,
+    os::unix::io::{AsRawFd, RawFd},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use nix::{
+    fcntl::OFlag,
+    poll::{poll, PollFd, PollFlags},
+    unistd::{close, pipe2, read, write},
+};
+use wayland_backend::server::ClientData;
+
+use crate::{
+    socket::{ListeningSocket, SecurityContextListener},
+    Display, DisplayHandle,
+};
+
+/// What to do with a timer once its callback was invoked
+///
+/// One-shot timers return `Drop`, while timers firing several times return the next deadline.
+/// [`LoopHandle::add_periodic_timer()`] provides timers firing at a fixed interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutAction {
+    /// Remove the timer
+    Drop,
+    /// Fire the timer again at the given instant
+    ToInstant(Instant),
+    /// Fire the timer again after the given duration
+    ToDuration(Duration),
+}
+
+/// The token of a timer, allowing to remove it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerToken(u64);
+
+/// A signal stopping an event loop
+///
+/// It can be sent to other threads. The loop is woken up if it is waiting for events, and stops
+/// once its current iteration is done. If the loop is not running, its next
+/// [`run()`](EventLoop::run) returns right away.
+#[derive(Debug, Clone)]
+pub struct LoopSignal {
+    inner: Arc<SignalInner>,
+}
+
+#[derive(Debug)]
+struct SignalInner {
+    stopped: AtomicBool,
+    // a pipe monitored by the loop, written to wake it up when it is stopped
+    wake_read: RawFd,
+    wake_write: RawFd,
+}
+
+impl Drop for SignalInner {
+    fn drop(&mut self) {
+        let _ = close(self.wake_read);
+        let _ = close(self.wake_write);
+    }
+}
+
+impl LoopSignal {
+    fn new() -> io::Result<LoopSignal> {
+        let (wake_read, wake_write) = pipe2(OFlag::O_CLOEXEC | OFlag::O_NONBLOCK)?;
+        Ok(LoopSignal {
+            inner: Arc::new(SignalInner { stopped: AtomicBool::new(false), wake_read, wake_write }),
+        })
+    }
+
+    /// Stop the event loop
+    pub fn stop(&self) {
+        self.inner.stopped.store(true, Ordering::SeqCst);
+        // if the pipe is full, the loop is already woken up
+        let _ = write(self.inner.wake_write, &[0]);
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.inner.stopped.load(Ordering::SeqCst)
+    }
+
+    // empty the pipe once the loop was woken up
+    fn drain(&self) {
+        let mut buffer = [0; 64];
+        while let Ok(len) = read(self.inner.wake_read, &mut buffer) {
+            if len < buffer.len() {
+                break;
+            }
+        }
+    }
+}
+
+// the callbacks of the timers receive their deadline
+type TimerCallback<D> = Box<dyn FnMut(Instant, &mut D, &mut DisplayHandle<'_>) -> TimeoutAction>;
+type IdleCallback<D> = Box<dyn FnOnce(&mut D, &mut DisplayHandle<'_>)>;
+
+struct Timer<D> {
+    token: TimerToken,
+    deadline: Instant,
+    // taken out while the callback is invoked, so that it can access the loop
+    callback: Option<TimerCallback<D>>,
+}
+
+struct LoopInner<D> {
+    timers: Vec<Timer<D>>,
+    idles: Vec<IdleCallback<D>>,
+    next_token: u64,
+}
+
+/// A handle to an event loop, to register timers and idle callbacks
+///
+/// It can be cloned, captured by the callbacks of the loop and stored in the state of the
+/// compositor, to be used from request handlers.
+///
+/// The callbacks receive a [`DisplayHandle`], which holds the lock of the [`Display`]: they must
+/// not use the `Display` itself.
+pub struct LoopHandle<D> {
+    inner: Rc<RefCell<LoopInner<D>>>,
+}
+
+// manual impl to avoid requiring `D: Clone`
+impl<D> Clone for LoopHandle<D> {
+    fn clone(&self) -> LoopHandle<D> {
+        LoopHandle { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<D> fmt::Debug for LoopHandle<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoopHandle").finish_non_exhaustive()
+    }
+}
+
+impl<D> LoopHandle<D> {
+    /// Add a timer firing after the given duration
+    pub fn add_timer(
+        &self,
+        timeout: Duration,
+        callback: impl FnMut(&mut D, &mut DisplayHandle<'_>) -> TimeoutAction + 'static,
+    ) -> TimerToken {
+        self.add_timer_at(Instant::now() + timeout, callback)
+    }
+
+    /// Add a timer firing at the given instant
+    pub fn add_timer_at(
+        &self,
+        deadline: Instant,
+        mut callback: impl FnMut(&mut D, &mut DisplayHandle<'_>) -> TimeoutAction + 'static,
+    ) -> TimerToken {
+        self.insert_timer(deadline, Box::new(move |_, data, handle| callback(data, handle)))
+    }
+
+    /// Add a timer firing at a fixed interval, starting after one interval
+    ///
+    /// The deadlines do not drift with the delays of the loop. If the loop was late by more than
+    /// one interval, the missed ticks are skipped rather than fired in a burst.
+    pub fn add_periodic_timer(
+        &self,
+        interval: Duration,
+        mut callback: impl FnMut(&mut D, &mut DisplayHandle<'_>) + 'static,
+    ) -> TimerToken {
+        self.insert_timer(
+            Instant::now() + interval,
+            Box::new(move |deadline, data, handle| {
+                callback(data, handle);
+                let now = Instant::now();
+                let next = deadline + interval;
+                TimeoutAction::ToInstant(if next > now { next } else { now + interval })
+            }),
+        )
+    }
+
+    /// Change the deadline of a timer
+    ///
+    /// Returns `false` if the timer was removed. When invoked from the callback of the timer
+    /// itself, the [`TimeoutAction`] it returns takes precedence.
+    pub fn reschedule_timer(&self, token: TimerToken, deadline: Instant) -> bool {
+        match self.inner.borrow_mut().timers.iter_mut().find(|timer| timer.token == token) {
+            Some(timer) => {
+                timer.deadline = deadline;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a timer, if it was not already removed
+    pub fn remove_timer(&self, token: TimerToken) {
+        self.inner.borrow_mut().timers.retain(|timer| timer.token != token);
+    }
+
+    /// Add a callback invoked once at the end of the next iteration of the loop
+    ///
+    /// The loop does not wait for events while idle callbacks are pending.
+    pub fn add_idle(&self, callback: impl FnOnce(&mut D, &mut DisplayHandle<'_>) + 'static) {
+        self.inner.borrow_mut().idles.push(Box::new(callback));
+    }
+
+    fn insert_timer(&self, deadline: Instant, callback: TimerCallback<D>) -> TimerToken {
+        let mut inner = self.inner.borrow_mut();
+        let token = TimerToken(inner.next_token);
+        inner.next_token += 1;
+        inner.timers.push(Timer { token, deadline, callback: Some(callback) });
+        token
+    }
+}
+
+type ClientDataFactory<D> = Box<dyn FnMut(&mut D) -> Arc<dyn ClientData<D>>>;
+
+/// A minimal event loop driving a [`Display`]
+///
+/// See the [module-level documentation](self) for an example.
+pub struct EventLoop<D> {
+    handle: LoopHandle<D>,
+    sockets: Vec<(ListeningSocket, ClientDataFactory<D>)>,
+    security_listeners: Vec<(SecurityContextListener, ClientDataFactory<D>)>,
+    signal: LoopSignal,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl<D> fmt::Debug for EventLoop<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventLoop").field("signal", &self.signal).finish_non_exhaustive()
+    }
+}
+
+impl<D: 'static> EventLoop<D> {
+    /// Create a new event loop
+    ///
+    /// This fails if the pipe waking up the loop when it is stopped cannot be created.
+    pub fn new() -> io::Result<EventLoop<D>> {
+        Ok(EventLoop {
+            handle: LoopHandle {
+                inner: Rc::new(RefCell::new(LoopInner {
+                    timers: Vec::new(),
+                    idles: Vec::new(),
+                    next_token: 0,
+                })),
+            },
+            sockets: Vec::new(),
+            security_listeners: Vec::new(),
+            signal: LoopSignal::new()?,
+        })
+    }
+
+    /// A handle to this loop, to register timers and idle callbacks
+    pub fn handle(&self) -> LoopHandle<D> {
+        self.handle.clone()
+    }
+
+    /// A signal to stop this loop
+    pub fn signal(&self) -> LoopSignal {
+        self.signal.clone()
+    }
+
+    /// Accept the clients connecting to a listening socket
+    ///
+    /// The client data of each accepted client is provided by `client_data`.
+    pub fn add_listening_socket(
+        &mut self,
+        socket: ListeningSocket,
+        client_data: impl FnMut(&mut D) -> Arc<dyn ClientData<D>> + 'static,
+    ) {
+        self.sockets.push((socket, Box::new(client_data)));
+    }
+
+    /// Accept the clients connecting to a listener created with `wp_security_context_v1`
+    ///
+    /// The accepted clients are tagged with the security context of the listener, and their client
+    /// data is provided by `client_data`. The listener is dropped once the sandbox engine closes
+    /// its close fd.
+    pub fn add_security_context_listener(
+        &mut self,
+        listener: SecurityContextListener,
+        client_data: impl FnMut(&mut D) -> Arc<dyn ClientData<D>> + 'static,
+    ) {
+        self.security_listeners.push((listener, Box::new(client_data)));
+    }
+
+    /// Run a single iteration of the loop
+    ///
+    /// This waits for a client to connect or send requests, for a timer to expire, for the loop to
+    /// be stopped, or for the timeout if any. The new clients are then accepted, the requests dispatched, the expired
+    /// timers and the idle callbacks invoked, and the events flushed to the clients.
+    pub fn dispatch(
+        &mut self,
+        display: &Display<D>,
+        timeout: Option<Duration>,
+        data: &mut D,
+    ) -> io::Result<()> {
+        let timeout = self.next_timeout(timeout);
+
+        // the security context listeners are monitored for both their socket and their close fd,
+        // and the wake up pipe of the signal comes last
+        let mut fds = self
+            .sockets
+            .iter()
+            .map(|(socket, _)| socket.as_raw_fd())
+            .chain(
+                self.security_listeners
+                    .iter()
+                    .flat_map(|(listener, _)| [listener.as_raw_fd(), listener.close_fd()]),
+            )
+            .chain([display.poll_fd(), self.signal.inner.wake_read])
+            .map(|fd| PollFd::new(fd, PollFlags::POLLIN))
+            .collect::<Vec<_>>();
+        let timeout_ms = match timeout {
+            // round up, to not wake up right before the deadline of a timer
+            Some(timeout) => timeout
+                .saturating_add(Duration::from_nanos(999_999))
+                .as_millis()
+                .min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+        match poll(&mut fds, timeout_ms) {
+            Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        if fds.last().and_then(|fd| fd.revents()).map(|flags| !flags.is_empty()).unwrap_or(false) {
+            self.signal.drain();
+        }
+
+        for ((socket, client_data), fd) in self.sockets.iter_mut().zip(&fds) {
+            if fd.revents().map(|flags| flags.contains(PollFlags::POLLIN)).unwrap_or(false) {
+                while let Some(stream) = socket.accept()? {
+                    display.insert_client(stream, client_data(data))?;
+                }
+            }
+        }
+
+        let listener_fds = fds[self.sockets.len()..].chunks(2);
+        let mut closed = Vec::new();
+        for (index, ((listener, client_data), fds)) in
+            self.security_listeners.iter_mut().zip(listener_fds).enumerate()
+        {
+            if fds[0].revents().map(|flags| flags.contains(PollFlags::POLLIN)).unwrap_or(false) {
+                while let Some(stream) = listener.accept()? {
+                    display.insert_client_with_security_context(
+                        stream,
+                        listener.shared_context(),
+                        client_data(data),
+                    )?;
+                }
+            }
+            if fds[1].revents().map(|flags| !flags.is_empty()).unwrap_or(false) {
+                closed.push(index);
+            }
+        }
+        for index in closed.into_iter().rev() {
+            // dropping the listener closes its socket
+            let _listener = self.security_listeners.remove(index);
+        }
+
+        display.dispatch_clients(data)?;
+        self.run_timers(display, data);
+        self.run_idles(display, data);
+        display.flush_clients()
+    }
+
+    /// Run the loop until it is stopped by its [`LoopSignal`]
+    ///
+    /// Returns right away if the loop was stopped while not running. The stop is then consumed,
+    /// the loop can be run again.
+    pub fn run(&mut self, display: &Display<D>, data: &mut D) -> io::Result<()> {
+        while !self.signal.is_stopped() {
+            self.dispatch(display, None, data)?;
+        }
+        self.signal.inner.stopped.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn next_timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let inner = self.handle.inner.borrow();
+        if !inner.idles.is_empty() {
+            return Some(Duration::ZERO);
+        }
+        let now = Instant::now();
+        match inner.timers.iter().map(|timer| timer.deadline).min() {
+            Some(deadline) => {
+                let until = deadline.saturating_duration_since(now);
+                Some(timeout.map_or(until, |timeout| timeout.min(until)))
+            }
+            None => timeout,
+        }
+    }
+
+    fn run_timers(&mut self, display: &Display<D>, data: &mut D) {
+        let now = Instant::now();
+        let expired = self
+            .handle
+            .inner
+            .borrow()
+            .timers
+            .iter()
+            .filter(|timer| timer.deadline <= now)
+            .map(|timer| timer.token)
+            .collect::<Vec<_>>();
+        for token in expired {
+            // the timer may have been removed by a previous callback
+            let callback = self
+                .handle
+                .inner
+                .borrow_mut()
+                .timers
+                .iter_mut()
+                .find(|timer| timer.token == token)
+                .and_then(|timer| Some((timer.deadline, timer.callback.take()?)));
+            let (deadline, mut callback) = match callback {
+                Some(callback) => callback,
+                None => continue,
+            };
+            let action = callback(deadline, data, &mut display.handle());
+            let mut inner = self.handle.inner.borrow_mut();
+            let index = match inner.timers.iter().position(|timer| timer.token == token) {
+                Some(index) => index,
+                // removed by its own callback
+                None => continue,
+            };
+            match action {
+                TimeoutAction::Drop => {
+                    inner.timers.remove(index);
+                }
+                TimeoutAction::ToInstant(deadline) => {
+                    inner.timers[index].deadline = deadline;
+                    inner.timers[index].callback = Some(callback);
+                }
+                TimeoutAction::ToDuration(duration) => {
+                    inner.timers[index].deadline = now + duration;
+                    inner.timers[index].callback = Some(callback);
+                }
+            }
+        }
+    }
+
+    fn run_idles(&mut self, display: &Display<D>, data: &mut D) {
+        let idles = std::mem::take(&mut self.handle.inner.borrow_mut().idles);
+        for idle in idles {
+            idle(data, &mut display.handle());
+        }
+    }
+}
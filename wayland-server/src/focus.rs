@@ -0,0 +1,110 @@
+//! Focus bookkeeping for input devices
+//!
+//! The [`FocusTracker`] keeps track of which surface currently has the focus of an input device
+//! (like the keyboard or pointer of a seat), and checks the invariants of the `enter`/`leave`
+//! events as the compositor reports focus changes:
+//!
+//! - a surface must be left before an other one can be entered, and only the focused surface
+//!   can be left;
+//! - only alive surfaces can be entered;
+//! - the serials of successive focus changes must be increasing.
+//!
+//! Violating one of these invariants is a bug in the compositor: it triggers a panic in debug
+//! builds, and is logged (with the focus state being updated anyway) in release builds.
+
+use crate::{protocol::wl_surface::WlSurface, DisplayHandle, Resource};
+
+/// Tracker of the focus of an input device
+#[derive(Debug, Default)]
+pub struct FocusTracker {
+    focus: Option<WlSurface>,
+    last_serial: Option<u32>,
+}
+
+impl FocusTracker {
+    /// Create a new tracker, for a device without focus and which never entered a surface
+    pub fn new() -> FocusTracker {
+        FocusTracker { focus: None, last_serial: None }
+    }
+
+    /// The surface currently having the focus, if any
+    pub fn current(&self) -> Option<&WlSurface> {
+        self.focus.as_ref()
+    }
+
+    /// Check whether given surface currently has the focus
+    pub fn has_focus(&self, surface: &WlSurface) -> bool {
+        self.focus.as_ref() == Some(surface)
+    }
+
+    /// The serial of the last focus change, if any
+    pub fn last_serial(&self) -> Option<u32> {
+        self.last_serial
+    }
+
+    /// Record that given surface has been entered with given serial
+    ///
+    /// The previously focused surface, if any, must have been left beforehand.
+    pub fn enter(&mut self, dh: &mut DisplayHandle<'_>, surface: &WlSurface, serial: u32) {
+        if let Some(ref focus) = self.focus {
+            focus_bug(format_args!(
+                "entering surface {} while surface {} still has the focus",
+                surface.id(),
+                focus.id()
+            ));
+        }
+        if dh.object_info(surface.id()).is_err() {
+            focus_bug(format_args!("entering dead surface {}", surface.id()));
+        }
+        self.check_serial(serial);
+        self.focus = Some(surface.clone());
+    }
+
+    /// Record that given surface has been left with given serial
+    ///
+    /// The surface must be the one currently having the focus.
+    pub fn leave(&mut self, surface: &WlSurface, serial: u32) {
+        if !self.has_focus(surface) {
+            focus_bug(format_args!(
+                "leaving surface {} which does not have the focus",
+                surface.id()
+            ));
+        }
+        self.check_serial(serial);
+        self.focus = None;
+    }
+
+    /// Forget the focus if given surface has it
+    ///
+    /// This should be invoked when a surface is destroyed, as no leave event can be sent for it.
+    /// Returns `true` if the surface had the focus.
+    pub fn surface_destroyed(&mut self, surface: &WlSurface) -> bool {
+        if self.has_focus(surface) {
+            self.focus = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn check_serial(&mut self, serial: u32) {
+        if let Some(last) = self.last_serial {
+            // serials wrap around, compare them as a sliding window
+            if (serial.wrapping_sub(last) as i32) <= 0 {
+                focus_bug(format_args!(
+                    "serial {} is not greater than the previous serial {}",
+                    serial, last
+                ));
+            }
+        }
+        self.last_serial = Some(serial);
+    }
+}
+
+fn focus_bug(args: std::fmt::Arguments) {
+    if cfg!(debug_assertions) {
+        panic!("Focus invariant violated: {}", args);
+    } else {
+        log::error!("Focus invariant violated: {}", args);
+    }
+}
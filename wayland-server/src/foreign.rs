@@ -0,0 +1,145 @@
+//! Sharing objects between clients
+//!
+//! Some protocols (like `xdg-foreign`) let a client export one of its objects as an opaque
+//! handle, which it passes to an other client through some side channel, so that this client can
+//! import the object and refer to it in its own requests.
+//!
+//! The [`ForeignRegistry`] does the bookkeeping of these exchanges: it generates the handles of
+//! the exported objects, resolves them when they are imported, and keeps track of the objects
+//! representing each import so that they can be notified when the exported object goes away.
+//!
+//! With `xdg-foreign`, the exported objects are the `wl_surface`s given to `export_toplevel`, and
+//! the importers are the `zxdg_imported_v2` objects:
+//!
+//! - `zxdg_exporter_v2.export_toplevel` calls [`export()`](ForeignRegistry::export) and sends the
+//!   returned handle with `zxdg_exported_v2.handle`;
+//! - `zxdg_importer_v2.import_toplevel` calls [`import()`](ForeignRegistry::import) and
+//!   immediately sends `zxdg_imported_v2.destroyed` if it returns `None`;
+//! - the destruction of a `zxdg_exported_v2` calls [`unexport()`](ForeignRegistry::unexport), the
+//!   destruction of the surface calls [`object_destroyed()`](ForeignRegistry::object_destroyed),
+//!   and `zxdg_imported_v2.destroyed` is sent to the importers both return;
+//! - the destruction of a `zxdg_imported_v2` calls
+//!   [`import_destroyed()`](ForeignRegistry::import_destroyed).
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant},
+};
+
+use crate::{DisplayHandle, Resource};
+
+/// A registry of the objects exported by the clients
+///
+/// `E` is the type of the exported objects, and `I` the type of the objects representing their
+/// imports.
+#[derive(Debug)]
+pub struct ForeignRegistry<E, I> {
+    exports: HashMap<String, Export<E, I>>,
+    counter: u64,
+    random: RandomState,
+}
+
+#[derive(Debug)]
+struct Export<E, I> {
+    object: E,
+    deadline: Option<Instant>,
+    importers: Vec<I>,
+}
+
+impl<E: Resource + Clone, I: Resource> ForeignRegistry<E, I> {
+    /// Create an empty registry
+    pub fn new() -> ForeignRegistry<E, I> {
+        ForeignRegistry { exports: HashMap::new(), counter: 0, random: RandomState::new() }
+    }
+
+    /// Export an object, returning its handle
+    ///
+    /// The handle is unique and hard to guess, but it is not cryptographically secure. If a
+    /// lifetime is given, the handle can only be imported during this period: the imports made
+    /// before it expires remain valid until the export is revoked.
+    pub fn export(&mut self, object: E, lifetime: Option<Duration>) -> String {
+        let now = Instant::now();
+        let handle = loop {
+            let handle = self.generate_handle();
+            if !self.exports.contains_key(&handle) {
+                break handle;
+            }
+        };
+        let deadline = lifetime.map(|lifetime| now + lifetime);
+        self.exports.insert(handle.clone(), Export { object, deadline, importers: Vec::new() });
+        handle
+    }
+
+    /// The object exported with given handle, if the export still exists
+    pub fn exported(&self, handle: &str) -> Option<&E> {
+        self.exports.get(handle).map(|export| &export.object)
+    }
+
+    /// Import the object exported with given handle
+    ///
+    /// Returns `None` if the handle is unknown or expired, or if the exported object is dead.
+    /// Otherwise the importer is recorded, to be returned when the export is revoked.
+    pub fn import(&mut self, dh: &mut DisplayHandle<'_>, handle: &str, importer: I) -> Option<E> {
+        let export = self.exports.get_mut(handle)?;
+        if export.deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false) {
+            return None;
+        }
+        if dh.object_info(export.object.id()).is_err() {
+            return None;
+        }
+        export.importers.push(importer);
+        Some(export.object.clone())
+    }
+
+    /// Revoke the export with given handle
+    ///
+    /// Returns the importers of the object, which must be notified that it is no longer
+    /// available.
+    pub fn unexport(&mut self, handle: &str) -> Vec<I> {
+        self.exports.remove(handle).map(|export| export.importers).unwrap_or_default()
+    }
+
+    /// Revoke all the exports of a destroyed object
+    ///
+    /// Returns the importers of the object, which must be notified that it is no longer
+    /// available.
+    pub fn object_destroyed(&mut self, object: &E) -> Vec<I> {
+        let mut importers = Vec::new();
+        self.exports.retain(|_, export| {
+            if export.object.id() == object.id() {
+                importers.append(&mut export.importers);
+                false
+            } else {
+                true
+            }
+        });
+        importers
+    }
+
+    /// Forget a destroyed importer
+    pub fn import_destroyed(&mut self, importer: &I) {
+        for export in self.exports.values_mut() {
+            export.importers.retain(|i| i.id() != importer.id());
+        }
+    }
+
+    // 128 bits derived from the random keys of the registry, as hexadecimal
+    fn generate_handle(&mut self) -> String {
+        self.counter += 1;
+        let mut halves = [0u64; 2];
+        for (i, half) in halves.iter_mut().enumerate() {
+            let mut hasher = self.random.build_hasher();
+            hasher.write_u64(self.counter);
+            hasher.write_usize(i);
+            *half = hasher.finish();
+        }
+        format!("{:016x}{:016x}", halves[0], halves[1])
+    }
+}
+
+impl<E: Resource + Clone, I: Resource> Default for ForeignRegistry<E, I> {
+    fn default() -> ForeignRegistry<E, I> {
+        ForeignRegistry::new()
+    }
+}
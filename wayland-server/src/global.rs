@@ -15,7 +15,8 @@ pub(crate) struct GlobalData<I: Resource, D: GlobalDispatch<I>> {
 
 impl<I: Resource + 'static, D: GlobalDispatch<I> + 'static> GlobalHandler<D> for GlobalData<I, D> {
     fn can_view(&self, id: ClientId, data: &Arc<dyn ClientData<D>>, _: GlobalId) -> bool {
-        let client = Client { id, data: data.clone().into_any_arc() };
+        let (data, security_context) = crate::socket::split_client_data(data.clone());
+        let client = Client { id, data, security_context };
         <D as GlobalDispatch<I>>::can_view(client, &self.data)
     }
 
@@ -78,7 +79,8 @@ pub trait GlobalDispatch<I: Resource>: Dispatch<I> {
     /// will raise a protocol error.
     ///
     /// One use of this function is implementing privileged protocols such as XWayland keyboard grabbing
-    /// which must only be used by XWayland.
+    /// which must only be used by XWayland. Another is hiding globals from sandboxed clients, which
+    /// are identified by their [`security_context()`](Client::security_context).
     ///
     /// The default implementation allows all clients to see the global.
     fn can_view(_client: Client, _global_data: &Self::GlobalData) -> bool {
@@ -125,7 +127,8 @@ pub trait DelegateGlobalDispatch<
     /// will raise a protocol error.
     ///
     /// One use of this function is implementing privileged protocols such as XWayland keyboard grabbing
-    /// which must only be used by XWayland.
+    /// which must only be used by XWayland. Another is hiding globals from sandboxed clients, which
+    /// are identified by their [`security_context()`](Client::security_context).
     ///
     /// The default implementation allows all clients to see the global.
     fn can_view(_client: Client, _global_data: &Self::GlobalData) -> bool {
@@ -8,14 +8,23 @@ use wayland_backend::{
 mod client;
 mod dispatch;
 mod display;
+pub mod event_loop;
+pub mod focus;
+pub mod foreign;
 mod global;
+#[cfg(feature = "nested")]
+pub mod nested;
+pub mod output;
+pub mod seat;
+pub mod serial;
+pub mod shm;
 pub mod socket;
+pub mod transaction;
 
 pub use client::Client;
-pub use dispatch::{
-    DataInit, DelegateDispatch, DelegateDispatchBase, DestructionNotify, Dispatch, New,
-    ResourceData,
-};
+#[allow(deprecated)]
+pub use dispatch::DestructionNotify;
+pub use dispatch::{DataInit, DelegateDispatch, DelegateDispatchBase, Dispatch, New, ResourceData};
 pub use display::{Display, DisplayHandle};
 pub use global::{DelegateGlobalDispatch, DelegateGlobalDispatchBase, GlobalDispatch};
 
@@ -29,6 +38,7 @@ pub mod backend {
 }
 
 pub use wayland_backend::protocol::WEnum;
+pub use wayland_backend::region;
 
 pub mod protocol {
     use self::__interfaces::*;
@@ -49,6 +59,10 @@ pub trait Resource: Sized {
 
     fn version(&self) -> u32;
 
+    /// Access the user data associated with this resource
+    ///
+    /// This is the [`Dispatch::UserData`] given when the resource was created. Returns `None` if
+    /// `U` is not the type of the user data of this resource.
     fn data<U: 'static>(&self) -> Option<&U>;
 
     fn from_id(dh: &mut DisplayHandle, id: ObjectId) -> Result<Self, InvalidId>;
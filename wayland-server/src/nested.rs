@@ -0,0 +1,775 @@
+//! Helpers for nested compositors
+//!
+//! A nested compositor is a Wayland server which is itself a client of an upstream compositor,
+//! and presents the surfaces of its own clients as surfaces of the upstream compositor. This
+//! module provides templates for the plumbing such a compositor needs:
+//!
+//! - [`NestedCompositor`] pairs the [`Display`] of the nested compositor with its upstream
+//!   [`Connection`], and dispatches and flushes them together;
+//! - [`SurfaceForwarder`] maps the surfaces of the downstream clients to upstream surfaces, gives
+//!   them an upstream role (a toplevel or a subsurface) and forwards their state requests;
+//! - [`InputRedirect`] redirects the input events received from the upstream compositor to the
+//!   downstream client owning the surface they target;
+//! - [`ObjectMap`] maps the upstream proxies to the downstream resources representing the same
+//!   object, with [`BufferMap`] forwarding the attached buffers and their release, and
+//!   [`FrameForwarder`] forwarding the frame callbacks of the downstream surfaces.
+//!
+//! The buffers themselves are not created: how the content of the downstream buffers reaches the
+//! upstream compositor (by re-using `wl_shm` pools, importing dmabufs, or compositing the
+//! downstream surfaces into a single upstream buffer) is up to the nested compositor, which
+//! records the upstream buffers it creates in a [`BufferMap`].
+//!
+//! This module requires the `nested` cargo feature.
+
+use std::os::unix::io::RawFd;
+
+use wayland_client::{
+    protocol as upstream, Connection, ConnectionHandle, DispatchError, EventQueue, Proxy,
+    QueueHandle,
+};
+
+use crate::{
+    focus::FocusTracker,
+    protocol::{wl_buffer, wl_callback, wl_keyboard, wl_pointer, wl_surface},
+    Display, DisplayHandle, Resource,
+};
+
+/// An error of a nested compositor
+#[derive(Debug, thiserror::Error)]
+pub enum NestedError {
+    /// The upstream connection failed
+    #[error("Upstream connection error: {0}")]
+    Upstream(#[from] DispatchError),
+    /// The downstream display failed
+    #[error("Downstream display error: {0}")]
+    Downstream(#[from] std::io::Error),
+}
+
+/// A server [`Display`] paired with its upstream [`Connection`]
+#[derive(Debug)]
+pub struct NestedCompositor<D> {
+    display: Display<D>,
+    connection: Connection,
+}
+
+impl<D: 'static> NestedCompositor<D> {
+    /// Pair a display with the connection to the upstream compositor
+    pub fn new(display: Display<D>, connection: Connection) -> NestedCompositor<D> {
+        NestedCompositor { display, connection }
+    }
+
+    /// The display serving the downstream clients
+    pub fn display(&self) -> &Display<D> {
+        &self.display
+    }
+
+    /// The connection to the upstream compositor
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Dispatch the pending upstream events, then the pending downstream requests
+    ///
+    /// The upstream events are dispatched first so that the downstream clients see a state as
+    /// up to date as possible. Returns the number of dispatched events and requests.
+    pub fn dispatch_pending(
+        &self,
+        queue: &mut EventQueue<D>,
+        data: &mut D,
+    ) -> Result<usize, NestedError> {
+        let events = queue.dispatch_pending(data)?;
+        let requests = self.display.dispatch_clients(data)?;
+        Ok(events + requests)
+    }
+
+    /// Flush the downstream clients and the upstream connection
+    pub fn flush(&self) -> Result<(), NestedError> {
+        self.display.flush_clients()?;
+        self.connection.flush().map_err(DispatchError::from)?;
+        Ok(())
+    }
+}
+
+/// The role given to a forwarded surface on the upstream compositor
+#[derive(Debug, Clone)]
+pub enum UpstreamRole {
+    /// The surface is a toplevel of the upstream compositor
+    Toplevel(upstream::wl_shell_surface::WlShellSurface),
+    /// The surface is a subsurface of an other upstream surface
+    Subsurface(upstream::wl_subsurface::WlSubsurface),
+}
+
+/// A downstream surface and its upstream counterpart
+#[derive(Debug, Clone)]
+pub struct ForwardedSurface {
+    downstream: wl_surface::WlSurface,
+    upstream: upstream::wl_surface::WlSurface,
+    role: Option<UpstreamRole>,
+}
+
+impl ForwardedSurface {
+    /// The surface of the downstream client
+    pub fn downstream(&self) -> &wl_surface::WlSurface {
+        &self.downstream
+    }
+
+    /// The surface of the upstream compositor
+    pub fn upstream(&self) -> &upstream::wl_surface::WlSurface {
+        &self.upstream
+    }
+
+    /// The upstream role of the surface, if it was given one
+    pub fn role(&self) -> Option<&UpstreamRole> {
+        self.role.as_ref()
+    }
+}
+
+/// Mapping of downstream surfaces to upstream surfaces
+#[derive(Debug, Default)]
+pub struct SurfaceForwarder {
+    surfaces: Vec<ForwardedSurface>,
+}
+
+impl SurfaceForwarder {
+    /// Create a new forwarder, without any forwarded surface
+    pub fn new() -> SurfaceForwarder {
+        SurfaceForwarder { surfaces: Vec::new() }
+    }
+
+    /// Create the upstream surface of a downstream surface
+    ///
+    /// This is typically invoked when handling the `wl_compositor.create_surface` request of a
+    /// downstream client.
+    pub fn forward_surface<U: wayland_client::Dispatch<upstream::wl_surface::WlSurface>>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        compositor: &upstream::wl_compositor::WlCompositor,
+        qh: &QueueHandle<U>,
+        udata: U::UserData,
+        downstream: &wl_surface::WlSurface,
+    ) -> Result<upstream::wl_surface::WlSurface, DispatchError> {
+        let upstream = compositor.create_surface(conn, qh, udata)?;
+        self.insert(downstream.clone(), upstream.clone());
+        Ok(upstream)
+    }
+
+    /// Record an already created upstream surface for a downstream surface
+    pub fn insert(
+        &mut self,
+        downstream: wl_surface::WlSurface,
+        upstream: upstream::wl_surface::WlSurface,
+    ) {
+        self.surfaces.retain(|s| s.downstream != downstream);
+        self.surfaces.push(ForwardedSurface { downstream, upstream, role: None });
+    }
+
+    /// Make the upstream surface of a downstream surface an upstream toplevel
+    ///
+    /// Panics if the downstream surface is not forwarded.
+    pub fn make_toplevel<
+        U: wayland_client::Dispatch<upstream::wl_shell_surface::WlShellSurface>,
+    >(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        shell: &upstream::wl_shell::WlShell,
+        qh: &QueueHandle<U>,
+        udata: U::UserData,
+        downstream: &wl_surface::WlSurface,
+    ) -> Result<upstream::wl_shell_surface::WlShellSurface, DispatchError> {
+        let surface = self.get_mut(downstream);
+        let shell_surface = shell.get_shell_surface(conn, &surface.upstream, qh, udata)?;
+        shell_surface.set_toplevel(conn);
+        surface.role = Some(UpstreamRole::Toplevel(shell_surface.clone()));
+        Ok(shell_surface)
+    }
+
+    /// Make the upstream surface of a downstream surface a subsurface of the upstream surface of
+    /// an other downstream surface
+    ///
+    /// Panics if the downstream surface or its parent are not forwarded.
+    pub fn make_subsurface<U: wayland_client::Dispatch<upstream::wl_subsurface::WlSubsurface>>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        subcompositor: &upstream::wl_subcompositor::WlSubcompositor,
+        qh: &QueueHandle<U>,
+        udata: U::UserData,
+        downstream: &wl_surface::WlSurface,
+        parent: &wl_surface::WlSurface,
+    ) -> Result<upstream::wl_subsurface::WlSubsurface, DispatchError> {
+        let parent = self.get_mut(parent).upstream.clone();
+        let surface = self.get_mut(downstream);
+        let subsurface =
+            subcompositor.get_subsurface(conn, &surface.upstream, &parent, qh, udata)?;
+        surface.role = Some(UpstreamRole::Subsurface(subsurface.clone()));
+        Ok(subsurface)
+    }
+
+    /// The forwarding of a downstream surface, if any
+    pub fn get(&self, downstream: &wl_surface::WlSurface) -> Option<&ForwardedSurface> {
+        self.surfaces.iter().find(|s| &s.downstream == downstream)
+    }
+
+    /// The upstream surface of a downstream surface, if any
+    pub fn upstream(
+        &self,
+        downstream: &wl_surface::WlSurface,
+    ) -> Option<&upstream::wl_surface::WlSurface> {
+        self.get(downstream).map(|s| &s.upstream)
+    }
+
+    /// The downstream surface of an upstream surface, if any
+    pub fn downstream(
+        &self,
+        upstream: &upstream::wl_surface::WlSurface,
+    ) -> Option<&wl_surface::WlSurface> {
+        self.surfaces.iter().find(|s| &s.upstream == upstream).map(|s| &s.downstream)
+    }
+
+    /// Forward a request of a downstream surface to its upstream surface
+    ///
+    /// The requests changing the state of the surface (`damage`, `damage_buffer`, `commit`,
+    /// `set_buffer_scale`, `set_buffer_transform` and `offset`) are forwarded. The request is
+    /// returned if it was not forwarded, because it needs to be handled by the nested
+    /// compositor, the downstream surface is not forwarded, or the upstream surface is too old.
+    pub fn forward_request(
+        &self,
+        conn: &mut ConnectionHandle,
+        downstream: &wl_surface::WlSurface,
+        request: wl_surface::Request,
+    ) -> Option<wl_surface::Request> {
+        use upstream::wl_surface::{self as up, Request as Up};
+        use wl_surface::Request as Down;
+
+        let upstream = match self.upstream(downstream) {
+            Some(upstream) => upstream,
+            None => return Some(request),
+        };
+        let (forwarded, since) = match request {
+            Down::Damage { x, y, width, height } => {
+                (Up::Damage { x, y, width, height }, up::REQ_DAMAGE_SINCE)
+            }
+            Down::DamageBuffer { x, y, width, height } => {
+                (Up::DamageBuffer { x, y, width, height }, up::REQ_DAMAGE_BUFFER_SINCE)
+            }
+            Down::Commit => (Up::Commit, up::REQ_COMMIT_SINCE),
+            Down::SetBufferScale { scale } => {
+                (Up::SetBufferScale { scale }, up::REQ_SET_BUFFER_SCALE_SINCE)
+            }
+            Down::SetBufferTransform { transform } => (
+                Up::SetBufferTransform { transform: u32::from(transform).into() },
+                up::REQ_SET_BUFFER_TRANSFORM_SINCE,
+            ),
+            Down::Offset { x, y } => (Up::Offset { x, y }, up::REQ_OFFSET_SINCE),
+            _ => return Some(request),
+        };
+        if upstream.version() < since {
+            return Some(request);
+        }
+        let _ = conn.send_request(upstream, forwarded, None);
+        None
+    }
+
+    /// Forward the `attach` request of a downstream surface, attaching the upstream counterpart of
+    /// its buffer
+    ///
+    /// Returns `false` if the downstream surface is not forwarded, or if the buffer has no
+    /// upstream counterpart in `buffers`: the content of the surface then needs to be provided by
+    /// the nested compositor.
+    pub fn forward_attach(
+        &self,
+        conn: &mut ConnectionHandle,
+        buffers: &BufferMap,
+        downstream: &wl_surface::WlSurface,
+        buffer: Option<&wl_buffer::WlBuffer>,
+        x: i32,
+        y: i32,
+    ) -> bool {
+        use upstream::wl_surface as up;
+
+        let upstream = match self.upstream(downstream) {
+            Some(upstream) => upstream,
+            None => return false,
+        };
+        let buffer = match buffer {
+            Some(buffer) => match buffers.upstream(buffer) {
+                Some(buffer) => Some(buffer),
+                None => return false,
+            },
+            None => None,
+        };
+        // the offset of the attach request is a protocol error since the offset request
+        if upstream.version() >= up::REQ_OFFSET_SINCE {
+            upstream.attach(conn, buffer, 0, 0);
+            if (x, y) != (0, 0) {
+                upstream.offset(conn, x, y);
+            }
+        } else {
+            upstream.attach(conn, buffer, x, y);
+        }
+        true
+    }
+
+    /// Forget a downstream surface, destroying its upstream counterpart
+    ///
+    /// This should be invoked when the downstream surface is destroyed. The upstream surface is
+    /// returned if the downstream surface was forwarded, so that it can be removed from an
+    /// [`InputRedirect`] as well.
+    pub fn remove(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        downstream: &wl_surface::WlSurface,
+    ) -> Option<upstream::wl_surface::WlSurface> {
+        let idx = self.surfaces.iter().position(|s| &s.downstream == downstream)?;
+        let surface = self.surfaces.remove(idx);
+        if let Some(UpstreamRole::Subsurface(ref subsurface)) = surface.role {
+            subsurface.destroy(conn);
+        }
+        surface.upstream.destroy(conn);
+        Some(surface.upstream)
+    }
+
+    fn get_mut(&mut self, downstream: &wl_surface::WlSurface) -> &mut ForwardedSurface {
+        match self.surfaces.iter_mut().find(|s| &s.downstream == downstream) {
+            Some(surface) => surface,
+            None => panic!("Surface {} is not forwarded upstream.", downstream.id()),
+        }
+    }
+}
+
+/// A mapping between upstream proxies and the downstream resources representing the same objects
+///
+/// Each upstream proxy is paired with at most one downstream resource, and conversely.
+#[derive(Debug)]
+pub struct ObjectMap<U, S> {
+    pairs: Vec<(U, S)>,
+}
+
+// manual impl to avoid requiring `U: Default` and `S: Default`
+impl<U, S> Default for ObjectMap<U, S> {
+    fn default() -> ObjectMap<U, S> {
+        ObjectMap { pairs: Vec::new() }
+    }
+}
+
+impl<U: Proxy + PartialEq, S: Resource + PartialEq> ObjectMap<U, S> {
+    /// Create a new empty map
+    pub fn new() -> ObjectMap<U, S> {
+        ObjectMap::default()
+    }
+
+    /// Pair an upstream proxy with a downstream resource
+    ///
+    /// The previous pairs of either object are removed.
+    pub fn insert(&mut self, upstream: U, downstream: S) {
+        self.pairs.retain(|(u, s)| u != &upstream && s != &downstream);
+        self.pairs.push((upstream, downstream));
+    }
+
+    /// The upstream counterpart of a downstream resource, if any
+    pub fn upstream(&self, downstream: &S) -> Option<&U> {
+        self.pairs.iter().find(|(_, s)| s == downstream).map(|(u, _)| u)
+    }
+
+    /// The downstream counterpart of an upstream proxy, if any
+    pub fn downstream(&self, upstream: &U) -> Option<&S> {
+        self.pairs.iter().find(|(u, _)| u == upstream).map(|(_, s)| s)
+    }
+
+    /// Remove the pair of a downstream resource, returning its upstream counterpart
+    pub fn remove_downstream(&mut self, downstream: &S) -> Option<U> {
+        let idx = self.pairs.iter().position(|(_, s)| s == downstream)?;
+        Some(self.pairs.remove(idx).0)
+    }
+
+    /// Remove the pair of an upstream proxy, returning its downstream counterpart
+    pub fn remove_upstream(&mut self, upstream: &U) -> Option<S> {
+        let idx = self.pairs.iter().position(|(u, _)| u == upstream)?;
+        Some(self.pairs.remove(idx).1)
+    }
+
+    /// The number of pairs
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Whether there are no pairs
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Iterate over the pairs of upstream proxies and downstream resources
+    pub fn iter(&self) -> impl Iterator<Item = (&U, &S)> {
+        self.pairs.iter().map(|(u, s)| (u, s))
+    }
+}
+
+/// The mapping of downstream buffers to the upstream buffers holding their content
+///
+/// It is used by [`SurfaceForwarder::forward_attach()`] to attach the upstream buffers.
+pub type BufferMap = ObjectMap<upstream::wl_buffer::WlBuffer, wl_buffer::WlBuffer>;
+
+impl ObjectMap<upstream::wl_buffer::WlBuffer, wl_buffer::WlBuffer> {
+    /// Forward the `release` event of an upstream buffer to its downstream counterpart
+    ///
+    /// Returns `false` if the upstream buffer has no downstream counterpart.
+    pub fn forward_release(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        upstream: &upstream::wl_buffer::WlBuffer,
+    ) -> bool {
+        match self.downstream(upstream) {
+            Some(downstream) => {
+                let _ = dh.send_event(downstream, wl_buffer::Event::Release);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Forwarding of the frame callbacks of downstream surfaces
+///
+/// Each frame callback requested by a downstream surface is forwarded as a frame callback of its
+/// upstream surface, so that the downstream clients are throttled by the upstream compositor.
+#[derive(Debug, Default)]
+pub struct FrameForwarder {
+    callbacks: ObjectMap<upstream::wl_callback::WlCallback, wl_callback::WlCallback>,
+}
+
+impl FrameForwarder {
+    /// Create a new forwarder, without any pending callback
+    pub fn new() -> FrameForwarder {
+        FrameForwarder::default()
+    }
+
+    /// Forward the frame callback of a downstream surface
+    ///
+    /// This is typically invoked when handling the `wl_surface.frame` request of a downstream
+    /// client. Returns `None` if the surface is not forwarded, in which case the nested
+    /// compositor needs to signal the callback itself.
+    pub fn forward_frame<U: wayland_client::Dispatch<upstream::wl_callback::WlCallback>>(
+        &mut self,
+        conn: &mut ConnectionHandle,
+        surfaces: &SurfaceForwarder,
+        qh: &QueueHandle<U>,
+        udata: U::UserData,
+        downstream: &wl_surface::WlSurface,
+        callback: wl_callback::WlCallback,
+    ) -> Result<Option<upstream::wl_callback::WlCallback>, DispatchError> {
+        let upstream = match surfaces.upstream(downstream) {
+            Some(upstream) => upstream.frame(conn, qh, udata)?,
+            None => return Ok(None),
+        };
+        self.callbacks.insert(upstream.clone(), callback);
+        Ok(Some(upstream))
+    }
+
+    /// Forward the `done` event of an upstream frame callback to its downstream counterpart
+    ///
+    /// Returns `false` if the upstream callback was not forwarded.
+    pub fn done(
+        &mut self,
+        dh: &mut DisplayHandle<'_>,
+        upstream: &upstream::wl_callback::WlCallback,
+        callback_data: u32,
+    ) -> bool {
+        match self.callbacks.remove_upstream(upstream) {
+            Some(downstream) => {
+                let _ = dh.send_event(&downstream, wl_callback::Event::Done { callback_data });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget a downstream callback destroyed before being signaled, like when its client
+    /// disconnects
+    pub fn callback_destroyed(&mut self, callback: &wl_callback::WlCallback) {
+        self.callbacks.remove_downstream(callback);
+    }
+
+    /// The number of callbacks waiting for the upstream compositor
+    pub fn pending(&self) -> usize {
+        self.callbacks.len()
+    }
+}
+
+/// Redirection of upstream input events to the downstream clients
+///
+/// The pointer and keyboard events received from the upstream compositor are sent to the
+/// downstream pointers and keyboards of the client owning the targeted surface, as mapped by a
+/// [`SurfaceForwarder`]. Events targeting upstream surfaces which are not forwarded (like the
+/// decorations drawn by the nested compositor) are ignored.
+///
+/// The serials of the upstream events are forwarded as-is.
+#[derive(Debug, Default)]
+pub struct InputRedirect {
+    pointers: Vec<wl_pointer::WlPointer>,
+    keyboards: Vec<wl_keyboard::WlKeyboard>,
+    pointer_focus: FocusTracker,
+    keyboard_focus: FocusTracker,
+    keymap: Option<Keymap>,
+}
+
+#[derive(Debug)]
+struct Keymap {
+    format: u32,
+    fd: RawFd,
+    size: u32,
+}
+
+impl InputRedirect {
+    /// Create a new redirection, without any downstream pointer or keyboard
+    ///
+    /// The downstream devices are added as the clients create them, with
+    /// [`add_pointer()`](InputRedirect::add_pointer) and
+    /// [`add_keyboard()`](InputRedirect::add_keyboard).
+    pub fn new() -> InputRedirect {
+        InputRedirect::default()
+    }
+
+    /// Add a downstream pointer receiving the redirected pointer events
+    pub fn add_pointer(&mut self, pointer: wl_pointer::WlPointer) {
+        self.pointers.push(pointer);
+    }
+
+    /// Add a downstream keyboard receiving the redirected keyboard events
+    ///
+    /// The last keymap received from the upstream compositor is sent to the keyboard.
+    pub fn add_keyboard(&mut self, dh: &mut DisplayHandle<'_>, keyboard: wl_keyboard::WlKeyboard) {
+        if let Some(ref keymap) = self.keymap {
+            let _ = dh.send_event(
+                &keyboard,
+                wl_keyboard::Event::Keymap {
+                    format: keymap.format.into(),
+                    fd: keymap.fd,
+                    size: keymap.size,
+                },
+            );
+        }
+        self.keyboards.push(keyboard);
+    }
+
+    /// Remove a destroyed downstream pointer
+    pub fn remove_pointer(&mut self, pointer: &wl_pointer::WlPointer) {
+        self.pointers.retain(|p| p != pointer);
+    }
+
+    /// Remove a destroyed downstream keyboard
+    pub fn remove_keyboard(&mut self, keyboard: &wl_keyboard::WlKeyboard) {
+        self.keyboards.retain(|k| k != keyboard);
+    }
+
+    /// Forget the focus of a destroyed downstream surface
+    pub fn surface_destroyed(&mut self, surface: &wl_surface::WlSurface) {
+        self.pointer_focus.surface_destroyed(surface);
+        self.keyboard_focus.surface_destroyed(surface);
+    }
+
+    /// The downstream surface having the pointer focus, if any
+    pub fn pointer_focus(&self) -> Option<&wl_surface::WlSurface> {
+        self.pointer_focus.current()
+    }
+
+    /// The downstream surface having the keyboard focus, if any
+    pub fn keyboard_focus(&self) -> Option<&wl_surface::WlSurface> {
+        self.keyboard_focus.current()
+    }
+
+    /// Redirect an event of an upstream pointer
+    pub fn redirect_pointer(
+        &mut self,
+        dh: &mut DisplayHandle<'_>,
+        surfaces: &SurfaceForwarder,
+        event: upstream::wl_pointer::Event,
+    ) {
+        use upstream::wl_pointer::Event as Up;
+
+        let focus = match event {
+            Up::Enter { serial, ref surface, .. } => match surfaces.downstream(surface) {
+                Some(surface) => {
+                    self.pointer_focus.enter(dh, surface, serial);
+                    surface.clone()
+                }
+                None => return,
+            },
+            Up::Leave { serial, ref surface } => match surfaces.downstream(surface) {
+                Some(surface) if self.pointer_focus.has_focus(surface) => {
+                    self.pointer_focus.leave(surface, serial);
+                    surface.clone()
+                }
+                _ => return,
+            },
+            _ => match self.pointer_focus.current() {
+                Some(focus) => focus.clone(),
+                None => return,
+            },
+        };
+        for pointer in client_resources(&self.pointers, &focus) {
+            if let Some(event) = pointer_event(&event, &focus, pointer.version()) {
+                let _ = dh.send_event(pointer, event);
+            }
+        }
+    }
+
+    /// Redirect an event of an upstream keyboard
+    ///
+    /// The keymap file descriptor is kept open to be sent to the keyboards added later, until
+    /// an other keymap is received or the `InputRedirect` is dropped.
+    pub fn redirect_keyboard(
+        &mut self,
+        dh: &mut DisplayHandle<'_>,
+        surfaces: &SurfaceForwarder,
+        event: upstream::wl_keyboard::Event,
+    ) {
+        use upstream::wl_keyboard::Event as Up;
+
+        let focus = match event {
+            Up::Keymap { format, fd, size } => {
+                let format = u32::from(format);
+                for keyboard in &self.keyboards {
+                    let _ = dh.send_event(
+                        keyboard,
+                        wl_keyboard::Event::Keymap { format: format.into(), fd, size },
+                    );
+                }
+                if let Some(old) = self.keymap.replace(Keymap { format, fd, size }) {
+                    let _ = nix::unistd::close(old.fd);
+                }
+                return;
+            }
+            Up::RepeatInfo { rate, delay } => {
+                // the repeat info is not tied to the focus
+                for keyboard in &self.keyboards {
+                    if keyboard.version() >= wl_keyboard::EVT_REPEAT_INFO_SINCE {
+                        let _ =
+                            dh.send_event(keyboard, wl_keyboard::Event::RepeatInfo { rate, delay });
+                    }
+                }
+                return;
+            }
+            Up::Enter { serial, ref surface, .. } => match surfaces.downstream(surface) {
+                Some(surface) => {
+                    self.keyboard_focus.enter(dh, surface, serial);
+                    surface.clone()
+                }
+                None => return,
+            },
+            Up::Leave { serial, ref surface } => match surfaces.downstream(surface) {
+                Some(surface) if self.keyboard_focus.has_focus(surface) => {
+                    self.keyboard_focus.leave(surface, serial);
+                    surface.clone()
+                }
+                _ => return,
+            },
+            _ => match self.keyboard_focus.current() {
+                Some(focus) => focus.clone(),
+                None => return,
+            },
+        };
+        for keyboard in client_resources(&self.keyboards, &focus) {
+            if let Some(event) = keyboard_event(&event, &focus) {
+                let _ = dh.send_event(keyboard, event);
+            }
+        }
+    }
+}
+
+impl Drop for InputRedirect {
+    fn drop(&mut self) {
+        if let Some(keymap) = self.keymap.take() {
+            let _ = nix::unistd::close(keymap.fd);
+        }
+    }
+}
+
+// The resources belonging to the client owning given surface
+fn client_resources<'a, I: Resource>(
+    resources: &'a [I],
+    surface: &'a wl_surface::WlSurface,
+) -> impl Iterator<Item = &'a I> + 'a {
+    resources.iter().filter(move |r| r.id().same_client_as(&surface.id()))
+}
+
+// Translate an upstream pointer event, if supported by the downstream pointer version
+fn pointer_event(
+    event: &upstream::wl_pointer::Event,
+    focus: &wl_surface::WlSurface,
+    version: u32,
+) -> Option<wl_pointer::Event> {
+    use upstream::wl_pointer::Event as Up;
+    use wl_pointer::Event as Down;
+
+    let (event, since) = match *event {
+        Up::Enter { serial, surface_x, surface_y, .. } => (
+            Down::Enter { serial, surface: focus.clone(), surface_x, surface_y },
+            wl_pointer::EVT_ENTER_SINCE,
+        ),
+        Up::Leave { serial, .. } => {
+            (Down::Leave { serial, surface: focus.clone() }, wl_pointer::EVT_LEAVE_SINCE)
+        }
+        Up::Motion { time, surface_x, surface_y } => {
+            (Down::Motion { time, surface_x, surface_y }, wl_pointer::EVT_MOTION_SINCE)
+        }
+        Up::Button { serial, time, button, state } => (
+            Down::Button { serial, time, button, state: u32::from(state).into() },
+            wl_pointer::EVT_BUTTON_SINCE,
+        ),
+        Up::Axis { time, axis, value } => {
+            (Down::Axis { time, axis: u32::from(axis).into(), value }, wl_pointer::EVT_AXIS_SINCE)
+        }
+        Up::Frame => (Down::Frame, wl_pointer::EVT_FRAME_SINCE),
+        Up::AxisSource { axis_source } => (
+            Down::AxisSource { axis_source: u32::from(axis_source).into() },
+            wl_pointer::EVT_AXIS_SOURCE_SINCE,
+        ),
+        Up::AxisStop { time, axis } => {
+            (Down::AxisStop { time, axis: u32::from(axis).into() }, wl_pointer::EVT_AXIS_STOP_SINCE)
+        }
+        Up::AxisDiscrete { axis, discrete } => (
+            Down::AxisDiscrete { axis: u32::from(axis).into(), discrete },
+            wl_pointer::EVT_AXIS_DISCRETE_SINCE,
+        ),
+        Up::AxisValue120 { axis, value120 } => (
+            Down::AxisValue120 { axis: u32::from(axis).into(), value120 },
+            wl_pointer::EVT_AXIS_VALUE120_SINCE,
+        ),
+        Up::AxisRelativeDirection { axis, direction } => (
+            Down::AxisRelativeDirection {
+                axis: u32::from(axis).into(),
+                direction: u32::from(direction).into(),
+            },
+            wl_pointer::EVT_AXIS_RELATIVE_DIRECTION_SINCE,
+        ),
+        _ => return None,
+    };
+    if version >= since {
+        Some(event)
+    } else {
+        None
+    }
+}
+
+// Translate an upstream keyboard event tied to the focus
+fn keyboard_event(
+    event: &upstream::wl_keyboard::Event,
+    focus: &wl_surface::WlSurface,
+) -> Option<wl_keyboard::Event> {
+    use upstream::wl_keyboard::Event as Up;
+    use wl_keyboard::Event as Down;
+
+    // all these events are available since version 1
+    match *event {
+        Up::Enter { serial, ref keys, .. } => {
+            Some(Down::Enter { serial, surface: focus.clone(), keys: keys.clone() })
+        }
+        Up::Leave { serial, .. } => Some(Down::Leave { serial, surface: focus.clone() }),
+        Up::Key { serial, time, key, state } => {
+            Some(Down::Key { serial, time, key, state: u32::from(state).into() })
+        }
+        Up::Modifiers { serial, mods_depressed, mods_latched, mods_locked, group } => {
+            Some(Down::Modifiers { serial, mods_depressed, mods_latched, mods_locked, group })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,347 @@
+//! Server-side outputs
+//!
+//! An [`Output`] stores the properties of a monitor (its geometry, modes, scale, transform, name
+//! and description) and advertises them with a `wl_output` global. The events describing these
+//! properties are sent to the clients when they bind the global, and again with a final `done`
+//! event whenever the properties change with [`Output::update()`], taking the version of each
+//! resource into account.
+//!
+//! The compositor delegates `wl_output` to [`OutputManager`]:
+//!
+//! ```
+//! use wayland_server::{
+//!     delegate_dispatch, delegate_global_dispatch,
+//!     output::{Mode, Output, OutputManager, OutputProperties},
+//!     protocol::wl_output,
+//!     Display,
+//! };
+//!
+//! struct State;
+//!
+//! delegate_global_dispatch!(State: [wl_output::WlOutput] => OutputManager);
+//! delegate_dispatch!(State: [wl_output::WlOutput] => OutputManager);
+//!
+//! let display = Display::<State>::new().unwrap();
+//! let mode = Mode { width: 1920, height: 1080, refresh: 60_000 };
+//! let output = Output::new(
+//!     "DP-1".into(),
+//!     OutputProperties { modes: vec![mode], current_mode: Some(mode), ..Default::default() },
+//! );
+//! output.create_global(&display);
+//!
+//! // later, switch to HiDPI
+//! output.update(&mut display.handle(), |properties| properties.scale = 2);
+//! ```
+
+use std::sync::{Arc, Mutex, Weak};
+
+use wayland_backend::server::{ClientId, GlobalId, ObjectId};
+
+use crate::{
+    protocol::wl_output, Client, DataInit, DelegateDispatch, DelegateDispatchBase,
+    DelegateGlobalDispatch, DelegateGlobalDispatchBase, Dispatch, Display, DisplayHandle,
+    GlobalDispatch, New, Resource,
+};
+
+/// A mode of an output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mode {
+    /// The width of the mode, in hardware units
+    pub width: i32,
+    /// The height of the mode, in hardware units
+    pub height: i32,
+    /// The refresh rate of the mode, in mHz
+    pub refresh: i32,
+}
+
+/// The properties of an output
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputProperties {
+    /// The position of the output in the compositor space
+    pub location: (i32, i32),
+    /// The physical size of the output, in millimeters
+    pub physical_size: (i32, i32),
+    /// The subpixel orientation of the output
+    pub subpixel: wl_output::Subpixel,
+    /// The manufacturer of the output
+    pub make: String,
+    /// The model of the output
+    pub model: String,
+    /// The transform applied to the contents of the output
+    pub transform: wl_output::Transform,
+    /// The scale factor of the output
+    pub scale: i32,
+    /// The modes supported by the output
+    ///
+    /// The protocol has no way to withdraw a mode: removing modes only affects the clients
+    /// binding the output afterwards.
+    pub modes: Vec<Mode>,
+    /// The current mode of the output, if any
+    pub current_mode: Option<Mode>,
+    /// The preferred mode of the output, if any
+    pub preferred_mode: Option<Mode>,
+    /// A human-readable description of the output
+    pub description: String,
+}
+
+impl Default for OutputProperties {
+    fn default() -> OutputProperties {
+        OutputProperties {
+            location: (0, 0),
+            physical_size: (0, 0),
+            subpixel: wl_output::Subpixel::Unknown,
+            make: "Unknown".into(),
+            model: "Unknown".into(),
+            transform: wl_output::Transform::Normal,
+            scale: 1,
+            modes: Vec::new(),
+            current_mode: None,
+            preferred_mode: None,
+            description: String::new(),
+        }
+    }
+}
+
+impl OutputProperties {
+    fn mode_flags(&self, mode: Mode) -> wl_output::Mode {
+        let mut flags = wl_output::Mode::empty();
+        if self.current_mode == Some(mode) {
+            flags |= wl_output::Mode::Current;
+        }
+        if self.preferred_mode == Some(mode) {
+            flags |= wl_output::Mode::Preferred;
+        }
+        flags
+    }
+}
+
+/// An output advertised to the clients
+///
+/// It can be cloned, all clones refer to the same output.
+#[derive(Debug, Clone)]
+pub struct Output {
+    inner: Arc<Mutex<OutputInner>>,
+}
+
+#[derive(Debug)]
+struct OutputInner {
+    name: String,
+    properties: OutputProperties,
+    resources: Vec<wl_output::WlOutput>,
+}
+
+impl Output {
+    /// Create an output with given name and initial properties
+    ///
+    /// The name identifies the output for the lifetime of the compositor, like `DP-1`, and cannot
+    /// change.
+    pub fn new(name: String, properties: OutputProperties) -> Output {
+        Output {
+            inner: Arc::new(Mutex::new(OutputInner { name, properties, resources: Vec::new() })),
+        }
+    }
+
+    /// Advertise this output to the clients, with a `wl_output` global of the highest version
+    /// supported
+    pub fn create_global<D>(&self, display: &Display<D>) -> GlobalId
+    where
+        D: GlobalDispatch<wl_output::WlOutput, GlobalData = Output>
+            + Dispatch<wl_output::WlOutput, UserData = OutputData>
+            + 'static,
+    {
+        display.create_global::<wl_output::WlOutput>(
+            wl_output::WlOutput::interface().version,
+            self.clone(),
+        )
+    }
+
+    /// The name of this output
+    pub fn name(&self) -> String {
+        self.inner.lock().unwrap().name.clone()
+    }
+
+    /// The current properties of this output
+    pub fn properties(&self) -> OutputProperties {
+        self.inner.lock().unwrap().properties.clone()
+    }
+
+    /// Change the properties of this output
+    ///
+    /// The events describing the changed properties are sent to all the resources of this output,
+    /// followed by a `done` event if their version supports it.
+    pub fn update(&self, dh: &mut DisplayHandle<'_>, f: impl FnOnce(&mut OutputProperties)) {
+        let mut inner = self.inner.lock().unwrap();
+        let old = inner.properties.clone();
+        f(&mut inner.properties);
+        for output in &inner.resources {
+            send_changes(dh, output, &old, &inner.properties);
+        }
+    }
+
+    /// Check whether given `wl_output` is a resource of this output
+    pub fn owns(&self, output: &wl_output::WlOutput) -> bool {
+        output
+            .data::<OutputData>()
+            .map(|data| Weak::ptr_eq(&data.output, &Arc::downgrade(&self.inner)))
+            .unwrap_or(false)
+    }
+
+    /// The output of given `wl_output`, if it is managed by an [`OutputManager`] and still exists
+    pub fn from_resource(output: &wl_output::WlOutput) -> Option<Output> {
+        let inner = output.data::<OutputData>()?.output.upgrade()?;
+        Some(Output { inner })
+    }
+}
+
+/// The user data of the `wl_output`s of an [`Output`]
+#[derive(Debug)]
+pub struct OutputData {
+    // weak, as the output keeps its resources
+    output: Weak<Mutex<OutputInner>>,
+}
+
+/// The delegate implementing `wl_output`
+///
+/// The global data of the `wl_output` globals is their [`Output`].
+#[derive(Debug)]
+pub struct OutputManager;
+
+impl DelegateDispatchBase<wl_output::WlOutput> for OutputManager {
+    type UserData = OutputData;
+}
+
+impl DelegateGlobalDispatchBase<wl_output::WlOutput> for OutputManager {
+    type GlobalData = Output;
+}
+
+impl<D> DelegateGlobalDispatch<wl_output::WlOutput, D> for OutputManager
+where
+    D: GlobalDispatch<wl_output::WlOutput, GlobalData = Output>
+        + Dispatch<wl_output::WlOutput, UserData = OutputData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        dhandle: &mut DisplayHandle<'_>,
+        _client: &Client,
+        resource: New<wl_output::WlOutput>,
+        global_data: &Output,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let output =
+            data_init.init(resource, OutputData { output: Arc::downgrade(&global_data.inner) });
+        let mut inner = global_data.inner.lock().unwrap();
+        send_all(dhandle, &output, &inner.name, &inner.properties);
+        inner.resources.push(output);
+    }
+}
+
+impl<D> DelegateDispatch<wl_output::WlOutput, D> for OutputManager
+where
+    D: Dispatch<wl_output::WlOutput, UserData = OutputData> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &wl_output::WlOutput,
+        _request: wl_output::Request,
+        _data: &OutputData,
+        _dhandle: &mut DisplayHandle<'_>,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // the only request is the destructor
+    }
+
+    fn destroyed(_client_id: ClientId, resource: ObjectId, data: &OutputData) {
+        if let Some(inner) = data.output.upgrade() {
+            inner.lock().unwrap().resources.retain(|output| output.id() != resource);
+        }
+    }
+}
+
+// The complete description of an output, sent when it is bound
+fn send_all(
+    dh: &mut DisplayHandle<'_>,
+    output: &wl_output::WlOutput,
+    name: &str,
+    properties: &OutputProperties,
+) {
+    send_geometry(dh, output, properties);
+    let current = properties.current_mode.iter();
+    for &mode in properties.modes.iter().chain(current.filter(|m| !properties.modes.contains(m))) {
+        send_mode(dh, output, properties, mode);
+    }
+    if output.version() >= 2 {
+        output.scale(dh, properties.scale);
+    }
+    if output.version() >= 4 {
+        output.name(dh, name.into());
+        output.description(dh, properties.description.clone());
+    }
+    if output.version() >= 2 {
+        output.done(dh);
+    }
+}
+
+// The events describing the properties which changed, followed by `done` if any was sent
+fn send_changes(
+    dh: &mut DisplayHandle<'_>,
+    output: &wl_output::WlOutput,
+    old: &OutputProperties,
+    new: &OutputProperties,
+) {
+    let mut changed = false;
+    if (&old.location, &old.physical_size, &old.subpixel, &old.make, &old.model, &old.transform)
+        != (&new.location, &new.physical_size, &new.subpixel, &new.make, &new.model, &new.transform)
+    {
+        send_geometry(dh, output, new);
+        changed = true;
+    }
+    // the modes which were added or whose flags changed
+    let current = new.current_mode.iter();
+    for &mode in new.modes.iter().chain(current.filter(|m| !new.modes.contains(m))) {
+        let known = old.modes.contains(&mode) || old.current_mode == Some(mode);
+        if !known || old.mode_flags(mode) != new.mode_flags(mode) {
+            send_mode(dh, output, new, mode);
+            changed = true;
+        }
+    }
+    if old.scale != new.scale && output.version() >= 2 {
+        output.scale(dh, new.scale);
+        changed = true;
+    }
+    if old.description != new.description && output.version() >= 4 {
+        output.description(dh, new.description.clone());
+        changed = true;
+    }
+    if changed && output.version() >= 2 {
+        output.done(dh);
+    }
+}
+
+fn send_geometry(
+    dh: &mut DisplayHandle<'_>,
+    output: &wl_output::WlOutput,
+    properties: &OutputProperties,
+) {
+    output.geometry(
+        dh,
+        properties.location.0,
+        properties.location.1,
+        properties.physical_size.0,
+        properties.physical_size.1,
+        properties.subpixel,
+        properties.make.clone(),
+        properties.model.clone(),
+        properties.transform,
+    );
+}
+
+fn send_mode(
+    dh: &mut DisplayHandle<'_>,
+    output: &wl_output::WlOutput,
+    properties: &OutputProperties,
+    mode: Mode,
+) {
+    output.mode(dh, properties.mode_flags(mode), mode.width, mode.height, mode.refresh);
+}
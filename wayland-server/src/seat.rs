@@ -0,0 +1,679 @@
+//! Server-side seats
+//!
+//! A [`Seat`] advertises a `wl_seat` global with its capabilities, and keeps track of the
+//! keyboards, pointers and touch devices created by the clients from it. It sends the input
+//! events of the compositor to the devices of the client owning the focused surface, checking the
+//! focus invariants with a [`FocusTracker`](crate::focus::FocusTracker), and shares the keymap of
+//! the keyboards through a sealed memory file.
+//!
+//! The compositor delegates `wl_seat`, `wl_keyboard`, `wl_pointer` and `wl_touch` to
+//! [`SeatManager`]:
+//!
+//! ```
+//! use wayland_server::{
+//!     delegate_dispatch, delegate_global_dispatch,
+//!     protocol::{wl_keyboard, wl_pointer, wl_seat, wl_touch},
+//!     seat::{Seat, SeatManager},
+//!     Display,
+//! };
+//!
+//! struct State;
+//!
+//! delegate_global_dispatch!(State: [wl_seat::WlSeat] => SeatManager);
+//! delegate_dispatch!(State: [
+//!     wl_seat::WlSeat,
+//!     wl_keyboard::WlKeyboard,
+//!     wl_pointer::WlPointer,
+//!     wl_touch::WlTouch
+//! ] => SeatManager);
+//!
+//! let display = Display::<State>::new().unwrap();
+//! let seat = Seat::new("seat0".into());
+//! seat.create_global(&display);
+//! seat.set_capabilities(
+//!     &mut display.handle(),
+//!     wl_seat::Capability::Keyboard | wl_seat::Capability::Pointer,
+//! );
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    sync::{Arc, Mutex, Weak},
+};
+
+use wayland_backend::server::{ClientId, GlobalId, ObjectId};
+
+use crate::{
+    focus::FocusTracker,
+    protocol::{wl_keyboard, wl_pointer, wl_seat, wl_surface, wl_touch},
+    Client, DataInit, DelegateDispatch, DelegateDispatchBase, DelegateGlobalDispatch,
+    DelegateGlobalDispatchBase, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+/// The cursor image requested by the client having the pointer focus
+#[derive(Debug, Clone, PartialEq)]
+pub enum CursorImage {
+    /// The cursor must be hidden
+    Hidden,
+    /// The cursor must be drawn with given surface
+    Surface {
+        /// The surface of the cursor
+        surface: wl_surface::WlSurface,
+        /// The position of the pointer in the surface
+        hotspot: (i32, i32),
+    },
+}
+
+/// A seat advertised to the clients
+///
+/// It can be cloned, all clones refer to the same seat.
+#[derive(Debug, Clone)]
+pub struct Seat {
+    inner: Arc<Mutex<SeatInner>>,
+}
+
+#[derive(Debug)]
+struct SeatInner {
+    name: String,
+    capabilities: wl_seat::Capability,
+    // the capabilities the seat ever had, allowing to create the devices
+    ever_capabilities: wl_seat::Capability,
+    seats: Vec<wl_seat::WlSeat>,
+    keyboards: Vec<wl_keyboard::WlKeyboard>,
+    pointers: Vec<wl_pointer::WlPointer>,
+    touches: Vec<wl_touch::WlTouch>,
+    keymap: Option<(File, u32)>,
+    repeat_info: (i32, i32),
+    keyboard_focus: FocusTracker,
+    pointer_focus: FocusTracker,
+    pointer_enter_serial: Option<u32>,
+    cursor_image: Option<CursorImage>,
+}
+
+impl Seat {
+    /// Create a seat with given name and no capabilities
+    pub fn new(name: String) -> Seat {
+        Seat {
+            inner: Arc::new(Mutex::new(SeatInner {
+                name,
+                capabilities: wl_seat::Capability::empty(),
+                ever_capabilities: wl_seat::Capability::empty(),
+                seats: Vec::new(),
+                keyboards: Vec::new(),
+                pointers: Vec::new(),
+                touches: Vec::new(),
+                keymap: None,
+                repeat_info: (25, 600),
+                keyboard_focus: FocusTracker::new(),
+                pointer_focus: FocusTracker::new(),
+                pointer_enter_serial: None,
+                cursor_image: None,
+            })),
+        }
+    }
+
+    /// Advertise this seat to the clients, with a `wl_seat` global of the highest version
+    /// supported
+    pub fn create_global<D>(&self, display: &Display<D>) -> GlobalId
+    where
+        D: GlobalDispatch<wl_seat::WlSeat, GlobalData = Seat>
+            + Dispatch<wl_seat::WlSeat, UserData = SeatData>
+            + 'static,
+    {
+        display.create_global::<wl_seat::WlSeat>(wl_seat::WlSeat::interface().version, self.clone())
+    }
+
+    /// The name of this seat
+    pub fn name(&self) -> String {
+        self.inner.lock().unwrap().name.clone()
+    }
+
+    /// The capabilities of this seat
+    pub fn capabilities(&self) -> wl_seat::Capability {
+        self.inner.lock().unwrap().capabilities
+    }
+
+    /// Change the capabilities of this seat, and advertise them to the clients
+    pub fn set_capabilities(&self, dh: &mut DisplayHandle<'_>, capabilities: wl_seat::Capability) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.capabilities = capabilities;
+        inner.ever_capabilities |= capabilities;
+        for seat in &inner.seats {
+            seat.capabilities(dh, capabilities);
+        }
+    }
+
+    /// Set the XKB keymap of the keyboards of this seat
+    ///
+    /// The keymap is stored in a sealed memory file, which is sent to the existing keyboards and
+    /// to the keyboards created afterwards.
+    pub fn set_keymap(&self, dh: &mut DisplayHandle<'_>, keymap: &str) -> io::Result<()> {
+        let file = keymap_file(keymap)?;
+        let size = keymap.len() as u32 + 1;
+        let mut inner = self.inner.lock().unwrap();
+        for keyboard in &inner.keyboards {
+            keyboard.keymap(dh, wl_keyboard::KeymapFormat::XkbV1, file.as_raw_fd(), size);
+        }
+        inner.keymap = Some((file, size));
+        Ok(())
+    }
+
+    /// Set the key repeat rate (in characters per second) and delay (in milliseconds)
+    ///
+    /// It is sent to the keyboards supporting it, the default is a rate of 25 and a delay of 600.
+    pub fn set_repeat_info(&self, dh: &mut DisplayHandle<'_>, rate: i32, delay: i32) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.repeat_info = (rate, delay);
+        for keyboard in &inner.keyboards {
+            if keyboard.version() >= wl_keyboard::EVT_REPEAT_INFO_SINCE {
+                keyboard.repeat_info(dh, rate, delay);
+            }
+        }
+    }
+
+    /// The keyboards created by the client owning given surface
+    pub fn keyboards(&self, surface: &wl_surface::WlSurface) -> Vec<wl_keyboard::WlKeyboard> {
+        client_resources(&self.inner.lock().unwrap().keyboards, surface)
+    }
+
+    /// The pointers created by the client owning given surface
+    pub fn pointers(&self, surface: &wl_surface::WlSurface) -> Vec<wl_pointer::WlPointer> {
+        client_resources(&self.inner.lock().unwrap().pointers, surface)
+    }
+
+    /// The touch devices created by the client owning given surface
+    pub fn touches(&self, surface: &wl_surface::WlSurface) -> Vec<wl_touch::WlTouch> {
+        client_resources(&self.inner.lock().unwrap().touches, surface)
+    }
+
+    /// The surface having the keyboard focus, if any
+    pub fn keyboard_focus(&self) -> Option<wl_surface::WlSurface> {
+        self.inner.lock().unwrap().keyboard_focus.current().cloned()
+    }
+
+    /// The surface having the pointer focus, if any
+    pub fn pointer_focus(&self) -> Option<wl_surface::WlSurface> {
+        self.inner.lock().unwrap().pointer_focus.current().cloned()
+    }
+
+    /// The cursor image last requested by the client having the pointer focus
+    ///
+    /// `None` if the client did not request any since the pointer entered its surface.
+    pub fn cursor_image(&self) -> Option<CursorImage> {
+        self.inner.lock().unwrap().cursor_image.clone()
+    }
+
+    /// Forget the focus of a destroyed surface
+    pub fn surface_destroyed(&self, surface: &wl_surface::WlSurface) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.keyboard_focus.surface_destroyed(surface);
+        if inner.pointer_focus.surface_destroyed(surface) {
+            inner.pointer_enter_serial = None;
+            inner.cursor_image = None;
+        }
+    }
+
+    /// Give the keyboard focus to given surface, with the keys currently pressed
+    ///
+    /// The previously focused surface must have been left with
+    /// [`keyboard_leave()`](Seat::keyboard_leave).
+    pub fn keyboard_enter(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        surface: &wl_surface::WlSurface,
+        serial: u32,
+        keys: &[u32],
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.keyboard_focus.enter(dh, surface, serial);
+        let keys = keys.iter().flat_map(|key| key.to_ne_bytes()).collect::<Vec<u8>>();
+        for keyboard in client_resources(&inner.keyboards, surface) {
+            keyboard.enter(dh, serial, surface, keys.clone());
+        }
+    }
+
+    /// Remove the keyboard focus from the focused surface, if any
+    pub fn keyboard_leave(&self, dh: &mut DisplayHandle<'_>, serial: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        let focus = match inner.keyboard_focus.current() {
+            Some(focus) => focus.clone(),
+            None => return,
+        };
+        inner.keyboard_focus.leave(&focus, serial);
+        for keyboard in client_resources(&inner.keyboards, &focus) {
+            keyboard.leave(dh, serial, &focus);
+        }
+    }
+
+    /// Send a key event to the keyboards of the focused client, if any
+    pub fn keyboard_key(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        serial: u32,
+        time: u32,
+        key: u32,
+        state: wl_keyboard::KeyState,
+    ) {
+        self.for_each_focused_keyboard(|keyboard| keyboard.key(dh, serial, time, key, state));
+    }
+
+    /// Send the state of the modifiers to the keyboards of the focused client, if any
+    pub fn keyboard_modifiers(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        serial: u32,
+        depressed: u32,
+        latched: u32,
+        locked: u32,
+        group: u32,
+    ) {
+        self.for_each_focused_keyboard(|keyboard| {
+            keyboard.modifiers(dh, serial, depressed, latched, locked, group)
+        });
+    }
+
+    /// Give the pointer focus to given surface, at given position in surface coordinates
+    ///
+    /// The previously focused surface must have been left with
+    /// [`pointer_leave()`](Seat::pointer_leave).
+    pub fn pointer_enter(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        surface: &wl_surface::WlSurface,
+        serial: u32,
+        x: f64,
+        y: f64,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.pointer_focus.enter(dh, surface, serial);
+        inner.pointer_enter_serial = Some(serial);
+        inner.cursor_image = None;
+        for pointer in client_resources(&inner.pointers, surface) {
+            pointer.enter(dh, serial, surface, x, y);
+        }
+    }
+
+    /// Remove the pointer focus from the focused surface, if any
+    pub fn pointer_leave(&self, dh: &mut DisplayHandle<'_>, serial: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        let focus = match inner.pointer_focus.current() {
+            Some(focus) => focus.clone(),
+            None => return,
+        };
+        inner.pointer_focus.leave(&focus, serial);
+        inner.pointer_enter_serial = None;
+        inner.cursor_image = None;
+        for pointer in client_resources(&inner.pointers, &focus) {
+            pointer.leave(dh, serial, &focus);
+        }
+    }
+
+    /// Send a motion event to the pointers of the focused client, if any
+    pub fn pointer_motion(&self, dh: &mut DisplayHandle<'_>, time: u32, x: f64, y: f64) {
+        self.for_each_focused_pointer(|pointer| pointer.motion(dh, time, x, y));
+    }
+
+    /// Send a button event to the pointers of the focused client, if any
+    pub fn pointer_button(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        serial: u32,
+        time: u32,
+        button: u32,
+        state: wl_pointer::ButtonState,
+    ) {
+        self.for_each_focused_pointer(|pointer| pointer.button(dh, serial, time, button, state));
+    }
+
+    /// Send an axis event to the pointers of the focused client, if any
+    pub fn pointer_axis(
+        &self,
+        dh: &mut DisplayHandle<'_>,
+        time: u32,
+        axis: wl_pointer::Axis,
+        value: f64,
+    ) {
+        self.for_each_focused_pointer(|pointer| pointer.axis(dh, time, axis, value));
+    }
+
+    /// Terminate a group of pointer events, for the pointers of the focused client supporting it
+    pub fn pointer_frame(&self, dh: &mut DisplayHandle<'_>) {
+        self.for_each_focused_pointer(|pointer| {
+            if pointer.version() >= wl_pointer::EVT_FRAME_SINCE {
+                pointer.frame(dh);
+            }
+        });
+    }
+
+    fn for_each_focused_keyboard(&self, mut f: impl FnMut(&wl_keyboard::WlKeyboard)) {
+        let inner = self.inner.lock().unwrap();
+        if let Some(focus) = inner.keyboard_focus.current() {
+            client_resources(&inner.keyboards, focus).iter().for_each(&mut f);
+        }
+    }
+
+    fn for_each_focused_pointer(&self, mut f: impl FnMut(&wl_pointer::WlPointer)) {
+        let inner = self.inner.lock().unwrap();
+        if let Some(focus) = inner.pointer_focus.current() {
+            client_resources(&inner.pointers, focus).iter().for_each(&mut f);
+        }
+    }
+}
+
+/// The user data of the `wl_seat`s of a [`Seat`] and of the devices created from them
+#[derive(Debug)]
+pub struct SeatData {
+    // weak, as the seat keeps its resources
+    seat: Weak<Mutex<SeatInner>>,
+}
+
+/// The delegate implementing `wl_seat`, `wl_keyboard`, `wl_pointer` and `wl_touch`
+///
+/// The global data of the `wl_seat` globals is their [`Seat`].
+#[derive(Debug)]
+pub struct SeatManager;
+
+impl DelegateDispatchBase<wl_seat::WlSeat> for SeatManager {
+    type UserData = SeatData;
+}
+
+impl DelegateGlobalDispatchBase<wl_seat::WlSeat> for SeatManager {
+    type GlobalData = Seat;
+}
+
+impl<D> DelegateGlobalDispatch<wl_seat::WlSeat, D> for SeatManager
+where
+    D: GlobalDispatch<wl_seat::WlSeat, GlobalData = Seat>
+        + Dispatch<wl_seat::WlSeat, UserData = SeatData>
+        + Dispatch<wl_keyboard::WlKeyboard, UserData = SeatData>
+        + Dispatch<wl_pointer::WlPointer, UserData = SeatData>
+        + Dispatch<wl_touch::WlTouch, UserData = SeatData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        dhandle: &mut DisplayHandle<'_>,
+        _client: &Client,
+        resource: New<wl_seat::WlSeat>,
+        global_data: &Seat,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let seat = data_init.init(resource, SeatData { seat: Arc::downgrade(&global_data.inner) });
+        let mut inner = global_data.inner.lock().unwrap();
+        seat.capabilities(dhandle, inner.capabilities);
+        if seat.version() >= wl_seat::EVT_NAME_SINCE {
+            seat.name(dhandle, inner.name.clone());
+        }
+        inner.seats.push(seat);
+    }
+}
+
+impl<D> DelegateDispatch<wl_seat::WlSeat, D> for SeatManager
+where
+    D: Dispatch<wl_seat::WlSeat, UserData = SeatData>
+        + Dispatch<wl_keyboard::WlKeyboard, UserData = SeatData>
+        + Dispatch<wl_pointer::WlPointer, UserData = SeatData>
+        + Dispatch<wl_touch::WlTouch, UserData = SeatData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        seat: &wl_seat::WlSeat,
+        request: wl_seat::Request,
+        data: &SeatData,
+        dhandle: &mut DisplayHandle<'_>,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let inner = data.seat.upgrade();
+        let mut inner = inner.as_ref().map(|inner| inner.lock().unwrap());
+        let required = match request {
+            wl_seat::Request::GetKeyboard { .. } => {
+                Some((wl_seat::Capability::Keyboard, "keyboard"))
+            }
+            wl_seat::Request::GetPointer { .. } => Some((wl_seat::Capability::Pointer, "pointer")),
+            wl_seat::Request::GetTouch { .. } => Some((wl_seat::Capability::Touch, "touch")),
+            wl_seat::Request::Release => None,
+        };
+        let missing = required.and_then(|(capability, name)| {
+            let advertised = inner
+                .as_ref()
+                .map(|inner| inner.ever_capabilities.contains(capability))
+                .unwrap_or(false);
+            if advertised {
+                None
+            } else {
+                Some(name)
+            }
+        });
+        if let Some(capability) = missing {
+            seat.post_error(
+                dhandle,
+                wl_seat::Error::MissingCapability,
+                format!("the seat never had the {} capability", capability),
+            );
+        }
+
+        let device_data = || SeatData { seat: data.seat.clone() };
+        match request {
+            wl_seat::Request::GetKeyboard { id } => {
+                let keyboard = data_init.init(id, device_data());
+                if let Some(ref mut inner) = inner {
+                    if let Some((ref file, size)) = inner.keymap {
+                        keyboard.keymap(
+                            dhandle,
+                            wl_keyboard::KeymapFormat::XkbV1,
+                            file.as_raw_fd(),
+                            size,
+                        );
+                    }
+                    if keyboard.version() >= wl_keyboard::EVT_REPEAT_INFO_SINCE {
+                        keyboard.repeat_info(dhandle, inner.repeat_info.0, inner.repeat_info.1);
+                    }
+                    inner.keyboards.push(keyboard);
+                }
+            }
+            wl_seat::Request::GetPointer { id } => {
+                let pointer = data_init.init(id, device_data());
+                if let Some(ref mut inner) = inner {
+                    inner.pointers.push(pointer);
+                }
+            }
+            wl_seat::Request::GetTouch { id } => {
+                let touch = data_init.init(id, device_data());
+                if let Some(ref mut inner) = inner {
+                    inner.touches.push(touch);
+                }
+            }
+            wl_seat::Request::Release => {}
+        }
+    }
+
+    fn destroyed(_client_id: ClientId, resource: ObjectId, data: &SeatData) {
+        forget_resource(data, resource);
+    }
+}
+
+impl DelegateDispatchBase<wl_keyboard::WlKeyboard> for SeatManager {
+    type UserData = SeatData;
+}
+
+impl<D> DelegateDispatch<wl_keyboard::WlKeyboard, D> for SeatManager
+where
+    D: Dispatch<wl_keyboard::WlKeyboard, UserData = SeatData> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _keyboard: &wl_keyboard::WlKeyboard,
+        _request: wl_keyboard::Request,
+        _data: &SeatData,
+        _dhandle: &mut DisplayHandle<'_>,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // the only request is the destructor
+    }
+
+    fn destroyed(_client_id: ClientId, resource: ObjectId, data: &SeatData) {
+        forget_resource(data, resource);
+    }
+}
+
+impl DelegateDispatchBase<wl_pointer::WlPointer> for SeatManager {
+    type UserData = SeatData;
+}
+
+impl<D> DelegateDispatch<wl_pointer::WlPointer, D> for SeatManager
+where
+    D: Dispatch<wl_pointer::WlPointer, UserData = SeatData> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        pointer: &wl_pointer::WlPointer,
+        request: wl_pointer::Request,
+        data: &SeatData,
+        _dhandle: &mut DisplayHandle<'_>,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        if let wl_pointer::Request::SetCursor { serial, surface, hotspot_x, hotspot_y } = request {
+            let inner = match data.seat.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let mut inner = inner.lock().unwrap();
+            // only the client having the focus can set the cursor, in response to the last enter
+            let focused = inner
+                .pointer_focus
+                .current()
+                .map(|focus| focus.id().same_client_as(&pointer.id()))
+                .unwrap_or(false);
+            if !focused || inner.pointer_enter_serial != Some(serial) {
+                return;
+            }
+            inner.cursor_image = Some(match surface {
+                Some(surface) => CursorImage::Surface { surface, hotspot: (hotspot_x, hotspot_y) },
+                None => CursorImage::Hidden,
+            });
+        }
+    }
+
+    fn destroyed(_client_id: ClientId, resource: ObjectId, data: &SeatData) {
+        forget_resource(data, resource);
+    }
+}
+
+impl DelegateDispatchBase<wl_touch::WlTouch> for SeatManager {
+    type UserData = SeatData;
+}
+
+impl<D> DelegateDispatch<wl_touch::WlTouch, D> for SeatManager
+where
+    D: Dispatch<wl_touch::WlTouch, UserData = SeatData> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _touch: &wl_touch::WlTouch,
+        _request: wl_touch::Request,
+        _data: &SeatData,
+        _dhandle: &mut DisplayHandle<'_>,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // the only request is the destructor
+    }
+
+    fn destroyed(_client_id: ClientId, resource: ObjectId, data: &SeatData) {
+        forget_resource(data, resource);
+    }
+}
+
+// Remove a destroyed resource from the lists of its seat
+fn forget_resource(data: &SeatData, resource: ObjectId) {
+    if let Some(inner) = data.seat.upgrade() {
+        let mut inner = inner.lock().unwrap();
+        inner.seats.retain(|seat| seat.id() != resource);
+        inner.keyboards.retain(|keyboard| keyboard.id() != resource);
+        inner.pointers.retain(|pointer| pointer.id() != resource);
+        inner.touches.retain(|touch| touch.id() != resource);
+    }
+}
+
+// The resources belonging to the client owning given surface
+fn client_resources<I: Resource + Clone>(
+    resources: &[I],
+    surface: &wl_surface::WlSurface,
+) -> Vec<I> {
+    resources.iter().filter(|r| r.id().same_client_as(&surface.id())).cloned().collect()
+}
+
+/// Create a sealed memory file containing a keymap, terminated by a nul byte
+fn keymap_file(keymap: &str) -> io::Result<File> {
+    let mut file = unsafe { File::from_raw_fd(create_memory_fd()?) };
+    file.write_all(keymap.as_bytes())?;
+    file.write_all(&[0])?;
+    #[cfg(target_os = "linux")]
+    {
+        use nix::fcntl::{fcntl, FcntlArg, SealFlag};
+        let _ = fcntl(
+            file.as_raw_fd(),
+            FcntlArg::F_ADD_SEALS(
+                SealFlag::F_SEAL_SHRINK
+                    | SealFlag::F_SEAL_GROW
+                    | SealFlag::F_SEAL_WRITE
+                    | SealFlag::F_SEAL_SEAL,
+            ),
+        );
+    }
+    Ok(file)
+}
+
+/// Create a file descriptor in memory
+fn create_memory_fd() -> io::Result<RawFd> {
+    use nix::{errno::Errno, fcntl::OFlag, sys::mman, sys::stat::Mode, unistd};
+
+    // Only try memfd on linux
+    #[cfg(target_os = "linux")]
+    loop {
+        match nix::sys::memfd::memfd_create(
+            std::ffi::CStr::from_bytes_with_nul(b"wayland-server-keymap\0").unwrap(),
+            nix::sys::memfd::MemFdCreateFlag::MFD_CLOEXEC
+                | nix::sys::memfd::MemFdCreateFlag::MFD_ALLOW_SEALING,
+        ) {
+            Ok(fd) => return Ok(fd),
+            Err(Errno::EINTR) => continue,
+            Err(Errno::ENOSYS) => break,
+            Err(errno) => return Err(errno.into()),
+        }
+    }
+
+    // Fallback to using shm_open
+    loop {
+        let name = format!(
+            "/wayland-server-keymap-{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .subsec_nanos()
+        );
+        match mman::shm_open(
+            name.as_str(),
+            OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_RDWR | OFlag::O_CLOEXEC,
+            Mode::S_IRUSR | Mode::S_IWUSR,
+        ) {
+            Ok(fd) => {
+                return match mman::shm_unlink(name.as_str()) {
+                    Ok(()) => Ok(fd),
+                    Err(errno) => {
+                        let _ = unistd::close(fd);
+                        Err(errno.into())
+                    }
+                }
+            }
+            Err(Errno::EEXIST) | Err(Errno::EINTR) => continue,
+            Err(errno) => return Err(errno.into()),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Allocation and validation of serials
+//!
+//! The serials sent in events are later given back by the clients in their requests, to prove
+//! that a grab answers a recent input event, or to acknowledge a configure. The [`SerialCounter`]
+//! of a display, from [`Display::serial_counter()`](crate::Display::serial_counter), allocates the
+//! serials of the whole display, labelling each with the kind of event it was allocated for and
+//! the client it was sent to. It keeps the latest of them, so that the serials provided by clients
+//! can be checked against this history.
+//!
+//! ```
+//! use wayland_server::serial::{SerialCounter, SerialLabel};
+//!
+//! let counter = SerialCounter::new();
+//! let serial = counter.next_serial(SerialLabel::PointerButton, None);
+//! // a client starting an interactive move with the serial of the button press
+//! let grab = [SerialLabel::PointerButton, SerialLabel::TouchDown];
+//! assert!(counter.validate(serial, None, &grab).is_ok());
+//! // a client acknowledging a configure with it
+//! assert!(counter.validate(serial, None, &[SerialLabel::Configure]).is_err());
+//! ```
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use wayland_backend::server::ClientId;
+
+/// The number of serials kept by default in the history of a [`SerialCounter`]
+pub const DEFAULT_HISTORY_DEPTH: usize = 256;
+
+/// The kind of event a serial was allocated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialLabel {
+    /// `wl_pointer.enter`
+    PointerEnter,
+    /// `wl_pointer.button`
+    PointerButton,
+    /// `wl_keyboard.enter`
+    KeyboardEnter,
+    /// `wl_keyboard.key`
+    Key,
+    /// `wl_touch.down`
+    TouchDown,
+    /// A configure event, like `xdg_surface.configure`
+    Configure,
+    /// Any other event, named by the compositor
+    Other(&'static str),
+}
+
+/// A serial allocated by a [`SerialCounter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialRecord {
+    /// the serial
+    pub serial: u32,
+    /// the kind of event it was allocated for
+    pub label: SerialLabel,
+    /// the client it was sent to, `None` if it is not specific to a client
+    pub client: Option<ClientId>,
+}
+
+/// A serial provided by a client which does not match the history of the [`SerialCounter`]
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SerialError {
+    /// The serial was never allocated, or is older than the history
+    #[error("Serial {0} is not a recent serial")]
+    Unknown(u32),
+    /// The serial was sent to another client
+    #[error("Serial {0} was not sent to this client")]
+    WrongClient(u32),
+    /// The serial was allocated for an event not allowing the request
+    #[error("Serial {serial} was allocated for an event of kind {label:?}")]
+    WrongLabel {
+        /// the serial
+        serial: u32,
+        /// the kind of event it was allocated for
+        label: SerialLabel,
+    },
+}
+
+/// The serial allocator of a display
+///
+/// It is cheaply cloneable, the clones sharing the same counter and history. Serials start at 1
+/// and skip 0 when wrapping around, so that 0 can keep its usual meaning of "no serial".
+#[derive(Debug, Clone)]
+pub struct SerialCounter {
+    inner: Arc<Mutex<CounterInner>>,
+}
+
+#[derive(Debug)]
+struct CounterInner {
+    last: u32,
+    history: VecDeque<SerialRecord>,
+    depth: usize,
+}
+
+impl Default for SerialCounter {
+    fn default() -> SerialCounter {
+        SerialCounter::new()
+    }
+}
+
+impl SerialCounter {
+    /// Create a new counter, keeping the [`DEFAULT_HISTORY_DEPTH`] latest serials
+    pub fn new() -> SerialCounter {
+        SerialCounter::with_history_depth(DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Create a new counter, keeping the given number of latest serials
+    pub fn with_history_depth(depth: usize) -> SerialCounter {
+        SerialCounter {
+            inner: Arc::new(Mutex::new(CounterInner {
+                last: 0,
+                history: VecDeque::with_capacity(depth),
+                depth,
+            })),
+        }
+    }
+
+    /// Change the number of latest serials kept in the history
+    pub fn set_history_depth(&self, depth: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.depth = depth;
+        let excess = inner.history.len().saturating_sub(depth);
+        inner.history.drain(..excess);
+    }
+
+    /// Allocate a new serial for an event
+    ///
+    /// `client` is the client the event is sent to, if the serial is specific to one. The serials
+    /// sent to several clients, like the ones of input events delivered to the clients of
+    /// subsurfaces, should not be specific.
+    pub fn next_serial(&self, label: SerialLabel, client: Option<ClientId>) -> u32 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.last = match inner.last.wrapping_add(1) {
+            0 => 1,
+            serial => serial,
+        };
+        let serial = inner.last;
+        if inner.depth > 0 {
+            if inner.history.len() == inner.depth {
+                inner.history.pop_front();
+            }
+            inner.history.push_back(SerialRecord { serial, label, client });
+        }
+        serial
+    }
+
+    /// The last allocated serial, if any
+    pub fn last_serial(&self) -> Option<u32> {
+        match self.inner.lock().unwrap().last {
+            0 => None,
+            serial => Some(serial),
+        }
+    }
+
+    /// Get the record of a serial, if it is in the history
+    pub fn get(&self, serial: u32) -> Option<SerialRecord> {
+        let inner = self.inner.lock().unwrap();
+        inner.history.iter().rev().find(|record| record.serial == serial).cloned()
+    }
+
+    /// Check a serial provided by a client against the history
+    ///
+    /// The serial must be in the history, and have been allocated for an event with one of the
+    /// `allowed` labels. If `client` is given and the serial was sent to a specific client, they
+    /// must be the same.
+    pub fn validate(
+        &self,
+        serial: u32,
+        client: Option<&ClientId>,
+        allowed: &[SerialLabel],
+    ) -> Result<SerialRecord, SerialError> {
+        let record = self.get(serial).ok_or(SerialError::Unknown(serial))?;
+        if let (Some(expected), Some(client)) = (&record.client, client) {
+            if expected != client {
+                return Err(SerialError::WrongClient(serial));
+            }
+        }
+        if !allowed.contains(&record.label) {
+            return Err(SerialError::WrongLabel { serial, label: record.label });
+        }
+        Ok(record)
+    }
+
+    /// Check that a serial is the latest one allocated for a label and a client
+    ///
+    /// This checks for example that a client acknowledges the last configure it was sent. The
+    /// later serials with other labels or for other clients are ignored.
+    pub fn is_latest(&self, serial: u32, client: Option<&ClientId>, label: SerialLabel) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .history
+            .iter()
+            .rev()
+            .find(|record| record.label == label && record.client.as_ref() == client)
+            .map(|record| record.serial)
+            == Some(serial)
+    }
+}
@@ -0,0 +1,488 @@
+//! Server-side shared memory buffers
+//!
+//! [`ShmState`] implements the `wl_shm` global and the `wl_shm_pool` and `wl_buffer` objects
+//! created from it, for compositors delegating these interfaces to it. It advertises the supported
+//! formats, validates the requests of the clients like libwayland does, and maps the memory of
+//! the pools. The contents of the buffers can then be read with [`with_buffer_contents()`].
+//!
+//! A client can truncate the file backing a pool at any time, making accesses to the mapped
+//! memory raise `SIGBUS`. [`with_buffer_contents()`] catches these faults and reports them as an
+//! error, the compositor is then expected to kill the misbehaving client.
+//!
+//! ```
+//! use wayland_server::{
+//!     delegate_dispatch, delegate_global_dispatch,
+//!     protocol::{wl_buffer, wl_shm, wl_shm_pool},
+//!     shm::ShmState,
+//!     Display,
+//! };
+//!
+//! struct State {
+//!     shm: ShmState,
+//! }
+//!
+//! impl AsMut<ShmState> for State {
+//!     fn as_mut(&mut self) -> &mut ShmState {
+//!         &mut self.shm
+//!     }
+//! }
+//!
+//! delegate_global_dispatch!(State: [wl_shm::WlShm] => ShmState);
+//! delegate_dispatch!(State: [wl_shm::WlShm, wl_shm_pool::WlShmPool, wl_buffer::WlBuffer] => ShmState);
+//!
+//! let display = Display::<State>::new().unwrap();
+//! display.create_global::<wl_shm::WlShm>(1, ());
+//! let state = State { shm: ShmState::new([wl_shm::Format::Rgb565]) };
+//! ```
+
+use std::{
+    cell::Cell,
+    os::unix::io::RawFd,
+    sync::{Arc, Once, RwLock},
+};
+
+use nix::{
+    libc,
+    sys::{
+        mman::{self, MapFlags, ProtFlags},
+        signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal},
+    },
+    unistd,
+};
+
+use crate::{
+    protocol::{wl_buffer, wl_shm, wl_shm_pool},
+    Client, DataInit, DelegateDispatch, DelegateDispatchBase, DelegateGlobalDispatch,
+    DelegateGlobalDispatchBase, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+
+/// An error accessing the contents of a buffer
+#[derive(Debug, thiserror::Error)]
+pub enum BufferAccessError {
+    /// The buffer is not a `wl_shm` buffer managed by a [`ShmState`]
+    #[error("The buffer is not a wl_shm buffer")]
+    NotShm,
+    /// The client truncated the file backing the pool of the buffer
+    #[error("The memory of the buffer is no longer accessible")]
+    BadMemory,
+}
+
+/// The delegate implementing `wl_shm`, `wl_shm_pool` and `wl_buffer`
+///
+/// The state of the compositor must implement `AsMut<ShmState>`.
+#[derive(Debug)]
+pub struct ShmState {
+    formats: Vec<wl_shm::Format>,
+}
+
+impl ShmState {
+    /// Create the state, advertising given formats
+    ///
+    /// The `argb8888` and `xrgb8888` formats, which all compositors must support, are always
+    /// advertised.
+    pub fn new(formats: impl IntoIterator<Item = wl_shm::Format>) -> ShmState {
+        let mut list = vec![wl_shm::Format::Argb8888, wl_shm::Format::Xrgb8888];
+        for format in formats {
+            if !list.contains(&format) {
+                list.push(format);
+            }
+        }
+        ShmState { formats: list }
+    }
+
+    /// The advertised formats
+    pub fn formats(&self) -> &[wl_shm::Format] {
+        &self.formats
+    }
+}
+
+/// The description of a shared memory buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferMetadata {
+    /// The width of the buffer, in pixels
+    pub width: i32,
+    /// The height of the buffer, in pixels
+    pub height: i32,
+    /// The number of bytes between the starts of two consecutive rows
+    pub stride: i32,
+    /// The format of the pixels
+    pub format: wl_shm::Format,
+}
+
+/// The user data of the `wl_shm_pool`s
+#[derive(Debug)]
+pub struct ShmPoolData {
+    pool: Arc<Pool>,
+}
+
+/// The user data of the `wl_buffer`s created from a `wl_shm_pool`
+#[derive(Debug)]
+pub struct ShmBufferData {
+    pool: Arc<Pool>,
+    offset: usize,
+    metadata: BufferMetadata,
+}
+
+impl ShmBufferData {
+    /// The description of the buffer
+    pub fn metadata(&self) -> BufferMetadata {
+        self.metadata
+    }
+}
+
+/// Access the contents of a shared memory buffer
+///
+/// The closure receives the bytes of the buffer, starting at its first pixel and spanning
+/// `stride * height` bytes, along with its description. If the client truncated the file backing
+/// its pool, the missing memory reads as zeroes and [`BufferAccessError::BadMemory`] is returned
+/// once the closure returns.
+pub fn with_buffer_contents<T>(
+    buffer: &wl_buffer::WlBuffer,
+    f: impl FnOnce(&[u8], BufferMetadata) -> T,
+) -> Result<T, BufferAccessError> {
+    let data = buffer.data::<ShmBufferData>().ok_or(BufferAccessError::NotShm)?;
+    let mapping = data.pool.mapping.read().unwrap();
+    let len = data.metadata.stride as usize * data.metadata.height as usize;
+    if len == 0 {
+        // the buffer is invalid and its client is being killed
+        return Ok(f(&[], data.metadata));
+    }
+
+    install_sigbus_handler();
+    let previous =
+        SIGBUS_GUARD.with(|guard| guard.replace((mapping.ptr as usize, mapping.len, false)));
+    // the size of the buffer was checked against the size of the pool at its creation, and pools
+    // can only grow
+    let contents = unsafe { std::slice::from_raw_parts(mapping.ptr.add(data.offset), len) };
+    let ret = f(contents, data.metadata);
+    let (_, _, faulted) = SIGBUS_GUARD.with(|guard| guard.replace(previous));
+
+    if faulted {
+        Err(BufferAccessError::BadMemory)
+    } else {
+        Ok(ret)
+    }
+}
+
+impl DelegateDispatchBase<wl_shm::WlShm> for ShmState {
+    type UserData = ();
+}
+
+impl DelegateGlobalDispatchBase<wl_shm::WlShm> for ShmState {
+    type GlobalData = ();
+}
+
+impl<D> DelegateGlobalDispatch<wl_shm::WlShm, D> for ShmState
+where
+    D: GlobalDispatch<wl_shm::WlShm, GlobalData = ()>
+        + Dispatch<wl_shm::WlShm, UserData = ()>
+        + Dispatch<wl_shm_pool::WlShmPool, UserData = ShmPoolData>
+        + AsMut<ShmState>
+        + 'static,
+{
+    fn bind(
+        state: &mut D,
+        dhandle: &mut DisplayHandle<'_>,
+        _client: &Client,
+        resource: New<wl_shm::WlShm>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let shm = data_init.init(resource, ());
+        for &format in &state.as_mut().formats {
+            shm.format(dhandle, format);
+        }
+    }
+}
+
+impl<D> DelegateDispatch<wl_shm::WlShm, D> for ShmState
+where
+    D: Dispatch<wl_shm::WlShm, UserData = ()>
+        + Dispatch<wl_shm_pool::WlShmPool, UserData = ShmPoolData>
+        + AsMut<ShmState>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        shm: &wl_shm::WlShm,
+        request: wl_shm::Request,
+        _data: &(),
+        dhandle: &mut DisplayHandle<'_>,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let wl_shm::Request::CreatePool { id, fd, size } = request;
+        // the object must be created even if the request is invalid, the client is then killed
+        let pool = if size <= 0 {
+            shm.post_error(
+                dhandle,
+                wl_shm::Error::InvalidStride,
+                format!("invalid size ({})", size),
+            );
+            Pool::invalid(fd)
+        } else {
+            match Mapping::new(fd, size as usize) {
+                Ok(mapping) => Pool { fd, mapping: RwLock::new(mapping) },
+                Err(errno) => {
+                    shm.post_error(
+                        dhandle,
+                        wl_shm::Error::InvalidFd,
+                        format!("failed mmap fd {}: {}", fd, errno),
+                    );
+                    Pool::invalid(fd)
+                }
+            }
+        };
+        data_init.init(id, ShmPoolData { pool: Arc::new(pool) });
+    }
+}
+
+impl DelegateDispatchBase<wl_shm_pool::WlShmPool> for ShmState {
+    type UserData = ShmPoolData;
+}
+
+impl<D> DelegateDispatch<wl_shm_pool::WlShmPool, D> for ShmState
+where
+    D: Dispatch<wl_shm_pool::WlShmPool, UserData = ShmPoolData>
+        + Dispatch<wl_buffer::WlBuffer, UserData = ShmBufferData>
+        + AsMut<ShmState>
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        pool: &wl_shm_pool::WlShmPool,
+        request: wl_shm_pool::Request,
+        data: &ShmPoolData,
+        dhandle: &mut DisplayHandle<'_>,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wl_shm_pool::Request::CreateBuffer { id, offset, width, height, stride, format } => {
+                let format = match format {
+                    WEnum::Value(format) if state.as_mut().formats.contains(&format) => Ok(format),
+                    other => Err(u32::from(other)),
+                };
+                let pool_size = data.pool.mapping.read().unwrap().len as i64;
+                let error = if let Err(format) = format {
+                    Some((wl_shm::Error::InvalidFormat, format!("invalid format 0x{:x}", format)))
+                } else if offset < 0
+                    || width <= 0
+                    || height <= 0
+                    || stride < width
+                    || i32::MAX / stride <= height
+                    || offset as i64 > pool_size - stride as i64 * height as i64
+                {
+                    Some((
+                        wl_shm::Error::InvalidStride,
+                        format!(
+                            "invalid width, height or stride ({}x{}, {})",
+                            width, height, stride
+                        ),
+                    ))
+                } else {
+                    None
+                };
+                let metadata = BufferMetadata {
+                    width,
+                    height,
+                    stride,
+                    format: format.unwrap_or(wl_shm::Format::Argb8888),
+                };
+                // the object must be created even if the request is invalid, the client is then
+                // killed
+                let buffer_data = match error {
+                    Some((code, message)) => {
+                        pool.post_error(dhandle, code, message);
+                        // make sure the buffer cannot be accessed
+                        ShmBufferData {
+                            pool: data.pool.clone(),
+                            offset: 0,
+                            metadata: BufferMetadata { height: 0, ..metadata },
+                        }
+                    }
+                    None => {
+                        ShmBufferData { pool: data.pool.clone(), offset: offset as usize, metadata }
+                    }
+                };
+                data_init.init(id, buffer_data);
+            }
+            wl_shm_pool::Request::Resize { size } => {
+                let mut mapping = data.pool.mapping.write().unwrap();
+                if size <= 0 || (size as usize) < mapping.len {
+                    pool.post_error(
+                        dhandle,
+                        wl_shm::Error::InvalidStride,
+                        "shrinking pool invalid".to_string(),
+                    );
+                    return;
+                }
+                if data.pool.fd < 0 {
+                    // the pool is invalid and the client is being killed
+                    return;
+                }
+                match Mapping::new(data.pool.fd, size as usize) {
+                    Ok(new_mapping) => *mapping = new_mapping,
+                    Err(errno) => {
+                        pool.post_error(
+                            dhandle,
+                            wl_shm::Error::InvalidFd,
+                            format!("failed mmap fd {}: {}", data.pool.fd, errno),
+                        );
+                    }
+                }
+            }
+            // the memory is unmapped once the pool and all its buffers are destroyed
+            wl_shm_pool::Request::Destroy => {}
+        }
+    }
+}
+
+impl DelegateDispatchBase<wl_buffer::WlBuffer> for ShmState {
+    type UserData = ShmBufferData;
+}
+
+impl<D> DelegateDispatch<wl_buffer::WlBuffer, D> for ShmState
+where
+    D: Dispatch<wl_buffer::WlBuffer, UserData = ShmBufferData> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _buffer: &wl_buffer::WlBuffer,
+        _request: wl_buffer::Request,
+        _data: &ShmBufferData,
+        _dhandle: &mut DisplayHandle<'_>,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // the only request is the destructor
+    }
+}
+
+// The memory of a pool, unmapped and closed once the pool and all its buffers are destroyed
+#[derive(Debug)]
+struct Pool {
+    fd: RawFd,
+    mapping: RwLock<Mapping>,
+}
+
+impl Pool {
+    fn invalid(fd: RawFd) -> Pool {
+        let _ = unistd::close(fd);
+        Pool { fd: -1, mapping: RwLock::new(Mapping { ptr: std::ptr::null_mut(), len: 0 }) }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        if self.fd >= 0 {
+            let _ = unistd::close(self.fd);
+        }
+    }
+}
+
+// A read-only shared mapping of a whole pool, unmapped when dropped
+#[derive(Debug)]
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The mapping is only read, and is only replaced while no buffer is being accessed
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(fd: RawFd, len: usize) -> nix::Result<Mapping> {
+        let ptr = unsafe {
+            mman::mmap(
+                std::ptr::null_mut(),
+                len,
+                ProtFlags::PROT_READ,
+                MapFlags::MAP_SHARED,
+                fd,
+                0,
+            )?
+        };
+        Ok(Mapping { ptr: ptr as *mut u8, len })
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            let _ = unsafe { mman::munmap(self.ptr as *mut _, self.len) };
+        }
+    }
+}
+
+/*
+ * SIGBUS protection
+ */
+
+thread_local! {
+    // The mapping being accessed by this thread, and whether accessing it raised SIGBUS
+    //
+    // It is read from the signal handler, so its initialization must not allocate nor register a
+    // destructor: a const initializer guarantees it.
+    static SIGBUS_GUARD: Cell<(usize, usize, bool)> = const { Cell::new((0, 0, false)) };
+}
+
+static SIGBUS_HANDLER_INIT: Once = Once::new();
+static mut PREVIOUS_SIGBUS_ACTION: Option<SigAction> = None;
+
+fn install_sigbus_handler() {
+    SIGBUS_HANDLER_INIT.call_once(|| unsafe {
+        let action = SigAction::new(
+            SigHandler::SigAction(sigbus_handler),
+            SaFlags::SA_NODEFER,
+            SigSet::empty(),
+        );
+        match signal::sigaction(Signal::SIGBUS, &action) {
+            Ok(previous) => PREVIOUS_SIGBUS_ACTION = Some(previous),
+            Err(errno) => log::error!("Failed to install the SIGBUS handler: {}", errno),
+        }
+    });
+}
+
+extern "C" fn sigbus_handler(
+    _signum: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _context: *mut libc::c_void,
+) {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let address = unsafe { (*info).si_addr() } as usize;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let address = unsafe { (*info).si_addr } as usize;
+
+    let handled = SIGBUS_GUARD.with(|guard| {
+        let (ptr, len, _) = guard.get();
+        if ptr == 0 || address < ptr || address >= ptr + len {
+            return false;
+        }
+        // replace the pool with anonymous memory, so that the faulting access can complete
+        let remapped = unsafe {
+            mman::mmap(
+                ptr as *mut _,
+                len,
+                ProtFlags::PROT_READ,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED | MapFlags::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if remapped.is_err() {
+            return false;
+        }
+        guard.set((ptr, len, true));
+        true
+    });
+
+    if !handled {
+        // not caused by a buffer access, restore the previous action which will handle the signal
+        // when the faulting instruction is executed again
+        let previous = unsafe { &*std::ptr::addr_of!(PREVIOUS_SIGBUS_ACTION) };
+        let default = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+        let _ = unsafe { signal::sigaction(Signal::SIGBUS, previous.as_ref().unwrap_or(&default)) };
+    }
+}
@@ -1,20 +1,27 @@
 use std::{
     env,
     ffi::OsStr,
-    fs::File,
+    fs::{File, Permissions},
     ops::Range,
     os::unix::{
+        fs::PermissionsExt,
         io::{AsRawFd, FromRawFd, RawFd},
         net::{UnixListener, UnixStream},
     },
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use nix::{
     fcntl::{flock, open, FlockArg, OFlag},
-    sys::stat::{lstat, Mode},
-    unistd::unlink,
+    poll::{poll, PollFd, PollFlags},
+    sys::{
+        socket::{getsockopt, sockopt::AcceptConn},
+        stat::{lstat, Mode},
+    },
+    unistd::{chown, unlink, Gid},
 };
+use wayland_backend::server::{ClientData, ClientId, DisconnectReason};
 
 #[derive(Debug)]
 pub struct ListeningSocket {
@@ -25,10 +32,98 @@ pub struct ListeningSocket {
 }
 
 impl ListeningSocket {
+    /// Bind a socket with the given name in `XDG_RUNTIME_DIR`
+    ///
+    /// See [`ListeningSocketBuilder`] to control the placement and permissions of the socket.
     pub fn bind<S: AsRef<OsStr>>(socket_name: S) -> Result<ListeningSocket, BindError> {
-        let runtime_dir: PathBuf =
-            env::var("XDG_RUNTIME_DIR").map_err(|_| BindError::RuntimeDirNotSet)?.into();
-        let socket_path = runtime_dir.join(socket_name.as_ref());
+        ListeningSocket::builder().bind(socket_name)
+    }
+
+    /// Bind a socket in `XDG_RUNTIME_DIR` with the first available name `<basename>-<n>` for `n`
+    /// in `range`
+    pub fn bind_auto(basename: &str, range: Range<usize>) -> Result<Self, BindError> {
+        ListeningSocket::builder().bind_auto(basename, range)
+    }
+
+    /// A builder to bind a socket with explicit placement and permissions
+    pub fn builder() -> ListeningSocketBuilder {
+        ListeningSocketBuilder::default()
+    }
+
+    pub fn accept(&self) -> std::io::Result<Option<UnixStream>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Ok(Some(stream)),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The name of the bound socket
+    ///
+    /// This is the value to give to the clients as `WAYLAND_DISPLAY` when the socket is in
+    /// `XDG_RUNTIME_DIR`, which is notably needed after [`bind_auto()`](ListeningSocket::bind_auto).
+    pub fn socket_name(&self) -> Option<&OsStr> {
+        self.socket_path.file_name()
+    }
+
+    /// The full path of the bound socket
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+}
+
+/// A builder for [`ListeningSocket`]
+///
+/// By default the socket is created in `XDG_RUNTIME_DIR`, with permissions set by the umask of the
+/// process and owned by its user and group. For multi-user setups, the socket can be given
+/// explicit mode bits and group ownership, or be created in another directory.
+///
+/// ```no_run
+/// use wayland_server::socket::ListeningSocket;
+///
+/// let socket = ListeningSocket::builder()
+///     .directory("/run/my-compositor")
+///     .mode(0o660)
+///     .group(1000)
+///     .bind("wayland-0")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ListeningSocketBuilder {
+    directory: Option<PathBuf>,
+    mode: Option<u32>,
+    group: Option<u32>,
+}
+
+impl ListeningSocketBuilder {
+    /// Create the socket in this directory rather than in `XDG_RUNTIME_DIR`
+    pub fn directory<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// Set the permission bits of the socket, like `0o660`
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Set the group owning the socket, by its gid
+    ///
+    /// The process must be a member of this group, or binding will fail with
+    /// [`BindError::PermissionDenied`].
+    pub fn group(mut self, gid: u32) -> Self {
+        self.group = Some(gid);
+        self
+    }
+
+    /// Bind a socket with the given name
+    pub fn bind<S: AsRef<OsStr>>(&self, socket_name: S) -> Result<ListeningSocket, BindError> {
+        let directory = match self.directory {
+            Some(ref directory) => directory.clone(),
+            None => env::var("XDG_RUNTIME_DIR").map_err(|_| BindError::RuntimeDirNotSet)?.into(),
+        };
+        let socket_path = directory.join(socket_name.as_ref());
         let lock_path = socket_path.with_extension("lock");
 
         // open the lockfile
@@ -63,16 +158,34 @@ impl ListeningSocket {
 
         // At this point everything is good to start listening on the socket
         let listener = UnixListener::bind(&socket_path).map_err(BindError::Io)?;
+        // from now on the socket and lockfile are removed if anything fails
+        let socket = ListeningSocket { listener, _lock, socket_path, lock_path };
 
-        listener.set_nonblocking(true).map_err(BindError::Io)?;
+        if let Some(gid) = self.group {
+            chown(&socket.socket_path, None, Some(Gid::from_raw(gid))).map_err(|e| match e {
+                nix::Error::EPERM => BindError::PermissionDenied,
+                e => BindError::Io(e.into()),
+            })?;
+        }
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(&socket.socket_path, Permissions::from_mode(mode))
+                .map_err(BindError::Io)?;
+        }
 
-        Ok(ListeningSocket { listener, _lock, socket_path, lock_path })
+        socket.listener.set_nonblocking(true).map_err(BindError::Io)?;
+
+        Ok(socket)
     }
 
-    pub fn bind_auto(basename: &str, range: Range<usize>) -> Result<Self, BindError> {
+    /// Bind a socket with the first available name `<basename>-<n>` for `n` in `range`
+    pub fn bind_auto(
+        &self,
+        basename: &str,
+        range: Range<usize>,
+    ) -> Result<ListeningSocket, BindError> {
         for i in range {
             // early return on any error except AlreadyInUse
-            match ListeningSocket::bind(&format!("{}-{}", basename, i)) {
+            match self.bind(&format!("{}-{}", basename, i)) {
                 Ok(socket) => return Ok(socket),
                 Err(BindError::RuntimeDirNotSet) => return Err(BindError::RuntimeDirNotSet),
                 Err(BindError::PermissionDenied) => return Err(BindError::PermissionDenied),
@@ -82,14 +195,6 @@ impl ListeningSocket {
         }
         Err(BindError::AlreadyInUse)
     }
-
-    pub fn accept(&self) -> std::io::Result<Option<UnixStream>> {
-        match self.listener.accept() {
-            Ok((stream, _)) => Ok(Some(stream)),
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
-            Err(e) => Err(e),
-        }
-    }
 }
 
 impl AsRawFd for ListeningSocket {
@@ -109,10 +214,146 @@ impl Drop for ListeningSocket {
 pub enum BindError {
     #[error("Environment variable XDG_RUNTIME_DIR is not set")]
     RuntimeDirNotSet,
-    #[error("Could not write to XDG_RUNTIME_DIR")]
+    #[error("Could not write to the socket directory or set the socket group")]
     PermissionDenied,
     #[error("Requested socket name is already in use")]
     AlreadyInUse,
     #[error("I/O error: {0}")]
     Io(#[source] std::io::Error),
 }
+
+/// The security context attached to the clients connecting through a sandbox
+///
+/// Sandbox engines (like flatpak) create a listening socket for the clients of a sandbox with
+/// the `wp_security_context_v1` protocol, and attach this metadata to it. The clients accepted on
+/// this socket are tagged with the context, which is available from
+/// [`Client::security_context()`](crate::Client::security_context), notably in
+/// [`GlobalDispatch::can_view()`](crate::GlobalDispatch::can_view) to restrict the globals
+/// advertised to sandboxed clients.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecurityContext {
+    /// The name of the sandbox engine, like `org.flatpak`
+    pub sandbox_engine: Option<String>,
+    /// The identifier of the sandboxed application, as defined by the sandbox engine
+    pub app_id: Option<String>,
+    /// The identifier of the running instance of the application, as defined by the sandbox
+    /// engine
+    pub instance_id: Option<String>,
+}
+
+/// A listening socket created by a sandbox engine with `wp_security_context_v1`
+///
+/// It stops accepting clients once the sandbox engine closes its end of the close fd, as
+/// reported by [`is_closed()`](SecurityContextListener::is_closed). The
+/// [`EventLoop`](crate::event_loop::EventLoop) handles this with
+/// [`add_security_context_listener()`](crate::event_loop::EventLoop::add_security_context_listener),
+/// other event loops need to monitor [`close_fd()`](SecurityContextListener::close_fd) and drop
+/// the listener when it is readable or hung up.
+#[derive(Debug)]
+pub struct SecurityContextListener {
+    listener: UnixListener,
+    close_fd: File,
+    context: Arc<SecurityContext>,
+}
+
+impl SecurityContextListener {
+    /// Create a listener from the file descriptors given to `create_listener`
+    ///
+    /// Fails with an error of kind `InvalidInput` if `listen_fd` is not a listening socket, which
+    /// the compositor must report with the `invalid_listen_fd` protocol error.
+    ///
+    /// # Safety
+    ///
+    /// The listener takes ownership of both file descriptors, which are closed when it is dropped
+    /// or if this function fails.
+    pub unsafe fn from_raw_fds(
+        listen_fd: RawFd,
+        close_fd: RawFd,
+        context: SecurityContext,
+    ) -> std::io::Result<SecurityContextListener> {
+        let listener = UnixListener::from_raw_fd(listen_fd);
+        let close_fd = File::from_raw_fd(close_fd);
+        if !getsockopt(listen_fd, AcceptConn).unwrap_or(false) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the file descriptor is not a listening socket",
+            ));
+        }
+        listener.set_nonblocking(true)?;
+        Ok(SecurityContextListener { listener, close_fd, context: Arc::new(context) })
+    }
+
+    /// The security context of the clients accepted by this listener
+    pub fn context(&self) -> &SecurityContext {
+        &self.context
+    }
+
+    /// The file descriptor signaling that the listener must be closed
+    pub fn close_fd(&self) -> RawFd {
+        self.close_fd.as_raw_fd()
+    }
+
+    /// Check whether the sandbox engine asked for this listener to be closed
+    pub fn is_closed(&self) -> bool {
+        let mut fds = [PollFd::new(self.close_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, 0) {
+            Ok(_) => fds[0].revents().map(|flags| !flags.is_empty()).unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    /// Accept a pending connection, if any
+    ///
+    /// The client must be inserted with
+    /// [`Display::insert_client_with_security_context()`](crate::Display::insert_client_with_security_context)
+    /// to be tagged with the security context of the listener.
+    pub fn accept(&self) -> std::io::Result<Option<UnixStream>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Ok(Some(stream)),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) fn shared_context(&self) -> Arc<SecurityContext> {
+        self.context.clone()
+    }
+}
+
+impl AsRawFd for SecurityContextListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.listener.as_raw_fd()
+    }
+}
+
+// The client data of the clients tagged with a security context, wrapping the one provided by the
+// compositor
+pub(crate) struct SecurityContextClientData<D> {
+    pub(crate) context: Arc<SecurityContext>,
+    pub(crate) data: Arc<dyn ClientData<D>>,
+}
+
+impl<D: 'static> ClientData<D> for SecurityContextClientData<D> {
+    fn initialized(&self, client_id: ClientId) {
+        self.data.initialized(client_id)
+    }
+
+    fn disconnected(&self, client_id: ClientId, reason: DisconnectReason) {
+        self.data.disconnected(client_id, reason)
+    }
+
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.data.debug(f)
+    }
+}
+
+// Split the client data of a client between the one provided by the compositor and the security
+// context it was tagged with, if any
+pub(crate) fn split_client_data<D: 'static>(
+    data: Arc<dyn ClientData<D>>,
+) -> (Arc<dyn std::any::Any + Send + Sync>, Option<Arc<SecurityContext>>) {
+    match data.into_any_arc().downcast::<SecurityContextClientData<D>>() {
+        Ok(tagged) => (tagged.data.clone().into_any_arc(), Some(tagged.context.clone())),
+        Err(data) => (data, None),
+    }
+}
@@ -0,0 +1,309 @@
+//! Atomic application of double-buffered state
+//!
+//! The state of a `wl_surface` is double-buffered: the requests changing it only affect a pending
+//! state, which `wl_surface.commit` applies. The commits of synchronized subsurfaces do not apply
+//! their state but cache it, until the commit of their parent applies the state of the whole tree
+//! at once. Extensions may also delay the application of a commit, until the buffers it attaches
+//! are ready to be sampled for example.
+//!
+//! A [`TransactionTracker`] implements these rules over the nodes of a tree, identified by a key
+//! like the `ObjectId` of each surface, and for a state type implementing [`CommitState`]. Each
+//! commit of a node which is not synchronized creates a [`Transaction`], grouping its state and the
+//! cached states of its synchronized descendants. The transaction can be held back by blockers, and
+//! once none remains and the transactions it depends on were applied,
+//! [`apply_ready()`](TransactionTracker::apply_ready) hands it to the hook set with
+//! [`set_ready_hook()`](TransactionTracker::set_ready_hook), to be applied as a whole.
+//!
+//! ```
+//! use wayland_server::transaction::{CommitState, TransactionTracker};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct SurfaceState {
+//!     damage: Vec<(i32, i32, i32, i32)>,
+//! }
+//!
+//! impl CommitState for SurfaceState {
+//!     fn merge(&mut self, newer: SurfaceState) {
+//!         self.damage.extend(newer.damage);
+//!     }
+//! }
+//!
+//! let applied = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+//! let applied2 = applied.clone();
+//! let mut tracker = TransactionTracker::new();
+//! tracker.set_ready_hook(Box::new(move |transaction| {
+//!     applied2.lock().unwrap().push(transaction.into_states())
+//! }));
+//!
+//! // surface 2 is a synchronized subsurface of surface 1
+//! tracker.set_parent(2u32, Some(1));
+//! tracker.set_sync(2, true);
+//! assert!(tracker.commit(2, SurfaceState { damage: vec![(0, 0, 10, 10)] }).is_none());
+//! tracker.commit(1, SurfaceState { damage: vec![] });
+//! tracker.apply_ready();
+//! assert_eq!(
+//!     *applied.lock().unwrap(),
+//!     [vec![(1, SurfaceState { damage: vec![] }), (2, SurfaceState { damage: vec![(0, 0, 10, 10)] })]]
+//! );
+//! ```
+
+use std::{collections::HashMap, fmt, hash::Hash};
+
+/// A double-buffered state, applied by the commits of a node
+pub trait CommitState {
+    /// Merge the state of a newer commit into this one
+    ///
+    /// This is invoked when a synchronized node is committed while it already has a cached state:
+    /// the properties set by the newer commit replace the cached ones, and accumulated
+    /// properties like the damage are combined.
+    fn merge(&mut self, newer: Self);
+}
+
+/// The identifier of a transaction of a [`TransactionTracker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TransactionId(u64);
+
+/// The states of a tree of nodes, to be applied at once
+#[derive(Debug)]
+pub struct Transaction<K, S> {
+    id: TransactionId,
+    // parents before their children
+    states: Vec<(K, S)>,
+}
+
+impl<K, S> Transaction<K, S> {
+    /// The identifier of this transaction
+    pub fn id(&self) -> TransactionId {
+        self.id
+    }
+
+    /// The nodes and their states, the parents being listed before their children
+    pub fn states(&self) -> &[(K, S)] {
+        &self.states
+    }
+
+    /// Take the nodes and their states, the parents being listed before their children
+    pub fn into_states(self) -> Vec<(K, S)> {
+        self.states
+    }
+}
+
+type ReadyHook<K, S> = dyn FnMut(Transaction<K, S>) + Send;
+
+struct Node<K, S> {
+    parent: Option<K>,
+    children: Vec<K>,
+    sync: bool,
+    cached: Option<S>,
+}
+
+impl<K, S> Default for Node<K, S> {
+    fn default() -> Self {
+        Node { parent: None, children: Vec::new(), sync: false, cached: None }
+    }
+}
+
+struct Pending<K, S> {
+    transaction: Transaction<K, S>,
+    blockers: usize,
+}
+
+/// Grouping of the commits of a tree of nodes into transactions
+///
+/// See the [module-level documentation](self) for details. The nodes are created when first
+/// referenced, and must be removed with [`remove()`](TransactionTracker::remove) once destroyed.
+pub struct TransactionTracker<K, S> {
+    nodes: HashMap<K, Node<K, S>>,
+    pending: Vec<Pending<K, S>>,
+    next_id: u64,
+    ready_hook: Option<Box<ReadyHook<K, S>>>,
+}
+
+impl<K: Clone + Eq + Hash, S: CommitState> Default for TransactionTracker<K, S> {
+    fn default() -> Self {
+        TransactionTracker::new()
+    }
+}
+
+impl<K: Clone + Eq + Hash, S: CommitState> TransactionTracker<K, S> {
+    /// Create a new tracker, without any node
+    pub fn new() -> TransactionTracker<K, S> {
+        TransactionTracker {
+            nodes: HashMap::new(),
+            pending: Vec::new(),
+            next_id: 0,
+            ready_hook: None,
+        }
+    }
+
+    /// Set the hook invoked with each transaction ready to be applied
+    ///
+    /// This replaces the previously set hook, if any. The transactions becoming ready without a
+    /// hook are dropped.
+    pub fn set_ready_hook(&mut self, hook: Box<ReadyHook<K, S>>) {
+        self.ready_hook = Some(hook);
+    }
+
+    /// Set the parent of a node, or make it a root with `None`
+    pub fn set_parent(&mut self, node: K, parent: Option<K>) {
+        let previous = self.nodes.entry(node.clone()).or_default().parent.take();
+        if let Some(previous) = previous {
+            if let Some(previous) = self.nodes.get_mut(&previous) {
+                previous.children.retain(|child| child != &node);
+            }
+        }
+        if let Some(ref parent) = parent {
+            self.nodes.entry(parent.clone()).or_default().children.push(node.clone());
+        }
+        self.nodes.get_mut(&node).unwrap().parent = parent;
+    }
+
+    /// Set whether a node is synchronized with its parent
+    ///
+    /// The nodes are not synchronized when created, and the roots never are. A node switching to
+    /// the desynchronized mode keeps its cached state until its next commit, unless one of its
+    /// ancestors applies it before.
+    pub fn set_sync(&mut self, node: K, sync: bool) {
+        self.nodes.entry(node).or_default().sync = sync;
+    }
+
+    /// Whether the commits of a node are cached, because it or one of its ancestors is synchronized
+    pub fn is_synchronized(&self, node: &K) -> bool {
+        let mut current = self.nodes.get(node);
+        while let Some(node) = current {
+            let parent = match node.parent {
+                Some(ref parent) => parent,
+                None => return false,
+            };
+            if node.sync {
+                return true;
+            }
+            current = self.nodes.get(parent);
+        }
+        false
+    }
+
+    /// Commit the pending state of a node
+    ///
+    /// If the node is synchronized, the state is cached and `None` is returned. Otherwise a
+    /// transaction is created with the state of the node and the cached states of its
+    /// synchronized descendants, and queued until it is ready to be applied.
+    pub fn commit(&mut self, node: K, state: S) -> Option<TransactionId> {
+        let entry = self.nodes.entry(node.clone()).or_default();
+        let state = match entry.cached.take() {
+            Some(mut cached) => {
+                cached.merge(state);
+                cached
+            }
+            None => state,
+        };
+        if self.is_synchronized(&node) {
+            self.nodes.get_mut(&node).unwrap().cached = Some(state);
+            return None;
+        }
+
+        let mut states = vec![(node.clone(), state)];
+        self.collect_cached(&node, false, &mut states);
+        let id = TransactionId(self.next_id);
+        self.next_id += 1;
+        self.pending.push(Pending { transaction: Transaction { id, states }, blockers: 0 });
+        Some(id)
+    }
+
+    /// Prevent a queued transaction from being applied until the blocker is released
+    ///
+    /// Returns `false` if the transaction is not queued anymore.
+    pub fn add_blocker(&mut self, id: TransactionId) -> bool {
+        match self.pending.iter_mut().find(|pending| pending.transaction.id == id) {
+            Some(pending) => {
+                pending.blockers += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Release a blocker of a transaction added with [`add_blocker()`](Self::add_blocker)
+    pub fn release_blocker(&mut self, id: TransactionId) {
+        if let Some(pending) = self.pending.iter_mut().find(|pending| pending.transaction.id == id)
+        {
+            pending.blockers = pending.blockers.saturating_sub(1);
+        }
+    }
+
+    /// Hand the transactions ready to be applied to the hook
+    ///
+    /// A transaction is ready once it has no blocker, and all the transactions committed before it
+    /// involving the same nodes were applied. Returns the number of applied transactions.
+    pub fn apply_ready(&mut self) -> usize {
+        let mut applied = 0;
+        let mut i = 0;
+        while i < self.pending.len() {
+            let pending = &self.pending[i];
+            let waits_for_earlier = self.pending[..i].iter().any(|earlier| {
+                earlier.transaction.states.iter().any(|(node, _)| {
+                    pending.transaction.states.iter().any(|(other, _)| node == other)
+                })
+            });
+            if pending.blockers > 0 || waits_for_earlier {
+                i += 1;
+                continue;
+            }
+            let transaction = self.pending.remove(i).transaction;
+            applied += 1;
+            if let Some(ref mut hook) = self.ready_hook {
+                hook(transaction);
+            }
+        }
+        applied
+    }
+
+    /// Remove a destroyed node
+    ///
+    /// Its cached state is dropped, and its children become roots. The queued transactions
+    /// involving it are kept.
+    pub fn remove(&mut self, node: &K) {
+        let removed = match self.nodes.remove(node) {
+            Some(removed) => removed,
+            None => return,
+        };
+        if let Some(parent) = removed.parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            parent.children.retain(|child| child != node);
+        }
+        for child in removed.children {
+            if let Some(child) = self.nodes.get_mut(&child) {
+                child.parent = None;
+            }
+        }
+    }
+
+    // collect the cached states of the synchronized descendants, parents first
+    fn collect_cached(&mut self, node: &K, synchronized: bool, states: &mut Vec<(K, S)>) {
+        let children = match self.nodes.get(node) {
+            Some(node) => node.children.clone(),
+            None => return,
+        };
+        for child in children {
+            let entry = self.nodes.get_mut(&child).unwrap();
+            // the desynchronized children apply their state themselves, unless an ancestor below
+            // the committed node is synchronized
+            let synchronized = synchronized || entry.sync;
+            if !synchronized {
+                continue;
+            }
+            if let Some(cached) = entry.cached.take() {
+                states.push((child.clone(), cached));
+            }
+            self.collect_cached(&child, synchronized, states);
+        }
+    }
+}
+
+impl<K, S> fmt::Debug for TransactionTracker<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransactionTracker")
+            .field("nodes", &self.nodes.len())
+            .field("pending", &self.pending.len())
+            .finish_non_exhaustive()
+    }
+}
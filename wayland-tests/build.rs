@@ -0,0 +1,8 @@
+fn main() {
+    // build the C client of the `capi` test
+    if std::env::var_os("CARGO_FEATURE_CAPI").is_some() {
+        cc::Build::new().file("tests/capi/client.c").cargo_metadata(false).compile("capi_client");
+        println!("cargo:rustc-link-search=native={}", std::env::var("OUT_DIR").unwrap());
+        println!("cargo:rerun-if-changed=tests/capi/client.c");
+    }
+}
@@ -0,0 +1,154 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{ways, DumbClientData, TestCompositor, TestServer};
+
+use ways::protocol::{wl_output as soutput, wl_surface as ssurface};
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+// what the C client of tests/capi/client.c saw
+#[repr(C)]
+#[derive(Debug, Default)]
+struct CapiReport {
+    compositor_version: u32,
+    output_version: u32,
+    physical_width: i32,
+    physical_height: i32,
+    make: [c_char; 32],
+    mode_width: i32,
+    mode_height: i32,
+    output_done: u32,
+    surface_entered: u32,
+    callback_done: u32,
+}
+
+#[link(name = "capi_client", kind = "static")]
+extern "C" {
+    fn capi_run_client(fd: c_int, report: *mut CapiReport) -> c_int;
+}
+
+#[test]
+fn c_client() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<soutput::WlOutput>(2, ());
+    let mut server_ddata =
+        ServerHandler { compositor: TestCompositor::default(), output: None, requests: Vec::new() };
+
+    let (server_socket, client_socket) = UnixStream::pair().unwrap();
+    server.display.insert_client(server_socket, Arc::new(DumbClientData)).unwrap();
+
+    // the C client blocks in its roundtrips, the server answers it from this thread meanwhile
+    let (sender, receiver) = mpsc::channel();
+    let fd = client_socket.into_raw_fd();
+    std::thread::spawn(move || {
+        let mut report = CapiReport::default();
+        let ret = unsafe { capi_run_client(fd, &mut report) };
+        sender.send((ret, report)).unwrap();
+    });
+    let (ret, report) = loop {
+        server.answer(&mut server_ddata);
+        match receiver.recv_timeout(Duration::from_millis(10)) {
+            Ok(result) => break result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => panic!("The C client panicked."),
+        }
+    };
+
+    assert_eq!(ret, 0, "The C client failed at step {}: {:?}", ret, report);
+    assert_eq!(report.compositor_version, 1);
+    assert_eq!(report.output_version, 2);
+    assert_eq!((report.physical_width, report.physical_height), (520, 290));
+    assert_eq!(unsafe { CStr::from_ptr(report.make.as_ptr()) }.to_str(), Ok("ACME"));
+    assert_eq!((report.mode_width, report.mode_height), (1920, 1080));
+    assert_eq!(report.callback_done, 1);
+
+    server.answer(&mut server_ddata);
+    assert_eq!(server_ddata.compositor.surfaces.len(), 1);
+    assert_eq!(server_ddata.requests, ["commit", "destroy"]);
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    compositor: TestCompositor,
+    output: Option<soutput::WlOutput>,
+    requests: Vec<&'static str>,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler:
+    [ways::protocol::wl_compositor::WlCompositor] => TestCompositor
+);
+ways::delegate_dispatch!(ServerHandler: [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_region::WlRegion
+] => TestCompositor);
+
+impl ways::Dispatch<ssurface::WlSurface> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        surface: &ssurface::WlSurface,
+        request: ssurface::Request,
+        _: &(),
+        dh: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            ssurface::Request::Commit => {
+                self.requests.push("commit");
+                surface.enter(dh, self.output.as_ref().unwrap());
+            }
+            ssurface::Request::Destroy => self.requests.push("destroy"),
+            _ => {}
+        }
+    }
+}
+
+impl ways::GlobalDispatch<soutput::WlOutput> for ServerHandler {
+    type GlobalData = ();
+
+    fn bind(
+        &mut self,
+        dh: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        output: ways::New<soutput::WlOutput>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        use soutput::{Mode, Subpixel, Transform};
+
+        let output = data_init.init(output, ());
+        output.geometry(
+            dh,
+            0,
+            0,
+            520,
+            290,
+            Subpixel::Unknown,
+            "ACME".into(),
+            "Display".into(),
+            Transform::Normal,
+        );
+        output.mode(dh, Mode::Current | Mode::Preferred, 1920, 1080, 60000);
+        output.done(dh);
+        self.output = Some(output);
+    }
+}
+
+server_ignore_impl!(ServerHandler => [soutput::WlOutput]);
@@ -0,0 +1,314 @@
+// A client using the C API of wayland-backend, as a C library generated by wayland-scanner would
+//
+// It only relies on the declarations of wayland-client-core.h, the interfaces it uses are
+// described here like in the code generated by `wayland-scanner public-code`.
+
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+struct wl_display;
+struct wl_proxy;
+struct wl_object;
+
+struct wl_message {
+    const char *name;
+    const char *signature;
+    const struct wl_interface **types;
+};
+
+struct wl_interface {
+    const char *name;
+    int version;
+    int method_count;
+    const struct wl_message *methods;
+    int event_count;
+    const struct wl_message *events;
+};
+
+struct wl_array {
+    size_t size;
+    size_t alloc;
+    void *data;
+};
+
+union wl_argument {
+    int32_t i;
+    uint32_t u;
+    int32_t f;
+    const char *s;
+    struct wl_object *o;
+    uint32_t n;
+    struct wl_array *a;
+    int32_t h;
+};
+
+typedef int (*wl_dispatcher_func_t)(const void *, void *, uint32_t, const struct wl_message *,
+                                    union wl_argument *);
+
+#define WL_MARSHAL_FLAG_DESTROY (1 << 0)
+
+struct wl_display *wl_display_connect_to_fd(int fd);
+void wl_display_disconnect(struct wl_display *display);
+int wl_display_get_error(struct wl_display *display);
+int wl_display_roundtrip(struct wl_display *display);
+int wl_display_dispatch(struct wl_display *display);
+
+void wl_proxy_marshal_array(struct wl_proxy *proxy, uint32_t opcode, union wl_argument *args);
+struct wl_proxy *wl_proxy_marshal_array_constructor(struct wl_proxy *proxy, uint32_t opcode,
+                                                    union wl_argument *args,
+                                                    const struct wl_interface *interface);
+struct wl_proxy *wl_proxy_marshal_array_constructor_versioned(
+    struct wl_proxy *proxy, uint32_t opcode, union wl_argument *args,
+    const struct wl_interface *interface, uint32_t version);
+struct wl_proxy *wl_proxy_marshal_array_flags(struct wl_proxy *proxy, uint32_t opcode,
+                                              const struct wl_interface *interface,
+                                              uint32_t version, uint32_t flags,
+                                              union wl_argument *args);
+int wl_proxy_add_dispatcher(struct wl_proxy *proxy, wl_dispatcher_func_t dispatcher,
+                            const void *implementation, void *data);
+void wl_proxy_destroy(struct wl_proxy *proxy);
+void *wl_proxy_get_user_data(struct wl_proxy *proxy);
+uint32_t wl_proxy_get_version(struct wl_proxy *proxy);
+uint32_t wl_proxy_get_id(struct wl_proxy *proxy);
+const char *wl_proxy_get_class(struct wl_proxy *proxy);
+
+/*
+ * Interfaces
+ */
+
+extern const struct wl_interface wl_callback_interface;
+extern const struct wl_interface wl_compositor_interface;
+extern const struct wl_interface wl_output_interface;
+extern const struct wl_interface wl_registry_interface;
+extern const struct wl_interface wl_surface_interface;
+
+static const struct wl_interface *types[] = {
+    NULL,
+    NULL,
+    NULL,
+    NULL,
+    NULL,
+    NULL,
+    NULL,
+    NULL,
+    &wl_callback_interface,
+    &wl_registry_interface,
+    &wl_surface_interface,
+    &wl_output_interface,
+};
+
+static const struct wl_message wl_registry_requests[] = {
+    {"bind", "usun", types + 0},
+};
+
+static const struct wl_message wl_registry_events[] = {
+    {"global", "usu", types + 0},
+    {"global_remove", "u", types + 0},
+};
+
+const struct wl_interface wl_registry_interface = {
+    "wl_registry", 1, 1, wl_registry_requests, 2, wl_registry_events,
+};
+
+static const struct wl_message wl_callback_events[] = {
+    {"done", "u", types + 0},
+};
+
+const struct wl_interface wl_callback_interface = {
+    "wl_callback", 1, 0, NULL, 1, wl_callback_events,
+};
+
+static const struct wl_message wl_compositor_requests[] = {
+    {"create_surface", "n", types + 10},
+    {"create_region", "n", types + 0},
+};
+
+const struct wl_interface wl_compositor_interface = {
+    "wl_compositor", 1, 2, wl_compositor_requests, 0, NULL,
+};
+
+static const struct wl_message wl_surface_requests[] = {
+    {"destroy", "", types + 0},
+    {"attach", "?oii", types + 0},
+    {"damage", "iiii", types + 0},
+    {"frame", "n", types + 8},
+    {"set_opaque_region", "?o", types + 0},
+    {"set_input_region", "?o", types + 0},
+    {"commit", "", types + 0},
+};
+
+static const struct wl_message wl_surface_events[] = {
+    {"enter", "o", types + 11},
+    {"leave", "o", types + 11},
+};
+
+const struct wl_interface wl_surface_interface = {
+    "wl_surface", 1, 7, wl_surface_requests, 2, wl_surface_events,
+};
+
+static const struct wl_message wl_output_events[] = {
+    {"geometry", "iiiiissi", types + 0},
+    {"mode", "uiii", types + 0},
+    {"done", "2", types + 0},
+    {"scale", "2i", types + 0},
+};
+
+const struct wl_interface wl_output_interface = {
+    "wl_output", 2, 0, NULL, 4, wl_output_events,
+};
+
+/*
+ * Client
+ */
+
+struct capi_report {
+    uint32_t compositor_version;
+    uint32_t output_version;
+    int32_t physical_width;
+    int32_t physical_height;
+    char make[32];
+    int32_t mode_width;
+    int32_t mode_height;
+    uint32_t output_done;
+    uint32_t surface_entered;
+    uint32_t callback_done;
+};
+
+struct client {
+    struct capi_report *report;
+    uint32_t compositor_name;
+    uint32_t output_name;
+    struct wl_proxy *output;
+};
+
+static int registry_dispatch(const void *implementation, void *target, uint32_t opcode,
+                             const struct wl_message *message, union wl_argument *args) {
+    struct client *client = wl_proxy_get_user_data(target);
+    (void)implementation;
+    if (opcode == 0 && strcmp(message->name, "global") == 0) {
+        if (strcmp(args[1].s, "wl_compositor") == 0)
+            client->compositor_name = args[0].u;
+        else if (strcmp(args[1].s, "wl_output") == 0)
+            client->output_name = args[0].u;
+    }
+    return 0;
+}
+
+static int output_dispatch(const void *implementation, void *target, uint32_t opcode,
+                           const struct wl_message *message, union wl_argument *args) {
+    struct client *client = wl_proxy_get_user_data(target);
+    (void)implementation;
+    (void)message;
+    switch (opcode) {
+    case 0:
+        client->report->physical_width = args[2].i;
+        client->report->physical_height = args[3].i;
+        strncpy(client->report->make, args[5].s, sizeof(client->report->make) - 1);
+        break;
+    case 1:
+        client->report->mode_width = args[1].i;
+        client->report->mode_height = args[2].i;
+        break;
+    case 2:
+        client->report->output_done++;
+        break;
+    }
+    return 0;
+}
+
+static int surface_dispatch(const void *implementation, void *target, uint32_t opcode,
+                            const struct wl_message *message, union wl_argument *args) {
+    struct client *client = wl_proxy_get_user_data(target);
+    (void)implementation;
+    (void)message;
+    // the objects are given as their proxies
+    if (opcode == 0 && (struct wl_proxy *)args[0].o == client->output)
+        client->report->surface_entered++;
+    return 0;
+}
+
+static int callback_dispatch(const void *implementation, void *target, uint32_t opcode,
+                             const struct wl_message *message, union wl_argument *args) {
+    struct client *client = wl_proxy_get_user_data(target);
+    (void)implementation;
+    (void)opcode;
+    (void)message;
+    (void)args;
+    client->report->callback_done++;
+    // done is a destructor event
+    wl_proxy_destroy(target);
+    return 0;
+}
+
+// Run the client over the connected socket `fd`, returning 0 or the number of the failed step
+int capi_run_client(int fd, struct capi_report *report) {
+    struct client client = {report, 0, 0, NULL};
+    union wl_argument args[4];
+    memset(args, 0, sizeof(args));
+
+    struct wl_display *display = wl_display_connect_to_fd(fd);
+    if (display == NULL)
+        return 1;
+
+    struct wl_proxy *registry = wl_proxy_marshal_array_constructor(
+        (struct wl_proxy *)display, 1, args, &wl_registry_interface);
+    if (registry == NULL || wl_proxy_add_dispatcher(registry, registry_dispatch, NULL, &client))
+        return 2;
+    // a proxy only has one dispatcher
+    if (wl_proxy_add_dispatcher(registry, registry_dispatch, NULL, &client) == 0)
+        return 3;
+    if (wl_display_roundtrip(display) < 0)
+        return 4;
+    if (client.compositor_name == 0 || client.output_name == 0)
+        return 5;
+
+    args[0].u = client.compositor_name;
+    args[1].s = "wl_compositor";
+    args[2].u = 1;
+    args[3].n = 0;
+    struct wl_proxy *compositor = wl_proxy_marshal_array_constructor_versioned(
+        registry, 0, args, &wl_compositor_interface, 1);
+    args[0].u = client.output_name;
+    args[1].s = "wl_output";
+    args[2].u = 2;
+    client.output = wl_proxy_marshal_array_constructor_versioned(registry, 0, args,
+                                                                 &wl_output_interface, 2);
+    if (compositor == NULL || client.output == NULL)
+        return 6;
+    if (wl_proxy_add_dispatcher(client.output, output_dispatch, NULL, &client))
+        return 7;
+    report->compositor_version = wl_proxy_get_version(compositor);
+    report->output_version = wl_proxy_get_version(client.output);
+    if (strcmp(wl_proxy_get_class(client.output), "wl_output") != 0)
+        return 8;
+
+    args[0].n = 0;
+    struct wl_proxy *surface =
+        wl_proxy_marshal_array_constructor(compositor, 0, args, &wl_surface_interface);
+    if (surface == NULL || wl_proxy_get_version(surface) != 1 || wl_proxy_get_id(surface) == 0)
+        return 9;
+    if (wl_proxy_add_dispatcher(surface, surface_dispatch, NULL, &client))
+        return 10;
+    wl_proxy_marshal_array(surface, 6, args);
+
+    struct wl_proxy *callback = wl_proxy_marshal_array_constructor(
+        (struct wl_proxy *)display, 0, args, &wl_callback_interface);
+    if (callback == NULL || wl_proxy_add_dispatcher(callback, callback_dispatch, NULL, &client))
+        return 11;
+    while (report->callback_done == 0)
+        if (wl_display_dispatch(display) < 0)
+            return 12;
+    if (report->output_done != 1 || report->surface_entered != 1)
+        return 13;
+
+    wl_proxy_marshal_array_flags(surface, 0, NULL, 0, WL_MARSHAL_FLAG_DESTROY, args);
+    wl_proxy_destroy(client.output);
+    wl_proxy_destroy(compositor);
+    wl_proxy_destroy(registry);
+    if (wl_display_roundtrip(display) < 0 || wl_display_get_error(display) != 0)
+        return 14;
+
+    wl_display_disconnect(display);
+    return 0;
+}
@@ -0,0 +1,42 @@
+#[macro_use]
+mod helpers;
+
+use std::sync::Arc;
+
+use helpers::{wayc, ways};
+
+static SOCKET_NAME: &str = "wayland-rs-test-client-connect-shared";
+
+fn main() {
+    let listening = ways::socket::ListeningSocket::bind(&SOCKET_NAME).unwrap();
+    ::std::env::set_var("WAYLAND_DISPLAY", &SOCKET_NAME);
+
+    // two components of the process get the same connection
+    let conn_1 = wayc::Connection::connect_shared().unwrap();
+    let conn_2 = wayc::Connection::connect_shared().unwrap();
+    assert!(Arc::ptr_eq(&conn_1.backend(), &conn_2.backend()));
+    assert!(listening.accept().unwrap().is_some());
+    assert!(listening.accept().unwrap().is_none());
+
+    // once they all dropped it, a new connection is opened
+    std::mem::drop((conn_1, conn_2));
+    let conn_3 = wayc::Connection::connect_shared().unwrap();
+    assert!(listening.accept().unwrap().is_some());
+
+    // the application can provide the shared connection
+    let (client_socket, _server_socket) = std::os::unix::net::UnixStream::pair().unwrap();
+    let app_conn = wayc::Connection::from_socket(client_socket).unwrap();
+    app_conn.share();
+    let conn_4 = wayc::Connection::connect_shared().unwrap();
+    assert!(Arc::ptr_eq(&app_conn.backend(), &conn_4.backend()));
+    assert!(!Arc::ptr_eq(&conn_3.backend(), &conn_4.backend()));
+
+    // without sharing, each call opens a new connection
+    wayc::Connection::disable_sharing();
+    let conn_5 = wayc::Connection::connect_shared().unwrap();
+    let conn_6 = wayc::Connection::connect_shared().unwrap();
+    assert!(!Arc::ptr_eq(&conn_5.backend(), &conn_6.backend()));
+    assert!(!Arc::ptr_eq(&app_conn.backend(), &conn_5.backend()));
+    assert!(listening.accept().unwrap().is_some());
+    assert!(listening.accept().unwrap().is_some());
+}
@@ -6,12 +6,36 @@ use helpers::{roundtrip, wayc, ways, DumbClientData, TestClient, TestServer};
 use ways::protocol::wl_output::WlOutput as ServerOutput;
 
 use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixDatagram;
 use std::sync::Arc;
 
 fn main() {
     let mut server = TestServer::new();
     server.display.create_global::<ServerOutput>(2, ());
 
+    // file descriptors which are not connected unix stream sockets are rejected
+    let (d1, _d2) = UnixDatagram::pair().unwrap();
+    ::std::env::set_var("WAYLAND_SOCKET", format!("{}", d1.into_raw_fd()));
+    assert!(matches!(wayc::Connection::connect_to_env(), Err(wayc::ConnectError::InvalidSocket)));
+    let file = ::std::fs::File::open("/dev/null").unwrap();
+    let ret = unsafe { wayc::Connection::from_raw_fd_checked(file.into_raw_fd()) };
+    assert!(matches!(ret, Err(wayc::ConnectError::InvalidSocket)));
+
+    // the sockets handed by a launcher can be used directly
+    let (s1, s2) = ::std::os::unix::net::UnixStream::pair().unwrap();
+    server.display.insert_client(s1, Arc::new(DumbClientData)).unwrap();
+    let conn = unsafe { wayc::Connection::from_raw_fd_checked(s2.into_raw_fd()) }.unwrap();
+    let event_queue = conn.new_event_queue();
+    let display = conn.handle().display();
+    let mut client = TestClient { conn, display, event_queue };
+    let mut globals = wayc::globals::GlobalList::new();
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut globals, &mut ServerData).unwrap();
+    assert_eq!(globals.list().len(), 1);
+
     let (s1, s2) = ::std::os::unix::net::UnixStream::pair().unwrap();
 
     let my_client = server.display.insert_client(s1, Arc::new(DumbClientData)).unwrap();
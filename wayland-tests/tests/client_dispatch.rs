@@ -1,10 +1,13 @@
+#[macro_use]
 mod helpers;
 
 use helpers::*;
 
+use wayc::Proxy;
+
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 
 #[test]
@@ -30,3 +33,529 @@ fn client_roundtrip() {
 
     server_thread.join().unwrap();
 }
+
+#[test]
+fn client_dispatch_borrowed_state() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(2, ());
+
+    let (_, mut client) = server.add_client::<BorrowingHandler>();
+
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    let mut globals = Vec::new();
+
+    roundtrip(
+        &mut client,
+        &mut server,
+        &mut BorrowingHandler { globals: &mut globals },
+        &mut ServerHandler,
+    )
+    .unwrap();
+
+    assert_eq!(globals, ["wl_compositor".to_owned(), "wl_output".to_owned()]);
+}
+
+#[test]
+fn client_read_without_dispatch() {
+    let mut server = TestServer::new();
+
+    let (_, client) = server.add_client::<SyncHandler>();
+    let TestClient { conn, display, mut event_queue } = client;
+    let mut other_queue = conn.new_event_queue::<SyncHandler>();
+
+    display.sync(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+    display.sync(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+    display.sync(&mut conn.handle(), &other_queue.handle(), ()).unwrap();
+    conn.flush().unwrap();
+
+    // nothing to read yet
+    assert_eq!(conn.read_without_dispatch().unwrap(), 0);
+
+    // the server writes its answers to the socket before returning
+    server.answer(&mut ServerHandler);
+
+    // the events of both queues are read, but not dispatched
+    assert_eq!(conn.read_without_dispatch().unwrap(), 3);
+    assert_eq!(conn.read_without_dispatch().unwrap(), 3);
+
+    let mut sync_handler = SyncHandler { done: false };
+    assert_eq!(conn.dispatch_buffered(&mut event_queue, &mut sync_handler).unwrap(), 2);
+    assert!(sync_handler.done);
+    assert_eq!(conn.read_without_dispatch().unwrap(), 1);
+
+    let mut other_handler = SyncHandler { done: false };
+    assert_eq!(conn.dispatch_buffered(&mut other_queue, &mut other_handler).unwrap(), 1);
+    assert!(other_handler.done);
+    assert_eq!(conn.read_without_dispatch().unwrap(), 0);
+    assert_eq!(conn.dispatch_buffered(&mut event_queue, &mut sync_handler).unwrap(), 0);
+}
+
+#[test]
+fn client_prepare_read_exclusive() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+
+    let (_, mut client) = server.add_client::<BorrowingHandler>();
+
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    client.conn.flush().unwrap();
+
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+
+    // the guard keeps the connection locked until it is read
+    let guard = client.conn.prepare_read_exclusive().unwrap();
+    assert!(client.conn.backend().try_lock().is_err());
+    assert!(guard.connection_fd() >= 0);
+    guard.read().unwrap();
+
+    let mut globals = Vec::new();
+    client.event_queue.dispatch_pending(&mut BorrowingHandler { globals: &mut globals }).unwrap();
+    assert_eq!(globals, ["wl_compositor".to_owned()]);
+}
+
+#[test]
+fn client_dispatch_budget() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(2, ());
+
+    let (_, mut client) = server.add_client::<BorrowingHandler>();
+
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    client.conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    client.conn.read_without_dispatch().unwrap();
+
+    let mut globals = Vec::new();
+    let mut handler = BorrowingHandler { globals: &mut globals };
+
+    // the events exceeding the budget stay in the queue
+    assert!(client.event_queue.has_pending());
+    client.event_queue.set_budget(Some(1));
+    assert_eq!(client.event_queue.dispatch_pending(&mut handler).unwrap(), 1);
+    assert!(client.event_queue.has_pending());
+    assert_eq!(client.event_queue.dispatch_budget(&mut handler, 5).unwrap(), 1);
+    assert!(!client.event_queue.has_pending());
+
+    assert_eq!(globals, ["wl_compositor".to_owned(), "wl_output".to_owned()]);
+}
+
+#[test]
+fn client_event_queue_inspection() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(2, ());
+
+    let (_, mut client) = server.add_client::<BorrowingHandler>();
+
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    client.conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    client.conn.read_without_dispatch().unwrap();
+
+    // peeking leaves the events in the queue
+    assert!(client.event_queue.has_pending());
+    assert_eq!(client.event_queue.pending_len(), 2);
+    let names = client
+        .event_queue
+        .peek()
+        .map(|msg| {
+            let interface = msg.sender_id.interface();
+            (interface.name, interface.events[msg.opcode as usize].name)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, [("wl_registry", "global"), ("wl_registry", "global")]);
+    assert_eq!(client.event_queue.pending_len(), 2);
+
+    let mut globals = Vec::new();
+    let mut handler = BorrowingHandler { globals: &mut globals };
+    assert_eq!(client.event_queue.dispatch_budget(&mut handler, 1).unwrap(), 1);
+    assert_eq!(client.event_queue.pending_len(), 1);
+
+    // the drained events are not dispatched
+    let mut events = Vec::new();
+    assert_eq!(client.event_queue.drain_into(&mut events), 1);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sender_id.interface().name, "wl_registry");
+    assert!(matches!(
+        events[0].args[1],
+        wayc::backend::protocol::Argument::Str(ref name) if name.to_bytes() == b"wl_output"
+    ));
+    assert_eq!(client.event_queue.pending_len(), 0);
+    assert!(!client.event_queue.has_pending());
+    assert_eq!(client.event_queue.dispatch_pending(&mut handler).unwrap(), 0);
+
+    assert_eq!(globals, ["wl_compositor".to_owned()]);
+}
+
+#[test]
+fn client_ignore_remaining_events() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+
+    let (_, mut client) = server.add_client::<BorrowingHandler>();
+
+    let ignored = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    client.conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    client.conn.read_without_dispatch().unwrap();
+    assert_eq!(client.event_queue.pending_len(), 2);
+
+    // the event already queued for the ignored registry is dropped
+    ignored.ignore_remaining_events(&mut client.conn.handle());
+    let mut globals = Vec::new();
+    let mut handler = BorrowingHandler { globals: &mut globals };
+    client.event_queue.dispatch_pending(&mut handler).unwrap();
+
+    // and so are the ones received afterwards
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(2, ());
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    client.conn.read_without_dispatch().unwrap();
+    assert_eq!(client.event_queue.pending_len(), 1);
+    client.event_queue.dispatch_pending(&mut handler).unwrap();
+
+    assert_eq!(globals, ["wl_compositor".to_owned(), "wl_output".to_owned()]);
+}
+
+#[test]
+fn client_any_event_queue_views() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(2, ());
+
+    let (_, client) = server.add_client::<BorrowingHandler>();
+    let TestClient { conn, display, event_queue } = client;
+
+    // the registry is created before the queue is converted, with the original state type
+    let registry_handle = event_queue.handle();
+    display.get_registry(&mut conn.handle(), &registry_handle, ()).unwrap();
+
+    let any_queue = event_queue.into_any();
+    let mut registry_view = any_queue.view_from_handle(&registry_handle).unwrap();
+    let mut sync_view = any_queue.view::<SyncHandler>();
+    display.sync(&mut conn.handle(), &sync_view.handle(), ()).unwrap();
+
+    assert!(any_queue.view_from_handle(&conn.new_event_queue::<SyncHandler>().handle()).is_none());
+
+    conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    conn.read_without_dispatch().unwrap();
+
+    // each view only dispatches the events of its own objects
+    let mut sync_handler = SyncHandler { done: false };
+    assert_eq!(sync_view.dispatch_pending(&mut sync_handler).unwrap(), 1);
+    assert!(sync_handler.done);
+
+    let mut globals = Vec::new();
+    assert_eq!(
+        registry_view.dispatch_pending(&mut BorrowingHandler { globals: &mut globals }).unwrap(),
+        2
+    );
+    assert_eq!(globals, ["wl_compositor".to_owned(), "wl_output".to_owned()]);
+    assert_eq!(sync_view.dispatch_pending(&mut sync_handler).unwrap(), 0);
+}
+
+#[test]
+fn client_event_queue_profiler() {
+    let mut server = TestServer::new();
+
+    let (_, client) = server.add_client::<SyncHandler>();
+    let TestClient { conn, display, mut event_queue } = client;
+
+    let timings = Arc::new(Mutex::new(Vec::new()));
+    let profiler_timings = timings.clone();
+    event_queue.set_profiler(move |timing| profiler_timings.lock().unwrap().push(*timing));
+
+    display.sync(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+    conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    conn.read_without_dispatch().unwrap();
+    ::std::thread::sleep(::std::time::Duration::from_millis(50));
+
+    let mut sync_handler = SyncHandler { done: false };
+    assert_eq!(event_queue.dispatch_pending(&mut sync_handler).unwrap(), 1);
+    assert!(sync_handler.done);
+
+    // the event waited in the queue since it was read
+    let timings = timings.lock().unwrap();
+    assert_eq!(timings.len(), 1);
+    assert_eq!(timings[0].interface.name, "wl_callback");
+    assert_eq!(timings[0].event_name(), "done");
+    assert!(timings[0].queued >= ::std::time::Duration::from_millis(50));
+    assert!(timings[0].total() <= timings[0].received.elapsed());
+}
+
+#[test]
+fn client_event_queue_latency_watermark() {
+    let mut server = TestServer::new();
+
+    let (_, client) = server.add_client::<SyncHandler>();
+    let TestClient { conn, display, mut event_queue } = client;
+
+    assert_eq!(event_queue.latency_watermark(), ::std::time::Duration::ZERO);
+    event_queue.enable_latency_watermark();
+
+    display.sync(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+    conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    conn.read_without_dispatch().unwrap();
+    ::std::thread::sleep(::std::time::Duration::from_millis(50));
+
+    let mut sync_handler = SyncHandler { done: false };
+    assert_eq!(event_queue.dispatch_pending(&mut sync_handler).unwrap(), 1);
+    assert!(event_queue.latency_watermark() >= ::std::time::Duration::from_millis(50));
+
+    event_queue.reset_latency_watermark();
+    assert_eq!(event_queue.latency_watermark(), ::std::time::Duration::ZERO);
+}
+
+#[test]
+fn client_nested_dispatch() {
+    let mut server = TestServer::new();
+
+    let (_, client) = server.add_client::<NestedHandler>();
+    let TestClient { conn, display, mut event_queue } = client;
+
+    display.sync(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+    conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    conn.read_without_dispatch().unwrap();
+
+    let mut handler = NestedHandler {
+        conn: conn.clone(),
+        other_queue: conn.new_event_queue(),
+        errors: Vec::new(),
+    };
+    assert_eq!(event_queue.dispatch_pending(&mut handler).unwrap(), 1);
+
+    // the blocking operations fail rather than deadlocking
+    assert_eq!(handler.errors.len(), 4);
+    assert!(handler
+        .errors
+        .iter()
+        .all(|e| matches!(e, wayc::backend::WaylandError::NestedDispatch)));
+
+    // and the connection is still usable afterwards
+    let mut sync_handler = SyncHandler { done: false };
+    let mut sync_queue = conn.new_event_queue();
+    display.sync(&mut conn.handle(), &sync_queue.handle(), ()).unwrap();
+    conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    sync_queue.blocking_dispatch(&mut sync_handler).unwrap();
+    assert!(sync_handler.done);
+}
+
+#[test]
+fn client_poll_event_source() {
+    use wayc::poll::{EventSource, Readiness, WaylandSource};
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+
+    let (_, client) = server.add_client::<BorrowingHandler>();
+    let TestClient { conn, display, mut event_queue } = client;
+
+    let mut source = WaylandSource::new(conn.clone()).unwrap();
+    display.get_registry(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+
+    // the source flushes the request, and the read is cancelled if the fd is not ready
+    source.before_poll().unwrap();
+    assert_eq!(source.after_poll(Readiness::default()).unwrap(), 0);
+
+    server.answer(&mut ServerHandler);
+
+    source.before_poll().unwrap();
+    let mut fds = [nix::poll::PollFd::new(source.fd(), nix::poll::PollFlags::POLLIN)];
+    assert_eq!(nix::poll::poll(&mut fds, 1000).unwrap(), 1);
+    assert_eq!(source.after_poll(Readiness { readable: true, error: false }).unwrap(), 1);
+
+    let mut globals = Vec::new();
+    event_queue.dispatch_pending(&mut BorrowingHandler { globals: &mut globals }).unwrap();
+    assert_eq!(globals, ["wl_compositor".to_owned()]);
+}
+
+#[test]
+fn client_connection_set() {
+    use wayc::connection_set::ConnectionSet;
+
+    let mut server_1 = TestServer::new();
+    server_1.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    let (_, client_1) = server_1.add_client::<TaggedHandler>();
+
+    let mut server_2 = TestServer::new();
+    server_2.display.create_global::<ways::protocol::wl_output::WlOutput>(1, ());
+    let (_, client_2) = server_2.add_client::<TaggedHandler>();
+
+    let mut set = ConnectionSet::new();
+    let id_1 = set.insert(client_1.conn.clone()).unwrap();
+    let id_2 = set.insert(client_2.conn.clone()).unwrap();
+    assert_ne!(id_1, id_2);
+    assert_eq!(set.len(), 2);
+
+    for (id, client) in [(id_1, &client_1), (id_2, &client_2)] {
+        let qh = set.queue_handle(id).unwrap();
+        client.display.get_registry(&mut client.conn.handle(), &qh, ()).unwrap();
+    }
+
+    let mut handler = TaggedHandler { current: None, globals: Vec::new() };
+
+    // the requests are flushed, and nothing is received before the servers answer
+    let timeout = Some(std::time::Duration::from_millis(0));
+    assert_eq!(set.blocking_dispatch(&mut handler, timeout).unwrap(), 0);
+
+    server_1.answer(&mut ServerHandler);
+    server_2.answer(&mut ServerHandler);
+
+    let timeout = Some(std::time::Duration::from_millis(1000));
+    assert_eq!(set.blocking_dispatch(&mut handler, timeout).unwrap(), 2);
+    assert_eq!(
+        handler.globals,
+        [(id_1, "wl_compositor".to_owned()), (id_2, "wl_output".to_owned())]
+    );
+    assert_eq!(handler.current, None);
+
+    // a removed connection is not dispatched anymore
+    assert!(set.remove(id_1).is_some());
+    assert!(set.connection(id_1).is_none());
+    assert_eq!(set.ids().collect::<Vec<_>>(), [id_2]);
+}
+
+struct TaggedHandler {
+    current: Option<wayc::connection_set::ConnectionId>,
+    globals: Vec<(wayc::connection_set::ConnectionId, String)>,
+}
+
+impl wayc::connection_set::CurrentConnection for TaggedHandler {
+    fn set_current_connection(&mut self, connection: Option<wayc::connection_set::ConnectionId>) {
+        self.current = connection;
+    }
+}
+
+impl wayc::Dispatch<wayc::protocol::wl_registry::WlRegistry> for TaggedHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wayc::protocol::wl_registry::WlRegistry,
+        event: wayc::protocol::wl_registry::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let wayc::protocol::wl_registry::Event::Global { interface, .. } = event {
+            self.globals.push((self.current.unwrap(), interface));
+        }
+    }
+}
+
+struct BorrowingHandler<'a> {
+    globals: &'a mut Vec<String>,
+}
+
+impl wayc::Dispatch<wayc::protocol::wl_registry::WlRegistry> for BorrowingHandler<'_> {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wayc::protocol::wl_registry::WlRegistry,
+        event: wayc::protocol::wl_registry::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let wayc::protocol::wl_registry::Event::Global { interface, .. } = event {
+            self.globals.push(interface);
+        }
+    }
+}
+
+struct SyncHandler {
+    done: bool,
+}
+
+impl wayc::Dispatch<wayc::protocol::wl_callback::WlCallback> for SyncHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wayc::protocol::wl_callback::WlCallback,
+        event: wayc::protocol::wl_callback::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let wayc::protocol::wl_callback::Event::Done { .. } = event {
+            self.done = true;
+        }
+    }
+}
+
+struct NestedHandler {
+    conn: wayc::Connection,
+    other_queue: wayc::EventQueue<SyncHandler>,
+    errors: Vec<wayc::backend::WaylandError>,
+}
+
+impl wayc::Dispatch<wayc::protocol::wl_callback::WlCallback> for NestedHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wayc::protocol::wl_callback::WlCallback,
+        _: wayc::protocol::wl_callback::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        self.errors.push(self.conn.roundtrip().unwrap_err());
+        self.errors.push(self.conn.blocking_dispatch().unwrap_err());
+        self.errors.push(self.conn.prepare_read().unwrap_err());
+        match self.other_queue.dispatch_pending(&mut SyncHandler { done: false }) {
+            Err(wayc::DispatchError::Backend(e)) => self.errors.push(e),
+            other => panic!("Unexpected result of a nested dispatch: {:?}", other),
+        }
+    }
+}
+
+struct ServerHandler;
+
+server_ignore_impl!(ServerHandler => [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_output::WlOutput
+]);
+
+server_ignore_global_impl!(ServerHandler => [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_output::WlOutput
+]);
@@ -0,0 +1,138 @@
+#[macro_use]
+mod helpers;
+
+use std::sync::{Arc, Mutex};
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use wayc::{
+    backend::ObjectId,
+    dynamic::{DynamicError, Value},
+    Proxy,
+};
+
+#[test]
+fn dynamic_bind() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(4, ());
+    let mut server_ddata = ServerHandler { surfaces: 0 };
+
+    let (_, mut client) = server.add_client::<()>();
+
+    // bind the globals from the callback, as they are advertized
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let compositor = Arc::new(Mutex::new(None));
+    let events2 = events.clone();
+    let compositor2 = compositor.clone();
+    client.conn.set_dynamic_callback(move |conn, event| {
+        if event.interface.name == "wl_registry" && event.name == "global" {
+            assert_eq!(event.arg_names, ["name", "interface", "version"]);
+            let id = conn
+                .send_dynamic(
+                    &event.sender,
+                    "bind",
+                    &[event.args[0].clone(), event.args[1].clone(), Value::Uint(3)],
+                )
+                .unwrap();
+            *compositor2.lock().unwrap() = id;
+        }
+        events2.lock().unwrap().push((event.interface.name, event.name, event.args));
+    });
+
+    let display_id = client.display.id();
+    let registry = client.conn.send_dynamic(&display_id, "get_registry", &[]).unwrap().unwrap();
+    assert_eq!(registry.interface().name, "wl_registry");
+
+    roundtrip(&mut client, &mut server, &mut (), &mut server_ddata).unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [(
+            "wl_registry",
+            "global",
+            vec![Value::Uint(1), Value::Str("wl_compositor".into()), Value::Uint(4)]
+        )]
+    );
+    let compositor = compositor.lock().unwrap().clone().unwrap();
+    let info = client.conn.handle().object_info(compositor.clone()).unwrap();
+    assert_eq!(info.interface.name, "wl_compositor");
+    assert_eq!(info.version, 3);
+
+    // the objects created by the bound global are dynamic too
+    let surface = client.conn.send_dynamic(&compositor, "create_surface", &[]).unwrap().unwrap();
+    assert_eq!(surface.interface().name, "wl_surface");
+    assert!(client
+        .conn
+        .send_dynamic(&surface, "attach", &[Value::Object(None), Value::Int(0), Value::Int(0)])
+        .unwrap()
+        .is_none());
+
+    roundtrip(&mut client, &mut server, &mut (), &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.surfaces, 1);
+}
+
+#[test]
+fn dynamic_errors() {
+    let mut server = TestServer::<ServerHandler>::new();
+    let (_, client) = server.add_client::<()>();
+    let display_id: ObjectId = client.display.id();
+
+    assert!(matches!(
+        client.conn.send_dynamic(&display_id, "frobnicate", &[]),
+        Err(DynamicError::UnknownRequest { interface: "wl_display", .. })
+    ));
+    assert!(matches!(
+        client.conn.send_dynamic(&display_id, "sync", &[Value::Uint(0)]),
+        Err(DynamicError::ArgumentCount { request: "sync", expected: 0, found: 1 })
+    ));
+    // the events of the created object would have nowhere to go
+    assert!(matches!(
+        client.conn.send_dynamic(&display_id, "sync", &[]),
+        Err(DynamicError::NoCallback("sync"))
+    ));
+
+    client.conn.set_dynamic_callback(|_, _| {});
+    let registry = client.conn.send_dynamic(&display_id, "get_registry", &[]).unwrap().unwrap();
+    assert!(matches!(
+        client.conn.send_dynamic(
+            &registry,
+            "bind",
+            &[Value::Uint(1), Value::Uint(1), Value::Uint(1)]
+        ),
+        Err(DynamicError::BadArgument { request: "bind", index: 1, .. })
+    ));
+    assert!(matches!(
+        client.conn.send_dynamic(&registry, "bind", &[
+            Value::Uint(1),
+            Value::Str("wl_unknown".into()),
+            Value::Uint(1)
+        ]),
+        Err(DynamicError::UnknownInterface(ref name)) if name == "wl_unknown"
+    ));
+}
+
+struct ServerHandler {
+    surfaces: usize,
+}
+
+impl ways::Dispatch<ways::protocol::wl_compositor::WlCompositor> for ServerHandler {
+    type UserData = ();
+
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ways::protocol::wl_compositor::WlCompositor,
+        request: ways::protocol::wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let ways::protocol::wl_compositor::Request::CreateSurface { id } = request {
+            data_init.init(id, ());
+            self.surfaces += 1;
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [ways::protocol::wl_surface::WlSurface]);
+server_ignore_global_impl!(ServerHandler => [ways::protocol::wl_compositor::WlCompositor]);
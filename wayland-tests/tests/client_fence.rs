@@ -0,0 +1,173 @@
+#[macro_use]
+mod helpers;
+
+use helpers::*;
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use wayc::fence::{Fence, FrameThrottle};
+use ways::protocol::{wl_callback, wl_compositor, wl_surface};
+
+#[test]
+fn sync_fence() {
+    let mut server = TestServer::new();
+
+    let (_, client) = server.add_client::<()>();
+    let TestClient { conn, .. } = client;
+
+    let fence = Fence::sync(&mut conn.handle()).unwrap();
+    let signaled = Arc::new(AtomicU32::new(0));
+    let cb_signaled = signaled.clone();
+    fence.on_signal(move |serial| cb_signaled.store(serial + 1, Ordering::SeqCst));
+    assert!(!fence.is_signaled());
+
+    conn.flush().unwrap();
+    server.answer(&mut ServerHandler);
+
+    // the fence is signaled without dispatching any event queue
+    let serial = fence.wait(&conn).unwrap();
+    assert!(fence.is_signaled());
+    assert_eq!(fence.callback_data(), Some(serial));
+    assert_eq!(signaled.load(Ordering::SeqCst), serial + 1);
+
+    // callbacks registered after the signal are invoked immediately
+    let late_signaled = Arc::new(AtomicU32::new(0));
+    let cb_late_signaled = late_signaled.clone();
+    fence.on_signal(move |serial| cb_late_signaled.store(serial + 1, Ordering::SeqCst));
+    assert_eq!(late_signaled.load(Ordering::SeqCst), serial + 1);
+}
+
+#[test]
+fn frame_throttle() {
+    let mut server = TestServer::new();
+    server.display.create_global::<wl_compositor::WlCompositor>(1, ());
+    let mut server_ddata = FrameServerHandler { frame_callbacks: Vec::new(), commits: 0 };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let compositor = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let surface = compositor
+        .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    let draws = Arc::new(Mutex::new(Vec::new()));
+    let throttle_draws = draws.clone();
+    let mut throttle = FrameThrottle::new(surface, move |conn, surface, callback_data| {
+        throttle_draws.lock().unwrap().push(callback_data);
+        surface.commit(conn);
+    });
+
+    // the first frame is drawn immediately
+    assert!(throttle.request_redraw(&mut client.conn.handle()).unwrap());
+    assert!(throttle.is_waiting());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.commits, 1);
+    assert_eq!(server_ddata.frame_callbacks.len(), 1);
+
+    // the next redraws wait for the frame callback, and are merged
+    assert!(!throttle.request_redraw(&mut client.conn.handle()).unwrap());
+    assert!(!throttle.request_redraw(&mut client.conn.handle()).unwrap());
+    assert!(!throttle.dispatch(&mut client.conn.handle()).unwrap());
+    assert_eq!(*draws.lock().unwrap(), [None]);
+
+    server_ddata.frame_callbacks.remove(0).done(&mut server.display.handle(), 42);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert!(!throttle.is_waiting());
+    assert!(throttle.dispatch(&mut client.conn.handle()).unwrap());
+    assert!(!throttle.dispatch(&mut client.conn.handle()).unwrap());
+    assert_eq!(*draws.lock().unwrap(), [None, Some(42)]);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.commits, 2);
+    assert_eq!(server_ddata.frame_callbacks.len(), 1);
+
+    // a reset throttle no longer waits for the pending callback
+    throttle.reset();
+    assert!(throttle.request_redraw(&mut client.conn.handle()).unwrap());
+    assert_eq!(*draws.lock().unwrap(), [None, Some(42), Some(42)]);
+}
+
+struct ServerHandler;
+
+struct FrameServerHandler {
+    frame_callbacks: Vec<wl_callback::WlCallback>,
+    commits: usize,
+}
+
+impl ways::Dispatch<wl_compositor::WlCompositor> for FrameServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_compositor::WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_compositor::Request::CreateSurface { id } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<wl_surface::WlSurface> for FrameServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_surface::WlSurface,
+        request: wl_surface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_surface::Request::Frame { callback } => {
+                self.frame_callbacks.push(data_init.init(callback, ()))
+            }
+            wl_surface::Request::Commit => self.commits += 1,
+            _ => {}
+        }
+    }
+}
+
+server_ignore_impl!(FrameServerHandler => [wl_callback::WlCallback]);
+server_ignore_global_impl!(FrameServerHandler => [wl_compositor::WlCompositor]);
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+client_ignore_impl!(ClientHandler => [
+    wayc::protocol::wl_compositor::WlCompositor,
+    wayc::protocol::wl_surface::WlSurface
+]);
@@ -188,6 +188,49 @@ fn dead_object_argument() {
     assert!(client_ddata.entered);
 }
 
+#[test]
+fn dead_object_request() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_seat::WlSeat>(5, ());
+    let mut server_ddata = ServerHandler { output: None };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler::new();
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_seat::WlSeat, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            5..6,
+            (),
+        )
+        .unwrap();
+
+    assert!(seat.is_alive(&mut client.conn.handle()));
+
+    seat.release(&mut client.conn.handle());
+
+    assert!(!seat.is_alive(&mut client.conn.handle()));
+
+    let ret = seat.get_pointer(&mut client.conn.handle(), &client.event_queue.handle(), ());
+    match ret {
+        Err(wayc::DispatchError::DeadObject { interface, id }) => {
+            assert_eq!(interface, "wl_seat");
+            assert_eq!(id, seat.id());
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
 struct ServerHandler {
     output: Option<ways::protocol::wl_output::WlOutput>,
 }
@@ -229,11 +272,13 @@ impl ways::Dispatch<ways::protocol::wl_compositor::WlCompositor> for ServerHandl
 
 server_ignore_impl!(ServerHandler => [
     ways::protocol::wl_output::WlOutput,
-    ways::protocol::wl_surface::WlSurface
+    ways::protocol::wl_surface::WlSurface,
+    ways::protocol::wl_seat::WlSeat
 ]);
 
 server_ignore_global_impl!(ServerHandler => [
-    ways::protocol::wl_compositor::WlCompositor
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_seat::WlSeat
 ]);
 
 struct ClientHandler {
@@ -292,5 +337,7 @@ impl wayc::Dispatch<wayc::protocol::wl_surface::WlSurface> for ClientHandler {
 }
 
 client_ignore_impl!(ClientHandler => [
-    wayc::protocol::wl_output::WlOutput
+    wayc::protocol::wl_output::WlOutput,
+    wayc::protocol::wl_seat::WlSeat,
+    wayc::protocol::wl_pointer::WlPointer
 ]);
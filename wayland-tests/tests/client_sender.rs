@@ -0,0 +1,136 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use ways::protocol::{wl_compositor, wl_surface};
+
+#[test]
+fn sender_from_thread() {
+    let mut server = TestServer::new();
+    server.display.create_global::<wl_compositor::WlCompositor>(1, ());
+    let mut server_ddata = ServerHandler { requests: Vec::new() };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let compositor = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let surface = compositor
+        .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let sender = client.conn.sender();
+    {
+        // the sender does not wait for the connection held by this thread
+        let _handle = client.conn.handle();
+        let sender = sender.clone();
+        let surface = surface.clone();
+        let compositor = compositor.clone();
+        std::thread::spawn(move || {
+            sender.send_request(
+                &surface,
+                wayc::protocol::wl_surface::Request::Damage { x: 0, y: 0, width: 8, height: 8 },
+            );
+            // requests creating objects are dropped
+            sender
+                .send_request(&compositor, wayc::protocol::wl_compositor::Request::CreateRegion {});
+            sender.send_request(&surface, wayc::protocol::wl_surface::Request::Commit {});
+            assert!(!sender.flush().unwrap());
+        })
+        .join()
+        .unwrap();
+    }
+
+    // the queued requests are sent by the next flush of the connection
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["damage", "commit"]);
+
+    // the sender flushes by itself when the connection is free
+    sender.send_request(&surface, wayc::protocol::wl_surface::Request::Commit {});
+    assert!(sender.flush().unwrap());
+    server.answer(&mut server_ddata);
+    assert_eq!(server_ddata.requests, ["damage", "commit", "commit"]);
+}
+
+struct ServerHandler {
+    requests: Vec<&'static str>,
+}
+
+impl ways::Dispatch<wl_compositor::WlCompositor> for ServerHandler {
+    type UserData = ();
+
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_compositor::WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_compositor::Request::CreateSurface { id } => {
+                data_init.init(id, ());
+            }
+            wl_compositor::Request::CreateRegion { .. } => panic!("Unexpected region"),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl ways::Dispatch<wl_surface::WlSurface> for ServerHandler {
+    type UserData = ();
+
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_surface::WlSurface,
+        request: wl_surface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_surface::Request::Damage { .. } => self.requests.push("damage"),
+            wl_surface::Request::Commit { .. } => self.requests.push("commit"),
+            _ => {}
+        }
+    }
+}
+
+server_ignore_global_impl!(ServerHandler => [wl_compositor::WlCompositor]);
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+client_ignore_impl!(ClientHandler => [
+    wayc::protocol::wl_compositor::WlCompositor,
+    wayc::protocol::wl_surface::WlSurface
+]);
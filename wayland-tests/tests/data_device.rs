@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::io::FromRawFd;
+
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use ways::protocol::{
+    wl_data_device as sdevice, wl_data_device_manager as smanager, wl_data_offer as soffer,
+    wl_data_source as ssource, wl_seat as sseat,
+};
+use ways::Resource;
+
+use wayc::data_device::{DataDeviceError, DataDeviceEvent, DataDeviceState};
+use wayc::protocol::{
+    wl_data_device as cdevice, wl_data_device_manager as cmanager, wl_data_offer as coffer,
+    wl_data_source as csource, wl_seat as cseat,
+};
+
+#[test]
+fn clipboard_copy_paste() {
+    let mut server = TestServer::new();
+    server.display.create_global::<sseat::WlSeat>(1, ());
+    server.display.create_global::<smanager::WlDataDeviceManager>(3, ());
+    let mut server_ddata = ServerHandler {
+        device: None,
+        source: None,
+        source_mime_types: Vec::new(),
+        source_destroyed: false,
+        selection_serial: None,
+    };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new(), data: None };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata
+        .globals
+        .bind::<cseat::WlSeat, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let manager = client_ddata
+        .globals
+        .bind::<cmanager::WlDataDeviceManager, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            3..4,
+            (),
+        )
+        .unwrap();
+    let mut data = DataDeviceState::new(manager);
+    data.add_seat(&mut client.conn.handle(), &client.event_queue.handle(), &seat).unwrap();
+    client_ddata.data = Some(data);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // copy
+    let source = client_ddata
+        .data
+        .as_mut()
+        .unwrap()
+        .copy(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &seat,
+            42,
+            "text/plain",
+            b"copied".to_vec(),
+        )
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(server_ddata.source_mime_types, ["text/plain"]);
+    assert_eq!(server_ddata.selection_serial, Some(42));
+
+    // another client pastes the selection
+    let server_source = server_ddata.source.clone().unwrap();
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+    server_source.send(&mut server.display.handle(), "text/plain".into(), write_fd);
+    nix::unistd::close(write_fd).unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let mut contents = Vec::new();
+    unsafe { File::from_raw_fd(read_fd) }.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, b"copied");
+
+    // another client sets the selection
+    let server_device = server_ddata.device.clone().unwrap();
+    let s_client = server.display.handle().get_client(server_device.id()).unwrap();
+    let offer = s_client
+        .create_resource::<soffer::WlDataOffer, ServerHandler>(
+            &mut server.display.handle(),
+            server_device.version(),
+            (),
+        )
+        .unwrap();
+    {
+        let mut handle = server.display.handle();
+        server_device.data_offer(&mut handle, &offer);
+        offer.offer(&mut handle, "text/plain".into());
+        server_device.selection(&mut handle, Some(&offer));
+        server_source.cancelled(&mut handle);
+    }
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let data = client_ddata.data.as_mut().unwrap();
+    let events = data.take_events();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        events[0],
+        DataDeviceEvent::Selection { ref mime_types, .. } if mime_types == &["text/plain"]
+    ));
+    assert!(matches!(events[1], DataDeviceEvent::SourceFinished { source: ref s } if s == &source));
+    assert_eq!(data.selection_mime_types(&seat), ["text/plain"]);
+
+    // paste
+    assert!(matches!(
+        data.paste(&mut client.conn.handle(), &seat, "image/png"),
+        Err(DataDeviceError::UnsupportedMimeType(_))
+    ));
+    let pasted = data.paste(&mut client.conn.handle(), &seat, "text/plain").unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(futures_executor::block_on(pasted).unwrap(), b"pasted");
+    assert!(server_ddata.source_destroyed);
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    device: Option<sdevice::WlDataDevice>,
+    source: Option<ssource::WlDataSource>,
+    source_mime_types: Vec<String>,
+    source_destroyed: bool,
+    selection_serial: Option<u32>,
+}
+
+impl ways::Dispatch<smanager::WlDataDeviceManager> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &smanager::WlDataDeviceManager,
+        request: smanager::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            smanager::Request::CreateDataSource { id } => {
+                self.source = Some(data_init.init(id, ()));
+            }
+            smanager::Request::GetDataDevice { id, .. } => {
+                self.device = Some(data_init.init(id, ()));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<ssource::WlDataSource> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ssource::WlDataSource,
+        request: ssource::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            ssource::Request::Offer { mime_type } => self.source_mime_types.push(mime_type),
+            ssource::Request::Destroy => self.source_destroyed = true,
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<sdevice::WlDataDevice> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sdevice::WlDataDevice,
+        request: sdevice::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let sdevice::Request::SetSelection { serial, .. } = request {
+            self.selection_serial = Some(serial);
+        }
+    }
+}
+
+impl ways::Dispatch<soffer::WlDataOffer> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &soffer::WlDataOffer,
+        request: soffer::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let soffer::Request::Receive { mime_type, fd } = request {
+            assert_eq!(mime_type, "text/plain");
+            unsafe { File::from_raw_fd(fd) }.write_all(b"pasted").unwrap();
+        }
+    }
+}
+
+server_ignore_global_impl!(ServerHandler => [sseat::WlSeat, smanager::WlDataDeviceManager]);
+server_ignore_impl!(ServerHandler => [sseat::WlSeat]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    data: Option<DataDeviceState>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<DataDeviceState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut DataDeviceState {
+        self.data.as_mut().unwrap()
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler: [
+    cmanager::WlDataDeviceManager,
+    cdevice::WlDataDevice,
+    coffer::WlDataOffer,
+    csource::WlDataSource
+] => DataDeviceState);
+
+client_ignore_impl!(ClientHandler => [cseat::WlSeat]);
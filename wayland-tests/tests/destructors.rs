@@ -5,14 +5,14 @@ use helpers::{roundtrip, wayc, ways, TestServer};
 
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 
 #[test]
 fn resource_destructor_request() {
     let mut server = TestServer::new();
     server.display.create_global::<ways::protocol::wl_output::WlOutput>(3, ());
-    let mut server_ddata = ServerHandler { destructor_called: Arc::new(AtomicBool::new(false)) };
+    let mut server_ddata = ServerHandler::new();
 
     let (_, mut client) = server.add_client();
     let mut client_ddata = ClientHandler::new();
@@ -48,7 +48,7 @@ fn resource_destructor_request() {
 fn resource_destructor_cleanup() {
     let mut server = TestServer::new();
     server.display.create_global::<ways::protocol::wl_output::WlOutput>(3, ());
-    let mut server_ddata = ServerHandler { destructor_called: Arc::new(AtomicBool::new(false)) };
+    let mut server_ddata = ServerHandler::new();
 
     let (_, mut client) = server.add_client();
     let mut client_ddata = ClientHandler::new();
@@ -84,7 +84,7 @@ fn resource_destructor_cleanup() {
 fn client_destructor_cleanup() {
     let mut server = TestServer::new();
     server.display.create_global::<ways::protocol::wl_output::WlOutput>(3, ());
-    let mut server_ddata = ServerHandler { destructor_called: Arc::new(AtomicBool::new(false)) };
+    let mut server_ddata = ServerHandler::new();
 
     let destructor_called = Arc::new(AtomicBool::new(false));
 
@@ -119,6 +119,57 @@ fn client_destructor_cleanup() {
     assert!(destructor_called.load(Ordering::Acquire));
 }
 
+#[test]
+fn destruction_hooks() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(3, ());
+    let mut server_ddata = ServerHandler::new();
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler::new();
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let output = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_output::WlOutput, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            3..4,
+            (),
+        )
+        .unwrap();
+
+    let client_log = Arc::new(Mutex::new(Vec::new()));
+    for entry in ["input", "rendering"] {
+        let log = client_log.clone();
+        client
+            .conn
+            .handle()
+            .add_destruction_hook(&output, move |_| log.lock().unwrap().push(entry))
+            .unwrap();
+    }
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert!(client_log.lock().unwrap().is_empty());
+    assert!(server_ddata.hook_log.lock().unwrap().is_empty());
+
+    output.release(&mut client.conn.handle());
+
+    assert_eq!(*client_log.lock().unwrap(), ["input", "rendering"]);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(*server_ddata.hook_log.lock().unwrap(), ["destroyed", "input", "rendering"]);
+}
+
 struct DestructorClientData(Arc<AtomicBool>);
 
 impl ways::backend::ClientData<ServerHandler> for DestructorClientData {
@@ -135,28 +186,37 @@ impl ways::backend::ClientData<ServerHandler> for DestructorClientData {
 
 struct ServerHandler {
     destructor_called: Arc<AtomicBool>,
+    hook_log: Arc<Mutex<Vec<&'static str>>>,
 }
 
-struct ServerUData(Arc<AtomicBool>);
-
-impl ways::DestructionNotify for ServerUData {
-    fn object_destroyed(&self, _: ways::backend::ClientId, _: ways::backend::ObjectId) {
-        self.0.store(true, Ordering::Release);
+impl ServerHandler {
+    fn new() -> ServerHandler {
+        ServerHandler {
+            destructor_called: Arc::new(AtomicBool::new(false)),
+            hook_log: Arc::new(Mutex::new(Vec::new())),
+        }
     }
 }
 
+struct ServerUData(Arc<AtomicBool>, Arc<Mutex<Vec<&'static str>>>);
+
 impl ways::GlobalDispatch<ways::protocol::wl_output::WlOutput> for ServerHandler {
     type GlobalData = ();
 
     fn bind(
         &mut self,
-        _: &mut ways::DisplayHandle<'_>,
+        dh: &mut ways::DisplayHandle<'_>,
         _: &ways::Client,
         output: ways::New<ways::protocol::wl_output::WlOutput>,
         _: &Self::GlobalData,
         data_init: &mut ways::DataInit<'_, Self>,
     ) {
-        data_init.init(output, ServerUData(self.destructor_called.clone()));
+        let output = data_init
+            .init(output, ServerUData(self.destructor_called.clone(), self.hook_log.clone()));
+        for entry in ["input", "rendering"] {
+            let log = self.hook_log.clone();
+            dh.add_destruction_hook(&output, move |_, _| log.lock().unwrap().push(entry)).unwrap();
+        }
     }
 }
 
@@ -172,6 +232,11 @@ impl ways::Dispatch<ways::protocol::wl_output::WlOutput> for ServerHandler {
         _: &mut ways::DataInit<'_, Self>,
     ) {
     }
+
+    fn destroyed(_: ways::backend::ClientId, _: ways::backend::ObjectId, data: &ServerUData) {
+        data.0.store(true, Ordering::Release);
+        data.1.lock().unwrap().push("destroyed");
+    }
 }
 
 struct ClientHandler {
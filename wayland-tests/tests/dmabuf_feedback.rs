@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+use std::os::unix::io::IntoRawFd;
+
+use nix::libc::dev_t;
+use wayland_client::WEnum;
+use wayland_protocols::unstable::linux_dmabuf::v1::client::zwp_linux_dmabuf_feedback_v1::{
+    Event, TrancheFlags,
+};
+use wayland_protocols_helpers::unstable::linux_dmabuf::v1::feedback::{
+    DmabufFormat, DmabufTranche, FeedbackError, FeedbackParser,
+};
+
+const MAIN_DEVICE: dev_t = 0xe200;
+const SCANOUT_DEVICE: dev_t = 0xe280;
+
+// DRM_FORMAT_ARGB8888 and DRM_FORMAT_XRGB8888, linear and with a vendor modifier
+const TABLE: [DmabufFormat; 3] = [
+    DmabufFormat { format: 0x3432_5241, modifier: 0 },
+    DmabufFormat { format: 0x3432_5258, modifier: 0 },
+    DmabufFormat { format: 0x3432_5241, modifier: 0x0100_0000_0000_0001 },
+];
+
+// A format table event for the given contents, advertised with the given size
+fn format_table(contents: &[u8], size: u32) -> Event {
+    let mut file = tempfile::tempfile().unwrap();
+    file.write_all(contents).unwrap();
+    Event::FormatTable { fd: file.into_raw_fd(), size }
+}
+
+// The contents of a format table
+fn table_bytes(formats: &[DmabufFormat]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for format in formats {
+        bytes.extend_from_slice(&format.format.to_ne_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&format.modifier.to_ne_bytes());
+    }
+    bytes
+}
+
+// A tranche formats event for the given indices in the format table
+fn indices(indices: &[u16]) -> Event {
+    Event::TrancheFormats { indices: indices.iter().flat_map(|i| i.to_ne_bytes()).collect() }
+}
+
+// Feed a whole feedback to the parser, only its last event completing it
+fn feed(parser: &mut FeedbackParser, events: Vec<Event>) -> Vec<DmabufTranche> {
+    let count = events.len();
+    let mut feedback = None;
+    for (i, event) in events.into_iter().enumerate() {
+        feedback = parser.handle_event(event).unwrap();
+        assert_eq!(feedback.is_some(), i + 1 == count);
+    }
+    let feedback = feedback.unwrap();
+    assert_eq!(feedback.main_device, MAIN_DEVICE);
+    feedback.tranches
+}
+
+#[test]
+fn tranche_ordering() {
+    let mut parser = FeedbackParser::new();
+    let bytes = table_bytes(&TABLE);
+    parser.handle_event(format_table(&bytes, bytes.len() as u32)).unwrap();
+    assert_eq!(parser.format_table(), TABLE);
+
+    let tranches = feed(
+        &mut parser,
+        vec![
+            Event::MainDevice { device: MAIN_DEVICE.to_ne_bytes().to_vec() },
+            // the most preferred tranche comes first
+            Event::TrancheTargetDevice { device: SCANOUT_DEVICE.to_ne_bytes().to_vec() },
+            indices(&[2, 0]),
+            Event::TrancheFlags { flags: WEnum::Value(TrancheFlags::Scanout) },
+            Event::TrancheDone,
+            // the formats of a tranche may be split across several events
+            indices(&[1]),
+            indices(&[0]),
+            Event::TrancheDone,
+            Event::Done,
+        ],
+    );
+    assert_eq!(
+        tranches,
+        [
+            DmabufTranche {
+                target_device: SCANOUT_DEVICE,
+                formats: vec![TABLE[2], TABLE[0]],
+                flags: TrancheFlags::Scanout,
+            },
+            // the target device and the flags do not carry over to the next tranche
+            DmabufTranche {
+                target_device: MAIN_DEVICE,
+                formats: vec![TABLE[1], TABLE[0]],
+                flags: TrancheFlags::empty(),
+            },
+        ]
+    );
+
+    // the next feedback starts from scratch, but keeps the format table
+    let tranches = feed(
+        &mut parser,
+        vec![
+            Event::MainDevice { device: MAIN_DEVICE.to_ne_bytes().to_vec() },
+            indices(&[1]),
+            Event::TrancheDone,
+            Event::Done,
+        ],
+    );
+    assert_eq!(
+        tranches,
+        [DmabufTranche {
+            target_device: MAIN_DEVICE,
+            formats: vec![TABLE[1]],
+            flags: TrancheFlags::empty()
+        }]
+    );
+
+    // the main device must be sent with each feedback
+    assert!(matches!(parser.handle_event(Event::Done), Err(FeedbackError::MissingMainDevice)));
+}
+
+#[test]
+fn truncated_format_table() {
+    let mut parser = FeedbackParser::new();
+    let bytes = table_bytes(&TABLE);
+    parser.handle_event(format_table(&bytes, bytes.len() as u32)).unwrap();
+
+    // the advertised size goes beyond the end of the file
+    let truncated = table_bytes(&TABLE[..1]);
+    match parser.handle_event(format_table(&truncated, bytes.len() as u32)) {
+        Err(FeedbackError::FormatTable(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    // the previous table is left in place
+    assert_eq!(parser.format_table(), TABLE);
+
+    // an empty table is valid, and needs no file contents
+    parser.handle_event(format_table(&[], 0)).unwrap();
+    assert!(parser.format_table().is_empty());
+}
+
+#[test]
+fn misaligned_format_table() {
+    let mut parser = FeedbackParser::new();
+
+    // the size is not a whole number of entries
+    let bytes = table_bytes(&TABLE);
+    match parser.handle_event(format_table(&bytes, bytes.len() as u32 - 8)) {
+        Err(FeedbackError::FormatTable(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert!(parser.format_table().is_empty());
+
+    // a shorter size than the file only reads the advertised entries
+    parser.handle_event(format_table(&bytes, 32)).unwrap();
+    assert_eq!(parser.format_table(), &TABLE[..2]);
+
+    // the tranches can only refer to the entries of the table
+    parser.handle_event(Event::MainDevice { device: MAIN_DEVICE.to_ne_bytes().to_vec() }).unwrap();
+    assert!(matches!(
+        parser.handle_event(indices(&[2])),
+        Err(FeedbackError::InvalidFormatIndex(2))
+    ));
+    // as well as devices of the size of a dev_t
+    assert!(matches!(
+        parser.handle_event(Event::TrancheTargetDevice { device: vec![0; 3] }),
+        Err(FeedbackError::InvalidDevice)
+    ));
+}
@@ -0,0 +1,214 @@
+// The examples of wayland-client, run against a mock compositor
+
+#[macro_use]
+mod helpers;
+
+use helpers::{ways, TestServer};
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use ways::protocol::{
+    wl_buffer::WlBuffer as ServerBuffer, wl_output::WlOutput as ServerOutput,
+    wl_shm::WlShm as ServerShm, wl_shm_pool::WlShmPool as ServerPool,
+};
+use ways::shm::{with_buffer_contents, ShmState};
+
+use wayland_protocols::wlr::unstable::screencopy::v1::server::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1 as ServerFrame},
+    zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1 as ServerManager},
+};
+
+#[path = "../../wayland-client/examples/output_info.rs"]
+#[allow(dead_code)]
+mod output_info;
+
+#[path = "../../wayland-client/examples/screencopy.rs"]
+#[allow(dead_code)]
+mod screencopy;
+
+#[test]
+fn example_output_info() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput>(4, ());
+    let (_, client) = server.add_client::<()>();
+
+    let (out, _) = with_server(server, MockCompositor::new(), || {
+        let mut out = Vec::new();
+        output_info::run(&client.conn, &mut out).unwrap();
+        out
+    });
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "Output 0: DP-1\n\
+         \tdescription: ACME Display\n\
+         \tmake: ACME, model: Display\n\
+         \tlocation: 0, 0\n\
+         \tphysical size: 520 x 290 mm\n\
+         \tscale: 1\n\
+         \ttransform: Normal\n\
+         \tmode: 4 x 2 @ 60.000 Hz, current, preferred\n"
+    );
+}
+
+#[test]
+fn example_screencopy() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput>(1, ());
+    server.display.create_global::<ServerShm>(1, ());
+    server.display.create_global::<ServerManager>(3, ());
+    let (_, client) = server.add_client::<()>();
+    let directory = tempfile::tempdir().unwrap();
+
+    let (paths, compositor) = with_server(server, MockCompositor::new(), || {
+        screencopy::run(&client.conn, directory.path()).unwrap()
+    });
+
+    // the mock compositor does not draw anything in the buffer
+    assert_eq!(compositor.copies, 1);
+    assert_eq!(paths, [directory.path().join("output-0.ppm")]);
+    let mut expected = b"P6\n4 2\n255\n".to_vec();
+    expected.resize(expected.len() + 4 * 2 * 3, 0);
+    assert_eq!(std::fs::read(&paths[0]).unwrap(), expected);
+}
+
+// Run the server on a thread while the client does its work
+fn with_server<T>(
+    mut server: TestServer<MockCompositor>,
+    mut compositor: MockCompositor,
+    client: impl FnOnce() -> T,
+) -> (T, MockCompositor) {
+    let kill_switch = Arc::new(AtomicBool::new(false));
+    let server_kill_switch = kill_switch.clone();
+
+    let server_thread = ::std::thread::spawn(move || {
+        loop {
+            server.display.dispatch_clients(&mut compositor).unwrap();
+            server.display.flush_clients().unwrap();
+            if server_kill_switch.load(Ordering::Acquire) {
+                break;
+            }
+        }
+        compositor
+    });
+
+    let ret = client();
+
+    kill_switch.store(true, Ordering::Release);
+    (ret, server_thread.join().unwrap())
+}
+
+/*
+ * Mock compositor, with a single 4x2 output
+ */
+
+struct MockCompositor {
+    shm: ShmState,
+    copies: usize,
+}
+
+impl MockCompositor {
+    fn new() -> MockCompositor {
+        MockCompositor { shm: ShmState::new([]), copies: 0 }
+    }
+}
+
+impl AsMut<ShmState> for MockCompositor {
+    fn as_mut(&mut self) -> &mut ShmState {
+        &mut self.shm
+    }
+}
+
+ways::delegate_dispatch!(MockCompositor: [ServerShm, ServerPool, ServerBuffer] => ShmState);
+ways::delegate_global_dispatch!(MockCompositor: [ServerShm] => ShmState);
+
+server_ignore_impl!(MockCompositor => [ServerOutput]);
+server_ignore_global_impl!(MockCompositor => [ServerManager]);
+
+impl ways::GlobalDispatch<ServerOutput> for MockCompositor {
+    type GlobalData = ();
+
+    fn bind(
+        &mut self,
+        dh: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        output: ways::New<ServerOutput>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        use ways::protocol::wl_output::{Mode, Subpixel, Transform};
+
+        let output = data_init.init(output, ());
+        output.geometry(
+            dh,
+            0,
+            0,
+            520,
+            290,
+            Subpixel::Unknown,
+            "ACME".into(),
+            "Display".into(),
+            Transform::Normal,
+        );
+        output.mode(dh, Mode::Current | Mode::Preferred, 4, 2, 60000);
+        if ways::Resource::version(&output) >= 4 {
+            output.name(dh, "DP-1".into());
+            output.description(dh, "ACME Display".into());
+        }
+        if ways::Resource::version(&output) >= 2 {
+            output.done(dh);
+        }
+    }
+}
+
+impl ways::Dispatch<ServerManager> for MockCompositor {
+    type UserData = ();
+
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ServerManager,
+        request: zwlr_screencopy_manager_v1::Request,
+        _: &(),
+        dh: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let zwlr_screencopy_manager_v1::Request::CaptureOutput { frame, .. } = request {
+            let frame = data_init.init(frame, ());
+            frame.buffer(dh, ways::protocol::wl_shm::Format::Xrgb8888, 4, 2, 16);
+            if ways::Resource::version(&frame) >= 3 {
+                frame.buffer_done(dh);
+            }
+        }
+    }
+}
+
+impl ways::Dispatch<ServerFrame> for MockCompositor {
+    type UserData = ();
+
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        frame: &ServerFrame,
+        request: zwlr_screencopy_frame_v1::Request,
+        _: &(),
+        dh: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let zwlr_screencopy_frame_v1::Request::Copy { buffer } = request {
+            let valid = with_buffer_contents(&buffer, |_, metadata| {
+                (metadata.width, metadata.height, metadata.stride) == (4, 2, 16)
+            });
+            if valid.unwrap_or(false) {
+                self.copies += 1;
+                frame.flags(dh, zwlr_screencopy_frame_v1::Flags::empty());
+                frame.ready(dh, 0, 0, 0);
+            } else {
+                frame.failed(dh);
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+#[macro_use]
+mod helpers;
+
+use helpers::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glib_crate as glib;
+use wayc::glib_source::GlibSource;
+
+#[test]
+fn glib_source_roundtrip() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+
+    let (_, client) = server.add_client::<SyncHandler>();
+    let TestClient { conn, display, mut event_queue } = client;
+
+    display.get_registry(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+    display.sync(&mut conn.handle(), &event_queue.handle(), ()).unwrap();
+
+    let handler = Rc::new(RefCell::new(SyncHandler { globals: Vec::new(), done: false }));
+    let dispatches = Rc::new(RefCell::new(0));
+
+    let context = glib::MainContext::new();
+    let source = {
+        let handler = handler.clone();
+        let dispatches = dispatches.clone();
+        GlibSource::attach(conn, Some(&context), move |read| {
+            read.unwrap();
+            *dispatches.borrow_mut() += 1;
+            event_queue.dispatch_pending(&mut *handler.borrow_mut()).unwrap();
+            glib::Continue(true)
+        })
+        .unwrap()
+    };
+
+    // the callback is invoked right away for the events already read
+    assert!(context.iteration(false));
+    assert_eq!(*dispatches.borrow(), 1);
+
+    // the requests are flushed when the context prepares the source, and the events are
+    // dispatched once it reads them
+    for _ in 0..10 {
+        if handler.borrow().done {
+            break;
+        }
+        context.iteration(false);
+        server.answer(&mut ServerHandler);
+        ::std::thread::sleep(::std::time::Duration::from_millis(100));
+        context.iteration(false);
+    }
+    assert!(handler.borrow().done);
+    assert_eq!(handler.borrow().globals, ["wl_compositor".to_owned()]);
+
+    // the source is removed from the context when dropped
+    let raw = source.source().clone();
+    drop(source);
+    assert!(raw.is_destroyed());
+}
+
+struct SyncHandler {
+    globals: Vec<String>,
+    done: bool,
+}
+
+impl wayc::Dispatch<wayc::protocol::wl_registry::WlRegistry> for SyncHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wayc::protocol::wl_registry::WlRegistry,
+        event: wayc::protocol::wl_registry::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let wayc::protocol::wl_registry::Event::Global { interface, .. } = event {
+            self.globals.push(interface);
+        }
+    }
+}
+
+impl wayc::Dispatch<wayc::protocol::wl_callback::WlCallback> for SyncHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &wayc::protocol::wl_callback::WlCallback,
+        event: wayc::protocol::wl_callback::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let wayc::protocol::wl_callback::Event::Done { .. } = event {
+            self.done = true;
+        }
+    }
+}
+
+struct ServerHandler;
+
+server_ignore_impl!(ServerHandler => [ways::protocol::wl_compositor::WlCompositor]);
+server_ignore_global_impl!(ServerHandler => [ways::protocol::wl_compositor::WlCompositor]);
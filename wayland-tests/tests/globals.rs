@@ -4,6 +4,7 @@ mod helpers;
 use helpers::{roundtrip, wayc, ways, TestServer};
 
 use ways::protocol::wl_compositor::WlCompositor as ServerCompositor;
+use ways::protocol::wl_fixes::WlFixes as ServerFixes;
 use ways::protocol::wl_output::WlOutput as ServerOutput;
 use ways::protocol::wl_shell::WlShell as ServerShell;
 
@@ -161,6 +162,56 @@ fn range_instantiate() {
     ));
 }
 
+#[test]
+fn version_negotiation() {
+    use wayc::{
+        globals::{negotiate_version, BindError},
+        protocol::wl_compositor::WlCompositor,
+        Proxy,
+    };
+
+    let max = WlCompositor::interface().version;
+    assert_eq!(negotiate_version::<WlCompositor>(3, 1..3).unwrap(), 2);
+    assert_eq!(negotiate_version::<WlCompositor>(1, 1..3).unwrap(), 1);
+    assert_eq!(negotiate_version::<WlCompositor>(max + 1, 1..u32::MAX).unwrap(), max);
+    assert!(matches!(
+        negotiate_version::<WlCompositor>(1, 2..4),
+        Err(BindError::WrongVersion { interface: "wl_compositor", got: 1, .. })
+    ));
+    assert!(matches!(
+        negotiate_version::<WlCompositor>(max, max + 1..max + 3),
+        Err(BindError::WrongVersion { .. })
+    ));
+
+    // binding through the global list clamps to the supported range
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(4, ());
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut ServerHandler).unwrap();
+
+    let compositor = client_ddata
+        .globals
+        .bind::<WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..3,
+            (),
+        )
+        .unwrap();
+    assert_eq!(compositor.version(), 2);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut ServerHandler).unwrap();
+}
+
 #[test]
 #[should_panic]
 fn wrong_version_create_global() {
@@ -312,11 +363,314 @@ fn two_step_binding() {
     roundtrip(&mut client, &mut server, &mut client_ddata, &mut ServerHandler).unwrap();
 }
 
+#[test]
+fn capability_changes() {
+    use wayc::globals::CapabilityChange;
+
+    // the globals advertized by a server, as seen by a client
+    fn advertized_globals(server: &mut TestServer<ServerHandler>) -> wayc::globals::GlobalList {
+        let (_, mut client) = server.add_client();
+        let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+        client
+            .display
+            .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+            .unwrap();
+        roundtrip(&mut client, server, &mut client_ddata, &mut ServerHandler).unwrap();
+        client_ddata.globals
+    }
+
+    let mut old_server = TestServer::new();
+    old_server.display.create_global::<ServerCompositor>(4, ());
+    old_server.display.create_global::<ServerOutput>(3, ());
+    old_server.display.create_global::<ServerOutput>(2, ());
+    old_server.display.create_global::<ServerShell>(1, ());
+    let old_globals = advertized_globals(&mut old_server);
+
+    // the compositor we reconnect to is older
+    let mut new_server = TestServer::new();
+    new_server.display.create_global::<ServerCompositor>(4, ());
+    new_server.display.create_global::<ServerOutput>(2, ());
+    let new_globals = advertized_globals(&mut new_server);
+
+    let changes = new_globals.capability_changes(&old_globals);
+    assert_eq!(
+        changes,
+        [
+            CapabilityChange {
+                interface: "wl_output".into(),
+                old_version: Some(3),
+                new_version: Some(2)
+            },
+            CapabilityChange {
+                interface: "wl_shell".into(),
+                old_version: Some(1),
+                new_version: None
+            },
+        ]
+    );
+    assert!(changes.iter().all(CapabilityChange::is_downgrade));
+
+    // and going back is an upgrade
+    let changes = old_globals.capability_changes(&new_globals);
+    assert_eq!(changes.len(), 2);
+    assert!(!changes.iter().any(CapabilityChange::is_downgrade));
+
+    assert!(new_globals.capability_changes(&new_globals).is_empty());
+}
+
+#[test]
+fn fetch_global_contents() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+    use wayc::{
+        protocol::wl_output::{Subpixel, Transform, WlOutput},
+        WEnum,
+    };
+
+    let kill_switch = Arc::new(AtomicBool::new(false));
+    let server_kill_switch = kill_switch.clone();
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput>(4, "DP-1");
+    server.display.create_global::<ServerCompositor>(1, ());
+    server.display.create_global::<ServerOutput>(2, "HDMI-A-1");
+
+    let (_, client) = server.add_client::<()>();
+
+    let server_thread = ::std::thread::spawn(move || loop {
+        server.display.dispatch_clients(&mut OutputServerHandler).unwrap();
+        server.display.flush_clients().unwrap();
+        if server_kill_switch.load(Ordering::Acquire) {
+            break;
+        }
+    });
+
+    let outputs = client.conn.fetch_global_contents::<WlOutput>().unwrap();
+
+    kill_switch.store(true, Ordering::Release);
+    server_thread.join().unwrap();
+
+    // the snapshots are in the order of the globals, with the events supported by their version
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].name.as_deref(), Some("DP-1"));
+    assert_eq!(outputs[1].name, None);
+    for output in &outputs {
+        assert_eq!(output.make, "ACME");
+        assert_eq!(output.location, (0, 0));
+        assert_eq!(output.physical_size, (520, 290));
+        assert_eq!(output.subpixel, Some(WEnum::Value(Subpixel::Unknown)));
+        assert_eq!(output.transform, Some(WEnum::Value(Transform::Normal)));
+        assert_eq!(output.scale, 2);
+        assert_eq!(output.modes.len(), 2);
+        assert!(!output.modes[0].current);
+        assert_eq!(output.modes[1].dimensions, (3840, 2160));
+        assert!(output.modes[1].current && output.modes[1].preferred);
+    }
+}
+
+#[test]
+fn describe_compositor() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let kill_switch = Arc::new(AtomicBool::new(false));
+    let server_kill_switch = kill_switch.clone();
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(4, ());
+    server.display.create_global::<ServerOutput>(4, "DP-1");
+
+    let (_, client) = server.add_client::<()>();
+
+    let server_thread = ::std::thread::spawn(move || loop {
+        server.display.dispatch_clients(&mut OutputServerHandler).unwrap();
+        server.display.flush_clients().unwrap();
+        if server_kill_switch.load(Ordering::Acquire) {
+            break;
+        }
+    });
+
+    let description = wayc::describe_compositor(&client.conn).unwrap();
+
+    kill_switch.store(true, Ordering::Release);
+    server_thread.join().unwrap();
+
+    let globals: Vec<_> =
+        description.globals.iter().map(|g| (g.interface.as_str(), g.version)).collect();
+    assert_eq!(globals, [("wl_compositor", 4), ("wl_output", 4)]);
+    assert_eq!(description.outputs.len(), 1);
+    assert_eq!(description.outputs[0].name.as_deref(), Some("DP-1"));
+    assert!(description.seats.is_empty());
+    assert!(description.shm_formats.is_empty());
+
+    let report = description.to_string();
+    assert!(report.contains("interface: 'wl_compositor', version: 4, name: 1\n"));
+    assert!(report.contains("\tname: DP-1\n"));
+    assert!(report.contains(
+        "\tmode: width: 3840 px, height: 2160 px, refresh: 60.000 Hz, current, preferred\n"
+    ));
+}
+
+#[test]
+fn probe_protocols() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let kill_switch = Arc::new(AtomicBool::new(false));
+    let server_kill_switch = kill_switch.clone();
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput>(2, "DP-1");
+    server.display.create_global::<ServerCompositor>(4, ());
+    server.display.create_global::<ServerOutput>(4, "HDMI-A-1");
+
+    let (_, client) = server.add_client::<()>();
+
+    let server_thread = ::std::thread::spawn(move || loop {
+        server.display.dispatch_clients(&mut OutputServerHandler).unwrap();
+        server.display.flush_clients().unwrap();
+        if server_kill_switch.load(Ordering::Acquire) {
+            break;
+        }
+    });
+
+    let support = client.conn.probe_protocols(&["wl_output", "wl_shell", "wl_compositor"]).unwrap();
+
+    kill_switch.store(true, Ordering::Release);
+    server_thread.join().unwrap();
+
+    // the highest version of the interfaces advertized several times is reported
+    assert_eq!(support.version("wl_output"), Some(4));
+    assert_eq!(support.version("wl_compositor"), Some(4));
+    assert!(!support.is_supported("wl_shell"));
+    // the interfaces which were not probed are not reported
+    assert_eq!(support.version("wl_seat"), None);
+    assert_eq!(
+        support.iter().collect::<Vec<_>>(),
+        [("wl_output", Some(4)), ("wl_shell", None), ("wl_compositor", Some(4))]
+    );
+}
+
+#[test]
+fn destroy_registry() {
+    use wayc::Proxy;
+
+    // the registry is left untouched if the compositor does not support wl_fixes
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(1, ());
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut ServerHandler).unwrap();
+    assert!(!client_ddata.globals.destroy_registry(&mut client.conn.handle(), &registry));
+    assert!(registry.is_alive(&mut client.conn.handle()));
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerFixes>(1, ());
+    let mut server_ddata = FixesServerHandler { requests: Vec::new() };
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // the registry is destroyed through a wl_fixes object, which is released right away
+    assert!(client_ddata.globals.destroy_registry(&mut client.conn.handle(), &registry));
+    assert!(!registry.is_alive(&mut client.conn.handle()));
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(server_ddata.requests, ["destroy_registry", "destroy"]);
+}
+
+#[test]
+fn registry_cache() {
+    use wayc::Proxy;
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(3, ());
+    let output = server.display.create_global::<ServerOutput>(2, ());
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+
+    let (mut globals, registry) =
+        wayc::globals::GlobalList::from_registry_cache(&mut client.conn.handle()).unwrap();
+    assert!(globals.list().is_empty());
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut ServerHandler).unwrap();
+    // the events of the registry are handled by the backend, not by the event queue
+    assert!(client_ddata.globals.list().is_empty());
+    globals.refresh_from_cache(&mut client.conn.handle());
+    let interfaces = globals.list().iter().map(|desc| &desc.interface[..]).collect::<Vec<_>>();
+    assert_eq!(interfaces, ["wl_compositor", "wl_output"]);
+
+    // the cached registry can bind the globals
+    let compositor = globals
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..4,
+            (),
+        )
+        .unwrap();
+    assert_eq!(compositor.version(), 3);
+
+    // a second list shares the same registry
+    let (other, other_registry) =
+        wayc::globals::GlobalList::from_registry_cache(&mut client.conn.handle()).unwrap();
+    assert_eq!(other_registry, registry);
+    assert_eq!(other.list().len(), 2);
+
+    server.display.remove_global(output);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut ServerHandler).unwrap();
+    globals.refresh_from_cache(&mut client.conn.handle());
+    assert_eq!(globals.list().len(), 1);
+    assert_eq!(globals.list()[0].interface, "wl_compositor");
+}
+
 struct ServerHandler;
 
 server_ignore_impl!(ServerHandler => [ServerCompositor, ServerShell, ServerOutput]);
 server_ignore_global_impl!(ServerHandler => [ServerCompositor, ServerShell, ServerOutput]);
 
+struct FixesServerHandler {
+    requests: Vec<&'static str>,
+}
+
+server_ignore_global_impl!(FixesServerHandler => [ServerFixes]);
+
+impl ways::Dispatch<ServerFixes> for FixesServerHandler {
+    type UserData = ();
+
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ServerFixes,
+        request: ways::protocol::wl_fixes::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        self.requests.push(match request {
+            ways::protocol::wl_fixes::Request::DestroyRegistry { .. } => "destroy_registry",
+            ways::protocol::wl_fixes::Request::Destroy => "destroy",
+            _ => unreachable!(),
+        });
+    }
+}
+
 struct ClientHandler {
     globals: wayc::globals::GlobalList,
 }
@@ -336,3 +690,43 @@ client_ignore_impl!(ClientHandler => [
     wayc::protocol::wl_shell::WlShell,
     wayc::protocol::wl_output::WlOutput
 ]);
+
+struct OutputServerHandler;
+
+server_ignore_impl!(OutputServerHandler => [ServerCompositor, ServerOutput]);
+server_ignore_global_impl!(OutputServerHandler => [ServerCompositor]);
+
+impl ways::GlobalDispatch<ServerOutput> for OutputServerHandler {
+    type GlobalData = &'static str;
+
+    fn bind(
+        &mut self,
+        dh: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        output: ways::New<ServerOutput>,
+        name: &&'static str,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        use ways::protocol::wl_output::{Mode, Subpixel, Transform};
+
+        let output = data_init.init(output, ());
+        output.geometry(
+            dh,
+            0,
+            0,
+            520,
+            290,
+            Subpixel::Unknown,
+            "ACME".into(),
+            "Display".into(),
+            Transform::Normal,
+        );
+        output.mode(dh, Mode::Current, 1920, 1080, 60000);
+        output.mode(dh, Mode::Current | Mode::Preferred, 3840, 2160, 60000);
+        output.scale(dh, 2);
+        if ways::Resource::version(&output) >= 4 {
+            output.name(dh, name.to_string());
+        }
+        output.done(dh);
+    }
+}
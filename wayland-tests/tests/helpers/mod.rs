@@ -65,7 +65,7 @@ impl<D> TestClient<D> {
     }
 }
 
-pub fn roundtrip<CD: 'static, SD: 'static>(
+pub fn roundtrip<CD, SD: 'static>(
     client: &mut TestClient<CD>,
     server: &mut TestServer<SD>,
     client_ddata: &mut CD,
@@ -86,8 +86,8 @@ pub fn roundtrip<CD: 'static, SD: 'static>(
     while !done2.load(Ordering::Acquire) {
         match client.conn.flush() {
             Ok(_) => {}
-            Err(wayc::backend::WaylandError::Io(e))
-                if e.kind() == ::std::io::ErrorKind::BrokenPipe => {}
+            Err(wayc::backend::WaylandError::Io { error, .. })
+                if error.kind() == ::std::io::ErrorKind::BrokenPipe => {}
             Err(e) => return Err(e),
         }
         ::std::thread::sleep(::std::time::Duration::from_millis(100));
@@ -121,6 +121,199 @@ impl wayc::backend::ObjectData for SyncData {
     fn destroyed(&self, _: wayc::backend::ObjectId) {}
 }
 
+/// Connect a new client to the server, and bind the `wl_compositor` global
+///
+/// The registry is returned as well, for the test to bind the other globals it needs.
+#[allow(clippy::type_complexity)]
+pub fn connect_client<CD, SD: 'static>(
+    server: &mut TestServer<SD>,
+    client_ddata: &mut CD,
+    server_ddata: &mut SD,
+) -> (
+    TestClient<CD>,
+    wayc::protocol::wl_registry::WlRegistry,
+    wayc::protocol::wl_compositor::WlCompositor,
+)
+where
+    CD: AsMut<wayc::globals::GlobalList>
+        + wayc::Dispatch<wayc::protocol::wl_registry::WlRegistry, UserData = ()>
+        + wayc::Dispatch<wayc::protocol::wl_compositor::WlCompositor, UserData = ()>,
+{
+    let (_, mut client) = server.add_client();
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, server, client_ddata, server_ddata).unwrap();
+    let compositor = client_ddata
+        .as_mut()
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    (client, registry, compositor)
+}
+
+/// A server with a single connected client, along with the state of both sides
+pub struct TestSetup<SD, CD> {
+    pub server: TestServer<SD>,
+    pub client: TestClient<CD>,
+    pub server_ddata: SD,
+    pub client_ddata: CD,
+}
+
+/// Connect a client to the server with [`connect_client`], and set up the objects of a test
+///
+/// `init` binds the other globals the test needs from the registry, and creates its objects. Its
+/// result is returned along with the setup, after a roundtrip.
+#[allow(clippy::type_complexity)]
+pub fn setup_client<SD: 'static, CD, T>(
+    mut server: TestServer<SD>,
+    mut server_ddata: SD,
+    mut client_ddata: CD,
+    init: impl FnOnce(
+        &mut TestClient<CD>,
+        &mut CD,
+        &wayc::protocol::wl_registry::WlRegistry,
+        &wayc::protocol::wl_compositor::WlCompositor,
+    ) -> T,
+) -> (TestSetup<SD, CD>, T)
+where
+    CD: AsMut<wayc::globals::GlobalList>
+        + wayc::Dispatch<wayc::protocol::wl_registry::WlRegistry, UserData = ()>
+        + wayc::Dispatch<wayc::protocol::wl_compositor::WlCompositor, UserData = ()>,
+{
+    let (mut client, registry, compositor) =
+        connect_client(&mut server, &mut client_ddata, &mut server_ddata);
+    let value = init(&mut client, &mut client_ddata, &registry, &compositor);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    (TestSetup { server, client, server_ddata, client_ddata }, value)
+}
+
+/// A server-side `wl_compositor` keeping track of the objects created by the clients
+///
+/// The handler of a test delegates `wl_compositor`, `wl_surface` and `wl_region` to it, and
+/// implements `AsMut<TestCompositor>`.
+#[derive(Debug, Default)]
+pub struct TestCompositor {
+    pub surfaces: Vec<ways::protocol::wl_surface::WlSurface>,
+    pub regions: Vec<ways::protocol::wl_region::WlRegion>,
+    /// The buffer of the last `wl_surface.attach` request
+    pub buffer: Option<ways::protocol::wl_buffer::WlBuffer>,
+}
+
+impl ways::DelegateDispatchBase<ways::protocol::wl_compositor::WlCompositor> for TestCompositor {
+    type UserData = ();
+}
+
+impl ways::DelegateGlobalDispatchBase<ways::protocol::wl_compositor::WlCompositor>
+    for TestCompositor
+{
+    type GlobalData = ();
+}
+
+impl<D> ways::DelegateGlobalDispatch<ways::protocol::wl_compositor::WlCompositor, D>
+    for TestCompositor
+where
+    D: ways::GlobalDispatch<ways::protocol::wl_compositor::WlCompositor, GlobalData = ()>
+        + ways::Dispatch<ways::protocol::wl_compositor::WlCompositor, UserData = ()>
+        + ways::Dispatch<ways::protocol::wl_surface::WlSurface, UserData = ()>
+        + ways::Dispatch<ways::protocol::wl_region::WlRegion, UserData = ()>
+        + AsMut<TestCompositor>
+        + 'static,
+{
+    fn bind(
+        _: &mut D,
+        _: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        resource: ways::New<ways::protocol::wl_compositor::WlCompositor>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> ways::DelegateDispatch<ways::protocol::wl_compositor::WlCompositor, D> for TestCompositor
+where
+    D: ways::Dispatch<ways::protocol::wl_compositor::WlCompositor, UserData = ()>
+        + ways::Dispatch<ways::protocol::wl_surface::WlSurface, UserData = ()>
+        + ways::Dispatch<ways::protocol::wl_region::WlRegion, UserData = ()>
+        + AsMut<TestCompositor>
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _: &ways::Client,
+        _: &ways::protocol::wl_compositor::WlCompositor,
+        request: ways::protocol::wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, D>,
+    ) {
+        match request {
+            ways::protocol::wl_compositor::Request::CreateSurface { id } => {
+                let surface = data_init.init(id, ());
+                state.as_mut().surfaces.push(surface);
+            }
+            ways::protocol::wl_compositor::Request::CreateRegion { id } => {
+                let region = data_init.init(id, ());
+                state.as_mut().regions.push(region);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl ways::DelegateDispatchBase<ways::protocol::wl_surface::WlSurface> for TestCompositor {
+    type UserData = ();
+}
+
+impl<D> ways::DelegateDispatch<ways::protocol::wl_surface::WlSurface, D> for TestCompositor
+where
+    D: ways::Dispatch<ways::protocol::wl_surface::WlSurface, UserData = ()>
+        + AsMut<TestCompositor>
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _: &ways::Client,
+        _: &ways::protocol::wl_surface::WlSurface,
+        request: ways::protocol::wl_surface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, D>,
+    ) {
+        if let ways::protocol::wl_surface::Request::Attach { buffer, .. } = request {
+            state.as_mut().buffer = buffer;
+        }
+    }
+}
+
+impl ways::DelegateDispatchBase<ways::protocol::wl_region::WlRegion> for TestCompositor {
+    type UserData = ();
+}
+
+impl<D> ways::DelegateDispatch<ways::protocol::wl_region::WlRegion, D> for TestCompositor
+where
+    D: ways::Dispatch<ways::protocol::wl_region::WlRegion, UserData = ()> + 'static,
+{
+    fn request(
+        _: &mut D,
+        _: &ways::Client,
+        _: &ways::protocol::wl_region::WlRegion,
+        _: ways::protocol::wl_region::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, D>,
+    ) {
+    }
+}
+
 pub struct DumbClientData;
 
 impl<D> ways::backend::ClientData<D> for DumbClientData {
@@ -0,0 +1,206 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{connect_client, roundtrip, wayc, ways, TestCompositor, TestServer};
+
+use wayland_protocols::unstable::idle_inhibit::v1::server::{
+    zwp_idle_inhibit_manager_v1 as smanager, zwp_idle_inhibitor_v1 as sinhibitor,
+};
+
+use wayc::protocol::{wl_compositor as ccompositor, wl_surface as csurface};
+use wayland_protocols::unstable::idle_inhibit::v1::client::{
+    zwp_idle_inhibit_manager_v1 as cmanager, zwp_idle_inhibitor_v1 as cinhibitor,
+};
+use wayland_protocols_helpers::unstable::idle_inhibit::v1::inhibitor::IdleInhibitState;
+
+#[test]
+fn inhibit_while_visible() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<smanager::ZwpIdleInhibitManagerV1>(1, ());
+    let mut server_ddata =
+        ServerHandler { compositor: TestCompositor::default(), requests: Vec::new() };
+
+    let mut client_ddata =
+        ClientHandler { globals: wayc::globals::GlobalList::new(), idle_inhibit: None };
+    let (mut client, registry, compositor) =
+        connect_client(&mut server, &mut client_ddata, &mut server_ddata);
+
+    let (surface, other) = {
+        let mut handle = client.conn.handle();
+        let qh = client.event_queue.handle();
+        let manager = client_ddata
+            .globals
+            .bind::<cmanager::ZwpIdleInhibitManagerV1, _>(&mut handle, &qh, &registry, 1..2, ())
+            .unwrap();
+        client_ddata.idle_inhibit = Some(IdleInhibitState::new(manager));
+        let surface = compositor.create_surface(&mut handle, &qh, ()).unwrap();
+        let other = compositor.create_surface(&mut handle, &qh, ()).unwrap();
+        (surface, other)
+    };
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // an inhibitor is created right away, and only once
+    let idle_inhibit = client_ddata.idle_inhibit.as_mut().unwrap();
+    let mut handle = client.conn.handle();
+    let qh = client.event_queue.handle();
+    idle_inhibit.inhibit(&mut handle, &qh, &surface).unwrap();
+    idle_inhibit.inhibit(&mut handle, &qh, &surface).unwrap();
+    assert!(idle_inhibit.is_inhibiting(&surface));
+    assert!(!idle_inhibit.is_inhibiting(&other));
+    assert_eq!(idle_inhibit.surfaces().collect::<Vec<_>>(), [&surface]);
+
+    // the inhibitor only lives while the surface is visible
+    idle_inhibit.set_visible(&mut handle, &qh, &surface, false).unwrap();
+    idle_inhibit.set_visible(&mut handle, &qh, &surface, false).unwrap();
+    assert!(!idle_inhibit.is_inhibiting(&surface));
+    assert_eq!(idle_inhibit.surfaces().count(), 1);
+    idle_inhibit.set_visible(&mut handle, &qh, &surface, true).unwrap();
+    idle_inhibit.set_visible(&mut handle, &qh, &surface, true).unwrap();
+    assert!(idle_inhibit.is_inhibiting(&surface));
+
+    // the surfaces which don't inhibit idleness are ignored
+    idle_inhibit.set_visible(&mut handle, &qh, &other, true).unwrap();
+    idle_inhibit.uninhibit(&mut handle, &other);
+    assert!(!idle_inhibit.is_inhibiting(&other));
+
+    idle_inhibit.uninhibit(&mut handle, &surface);
+    assert!(!idle_inhibit.is_inhibiting(&surface));
+    assert_eq!(idle_inhibit.surfaces().count(), 0);
+    std::mem::drop(handle);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(
+        server_ddata.requests,
+        ["create_inhibitor", "destroy", "create_inhibitor", "destroy"]
+    );
+}
+
+#[test]
+fn uninhibit_hidden() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<smanager::ZwpIdleInhibitManagerV1>(1, ());
+    let mut server_ddata =
+        ServerHandler { compositor: TestCompositor::default(), requests: Vec::new() };
+
+    let mut client_ddata =
+        ClientHandler { globals: wayc::globals::GlobalList::new(), idle_inhibit: None };
+    let (mut client, registry, compositor) =
+        connect_client(&mut server, &mut client_ddata, &mut server_ddata);
+
+    {
+        let mut handle = client.conn.handle();
+        let qh = client.event_queue.handle();
+        let manager = client_ddata
+            .globals
+            .bind::<cmanager::ZwpIdleInhibitManagerV1, _>(&mut handle, &qh, &registry, 1..2, ())
+            .unwrap();
+        let mut idle_inhibit = IdleInhibitState::new(manager);
+        let surface = compositor.create_surface(&mut handle, &qh, ()).unwrap();
+        idle_inhibit.inhibit(&mut handle, &qh, &surface).unwrap();
+        idle_inhibit.set_visible(&mut handle, &qh, &surface, false).unwrap();
+        // a hidden surface has no inhibitor left to destroy
+        idle_inhibit.uninhibit(&mut handle, &surface);
+        assert_eq!(idle_inhibit.surfaces().count(), 0);
+        // and it is not inhibiting anymore once visible again
+        idle_inhibit.set_visible(&mut handle, &qh, &surface, true).unwrap();
+        assert!(!idle_inhibit.is_inhibiting(&surface));
+        client_ddata.idle_inhibit = Some(idle_inhibit);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(server_ddata.requests, ["create_inhibitor", "destroy"]);
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    compositor: TestCompositor,
+    requests: Vec<&'static str>,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler:
+    [ways::protocol::wl_compositor::WlCompositor] => TestCompositor
+);
+ways::delegate_dispatch!(ServerHandler: [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_surface::WlSurface,
+    ways::protocol::wl_region::WlRegion
+] => TestCompositor);
+
+impl ways::Dispatch<smanager::ZwpIdleInhibitManagerV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &smanager::ZwpIdleInhibitManagerV1,
+        request: smanager::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let smanager::Request::CreateInhibitor { id, .. } = request {
+            self.requests.push("create_inhibitor");
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<sinhibitor::ZwpIdleInhibitorV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sinhibitor::ZwpIdleInhibitorV1,
+        request: sinhibitor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let sinhibitor::Request::Destroy = request {
+            self.requests.push("destroy");
+        }
+    }
+}
+
+server_ignore_global_impl!(ServerHandler => [smanager::ZwpIdleInhibitManagerV1]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    idle_inhibit: Option<IdleInhibitState>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<IdleInhibitState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut IdleInhibitState {
+        self.idle_inhibit.as_mut().unwrap()
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler:
+    [cmanager::ZwpIdleInhibitManagerV1, cinhibitor::ZwpIdleInhibitorV1] => IdleInhibitState
+);
+
+client_ignore_impl!(ClientHandler => [ccompositor::WlCompositor, csurface::WlSurface]);
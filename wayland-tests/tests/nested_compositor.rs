@@ -0,0 +1,460 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use ways::nested::{
+    FrameForwarder, InputRedirect, NestedCompositor, SurfaceForwarder, UpstreamRole,
+};
+use ways::protocol::{
+    wl_callback, wl_compositor, wl_pointer, wl_seat, wl_shell, wl_shell_surface, wl_surface,
+};
+
+use wayc::protocol::{
+    wl_callback as ccallback, wl_compositor as ccompositor, wl_pointer as cpointer,
+    wl_seat as cseat, wl_shell as cshell, wl_shell_surface as cshell_surface,
+    wl_surface as csurface,
+};
+
+#[test]
+fn nested_surface_and_pointer() {
+    // the upstream compositor
+    let mut upstream = TestServer::new();
+    upstream.display.create_global::<wl_compositor::WlCompositor>(4, ());
+    upstream.display.create_global::<wl_shell::WlShell>(1, ());
+    upstream.display.create_global::<wl_seat::WlSeat>(1, ());
+    let mut upstream_ddata = UpstreamHandler::default();
+
+    // the nested compositor, client of the upstream compositor
+    let (_, mut nested_client) = upstream.add_client();
+    let mut nested = TestServer::new();
+    nested.display.create_global::<wl_compositor::WlCompositor>(4, ());
+    nested.display.create_global::<wl_seat::WlSeat>(1, ());
+    let compositor = NestedCompositor::new(nested.display.clone(), nested_client.conn.clone());
+    let mut nested_ddata = NestedHandler {
+        display: nested.display.clone(),
+        upstream: nested_client.conn.clone(),
+        qh: nested_client.event_queue.handle(),
+        globals: Default::default(),
+        compositor: None,
+        shell: None,
+        client_surfaces: Vec::new(),
+        surfaces: SurfaceForwarder::new(),
+        frames: FrameForwarder::new(),
+        input: InputRedirect::new(),
+    };
+
+    let registry = nested_client
+        .display
+        .get_registry(&mut nested_client.conn.handle(), &nested_client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut nested_client, &mut upstream, &mut nested_ddata, &mut upstream_ddata).unwrap();
+
+    let mut conn = nested_client.conn.handle();
+    let qh = nested_client.event_queue.handle();
+    nested_ddata.compositor = Some(
+        nested_ddata
+            .globals
+            .bind::<ccompositor::WlCompositor, _>(&mut conn, &qh, &registry, 4..5, ())
+            .unwrap(),
+    );
+    nested_ddata.shell = Some(
+        nested_ddata
+            .globals
+            .bind::<cshell::WlShell, _>(&mut conn, &qh, &registry, 1..2, ())
+            .unwrap(),
+    );
+    let seat =
+        nested_ddata.globals.bind::<cseat::WlSeat, _>(&mut conn, &qh, &registry, 1..2, ()).unwrap();
+    seat.get_pointer(&mut conn, &qh, ()).unwrap();
+    std::mem::drop(conn);
+    roundtrip(&mut nested_client, &mut upstream, &mut nested_ddata, &mut upstream_ddata).unwrap();
+
+    // a client of the nested compositor
+    let (_, mut client) = nested.add_client();
+    let mut client_ddata =
+        ClientHandler { globals: Default::default(), events: Vec::new(), frames: Vec::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut nested, &mut client_ddata, &mut nested_ddata).unwrap();
+
+    let mut conn = client.conn.handle();
+    let qh = client.event_queue.handle();
+    let client_compositor = client_ddata
+        .globals
+        .bind::<ccompositor::WlCompositor, _>(&mut conn, &qh, &registry, 4..5, ())
+        .unwrap();
+    let client_seat =
+        client_ddata.globals.bind::<cseat::WlSeat, _>(&mut conn, &qh, &registry, 1..2, ()).unwrap();
+    let client_surface = client_compositor.create_surface(&mut conn, &qh, ()).unwrap();
+    client_seat.get_pointer(&mut conn, &qh, ()).unwrap();
+    client_surface.damage_buffer(&mut conn, 0, 0, 10, 10);
+    client_surface.commit(&mut conn);
+    std::mem::drop(conn);
+    roundtrip(&mut client, &mut nested, &mut client_ddata, &mut nested_ddata).unwrap();
+
+    // the surface is forwarded as an upstream toplevel
+    let forwarded = nested_ddata.surfaces.get(&nested_ddata.client_surfaces[0]).unwrap().clone();
+    assert!(matches!(forwarded.role(), Some(UpstreamRole::Toplevel(_))));
+    compositor.flush().unwrap();
+    upstream.answer(&mut upstream_ddata);
+    assert_eq!(upstream_ddata.surfaces.len(), 1);
+    assert_eq!(upstream_ddata.toplevels, 1);
+    assert_eq!(upstream_ddata.damages, 1);
+    assert_eq!(upstream_ddata.commits, 1);
+
+    // upstream pointer events are redirected to the client
+    let pointer = upstream_ddata.pointer.clone().unwrap();
+    let upstream_surface = upstream_ddata.surfaces[0].clone();
+    pointer.enter(&mut upstream.display.handle(), 1, &upstream_surface, 1.0, 2.0);
+    pointer.motion(&mut upstream.display.handle(), 42, 3.0, 4.0);
+    roundtrip(&mut nested_client, &mut upstream, &mut nested_ddata, &mut upstream_ddata).unwrap();
+    assert_eq!(nested_ddata.input.pointer_focus(), Some(forwarded.downstream()));
+
+    roundtrip(&mut client, &mut nested, &mut client_ddata, &mut nested_ddata).unwrap();
+    assert_eq!(client_ddata.events.len(), 2);
+    assert!(matches!(
+        client_ddata.events[0],
+        cpointer::Event::Enter { serial: 1, ref surface, surface_x, surface_y }
+            if surface == &client_surface && surface_x == 1.0 && surface_y == 2.0
+    ));
+    assert!(matches!(
+        client_ddata.events[1],
+        cpointer::Event::Motion { time: 42, surface_x, surface_y }
+            if surface_x == 3.0 && surface_y == 4.0
+    ));
+
+    // frame callbacks are forwarded upstream, and their completion downstream
+    client_surface.frame(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    client_surface.commit(&mut client.conn.handle());
+    roundtrip(&mut client, &mut nested, &mut client_ddata, &mut nested_ddata).unwrap();
+    assert_eq!(nested_ddata.frames.pending(), 1);
+    compositor.flush().unwrap();
+    upstream.answer(&mut upstream_ddata);
+    let upstream_callback = upstream_ddata.frame_callbacks.pop().unwrap();
+    upstream_callback.done(&mut upstream.display.handle(), 1234);
+    roundtrip(&mut nested_client, &mut upstream, &mut nested_ddata, &mut upstream_ddata).unwrap();
+    assert_eq!(nested_ddata.frames.pending(), 0);
+    roundtrip(&mut client, &mut nested, &mut client_ddata, &mut nested_ddata).unwrap();
+    assert_eq!(client_ddata.frames, vec![1234]);
+
+    // destroying the surface destroys its upstream counterpart
+    client_surface.destroy(&mut client.conn.handle());
+    roundtrip(&mut client, &mut nested, &mut client_ddata, &mut nested_ddata).unwrap();
+    assert!(nested_ddata.input.pointer_focus().is_none());
+    assert!(nested_ddata.surfaces.get(forwarded.downstream()).is_none());
+    compositor.flush().unwrap();
+    upstream.answer(&mut upstream_ddata);
+    assert_eq!(upstream_ddata.destroyed, 1);
+}
+
+/*
+ * Upstream compositor
+ */
+
+#[derive(Default)]
+struct UpstreamHandler {
+    surfaces: Vec<wl_surface::WlSurface>,
+    pointer: Option<wl_pointer::WlPointer>,
+    frame_callbacks: Vec<wl_callback::WlCallback>,
+    toplevels: usize,
+    damages: usize,
+    commits: usize,
+    destroyed: usize,
+}
+
+impl ways::Dispatch<wl_compositor::WlCompositor> for UpstreamHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_compositor::WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_compositor::Request::CreateSurface { id } = request {
+            self.surfaces.push(data_init.init(id, ()));
+        }
+    }
+}
+
+impl ways::Dispatch<wl_surface::WlSurface> for UpstreamHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_surface::WlSurface,
+        request: wl_surface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_surface::Request::Frame { callback } => {
+                self.frame_callbacks.push(data_init.init(callback, ()))
+            }
+            wl_surface::Request::DamageBuffer { .. } => self.damages += 1,
+            wl_surface::Request::Commit => self.commits += 1,
+            wl_surface::Request::Destroy => self.destroyed += 1,
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<wl_shell::WlShell> for UpstreamHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_shell::WlShell,
+        request: wl_shell::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_shell::Request::GetShellSurface { id, .. } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<wl_shell_surface::WlShellSurface> for UpstreamHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_shell_surface::WlShellSurface,
+        request: wl_shell_surface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_shell_surface::Request::SetToplevel = request {
+            self.toplevels += 1;
+        }
+    }
+}
+
+impl ways::Dispatch<wl_seat::WlSeat> for UpstreamHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_seat::WlSeat,
+        request: wl_seat::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_seat::Request::GetPointer { id } = request {
+            self.pointer = Some(data_init.init(id, ()));
+        }
+    }
+}
+
+server_ignore_impl!(UpstreamHandler => [wl_pointer::WlPointer, wl_callback::WlCallback]);
+server_ignore_global_impl!(UpstreamHandler => [
+    wl_compositor::WlCompositor,
+    wl_shell::WlShell,
+    wl_seat::WlSeat
+]);
+
+/*
+ * Nested compositor
+ */
+
+struct NestedHandler {
+    display: ways::Display<NestedHandler>,
+    upstream: wayc::Connection,
+    qh: wayc::QueueHandle<NestedHandler>,
+    globals: wayc::globals::GlobalList,
+    compositor: Option<ccompositor::WlCompositor>,
+    shell: Option<cshell::WlShell>,
+    client_surfaces: Vec<wl_surface::WlSurface>,
+    surfaces: SurfaceForwarder,
+    frames: FrameForwarder,
+    input: InputRedirect,
+}
+
+impl ways::Dispatch<wl_compositor::WlCompositor> for NestedHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_compositor::WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_compositor::Request::CreateSurface { id } = request {
+            let surface = data_init.init(id, ());
+            let mut conn = self.upstream.handle();
+            let compositor = self.compositor.as_ref().unwrap();
+            self.surfaces.forward_surface(&mut conn, compositor, &self.qh, (), &surface).unwrap();
+            self.surfaces
+                .make_toplevel(&mut conn, self.shell.as_ref().unwrap(), &self.qh, (), &surface)
+                .unwrap();
+            self.client_surfaces.push(surface);
+        }
+    }
+}
+
+impl ways::Dispatch<wl_surface::WlSurface> for NestedHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        surface: &wl_surface::WlSurface,
+        request: wl_surface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        let mut conn = self.upstream.handle();
+        match request {
+            wl_surface::Request::Destroy => {
+                self.input.surface_destroyed(surface);
+                self.surfaces.remove(&mut conn, surface);
+            }
+            wl_surface::Request::Frame { callback } => {
+                let callback = data_init.init(callback, ());
+                self.frames
+                    .forward_frame(&mut conn, &self.surfaces, &self.qh, (), surface, callback)
+                    .unwrap();
+            }
+            request => {
+                self.surfaces.forward_request(&mut conn, surface, request);
+            }
+        }
+    }
+}
+
+impl ways::Dispatch<wl_seat::WlSeat> for NestedHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_seat::WlSeat,
+        request: wl_seat::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_seat::Request::GetPointer { id } = request {
+            self.input.add_pointer(data_init.init(id, ()));
+        }
+    }
+}
+
+server_ignore_impl!(NestedHandler => [wl_pointer::WlPointer, wl_callback::WlCallback]);
+server_ignore_global_impl!(NestedHandler => [wl_compositor::WlCompositor, wl_seat::WlSeat]);
+
+impl AsMut<wayc::globals::GlobalList> for NestedHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(NestedHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<cpointer::WlPointer> for NestedHandler {
+    type UserData = ();
+    fn event(
+        &mut self,
+        _: &cpointer::WlPointer,
+        event: cpointer::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        self.input.redirect_pointer(&mut self.display.handle(), &self.surfaces, event);
+    }
+}
+
+impl wayc::Dispatch<ccallback::WlCallback> for NestedHandler {
+    type UserData = ();
+    fn event(
+        &mut self,
+        callback: &ccallback::WlCallback,
+        event: ccallback::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let ccallback::Event::Done { callback_data } = event {
+            self.frames.done(&mut self.display.handle(), callback, callback_data);
+        }
+    }
+}
+
+client_ignore_impl!(NestedHandler => [
+    ccompositor::WlCompositor,
+    csurface::WlSurface,
+    cshell::WlShell,
+    cshell_surface::WlShellSurface,
+    cseat::WlSeat
+]);
+
+/*
+ * Client of the nested compositor
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    events: Vec<cpointer::Event>,
+    frames: Vec<u32>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<cpointer::WlPointer> for ClientHandler {
+    type UserData = ();
+    fn event(
+        &mut self,
+        _: &cpointer::WlPointer,
+        event: cpointer::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        self.events.push(event);
+    }
+}
+
+impl wayc::Dispatch<ccallback::WlCallback> for ClientHandler {
+    type UserData = ();
+    fn event(
+        &mut self,
+        _: &ccallback::WlCallback,
+        event: ccallback::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let ccallback::Event::Done { callback_data } = event {
+            self.frames.push(callback_data);
+        }
+    }
+}
+
+client_ignore_impl!(ClientHandler => [
+    ccompositor::WlCompositor,
+    csurface::WlSurface,
+    cseat::WlSeat
+]);
@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::FromRawFd;
+
+use wayland_client::pipe::{read_pipe, write_pipe};
+
+#[test]
+fn pipe_transfer() {
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+    let writer = write_pipe(write_fd, b"some data".to_vec());
+    let reader = read_pipe(read_fd, None);
+
+    futures_executor::block_on(writer).unwrap();
+    assert_eq!(futures_executor::block_on(reader).unwrap(), b"some data");
+}
+
+#[test]
+fn pipe_size_limit() {
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+    let reader = read_pipe(read_fd, Some(4));
+    unsafe { File::from_raw_fd(write_fd) }.write_all(b"12345").unwrap();
+
+    let err = futures_executor::block_on(reader).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // the data fitting in the limit is read entirely
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+    let reader = read_pipe(read_fd, Some(4));
+    unsafe { File::from_raw_fd(write_fd) }.write_all(b"1234").unwrap();
+
+    assert_eq!(futures_executor::block_on(reader).unwrap(), b"1234");
+}
+
+#[test]
+fn pipe_slow_peer() {
+    // a transfer stuck on a peer not closing its pipe does not prevent the others from running
+    let (stuck_fd, stuck_write_fd) = nix::unistd::pipe().unwrap();
+    let stuck = read_pipe(stuck_fd, None);
+
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+    let reader = read_pipe(read_fd, None);
+    unsafe { File::from_raw_fd(write_fd) }.write_all(b"data").unwrap();
+    assert_eq!(futures_executor::block_on(reader).unwrap(), b"data");
+
+    unsafe { File::from_raw_fd(stuck_write_fd) }.write_all(b"late").unwrap();
+    assert_eq!(futures_executor::block_on(stuck).unwrap(), b"late");
+}
@@ -0,0 +1,161 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use ways::protocol::{wl_pointer as spointer, wl_seat as sseat};
+use ways::Resource;
+
+use wayc::protocol::{wl_pointer as cpointer, wl_seat as cseat};
+use wayc::Proxy;
+
+fn send_scroll(pointer: &spointer::WlPointer, dh: &mut ways::DisplayHandle<'_>) {
+    // only send the events the client can understand
+    if pointer.version() >= spointer::EVT_AXIS_RELATIVE_DIRECTION_SINCE {
+        pointer.axis_relative_direction(
+            dh,
+            spointer::Axis::VerticalScroll,
+            spointer::AxisRelativeDirection::Inverted,
+        );
+    }
+    if pointer.version() >= spointer::EVT_AXIS_VALUE120_SINCE {
+        pointer.axis_value120(dh, spointer::Axis::VerticalScroll, 60);
+    } else {
+        pointer.axis_discrete(dh, spointer::Axis::VerticalScroll, 1);
+    }
+    pointer.axis(dh, 42, spointer::Axis::VerticalScroll, 7.5);
+    pointer.frame(dh);
+}
+
+fn scroll_with_version(version: u32) -> Vec<cpointer::Event> {
+    let mut server = TestServer::new();
+    server.display.create_global::<sseat::WlSeat>(version, ());
+    let mut server_ddata = ServerHandler { pointer: None };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: Default::default(), events: Vec::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata
+        .globals
+        .bind::<cseat::WlSeat, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            version..version + 1,
+            (),
+        )
+        .unwrap();
+    let pointer =
+        seat.get_pointer(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    assert_eq!(pointer.version(), version);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_pointer = server_ddata.pointer.take().unwrap();
+    assert_eq!(server_pointer.version(), version);
+    send_scroll(&server_pointer, &mut server.display.handle());
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    client_ddata.events
+}
+
+#[test]
+fn high_resolution_scroll() {
+    let events = scroll_with_version(9);
+    assert_eq!(events.len(), 4);
+    assert!(matches!(
+        events[0],
+        cpointer::Event::AxisRelativeDirection {
+            axis: wayc::WEnum::Value(cpointer::Axis::VerticalScroll),
+            direction: wayc::WEnum::Value(cpointer::AxisRelativeDirection::Inverted),
+        }
+    ));
+    assert!(matches!(
+        events[1],
+        cpointer::Event::AxisValue120 {
+            axis: wayc::WEnum::Value(cpointer::Axis::VerticalScroll),
+            value120: 60,
+        }
+    ));
+    assert!(matches!(events[2], cpointer::Event::Axis { time: 42, value, .. } if value == 7.5));
+    assert!(matches!(events[3], cpointer::Event::Frame));
+}
+
+#[test]
+fn legacy_discrete_scroll() {
+    let events = scroll_with_version(7);
+    assert_eq!(events.len(), 3);
+    assert!(matches!(
+        events[0],
+        cpointer::Event::AxisDiscrete {
+            axis: wayc::WEnum::Value(cpointer::Axis::VerticalScroll),
+            discrete: 1,
+        }
+    ));
+    assert!(matches!(events[1], cpointer::Event::Axis { time: 42, value, .. } if value == 7.5));
+    assert!(matches!(events[2], cpointer::Event::Frame));
+}
+
+struct ServerHandler {
+    pointer: Option<spointer::WlPointer>,
+}
+
+impl ways::Dispatch<sseat::WlSeat> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sseat::WlSeat,
+        request: sseat::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let sseat::Request::GetPointer { id } = request {
+            self.pointer = Some(data_init.init(id, ()));
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [spointer::WlPointer]);
+server_ignore_global_impl!(ServerHandler => [sseat::WlSeat]);
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    events: Vec<cpointer::Event>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<cpointer::WlPointer> for ClientHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &cpointer::WlPointer,
+        event: cpointer::Event,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        self.events.push(event);
+    }
+}
+
+client_ignore_impl!(ClientHandler => [cseat::WlSeat]);
@@ -0,0 +1,278 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, setup_client, wayc, ways, TestCompositor, TestServer, TestSetup};
+
+use wayland_protocols::unstable::pointer_gestures::v1::server::{
+    zwp_pointer_gesture_hold_v1 as shold, zwp_pointer_gestures_v1 as sgestures,
+};
+use ways::protocol::{wl_pointer as spointer, wl_seat as sseat};
+
+use wayc::protocol::{
+    wl_compositor as ccompositor, wl_keyboard as ckeyboard, wl_pointer as cpointer,
+    wl_seat as cseat, wl_surface as csurface, wl_touch as ctouch,
+};
+use wayc::seat::{InputEvent, SeatState};
+use wayc::Proxy;
+use wayland_protocols::unstable::pointer_gestures::v1::client::{
+    zwp_pointer_gesture_hold_v1 as chold, zwp_pointer_gestures_v1 as cgestures,
+};
+use wayland_protocols_helpers::unstable::pointer_gestures::v1::hold::HoldGestureState;
+use ways::Resource;
+
+// Connect a client to a seat with a pointer, and follow its pointers with a hold gesture helper
+fn setup(gestures_version: u32) -> TestSetup<ServerHandler, ClientHandler> {
+    let server = TestServer::new();
+    server.display.create_global::<sseat::WlSeat>(7, ());
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<sgestures::ZwpPointerGesturesV1>(gestures_version, ());
+    let server_ddata = ServerHandler {
+        compositor: TestCompositor::default(),
+        seat: None,
+        holds: Vec::new(),
+        destroyed_holds: 0,
+    };
+    let client_ddata = ClientHandler {
+        globals: wayc::globals::GlobalList::new(),
+        seats: SeatState::new(),
+        gestures: None,
+    };
+
+    let (mut setup, ()) = setup_client(
+        server,
+        server_ddata,
+        client_ddata,
+        |client, client_ddata, registry, compositor| {
+            let mut handle = client.conn.handle();
+            let qh = client.event_queue.handle();
+            client_ddata
+                .seats
+                .bind_seats(&mut handle, &qh, registry, &client_ddata.globals)
+                .unwrap();
+            let manager = client_ddata
+                .globals
+                .bind::<cgestures::ZwpPointerGesturesV1, _>(&mut handle, &qh, registry, 1..4, ())
+                .unwrap();
+            client_ddata.gestures = Some(HoldGestureState::new(manager));
+            compositor.create_surface(&mut handle, &qh, ()).unwrap();
+        },
+    );
+
+    let TestSetup { server, client, server_ddata, client_ddata } = &mut setup;
+    let server_seat = server_ddata.seat.clone().unwrap();
+    server_seat.capabilities(&mut server.display.handle(), sseat::Capability::Pointer);
+    roundtrip(client, server, client_ddata, server_ddata).unwrap();
+    roundtrip(client, server, client_ddata, server_ddata).unwrap();
+
+    setup
+}
+
+#[test]
+fn hold_gestures() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup(3);
+
+    let events = client_ddata.seats.take_events();
+    assert!(matches!(events[..], [InputEvent::Capabilities { .. }]));
+    let gestures = client_ddata.gestures.as_mut().unwrap();
+    assert!(gestures.is_supported());
+    gestures
+        .update(&mut client.conn.handle(), &client.event_queue.handle(), &client_ddata.seats)
+        .unwrap();
+    let pointer = client_ddata.seats.seats()[0].pointer().unwrap();
+    assert_eq!(gestures.pointers().collect::<Vec<_>>(), [pointer]);
+    // the pointers already followed are left untouched
+    gestures
+        .update(&mut client.conn.handle(), &client.event_queue.handle(), &client_ddata.seats)
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.holds.len(), 1);
+
+    let hold = server_ddata.holds[0].clone();
+    let server_surface = server_ddata.compositor.surfaces[0].clone();
+    {
+        let mut handle = server.display.handle();
+        hold.begin(&mut handle, 5, 10, &server_surface, 3);
+        hold.end(&mut handle, 6, 11, 1);
+        // a gesture ending without having begun is ignored
+        hold.end(&mut handle, 7, 12, 0);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // the gestures are reported with the events of the seat
+    let events = client_ddata.seats.take_events();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        events[0],
+        InputEvent::PointerHoldBegin { serial: 5, time: 10, fingers: 3, ref surface, .. }
+            if surface.id().protocol_id() == server_surface.id().protocol_id()
+    ));
+    assert!(matches!(
+        events[1],
+        InputEvent::PointerHoldEnd { serial: 6, time: 11, cancelled: true, .. }
+    ));
+
+    // the gesture of a released pointer is destroyed
+    let server_seat = server_ddata.seat.clone().unwrap();
+    server_seat.capabilities(&mut server.display.handle(), sseat::Capability::empty());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    client_ddata
+        .gestures
+        .as_mut()
+        .unwrap()
+        .update(&mut client.conn.handle(), &client.event_queue.handle(), &client_ddata.seats)
+        .unwrap();
+    assert_eq!(client_ddata.gestures.as_ref().unwrap().pointers().count(), 0);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.destroyed_holds, 1);
+}
+
+#[test]
+fn hold_gestures_unsupported() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup(2);
+
+    let gestures = client_ddata.gestures.as_mut().unwrap();
+    assert!(!gestures.is_supported());
+    gestures
+        .update(&mut client.conn.handle(), &client.event_queue.handle(), &client_ddata.seats)
+        .unwrap();
+    assert_eq!(gestures.pointers().count(), 0);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert!(server_ddata.holds.is_empty());
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    compositor: TestCompositor,
+    seat: Option<sseat::WlSeat>,
+    holds: Vec<shold::ZwpPointerGestureHoldV1>,
+    destroyed_holds: usize,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler:
+    [ways::protocol::wl_compositor::WlCompositor] => TestCompositor
+);
+ways::delegate_dispatch!(ServerHandler: [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_surface::WlSurface,
+    ways::protocol::wl_region::WlRegion
+] => TestCompositor);
+
+impl ways::GlobalDispatch<sseat::WlSeat> for ServerHandler {
+    type GlobalData = ();
+
+    fn bind(
+        &mut self,
+        _: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        new_id: ways::New<sseat::WlSeat>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        self.seat = Some(data_init.init(new_id, ()));
+    }
+}
+
+impl ways::Dispatch<sseat::WlSeat> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sseat::WlSeat,
+        request: sseat::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let sseat::Request::GetPointer { id } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<sgestures::ZwpPointerGesturesV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sgestures::ZwpPointerGesturesV1,
+        request: sgestures::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let sgestures::Request::GetHoldGesture { id, .. } = request {
+            self.holds.push(data_init.init(id, ()));
+        }
+    }
+}
+
+impl ways::Dispatch<shold::ZwpPointerGestureHoldV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &shold::ZwpPointerGestureHoldV1,
+        request: shold::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let shold::Request::Destroy = request {
+            self.destroyed_holds += 1;
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [spointer::WlPointer]);
+server_ignore_global_impl!(ServerHandler => [sgestures::ZwpPointerGesturesV1]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    seats: SeatState,
+    gestures: Option<HoldGestureState>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<SeatState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut SeatState {
+        &mut self.seats
+    }
+}
+
+impl AsMut<HoldGestureState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut HoldGestureState {
+        self.gestures.as_mut().unwrap()
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler:
+    [cseat::WlSeat, cpointer::WlPointer, ckeyboard::WlKeyboard, ctouch::WlTouch] => SeatState
+);
+
+wayc::delegate_dispatch!(ClientHandler:
+    [cgestures::ZwpPointerGesturesV1, chold::ZwpPointerGestureHoldV1] => HoldGestureState
+);
+
+client_ignore_impl!(ClientHandler => [ccompositor::WlCompositor, csurface::WlSurface]);
@@ -0,0 +1,436 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::time::Duration;
+
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, setup_client, wayc, ways, TestClient, TestServer, TestSetup};
+
+use wayland_protocols::wlr::unstable::screencopy::v1::server::{
+    zwlr_screencopy_frame_v1 as sframe, zwlr_screencopy_manager_v1 as smanager,
+};
+use ways::protocol::{
+    wl_buffer as sbuffer, wl_compositor as scompositor, wl_output as soutput, wl_shm as sshm,
+    wl_shm_pool as spool,
+};
+
+use wayc::protocol::{wl_compositor as ccompositor, wl_output as coutput, wl_shm as cshm};
+use wayc::shm::ShmPool;
+use wayland_protocols::wlr::unstable::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1 as cframe, zwlr_screencopy_manager_v1 as cmanager,
+};
+use wayland_protocols_helpers::wlr::unstable::screencopy::v1::capture::{
+    CaptureError, Frame, FrameBuffer, FrameStatus, ScreencopyEvent, ScreencopyState,
+};
+use ways::Resource;
+
+// DRM_FORMAT_ARGB8888
+const FOURCC_ARGB8888: u32 = 0x3432_5241;
+
+// Connect a client binding the screencopy global with the given version, along with an output
+fn setup(screencopy_version: u32) -> TestSetup<ServerHandler, ClientHandler> {
+    let server = TestServer::new();
+    server.display.create_global::<scompositor::WlCompositor>(1, ());
+    server.display.create_global::<sshm::WlShm>(1, ());
+    server.display.create_global::<soutput::WlOutput>(2, ());
+    server.display.create_global::<smanager::ZwlrScreencopyManagerV1>(screencopy_version, ());
+    let server_ddata =
+        ServerHandler { fd: None, frames: Vec::new(), copies: Vec::new(), destroyed_frames: 0 };
+    let client_ddata = ClientHandler {
+        globals: wayc::globals::GlobalList::new(),
+        shm: None,
+        output: None,
+        screencopy: None,
+    };
+
+    setup_client(server, server_ddata, client_ddata, |client, client_ddata, registry, _| {
+        let mut handle = client.conn.handle();
+        let qh = client.event_queue.handle();
+        let globals = &client_ddata.globals;
+        let shm = globals.bind::<cshm::WlShm, _>(&mut handle, &qh, registry, 1..2, ()).unwrap();
+        let output =
+            globals.bind::<coutput::WlOutput, _>(&mut handle, &qh, registry, 1..3, ()).unwrap();
+        let manager = globals
+            .bind::<cmanager::ZwlrScreencopyManagerV1, _>(&mut handle, &qh, registry, 1..4, ())
+            .unwrap();
+        client_ddata.screencopy = Some(ScreencopyState::new(manager, shm.clone()));
+        client_ddata.shm = Some(shm);
+        client_ddata.output = Some(output);
+    })
+    .0
+}
+
+// Start the capture of the output, and return its frame along with the server-side frame
+fn capture(
+    server: &mut TestServer<ServerHandler>,
+    client: &mut TestClient<ClientHandler>,
+    server_ddata: &mut ServerHandler,
+    client_ddata: &mut ClientHandler,
+) -> (Frame, sframe::ZwlrScreencopyFrameV1) {
+    let output = client_ddata.output.clone().unwrap();
+    let frame = client_ddata
+        .screencopy
+        .as_mut()
+        .unwrap()
+        .capture_output(&mut client.conn.handle(), &client.event_queue.handle(), &output, false)
+        .unwrap();
+    roundtrip(client, server, client_ddata, server_ddata).unwrap();
+    assert_eq!(frame.status(), FrameStatus::Negotiating);
+    (frame, server_ddata.frames.last().unwrap().clone())
+}
+
+#[test]
+fn capture_shm() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup(3);
+    let (frame, server_frame) =
+        capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+
+    {
+        let mut handle = server.display.handle();
+        server_frame.buffer(&mut handle, sshm::Format::Argb8888, 4, 4, 16);
+        server_frame.linux_dmabuf(&mut handle, FOURCC_ARGB8888, 4, 4);
+    }
+    // nothing is allocated before all the buffer types were described
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(frame.status(), FrameStatus::Negotiating);
+    assert!(server_ddata.copies.is_empty());
+
+    server_frame.buffer_done(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    // the dmabufs were not preferred, the frame is copied into shared memory
+    assert_eq!(frame.status(), FrameStatus::Copying);
+    // the copy is requested while dispatching the events, it reaches the server afterwards
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.copies.len(), 1);
+    assert!(client_ddata.screencopy.as_mut().unwrap().take_events().is_empty());
+
+    // the compositor copies the image into the buffer
+    let mut file = unsafe { File::from_raw_fd(server_ddata.fd.take().unwrap()) };
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.write_all(&[0x42; 64]).unwrap();
+    {
+        let mut handle = server.display.handle();
+        server_frame.flags(&mut handle, sframe::Flags::YInvert);
+        server_frame.ready(&mut handle, 1, 2, 3);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let screencopy = client_ddata.screencopy.as_mut().unwrap();
+    let mut events = screencopy.take_events();
+    assert_eq!(events.len(), 1);
+    match events.remove(0) {
+        ScreencopyEvent::Ready { frame: ready, captured } => {
+            assert_eq!(ready, frame);
+            assert!(captured.y_invert);
+            assert_eq!(captured.presented_at, Duration::new((1 << 32) | 2, 3));
+            match captured.buffer {
+                FrameBuffer::Shm { params, data } => {
+                    assert_eq!(params.format, cshm::Format::Argb8888);
+                    assert_eq!((params.width, params.height, params.stride), (4, 4, 16));
+                    assert_eq!(data, [0x42; 64]);
+                }
+                buffer => panic!("Unexpected buffer: {:?}", buffer),
+            }
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    // the frame is destroyed once ready
+    assert_eq!(frame.status(), FrameStatus::Ready);
+    assert!(screencopy.frames().is_empty());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.destroyed_frames, 1);
+
+    // the pool is reused by the next captures
+    let (_, server_frame) = capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+    {
+        let mut handle = server.display.handle();
+        server_frame.buffer(&mut handle, sshm::Format::Argb8888, 2, 2, 8);
+        server_frame.buffer_done(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.copies.len(), 2);
+    assert!(server_ddata.fd.is_none());
+}
+
+#[test]
+fn capture_dmabuf() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup(3);
+    client_ddata.screencopy.as_mut().unwrap().set_prefer_dmabuf(true);
+    let (frame, server_frame) =
+        capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+
+    {
+        let mut handle = server.display.handle();
+        server_frame.buffer(&mut handle, sshm::Format::Argb8888, 4, 4, 16);
+        server_frame.linux_dmabuf(&mut handle, FOURCC_ARGB8888, 4, 4);
+        server_frame.buffer_done(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // the application is asked for a dmabuf
+    assert_eq!(frame.status(), FrameStatus::DmabufRequested);
+    assert!(server_ddata.copies.is_empty());
+    let events = client_ddata.screencopy.as_mut().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        ScreencopyEvent::DmabufRequested { frame: ref requested, params }
+            if requested == &frame
+                && (params.format, params.width, params.height) == (FOURCC_ARGB8888, 4, 4)
+    ));
+
+    // a shared memory buffer stands for the dmabuf on the client side
+    let shm = client_ddata.shm.clone().unwrap();
+    let mut pool = ShmPool::new(&mut client.conn.handle(), &shm, 64).unwrap();
+    let standin =
+        pool.create_buffer(&mut client.conn.handle(), 4, 4, 16, cshm::Format::Argb8888).unwrap();
+    let buffer = standin.wl_buffer().clone();
+    let screencopy = client_ddata.screencopy.as_mut().unwrap();
+    screencopy.copy_to_dmabuf(&mut client.conn.handle(), &frame, buffer.clone());
+    assert_eq!(frame.status(), FrameStatus::Copying);
+    // the frame is only copied once
+    screencopy.copy_to_dmabuf(&mut client.conn.handle(), &frame, buffer.clone());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.copies.len(), 1);
+    assert_eq!(server_ddata.copies[0].id().protocol_id(), wayc::Proxy::id(&buffer).protocol_id());
+
+    server_frame.ready(&mut server.display.handle(), 0, 5, 0);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let events = client_ddata.screencopy.as_mut().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreencopyEvent::Ready { ref captured, .. } => {
+            assert!(!captured.y_invert);
+            assert_eq!(captured.presented_at, Duration::from_secs(5));
+            assert!(matches!(
+                captured.buffer,
+                FrameBuffer::Dmabuf { buffer: ref dmabuf, params }
+                    if dmabuf == &buffer && params.format == FOURCC_ARGB8888
+            ));
+        }
+        ref event => panic!("Unexpected event: {:?}", event),
+    }
+    assert_eq!(frame.status(), FrameStatus::Ready);
+}
+
+#[test]
+fn capture_failures() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup(3);
+
+    // the compositor fails to copy the frame
+    let (frame, server_frame) =
+        capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+    {
+        let mut handle = server.display.handle();
+        server_frame.buffer(&mut handle, sshm::Format::Argb8888, 4, 4, 16);
+        server_frame.buffer_done(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(frame.status(), FrameStatus::Copying);
+    server_frame.failed(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let events = client_ddata.screencopy.as_mut().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        ScreencopyEvent::Failed { frame: ref failed, error: CaptureError::Failed } if failed == &frame
+    ));
+    assert_eq!(frame.status(), FrameStatus::Failed);
+
+    // only dmabufs are offered, but they were not preferred
+    let (frame, server_frame) =
+        capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+    {
+        let mut handle = server.display.handle();
+        server_frame.linux_dmabuf(&mut handle, FOURCC_ARGB8888, 4, 4);
+        server_frame.buffer_done(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let events = client_ddata.screencopy.as_mut().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        ScreencopyEvent::Failed { error: CaptureError::UnsupportedBuffer, .. }
+    ));
+    assert_eq!(frame.status(), FrameStatus::Failed);
+    assert_eq!(server_ddata.copies.len(), 1);
+
+    // a cancelled frame is destroyed without reporting anything
+    let (frame, server_frame) =
+        capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+    client_ddata.screencopy.as_mut().unwrap().cancel(&mut client.conn.handle(), &frame);
+    server_frame.failed(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let screencopy = client_ddata.screencopy.as_mut().unwrap();
+    assert!(screencopy.take_events().is_empty());
+    assert!(screencopy.frames().is_empty());
+    assert_eq!(server_ddata.destroyed_frames, 3);
+}
+
+#[test]
+fn capture_before_v3() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup(2);
+    // the dmabufs cannot be described before version 3
+    client_ddata.screencopy.as_mut().unwrap().set_prefer_dmabuf(true);
+    let (frame, server_frame) =
+        capture(&mut server, &mut client, &mut server_ddata, &mut client_ddata);
+
+    // the shm buffer is the only one described, the copy starts right away
+    server_frame.buffer(&mut server.display.handle(), sshm::Format::Xrgb8888, 4, 4, 16);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(frame.status(), FrameStatus::Copying);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.copies.len(), 1);
+
+    {
+        let mut handle = server.display.handle();
+        server_frame.damage(&mut handle, 0, 0, 4, 4);
+        server_frame.flags(&mut handle, sframe::Flags::empty());
+        server_frame.ready(&mut handle, 0, 1, 0);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let events = client_ddata.screencopy.as_mut().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        ScreencopyEvent::Ready { ref captured, .. }
+            if matches!(captured.buffer, FrameBuffer::Shm { ref data, .. } if data.len() == 64)
+    ));
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    fd: Option<RawFd>,
+    frames: Vec<sframe::ZwlrScreencopyFrameV1>,
+    copies: Vec<sbuffer::WlBuffer>,
+    destroyed_frames: usize,
+}
+
+impl ways::Dispatch<smanager::ZwlrScreencopyManagerV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &smanager::ZwlrScreencopyManagerV1,
+        request: smanager::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let smanager::Request::CaptureOutput { frame, .. } = request {
+            self.frames.push(data_init.init(frame, ()));
+        }
+    }
+}
+
+impl ways::Dispatch<sframe::ZwlrScreencopyFrameV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sframe::ZwlrScreencopyFrameV1,
+        request: sframe::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            sframe::Request::Copy { buffer } => self.copies.push(buffer),
+            sframe::Request::Destroy => self.destroyed_frames += 1,
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<sshm::WlShm> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sshm::WlShm,
+        request: sshm::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let sshm::Request::CreatePool { fd, id, .. } = request {
+            // keep the pool of the helper, the first one to be created
+            if self.fd.is_none() {
+                self.fd = Some(fd);
+            }
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<spool::WlShmPool> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &spool::WlShmPool,
+        request: spool::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let spool::Request::CreateBuffer { id, .. } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [
+    scompositor::WlCompositor,
+    soutput::WlOutput,
+    sbuffer::WlBuffer
+]);
+server_ignore_global_impl!(ServerHandler => [
+    scompositor::WlCompositor,
+    sshm::WlShm,
+    soutput::WlOutput,
+    smanager::ZwlrScreencopyManagerV1
+]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    shm: Option<cshm::WlShm>,
+    output: Option<coutput::WlOutput>,
+    screencopy: Option<ScreencopyState>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<ScreencopyState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut ScreencopyState {
+        self.screencopy.as_mut().unwrap()
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler:
+    [cmanager::ZwlrScreencopyManagerV1, cframe::ZwlrScreencopyFrameV1] => ScreencopyState
+);
+
+client_ignore_impl!(ClientHandler => [
+    ccompositor::WlCompositor,
+    cshm::WlShm,
+    coutput::WlOutput
+]);
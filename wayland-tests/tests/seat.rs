@@ -0,0 +1,465 @@
+use std::os::unix::io::AsRawFd;
+
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{
+    wl_compositor as scompositor, wl_keyboard as skeyboard, wl_pointer as spointer,
+    wl_seat as sseat, wl_surface as ssurface,
+};
+use ways::Resource;
+
+use wayc::protocol::{
+    wl_compositor as ccompositor, wl_keyboard as ckeyboard, wl_pointer as cpointer,
+    wl_seat as cseat, wl_surface as csurface, wl_touch as ctouch,
+};
+use wayc::seat::{InputEvent, SeatState};
+use wayc::serial::{SerialError, SerialKind, SerialUsage};
+
+#[test]
+fn seat_input_events() {
+    let mut server = TestServer::new();
+    server.display.create_global::<sseat::WlSeat>(7, ());
+    server.display.create_global::<scompositor::WlCompositor>(1, ());
+    let mut server_ddata = ServerHandler {
+        seat: None,
+        pointer: None,
+        keyboard: None,
+        surface: None,
+        released_pointers: 0,
+    };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata =
+        ClientHandler { globals: wayc::globals::GlobalList::new(), seats: SeatState::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    client_ddata
+        .seats
+        .bind_seats(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            &client_ddata.globals,
+        )
+        .unwrap();
+    let compositor = client_ddata
+        .globals
+        .bind::<ccompositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let surface = compositor
+        .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_seat = server_ddata.seat.clone().unwrap();
+    server_seat.name(&mut server.display.handle(), "seat0".into());
+    server_seat.capabilities(
+        &mut server.display.handle(),
+        sseat::Capability::Pointer | sseat::Capability::Keyboard,
+    );
+
+    // the devices are created when the capabilities are received
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata.seats.seats()[0].seat().clone();
+    let info = client_ddata.seats.info(&seat).unwrap();
+    assert_eq!(info.name(), Some("seat0"));
+    assert!(info.pointer().is_some());
+    assert!(info.keyboard().is_some());
+    assert!(info.touch().is_none());
+    let events = client_ddata.seats.take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        InputEvent::Capabilities { capabilities, .. }
+            if capabilities == cseat::Capability::Pointer | cseat::Capability::Keyboard
+    ));
+
+    let pointer = server_ddata.pointer.clone().unwrap();
+    let keyboard = server_ddata.keyboard.clone().unwrap();
+    let server_surface = server_ddata.surface.clone().unwrap();
+    let keymap = tempfile::tempfile().unwrap();
+    {
+        let mut handle = server.display.handle();
+        keyboard.keymap(&mut handle, skeyboard::KeymapFormat::XkbV1, keymap.as_raw_fd(), 1);
+        keyboard.enter(&mut handle, 1, &server_surface, 30u32.to_ne_bytes().to_vec());
+        keyboard.key(&mut handle, 2, 10, 30, skeyboard::KeyState::Released);
+        keyboard.modifiers(&mut handle, 3, 1, 0, 0, 0);
+        pointer.enter(&mut handle, 4, &server_surface, 1.0, 2.0);
+        pointer.motion(&mut handle, 11, 3.0, 4.0);
+        pointer.button(&mut handle, 5, 12, 0x110, spointer::ButtonState::Pressed);
+        pointer.leave(&mut handle, 6, &server_surface);
+        // not focused anymore, this event is dropped
+        pointer.motion(&mut handle, 13, 5.0, 6.0);
+    }
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let events = client_ddata.seats.take_events();
+    assert_eq!(events.len(), 7);
+    assert!(matches!(
+        events[0],
+        InputEvent::KeyboardEnter { serial: 1, surface: ref s, ref keys, .. }
+            if s == &surface && keys == &[30]
+    ));
+    assert!(matches!(
+        events[1],
+        InputEvent::Key { serial: 2, ref event, .. }
+            if event.time == 10
+                && event.raw_code == 30
+                && event.state == wayc::WEnum::Value(ckeyboard::KeyState::Released)
+    ));
+    assert!(matches!(
+        events[2],
+        InputEvent::Modifiers { modifiers, .. } if modifiers.depressed == 1
+    ));
+    assert!(matches!(
+        events[3],
+        InputEvent::PointerEnter { serial: 4, position: (x, y), .. } if x == 1.0 && y == 2.0
+    ));
+    assert!(matches!(
+        events[4],
+        InputEvent::PointerMotion { time: 11, position: (x, y), .. } if x == 3.0 && y == 4.0
+    ));
+    assert!(matches!(
+        events[5],
+        InputEvent::PointerButton {
+            serial: 5,
+            button: 0x110,
+            state: wayc::WEnum::Value(cpointer::ButtonState::Pressed),
+            ..
+        }
+    ));
+    assert!(matches!(events[6], InputEvent::PointerLeave { serial: 6, .. }));
+
+    let info = client_ddata.seats.info(&seat).unwrap();
+    assert_eq!(info.keyboard_focus(), Some(&surface));
+    assert_eq!(info.pointer_focus(), None);
+    assert_eq!(info.modifiers().depressed, 1);
+
+    // the serials of the events allowing requests are tracked
+    let serials = client_ddata.seats.serials();
+    assert!(matches!(
+        serials.latest(&seat, SerialUsage::SetCursor),
+        Err(SerialError::NoSerial { usage: SerialUsage::SetCursor })
+    ));
+    assert_eq!(serials.latest(&seat, SerialUsage::Move).unwrap().serial, 5);
+    assert_eq!(
+        serials.check(&seat, SerialUsage::SetSelection, 1).unwrap().kind,
+        SerialKind::KeyboardEnter
+    );
+    assert!(matches!(
+        serials.check(&seat, SerialUsage::Move, 1),
+        Err(SerialError::WrongEvent { serial: 1, kind: SerialKind::KeyboardEnter, .. })
+    ));
+    // the key was released
+    assert!(matches!(
+        serials.check(&seat, SerialUsage::PopupGrab, 2),
+        Err(SerialError::UnknownSerial { serial: 2, .. })
+    ));
+
+    // losing the pointer capability releases the pointer
+    server_seat.capabilities(&mut server.display.handle(), sseat::Capability::Keyboard);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(server_ddata.released_pointers, 1);
+    let info = client_ddata.seats.info(&seat).unwrap();
+    assert!(info.pointer().is_none());
+    assert!(info.keyboard().is_some());
+}
+
+// Connect a client to a seat of the given version, and focus its pointer on a surface
+fn focused_pointer(
+    version: u32,
+) -> (TestServer<ServerHandler>, TestClient<ClientHandler>, ServerHandler, ClientHandler) {
+    let mut server = TestServer::new();
+    server.display.create_global::<sseat::WlSeat>(version, ());
+    server.display.create_global::<scompositor::WlCompositor>(1, ());
+    let mut server_ddata = ServerHandler {
+        seat: None,
+        pointer: None,
+        keyboard: None,
+        surface: None,
+        released_pointers: 0,
+    };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata =
+        ClientHandler { globals: wayc::globals::GlobalList::new(), seats: SeatState::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    client_ddata
+        .seats
+        .bind_seats(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            &client_ddata.globals,
+        )
+        .unwrap();
+    let compositor = client_ddata
+        .globals
+        .bind::<ccompositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    compositor.create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_seat = server_ddata.seat.clone().unwrap();
+    server_seat.capabilities(&mut server.display.handle(), sseat::Capability::Pointer);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let pointer = server_ddata.pointer.clone().unwrap();
+    assert_eq!(pointer.version(), version);
+    let server_surface = server_ddata.surface.clone().unwrap();
+    pointer.enter(&mut server.display.handle(), 1, &server_surface, 0.0, 0.0);
+    if version >= spointer::EVT_FRAME_SINCE {
+        pointer.frame(&mut server.display.handle());
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    client_ddata.seats.take_events();
+
+    (server, client, server_ddata, client_ddata)
+}
+
+#[test]
+fn seat_pointer_axis_frames() {
+    let (mut server, mut client, mut server_ddata, mut client_ddata) = focused_pointer(9);
+    let pointer = server_ddata.pointer.clone().unwrap();
+    {
+        let mut handle = server.display.handle();
+        pointer.axis_source(&mut handle, spointer::AxisSource::Wheel);
+        pointer.axis_relative_direction(
+            &mut handle,
+            spointer::Axis::VerticalScroll,
+            spointer::AxisRelativeDirection::Inverted,
+        );
+        pointer.axis_value120(&mut handle, spointer::Axis::VerticalScroll, 60);
+        pointer.axis(&mut handle, 10, spointer::Axis::VerticalScroll, 7.5);
+        pointer.axis(&mut handle, 10, spointer::Axis::HorizontalScroll, -2.0);
+        pointer.frame(&mut handle);
+        pointer.axis_stop(&mut handle, 11, spointer::Axis::VerticalScroll);
+        pointer.frame(&mut handle);
+        // frames without scrolling are not reported
+        pointer.motion(&mut handle, 12, 1.0, 1.0);
+        pointer.frame(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // the scrolling of both axes is reported once per frame
+    let events = client_ddata.seats.take_events();
+    assert_eq!(events.len(), 3);
+    match events[0] {
+        InputEvent::PointerAxis { time, horizontal, vertical, source, .. } => {
+            assert_eq!(time, 10);
+            assert_eq!(source, Some(wayc::WEnum::Value(cpointer::AxisSource::Wheel)));
+            assert_eq!(vertical.absolute, 7.5);
+            assert_eq!(vertical.value120, 60);
+            assert!(vertical.inverted);
+            assert!(!vertical.stop);
+            assert_eq!(horizontal.absolute, -2.0);
+            assert_eq!(horizontal.value120, 0);
+            assert!(!horizontal.inverted);
+        }
+        ref event => panic!("Unexpected event {:?}", event),
+    }
+    match events[1] {
+        InputEvent::PointerAxis { time, horizontal, vertical, source, .. } => {
+            assert_eq!(time, 11);
+            assert_eq!(source, None);
+            assert!(vertical.stop);
+            assert_eq!(vertical.absolute, 0.0);
+            assert!(horizontal.is_none());
+        }
+        ref event => panic!("Unexpected event {:?}", event),
+    }
+    assert!(matches!(events[2], InputEvent::PointerMotion { time: 12, .. }));
+}
+
+#[test]
+fn seat_pointer_axis_legacy() {
+    // discrete steps are converted to high-resolution ones
+    let (mut server, mut client, mut server_ddata, mut client_ddata) = focused_pointer(7);
+    let pointer = server_ddata.pointer.clone().unwrap();
+    {
+        let mut handle = server.display.handle();
+        pointer.axis_discrete(&mut handle, spointer::Axis::VerticalScroll, -2);
+        pointer.axis(&mut handle, 10, spointer::Axis::VerticalScroll, -15.0);
+        pointer.frame(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let events = client_ddata.seats.take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        InputEvent::PointerAxis { vertical, .. }
+            if vertical.value120 == -240 && vertical.absolute == -15.0 && !vertical.inverted
+    ));
+
+    // without frames, each axis is reported separately
+    let (mut server, mut client, mut server_ddata, mut client_ddata) = focused_pointer(4);
+    let pointer = server_ddata.pointer.clone().unwrap();
+    {
+        let mut handle = server.display.handle();
+        pointer.axis(&mut handle, 10, spointer::Axis::VerticalScroll, 3.0);
+        pointer.axis(&mut handle, 11, spointer::Axis::HorizontalScroll, 4.0);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let events = client_ddata.seats.take_events();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        events[0],
+        InputEvent::PointerAxis { time: 10, vertical, horizontal, .. }
+            if vertical.absolute == 3.0 && horizontal.is_none()
+    ));
+    assert!(matches!(
+        events[1],
+        InputEvent::PointerAxis { time: 11, vertical, horizontal, .. }
+            if horizontal.absolute == 4.0 && vertical.is_none()
+    ));
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    seat: Option<sseat::WlSeat>,
+    pointer: Option<spointer::WlPointer>,
+    keyboard: Option<skeyboard::WlKeyboard>,
+    surface: Option<ssurface::WlSurface>,
+    released_pointers: usize,
+}
+
+impl ways::GlobalDispatch<sseat::WlSeat> for ServerHandler {
+    type GlobalData = ();
+
+    fn bind(
+        &mut self,
+        _: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        new_id: ways::New<sseat::WlSeat>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        self.seat = Some(data_init.init(new_id, ()));
+    }
+}
+
+impl ways::Dispatch<sseat::WlSeat> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sseat::WlSeat,
+        request: sseat::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            sseat::Request::GetPointer { id } => self.pointer = Some(data_init.init(id, ())),
+            sseat::Request::GetKeyboard { id } => self.keyboard = Some(data_init.init(id, ())),
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<spointer::WlPointer> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &spointer::WlPointer,
+        request: spointer::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let spointer::Request::Release = request {
+            self.released_pointers += 1;
+        }
+    }
+}
+
+impl ways::Dispatch<scompositor::WlCompositor> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &scompositor::WlCompositor,
+        request: scompositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let scompositor::Request::CreateSurface { id } = request {
+            self.surface = Some(data_init.init(id, ()));
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [skeyboard::WlKeyboard, ssurface::WlSurface]);
+server_ignore_global_impl!(ServerHandler => [scompositor::WlCompositor]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    seats: SeatState,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<SeatState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut SeatState {
+        &mut self.seats
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler:
+    [cseat::WlSeat, cpointer::WlPointer, ckeyboard::WlKeyboard, ctouch::WlTouch] => SeatState
+);
+
+client_ignore_impl!(ClientHandler => [ccompositor::WlCompositor, csurface::WlSurface]);
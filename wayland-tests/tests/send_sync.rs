@@ -7,6 +7,7 @@ fn ensure_both<I: Send + Sync>() {}
 fn send_sync_client() {
     ensure_both::<wayc::Connection>();
     ensure_both::<wayc::EventQueue<()>>();
+    ensure_both::<wayc::Sender>();
     ensure_both::<wayc::protocol::wl_callback::WlCallback>();
 }
 
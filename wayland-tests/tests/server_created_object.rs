@@ -80,6 +80,136 @@ fn data_offer() {
     assert_eq!(client_do.id().protocol_id(), 0xFF000000);
 }
 
+#[test]
+fn data_offer_with_event() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerSeat>(1, ());
+    server.display.create_global::<ServerDDMgr>(3, ());
+    let mut server_ddata = ServerHandler { data_device: None };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler::new();
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata
+        .globals
+        .bind::<ClientSeat, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let ddmgr = client_ddata
+        .globals
+        .bind::<ClientDDMgr, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            3..4,
+            (),
+        )
+        .unwrap();
+
+    ddmgr
+        .get_data_device(&mut client.conn.handle(), &seat, &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_dd = server_ddata.data_device.take().unwrap();
+    let s_client = server.display.handle().get_client(server_dd.id()).unwrap();
+    let offer = s_client
+        .create_resource_with_event::<ServerDO, _, ServerHandler, _>(
+            &mut server.display.handle(),
+            server_dd.version(),
+            (),
+            &server_dd,
+            |id| ways::protocol::wl_data_device::Event::DataOffer { id },
+        )
+        .unwrap();
+    assert_eq!(offer.id().protocol_id(), 0xFF000000);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let client_do = client_ddata.data_offer.take().unwrap();
+    assert_eq!(client_do.version(), 3);
+    assert_eq!(client_do.id().protocol_id(), 0xFF000000);
+}
+
+#[test]
+fn data_offer_with_event_dead_parent() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerSeat>(1, ());
+    server.display.create_global::<ServerDDMgr>(3, ());
+    let mut server_ddata = ServerHandler { data_device: None };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler::new();
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata
+        .globals
+        .bind::<ClientSeat, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let ddmgr = client_ddata
+        .globals
+        .bind::<ClientDDMgr, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            3..4,
+            (),
+        )
+        .unwrap();
+
+    let client_dd = ddmgr
+        .get_data_device(&mut client.conn.handle(), &seat, &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_dd = server_ddata.data_device.take().unwrap();
+    let s_client = server.display.handle().get_client(server_dd.id()).unwrap();
+
+    client_dd.release(&mut client.conn.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // the data device is dead, the offer is not created
+    assert!(s_client
+        .create_resource_with_event::<ServerDO, _, ServerHandler, _>(
+            &mut server.display.handle(),
+            server_dd.version(),
+            (),
+            &server_dd,
+            |id| ways::protocol::wl_data_device::Event::DataOffer { id },
+        )
+        .is_err());
+    assert!(s_client.objects_of::<ServerDO>(&mut server.display.handle()).unwrap().is_empty());
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert!(client_ddata.data_offer.is_none());
+}
+
 #[test]
 fn server_id_reuse() {
     let mut server = TestServer::new();
@@ -423,6 +553,78 @@ fn creation_destruction_queue_dispatch_race() {
     assert!(client_ddata.received_dead);
 }
 
+#[test]
+fn missing_event_created_child() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerSeat>(1, ());
+    server.display.create_global::<ServerDDMgr>(3, ());
+    let mut server_ddata = ServerHandler { data_device: None };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = NoChildHandler { globals: Default::default() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let seat = client_ddata
+        .globals
+        .bind::<ClientSeat, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let ddmgr = client_ddata
+        .globals
+        .bind::<ClientDDMgr, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            3..4,
+            (),
+        )
+        .unwrap();
+
+    let creation_line = line!() + 2;
+    ddmgr
+        .get_data_device(&mut client.conn.handle(), &seat, &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_dd = server_ddata.data_device.take().unwrap();
+    let s_client = server.display.handle().get_client(server_dd.id()).unwrap();
+    let offer = s_client
+        .create_resource::<ServerDO, ServerHandler>(
+            &mut server.display.handle(),
+            server_dd.version(),
+            (),
+        )
+        .unwrap();
+    server_dd.data_offer(&mut server.display.handle(), &offer);
+
+    server.display.flush_clients().unwrap();
+    client.conn.prepare_read().unwrap().read().unwrap();
+
+    // the Dispatch<WlDataDevice> implementation does not know how to initialize the data offer
+    match client.event_queue.dispatch_pending(&mut client_ddata) {
+        Err(wayc::DispatchError::MissingChildData { interface, event, state, created_at }) => {
+            assert_eq!(interface, "wl_data_device");
+            assert_eq!(event, "data_offer");
+            assert!(state.ends_with("NoChildHandler"));
+            assert_eq!(created_at.file(), file!());
+            assert_eq!(created_at.line(), creation_line);
+        }
+        other => panic!("Unexpected dispatch result: {:?}", other),
+    }
+}
+
 struct ClientHandler {
     globals: wayc::globals::GlobalList,
     data_offer: Option<ClientDO>,
@@ -477,7 +679,7 @@ impl wayc::Dispatch<wayc::protocol::wl_data_device::WlDataDevice> for ClientHand
     }
 
     wayc::event_created_child!(ClientHandler, wayc::protocol::wl_data_device::WlDataDevice, [
-        0 => (ClientDO, ())
+        wayc::protocol::wl_data_device::EVT_DATA_OFFER_OPCODE => (ClientDO, ())
     ]);
 }
 
@@ -501,6 +703,25 @@ impl wayc::Dispatch<ClientDO> for ClientHandler {
     }
 }
 
+struct NoChildHandler {
+    globals: wayc::globals::GlobalList,
+}
+
+impl AsMut<wayc::globals::GlobalList> for NoChildHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(NoChildHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+client_ignore_impl!(NoChildHandler => [
+    ClientSeat,
+    ClientDDMgr,
+    wayc::protocol::wl_data_device::WlDataDevice
+]);
+
 struct ServerHandler {
     data_device: Option<ServerDD>,
 }
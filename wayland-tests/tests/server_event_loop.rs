@@ -0,0 +1,248 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{wayc, ways, DumbClientData, TestClient};
+
+use ways::event_loop::{EventLoop, LoopHandle, TimeoutAction};
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+use ways::socket::ListeningSocket;
+
+use wayc::protocol::wl_output::{Event as ClientOutputEvent, WlOutput as ClientOutput};
+
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Default)]
+struct ServerData {
+    accepted: usize,
+    ticks: usize,
+    idles: usize,
+}
+
+#[test]
+fn timers_and_idles() {
+    let display = ways::Display::<ServerData>::new().unwrap();
+    let mut event_loop = EventLoop::new().unwrap();
+    let handle = event_loop.handle();
+    let signal = event_loop.signal();
+
+    // a repeating timer, which also schedules an idle callback at each tick
+    let idle_handle = handle.clone();
+    handle.add_timer(Duration::from_millis(5), move |data: &mut ServerData, _| {
+        data.ticks += 1;
+        idle_handle.add_idle(|data: &mut ServerData, _| data.idles += 1);
+        if data.ticks == 3 {
+            TimeoutAction::Drop
+        } else {
+            TimeoutAction::ToDuration(Duration::from_millis(5))
+        }
+    });
+    // a timer removed before firing
+    let removed = handle.add_timer(Duration::from_millis(1), |_, _| panic!("Removed timer fired."));
+    handle.remove_timer(removed);
+    handle.add_timer(Duration::from_millis(50), move |_, _| {
+        signal.stop();
+        TimeoutAction::Drop
+    });
+
+    let mut data = ServerData::default();
+    display.run(&mut event_loop, &mut data).unwrap();
+
+    assert_eq!(data.ticks, 3);
+    assert_eq!(data.idles, 3);
+}
+
+#[test]
+fn stop_from_another_thread() {
+    let display = ways::Display::<ServerData>::new().unwrap();
+    let mut event_loop = EventLoop::new().unwrap();
+    let signal = event_loop.signal();
+
+    // without timers nor clients, the loop waits until it is woken up by the signal
+    let stop_thread = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        signal.stop();
+    });
+
+    let mut data = ServerData::default();
+    display.run(&mut event_loop, &mut data).unwrap();
+    stop_thread.join().unwrap();
+}
+
+#[test]
+fn stop_before_run() {
+    let display = ways::Display::<ServerData>::new().unwrap();
+    let mut event_loop = EventLoop::new().unwrap();
+
+    // the stop is not lost, the loop returns right away
+    event_loop.signal().stop();
+    let mut data = ServerData::default();
+    display.run(&mut event_loop, &mut data).unwrap();
+    assert_eq!(data.ticks, 0);
+
+    // and it is consumed, the loop can be run again
+    let signal = event_loop.signal();
+    event_loop.handle().add_timer(Duration::from_millis(5), move |data: &mut ServerData, _| {
+        data.ticks += 1;
+        signal.stop();
+        TimeoutAction::Drop
+    });
+    display.run(&mut event_loop, &mut data).unwrap();
+    assert_eq!(data.ticks, 1);
+}
+
+#[test]
+fn accept_clients() {
+    let runtime_dir = tempfile::tempdir().unwrap();
+    std::env::set_var("XDG_RUNTIME_DIR", runtime_dir.path());
+
+    let display = ways::Display::<ServerData>::new().unwrap();
+    let mut event_loop = EventLoop::new().unwrap();
+    let socket = ListeningSocket::bind("wayland-event-loop").unwrap();
+    event_loop.add_listening_socket(socket, |data: &mut ServerData| {
+        data.accepted += 1;
+        Arc::new(DumbClientData)
+    });
+
+    let done = Arc::new(AtomicBool::new(false));
+    let client_done = done.clone();
+    let path = runtime_dir.path().join("wayland-event-loop");
+    let client_thread = std::thread::spawn(move || {
+        let stream = UnixStream::connect(path).unwrap();
+        let client = helpers::TestClient::<()>::new(stream);
+        client.conn.roundtrip().unwrap();
+        client_done.store(true, Ordering::SeqCst);
+    });
+
+    // stop once the client did a roundtrip, or give up after a while
+    let signal = event_loop.signal();
+    let mut remaining = 500;
+    event_loop.handle().add_timer(Duration::from_millis(10), move |_, _| {
+        remaining -= 1;
+        if done.load(Ordering::SeqCst) || remaining == 0 {
+            signal.stop();
+            TimeoutAction::Drop
+        } else {
+            TimeoutAction::ToDuration(Duration::from_millis(10))
+        }
+    });
+
+    let mut data = ServerData::default();
+    display.run(&mut event_loop, &mut data).unwrap();
+    client_thread.join().unwrap();
+
+    assert_eq!(data.accepted, 1);
+}
+
+#[test]
+fn timers_from_request_handlers() {
+    let display = ways::Display::<OutputServer>::new().unwrap();
+    display.create_global::<ServerOutput>(2, ());
+    let mut event_loop = EventLoop::new().unwrap();
+
+    let (server_socket, client_socket) = UnixStream::pair().unwrap();
+    display.insert_client(server_socket, Arc::new(DumbClientData)).unwrap();
+
+    let client_thread = std::thread::spawn(move || {
+        let mut client = TestClient::new(client_socket);
+        let mut client_data = OutputClient { globals: Default::default(), done: 0 };
+        let registry = client
+            .display
+            .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+            .unwrap();
+        client.conn.roundtrip().unwrap();
+        client.event_queue.dispatch_pending(&mut client_data).unwrap();
+        client_data
+            .globals
+            .bind::<ClientOutput, _>(
+                &mut client.conn.handle(),
+                &client.event_queue.handle(),
+                &registry,
+                2..3,
+                (),
+            )
+            .unwrap();
+        while client_data.done < 3 {
+            client.event_queue.blocking_dispatch(&mut client_data).unwrap();
+        }
+    });
+
+    // give up after a while
+    let signal = event_loop.signal();
+    event_loop.handle().add_timer(Duration::from_secs(5), move |_, _| {
+        signal.stop();
+        TimeoutAction::Drop
+    });
+
+    let mut server_data =
+        OutputServer { handle: event_loop.handle(), signal: event_loop.signal(), done: 0 };
+    display.run(&mut event_loop, &mut server_data).unwrap();
+
+    assert_eq!(server_data.done, 3);
+    client_thread.join().unwrap();
+}
+
+struct OutputServer {
+    handle: LoopHandle<OutputServer>,
+    signal: ways::event_loop::LoopSignal,
+    done: usize,
+}
+
+server_ignore_impl!(OutputServer => [ServerOutput]);
+
+impl ways::GlobalDispatch<ServerOutput> for OutputServer {
+    type GlobalData = ();
+
+    fn bind(
+        &mut self,
+        _: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        new_id: ways::New<ServerOutput>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        let output = data_init.init(new_id, ());
+        // send a done event periodically, like a repeated key
+        self.handle.add_periodic_timer(Duration::from_millis(5), move |data, handle| {
+            output.done(handle);
+            data.done += 1;
+            if data.done == 3 {
+                data.signal.stop();
+            }
+        });
+    }
+}
+
+struct OutputClient {
+    globals: wayc::globals::GlobalList,
+    done: usize,
+}
+
+impl AsMut<wayc::globals::GlobalList> for OutputClient {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(OutputClient:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<ClientOutput> for OutputClient {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &ClientOutput,
+        event: ClientOutputEvent,
+        _: &Self::UserData,
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let ClientOutputEvent::Done = event {
+            self.done += 1;
+        }
+    }
+}
@@ -0,0 +1,167 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::focus::FocusTracker;
+use ways::protocol::{wl_compositor, wl_surface};
+
+// Create a client with two surfaces, returns the server-side surfaces
+fn two_surfaces() -> (TestServer<ServerHandler>, TestClient<ClientHandler>, ServerHandler) {
+    let mut server = TestServer::new();
+    server.display.create_global::<wl_compositor::WlCompositor>(1, ());
+    let mut server_ddata = ServerHandler { surfaces: Vec::new() };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: Default::default() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let compositor = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    compositor.create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    let surface = compositor
+        .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // destroy the second surface
+    surface.destroy(&mut client.conn.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(server_ddata.surfaces.len(), 2);
+    (server, client, server_ddata)
+}
+
+#[test]
+fn focus_enter_leave() {
+    let (server, _client, server_ddata) = two_surfaces();
+    let surface = &server_ddata.surfaces[0];
+
+    let mut tracker = FocusTracker::new();
+    assert!(tracker.current().is_none());
+
+    tracker.enter(&mut server.display.handle(), surface, 1);
+    assert_eq!(tracker.current(), Some(surface));
+    assert!(tracker.has_focus(surface));
+
+    tracker.leave(surface, 2);
+    assert!(tracker.current().is_none());
+    assert_eq!(tracker.last_serial(), Some(2));
+
+    // serials wrap around
+    let mut tracker = FocusTracker::new();
+    tracker.enter(&mut server.display.handle(), surface, u32::MAX);
+    tracker.leave(surface, 0);
+    assert!(tracker.current().is_none());
+}
+
+#[test]
+fn focus_surface_destroyed() {
+    let (server, _client, server_ddata) = two_surfaces();
+    let surface = &server_ddata.surfaces[0];
+
+    let mut tracker = FocusTracker::new();
+    tracker.enter(&mut server.display.handle(), surface, 1);
+    assert!(!tracker.surface_destroyed(&server_ddata.surfaces[1]));
+    assert!(tracker.surface_destroyed(surface));
+    assert!(tracker.current().is_none());
+}
+
+#[test]
+#[should_panic]
+fn focus_enter_twice() {
+    let (server, _client, server_ddata) = two_surfaces();
+    let surface = &server_ddata.surfaces[0];
+
+    let mut tracker = FocusTracker::new();
+    tracker.enter(&mut server.display.handle(), surface, 1);
+    tracker.enter(&mut server.display.handle(), surface, 2);
+}
+
+#[test]
+#[should_panic]
+fn focus_leave_without_enter() {
+    let (_server, _client, server_ddata) = two_surfaces();
+
+    let mut tracker = FocusTracker::new();
+    tracker.leave(&server_ddata.surfaces[0], 1);
+}
+
+#[test]
+#[should_panic]
+fn focus_enter_dead_surface() {
+    let (server, _client, server_ddata) = two_surfaces();
+
+    let mut tracker = FocusTracker::new();
+    tracker.enter(&mut server.display.handle(), &server_ddata.surfaces[1], 1);
+}
+
+#[test]
+#[should_panic]
+fn focus_serial_not_increasing() {
+    let (server, _client, server_ddata) = two_surfaces();
+    let surface = &server_ddata.surfaces[0];
+
+    let mut tracker = FocusTracker::new();
+    tracker.enter(&mut server.display.handle(), surface, 5);
+    tracker.leave(surface, 5);
+}
+
+struct ServerHandler {
+    surfaces: Vec<wl_surface::WlSurface>,
+}
+
+impl ways::Dispatch<wl_compositor::WlCompositor> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &wl_compositor::WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let wl_compositor::Request::CreateSurface { id } = request {
+            let surface = data_init.init(id, ());
+            self.surfaces.push(surface);
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [wl_surface::WlSurface]);
+server_ignore_global_impl!(ServerHandler => [wl_compositor::WlCompositor]);
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+client_ignore_impl!(ClientHandler => [
+    wayc::protocol::wl_compositor::WlCompositor,
+    wayc::protocol::wl_surface::WlSurface
+]);
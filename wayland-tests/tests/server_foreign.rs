@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+#[macro_use]
+mod helpers;
+
+use helpers::{connect_client, roundtrip, wayc, ways, TestCompositor, TestServer};
+
+use ways::foreign::ForeignRegistry;
+use ways::protocol::{
+    wl_compositor::WlCompositor as ServerCompositor, wl_region::WlRegion as ServerRegion,
+    wl_surface::WlSurface as ServerSurface,
+};
+
+use wayc::protocol::wl_compositor::WlCompositor as ClientCompositor;
+
+// The regions of the importing client stand for the objects representing its imports
+#[test]
+fn export_and_import() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(1, ());
+    let mut server_ddata = ServerHandler { compositor: TestCompositor::default() };
+    let mut registry = ForeignRegistry::<ServerSurface, ServerRegion>::new();
+
+    let mut exporter_ddata = ClientHandler { globals: Default::default() };
+    let mut importer_ddata = ClientHandler { globals: Default::default() };
+    let (mut exporter, _, exporter_compositor) =
+        connect_client(&mut server, &mut exporter_ddata, &mut server_ddata);
+    let (mut importer, _, importer_compositor) =
+        connect_client(&mut server, &mut importer_ddata, &mut server_ddata);
+
+    let surface = exporter_compositor
+        .create_surface(&mut exporter.conn.handle(), &exporter.event_queue.handle(), ())
+        .unwrap();
+    exporter_compositor
+        .create_surface(&mut exporter.conn.handle(), &exporter.event_queue.handle(), ())
+        .unwrap();
+    roundtrip(&mut exporter, &mut server, &mut exporter_ddata, &mut server_ddata).unwrap();
+    for _ in 0..4 {
+        importer_compositor
+            .create_region(&mut importer.conn.handle(), &importer.event_queue.handle(), ())
+            .unwrap();
+    }
+    roundtrip(&mut importer, &mut server, &mut importer_ddata, &mut server_ddata).unwrap();
+
+    let surfaces = server_ddata.compositor.surfaces.clone();
+    let regions = server_ddata.compositor.regions.clone();
+    let mut handle = server.display.handle();
+
+    let exported = registry.export(surfaces[0].clone(), None);
+    let expired = registry.export(surfaces[1].clone(), Some(Duration::ZERO));
+    assert_eq!(exported.len(), 32);
+    assert_ne!(exported, expired);
+    assert_eq!(registry.exported(&exported), Some(&surfaces[0]));
+
+    assert_eq!(
+        registry.import(&mut handle, &exported, regions[0].clone()),
+        Some(surfaces[0].clone())
+    );
+    assert_eq!(
+        registry.import(&mut handle, &exported, regions[1].clone()),
+        Some(surfaces[0].clone())
+    );
+    assert_eq!(registry.import(&mut handle, "not a handle", regions[2].clone()), None);
+    assert_eq!(registry.import(&mut handle, &expired, regions[2].clone()), None);
+
+    // revoking an export returns its importers which are still alive
+    registry.import_destroyed(&regions[1]);
+    assert_eq!(registry.unexport(&expired), []);
+    assert_eq!(registry.unexport(&exported), [regions[0].clone()]);
+    assert_eq!(registry.import(&mut handle, &exported, regions[3].clone()), None);
+
+    // a destroyed object cannot be imported
+    let exported = registry.export(surfaces[0].clone(), None);
+    drop(handle);
+    surface.destroy(&mut exporter.conn.handle());
+    roundtrip(&mut exporter, &mut server, &mut exporter_ddata, &mut server_ddata).unwrap();
+    let mut handle = server.display.handle();
+    assert_eq!(registry.import(&mut handle, &exported, regions[3].clone()), None);
+    assert_eq!(registry.object_destroyed(&surfaces[0]), []);
+    assert_eq!(registry.exported(&exported), None);
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    compositor: TestCompositor,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler: [ServerCompositor] => TestCompositor);
+ways::delegate_dispatch!(ServerHandler: [ServerCompositor, ServerSurface, ServerRegion] => TestCompositor);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+client_ignore_impl!(ClientHandler => [
+    ClientCompositor,
+    wayc::protocol::wl_surface::WlSurface,
+    wayc::protocol::wl_region::WlRegion
+]);
@@ -9,6 +9,10 @@ use helpers::{roundtrip, wayc, ways, TestServer};
 
 use ways::protocol::{wl_compositor, wl_output, wl_shm};
 
+use std::os::unix::{
+    io::IntoRawFd,
+    net::{UnixListener, UnixStream},
+};
 use std::sync::Arc;
 
 #[test]
@@ -112,6 +116,73 @@ fn global_filter_try_force() {
     assert!(roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).is_err());
 }
 
+#[test]
+fn global_filter_security_context() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_shm::WlShm>(1, ());
+    // sandboxed clients cannot see output, even if privileged
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(1, ());
+    let mut server_ddata = ServerHandler;
+
+    let runtime_dir = tempfile::tempdir().unwrap();
+    let path = runtime_dir.path().join("wayland-sandbox");
+    let listen_fd = UnixListener::bind(&path).unwrap().into_raw_fd();
+    let (close_fd, close_fd_peer) = nix::unistd::pipe().unwrap();
+    let context = ways::socket::SecurityContext {
+        sandbox_engine: Some("org.example.sandbox".into()),
+        app_id: Some("org.example.App".into()),
+        instance_id: None,
+    };
+    let listener = unsafe {
+        ways::socket::SecurityContextListener::from_raw_fds(listen_fd, close_fd, context.clone())
+    }
+    .unwrap();
+    assert!(!listener.is_closed());
+
+    let client_stream = UnixStream::connect(&path).unwrap();
+    let stream = listener.accept().unwrap().unwrap();
+    let server_client = server
+        .display
+        .insert_client_with_security_context(
+            stream,
+            Arc::new(listener.context().clone()),
+            Arc::new(MyClientData { privileged: true }),
+        )
+        .unwrap();
+    assert_eq!(server_client.security_context(), Some(&context));
+    assert!(server_client.get_data::<MyClientData>().unwrap().privileged);
+
+    let mut client = helpers::TestClient::new(client_stream);
+    let mut client_ddata = ClientHandler::new();
+    client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(client_ddata.globals.list().len(), 2);
+
+    // the sandbox engine closes the listener
+    nix::unistd::close(close_fd_peer).unwrap();
+    assert!(listener.is_closed());
+}
+
+#[test]
+fn security_context_invalid_listen_fd() {
+    let (socket, _) = UnixStream::pair().unwrap();
+    let (close_fd, _close_fd_peer) = nix::unistd::pipe().unwrap();
+    let ret = unsafe {
+        ways::socket::SecurityContextListener::from_raw_fds(
+            socket.into_raw_fd(),
+            close_fd,
+            Default::default(),
+        )
+    };
+    assert_eq!(ret.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
+
 struct ClientHandler {
     globals: wayc::globals::GlobalList,
 }
@@ -190,7 +261,7 @@ impl ways::GlobalDispatch<wl_output::WlOutput> for ServerHandler {
     }
 
     fn can_view(client: ways::Client, _: &Self::GlobalData) -> bool {
-        client.get_data::<MyClientData>().unwrap().privileged
+        client.security_context().is_none() && client.get_data::<MyClientData>().unwrap().privileged
     }
 }
 
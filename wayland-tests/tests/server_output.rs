@@ -0,0 +1,163 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use ways::output::{Mode, Output, OutputManager, OutputProperties};
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+use wayc::protocol::wl_output::{Event as OutputEvent, WlOutput as ClientOutput};
+
+#[test]
+fn output_events() {
+    let mut server = TestServer::new();
+    let mode = Mode { width: 1920, height: 1080, refresh: 60_000 };
+    let output = Output::new(
+        "DP-1".into(),
+        OutputProperties {
+            modes: vec![mode],
+            current_mode: Some(mode),
+            preferred_mode: Some(mode),
+            description: "Some monitor".into(),
+            ..Default::default()
+        },
+    );
+    output.create_global(&server.display);
+    let mut server_ddata = ServerHandler;
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: Default::default(), events: Vec::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // bind the output with the latest version and with the first one
+    for version in [4, 1] {
+        client_ddata
+            .globals
+            .bind::<ClientOutput, _>(
+                &mut client.conn.handle(),
+                &client.event_queue.handle(),
+                &registry,
+                version..version + 1,
+                version,
+            )
+            .unwrap();
+    }
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(
+        client_ddata.events,
+        [
+            (4, "geometry".into()),
+            (4, "mode 1920x1080 current|preferred".into()),
+            (4, "scale 1".into()),
+            (4, "name DP-1".into()),
+            (4, "description Some monitor".into()),
+            (4, "done".into()),
+            (1, "geometry".into()),
+            (1, "mode 1920x1080 current|preferred".into()),
+        ]
+    );
+    client_ddata.events.clear();
+
+    // switch to a new mode and scale
+    let hidpi = Mode { width: 3840, height: 2160, refresh: 60_000 };
+    output.update(&mut server.display.handle(), |properties| {
+        properties.modes.push(hidpi);
+        properties.current_mode = Some(hidpi);
+        properties.scale = 2;
+    });
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(
+        client_ddata.events,
+        [
+            (4, "mode 1920x1080 preferred".into()),
+            (4, "mode 3840x2160 current".into()),
+            (4, "scale 2".into()),
+            (4, "done".into()),
+            (1, "mode 1920x1080 preferred".into()),
+            (1, "mode 3840x2160 current".into()),
+        ]
+    );
+    client_ddata.events.clear();
+
+    // nothing is sent if nothing changed
+    output.update(&mut server.display.handle(), |properties| properties.scale = 2);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert!(client_ddata.events.is_empty());
+    assert_eq!(output.properties().scale, 2);
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler;
+
+ways::delegate_dispatch!(ServerHandler: [ServerOutput] => OutputManager);
+ways::delegate_global_dispatch!(ServerHandler: [ServerOutput] => OutputManager);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    events: Vec<(u32, String)>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<ClientOutput> for ClientHandler {
+    // the version the output was bound with
+    type UserData = u32;
+
+    fn event(
+        &mut self,
+        _: &ClientOutput,
+        event: OutputEvent,
+        version: &u32,
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        let event = match event {
+            OutputEvent::Geometry { .. } => "geometry".into(),
+            OutputEvent::Mode { flags, width, height, .. } => {
+                let flags = match flags {
+                    wayc::WEnum::Value(flags) => flags,
+                    wayc::WEnum::Unknown(_) => panic!("Unknown mode flags."),
+                };
+                let mut names = Vec::new();
+                if flags.contains(wayc::protocol::wl_output::Mode::Current) {
+                    names.push("current");
+                }
+                if flags.contains(wayc::protocol::wl_output::Mode::Preferred) {
+                    names.push("preferred");
+                }
+                format!("mode {}x{} {}", width, height, names.join("|"))
+            }
+            OutputEvent::Scale { factor } => format!("scale {}", factor),
+            OutputEvent::Name { name } => format!("name {}", name),
+            OutputEvent::Description { description } => format!("description {}", description),
+            OutputEvent::Done => "done".into(),
+            _ => unreachable!(),
+        };
+        self.events.push((*version, event));
+    }
+}
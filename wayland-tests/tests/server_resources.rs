@@ -5,7 +5,7 @@ use helpers::{roundtrip, wayc, ways, TestServer};
 
 use ways::{
     protocol::{wl_compositor, wl_output},
-    DestructionNotify, Resource,
+    Resource,
 };
 
 use wayc::protocol::wl_output::WlOutput as ClientOutput;
@@ -103,6 +103,7 @@ fn resource_user_data() {
     assert_eq!(server_ddata.outputs[1].data::<UData>().unwrap().0, 1001);
     let cloned = server_ddata.outputs[0].clone();
     assert_eq!(cloned.data::<UData>().unwrap().0, 1000);
+    assert!(cloned.data::<usize>().is_none());
 }
 
 #[test]
@@ -206,6 +207,65 @@ fn get_resource() {
         .is_ok());
 }
 
+#[test]
+fn objects_of() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_output::WlOutput>(3, ());
+    let mut server_ddata = ServerHandler { outputs: Vec::new() };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler::new();
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    // create two outputs
+    let client_outputs = (0..2)
+        .map(|_| {
+            client_ddata
+                .globals
+                .bind::<wayc::protocol::wl_output::WlOutput, _>(
+                    &mut client.conn.handle(),
+                    &client.event_queue.handle(),
+                    &registry,
+                    3..4,
+                    (),
+                )
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_client = server.display.handle().get_client(server_ddata.outputs[0].id()).unwrap();
+    let outputs =
+        server_client.objects_of::<wl_output::WlOutput>(&mut server.display.handle()).unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert!(server_ddata.outputs.iter().all(|output| outputs.contains(output)));
+    // resources of other interfaces are not listed
+    assert!(server_client
+        .objects_of::<wl_compositor::WlCompositor>(&mut server.display.handle())
+        .unwrap()
+        .is_empty());
+    // the untyped variant lists the same resources
+    let ids = server_client
+        .objects_of_interface(&mut server.display.handle(), wl_output::WlOutput::interface())
+        .unwrap();
+    assert_eq!(ids.len(), 2);
+    assert!(outputs.iter().all(|output| ids.contains(&output.id())));
+
+    // destroyed resources are no longer listed
+    client_outputs[0].release(&mut client.conn.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let outputs =
+        server_client.objects_of::<wl_output::WlOutput>(&mut server.display.handle()).unwrap();
+    assert_eq!(outputs.len(), 1);
+}
+
 struct ClientHandler {
     globals: wayc::globals::GlobalList,
 }
@@ -249,8 +309,6 @@ impl ways::GlobalDispatch<wl_output::WlOutput> for ServerHandler {
 
 struct UData(usize);
 
-impl DestructionNotify for UData {}
-
 impl ways::Dispatch<wl_output::WlOutput> for ServerHandler {
     type UserData = UData;
 
@@ -0,0 +1,323 @@
+use std::fs::File;
+use std::os::unix::{fs::FileExt, io::FromRawFd};
+
+#[macro_use]
+mod helpers;
+
+use helpers::{
+    roundtrip, setup_client, wayc, ways, TestClient, TestCompositor, TestServer, TestSetup,
+};
+
+use ways::protocol::{
+    wl_compositor::WlCompositor as ServerCompositor, wl_keyboard::WlKeyboard as ServerKeyboard,
+    wl_pointer::WlPointer as ServerPointer, wl_region::WlRegion as ServerRegion, wl_seat as sseat,
+    wl_surface::WlSurface as ServerSurface, wl_touch::WlTouch as ServerTouch,
+};
+use ways::seat::{CursorImage, Seat, SeatManager};
+
+use wayc::protocol::{
+    wl_keyboard::{Event as KeyboardEvent, WlKeyboard as ClientKeyboard},
+    wl_pointer::{Event as PointerEvent, WlPointer as ClientPointer},
+    wl_seat::{Event as SeatEvent, WlSeat as ClientSeat},
+};
+
+const KEYMAP: &str = "xkb_keymap { xkb_keycodes { include \"evdev\" }; };";
+
+#[test]
+fn seat_capabilities() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, seat) = setup();
+
+    assert_eq!(client_ddata.events, ["capabilities ", "name seat0"]);
+    client_ddata.events.clear();
+
+    server_ddata.seat.set_capabilities(&mut server.display.handle(), sseat::Capability::Keyboard);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(client_ddata.events, ["capabilities keyboard"]);
+
+    // the pointer capability was never advertised
+    seat.get_pointer(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    assert!(roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).is_err());
+    let error = client.conn.protocol_error().unwrap();
+    assert_eq!(error.code, sseat::Error::MissingCapability as u32);
+    assert_eq!(error.object_interface, "wl_seat");
+}
+
+#[test]
+fn seat_keymap() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, seat) = setup();
+
+    server_ddata.seat.set_capabilities(&mut server.display.handle(), sseat::Capability::Keyboard);
+    server_ddata.seat.set_keymap(&mut server.display.handle(), KEYMAP).unwrap();
+    server_ddata.seat.set_repeat_info(&mut server.display.handle(), 30, 200);
+    seat.get_keyboard(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let (file, size) = client_ddata.keymap.take().unwrap();
+    assert_eq!(size as usize, KEYMAP.len() + 1);
+    let mut contents = vec![0; size as usize];
+    file.read_exact_at(&mut contents, 0).unwrap();
+    assert_eq!(&contents[..KEYMAP.len()], KEYMAP.as_bytes());
+    assert_eq!(contents[KEYMAP.len()], 0);
+    // the keymap is sealed, the client cannot alter it
+    assert!(file.write_at(b"x", 0).is_err());
+    assert!(file.set_len(0).is_err());
+
+    assert_eq!(client_ddata.events.last().unwrap(), "repeat_info 30 200");
+}
+
+#[test]
+fn seat_focus() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, seat) = setup();
+
+    server_ddata.seat.set_capabilities(
+        &mut server.display.handle(),
+        sseat::Capability::Keyboard | sseat::Capability::Pointer,
+    );
+    seat.get_keyboard(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    let pointer =
+        seat.get_pointer(&mut client.conn.handle(), &client.event_queue.handle(), ()).unwrap();
+    let surface = client_ddata.surface(&client);
+    let cursor = client_ddata.surface(&client);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    client_ddata.events.clear();
+
+    let server_surface = server_ddata.compositor.surfaces[0].clone();
+    let server_cursor = server_ddata.compositor.surfaces[1].clone();
+    {
+        let handle = &mut server.display.handle();
+        // nothing is sent without focus
+        server_ddata.seat.keyboard_key(
+            handle,
+            1,
+            10,
+            30,
+            ways::protocol::wl_keyboard::KeyState::Pressed,
+        );
+        server_ddata.seat.keyboard_enter(handle, &server_surface, 2, &[30, 48]);
+        server_ddata.seat.keyboard_key(
+            handle,
+            3,
+            11,
+            30,
+            ways::protocol::wl_keyboard::KeyState::Released,
+        );
+        server_ddata.seat.keyboard_leave(handle, 4);
+        server_ddata.seat.pointer_enter(handle, &server_surface, 5, 1.0, 2.0);
+        server_ddata.seat.pointer_motion(handle, 12, 3.0, 4.0);
+        server_ddata.seat.pointer_frame(handle);
+    }
+    assert_eq!(server_ddata.seat.keyboard_focus(), None);
+    assert_eq!(server_ddata.seat.pointer_focus(), Some(server_surface.clone()));
+    assert_eq!(server_ddata.seat.keyboards(&server_surface).len(), 1);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(
+        client_ddata.events,
+        [
+            "keyboard enter 2 [30, 48]",
+            "keyboard key 3 30",
+            "keyboard leave 4",
+            "pointer enter 5 1x2",
+            "pointer motion 3x4",
+            "pointer frame",
+        ]
+    );
+
+    // the cursor is only set in response to the last enter
+    pointer.set_cursor(&mut client.conn.handle(), 4, Some(&cursor), 1, 1);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.seat.cursor_image(), None);
+    pointer.set_cursor(&mut client.conn.handle(), 5, Some(&cursor), 1, 2);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(
+        server_ddata.seat.cursor_image(),
+        Some(CursorImage::Surface { surface: server_cursor, hotspot: (1, 2) })
+    );
+
+    server_ddata.seat.surface_destroyed(&server_surface);
+    assert_eq!(server_ddata.seat.pointer_focus(), None);
+    assert_eq!(server_ddata.seat.cursor_image(), None);
+    drop(surface);
+}
+
+#[allow(clippy::type_complexity)]
+fn setup() -> (TestSetup<ServerHandler, ClientHandler>, ClientSeat) {
+    let server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(1, ());
+    let seat = Seat::new("seat0".into());
+    seat.create_global(&server.display);
+    let server_ddata = ServerHandler { seat, compositor: TestCompositor::default() };
+    let client_ddata = ClientHandler {
+        globals: Default::default(),
+        compositor: None,
+        events: Vec::new(),
+        keymap: None,
+    };
+
+    setup_client(
+        server,
+        server_ddata,
+        client_ddata,
+        |client, client_ddata, registry, compositor| {
+            client_ddata.compositor = Some(compositor.clone());
+            client_ddata
+                .globals
+                .bind::<ClientSeat, _>(
+                    &mut client.conn.handle(),
+                    &client.event_queue.handle(),
+                    registry,
+                    1..10,
+                    (),
+                )
+                .unwrap()
+        },
+    )
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    seat: Seat,
+    compositor: TestCompositor,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler: [sseat::WlSeat] => SeatManager);
+ways::delegate_dispatch!(ServerHandler: [
+    sseat::WlSeat,
+    ServerKeyboard,
+    ServerPointer,
+    ServerTouch
+] => SeatManager);
+
+ways::delegate_global_dispatch!(ServerHandler: [ServerCompositor] => TestCompositor);
+ways::delegate_dispatch!(ServerHandler: [ServerCompositor, ServerSurface, ServerRegion] => TestCompositor);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    compositor: Option<wayc::protocol::wl_compositor::WlCompositor>,
+    events: Vec<String>,
+    keymap: Option<(File, u32)>,
+}
+
+impl ClientHandler {
+    fn surface(&self, client: &TestClient<ClientHandler>) -> wayc::protocol::wl_surface::WlSurface {
+        self.compositor
+            .as_ref()
+            .unwrap()
+            .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+            .unwrap()
+    }
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<ClientSeat> for ClientHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &ClientSeat,
+        event: SeatEvent,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        let event = match event {
+            SeatEvent::Capabilities { capabilities: wayc::WEnum::Value(capabilities) } => {
+                let mut names = Vec::new();
+                if capabilities.contains(wayc::protocol::wl_seat::Capability::Pointer) {
+                    names.push("pointer");
+                }
+                if capabilities.contains(wayc::protocol::wl_seat::Capability::Keyboard) {
+                    names.push("keyboard");
+                }
+                format!("capabilities {}", names.join("|"))
+            }
+            SeatEvent::Name { name } => format!("name {}", name),
+            _ => unreachable!(),
+        };
+        self.events.push(event);
+    }
+}
+
+impl wayc::Dispatch<ClientKeyboard> for ClientHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &ClientKeyboard,
+        event: KeyboardEvent,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        let event = match event {
+            KeyboardEvent::Keymap { fd, size, .. } => {
+                self.keymap = Some((unsafe { File::from_raw_fd(fd) }, size));
+                "keymap".into()
+            }
+            KeyboardEvent::RepeatInfo { rate, delay } => format!("repeat_info {} {}", rate, delay),
+            KeyboardEvent::Enter { serial, keys, .. } => {
+                let keys = keys
+                    .chunks_exact(4)
+                    .map(|key| u32::from_ne_bytes([key[0], key[1], key[2], key[3]]))
+                    .collect::<Vec<_>>();
+                format!("keyboard enter {} {:?}", serial, keys)
+            }
+            KeyboardEvent::Key { serial, key, .. } => format!("keyboard key {} {}", serial, key),
+            KeyboardEvent::Leave { serial, .. } => format!("keyboard leave {}", serial),
+            _ => unreachable!(),
+        };
+        self.events.push(event);
+    }
+}
+
+impl wayc::Dispatch<ClientPointer> for ClientHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &ClientPointer,
+        event: PointerEvent,
+        _: &(),
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        let event = match event {
+            PointerEvent::Enter { serial, surface_x, surface_y, .. } => {
+                format!("pointer enter {} {}x{}", serial, surface_x, surface_y)
+            }
+            PointerEvent::Motion { surface_x, surface_y, .. } => {
+                format!("pointer motion {}x{}", surface_x, surface_y)
+            }
+            PointerEvent::Frame => "pointer frame".into(),
+            _ => unreachable!(),
+        };
+        self.events.push(event);
+    }
+}
+
+client_ignore_impl!(ClientHandler => [
+    wayc::protocol::wl_compositor::WlCompositor,
+    wayc::protocol::wl_surface::WlSurface
+]);
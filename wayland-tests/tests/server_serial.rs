@@ -0,0 +1,48 @@
+mod helpers;
+
+use helpers::{ways, TestServer};
+
+use ways::serial::{SerialError, SerialLabel};
+
+#[test]
+fn serial_validation() {
+    let mut server = TestServer::<()>::new();
+    let (client_a, _) = server.add_client::<()>();
+    let (client_b, _) = server.add_client::<()>();
+
+    // the clones of the display share the same counter
+    let counter = server.display.serial_counter();
+    let first = counter.next_serial(SerialLabel::PointerButton, Some(client_a.id()));
+    assert_eq!(server.display.clone().serial_counter().last_serial(), Some(first));
+
+    let configure = counter.next_serial(SerialLabel::Configure, Some(client_a.id()));
+    let key = counter.next_serial(SerialLabel::Key, None);
+    assert!(first < configure && configure < key);
+
+    let grab = [SerialLabel::PointerButton, SerialLabel::TouchDown];
+    let record = counter.validate(first, Some(&client_a.id()), &grab).unwrap();
+    assert_eq!(record.label, SerialLabel::PointerButton);
+    assert!(matches!(
+        counter.validate(first, Some(&client_b.id()), &grab),
+        Err(SerialError::WrongClient(s)) if s == first
+    ));
+    assert!(matches!(
+        counter.validate(configure, Some(&client_a.id()), &grab),
+        Err(SerialError::WrongLabel { label: SerialLabel::Configure, .. })
+    ));
+    // the serials not specific to a client can be used by any client
+    assert!(counter.validate(key, Some(&client_b.id()), &[SerialLabel::Key]).is_ok());
+    assert!(matches!(counter.validate(key + 1, None, &grab), Err(SerialError::Unknown(_))));
+
+    assert!(counter.is_latest(configure, Some(&client_a.id()), SerialLabel::Configure));
+    let second_configure = counter.next_serial(SerialLabel::Configure, Some(client_a.id()));
+    assert!(!counter.is_latest(configure, Some(&client_a.id()), SerialLabel::Configure));
+    assert!(counter.is_latest(second_configure, Some(&client_a.id()), SerialLabel::Configure));
+
+    // the serials older than the history are forgotten
+    counter.set_history_depth(2);
+    assert!(matches!(counter.validate(first, None, &grab), Err(SerialError::Unknown(_))));
+    assert!(counter.get(key).is_some());
+    counter.next_serial(SerialLabel::Other("wl_pointer.leave"), None);
+    assert!(counter.get(key).is_none());
+}
@@ -0,0 +1,254 @@
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+
+#[macro_use]
+mod helpers;
+
+use helpers::{
+    roundtrip, setup_client, wayc, ways, TestClient, TestCompositor, TestServer, TestSetup,
+};
+
+use ways::protocol::{
+    wl_buffer::WlBuffer as ServerBuffer, wl_compositor::WlCompositor as ServerCompositor,
+    wl_region::WlRegion as ServerRegion, wl_shm::WlShm as ServerShm,
+    wl_shm_pool::WlShmPool as ServerPool, wl_surface::WlSurface as ServerSurface,
+};
+use ways::shm::{with_buffer_contents, BufferAccessError, ShmState};
+
+use wayc::protocol::wl_shm::{Event as ShmEvent, Format, WlShm as ClientShm};
+use wayc::{shm::ShmPool, WEnum};
+
+#[test]
+fn shm_formats_and_contents() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, shm) = setup();
+
+    assert_eq!(client_ddata.formats, [Format::Argb8888, Format::Xrgb8888, Format::Rgb565]);
+
+    let mut pool = ShmPool::new(&mut client.conn.handle(), &shm, 4096).unwrap();
+    let mut buffer =
+        pool.create_buffer(&mut client.conn.handle(), 8, 4, 32, Format::Xrgb8888).unwrap();
+    buffer.canvas().unwrap().fill(0x42);
+    let surface = client_ddata.surface(&client);
+    buffer.attach_to(&mut client.conn.handle(), &surface).unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_buffer = server_ddata.compositor.buffer.take().unwrap();
+    let ret = with_buffer_contents(&server_buffer, |contents, metadata| {
+        assert_eq!(metadata.width, 8);
+        assert_eq!(metadata.height, 4);
+        assert_eq!(metadata.stride, 32);
+        assert_eq!(metadata.format, ways::protocol::wl_shm::Format::Xrgb8888);
+        assert_eq!(contents.len(), 128);
+        contents.iter().all(|&b| b == 0x42)
+    });
+    assert!(ret.unwrap());
+}
+
+#[test]
+fn shm_invalid_stride() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, shm) = setup();
+
+    let file = tempfile::tempfile().unwrap();
+    file.set_len(4096).unwrap();
+    let pool = shm
+        .create_pool(
+            &mut client.conn.handle(),
+            file.as_raw_fd(),
+            4096,
+            &client.event_queue.handle(),
+            (),
+        )
+        .unwrap();
+    // the buffer does not fit in the pool
+    pool.create_buffer(
+        &mut client.conn.handle(),
+        0,
+        32,
+        64,
+        128,
+        Format::Argb8888,
+        &client.event_queue.handle(),
+        (),
+    )
+    .unwrap();
+
+    assert!(roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).is_err());
+    let error = client.conn.protocol_error().unwrap();
+    assert_eq!(error.code, ways::protocol::wl_shm::Error::InvalidStride as u32);
+    assert_eq!(error.object_interface, "wl_shm_pool");
+}
+
+#[test]
+fn shm_invalid_format() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, shm) = setup();
+
+    let mut pool = ShmPool::new(&mut client.conn.handle(), &shm, 4096).unwrap();
+    // not advertised by the server
+    pool.create_buffer(&mut client.conn.handle(), 8, 4, 32, Format::Bgr888).unwrap();
+
+    assert!(roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).is_err());
+    let error = client.conn.protocol_error().unwrap();
+    assert_eq!(error.code, ways::protocol::wl_shm::Error::InvalidFormat as u32);
+}
+
+#[test]
+fn shm_truncated_pool() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, shm) = setup();
+
+    // a file without seals, which the client can truncate
+    let mut file = tempfile::tempfile().unwrap();
+    file.write_all(&[0x42; 8192]).unwrap();
+    let pool = shm
+        .create_pool(
+            &mut client.conn.handle(),
+            file.as_raw_fd(),
+            8192,
+            &client.event_queue.handle(),
+            (),
+        )
+        .unwrap();
+    let buffer = pool
+        .create_buffer(
+            &mut client.conn.handle(),
+            4096,
+            32,
+            32,
+            128,
+            Format::Argb8888,
+            &client.event_queue.handle(),
+            (),
+        )
+        .unwrap();
+    let surface = client_ddata.surface(&client);
+    surface.attach(&mut client.conn.handle(), Some(&buffer), 0, 0);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let server_buffer = server_ddata.compositor.buffer.take().unwrap();
+    let ret =
+        with_buffer_contents(&server_buffer, |contents, _| contents.iter().all(|&b| b == 0x42));
+    assert!(ret.unwrap());
+
+    // the pages of the buffer are no longer backed by the file
+    file.set_len(4096).unwrap();
+    let ret = with_buffer_contents(&server_buffer, |contents, _| contents.iter().all(|&b| b == 0));
+    assert!(matches!(ret, Err(BufferAccessError::BadMemory)));
+
+    // the buffer can still be accessed, but its contents are lost
+    let ret = with_buffer_contents(&server_buffer, |contents, _| contents.iter().all(|&b| b == 0));
+    assert!(ret.unwrap());
+}
+
+#[allow(clippy::type_complexity)]
+fn setup() -> (TestSetup<ServerHandler, ClientHandler>, ClientShm) {
+    let server = TestServer::new();
+    server.display.create_global::<ServerCompositor>(1, ());
+    server.display.create_global::<ServerShm>(1, ());
+    let server_ddata = ServerHandler {
+        shm: ShmState::new([ways::protocol::wl_shm::Format::Rgb565]),
+        compositor: TestCompositor::default(),
+    };
+    let client_ddata =
+        ClientHandler { globals: Default::default(), formats: Vec::new(), compositor: None };
+
+    setup_client(
+        server,
+        server_ddata,
+        client_ddata,
+        |client, client_ddata, registry, compositor| {
+            client_ddata.compositor = Some(compositor.clone());
+            client_ddata
+                .globals
+                .bind::<ClientShm, _>(
+                    &mut client.conn.handle(),
+                    &client.event_queue.handle(),
+                    registry,
+                    1..2,
+                    (),
+                )
+                .unwrap()
+        },
+    )
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    shm: ShmState,
+    compositor: TestCompositor,
+}
+
+impl AsMut<ShmState> for ServerHandler {
+    fn as_mut(&mut self) -> &mut ShmState {
+        &mut self.shm
+    }
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_dispatch!(ServerHandler: [ServerShm, ServerPool, ServerBuffer] => ShmState);
+ways::delegate_global_dispatch!(ServerHandler: [ServerShm] => ShmState);
+
+ways::delegate_global_dispatch!(ServerHandler: [ServerCompositor] => TestCompositor);
+ways::delegate_dispatch!(ServerHandler: [ServerCompositor, ServerSurface, ServerRegion] => TestCompositor);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    formats: Vec<Format>,
+    compositor: Option<wayc::protocol::wl_compositor::WlCompositor>,
+}
+
+impl ClientHandler {
+    fn surface(&self, client: &TestClient<ClientHandler>) -> wayc::protocol::wl_surface::WlSurface {
+        self.compositor
+            .as_ref()
+            .unwrap()
+            .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+            .unwrap()
+    }
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+impl wayc::Dispatch<ClientShm> for ClientHandler {
+    type UserData = ();
+
+    fn event(
+        &mut self,
+        _: &ClientShm,
+        event: ShmEvent,
+        _: &Self::UserData,
+        _: &mut wayc::ConnectionHandle,
+        _: &wayc::QueueHandle<Self>,
+    ) {
+        if let ShmEvent::Format { format: WEnum::Value(format) } = event {
+            self.formats.push(format);
+        }
+    }
+}
+
+client_ignore_impl!(ClientHandler => [
+    wayc::protocol::wl_compositor::WlCompositor,
+    wayc::protocol::wl_surface::WlSurface,
+    wayc::protocol::wl_shm_pool::WlShmPool,
+    wayc::protocol::wl_buffer::WlBuffer
+]);
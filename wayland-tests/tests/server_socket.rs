@@ -0,0 +1,57 @@
+#[macro_use]
+mod helpers;
+
+use helpers::ways;
+
+use ways::socket::{BindError, ListeningSocket};
+
+use std::ffi::OsStr;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+
+#[test]
+fn bind_in_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = ListeningSocket::builder().directory(dir.path()).bind("wayland-custom").unwrap();
+
+    assert_eq!(socket.socket_name(), Some(OsStr::new("wayland-custom")));
+    assert_eq!(socket.socket_path(), dir.path().join("wayland-custom"));
+
+    let _client = UnixStream::connect(socket.socket_path()).unwrap();
+    assert!(socket.accept().unwrap().is_some());
+
+    // the socket and its lockfile are removed with the listening socket
+    drop(socket);
+    assert!(!dir.path().join("wayland-custom").exists());
+    assert!(!dir.path().join("wayland-custom.lock").exists());
+}
+
+#[test]
+fn bind_mode_and_group() {
+    let dir = tempfile::tempdir().unwrap();
+    let gid = nix::unistd::getegid().as_raw();
+    let socket = ListeningSocket::builder()
+        .directory(dir.path())
+        .mode(0o660)
+        .group(gid)
+        .bind("wayland-shared")
+        .unwrap();
+
+    let metadata = std::fs::metadata(socket.socket_path()).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o660);
+    assert_eq!(metadata.gid(), gid);
+}
+
+#[test]
+fn bind_auto_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let builder = ListeningSocket::builder().directory(dir.path());
+
+    let first = builder.bind_auto("wayland", 1..4).unwrap();
+    let second = builder.bind_auto("wayland", 1..4).unwrap();
+    assert_eq!(first.socket_name(), Some(OsStr::new("wayland-1")));
+    assert_eq!(second.socket_name(), Some(OsStr::new("wayland-2")));
+
+    let _third = builder.bind_auto("wayland", 1..4).unwrap();
+    assert!(matches!(builder.bind_auto("wayland", 1..4), Err(BindError::AlreadyInUse)));
+}
@@ -0,0 +1,123 @@
+mod helpers;
+
+use std::sync::{Arc, Mutex};
+
+use helpers::ways;
+
+use ways::transaction::{CommitState, TransactionTracker};
+
+#[derive(Debug, PartialEq)]
+struct State {
+    buffer: Option<u32>,
+    damage: Vec<u32>,
+}
+
+impl State {
+    fn new(buffer: Option<u32>, damage: &[u32]) -> State {
+        State { buffer, damage: damage.to_vec() }
+    }
+}
+
+impl CommitState for State {
+    fn merge(&mut self, newer: State) {
+        if newer.buffer.is_some() {
+            self.buffer = newer.buffer;
+        }
+        self.damage.extend(newer.damage);
+    }
+}
+
+type Applied = Arc<Mutex<Vec<Vec<(u32, State)>>>>;
+
+fn tracker() -> (TransactionTracker<u32, State>, Applied) {
+    let applied = Applied::default();
+    let applied2 = applied.clone();
+    let mut tracker = TransactionTracker::new();
+    tracker.set_ready_hook(Box::new(move |transaction| {
+        applied2.lock().unwrap().push(transaction.into_states())
+    }));
+    (tracker, applied)
+}
+
+#[test]
+fn transaction_subsurface_tree() {
+    let (mut tracker, applied) = tracker();
+
+    // 1 <- 2 (sync) <- 3 (desync, but below a sync subsurface)
+    //   <- 4 (desync)
+    tracker.set_parent(2, Some(1));
+    tracker.set_sync(2, true);
+    tracker.set_parent(3, Some(2));
+    tracker.set_parent(4, Some(1));
+    assert!(tracker.is_synchronized(&2));
+    assert!(tracker.is_synchronized(&3));
+    assert!(!tracker.is_synchronized(&4));
+    assert!(!tracker.is_synchronized(&1));
+
+    // the cached commits accumulate
+    assert!(tracker.commit(2, State::new(Some(20), &[1])).is_none());
+    assert!(tracker.commit(2, State::new(None, &[2])).is_none());
+    assert!(tracker.commit(3, State::new(Some(30), &[])).is_none());
+    // the desynchronized subsurface is applied on its own
+    assert!(tracker.commit(4, State::new(Some(40), &[])).is_some());
+    assert_eq!(tracker.apply_ready(), 1);
+    assert_eq!(*applied.lock().unwrap(), [vec![(4, State::new(Some(40), &[]))]]);
+    applied.lock().unwrap().clear();
+
+    // the parent applies the whole synchronized tree
+    tracker.commit(1, State::new(Some(10), &[]));
+    assert_eq!(tracker.apply_ready(), 1);
+    assert_eq!(
+        *applied.lock().unwrap(),
+        [vec![
+            (1, State::new(Some(10), &[])),
+            (2, State::new(Some(20), &[1, 2])),
+            (3, State::new(Some(30), &[])),
+        ]]
+    );
+    applied.lock().unwrap().clear();
+
+    // switching to the desynchronized mode keeps the cached state until the next commit
+    assert!(tracker.commit(2, State::new(Some(21), &[])).is_none());
+    tracker.set_sync(2, false);
+    tracker.commit(2, State::new(None, &[3]));
+    tracker.apply_ready();
+    assert_eq!(*applied.lock().unwrap(), [vec![(2, State::new(Some(21), &[3]))]]);
+    applied.lock().unwrap().clear();
+
+    // a removed parent makes its children roots, dropping its cached state
+    tracker.set_sync(2, true);
+    assert!(tracker.commit(3, State::new(Some(31), &[])).is_none());
+    tracker.remove(&2);
+    assert!(!tracker.is_synchronized(&3));
+    tracker.commit(1, State::new(None, &[]));
+    tracker.apply_ready();
+    assert_eq!(*applied.lock().unwrap(), [vec![(1, State::new(None, &[]))]]);
+}
+
+#[test]
+fn transaction_blockers() {
+    let (mut tracker, applied) = tracker();
+
+    let first = tracker.commit(1, State::new(Some(10), &[])).unwrap();
+    assert!(tracker.add_blocker(first));
+    assert!(tracker.add_blocker(first));
+    // the next commit of the same surface waits for the blocked one
+    tracker.commit(1, State::new(Some(11), &[]));
+    // another surface is not held back
+    tracker.commit(2, State::new(Some(20), &[]));
+    assert_eq!(tracker.apply_ready(), 1);
+    assert_eq!(*applied.lock().unwrap(), [vec![(2, State::new(Some(20), &[]))]]);
+    applied.lock().unwrap().clear();
+
+    tracker.release_blocker(first);
+    assert_eq!(tracker.apply_ready(), 0);
+    tracker.release_blocker(first);
+    assert_eq!(tracker.apply_ready(), 2);
+    assert_eq!(
+        *applied.lock().unwrap(),
+        [vec![(1, State::new(Some(10), &[]))], vec![(1, State::new(Some(11), &[]))]]
+    );
+    // the transaction was applied
+    assert!(!tracker.add_blocker(first));
+}
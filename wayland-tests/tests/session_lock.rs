@@ -0,0 +1,309 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, setup_client, wayc, ways, TestCompositor, TestServer, TestSetup};
+
+use wayland_protocols::staging::session_lock::v1::server::{
+    ext_session_lock_manager_v1 as smanager, ext_session_lock_surface_v1 as slocksurface,
+    ext_session_lock_v1 as slock,
+};
+use ways::protocol::wl_output as soutput;
+
+use wayc::protocol::{wl_compositor as ccompositor, wl_output as coutput, wl_surface as csurface};
+use wayland_protocols::staging::session_lock::v1::client::{
+    ext_session_lock_manager_v1 as cmanager, ext_session_lock_surface_v1 as clocksurface,
+    ext_session_lock_v1 as clock,
+};
+use wayland_protocols_helpers::staging::session_lock::v1::lock::{
+    LockSurfaceConfigure, SessionLock, SessionLockEvent, SessionLockState,
+};
+
+// Connect a client binding the session lock manager, and lock the session with a lock surface
+fn setup() -> TestSetup<ServerHandler, ClientHandler> {
+    let server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<soutput::WlOutput>(2, ());
+    server.display.create_global::<smanager::ExtSessionLockManagerV1>(1, ());
+    let server_ddata = ServerHandler {
+        compositor: TestCompositor::default(),
+        locks: Vec::new(),
+        lock_surfaces: Vec::new(),
+        requests: Vec::new(),
+    };
+    let client_ddata =
+        ClientHandler { globals: wayc::globals::GlobalList::new(), session_lock: None };
+
+    let (setup, ()) = setup_client(
+        server,
+        server_ddata,
+        client_ddata,
+        |client, client_ddata, registry, compositor| {
+            let mut handle = client.conn.handle();
+            let qh = client.event_queue.handle();
+            let globals = &client_ddata.globals;
+            let output =
+                globals.bind::<coutput::WlOutput, _>(&mut handle, &qh, registry, 1..3, ()).unwrap();
+            let manager = globals
+                .bind::<cmanager::ExtSessionLockManagerV1, _>(&mut handle, &qh, registry, 1..2, ())
+                .unwrap();
+            let surface = compositor.create_surface(&mut handle, &qh, ()).unwrap();
+            let mut session_lock = SessionLockState::new(manager);
+            let lock = session_lock.lock(&mut handle, &qh).unwrap();
+            session_lock.create_lock_surface(&mut handle, &qh, &lock, surface, &output).unwrap();
+            client_ddata.session_lock = Some(session_lock);
+        },
+    );
+    assert_eq!(setup.server_ddata.locks.len(), 1);
+    assert_eq!(setup.server_ddata.lock_surfaces.len(), 1);
+
+    setup
+}
+
+fn lock_of(client_ddata: &ClientHandler) -> SessionLock {
+    client_ddata.session_lock.as_ref().unwrap().locks()[0].clone()
+}
+
+#[test]
+fn lock_configure_unlock() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup();
+    let lock = lock_of(&client_ddata);
+    assert!(!lock.is_locked());
+
+    // the configuration is acknowledged before being reported
+    server_ddata.lock_surfaces[0].configure(&mut server.display.handle(), 7, 800, 600);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["ack_configure 7"]);
+    let session_lock = client_ddata.session_lock.as_mut().unwrap();
+    let surface = session_lock.lock_surfaces()[0].clone();
+    let expected = LockSurfaceConfigure { size: (800, 600), serial: 7 };
+    assert_eq!(surface.configure(), Some(expected));
+    assert_eq!(surface.lock(), &lock);
+    let events = session_lock.take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        SessionLockEvent::Configure { surface: ref configured, configure }
+            if configured == &surface && configure == expected
+    ));
+
+    server_ddata.locks[0].locked(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert!(lock.is_locked());
+    let session_lock = client_ddata.session_lock.as_mut().unwrap();
+    let events = session_lock.take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], SessionLockEvent::Locked { lock: ref locked } if locked == &lock));
+
+    // the lock surfaces are destroyed before the session is unlocked
+    session_lock.unlock(&mut client.conn.handle(), &lock);
+    assert!(session_lock.locks().is_empty());
+    assert!(session_lock.lock_surfaces().is_empty());
+    // the lock is only released once
+    session_lock.unlock(&mut client.conn.handle(), &lock);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(
+        server_ddata.requests,
+        ["ack_configure 7", "lock_surface.destroy", "unlock_and_destroy"]
+    );
+}
+
+#[test]
+fn lock_finished() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup();
+    let lock = lock_of(&client_ddata);
+
+    // the compositor denies the lock, it is released without unlocking
+    server_ddata.locks[0].finished(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["lock_surface.destroy", "destroy"]);
+    assert!(lock.is_finished());
+    assert!(!lock.is_locked());
+
+    let session_lock = client_ddata.session_lock.as_mut().unwrap();
+    let events = session_lock.take_events();
+    assert_eq!(events.len(), 1);
+    assert!(
+        matches!(events[0], SessionLockEvent::Finished { lock: ref finished } if finished == &lock)
+    );
+    assert!(session_lock.locks().is_empty());
+    assert!(session_lock.lock_surfaces().is_empty());
+
+    // nothing is left to unlock
+    session_lock.unlock(&mut client.conn.handle(), &lock);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["lock_surface.destroy", "destroy"]);
+}
+
+#[test]
+fn lock_finished_after_locked() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup();
+    let lock = lock_of(&client_ddata);
+
+    // a lock ended after the session was locked must still be unlocked
+    {
+        let mut handle = server.display.handle();
+        server_ddata.locks[0].locked(&mut handle);
+        server_ddata.locks[0].finished(&mut handle);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["lock_surface.destroy", "unlock_and_destroy"]);
+    assert!(lock.is_finished());
+    assert!(!lock.is_locked());
+    let events = client_ddata.session_lock.as_mut().unwrap().take_events();
+    assert!(matches!(
+        events[..],
+        [SessionLockEvent::Locked { .. }, SessionLockEvent::Finished { .. }]
+    ));
+}
+
+#[test]
+fn unlock_before_locked() {
+    let TestSetup { mut server, mut client, mut server_ddata, mut client_ddata } = setup();
+    let lock = lock_of(&client_ddata);
+
+    // a lock surface can be destroyed on its own, its lock is left untouched
+    let session_lock = client_ddata.session_lock.as_mut().unwrap();
+    let surface = session_lock.lock_surfaces()[0].clone();
+    session_lock.destroy_lock_surface(&mut client.conn.handle(), &surface);
+    assert!(session_lock.lock_surfaces().is_empty());
+    assert_eq!(session_lock.locks().len(), 1);
+    assert_eq!(session_lock.locks()[0], lock);
+
+    // the session was not locked yet, unlocking it would be a protocol error
+    session_lock.unlock(&mut client.conn.handle(), &lock);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["lock_surface.destroy", "destroy"]);
+    assert!(client_ddata.session_lock.as_mut().unwrap().take_events().is_empty());
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    compositor: TestCompositor,
+    locks: Vec<slock::ExtSessionLockV1>,
+    lock_surfaces: Vec<slocksurface::ExtSessionLockSurfaceV1>,
+    requests: Vec<String>,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler:
+    [ways::protocol::wl_compositor::WlCompositor] => TestCompositor
+);
+ways::delegate_dispatch!(ServerHandler: [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_surface::WlSurface,
+    ways::protocol::wl_region::WlRegion
+] => TestCompositor);
+
+impl ways::Dispatch<smanager::ExtSessionLockManagerV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &smanager::ExtSessionLockManagerV1,
+        request: smanager::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let smanager::Request::Lock { id } = request {
+            self.locks.push(data_init.init(id, ()));
+        }
+    }
+}
+
+impl ways::Dispatch<slock::ExtSessionLockV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &slock::ExtSessionLockV1,
+        request: slock::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            slock::Request::GetLockSurface { id, .. } => {
+                self.lock_surfaces.push(data_init.init(id, ()));
+            }
+            slock::Request::Destroy => self.requests.push("destroy".into()),
+            slock::Request::UnlockAndDestroy => self.requests.push("unlock_and_destroy".into()),
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<slocksurface::ExtSessionLockSurfaceV1> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &slocksurface::ExtSessionLockSurfaceV1,
+        request: slocksurface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            slocksurface::Request::AckConfigure { serial } => {
+                self.requests.push(format!("ack_configure {}", serial))
+            }
+            slocksurface::Request::Destroy => self.requests.push("lock_surface.destroy".into()),
+            _ => {}
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [soutput::WlOutput]);
+server_ignore_global_impl!(ServerHandler => [
+    soutput::WlOutput,
+    smanager::ExtSessionLockManagerV1
+]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    session_lock: Option<SessionLockState>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<SessionLockState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut SessionLockState {
+        self.session_lock.as_mut().unwrap()
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler: [
+    cmanager::ExtSessionLockManagerV1,
+    clock::ExtSessionLockV1,
+    clocksurface::ExtSessionLockSurfaceV1
+] => SessionLockState);
+
+client_ignore_impl!(ClientHandler => [
+    ccompositor::WlCompositor,
+    csurface::WlSurface,
+    coutput::WlOutput
+]);
@@ -0,0 +1,326 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::io::{FromRawFd, RawFd};
+
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestServer};
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use wayc::{
+    backlog::BufferBacklog,
+    protocol::{wl_shm::Format, wl_shm_pool},
+    shm::ShmPool,
+    WEnum,
+};
+
+#[test]
+fn shm_pool_buffers() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_shm::WlShm>(1, ());
+    let mut server_ddata = ServerHandler {
+        fd: None,
+        resizes: Vec::new(),
+        offsets: Vec::new(),
+        buffers: Vec::new(),
+        destroyed_buffers: 0,
+    };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let shm = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_shm::WlShm, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let compositor = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let surface = compositor
+        .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    // the pool grows to fit the first buffer
+    let mut pool = ShmPool::new(&mut client.conn.handle(), &shm, 16).unwrap();
+    let mut first =
+        pool.create_buffer(&mut client.conn.handle(), 4, 4, 16, Format::Argb8888).unwrap();
+    assert_eq!(pool.size(), 80);
+
+    first.canvas().unwrap().fill(0x42);
+    first.attach_to(&mut client.conn.handle(), &surface).unwrap();
+    assert!(first.is_busy());
+    assert!(first.canvas().is_none());
+
+    let _second =
+        pool.create_buffer(&mut client.conn.handle(), 4, 4, 16, Format::Argb8888).unwrap();
+    assert_eq!(pool.size(), 160);
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    assert_eq!(server_ddata.resizes, [80, 160]);
+    assert_eq!(server_ddata.offsets, [0, 64]);
+    let mut file = unsafe { File::from_raw_fd(server_ddata.fd.take().unwrap()) };
+    let mut contents = vec![0; 64];
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_exact(&mut contents).unwrap();
+    assert!(contents.iter().all(|&b| b == 0x42));
+
+    // dropping a busy buffer keeps its memory reserved until it is released
+    std::mem::drop(first);
+    let _third = pool.create_buffer(&mut client.conn.handle(), 2, 2, 8, Format::Argb8888).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.offsets, [0, 64, 128]);
+    assert_eq!(server_ddata.destroyed_buffers, 0);
+
+    server_ddata.buffers[0].release(&mut server.display.handle());
+    // the buffer is destroyed when the client receives the release event
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.destroyed_buffers, 1);
+
+    // the released memory is reused
+    let mut fourth =
+        pool.create_buffer(&mut client.conn.handle(), 4, 4, 16, Format::Argb8888).unwrap();
+    assert!(!fourth.is_busy());
+    assert_eq!(fourth.canvas().unwrap().len(), 64);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.offsets, [0, 64, 128, 0]);
+    assert_eq!(pool.size(), 160);
+}
+
+#[test]
+fn buffer_backlog() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<ways::protocol::wl_shm::WlShm>(1, ());
+    let mut server_ddata = ServerHandler {
+        fd: None,
+        resizes: Vec::new(),
+        offsets: Vec::new(),
+        buffers: Vec::new(),
+        destroyed_buffers: 0,
+    };
+
+    let (_, mut client) = server.add_client();
+    let mut client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new() };
+
+    let registry = client
+        .display
+        .get_registry(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+
+    let shm = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_shm::WlShm, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let compositor = client_ddata
+        .globals
+        .bind::<wayc::protocol::wl_compositor::WlCompositor, _>(
+            &mut client.conn.handle(),
+            &client.event_queue.handle(),
+            &registry,
+            1..2,
+            (),
+        )
+        .unwrap();
+    let surface = compositor
+        .create_surface(&mut client.conn.handle(), &client.event_queue.handle(), ())
+        .unwrap();
+
+    let pool = ShmPool::new(&mut client.conn.handle(), &shm, 16).unwrap();
+    let mut backlog = BufferBacklog::new();
+    for offset in [0, 8] {
+        let request = wl_shm_pool::Request::CreateBuffer {
+            offset,
+            width: 2,
+            height: 1,
+            stride: 8,
+            format: WEnum::Value(Format::Argb8888),
+        };
+        backlog
+            .create_buffer(&mut client.conn.handle(), pool.wl_shm_pool(), request, offset)
+            .unwrap();
+    }
+    let releases = Arc::new(AtomicUsize::new(0));
+    let releases2 = releases.clone();
+    backlog.on_release(move || {
+        releases2.fetch_add(1, Ordering::SeqCst);
+    });
+
+    // double buffering: both buffers get attached in turn
+    for expected in [0, 8] {
+        let slot = backlog.next_free().unwrap();
+        assert_eq!(*slot.data(), expected);
+        slot.attach_to(&mut client.conn.handle(), &surface).unwrap();
+        assert!(slot.is_busy());
+    }
+    assert!(backlog.next_free().is_none());
+
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.offsets, [0, 8]);
+
+    // the release of the second buffer is handled without user-side dispatching
+    server_ddata.buffers[1].release(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(releases.load(Ordering::SeqCst), 1);
+    assert_eq!(*backlog.next_free().unwrap().data(), 8);
+    assert!(backlog.slots()[0].is_busy());
+
+    assert_eq!(backlog.clear(&mut client.conn.handle()), [0, 8]);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.destroyed_buffers, 2);
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    fd: Option<RawFd>,
+    resizes: Vec<i32>,
+    offsets: Vec<i32>,
+    buffers: Vec<ways::protocol::wl_buffer::WlBuffer>,
+    destroyed_buffers: usize,
+}
+
+impl ways::Dispatch<ways::protocol::wl_compositor::WlCompositor> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ways::protocol::wl_compositor::WlCompositor,
+        request: ways::protocol::wl_compositor::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let ways::protocol::wl_compositor::Request::CreateSurface { id } = request {
+            init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<ways::protocol::wl_shm::WlShm> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ways::protocol::wl_shm::WlShm,
+        request: ways::protocol::wl_shm::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        init: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let ways::protocol::wl_shm::Request::CreatePool { fd, size, id } = request {
+            assert_eq!(size, 16);
+            self.fd = Some(fd);
+            init.init(id, ());
+        }
+    }
+}
+
+impl ways::Dispatch<ways::protocol::wl_shm_pool::WlShmPool> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ways::protocol::wl_shm_pool::WlShmPool,
+        request: ways::protocol::wl_shm_pool::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            ways::protocol::wl_shm_pool::Request::CreateBuffer { id, offset, .. } => {
+                self.offsets.push(offset);
+                self.buffers.push(init.init(id, ()));
+            }
+            ways::protocol::wl_shm_pool::Request::Resize { size } => self.resizes.push(size),
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<ways::protocol::wl_buffer::WlBuffer> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ways::protocol::wl_buffer::WlBuffer,
+        request: ways::protocol::wl_buffer::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let ways::protocol::wl_buffer::Request::Destroy = request {
+            self.destroyed_buffers += 1;
+        }
+    }
+}
+
+server_ignore_impl!(ServerHandler => [
+    ways::protocol::wl_surface::WlSurface
+]);
+
+server_ignore_global_impl!(ServerHandler => [
+    ways::protocol::wl_shm::WlShm,
+    ways::protocol::wl_compositor::WlCompositor
+]);
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+client_ignore_impl!(ClientHandler => [
+    wayc::protocol::wl_compositor::WlCompositor,
+    wayc::protocol::wl_surface::WlSurface,
+    wayc::protocol::wl_shm::WlShm
+]);
@@ -0,0 +1,302 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{roundtrip, setup_client, wayc, ways, TestCompositor, TestServer, TestSetup};
+
+use wayland_protocols::xdg_shell::server::{
+    xdg_surface as sxdgsurface, xdg_toplevel as stoplevel, xdg_wm_base as swmbase,
+};
+use ways::protocol::wl_surface as ssurface;
+
+use wayc::protocol::{wl_compositor as ccompositor, wl_surface as csurface};
+use wayland_protocols::xdg_shell::client::{
+    xdg_surface as cxdgsurface, xdg_toplevel as ctoplevel, xdg_wm_base as cwmbase,
+};
+use wayland_protocols_helpers::xdg_shell::window::{
+    Window, WindowConfigure, WindowEvent, XdgShellState,
+};
+
+// Connect a client binding the xdg_wm_base global with the given version, and create a window
+fn setup(wm_base_version: u32) -> (TestSetup<ServerHandler, ClientHandler>, Window) {
+    let server = TestServer::new();
+    server.display.create_global::<ways::protocol::wl_compositor::WlCompositor>(1, ());
+    server.display.create_global::<swmbase::XdgWmBase>(wm_base_version, ());
+    let server_ddata = ServerHandler {
+        compositor: TestCompositor::default(),
+        wm_base: None,
+        xdg_surfaces: Vec::new(),
+        toplevels: Vec::new(),
+        requests: Vec::new(),
+    };
+    let client_ddata = ClientHandler { globals: wayc::globals::GlobalList::new(), xdg_shell: None };
+
+    let (mut setup, window) = setup_client(
+        server,
+        server_ddata,
+        client_ddata,
+        |client, client_ddata, registry, compositor| {
+            let mut handle = client.conn.handle();
+            let qh = client.event_queue.handle();
+            let wm_base = client_ddata
+                .globals
+                .bind::<cwmbase::XdgWmBase, _>(&mut handle, &qh, registry, 1..5, ())
+                .unwrap();
+            let surface = compositor.create_surface(&mut handle, &qh, ()).unwrap();
+            let mut xdg_shell = XdgShellState::new(wm_base);
+            let window = xdg_shell.create_window(&mut handle, &qh, surface).unwrap();
+            client_ddata.xdg_shell = Some(xdg_shell);
+            window
+        },
+    );
+    // the surface is committed to request the initial configuration
+    assert_eq!(setup.server_ddata.requests, ["commit"]);
+    setup.server_ddata.requests.clear();
+
+    (setup, window)
+}
+
+// The raw values of toplevel states, as sent in the configure event
+fn states(states: &[u32]) -> Vec<u8> {
+    states.iter().flat_map(|state| state.to_ne_bytes()).collect()
+}
+
+#[test]
+fn configure_ack_round_trip() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, window) =
+        setup(4);
+    assert_eq!(window.configure(), None);
+
+    // a configure sequence is only complete with the configure event of the xdg_surface
+    {
+        let mut handle = server.display.handle();
+        let toplevel = &server_ddata.toplevels[0];
+        toplevel.configure_bounds(&mut handle, 1920, 1080);
+        // unknown states are skipped
+        toplevel.configure(&mut handle, 800, 600, states(&[1, 4, 1000]));
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(window.configure(), None);
+    assert!(client_ddata.xdg_shell.as_mut().unwrap().take_events().is_empty());
+
+    server_ddata.xdg_surfaces[0].configure(&mut server.display.handle(), 42);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let expected = WindowConfigure {
+        new_size: Some((800, 600)),
+        bounds: Some((1920, 1080)),
+        states: vec![ctoplevel::State::Maximized, ctoplevel::State::Activated],
+        serial: 42,
+    };
+    assert_eq!(window.configure(), Some(expected.clone()));
+    assert!(expected.is_maximized() && expected.is_activated());
+    assert!(!expected.is_fullscreen() && !expected.is_resizing());
+    let events = client_ddata.xdg_shell.as_mut().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0],
+        WindowEvent::Configure { window: ref configured, ref configure }
+            if configured == &window && configure == &expected
+    ));
+
+    // the configuration is acknowledged before the application commits the surface
+    window.wl_surface().commit(&mut client.conn.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["ack_configure 42", "commit"]);
+
+    // the next configurations replace the size and the states of the previous ones
+    {
+        let mut handle = server.display.handle();
+        server_ddata.toplevels[0].configure(&mut handle, 0, 0, states(&[]));
+        server_ddata.xdg_surfaces[0].configure(&mut handle, 43);
+    }
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let configure = window.configure().unwrap();
+    assert_eq!(configure.new_size, None);
+    assert!(configure.states.is_empty());
+    assert_eq!(configure.serial, 43);
+    assert_eq!(server_ddata.requests, ["ack_configure 42", "commit", "ack_configure 43"]);
+}
+
+#[test]
+fn ping_and_close() {
+    let (TestSetup { mut server, mut client, mut server_ddata, mut client_ddata }, window) =
+        setup(3);
+
+    // the pings are answered without the application being involved
+    server_ddata.wm_base.as_ref().unwrap().ping(&mut server.display.handle(), 7);
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["pong 7"]);
+    assert!(client_ddata.xdg_shell.as_mut().unwrap().take_events().is_empty());
+
+    // closing is only a request, the window is destroyed by the application
+    server_ddata.toplevels[0].close(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    let xdg_shell = client_ddata.xdg_shell.as_mut().unwrap();
+    let events = xdg_shell.take_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], WindowEvent::Close { window: ref closed } if closed == &window));
+    assert_eq!(xdg_shell.windows().len(), 1);
+
+    // the role object is destroyed before the xdg_surface
+    xdg_shell.destroy_window(&mut client.conn.handle(), &window);
+    assert!(xdg_shell.windows().is_empty());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert_eq!(server_ddata.requests, ["pong 7", "toplevel.destroy", "xdg_surface.destroy"]);
+
+    // the events of a destroyed window are ignored
+    server_ddata.toplevels[0].close(&mut server.display.handle());
+    roundtrip(&mut client, &mut server, &mut client_ddata, &mut server_ddata).unwrap();
+    assert!(client_ddata.xdg_shell.as_mut().unwrap().take_events().is_empty());
+}
+
+/*
+ * Server Handler
+ */
+
+struct ServerHandler {
+    compositor: TestCompositor,
+    wm_base: Option<swmbase::XdgWmBase>,
+    xdg_surfaces: Vec<sxdgsurface::XdgSurface>,
+    toplevels: Vec<stoplevel::XdgToplevel>,
+    requests: Vec<String>,
+}
+
+impl AsMut<TestCompositor> for ServerHandler {
+    fn as_mut(&mut self) -> &mut TestCompositor {
+        &mut self.compositor
+    }
+}
+
+ways::delegate_global_dispatch!(ServerHandler:
+    [ways::protocol::wl_compositor::WlCompositor] => TestCompositor
+);
+ways::delegate_dispatch!(ServerHandler: [
+    ways::protocol::wl_compositor::WlCompositor,
+    ways::protocol::wl_region::WlRegion
+] => TestCompositor);
+
+impl ways::Dispatch<ssurface::WlSurface> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &ssurface::WlSurface,
+        request: ssurface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let ssurface::Request::Commit = request {
+            self.requests.push("commit".into());
+        }
+    }
+}
+
+impl ways::GlobalDispatch<swmbase::XdgWmBase> for ServerHandler {
+    type GlobalData = ();
+
+    fn bind(
+        &mut self,
+        _: &mut ways::DisplayHandle<'_>,
+        _: &ways::Client,
+        new_id: ways::New<swmbase::XdgWmBase>,
+        _: &(),
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        self.wm_base = Some(data_init.init(new_id, ()));
+    }
+}
+
+impl ways::Dispatch<swmbase::XdgWmBase> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &swmbase::XdgWmBase,
+        request: swmbase::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            swmbase::Request::GetXdgSurface { id, .. } => {
+                self.xdg_surfaces.push(data_init.init(id, ()));
+            }
+            swmbase::Request::Pong { serial } => self.requests.push(format!("pong {}", serial)),
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<sxdgsurface::XdgSurface> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &sxdgsurface::XdgSurface,
+        request: sxdgsurface::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        data_init: &mut ways::DataInit<'_, Self>,
+    ) {
+        match request {
+            sxdgsurface::Request::GetToplevel { id } => {
+                self.toplevels.push(data_init.init(id, ()));
+            }
+            sxdgsurface::Request::AckConfigure { serial } => {
+                self.requests.push(format!("ack_configure {}", serial))
+            }
+            sxdgsurface::Request::Destroy => self.requests.push("xdg_surface.destroy".into()),
+            _ => {}
+        }
+    }
+}
+
+impl ways::Dispatch<stoplevel::XdgToplevel> for ServerHandler {
+    type UserData = ();
+    fn request(
+        &mut self,
+        _: &ways::Client,
+        _: &stoplevel::XdgToplevel,
+        request: stoplevel::Request,
+        _: &(),
+        _: &mut ways::DisplayHandle<'_>,
+        _: &mut ways::DataInit<'_, Self>,
+    ) {
+        if let stoplevel::Request::Destroy = request {
+            self.requests.push("toplevel.destroy".into());
+        }
+    }
+}
+
+/*
+ * Client Handler
+ */
+
+struct ClientHandler {
+    globals: wayc::globals::GlobalList,
+    xdg_shell: Option<XdgShellState>,
+}
+
+impl AsMut<wayc::globals::GlobalList> for ClientHandler {
+    fn as_mut(&mut self) -> &mut wayc::globals::GlobalList {
+        &mut self.globals
+    }
+}
+
+impl AsMut<XdgShellState> for ClientHandler {
+    fn as_mut(&mut self) -> &mut XdgShellState {
+        self.xdg_shell.as_mut().unwrap()
+    }
+}
+
+wayc::delegate_dispatch!(ClientHandler:
+    [wayc::protocol::wl_registry::WlRegistry] => wayc::globals::GlobalList
+);
+
+wayc::delegate_dispatch!(ClientHandler:
+    [cwmbase::XdgWmBase, cxdgsurface::XdgSurface, ctoplevel::XdgToplevel] => XdgShellState
+);
+
+client_ignore_impl!(ClientHandler => [ccompositor::WlCompositor, csurface::WlSurface]);